@@ -1,34 +1,108 @@
 //! Configuration management for llm.toml
 
+use crate::ollama::{OllamaClient, Timeouts};
 use anyhow::{Context, Result};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub ollama: OllamaConfig,
     pub network: NetworkConfig,
     pub models: ModelsConfig,
     pub aider: Option<AiderConfig>,
+    #[serde(default)]
+    pub menubar: MenubarConfig,
+    #[serde(default)]
+    pub warmup: WarmupConfig,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OllamaConfig {
     pub host: String,
     pub port: u16,
     pub models_path: PathBuf,
     pub ollama_home: PathBuf,
+    /// Restarts allowed per minute when `quant serve` supervises Ollama
+    #[serde(default = "default_max_restarts")]
+    pub max_restarts: u32,
+    /// How long Ollama keeps models loaded (OLLAMA_KEEP_ALIVE, e.g. "30m";
+    /// empty for Ollama's default)
+    #[serde(default)]
+    pub keep_alive: String,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+fn default_max_restarts() -> u32 {
+    5
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkConfig {
     pub expose_port: u16,
     pub auth_user: String,
     pub auth_password_hash: String,
     pub cors_origins: String,
+    /// Seconds to wait for a connection to Ollama (0 for no limit)
+    #[serde(default = "default_connect_timeout")]
+    pub connect_timeout: u64,
+    /// Seconds allowed for quick API calls such as listing models
+    #[serde(default = "default_request_timeout")]
+    pub request_timeout: u64,
+    /// Seconds allowed for a chat or generate request, streamed or not
+    #[serde(default = "default_chat_timeout")]
+    pub chat_timeout: u64,
+    /// Seconds allowed for a model download
+    #[serde(default = "default_pull_timeout")]
+    pub pull_timeout: u64,
+    /// Seconds allowed for loading or creating a model
+    #[serde(default = "default_load_timeout")]
+    pub load_timeout: u64,
+    /// Seconds to wait for a chat reply's first token before reloading the
+    /// model and retrying once (0 to wait indefinitely)
+    #[serde(default = "default_first_token_timeout")]
+    pub first_token_timeout: u64,
+}
+
+fn default_connect_timeout() -> u64 {
+    Timeouts::default().connect.as_secs()
+}
+
+fn default_request_timeout() -> u64 {
+    Timeouts::default().request.as_secs()
+}
+
+fn default_chat_timeout() -> u64 {
+    Timeouts::default().chat.as_secs()
+}
+
+fn default_pull_timeout() -> u64 {
+    Timeouts::default().pull.as_secs()
+}
+
+fn default_load_timeout() -> u64 {
+    Timeouts::default().load.as_secs()
+}
+
+fn default_first_token_timeout() -> u64 {
+    Timeouts::default().first_token.as_secs()
+}
+
+impl NetworkConfig {
+    /// Client timeouts from the `*_timeout` settings
+    pub fn timeouts(&self) -> Timeouts {
+        Timeouts {
+            connect: Duration::from_secs(self.connect_timeout),
+            request: Duration::from_secs(self.request_timeout),
+            chat: Duration::from_secs(self.chat_timeout),
+            pull: Duration::from_secs(self.pull_timeout),
+            load: Duration::from_secs(self.load_timeout),
+            first_token: Duration::from_secs(self.first_token_timeout),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelsConfig {
     pub coding: String,
     pub chat: String,
@@ -36,26 +110,100 @@ pub struct ModelsConfig {
     pub local: std::collections::HashMap<String, LocalModelConfig>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AutoSelectConfig {
     pub threshold_high: u64,
     pub threshold_medium: u64,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LocalModelConfig {
     pub name: String,
     pub file: String,
     pub modelfile: String,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AiderConfig {
     pub model: String,
     pub auto_commits: bool,
     pub log_file: String,
 }
 
+/// Log levels the menu bar app accepts for `menubar.log_level`
+pub const MENUBAR_LOG_LEVELS: &[&str] = &["error", "warn", "info", "debug", "trace"];
+
+/// Menu bar app settings, edited from its Preferences window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MenubarConfig {
+    /// Start the app when the user logs in
+    #[serde(default)]
+    pub launch_at_login: bool,
+    /// Seconds between status refreshes
+    #[serde(default = "default_refresh_secs")]
+    pub refresh_secs: u64,
+    /// Model loaded when Ollama is started from the menu (empty for none)
+    #[serde(default)]
+    pub default_model: String,
+    /// Share Ollama over Tailscale whenever both are up
+    #[serde(default)]
+    pub auto_share: bool,
+    /// Log level for the app and llm-core
+    #[serde(default = "default_log_level")]
+    pub log_level: String,
+}
+
+fn default_refresh_secs() -> u64 {
+    5
+}
+
+fn default_log_level() -> String {
+    "debug".to_string()
+}
+
+impl MenubarConfig {
+    /// Write these settings to the quant user config, where they override
+    /// llm.toml; returns the file written
+    pub fn save(&self) -> Result<PathBuf> {
+        let path = crate::layered::user_config_path().context("No config directory")?;
+        let values = toml::Table::try_from(self).context("Failed to encode menubar settings")?;
+        for (key, value) in &values {
+            crate::layered::write_key(&path, &format!("menubar.{}", key), value)?;
+        }
+        Ok(path)
+    }
+}
+
+impl Default for MenubarConfig {
+    fn default() -> Self {
+        Self {
+            launch_at_login: false,
+            refresh_secs: default_refresh_secs(),
+            default_model: String::new(),
+            auto_share: false,
+            log_level: default_log_level(),
+        }
+    }
+}
+
+/// Models preloaded when `quant serve start` runs or the menu bar app
+/// launches, and by `quant models warm`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WarmupConfig {
+    #[serde(default)]
+    pub models: Vec<WarmModel>,
+}
+
+/// A model in the warm pool
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WarmModel {
+    pub name: String,
+    /// How long Ollama keeps the model loaded once warmed (e.g. "2h", "-1"
+    /// for as long as Ollama runs; empty for `ollama.keep_alive`)
+    #[serde(default)]
+    pub keep_alive: String,
+}
+
 impl Config {
     /// Load configuration from llm.toml
     pub fn load() -> Result<Self> {
@@ -75,12 +223,20 @@ impl Config {
                 port: 11434,
                 models_path: std::path::PathBuf::from("/tmp/ollama/models"),
                 ollama_home: std::path::PathBuf::from("/tmp/ollama"),
+                max_restarts: default_max_restarts(),
+                keep_alive: String::new(),
             },
             network: NetworkConfig {
                 expose_port: 8080,
                 auth_user: String::new(),
                 auth_password_hash: String::new(),
                 cors_origins: "*".to_string(),
+                connect_timeout: default_connect_timeout(),
+                request_timeout: default_request_timeout(),
+                chat_timeout: default_chat_timeout(),
+                pull_timeout: default_pull_timeout(),
+                load_timeout: default_load_timeout(),
+                first_token_timeout: default_first_token_timeout(),
             },
             models: ModelsConfig {
                 coding: String::new(),
@@ -92,6 +248,8 @@ impl Config {
                 local: std::collections::HashMap::new(),
             },
             aider: None,
+            menubar: MenubarConfig::default(),
+            warmup: WarmupConfig::default(),
         }
     }
 
@@ -126,6 +284,39 @@ impl Config {
         format!("http://{}:{}", self.ollama.host, self.ollama.port)
     }
 
+    /// Ollama client for `ollama_url()` with the `[network]` timeouts
+    pub fn ollama_client(&self) -> OllamaClient {
+        OllamaClient::with_timeouts(self.ollama_url(), self.network.timeouts())
+    }
+
+    /// Environment for `ollama serve` from the `[ollama]` section
+    pub fn ollama_env(&self) -> Vec<(String, String)> {
+        let mut env = vec![
+            ("OLLAMA_HOST".to_string(), format!("{}:{}", self.ollama.host, self.ollama.port)),
+            ("OLLAMA_HOME".to_string(), self.ollama.ollama_home.display().to_string()),
+        ];
+        if !self.ollama.keep_alive.is_empty() {
+            env.push(("OLLAMA_KEEP_ALIVE".to_string(), self.ollama.keep_alive.clone()));
+        }
+        env
+    }
+
+    /// The `[warmup]` models, with `ollama.keep_alive` for those that don't
+    /// set their own
+    pub fn warm_pool(&self) -> Vec<WarmModel> {
+        self.warmup
+            .models
+            .iter()
+            .map(|model| WarmModel {
+                name: model.name.clone(),
+                keep_alive: match model.keep_alive.as_str() {
+                    "" => self.ollama.keep_alive.clone(),
+                    keep_alive => keep_alive.to_string(),
+                },
+            })
+            .collect()
+    }
+
     /// Get system RAM in GB (macOS)
     #[cfg(target_os = "macos")]
     pub fn system_ram_gb() -> Result<u64> {
@@ -144,7 +335,37 @@ impl Config {
         Ok(bytes / (1024 * 1024 * 1024))
     }
 
-    #[cfg(not(target_os = "macos"))]
+    /// Get system RAM in GB (Linux)
+    #[cfg(target_os = "linux")]
+    pub fn system_ram_gb() -> Result<u64> {
+        let meminfo = std::fs::read_to_string("/proc/meminfo").context("Failed to read /proc/meminfo")?;
+        let kb = parse_meminfo_total(&meminfo).context("Failed to parse memory size")?;
+        Ok(kb / (1024 * 1024))
+    }
+
+    /// Get system RAM in GB (Windows)
+    #[cfg(target_os = "windows")]
+    pub fn system_ram_gb() -> Result<u64> {
+        use std::process::Command;
+
+        let output = Command::new("powershell")
+            .args([
+                "-NoProfile",
+                "-Command",
+                "(Get-CimInstance Win32_ComputerSystem).TotalPhysicalMemory",
+            ])
+            .output()
+            .context("Failed to run powershell")?;
+
+        let bytes: u64 = String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse()
+            .context("Failed to parse memory size")?;
+
+        Ok(bytes / (1024 * 1024 * 1024))
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
     pub fn system_ram_gb() -> Result<u64> {
         anyhow::bail!("system_ram_gb not implemented for this platform")
     }
@@ -163,10 +384,27 @@ impl Config {
     }
 }
 
+/// `MemTotal` from /proc/meminfo, in kB
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_meminfo_total(meminfo: &str) -> Option<u64> {
+    meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix("MemTotal:"))
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|kb| kb.parse().ok())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_meminfo_total() {
+        let meminfo = "MemTotal:       32768000 kB\nMemFree:         1024000 kB\n";
+        assert_eq!(parse_meminfo_total(meminfo), Some(32768000));
+        assert_eq!(parse_meminfo_total("MemFree: 1 kB"), None);
+    }
+
     #[test]
     fn test_parse_config() {
         let toml = r#"
@@ -196,8 +434,20 @@ file = "qwen2.5-coder-7b-instruct-q4_k_m.gguf"
 modelfile = "modelfiles/qwen2.5-coder-7b-instruct-q4km"
 "#;
 
-        let config: Config = toml::from_str(toml).unwrap();
+        let mut config: Config = toml::from_str(toml).unwrap();
         assert_eq!(config.ollama.port, 11434);
         assert_eq!(config.models.coding, "local/qwen2.5-coder-7b-q4km");
+        assert_eq!(config.network.timeouts(), Timeouts::default());
+        assert!(config.warm_pool().is_empty());
+
+        config.ollama.keep_alive = "30m".to_string();
+        config.warmup = toml::from_str(
+            r#"models = [{ name = "qwen2.5-coder:7b", keep_alive = "-1" }, { name = "llama3.2" }]"#,
+        )
+        .unwrap();
+        let pool = config.warm_pool();
+        assert_eq!(pool.len(), 2);
+        assert_eq!((pool[0].name.as_str(), pool[0].keep_alive.as_str()), ("qwen2.5-coder:7b", "-1"));
+        assert_eq!((pool[1].name.as_str(), pool[1].keep_alive.as_str()), ("llama3.2", "30m"));
     }
 }
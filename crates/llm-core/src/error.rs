@@ -0,0 +1,180 @@
+//! Errors from Ollama that the user can act on
+//!
+//! Client methods still return `anyhow::Result`; failures worth telling
+//! apart carry an [`LlmError`], found with [`LlmError::find`].
+
+use serde::Deserialize;
+
+/// A failed Ollama request, classified by cause
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum LlmError {
+    #[error("Cannot connect to Ollama at {url}")]
+    ConnectionRefused { url: String },
+
+    #[error("Ollama at {url} did not respond in time")]
+    Timeout { url: String },
+
+    #[error("Lost the connection to Ollama at {url} mid-request")]
+    Interrupted { url: String },
+
+    #[error("Model '{model}' is not installed")]
+    ModelNotFound { model: String },
+
+    #[error("Model '{model}' does not exist in the registry")]
+    ModelNotInRegistry { model: String },
+
+    #[error("Not enough memory to run '{model}': {message}")]
+    OutOfMemory { model: String, message: String },
+
+    #[error("Model '{model}' does not support tool calling")]
+    ToolsUnsupported { model: String },
+
+    #[error("Model '{model}' sent no reply within {seconds}s, even after reloading it")]
+    Stalled { model: String, seconds: u64 },
+
+    #[error("Ollama returned {status}: {message}")]
+    Api { status: u16, message: String },
+
+    #[error("Invalid {option}: {message}")]
+    InvalidOption { option: String, message: String },
+}
+
+/// Body of an Ollama error response
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
+}
+
+impl LlmError {
+    /// Classify an error response for a request about `model`
+    pub fn from_response(status: u16, body: &str, model: &str) -> Self {
+        let message = serde_json::from_str::<ErrorBody>(body)
+            .map(|b| b.error)
+            .unwrap_or_else(|_| body.trim().to_string());
+        Self::from_message(Some(status), message, model)
+    }
+
+    /// Classify an `{"error": ...}` line inside a streamed response
+    pub fn from_stream_line(line: &str, model: &str) -> Option<Self> {
+        let body: ErrorBody = serde_json::from_str(line).ok()?;
+        Some(Self::from_message(None, body.error, model))
+    }
+
+    fn from_message(status: Option<u16>, message: String, model: &str) -> Self {
+        let model = model.to_string();
+        let lower = message.to_lowercase();
+
+        if lower.contains("file does not exist") || lower.contains("manifest unknown") {
+            Self::ModelNotInRegistry { model }
+        } else if lower.contains("not found") && (lower.contains("model") || status == Some(404)) {
+            Self::ModelNotFound { model }
+        } else if lower.contains("does not support tools") {
+            Self::ToolsUnsupported { model }
+        } else if lower.contains("more system memory")
+            || lower.contains("out of memory")
+            || lower.contains("insufficient memory")
+        {
+            Self::OutOfMemory { model, message }
+        } else {
+            Self::Api { status: status.unwrap_or(500), message }
+        }
+    }
+
+    /// What the user can do about it, phrased for the `quant` CLI
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            Self::ConnectionRefused { .. } => {
+                Some("Start Ollama with `quant serve start`, or check ollama.host in llm.toml".to_string())
+            }
+            Self::Timeout { .. } => {
+                Some("The model may still be loading; try again, or check `quant serve logs`".to_string())
+            }
+            Self::Interrupted { .. } => Some("Ollama may have restarted; check `quant serve logs`".to_string()),
+            Self::ModelNotFound { model } => Some(format!("Pull the model with `quant models pull {}`", model)),
+            Self::ModelNotInRegistry { .. } => {
+                Some("Check the name and tag at https://ollama.com/library".to_string())
+            }
+            Self::OutOfMemory { .. } => Some(
+                "Use a smaller model or quantization (`quant select` picks one for this machine), \
+                 or unload other models (`quant models ps`)"
+                    .to_string(),
+            ),
+            Self::ToolsUnsupported { .. } => {
+                Some("Pick a model with tool support, such as qwen2.5-coder or llama3.1".to_string())
+            }
+            Self::Stalled { .. } => Some(
+                "Check `quant serve logs`; if long prompts are just slow here, raise network.first_token_timeout"
+                    .to_string(),
+            ),
+            Self::Api { .. } => None,
+            Self::InvalidOption { .. } => Some(
+                "See the valid parameters at https://github.com/ollama/ollama/blob/main/docs/modelfile.md#parameter"
+                    .to_string(),
+            ),
+        }
+    }
+
+    /// Whether trying the same request again might succeed
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::ConnectionRefused { .. } | Self::Timeout { .. } | Self::Interrupted { .. } => true,
+            Self::Api { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+
+    /// The `LlmError` anywhere in an error's chain
+    pub fn find(err: &anyhow::Error) -> Option<&LlmError> {
+        err.chain().find_map(|cause| cause.downcast_ref::<LlmError>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_response() {
+        let not_found = LlmError::from_response(
+            404,
+            r#"{"error":"model \"qwen3:8b\" not found, try pulling it first"}"#,
+            "qwen3:8b",
+        );
+        assert_eq!(not_found, LlmError::ModelNotFound { model: "qwen3:8b".to_string() });
+        assert_eq!(not_found.hint().unwrap(), "Pull the model with `quant models pull qwen3:8b`");
+
+        let oom = LlmError::from_response(
+            500,
+            r#"{"error":"model requires more system memory (12.3 GiB) than is available (8.1 GiB)"}"#,
+            "llama3.1:70b",
+        );
+        assert!(matches!(oom, LlmError::OutOfMemory { ref model, .. } if model == "llama3.1:70b"));
+        assert!(!oom.is_retryable());
+
+        let tools = LlmError::from_response(
+            400,
+            r#"{"error":"registry.ollama.ai/library/gemma:2b does not support tools"}"#,
+            "gemma:2b",
+        );
+        assert_eq!(tools, LlmError::ToolsUnsupported { model: "gemma:2b".to_string() });
+
+        let other = LlmError::from_response(502, "Bad Gateway\n", "llama3.2");
+        assert_eq!(other, LlmError::Api { status: 502, message: "Bad Gateway".to_string() });
+        assert!(other.is_retryable());
+        assert!(other.hint().is_none());
+    }
+
+    #[test]
+    fn test_from_stream_line() {
+        let line = r#"{"error":"pull model manifest: file does not exist"}"#;
+        assert_eq!(
+            LlmError::from_stream_line(line, "lama3"),
+            Some(LlmError::ModelNotInRegistry { model: "lama3".to_string() })
+        );
+        assert_eq!(LlmError::from_stream_line(r#"{"status":"success"}"#, "llama3.2"), None);
+
+        let err = anyhow::Error::new(LlmError::Timeout { url: "http://localhost:11434".to_string() })
+            .context("Chat request failed");
+        assert!(matches!(LlmError::find(&err), Some(LlmError::Timeout { .. })));
+    }
+}
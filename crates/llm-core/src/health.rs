@@ -0,0 +1,308 @@
+//! Environment health checks
+//!
+//! Shared by `quant doctor` and the OllamaBar diagnostics submenu so both
+//! report the same problems: an unmounted models volume, low disk space,
+//! another process holding the Ollama port, and CLI/server version drift.
+
+use crate::config::Config;
+use crate::process::find_ollama_binary;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+/// Free space below this is a warning
+const DISK_WARN_BYTES: u64 = 20 * 1024 * 1024 * 1024;
+/// Free space below this is an error (model pulls will fail)
+const DISK_ERROR_BYTES: u64 = 5 * 1024 * 1024 * 1024;
+
+/// Outcome of a single check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HealthStatus {
+    Ok,
+    Warning,
+    Error,
+}
+
+impl HealthStatus {
+    /// Single-character marker for menus and terminal output
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            HealthStatus::Ok => "✓",
+            HealthStatus::Warning => "⚠",
+            HealthStatus::Error => "✗",
+        }
+    }
+}
+
+/// A single named check with a human-readable detail line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthCheck {
+    pub name: &'static str,
+    pub status: HealthStatus,
+    pub detail: String,
+}
+
+impl HealthCheck {
+    fn new(name: &'static str, status: HealthStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Results of all checks
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HealthReport {
+    pub checks: Vec<HealthCheck>,
+}
+
+impl HealthReport {
+    /// Worst status across all checks
+    pub fn overall(&self) -> HealthStatus {
+        self.checks
+            .iter()
+            .map(|c| c.status)
+            .max()
+            .unwrap_or(HealthStatus::Ok)
+    }
+
+    /// Checks that did not pass
+    pub fn problems(&self) -> impl Iterator<Item = &HealthCheck> {
+        self.checks.iter().filter(|c| c.status != HealthStatus::Ok)
+    }
+}
+
+/// Run every check against the given configuration
+pub async fn run_checks(config: &Config) -> HealthReport {
+    let client = config.ollama_client();
+    let running = client.health_check().await.unwrap_or(false);
+
+    let mut checks = vec![
+        check_models_path(&config.ollama.models_path),
+        check_disk_space(&config.ollama.models_path),
+        check_port(&config.ollama.host, config.ollama.port, running).await,
+    ];
+
+    let server_version = if running {
+        client.version().await.ok()
+    } else {
+        None
+    };
+    checks.push(check_version(cli_version().as_deref(), server_version.as_deref()));
+
+    HealthReport { checks }
+}
+
+/// Models path exists, and its volume is mounted when it lives on one
+pub fn check_models_path(path: &Path) -> HealthCheck {
+    const NAME: &str = "Models path";
+
+    if let Some(volume) = volume_root(path) {
+        if !volume.exists() {
+            return HealthCheck::new(
+                NAME,
+                HealthStatus::Error,
+                format!("Volume {} is not mounted", volume.display()),
+            );
+        }
+    }
+
+    if path.is_dir() {
+        HealthCheck::new(NAME, HealthStatus::Ok, path.display().to_string())
+    } else if path.exists() {
+        HealthCheck::new(
+            NAME,
+            HealthStatus::Error,
+            format!("{} is not a directory", path.display()),
+        )
+    } else {
+        HealthCheck::new(
+            NAME,
+            HealthStatus::Error,
+            format!("{} does not exist", path.display()),
+        )
+    }
+}
+
+/// The `/Volumes/<name>` mount point for paths on an external macOS volume
+fn volume_root(path: &Path) -> Option<PathBuf> {
+    let name = path.strip_prefix("/Volumes").ok()?.components().next()?;
+    Some(Path::new("/Volumes").join(name))
+}
+
+/// Free space on the filesystem holding the models path
+pub fn check_disk_space(path: &Path) -> HealthCheck {
+    const NAME: &str = "Disk space";
+
+    // df needs an existing path; fall back to the nearest ancestor
+    let Some(existing) = path.ancestors().find(|p| p.exists()) else {
+        return HealthCheck::new(NAME, HealthStatus::Warning, "No existing path to inspect");
+    };
+
+    let output = Command::new("df").arg("-Pk").arg(existing).output();
+    let available = match output {
+        Ok(out) if out.status.success() => parse_df_available(&String::from_utf8_lossy(&out.stdout)),
+        _ => None,
+    };
+
+    match available {
+        Some(bytes) => {
+            let status = disk_status(bytes);
+            HealthCheck::new(NAME, status, format!("{} free", format_gb(bytes)))
+        }
+        None => HealthCheck::new(NAME, HealthStatus::Warning, "Could not read free space"),
+    }
+}
+
+fn disk_status(available_bytes: u64) -> HealthStatus {
+    if available_bytes < DISK_ERROR_BYTES {
+        HealthStatus::Error
+    } else if available_bytes < DISK_WARN_BYTES {
+        HealthStatus::Warning
+    } else {
+        HealthStatus::Ok
+    }
+}
+
+/// Parse available bytes from POSIX `df -Pk` output
+fn parse_df_available(output: &str) -> Option<u64> {
+    let line = output.lines().nth(1)?;
+    let kb: u64 = line.split_whitespace().nth(3)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+fn format_gb(bytes: u64) -> String {
+    format!("{:.1} GB", bytes as f64 / (1024.0 * 1024.0 * 1024.0))
+}
+
+/// Detect another process listening on the Ollama port
+pub async fn check_port(host: &str, port: u16, ollama_running: bool) -> HealthCheck {
+    const NAME: &str = "Port";
+
+    if ollama_running {
+        return HealthCheck::new(NAME, HealthStatus::Ok, format!("Ollama listening on {}", port));
+    }
+
+    // A wildcard bind is reachable via loopback
+    let connect_host = match host {
+        "0.0.0.0" | "::" | "" => "127.0.0.1",
+        other => other,
+    };
+    let addr = format!("{}:{}", connect_host, port);
+    let connect = tokio::time::timeout(Duration::from_secs(1), tokio::net::TcpStream::connect(&addr)).await;
+
+    match connect {
+        Ok(Ok(_)) => HealthCheck::new(
+            NAME,
+            HealthStatus::Error,
+            format!("Port {} is in use by another process", port),
+        ),
+        _ => HealthCheck::new(NAME, HealthStatus::Ok, format!("Port {} is free", port)),
+    }
+}
+
+/// Version reported by the installed `ollama` CLI
+pub fn cli_version() -> Option<String> {
+    let bin = find_ollama_binary().ok()?;
+    let output = Command::new(bin).arg("--version").output().ok()?;
+    let text = format!(
+        "{}\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    parse_cli_version(&text)
+}
+
+/// Extract the client version from `ollama --version` output
+///
+/// When the server differs, the CLI prints the server version first and
+/// the client version in a trailing warning, so prefer the latter.
+fn parse_cli_version(output: &str) -> Option<String> {
+    let version_after = |marker: &str| {
+        output
+            .lines()
+            .find_map(|l| l.split_once(marker))
+            .and_then(|(_, rest)| rest.split_whitespace().next())
+            .map(|v| v.to_string())
+    };
+    version_after("client version is").or_else(|| version_after("version is"))
+}
+
+/// Compare the CLI and server versions
+pub fn check_version(cli: Option<&str>, server: Option<&str>) -> HealthCheck {
+    const NAME: &str = "Version";
+
+    match (cli, server) {
+        (None, _) => HealthCheck::new(NAME, HealthStatus::Error, "ollama binary not found"),
+        (Some(cli), None) => HealthCheck::new(NAME, HealthStatus::Ok, format!("CLI {}", cli)),
+        (Some(cli), Some(server)) if cli == server => {
+            HealthCheck::new(NAME, HealthStatus::Ok, cli.to_string())
+        }
+        (Some(cli), Some(server)) => HealthCheck::new(
+            NAME,
+            HealthStatus::Warning,
+            format!("CLI {} but server {} (restart Ollama)", cli, server),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_df_available() {
+        let output = "Filesystem 1024-blocks Used Available Capacity Mounted on\n\
+                      /dev/disk3s5 971350180 500000000 41943040 93% /System/Volumes/Data\n";
+        assert_eq!(parse_df_available(output), Some(41943040 * 1024));
+        assert_eq!(parse_df_available("Filesystem\n"), None);
+    }
+
+    #[test]
+    fn test_disk_status_thresholds() {
+        let gb = 1024 * 1024 * 1024;
+        assert_eq!(disk_status(100 * gb), HealthStatus::Ok);
+        assert_eq!(disk_status(10 * gb), HealthStatus::Warning);
+        assert_eq!(disk_status(gb), HealthStatus::Error);
+    }
+
+    #[test]
+    fn test_parse_cli_version() {
+        assert_eq!(parse_cli_version("ollama version is 0.5.7\n").as_deref(), Some("0.5.7"));
+        let mismatch = "ollama version is 0.5.4\nWarning: client version is 0.5.7\n";
+        assert_eq!(parse_cli_version(mismatch).as_deref(), Some("0.5.7"));
+        assert_eq!(parse_cli_version("command not found"), None);
+    }
+
+    #[test]
+    fn test_check_version() {
+        assert_eq!(check_version(Some("0.5.7"), Some("0.5.7")).status, HealthStatus::Ok);
+        assert_eq!(check_version(Some("0.5.7"), Some("0.5.4")).status, HealthStatus::Warning);
+        assert_eq!(check_version(None, None).status, HealthStatus::Error);
+    }
+
+    #[test]
+    fn test_volume_root_and_unmounted_volume() {
+        assert_eq!(
+            volume_root(Path::new("/Volumes/models/ollama")),
+            Some(PathBuf::from("/Volumes/models"))
+        );
+        assert_eq!(volume_root(Path::new("/Users/me/models")), None);
+
+        let check = check_models_path(Path::new("/Volumes/quant-missing-volume/models"));
+        assert_eq!(check.status, HealthStatus::Error);
+        assert!(check.detail.contains("not mounted"));
+    }
+
+    #[test]
+    fn test_report_overall() {
+        let mut report = HealthReport::default();
+        assert_eq!(report.overall(), HealthStatus::Ok);
+        report.checks.push(HealthCheck::new("a", HealthStatus::Warning, ""));
+        report.checks.push(HealthCheck::new("b", HealthStatus::Ok, ""));
+        assert_eq!(report.overall(), HealthStatus::Warning);
+        assert_eq!(report.problems().count(), 1);
+    }
+}
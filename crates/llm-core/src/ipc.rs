@@ -0,0 +1,399 @@
+//! Local IPC between quant and OllamaBar
+//!
+//! OllamaBar listens on a Unix socket ([`socket_path`]) with an [`IpcHub`].
+//! Each `quant chat` and `quant agent` connects with an [`IpcClient`] if the
+//! socket is there and reports its session: id, model and directory. The
+//! tray lists the sessions, can open one in a new terminal, and tells the
+//! connected sessions when a model is picked from its menu, so they switch
+//! too. Messages are JSON, one per line.
+//!
+//! Without OllamaBar nothing connects and quant works as before. There is
+//! no socket on Windows, where connecting and serving fail.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+#[cfg(unix)]
+use anyhow::Context;
+#[cfg(unix)]
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+#[cfg(unix)]
+use tokio::net::unix::OwnedWriteHalf;
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use tracing::{debug, info, warn};
+
+/// Models kept in the recently used list
+pub const RECENT_MODELS: usize = 5;
+
+/// Where OllamaBar listens
+pub fn socket_path() -> PathBuf {
+    dirs::runtime_dir()
+        .or_else(dirs::cache_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("quant")
+        .join("bar.sock")
+}
+
+/// What a quant process is running
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionKind {
+    Chat,
+    Agent,
+}
+
+/// A running quant session, as reported to OllamaBar
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub pid: u32,
+    pub kind: SessionKind,
+    /// Conversation id (chat) or session id (agent)
+    pub id: String,
+    pub model: String,
+    pub working_dir: PathBuf,
+    /// Conversation title or agent task
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Whether the session is saved, so another quant can pick it up
+    #[serde(default)]
+    pub resumable: bool,
+}
+
+impl SessionInfo {
+    /// Arguments to quant that continue this session
+    pub fn resume_args(&self) -> Vec<String> {
+        let args: [&str; 3] = match self.kind {
+            SessionKind::Chat => ["chat", "--load", &self.id],
+            SessionKind::Agent => ["sessions", "resume", &self.id],
+        };
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+}
+
+/// A message from quant to OllamaBar
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// The session started or changed (model, title, ...)
+    Session(SessionInfo),
+}
+
+/// A message from OllamaBar to quant
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// A model was picked from the tray menu
+    SwitchModel { model: String },
+}
+
+/// OllamaBar's side: the connected sessions and recently used models
+#[derive(Clone, Default)]
+pub struct IpcHub {
+    inner: Arc<Mutex<HubState>>,
+}
+
+#[derive(Default)]
+struct HubState {
+    next_connection: u64,
+    /// Sessions by connection, in connection order
+    sessions: BTreeMap<u64, SessionInfo>,
+    senders: BTreeMap<u64, mpsc::UnboundedSender<ServerMessage>>,
+    /// Most recent first
+    recent_models: Vec<String>,
+}
+
+impl IpcHub {
+    /// A hub that starts with these recently used models (most recent first)
+    pub fn with_recent_models(models: Vec<String>) -> Self {
+        let hub = Self::default();
+        hub.inner.lock().unwrap().recent_models = models.into_iter().take(RECENT_MODELS).collect();
+        hub
+    }
+
+    /// Sessions connected now
+    pub fn sessions(&self) -> Vec<SessionInfo> {
+        self.inner.lock().unwrap().sessions.values().cloned().collect()
+    }
+
+    /// Models the sessions used, most recent first
+    pub fn recent_models(&self) -> Vec<String> {
+        self.inner.lock().unwrap().recent_models.clone()
+    }
+
+    /// Tell every connected session to switch to `model`
+    pub fn switch_model(&self, model: &str) {
+        self.broadcast(ServerMessage::SwitchModel {
+            model: model.to_string(),
+        });
+    }
+
+    fn broadcast(&self, message: ServerMessage) {
+        let mut inner = self.inner.lock().unwrap();
+        // A closed channel belongs to a connection that is going away
+        inner.senders.retain(|_, sender| sender.send(message.clone()).is_ok());
+    }
+
+    fn connect(&self) -> (u64, mpsc::UnboundedReceiver<ServerMessage>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let mut inner = self.inner.lock().unwrap();
+        let id = inner.next_connection;
+        inner.next_connection += 1;
+        inner.senders.insert(id, sender);
+        (id, receiver)
+    }
+
+    fn disconnect(&self, connection: u64) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.sessions.remove(&connection);
+        inner.senders.remove(&connection);
+    }
+
+    fn handle(&self, connection: u64, message: ClientMessage) {
+        let mut inner = self.inner.lock().unwrap();
+        match message {
+            ClientMessage::Session(info) => {
+                let recent = &mut inner.recent_models;
+                recent.retain(|model| *model != info.model);
+                recent.insert(0, info.model.clone());
+                recent.truncate(RECENT_MODELS);
+                inner.sessions.insert(connection, info);
+            }
+        }
+    }
+
+    /// Accept sessions on `socket` until the listener fails
+    #[cfg(unix)]
+    pub async fn serve(&self, socket: &Path) -> Result<()> {
+        if let Some(parent) = socket.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // A leftover socket from a previous run would make bind fail
+        if socket.exists() {
+            std::fs::remove_file(socket).with_context(|| format!("Failed to remove stale socket {}", socket.display()))?;
+        }
+        let listener = UnixListener::bind(socket).with_context(|| format!("Failed to bind {}", socket.display()))?;
+        info!(socket = %socket.display(), "Listening for quant sessions");
+
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let hub = self.clone();
+            tokio::spawn(async move { hub.serve_connection(stream).await });
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub async fn serve(&self, _socket: &Path) -> Result<()> {
+        anyhow::bail!("quant sessions need Unix sockets, which this platform doesn't have")
+    }
+
+    #[cfg(unix)]
+    async fn serve_connection(&self, stream: UnixStream) {
+        let (connection, mut outgoing) = self.connect();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+
+        loop {
+            tokio::select! {
+                line = lines.next_line() => match line {
+                    Ok(Some(line)) if line.trim().is_empty() => {}
+                    Ok(Some(line)) => match serde_json::from_str(&line) {
+                        Ok(message) => self.handle(connection, message),
+                        Err(e) => warn!(error = %e, "Ignoring invalid message from quant"),
+                    },
+                    _ => break,
+                },
+                Some(message) = outgoing.recv() => {
+                    if write_message(&mut writer, &message).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        self.disconnect(connection);
+        debug!(connection, "quant session disconnected");
+    }
+}
+
+/// quant's side: a connection to OllamaBar
+pub struct IpcClient {
+    #[cfg(unix)]
+    writer: OwnedWriteHalf,
+    events: mpsc::UnboundedReceiver<ServerMessage>,
+}
+
+impl IpcClient {
+    /// Connect to OllamaBar at `socket`
+    #[cfg(unix)]
+    pub async fn connect(socket: &Path) -> Result<Self> {
+        let stream = UnixStream::connect(socket)
+            .await
+            .with_context(|| format!("Failed to connect to {}", socket.display()))?;
+        let (reader, writer) = stream.into_split();
+
+        let (sender, events) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                match serde_json::from_str(&line) {
+                    Ok(message) => {
+                        if sender.send(message).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => debug!(error = %e, "Ignoring invalid message from OllamaBar"),
+                }
+            }
+        });
+
+        Ok(Self { writer, events })
+    }
+
+    #[cfg(not(unix))]
+    pub async fn connect(_socket: &Path) -> Result<Self> {
+        anyhow::bail!("quant sessions need Unix sockets, which this platform doesn't have")
+    }
+
+    /// Connect to OllamaBar if it is running
+    pub async fn connect_default() -> Option<Self> {
+        let socket = socket_path();
+        if !socket.exists() {
+            return None;
+        }
+        match Self::connect(&socket).await {
+            Ok(client) => Some(client),
+            Err(e) => {
+                tracing::debug!(error = %e, "OllamaBar not reachable");
+                None
+            }
+        }
+    }
+
+    /// Report the session, when it starts and whenever it changes
+    #[cfg(unix)]
+    pub async fn report(&mut self, session: &SessionInfo) -> Result<()> {
+        write_message(&mut self.writer, &ClientMessage::Session(session.clone())).await
+    }
+
+    #[cfg(not(unix))]
+    pub async fn report(&mut self, _session: &SessionInfo) -> Result<()> {
+        Ok(())
+    }
+
+    /// Messages from OllamaBar received since the last call
+    pub fn take_events(&mut self) -> Vec<ServerMessage> {
+        let mut events = Vec::new();
+        while let Ok(event) = self.events.try_recv() {
+            events.push(event);
+        }
+        events
+    }
+}
+
+#[cfg(unix)]
+async fn write_message(writer: &mut OwnedWriteHalf, message: &impl Serialize) -> Result<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await?;
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn session(id: &str, model: &str) -> SessionInfo {
+        SessionInfo {
+            pid: 42,
+            kind: SessionKind::Chat,
+            id: id.to_string(),
+            model: model.to_string(),
+            working_dir: PathBuf::from("/work"),
+            title: None,
+            resumable: true,
+        }
+    }
+
+    /// Poll until `check` holds, for up to a second
+    async fn eventually(mut check: impl FnMut() -> bool) {
+        for _ in 0..50 {
+            if check() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("condition not met in time");
+    }
+
+    #[test]
+    fn test_resume_args() {
+        let mut info = session("20260101-abc", "llama3.2");
+        assert_eq!(info.resume_args(), ["chat", "--load", "20260101-abc"]);
+        info.kind = SessionKind::Agent;
+        assert_eq!(info.resume_args(), ["sessions", "resume", "20260101-abc"]);
+    }
+
+    #[test]
+    fn test_recent_models() {
+        let hub = IpcHub::with_recent_models(vec!["a".into(), "b".into()]);
+        let (first, _) = hub.connect();
+        hub.handle(first, ClientMessage::Session(session("1", "b")));
+        hub.handle(first, ClientMessage::Session(session("1", "c")));
+        assert_eq!(hub.recent_models(), ["c", "b", "a"]);
+        assert_eq!(hub.sessions().len(), 1);
+
+        hub.disconnect(first);
+        assert!(hub.sessions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let dir = std::env::temp_dir().join(format!("llm-core-ipc-{}", std::process::id()));
+        let socket = dir.join("bar.sock");
+        let hub = IpcHub::default();
+        let server = tokio::spawn({
+            let (hub, socket) = (hub.clone(), socket.clone());
+            async move { hub.serve(&socket).await }
+        });
+
+        let mut client = None;
+        for _ in 0..50 {
+            if let Ok(c) = IpcClient::connect(&socket).await {
+                client = Some(c);
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let mut client = client.expect("hub did not start");
+
+        client.report(&session("20260101-abc", "llama3.2")).await.unwrap();
+        eventually(|| hub.sessions().len() == 1).await;
+        assert_eq!(hub.sessions()[0].id, "20260101-abc");
+        assert_eq!(hub.recent_models(), ["llama3.2"]);
+
+        hub.switch_model("qwen2.5-coder:7b");
+        let expected = ServerMessage::SwitchModel {
+            model: "qwen2.5-coder:7b".to_string(),
+        };
+        let mut events = Vec::new();
+        eventually(|| {
+            events.extend(client.take_events());
+            !events.is_empty()
+        })
+        .await;
+        assert_eq!(events, [expected]);
+
+        drop(client);
+        eventually(|| hub.sessions().is_empty()).await;
+
+        server.abort();
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
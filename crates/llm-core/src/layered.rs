@@ -0,0 +1,540 @@
+//! Layered configuration with provenance
+//!
+//! llm.toml and the quant config files share one key space (`ollama.host`,
+//! `models.chat`, `repl.default_model`, ...). Sources are stacked lowest
+//! precedence first and merged key by key:
+//!
+//! 1. Built-in defaults
+//! 2. `llm.toml` (current directory or a parent)
+//! 3. User config (`~/.config/quant/config.toml`)
+//! 4. Project config (`.quant/config.toml` in the current directory or a parent)
+//! 5. Environment variables named `QUANT_<SECTION>__<KEY>`, e.g.
+//!    `QUANT_OLLAMA__PORT=11500` sets `ollama.port`
+//!
+//! Callers can push further layers (the CLI adds profiles and `--set`
+//! flags). Deserializing the merged table yields an [`EffectiveConfig`],
+//! which records the source of every value.
+
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+use crate::Config;
+
+/// Prefix of environment variable overrides
+pub const ENV_PREFIX: &str = "QUANT_";
+
+/// Project config location, relative to the project root
+pub const PROJECT_CONFIG: &str = ".quant/config.toml";
+
+/// Top-level sections of [`Config`] (llm.toml, plus the menu bar app settings)
+pub const LLM_SECTIONS: &[&str] = &["ollama", "network", "models", "aider", "menubar", "warmup"];
+
+/// Where a configuration value came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    Default,
+    LlmToml(PathBuf),
+    User(PathBuf),
+    Project(PathBuf),
+    /// Profile name
+    Profile(String),
+    /// Environment variable name
+    Env(String),
+    /// The raw `--set` argument
+    Cli(String),
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "built-in defaults"),
+            Self::LlmToml(path) | Self::User(path) | Self::Project(path) => write!(f, "{}", path.display()),
+            Self::Profile(name) => write!(f, "profile `{}`", name),
+            Self::Env(var) => write!(f, "environment variable {}", var),
+            Self::Cli(arg) => write!(f, "--set {}", arg),
+        }
+    }
+}
+
+/// An invalid configuration value, located by source and key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    pub source: ConfigSource,
+    /// Dotted key, e.g. `ask.temperature`
+    pub key: String,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid config key `{}` in {}: {}", self.key, self.source, self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Config files to read; missing files are skipped
+#[derive(Debug, Clone, Default)]
+pub struct ConfigPaths {
+    pub llm: Option<PathBuf>,
+    pub user: Option<PathBuf>,
+    pub project: Option<PathBuf>,
+}
+
+impl ConfigPaths {
+    /// Standard locations relative to the current directory
+    pub fn discover() -> Self {
+        let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        Self {
+            llm: Config::find_config_path().ok(),
+            user: user_config_path(),
+            project: find_project_config(&cwd),
+        }
+    }
+}
+
+/// The quant user config file (`~/.config/quant/config.toml` or platform equivalent)
+pub fn user_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("quant").join("config.toml"))
+}
+
+/// Find `.quant/config.toml` in `start` or its parents
+pub fn find_project_config(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .take(10)
+        .map(|dir| dir.join(PROJECT_CONFIG))
+        .find(|candidate| candidate.is_file())
+}
+
+#[derive(Debug)]
+struct Layer {
+    source: ConfigSource,
+    values: Table,
+}
+
+/// A stack of configuration sources, lowest precedence first
+#[derive(Debug)]
+pub struct LayeredConfig {
+    layers: Vec<Layer>,
+}
+
+impl LayeredConfig {
+    /// Start from built-in defaults
+    pub fn with_defaults(defaults: Table) -> Self {
+        Self {
+            layers: vec![Layer {
+                source: ConfigSource::Default,
+                values: defaults,
+            }],
+        }
+    }
+
+    /// llm.toml, user, and project config plus environment overrides
+    pub fn standard(paths: &ConfigPaths, defaults: Table, env: impl IntoIterator<Item = (String, String)>) -> Result<Self> {
+        let mut layered = Self::with_defaults(defaults);
+        layered.push_files(paths)?;
+        layered.push_env(env)?;
+        Ok(layered)
+    }
+
+    pub fn push(&mut self, source: ConfigSource, values: Table) {
+        self.layers.push(Layer { source, values });
+    }
+
+    /// Push the config files that exist, in precedence order
+    pub fn push_files(&mut self, paths: &ConfigPaths) -> Result<()> {
+        let files = [
+            (paths.llm.as_ref(), ConfigSource::LlmToml as fn(PathBuf) -> ConfigSource),
+            (paths.user.as_ref(), ConfigSource::User),
+            (paths.project.as_ref(), ConfigSource::Project),
+        ];
+        for (path, source) in files {
+            let Some(path) = path.filter(|p| p.is_file()) else {
+                continue;
+            };
+            let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            let values: Table =
+                toml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?;
+            self.push(source(path.clone()), values);
+        }
+        Ok(())
+    }
+
+    /// Push one layer per `QUANT_<SECTION>__<KEY>` variable, sorted by name
+    pub fn push_env(&mut self, env: impl IntoIterator<Item = (String, String)>) -> Result<(), ConfigError> {
+        let mut env: Vec<(String, String)> = env
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(ENV_PREFIX) && name.contains("__"))
+            .collect();
+        env.sort();
+        for (name, raw) in env {
+            let key = name[ENV_PREFIX.len()..].to_lowercase().replace("__", ".");
+            self.push_key(ConfigSource::Env(name), &key, parse_value(&raw))?;
+        }
+        Ok(())
+    }
+
+    /// Push a layer setting a single dotted key
+    pub fn push_key(&mut self, source: ConfigSource, key: &str, value: Value) -> Result<(), ConfigError> {
+        let mut values = Table::new();
+        insert_key(&mut values, key, value).map_err(|message| ConfigError {
+            source: source.clone(),
+            key: key.to_string(),
+            message,
+        })?;
+        self.push(source, values);
+        Ok(())
+    }
+
+    /// Check each layer on top of the ones below it
+    ///
+    /// `known_sections` rejects typos in top-level sections (llm.toml is
+    /// exempt since it may carry sections nothing reads). When a layer fails
+    /// `check`, its keys are tried one at a time so the error names the
+    /// offending key rather than the whole file.
+    pub fn validate(
+        &self,
+        known_sections: Option<&[&str]>,
+        check: impl Fn(&Table) -> Result<(), String>,
+    ) -> Result<(), ConfigError> {
+        let mut merged = Table::new();
+        for layer in &self.layers {
+            if let Some(known) = known_sections {
+                let exempt = matches!(layer.source, ConfigSource::LlmToml(_));
+                if let Some(section) = layer.values.keys().find(|s| !exempt && !known.contains(&s.as_str())) {
+                    return Err(ConfigError {
+                        source: layer.source.clone(),
+                        key: section.clone(),
+                        message: "unknown section".to_string(),
+                    });
+                }
+            }
+
+            let mut candidate = merged.clone();
+            merge(&mut candidate, &layer.values);
+            if let Err(message) = check(&candidate) {
+                let mut error = ConfigError {
+                    source: layer.source.clone(),
+                    key: layer.values.keys().next().cloned().unwrap_or_default(),
+                    message,
+                };
+                for (key, value) in leaves(&layer.values, "") {
+                    let mut single = merged.clone();
+                    // Keys that can't be inserted alone are covered by the layer-level error
+                    if insert_key(&mut single, &key, value).is_err() {
+                        continue;
+                    }
+                    if let Err(message) = check(&single) {
+                        error.key = key;
+                        error.message = message;
+                        break;
+                    }
+                }
+                return Err(error);
+            }
+            merged = candidate;
+        }
+        Ok(())
+    }
+
+    /// All layers merged
+    pub fn merged(&self) -> Table {
+        let mut merged = Table::new();
+        for layer in &self.layers {
+            merge(&mut merged, &layer.values);
+        }
+        merged
+    }
+
+    /// Resolved value of a dotted key and the highest-precedence source setting it
+    pub fn get(&self, key: &str) -> Option<(Value, &ConfigSource)> {
+        let value = lookup(&self.merged(), key)?.clone();
+        Some((value, self.source_of(key)?))
+    }
+
+    /// Highest-precedence source that sets a dotted key
+    pub fn source_of(&self, key: &str) -> Option<&ConfigSource> {
+        self.layers
+            .iter()
+            .rev()
+            .find(|layer| lookup(&layer.values, key).is_some())
+            .map(|layer| &layer.source)
+    }
+
+    /// Sources that contributed, highest precedence first
+    pub fn sources(&self) -> impl Iterator<Item = &ConfigSource> {
+        self.layers.iter().rev().map(|layer| &layer.source)
+    }
+
+    pub fn has_source(&self, matches: impl Fn(&ConfigSource) -> bool) -> bool {
+        self.layers.iter().any(|layer| matches(&layer.source))
+    }
+
+    /// Deserialize the merged layers, recording where every value came from
+    pub fn effective<T: DeserializeOwned>(&self) -> Result<EffectiveConfig<T>> {
+        let merged = self.merged();
+        let value = deserialize(&merged).map_err(|e| anyhow::anyhow!("Invalid configuration: {}", e))?;
+        let provenance = leaves(&merged, "")
+            .into_iter()
+            .filter_map(|(key, _)| self.source_of(&key).map(|source| (key, source.clone())))
+            .collect();
+        Ok(EffectiveConfig { value, provenance })
+    }
+}
+
+/// A resolved configuration and the source of each of its values
+#[derive(Debug, Clone)]
+pub struct EffectiveConfig<T> {
+    value: T,
+    /// Dotted leaf key -> source
+    provenance: BTreeMap<String, ConfigSource>,
+}
+
+impl<T> EffectiveConfig<T> {
+    /// Source of a dotted key, or of the most specific table containing it
+    pub fn source(&self, key: &str) -> Option<&ConfigSource> {
+        let mut key = key;
+        loop {
+            if let Some(source) = self.provenance.get(key) {
+                return Some(source);
+            }
+            key = key.rsplit_once('.')?.0;
+        }
+    }
+
+    /// Every resolved leaf key with its source, sorted by key
+    pub fn provenance(&self) -> impl Iterator<Item = (&str, &ConfigSource)> {
+        self.provenance.iter().map(|(key, source)| (key.as_str(), source))
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Deref for EffectiveConfig<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl Config {
+    /// llm.toml with overrides from the quant config files and environment
+    ///
+    /// Like [`Config::load`], fails when there is no llm.toml.
+    pub fn load_layered() -> Result<EffectiveConfig<Config>> {
+        let defaults = Table::try_from(Config::default_minimal()).context("Failed to encode default config")?;
+        let layered = LayeredConfig::standard(&ConfigPaths::discover(), defaults, std::env::vars())?;
+        if !layered.has_source(|s| matches!(s, ConfigSource::LlmToml(_))) {
+            anyhow::bail!("llm.toml not found in current directory or parents");
+        }
+        layered.validate(None, |table| deserialize::<Config>(table).map(|_| ()))?;
+        layered.effective()
+    }
+}
+
+/// Deserialize a table, returning serde's message on failure
+pub fn deserialize<T: DeserializeOwned>(table: &Table) -> Result<T, String> {
+    Value::Table(table.clone()).try_into().map_err(|e: toml::de::Error| e.message().to_string())
+}
+
+/// Parse a raw value as TOML, falling back to a plain string
+pub fn parse_value(raw: &str) -> Value {
+    toml::from_str::<Table>(&format!("v = {}", raw))
+        .ok()
+        .and_then(|mut t| t.remove("v"))
+        .unwrap_or_else(|| Value::String(raw.to_string()))
+}
+
+/// Recursively merge `overlay` into `base`; tables merge, other values replace
+pub fn merge(base: &mut Table, overlay: &Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(key), value) {
+            (Some(Value::Table(existing)), Value::Table(incoming)) => merge(existing, incoming),
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Look up a dotted key
+pub fn lookup<'a>(table: &'a Table, key: &str) -> Option<&'a Value> {
+    let (first, rest) = match key.split_once('.') {
+        Some((first, rest)) => (first, Some(rest)),
+        None => (key, None),
+    };
+    let value = table.get(first)?;
+    match rest {
+        None => Some(value),
+        Some(rest) => lookup(value.as_table()?, rest),
+    }
+}
+
+/// Set a dotted key, creating intermediate tables
+pub fn insert_key(table: &mut Table, key: &str, value: Value) -> Result<(), String> {
+    if key.is_empty() || key.split('.').any(str::is_empty) {
+        return Err("empty key segment".to_string());
+    }
+    match key.split_once('.') {
+        None => {
+            table.insert(key.to_string(), value);
+            Ok(())
+        }
+        Some((first, rest)) => {
+            let child = table
+                .entry(first.to_string())
+                .or_insert_with(|| Value::Table(Table::new()));
+            match child {
+                Value::Table(child) => insert_key(child, rest, value),
+                _ => Err(format!("`{}` is not a table", first)),
+            }
+        }
+    }
+}
+
+/// Set a dotted key in a TOML file, keeping its comments and layout; the
+/// file is created if it doesn't exist
+pub fn write_key(path: &Path, key: &str, value: &Value) -> Result<()> {
+    let content = if path.exists() {
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?
+    } else {
+        String::new()
+    };
+    let mut doc: toml_edit::Document = content
+        .parse()
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    let (parents, leaf) = key.rsplit_once('.').unwrap_or(("", key));
+    let mut table = doc.as_table_mut();
+    for segment in parents.split('.').filter(|s| !s.is_empty()) {
+        let item = table.entry(segment).or_insert_with(|| {
+            let mut t = toml_edit::Table::new();
+            t.set_implicit(true);
+            toml_edit::Item::Table(t)
+        });
+        table = item
+            .as_table_mut()
+            .with_context(|| format!("`{}` in {} is not a table", segment, path.display()))?;
+    }
+    let edit_value: toml_edit::Value = value.to_string().parse().context("Failed to encode value")?;
+    table[leaf] = toml_edit::Item::Value(edit_value);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, doc.to_string()).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Dotted paths of all non-table values
+fn leaves(table: &Table, prefix: &str) -> Vec<(String, Value)> {
+    let mut out = Vec::new();
+    for (key, value) in table {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match value {
+            Value::Table(child) => out.extend(leaves(child, &path)),
+            _ => out.push((path, value.clone())),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(toml: &str) -> Table {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn test_effective_provenance() {
+        let defaults = Table::try_from(Config::default_minimal()).unwrap();
+        let mut layered = LayeredConfig::with_defaults(defaults);
+        let user = PathBuf::from("/home/me/.config/quant/config.toml");
+        layered.push(ConfigSource::User(user.clone()), table("[ollama]\nport = 11500\n"));
+        layered
+            .push_env(vec![
+                ("QUANT_MODELS__CHAT".to_string(), "llama3.2:3b".to_string()),
+                ("QUANT_LOG_FORMAT".to_string(), "json".to_string()),
+            ])
+            .unwrap();
+
+        let effective: EffectiveConfig<Config> = layered.effective().unwrap();
+        assert_eq!(effective.ollama_url(), "http://127.0.0.1:11500");
+        assert_eq!(effective.models.chat, "llama3.2:3b");
+        assert_eq!(effective.source("ollama.port"), Some(&ConfigSource::User(user)));
+        assert_eq!(effective.source("ollama.host"), Some(&ConfigSource::Default));
+        assert_eq!(
+            effective.source("models.chat"),
+            Some(&ConfigSource::Env("QUANT_MODELS__CHAT".to_string()))
+        );
+        // Keys below a resolved leaf fall back to the leaf's source
+        assert_eq!(effective.source("ollama.port.x"), effective.source("ollama.port"));
+        assert!(effective.provenance().any(|(key, _)| key == "network.expose_port"));
+    }
+
+    #[test]
+    fn test_validate_names_key() {
+        let defaults = Table::try_from(Config::default_minimal()).unwrap();
+        let mut layered = LayeredConfig::with_defaults(defaults);
+        let path = PathBuf::from("llm.toml");
+        layered.push(
+            ConfigSource::LlmToml(path.clone()),
+            table("[ollama]\nhost = \"0.0.0.0\"\nport = \"high\"\n\n[extra]\nx = 1\n"),
+        );
+
+        let check = |t: &Table| deserialize::<Config>(t).map(|_| ());
+        let err = layered.validate(Some(LLM_SECTIONS), check).unwrap_err();
+        assert_eq!(err.source, ConfigSource::LlmToml(path));
+        assert_eq!(err.key, "ollama.port");
+
+        let mut layered = LayeredConfig::with_defaults(Table::new());
+        layered.push_key(ConfigSource::Cli("olama.port=1".into()), "olama.port", Value::Integer(1)).unwrap();
+        let err = layered.validate(Some(LLM_SECTIONS), |_| Ok(())).unwrap_err();
+        assert_eq!((err.key.as_str(), err.message.as_str()), ("olama", "unknown section"));
+    }
+
+    #[test]
+    fn test_write_key_keeps_comments() {
+        let dir = std::env::temp_dir().join(format!("llm-core-write-key-{}", std::process::id()));
+        let path = dir.join("config.toml");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, "# My settings\n[ask]\ntemperature = 0.2 # low\n").unwrap();
+
+        write_key(&path, "menubar.refresh_secs", &Value::Integer(10)).unwrap();
+        write_key(&path, "ask.temperature", &Value::Float(0.5)).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("# My settings\n"));
+        let values = table(&content);
+        assert_eq!(lookup(&values, "menubar.refresh_secs"), Some(&Value::Integer(10)));
+        assert_eq!(lookup(&values, "ask.temperature"), Some(&Value::Float(0.5)));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_value_and_insert() {
+        assert_eq!(parse_value("0.5"), Value::Float(0.5));
+        assert_eq!(parse_value("true"), Value::Boolean(true));
+        assert_eq!(parse_value("llama3.2:3b"), Value::String("llama3.2:3b".to_string()));
+
+        let mut t = Table::new();
+        insert_key(&mut t, "roles.coder.model", Value::String("x".into())).unwrap();
+        assert_eq!(lookup(&t, "roles.coder.model"), Some(&Value::String("x".into())));
+        assert!(insert_key(&mut t, "roles.coder.model.deeper", Value::Integer(1)).is_err());
+        assert!(insert_key(&mut t, "a..b", Value::Integer(1)).is_err());
+    }
+}
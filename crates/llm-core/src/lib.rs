@@ -2,20 +2,41 @@
 //!
 //! Provides:
 //! - Configuration loading (llm.toml)
+//! - Layered configuration with per-value provenance
 //! - Ollama API client (with streaming support)
+//! - Error taxonomy with user-facing hints
 //! - Tailscale integration
 //! - Process management
+//! - Environment health checks
+//! - Local IPC between quant sessions and OllamaBar
+//! - First-token watchdog for stalled models
+//! - Splitting reasoning models' thinking from their answers
+//! - A scriptable mock Ollama for tests (`test-util` feature)
 
 pub mod config;
+pub mod error;
+pub mod health;
+pub mod ipc;
+pub mod layered;
+#[cfg(any(test, feature = "test-util"))]
+pub mod mock;
 pub mod ollama;
 pub mod process;
 pub mod tailscale;
+pub mod thinking;
+pub mod watchdog;
 
-pub use config::Config;
+pub use config::{Config, WarmModel};
+pub use error::LlmError;
+pub use health::{HealthCheck, HealthReport, HealthStatus};
+pub use layered::{ConfigError, ConfigPaths, ConfigSource, EffectiveConfig, LayeredConfig};
 pub use ollama::{
-    ChatChunk, ChatChunkMessageWithTools, ChatChunkWithTools, ChatMessage, ChatMessageWithTools,
-    ChatOptions, ChatResponse, ChatResponseWithTools, ChatStream, ChatStreamWithTools,
-    FunctionCall, FunctionDefinition, Model, OllamaClient, OllamaStatus, PullProgress, PullStream,
-    RetryConfig, Role, RunningModel, ToolCall, ToolDefinition,
+    estimate_pull_size, ChatChunk, ChatChunkMessageWithTools, ChatChunkWithTools, ChatMessage,
+    ChatMessageWithTools, ChatOptions, ChatResponse, ChatResponseWithTools, ChatStream,
+    ChatStreamWithTools, FunctionCall, FunctionDefinition, GenerateResponse, Model, ModelInfo, OllamaClient,
+    OllamaStatus, PullProgress, PullStream, RetryConfig, Role, RunningModel, Timeouts, ToolCall,
+    ToolDefinition,
 };
 pub use tailscale::{TailscaleClient, TailscaleStatus};
+pub use thinking::{split_thinking, ThinkingSplitter};
+pub use watchdog::ModelRestart;
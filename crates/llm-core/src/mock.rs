@@ -0,0 +1,703 @@
+//! Scriptable stand-in for Ollama (the `test-util` feature)
+//!
+//! [`MockOllama`] serves Ollama's HTTP API on a local port, so the real
+//! [`OllamaClient`] and everything built on it can run without a live
+//! Ollama. Chat and generate requests are answered from a script of
+//! [`MockResponse`]s, in order, and every request is kept for asserting on
+//! prompt construction. [`MockOllama::recording`] instead forwards to a real
+//! Ollama and keeps its replies, which [`MockOllama::save`] writes as a
+//! golden file that [`MockOllama::from_file`] serves back later.
+
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use crate::ollama::{FunctionCall, OllamaClient, ToolCall};
+
+/// Env var that makes [`assert_golden`] rewrite golden files instead of comparing
+pub const UPDATE_GOLDEN_ENV: &str = "UPDATE_GOLDEN";
+
+/// A scripted reply to one chat or generate request
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MockResponse {
+    #[serde(default)]
+    pub content: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub thinking: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    /// Sent instead of a reply, the way Ollama reports errors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<MockError>,
+    #[serde(default)]
+    pub prompt_tokens: u32,
+    #[serde(default)]
+    pub completion_tokens: u32,
+}
+
+/// HTTP status and `{"error": ...}` message of a failed request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MockError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl MockResponse {
+    /// A plain answer
+    pub fn text(content: impl Into<String>) -> Self {
+        Self { content: content.into(), ..Self::default() }
+    }
+
+    /// A native call of tool `name`
+    pub fn tool_call(name: impl Into<String>, arguments: Value) -> Self {
+        Self::default().with_tool_call(name, arguments)
+    }
+
+    /// An error response, e.g. `error(404, "model \"x\" not found")`
+    pub fn error(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            error: Some(MockError { status, message: message.into() }),
+            ..Self::default()
+        }
+    }
+
+    /// A streamed reply that fails after `content`, the way Ollama reports
+    /// errors during generation (an error line in a 200 response)
+    pub fn stream_error(content: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            error: Some(MockError { status: 200, message: message.into() }),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_tool_call(mut self, name: impl Into<String>, arguments: Value) -> Self {
+        self.tool_calls.push(ToolCall {
+            id: format!("call_{}", self.tool_calls.len()),
+            function: FunctionCall { name: name.into(), arguments },
+        });
+        self
+    }
+
+    pub fn with_thinking(mut self, thinking: impl Into<String>) -> Self {
+        self.thinking = thinking.into();
+        self
+    }
+
+    pub fn with_tokens(mut self, prompt: u32, completion: u32) -> Self {
+        self.prompt_tokens = prompt;
+        self.completion_tokens = completion;
+        self
+    }
+
+    /// Assemble a reply from an Ollama response body, streamed or not
+    fn from_ollama(status: u16, body: &str) -> Self {
+        if status >= 400 {
+            let message = serde_json::from_str::<Value>(body)
+                .ok()
+                .and_then(|v| v.get("error").and_then(Value::as_str).map(str::to_string))
+                .unwrap_or_else(|| body.trim().to_string());
+            return Self::error(status, message);
+        }
+
+        let mut response = Self::default();
+        for line in body.lines().filter(|line| !line.trim().is_empty()) {
+            let Ok(chunk) = serde_json::from_str::<Value>(line) else {
+                continue;
+            };
+            let message = chunk.get("message");
+            let text = |key: &str| {
+                message.and_then(|m| m.get(key)).or_else(|| chunk.get(key)).and_then(Value::as_str)
+            };
+            response.content.push_str(text("content").or(text("response")).unwrap_or_default());
+            response.thinking.push_str(text("thinking").unwrap_or_default());
+            if let Some(calls) = message.and_then(|m| m.get("tool_calls")) {
+                response
+                    .tool_calls
+                    .extend(serde_json::from_value::<Vec<ToolCall>>(calls.clone()).unwrap_or_default());
+            }
+            if let Some(message) = chunk.get("error").and_then(Value::as_str) {
+                return Self::error(status, message);
+            }
+            let count = |key: &str| chunk.get(key).and_then(Value::as_u64).unwrap_or(0) as u32;
+            response.prompt_tokens += count("prompt_eval_count");
+            response.completion_tokens += count("eval_count");
+        }
+        response
+    }
+}
+
+/// A request the mock received
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MockRequest {
+    pub method: String,
+    pub path: String,
+    /// JSON body, `null` for requests without one
+    pub body: Value,
+}
+
+impl MockRequest {
+    /// Model the request names
+    pub fn model(&self) -> Option<&str> {
+        self.body.get("model").and_then(Value::as_str)
+    }
+
+    /// Chat messages as (role, content); a generate prompt is one user message
+    pub fn messages(&self) -> Vec<(String, String)> {
+        if let Some(prompt) = self.body.get("prompt").and_then(Value::as_str) {
+            return vec![("user".to_string(), prompt.to_string())];
+        }
+        let field = |message: &Value, key: &str| {
+            message.get(key).and_then(Value::as_str).unwrap_or_default().to_string()
+        };
+        self.body
+            .get("messages")
+            .and_then(Value::as_array)
+            .map(|messages| messages.iter().map(|m| (field(m, "role"), field(m, "content"))).collect())
+            .unwrap_or_default()
+    }
+
+    /// Names of the tools offered to the model
+    pub fn tool_names(&self) -> Vec<String> {
+        self.body
+            .get("tools")
+            .and_then(Value::as_array)
+            .map(|tools| {
+                tools
+                    .iter()
+                    .filter_map(|t| t.pointer("/function/name").and_then(Value::as_str))
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Sampling option `name`, e.g. `temperature`
+    pub fn option(&self, name: &str) -> Option<&Value> {
+        self.body.get("options").and_then(|options| options.get(name))
+    }
+
+    /// Whether this asked the model for a reply (chat, or generate with a prompt)
+    fn is_prompt(&self) -> bool {
+        match self.path.as_str() {
+            "/api/chat" => true,
+            // An empty prompt only loads the model
+            "/api/generate" => self.body.get("prompt").and_then(Value::as_str).is_some_and(|p| !p.is_empty()),
+            _ => false,
+        }
+    }
+}
+
+enum Mode {
+    Script(VecDeque<MockResponse>),
+    Record { upstream: String, http: reqwest::Client },
+}
+
+struct State {
+    mode: Mode,
+    requests: Vec<MockRequest>,
+    recorded: Vec<MockResponse>,
+    models: Vec<String>,
+    context_length: Option<u64>,
+}
+
+/// Local server speaking Ollama's API; stops when dropped
+pub struct MockOllama {
+    url: String,
+    state: Arc<Mutex<State>>,
+    server: JoinHandle<()>,
+}
+
+impl MockOllama {
+    /// Answer chat and generate requests with `responses`, in order
+    pub async fn new(responses: impl IntoIterator<Item = MockResponse>) -> Result<Self> {
+        Self::start(Mode::Script(responses.into_iter().collect())).await
+    }
+
+    /// Forward every request to the Ollama at `upstream`, keeping its replies
+    pub async fn recording(upstream: impl Into<String>) -> Result<Self> {
+        let upstream = upstream.into().trim_end_matches('/').to_string();
+        Self::start(Mode::Record { upstream, http: reqwest::Client::new() }).await
+    }
+
+    /// Answer with the replies of a golden file written by [`save`](Self::save)
+    pub async fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let responses: Vec<MockResponse> =
+            serde_json::from_str(&text).with_context(|| format!("Invalid golden file {}", path.display()))?;
+        Self::new(responses).await
+    }
+
+    async fn start(mode: Mode) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .context("Failed to bind mock Ollama")?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let state = Arc::new(Mutex::new(State {
+            mode,
+            requests: Vec::new(),
+            recorded: Vec::new(),
+            models: vec!["mock".to_string()],
+            context_length: None,
+        }));
+        let server = tokio::spawn(serve(listener, Arc::clone(&state)));
+        Ok(Self { url, state, server })
+    }
+
+    /// Models listed by `/api/tags` (default: `mock`)
+    pub fn with_models(self, models: &[&str]) -> Self {
+        self.lock().models = models.iter().map(|m| m.to_string()).collect();
+        self
+    }
+
+    /// Context length `/api/show` reports (default: none)
+    pub fn with_context_length(self, tokens: u64) -> Self {
+        self.lock().context_length = Some(tokens);
+        self
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// A client talking to this mock
+    pub fn client(&self) -> OllamaClient {
+        OllamaClient::new(&self.url)
+    }
+
+    /// Queue another scripted response
+    pub fn push(&self, response: MockResponse) {
+        if let Mode::Script(queue) = &mut self.lock().mode {
+            queue.push_back(response);
+        }
+    }
+
+    /// Scripted responses not served yet
+    pub fn remaining(&self) -> usize {
+        match &self.lock().mode {
+            Mode::Script(queue) => queue.len(),
+            Mode::Record { .. } => 0,
+        }
+    }
+
+    /// Every request received, in order
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.lock().requests.clone()
+    }
+
+    /// Requests that asked the model for a reply, in order
+    pub fn prompts(&self) -> Vec<MockRequest> {
+        self.lock().requests.iter().filter(|r| r.is_prompt()).cloned().collect()
+    }
+
+    /// Replies kept while recording
+    pub fn recorded(&self) -> Vec<MockResponse> {
+        self.lock().recorded.clone()
+    }
+
+    /// Write the recorded replies as a golden file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        write_pretty(path.as_ref(), &self.recorded())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        lock(&self.state)
+    }
+}
+
+impl Drop for MockOllama {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// Compare `actual` as pretty JSON with the golden file at `path`
+///
+/// A missing file, or [`UPDATE_GOLDEN_ENV`] set, writes it instead.
+pub fn assert_golden(path: impl AsRef<Path>, actual: &impl Serialize) {
+    let path = path.as_ref();
+    let actual = serde_json::to_string_pretty(actual).expect("golden value serializes") + "\n";
+    if std::env::var_os(UPDATE_GOLDEN_ENV).is_some() || !path.exists() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).expect("create golden directory");
+        }
+        std::fs::write(path, &actual).expect("write golden file");
+        return;
+    }
+    let expected = std::fs::read_to_string(path).expect("read golden file");
+    assert_eq!(
+        expected,
+        actual,
+        "{} is out of date; rerun with {}=1 to update it",
+        path.display(),
+        UPDATE_GOLDEN_ENV
+    );
+}
+
+fn write_pretty(path: &Path, value: &impl Serialize) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(value)? + "\n")
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+async fn serve(listener: TcpListener, state: Arc<Mutex<State>>) {
+    while let Ok((socket, _)) = listener.accept().await {
+        let state = Arc::clone(&state);
+        tokio::spawn(async move {
+            if let Err(e) = handle(socket, &state).await {
+                tracing::debug!("mock Ollama connection failed: {e:#}");
+            }
+        });
+    }
+}
+
+async fn handle(mut socket: TcpStream, state: &Mutex<State>) -> Result<()> {
+    let (method, path, body) = read_request(&mut socket).await?;
+    let request = MockRequest {
+        method,
+        path,
+        body: serde_json::from_slice(&body).unwrap_or(Value::Null),
+    };
+    lock(state).requests.push(request.clone());
+
+    let forward = match &lock(state).mode {
+        Mode::Record { upstream, http } => Some((upstream.clone(), http.clone())),
+        Mode::Script(_) => None,
+    };
+    let response = match forward {
+        Some((upstream, http)) => forward_request(&request, &upstream, &http, state).await?,
+        None => respond(&request, state),
+    };
+
+    socket.write_all(&response).await?;
+    socket.shutdown().await?;
+    Ok(())
+}
+
+/// Read one HTTP/1.1 request: method, path and body
+async fn read_request(socket: &mut TcpStream) -> Result<(String, String, Vec<u8>)> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let header_end = loop {
+        if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end + 4;
+        }
+        let n = socket.read(&mut chunk).await?;
+        anyhow::ensure!(n > 0, "connection closed before the request headers");
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+    let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+    let length = head
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+
+    while buf.len() < header_end + length {
+        let n = socket.read(&mut chunk).await?;
+        anyhow::ensure!(n > 0, "connection closed before the request body");
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    Ok((method, path, buf[header_end..header_end + length].to_vec()))
+}
+
+fn respond(request: &MockRequest, state: &Mutex<State>) -> Vec<u8> {
+    let model = request.model().unwrap_or("mock").to_string();
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/api/chat" | "/api/generate") if request.is_prompt() => {
+            let next = match &mut lock(state).mode {
+                Mode::Script(queue) => queue.pop_front(),
+                Mode::Record { .. } => None,
+            };
+            match next {
+                Some(reply) => render(&reply, request, &model),
+                None => json_response(500, &json!({"error": "mock Ollama has no scripted response left"})),
+            }
+        }
+        ("POST", "/api/generate") => json_response(200, &json!({"model": model, "response": "", "done": true})),
+        ("GET", "/api/tags") => {
+            let models: Vec<Value> = lock(state)
+                .models
+                .iter()
+                .map(|name| json!({"name": name, "size": 0, "digest": "", "modified_at": ""}))
+                .collect();
+            json_response(200, &json!({ "models": models }))
+        }
+        ("GET", "/api/ps") => json_response(200, &json!({"models": []})),
+        ("GET", "/api/version") => json_response(200, &json!({"version": "0.0.0-mock"})),
+        ("GET", "/") => text_response(200, "Ollama is running"),
+        ("POST", "/api/show") => {
+            let mut info = serde_json::Map::new();
+            info.insert("general.architecture".to_string(), json!("mock"));
+            if let Some(tokens) = lock(state).context_length {
+                info.insert("mock.context_length".to_string(), json!(tokens));
+            }
+            json_response(200, &json!({ "model_info": info }))
+        }
+        (method, path) => json_response(404, &json!({"error": format!("mock Ollama does not serve {method} {path}")})),
+    }
+}
+
+/// A scripted reply in the shape the request asked for
+fn render(reply: &MockResponse, request: &MockRequest, model: &str) -> Vec<u8> {
+    if let Some(error) = reply.error.as_ref().filter(|e| e.status >= 400) {
+        return json_response(error.status, &json!({"error": error.message}));
+    }
+
+    let chat = request.path == "/api/chat";
+    let done = json!({
+        "model": model,
+        "done": true,
+        "prompt_eval_count": reply.prompt_tokens,
+        "eval_count": reply.completion_tokens,
+    });
+    let piece = |content: &str, thinking: &str, tool_calls: &[ToolCall]| {
+        let mut message = json!({"role": "assistant", "content": content});
+        if !thinking.is_empty() {
+            message["thinking"] = json!(thinking);
+        }
+        if !tool_calls.is_empty() {
+            message["tool_calls"] = json!(tool_calls);
+        }
+        if chat {
+            json!({"model": model, "message": message, "done": false})
+        } else {
+            json!({"model": model, "response": content, "done": false})
+        }
+    };
+
+    if request.body.get("stream").and_then(Value::as_bool) == Some(false) {
+        let mut whole = piece(&reply.content, &reply.thinking, &reply.tool_calls);
+        merge(&mut whole, &done);
+        return json_response(200, &whole);
+    }
+
+    // Stream the way Ollama does: thinking, then the answer a word at a time,
+    // then tool calls, then the counts
+    let mut lines = Vec::new();
+    if !reply.thinking.is_empty() {
+        lines.push(piece("", &reply.thinking, &[]));
+    }
+    lines.extend(reply.content.split_inclusive(' ').map(|word| piece(word, "", &[])));
+    if !reply.tool_calls.is_empty() {
+        lines.push(piece("", "", &reply.tool_calls));
+    }
+    match &reply.error {
+        Some(error) => lines.push(json!({"error": error.message})),
+        None => {
+            let mut last = piece("", "", &[]);
+            merge(&mut last, &done);
+            lines.push(last);
+        }
+    }
+
+    let body: String = lines.iter().map(|line| format!("{line}\n")).collect();
+    http_response(200, "application/x-ndjson", body.as_bytes())
+}
+
+fn merge(into: &mut Value, from: &Value) {
+    if let (Some(into), Some(from)) = (into.as_object_mut(), from.as_object()) {
+        into.extend(from.clone());
+    }
+}
+
+async fn forward_request(
+    request: &MockRequest,
+    upstream: &str,
+    http: &reqwest::Client,
+    state: &Mutex<State>,
+) -> Result<Vec<u8>> {
+    let url = format!("{}{}", upstream, request.path);
+    let builder = match request.method.as_str() {
+        "GET" => http.get(&url),
+        "DELETE" => http.delete(&url).json(&request.body),
+        _ => http.post(&url).json(&request.body),
+    };
+    let resp = builder.send().await.with_context(|| format!("Failed to reach {}", upstream))?;
+    let status = resp.status().as_u16();
+    let content_type = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/json")
+        .to_string();
+    let body = resp.bytes().await?;
+
+    if request.is_prompt() {
+        let reply = MockResponse::from_ollama(status, &String::from_utf8_lossy(&body));
+        lock(state).recorded.push(reply);
+    }
+    Ok(http_response(status, &content_type, &body))
+}
+
+fn json_response(status: u16, body: &Value) -> Vec<u8> {
+    http_response(status, "application/json", body.to_string().as_bytes())
+}
+
+fn text_response(status: u16, body: &str) -> Vec<u8> {
+    http_response(status, "text/plain", body.as_bytes())
+}
+
+fn http_response(status: u16, content_type: &str, body: &[u8]) -> Vec<u8> {
+    let mut response = format!(
+        "HTTP/1.1 {status} {}\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+        reason(status),
+        body.len()
+    )
+    .into_bytes();
+    response.extend_from_slice(body);
+    response
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Status",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ollama::{ChatMessage, ChatMessageWithTools};
+    use crate::LlmError;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_scripted_chat_and_prompt_capture() {
+        let mock = MockOllama::new([MockResponse::text("Hello there").with_tokens(12, 2)])
+            .await
+            .unwrap();
+        let client = mock.client();
+
+        let messages = vec![ChatMessage::system("Be brief"), ChatMessage::user("Hi")];
+        let response = client.chat("qwen3:8b", &messages, None).await.unwrap();
+        assert_eq!(response.message.content, "Hello there");
+        assert_eq!(response.prompt_eval_count, 12);
+
+        let prompts = mock.prompts();
+        assert_eq!(prompts.len(), 1);
+        assert_eq!(prompts[0].model(), Some("qwen3:8b"));
+        assert_eq!(
+            prompts[0].messages(),
+            vec![("system".to_string(), "Be brief".to_string()), ("user".to_string(), "Hi".to_string())]
+        );
+        assert_eq!(mock.remaining(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_streamed_tool_call_and_thinking() {
+        let mock = MockOllama::new([MockResponse::tool_call("glob", json!({"pattern": "*.rs"}))
+            .with_thinking("Look for Rust files")])
+        .await
+        .unwrap();
+
+        let messages = vec![ChatMessageWithTools::from_message(&ChatMessage::user("List the sources"))];
+        let mut stream = mock
+            .client()
+            .chat_stream_with_tools("mock", &messages, None, None)
+            .await
+            .unwrap();
+        let (mut thinking, mut calls) = (String::new(), Vec::new());
+        while let Some(chunk) = stream.next().await {
+            let message = chunk.unwrap().message.unwrap();
+            thinking.push_str(&message.thinking);
+            calls.extend(message.tool_calls);
+        }
+        assert_eq!(thinking, "Look for Rust files");
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].function.name, "glob");
+        assert_eq!(calls[0].function.arguments, json!({"pattern": "*.rs"}));
+    }
+
+    #[tokio::test]
+    async fn test_errors_and_exhausted_script() {
+        let mock = MockOllama::new([MockResponse::error(404, "model \"nope\" not found, try pulling it first")])
+            .await
+            .unwrap();
+        let client = mock.client();
+        let messages = vec![ChatMessage::user("Hi")];
+
+        let err = client.chat("nope", &messages, None).await.unwrap_err();
+        assert_eq!(err.downcast_ref::<LlmError>(), Some(&LlmError::ModelNotFound { model: "nope".to_string() }));
+
+        let err = client.chat("nope", &messages, None).await.unwrap_err();
+        assert!(err.to_string().contains("no scripted response left"));
+    }
+
+    #[tokio::test]
+    async fn test_model_endpoints() {
+        let mock = MockOllama::new([]).await.unwrap().with_models(&["a:1b", "b:2b"]).with_context_length(8192);
+        let client = mock.client();
+
+        assert!(client.health_check().await.unwrap());
+        let names: Vec<String> = client.list_models().await.unwrap().into_iter().map(|m| m.name).collect();
+        assert_eq!(names, vec!["a:1b", "b:2b"]);
+        assert_eq!(client.show_model("a:1b").await.unwrap().context_length(), Some(8192));
+        // Loading a model doesn't use up the script
+        client.load_model("a:1b").await.unwrap();
+        assert!(mock.prompts().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_recording_round_trips_through_golden_file() {
+        let script = vec![
+            MockResponse::tool_call("file_read", json!({"path": "a.txt"})),
+            MockResponse::text("It says hi").with_tokens(30, 3),
+        ];
+        let upstream = MockOllama::new(script.clone()).await.unwrap();
+        let recorder = MockOllama::recording(upstream.url()).await.unwrap();
+
+        let messages = vec![ChatMessageWithTools::from_message(&ChatMessage::user("Read a.txt"))];
+        let first = recorder.client().chat_with_tools("mock", &messages, None, None).await.unwrap();
+        assert_eq!(first.message.tool_calls[0].function.name, "file_read");
+        let second = recorder.client().chat("mock", &[ChatMessage::user("and?")], None).await.unwrap();
+        assert_eq!(second.message.content, "It says hi");
+        assert_eq!(recorder.recorded(), script);
+        assert_eq!(upstream.prompts(), recorder.prompts());
+
+        let path = std::env::temp_dir().join(format!("llm-core-golden-{}.json", std::process::id()));
+        recorder.save(&path).unwrap();
+        let replay = MockOllama::from_file(&path).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let first = replay.client().chat_with_tools("mock", &messages, None, None).await.unwrap();
+        assert_eq!(first.message.tool_calls, script[0].tool_calls);
+        assert_eq!(replay.remaining(), 1);
+    }
+
+    #[test]
+    fn test_assert_golden_writes_then_compares() {
+        let path = std::env::temp_dir().join(format!("llm-core-assert-golden-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        assert_golden(&path, &json!({"a": 1}));
+        assert_golden(&path, &json!({"a": 1}));
+        let mismatch = std::panic::catch_unwind(|| assert_golden(&path, &json!({"a": 2})));
+        std::fs::remove_file(&path).unwrap();
+        assert!(mismatch.is_err() || std::env::var_os(UPDATE_GOLDEN_ENV).is_some());
+    }
+}
@@ -1,5 +1,6 @@
 //! Ollama API client
 
+use crate::error::LlmError;
 use anyhow::{Context, Result};
 use futures::Stream;
 use serde::{Deserialize, Serialize};
@@ -39,6 +40,14 @@ impl RetryConfig {
         }
     }
 
+    /// Delay before the given retry attempt (1-based), with exponential backoff
+    pub fn delay_for_attempt(&self, attempt: u32) -> Duration {
+        let factor = self.backoff_multiplier.powi(attempt.saturating_sub(1) as i32);
+        Duration::from_secs_f64(
+            (self.initial_delay.as_secs_f64() * factor).min(self.max_delay.as_secs_f64()),
+        )
+    }
+
     /// Create a config for aggressive retrying (good for health checks)
     pub fn aggressive() -> Self {
         Self {
@@ -50,6 +59,48 @@ impl RetryConfig {
     }
 }
 
+/// Request timeouts per kind of operation
+///
+/// A zero duration means no limit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Timeouts {
+    /// Establishing a connection to Ollama
+    pub connect: Duration,
+    /// Quick API calls such as listing or deleting models
+    pub request: Duration,
+    /// Chat and generate requests, including reading a streamed response
+    pub chat: Duration,
+    /// Downloading a model
+    pub pull: Duration,
+    /// Loading a model into memory or creating one from a Modelfile
+    pub load: Duration,
+    /// Waiting for a streamed chat reply's first token (see
+    /// [`OllamaClient::open_watched`])
+    pub first_token: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect: Duration::from_secs(60),
+            request: Duration::from_secs(30),
+            chat: Duration::from_secs(300),
+            pull: Duration::from_secs(3600),
+            load: Duration::from_secs(300),
+            first_token: Duration::from_secs(120),
+        }
+    }
+}
+
+/// Apply a timeout to a request unless it is zero (no limit)
+fn with_timeout(req: reqwest::RequestBuilder, timeout: Duration) -> reqwest::RequestBuilder {
+    if timeout.is_zero() {
+        req
+    } else {
+        req.timeout(timeout)
+    }
+}
+
 /// Ollama service status
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OllamaStatus {
@@ -102,11 +153,100 @@ struct PsResponse {
     models: Vec<RunningModel>,
 }
 
+#[derive(Debug, Deserialize)]
+struct VersionResponse {
+    version: String,
+}
+
+/// An installed model's details from `/api/show`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ModelInfo {
+    #[serde(default)]
+    pub details: ModelDetails,
+    /// Modelfile `PARAMETER` lines, one `name value` per line
+    #[serde(default)]
+    pub parameters: String,
+    #[serde(default)]
+    pub license: String,
+    /// Default system prompt
+    #[serde(default)]
+    pub system: String,
+    /// Prompt template
+    #[serde(default)]
+    pub template: String,
+    /// GGUF metadata, e.g. `llama.context_length`
+    #[serde(default)]
+    pub model_info: std::collections::HashMap<String, serde_json::Value>,
+}
+
+impl ModelInfo {
+    /// Context length the model was trained with
+    pub fn context_length(&self) -> Option<u64> {
+        self.model_info
+            .iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64())
+    }
+
+    /// Context window set with `PARAMETER num_ctx` in the Modelfile
+    pub fn num_ctx(&self) -> Option<u64> {
+        self.parameters.lines().find_map(|line| {
+            let (name, value) = line.trim().split_once(char::is_whitespace)?;
+            (name == "num_ctx").then(|| value.trim().parse().ok()).flatten()
+        })
+    }
+
+    /// Number of parameters
+    pub fn parameter_count(&self) -> Option<u64> {
+        self.model_info.get("general.parameter_count").and_then(|value| value.as_u64())
+    }
+}
+
+/// `keep_alive` as a duration string; Ollama only accepts bare numbers of
+/// seconds as JSON numbers
+fn keep_alive_duration(keep_alive: &str) -> String {
+    match keep_alive.trim().parse::<i64>() {
+        Ok(seconds) => format!("{}s", seconds),
+        Err(_) => keep_alive.trim().to_string(),
+    }
+}
+
 #[derive(Debug, Serialize)]
 struct GenerateRequest {
     model: String,
     prompt: String,
     stream: bool,
+    /// Text after the cursor, for fill-in-the-middle completion
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suffix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<ChatOptions>,
+    /// How long to keep the model loaded afterwards (e.g. "10m")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<String>,
+}
+
+/// Response from non-streaming generate
+#[derive(Debug, Clone, Deserialize)]
+pub struct GenerateResponse {
+    pub model: String,
+    pub response: String,
+    pub done: bool,
+    #[serde(default)]
+    pub total_duration: u64,
+    #[serde(default)]
+    pub eval_count: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbedRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbedResponse {
+    embeddings: Vec<Vec<f32>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -184,7 +324,7 @@ pub struct FunctionDefinition {
 }
 
 /// A tool call from the assistant
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ToolCall {
     /// Tool call ID (for tracking)
     #[serde(default)]
@@ -194,7 +334,7 @@ pub struct ToolCall {
 }
 
 /// Function call details
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FunctionCall {
     /// Function name
     pub name: String,
@@ -203,7 +343,7 @@ pub struct FunctionCall {
 }
 
 /// Extended chat message that can include tool calls
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ChatMessageWithTools {
     /// Message role
     pub role: Role,
@@ -461,6 +601,8 @@ struct ChatRequest {
     stream: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     options: Option<ChatOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -472,6 +614,8 @@ struct ChatRequestWithTools {
     options: Option<ChatOptions>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<ToolDefinition>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -481,9 +625,85 @@ pub struct ChatOptions {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    /// Drop tokens less likely than this fraction of the likeliest one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeat_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    /// Fixed seed, for repeatable output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub num_predict: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stop: Option<Vec<String>>,
+    /// Context window to load the model with (Ollama's default if unset)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+    /// Overrides the client's chat timeout for this request (zero for none)
+    #[serde(skip)]
+    pub timeout: Option<Duration>,
+    /// How long to keep the model loaded afterwards (e.g. "10m"); sent
+    /// alongside the options rather than in them
+    #[serde(skip)]
+    pub keep_alive: Option<String>,
+}
+
+impl ChatOptions {
+    /// Chat timeout for a request, falling back to the client's
+    fn timeout_or(options: Option<&ChatOptions>, default: Duration) -> Duration {
+        options.and_then(|o| o.timeout).unwrap_or(default)
+    }
+
+    fn keep_alive_of(options: Option<&ChatOptions>) -> Option<String> {
+        options.and_then(|o| o.keep_alive.clone())
+    }
+
+    /// Check the options are in the ranges Ollama accepts
+    pub fn validate(&self) -> std::result::Result<(), LlmError> {
+        fn in_range(option: &str, value: Option<f32>, min: f32, max: f32) -> std::result::Result<(), LlmError> {
+            match value {
+                Some(v) if !(min..=max).contains(&v) => Err(LlmError::InvalidOption {
+                    option: option.to_string(),
+                    message: format!("{} is outside {} to {}", v, min, max),
+                }),
+                _ => Ok(()),
+            }
+        }
+        let invalid = |option: &str, message: &str| {
+            Err(LlmError::InvalidOption {
+                option: option.to_string(),
+                message: message.to_string(),
+            })
+        };
+
+        in_range("temperature", self.temperature, 0.0, 2.0)?;
+        in_range("top_p", self.top_p, 0.0, 1.0)?;
+        in_range("min_p", self.min_p, 0.0, 1.0)?;
+        in_range("repeat_penalty", self.repeat_penalty, 0.0, 10.0)?;
+        in_range("presence_penalty", self.presence_penalty, -2.0, 2.0)?;
+        in_range("frequency_penalty", self.frequency_penalty, -2.0, 2.0)?;
+        if self.num_ctx == Some(0) {
+            return invalid("num_ctx", "the context window must be at least 1 token");
+        }
+        if self.num_predict.is_some_and(|n| n < -2) {
+            return invalid("num_predict", "use a token count, -1 for no limit or -2 to fill the context");
+        }
+        if self.stop.as_ref().is_some_and(|stop| stop.iter().any(String::is_empty)) {
+            return invalid("stop", "stop sequences can't be empty");
+        }
+        Ok(())
+    }
+
+    fn check(options: Option<&ChatOptions>) -> Result<()> {
+        options.map(ChatOptions::validate).transpose()?;
+        Ok(())
+    }
 }
 
 /// Response from non-streaming chat
@@ -525,6 +745,9 @@ pub struct ChatChunk {
 pub struct ChatChunkMessage {
     pub role: Role,
     pub content: String,
+    /// Reasoning, for thinking models when Ollama separates it
+    #[serde(default)]
+    pub thinking: String,
 }
 
 /// Type alias for the stream of chat chunks
@@ -555,6 +778,9 @@ pub struct ChatChunkMessageWithTools {
     pub role: Role,
     #[serde(default)]
     pub content: String,
+    /// Reasoning, for thinking models when Ollama separates it
+    #[serde(default)]
+    pub thinking: String,
     #[serde(default)]
     pub tool_calls: Vec<ToolCall>,
 }
@@ -581,22 +807,35 @@ pub struct PullProgress {
 pub struct OllamaClient {
     base_url: String,
     client: reqwest::Client,
+    timeouts: Timeouts,
 }
 
 impl OllamaClient {
-    /// Create a new client with default timeout
+    /// Create a new client with default timeouts
     pub fn new(base_url: impl Into<String>) -> Self {
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(30))
-            .build()
-            .expect("Failed to create HTTP client");
+        Self::with_timeouts(base_url, Timeouts::default())
+    }
+
+    /// Create a new client with the given timeouts
+    pub fn with_timeouts(base_url: impl Into<String>, timeouts: Timeouts) -> Self {
+        let mut builder = reqwest::Client::builder();
+        if !timeouts.connect.is_zero() {
+            builder = builder.connect_timeout(timeouts.connect);
+        }
+        let client = builder.build().expect("Failed to create HTTP client");
 
         Self {
             base_url: base_url.into(),
             client,
+            timeouts,
         }
     }
 
+    /// Timeouts this client applies to each kind of request
+    pub fn timeouts(&self) -> &Timeouts {
+        &self.timeouts
+    }
+
     /// Check if Ollama is running
     pub async fn health_check(&self) -> Result<bool> {
         let url = format!("{}/api/tags", self.base_url);
@@ -664,12 +903,10 @@ impl OllamaClient {
     pub async fn list_models(&self) -> Result<Vec<Model>> {
         let url = format!("{}/api/tags", self.base_url);
 
-        let resp: TagsResponse = self
-            .client
-            .get(&url)
+        let resp: TagsResponse = with_timeout(self.client.get(&url), self.timeouts.request)
             .send()
             .await
-            .context("Failed to connect to Ollama")?
+            .map_err(|e| self.request_error(e, "Failed to connect to Ollama"))?
             .json()
             .await
             .context("Failed to parse models response")?;
@@ -681,12 +918,10 @@ impl OllamaClient {
     pub async fn list_running(&self) -> Result<Vec<RunningModel>> {
         let url = format!("{}/api/ps", self.base_url);
 
-        let resp: PsResponse = self
-            .client
-            .get(&url)
+        let resp: PsResponse = with_timeout(self.client.get(&url), self.timeouts.request)
             .send()
             .await
-            .context("Failed to connect to Ollama")?
+            .map_err(|e| self.request_error(e, "Failed to connect to Ollama"))?
             .json()
             .await
             .context("Failed to parse running models response")?;
@@ -694,31 +929,115 @@ impl OllamaClient {
         Ok(resp.models)
     }
 
+    /// Get the server version
+    pub async fn version(&self) -> Result<String> {
+        let url = format!("{}/api/version", self.base_url);
+
+        let resp: VersionResponse = self
+            .client
+            .get(&url)
+            .timeout(Duration::from_secs(5))
+            .send()
+            .await
+            .map_err(|e| self.request_error(e, "Failed to connect to Ollama"))?
+            .json()
+            .await
+            .context("Failed to parse version response")?;
+
+        Ok(resp.version)
+    }
+
+    /// Details of an installed model
+    pub async fn show_model(&self, name: &str) -> Result<ModelInfo> {
+        let url = format!("{}/api/show", self.base_url);
+
+        #[derive(Serialize)]
+        struct ShowRequest<'a> {
+            model: &'a str,
+        }
+
+        let req = self.client.post(&url).json(&ShowRequest { model: name });
+        let resp = with_timeout(req, self.timeouts.request)
+            .send()
+            .await
+            .map_err(|e| self.request_error(e, "Failed to connect to Ollama"))?;
+        check_status(resp, name)
+            .await?
+            .json()
+            .await
+            .context("Failed to parse model details")
+    }
+
+    /// Whether a model is installed; an untagged name means `:latest`
+    pub async fn has_model(&self, name: &str) -> Result<bool> {
+        let models = self.list_models().await?;
+        Ok(models.iter().any(|m| same_model(&m.name, name)))
+    }
+
+    /// Make sure a model is installed, pulling it if `confirm` agrees
+    ///
+    /// `confirm` gets the estimated download size (see [`estimate_pull_size`]);
+    /// declining fails with [`LlmError::ModelNotFound`]. Returns whether the
+    /// model was pulled.
+    pub async fn ensure_model(
+        &self,
+        name: &str,
+        confirm: impl FnOnce(Option<u64>) -> bool,
+        mut on_progress: impl FnMut(&PullProgress),
+    ) -> Result<bool> {
+        use futures::StreamExt;
+
+        if self.has_model(name).await? {
+            return Ok(false);
+        }
+        if !confirm(estimate_pull_size(name)) {
+            return Err(LlmError::ModelNotFound { model: name.to_string() }.into());
+        }
+
+        let mut stream = self.pull_model_stream(name).await?;
+        while let Some(progress) = stream.next().await {
+            on_progress(&progress?);
+        }
+        Ok(true)
+    }
+
     /// Get the currently loaded model (if any)
     pub async fn current_model(&self) -> Result<Option<String>> {
         let running = self.list_running().await?;
         Ok(running.first().map(|m| m.name.clone()))
     }
 
+    /// Unload a model from memory
+    pub async fn unload_model(&self, model: &str) -> Result<()> {
+        self.warm_model(model, Some("0")).await
+    }
+
     /// Load a model (by running a minimal generate request)
     pub async fn load_model(&self, model: &str) -> Result<()> {
+        self.warm_model(model, None).await
+    }
+
+    /// Load a model and keep it loaded for `keep_alive` (e.g. "2h"; a bare
+    /// number is seconds, negative for as long as Ollama runs) instead of
+    /// Ollama's default
+    pub async fn warm_model(&self, model: &str, keep_alive: Option<&str>) -> Result<()> {
         let url = format!("{}/api/generate", self.base_url);
 
         let req = GenerateRequest {
             model: model.to_string(),
             prompt: String::new(),
             stream: false,
+            suffix: None,
+            options: None,
+            keep_alive: keep_alive.filter(|k| !k.is_empty()).map(keep_alive_duration),
         };
 
-        self.client
-            .post(&url)
-            .json(&req)
-            .timeout(Duration::from_secs(300)) // Models can take a while to load
+        // Models can take a while to load
+        let resp = with_timeout(self.client.post(&url).json(&req), self.timeouts.load)
             .send()
             .await
-            .context("Failed to load model")?
-            .error_for_status()
-            .context("Model load failed")?;
+            .map_err(|e| self.request_error(e, "Failed to load model"))?;
+        check_status(resp, model).await?;
 
         Ok(())
     }
@@ -732,15 +1051,11 @@ impl OllamaClient {
             stream: false,
         };
 
-        self.client
-            .post(&url)
-            .json(&req)
-            .timeout(Duration::from_secs(3600)) // 1 hour timeout for large models
+        let resp = with_timeout(self.client.post(&url).json(&req), self.timeouts.pull)
             .send()
             .await
-            .context("Failed to pull model")?
-            .error_for_status()
-            .context("Model pull failed")?;
+            .map_err(|e| self.request_error(e, "Failed to pull model"))?;
+        check_status(resp, name).await?;
 
         Ok(())
     }
@@ -754,15 +1069,12 @@ impl OllamaClient {
             stream: true,
         };
 
-        let resp = self
-            .client
-            .post(&url)
-            .json(&req)
+        let resp = with_timeout(self.client.post(&url).json(&req), self.timeouts.pull)
             .send()
             .await
-            .context("Failed to start model pull")?
-            .error_for_status()
-            .context("Model pull request failed")?;
+            .map_err(|e| self.request_error(e, "Failed to start model pull"))?;
+        let resp = check_status(resp, name).await?;
+        let model = name.to_string();
 
         let stream = async_stream::try_stream! {
             use futures::StreamExt as FuturesStreamExt;
@@ -784,6 +1096,11 @@ impl OllamaClient {
                         continue;
                     }
 
+                    // Failures after the download starts arrive as an error line
+                    if let Some(err) = LlmError::from_stream_line(&line, &model) {
+                        Err(err)?;
+                    }
+
                     let progress: PullProgress = serde_json::from_str(&line)
                         .with_context(|| format!("Failed to parse progress: {}", line))?;
 
@@ -811,16 +1128,14 @@ impl OllamaClient {
             name: String,
         }
 
-        self.client
-            .delete(&url)
-            .json(&DeleteRequest {
-                name: name.to_string(),
-            })
+        let req = self.client.delete(&url).json(&DeleteRequest {
+            name: name.to_string(),
+        });
+        let resp = with_timeout(req, self.timeouts.request)
             .send()
             .await
-            .context("Failed to delete model")?
-            .error_for_status()
-            .context("Model delete failed")?;
+            .map_err(|e| self.request_error(e, "Failed to delete model"))?;
+        check_status(resp, name).await?;
 
         Ok(())
     }
@@ -836,23 +1151,79 @@ impl OllamaClient {
             stream: bool,
         }
 
-        self.client
-            .post(&url)
-            .json(&CreateRequest {
-                name: name.to_string(),
-                modelfile: modelfile_content.to_string(),
-                stream: false,
-            })
-            .timeout(Duration::from_secs(300))
+        let req = self.client.post(&url).json(&CreateRequest {
+            name: name.to_string(),
+            modelfile: modelfile_content.to_string(),
+            stream: false,
+        });
+        let resp = with_timeout(req, self.timeouts.load)
             .send()
             .await
-            .context("Failed to create model")?
-            .error_for_status()
-            .context("Model creation failed")?;
+            .map_err(|e| self.request_error(e, "Failed to create model"))?;
+        check_status(resp, name).await?;
 
         Ok(())
     }
 
+    /// Generate a completion for a raw prompt (non-streaming)
+    ///
+    /// With a `suffix`, models that support fill-in-the-middle complete the
+    /// text between `prompt` and `suffix`.
+    pub async fn generate(
+        &self,
+        model: &str,
+        prompt: &str,
+        suffix: Option<&str>,
+        options: Option<ChatOptions>,
+        keep_alive: Option<&str>,
+    ) -> Result<GenerateResponse> {
+        let url = format!("{}/api/generate", self.base_url);
+        ChatOptions::check(options.as_ref())?;
+
+        let timeout = ChatOptions::timeout_or(options.as_ref(), self.timeouts.chat);
+        let req = GenerateRequest {
+            model: model.to_string(),
+            prompt: prompt.to_string(),
+            stream: false,
+            suffix: suffix.map(str::to_string),
+            keep_alive: keep_alive
+                .map(str::to_string)
+                .or_else(|| ChatOptions::keep_alive_of(options.as_ref())),
+            options,
+        };
+
+        let resp = with_timeout(self.client.post(&url).json(&req), timeout)
+            .send()
+            .await
+            .map_err(|e| self.request_error(e, "Failed to send generate request"))?;
+        let resp = check_status(resp, model).await?;
+
+        resp.json().await.context("Failed to parse generate response")
+    }
+
+    /// Embed each of `inputs` with an embedding model (e.g. nomic-embed-text)
+    pub async fn embed(&self, model: &str, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+        let url = format!("{}/api/embed", self.base_url);
+
+        let req = EmbedRequest { model, input: inputs };
+        let resp = with_timeout(self.client.post(&url).json(&req), self.timeouts.chat)
+            .send()
+            .await
+            .map_err(|e| self.request_error(e, "Failed to send embed request"))?;
+        let resp = check_status(resp, model).await?;
+
+        let response: EmbedResponse = resp.json().await.context("Failed to parse embed response")?;
+        if response.embeddings.len() != inputs.len() {
+            anyhow::bail!(
+                "Expected {} embeddings from {}, got {}",
+                inputs.len(),
+                model,
+                response.embeddings.len()
+            );
+        }
+        Ok(response.embeddings)
+    }
+
     /// Send a chat message (non-streaming)
     pub async fn chat(
         &self,
@@ -861,24 +1232,22 @@ impl OllamaClient {
         options: Option<ChatOptions>,
     ) -> Result<ChatResponse> {
         let url = format!("{}/api/chat", self.base_url);
+        ChatOptions::check(options.as_ref())?;
 
+        let timeout = ChatOptions::timeout_or(options.as_ref(), self.timeouts.chat);
         let req = ChatRequest {
             model: model.to_string(),
             messages: messages.to_vec(),
             stream: false,
+            keep_alive: ChatOptions::keep_alive_of(options.as_ref()),
             options,
         };
 
-        let resp = self
-            .client
-            .post(&url)
-            .json(&req)
-            .timeout(Duration::from_secs(300))
+        let resp = with_timeout(self.client.post(&url).json(&req), timeout)
             .send()
             .await
-            .context("Failed to send chat request")?
-            .error_for_status()
-            .context("Chat request failed")?;
+            .map_err(|e| self.request_error(e, "Failed to send chat request"))?;
+        let resp = check_status(resp, model).await?;
 
         resp.json().await.context("Failed to parse chat response")
     }
@@ -891,23 +1260,24 @@ impl OllamaClient {
         options: Option<ChatOptions>,
     ) -> Result<ChatStream> {
         let url = format!("{}/api/chat", self.base_url);
+        ChatOptions::check(options.as_ref())?;
 
+        let timeout = ChatOptions::timeout_or(options.as_ref(), self.timeouts.chat);
         let req = ChatRequest {
             model: model.to_string(),
             messages: messages.to_vec(),
             stream: true,
+            keep_alive: ChatOptions::keep_alive_of(options.as_ref()),
             options,
         };
 
-        let resp = self
-            .client
-            .post(&url)
-            .json(&req)
+        let resp = with_timeout(self.client.post(&url).json(&req), timeout)
             .send()
             .await
-            .context("Failed to send chat request")?
-            .error_for_status()
-            .context("Chat request failed")?;
+            .map_err(|e| self.request_error(e, "Failed to send chat request"))?;
+        let resp = check_status(resp, model).await?;
+        let base_url = self.base_url.clone();
+        let model = model.to_string();
 
         let stream = async_stream::try_stream! {
             use futures::StreamExt as FuturesStreamExt;
@@ -916,7 +1286,7 @@ impl OllamaClient {
             let mut buffer = String::new();
 
             while let Some(chunk_result) = FuturesStreamExt::next(&mut byte_stream).await {
-                let chunk: bytes::Bytes = chunk_result.context("Error reading stream")?;
+                let chunk: bytes::Bytes = chunk_result.map_err(|e| read_error(e, &base_url))?;
                 let text = String::from_utf8_lossy(&chunk);
                 buffer.push_str(&text);
 
@@ -929,6 +1299,11 @@ impl OllamaClient {
                         continue;
                     }
 
+                    // Failures during generation arrive as an error line
+                    if let Some(err) = LlmError::from_stream_line(&line, &model) {
+                        Err(err)?;
+                    }
+
                     let chat_chunk: ChatChunk = serde_json::from_str(&line)
                         .with_context(|| format!("Failed to parse chunk: {}", line))?;
 
@@ -952,6 +1327,20 @@ impl OllamaClient {
         &self.base_url
     }
 
+    /// Map a failed send to an `LlmError` when the cause is one the user can fix
+    fn request_error(&self, err: reqwest::Error, context: &'static str) -> anyhow::Error {
+        let url = self.base_url.clone();
+        if err.is_timeout() {
+            LlmError::Timeout { url }.into()
+        } else if err.is_connect() {
+            LlmError::ConnectionRefused { url }.into()
+        } else if err.is_request() || err.is_body() {
+            LlmError::Interrupted { url }.into()
+        } else {
+            anyhow::Error::new(err).context(context)
+        }
+    }
+
     /// Send a chat message with tool support (non-streaming)
     pub async fn chat_with_tools(
         &self,
@@ -961,25 +1350,23 @@ impl OllamaClient {
         options: Option<ChatOptions>,
     ) -> Result<ChatResponseWithTools> {
         let url = format!("{}/api/chat", self.base_url);
+        ChatOptions::check(options.as_ref())?;
 
+        let timeout = ChatOptions::timeout_or(options.as_ref(), self.timeouts.chat);
         let req = ChatRequestWithTools {
             model: model.to_string(),
             messages: messages.to_vec(),
             stream: false,
+            keep_alive: ChatOptions::keep_alive_of(options.as_ref()),
             options,
             tools: tools.map(|t| t.to_vec()),
         };
 
-        let resp = self
-            .client
-            .post(&url)
-            .json(&req)
-            .timeout(Duration::from_secs(300))
+        let resp = with_timeout(self.client.post(&url).json(&req), timeout)
             .send()
             .await
-            .context("Failed to send chat request")?
-            .error_for_status()
-            .context("Chat request failed")?;
+            .map_err(|e| self.request_error(e, "Failed to send chat request"))?;
+        let resp = check_status(resp, model).await?;
 
         let mut response: ChatResponseWithTools = resp.json().await.context("Failed to parse chat response")?;
 
@@ -1001,24 +1388,25 @@ impl OllamaClient {
         options: Option<ChatOptions>,
     ) -> Result<ChatStreamWithTools> {
         let url = format!("{}/api/chat", self.base_url);
+        ChatOptions::check(options.as_ref())?;
 
+        let timeout = ChatOptions::timeout_or(options.as_ref(), self.timeouts.chat);
         let req = ChatRequestWithTools {
             model: model.to_string(),
             messages: messages.to_vec(),
             stream: true, // Enable streaming
+            keep_alive: ChatOptions::keep_alive_of(options.as_ref()),
             options,
             tools: tools.map(|t| t.to_vec()),
         };
 
-        let resp = self
-            .client
-            .post(&url)
-            .json(&req)
+        let resp = with_timeout(self.client.post(&url).json(&req), timeout)
             .send()
             .await
-            .context("Failed to send chat request")?
-            .error_for_status()
-            .context("Chat request failed")?;
+            .map_err(|e| self.request_error(e, "Failed to send chat request"))?;
+        let resp = check_status(resp, model).await?;
+        let base_url = self.base_url.clone();
+        let model = model.to_string();
 
         let stream = async_stream::try_stream! {
             use futures::StreamExt as FuturesStreamExt;
@@ -1027,7 +1415,7 @@ impl OllamaClient {
             let mut buffer = String::new();
 
             while let Some(chunk_result) = FuturesStreamExt::next(&mut byte_stream).await {
-                let chunk: bytes::Bytes = chunk_result.context("Error reading stream")?;
+                let chunk: bytes::Bytes = chunk_result.map_err(|e| read_error(e, &base_url))?;
                 let text = String::from_utf8_lossy(&chunk);
                 buffer.push_str(&text);
 
@@ -1040,6 +1428,11 @@ impl OllamaClient {
                         continue;
                     }
 
+                    // Failures during generation arrive as an error line
+                    if let Some(err) = LlmError::from_stream_line(&line, &model) {
+                        Err(err)?;
+                    }
+
                     let chat_chunk: ChatChunkWithTools = serde_json::from_str(&line)
                         .with_context(|| format!("Failed to parse chunk: {}", line))?;
 
@@ -1059,6 +1452,74 @@ impl OllamaClient {
     }
 }
 
+/// Pass a successful response through; turn an error status into an `LlmError`
+async fn check_status(resp: reqwest::Response, model: &str) -> Result<reqwest::Response> {
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+    let body = resp.text().await.unwrap_or_default();
+    Err(LlmError::from_response(status.as_u16(), &body, model).into())
+}
+
+/// Turn a failed read of a streamed response into an `LlmError`
+fn read_error(err: reqwest::Error, base_url: &str) -> anyhow::Error {
+    let url = base_url.to_string();
+    if err.is_timeout() {
+        LlmError::Timeout { url }.into()
+    } else {
+        LlmError::Interrupted { url }.into()
+    }
+}
+
+/// Whether an installed model name refers to the requested one
+fn same_model(installed: &str, requested: &str) -> bool {
+    let with_tag = |name: &str| {
+        // Registry hosts can carry a port, so only look after the last '/'
+        let base = name.rsplit('/').next().unwrap_or(name);
+        if base.contains(':') {
+            name.to_string()
+        } else {
+            format!("{}:latest", name)
+        }
+    };
+    with_tag(installed) == with_tag(requested)
+}
+
+/// Rough download size of a model from its tag, e.g. `qwen2.5-coder:7b`
+///
+/// Ollama's default tags are 4-bit quantized; a `q8_0` or `fp16` in the tag
+/// scales the estimate. `None` when the tag has no parameter count.
+pub fn estimate_pull_size(name: &str) -> Option<u64> {
+    let (_, tag) = name.rsplit_once(':')?;
+    let tag = tag.to_lowercase();
+    let parts: Vec<&str> = tag.split('-').collect();
+    let params = parts.iter().find_map(|part| parse_param_count(part))?;
+
+    let bytes_per_param = parts
+        .iter()
+        .find_map(|part| match part.get(..2)? {
+            "fp" | "f1" => Some(2.0),
+            "q8" => Some(1.07),
+            "q6" => Some(0.82),
+            "q5" => Some(0.72),
+            "q3" => Some(0.5),
+            "q2" => Some(0.42),
+            _ => None,
+        })
+        .unwrap_or(0.61);
+    Some((params * 1e9 * bytes_per_param) as u64)
+}
+
+/// Billions of parameters from a tag part like `7b`, `0.5b` or `8x7b`
+fn parse_param_count(part: &str) -> Option<f64> {
+    let count = part.strip_suffix('b')?;
+    match count.split_once('x') {
+        Some((experts, each)) => Some(experts.parse::<f64>().ok()? * each.parse::<f64>().ok()?),
+        None => count.parse().ok(),
+    }
+}
+
 impl Model {
     /// Get human-readable size
     pub fn size_human(&self) -> String {
@@ -1086,6 +1547,20 @@ mod tests {
         assert_eq!(assistant.content, "Hi there!");
     }
 
+    #[test]
+    fn test_retry_delay_for_attempt() {
+        let config = RetryConfig {
+            max_retries: 5,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(500),
+            backoff_multiplier: 2.0,
+        };
+        assert_eq!(config.delay_for_attempt(1), Duration::from_millis(100));
+        assert_eq!(config.delay_for_attempt(2), Duration::from_millis(200));
+        assert_eq!(config.delay_for_attempt(3), Duration::from_millis(400));
+        assert_eq!(config.delay_for_attempt(4), Duration::from_millis(500));
+    }
+
     #[test]
     fn test_model_size_human() {
         let model = Model {
@@ -1098,6 +1573,25 @@ mod tests {
         assert_eq!(model.size_human(), "4.0 GB");
     }
 
+    #[test]
+    fn test_same_model() {
+        assert!(same_model("llama3.2:latest", "llama3.2"));
+        assert!(same_model("qwen2.5-coder:7b", "qwen2.5-coder:7b"));
+        assert!(!same_model("qwen2.5-coder:7b", "qwen2.5-coder"));
+        assert!(same_model("localhost:5000/team/coder:latest", "localhost:5000/team/coder"));
+    }
+
+    #[test]
+    fn test_estimate_pull_size() {
+        let gb = |name: &str| estimate_pull_size(name).map(|bytes| (bytes as f64 / 1e9 * 10.0).round() / 10.0);
+        assert_eq!(gb("llama3.1:8b"), Some(4.9));
+        assert_eq!(gb("qwen2.5:0.5b"), Some(0.3));
+        assert_eq!(gb("llama3.1:8b-instruct-q8_0"), Some(8.6));
+        assert_eq!(gb("mixtral:8x7b"), Some(34.2));
+        assert_eq!(gb("llama3.2"), None);
+        assert_eq!(gb("llama3.2:latest"), None);
+    }
+
     #[test]
     fn test_chat_options_default() {
         let opts = ChatOptions::default();
@@ -1107,6 +1601,77 @@ mod tests {
         assert!(opts.stop.is_none());
     }
 
+    #[test]
+    fn test_generate_request_serialization() {
+        let req = GenerateRequest {
+            model: "qwen2.5-coder".to_string(),
+            prompt: "fn add(a: i32, b: i32) -> i32 {".to_string(),
+            stream: false,
+            suffix: Some("}".to_string()),
+            options: None,
+            keep_alive: None,
+        };
+        let json = serde_json::to_value(&req).unwrap();
+        assert_eq!(json["suffix"], "}");
+        assert!(json.get("options").is_none());
+        assert!(json.get("keep_alive").is_none());
+    }
+
+    #[test]
+    fn test_validate_options() {
+        let options = ChatOptions {
+            top_k: Some(20),
+            min_p: Some(0.05),
+            seed: Some(7),
+            presence_penalty: Some(-0.5),
+            ..Default::default()
+        };
+        assert!(options.validate().is_ok());
+        let json = serde_json::to_value(&options).unwrap();
+        assert_eq!(json["min_p"], 0.05f32 as f64);
+        assert_eq!(json["seed"], 7);
+        assert!(json.get("frequency_penalty").is_none());
+
+        let bad = ChatOptions {
+            top_p: Some(1.5),
+            ..Default::default()
+        };
+        let err = bad.validate().unwrap_err();
+        assert_eq!(err.to_string(), "Invalid top_p: 1.5 is outside 0 to 1");
+        assert!(err.hint().is_some());
+
+        let empty_stop = ChatOptions {
+            stop: Some(vec![String::new()]),
+            ..Default::default()
+        };
+        assert!(empty_stop.validate().is_err());
+    }
+
+    #[test]
+    fn test_chat_request_hoists_keep_alive() {
+        let options = ChatOptions {
+            temperature: Some(0.2),
+            timeout: Some(Duration::from_secs(900)),
+            keep_alive: Some("30m".to_string()),
+            ..Default::default()
+        };
+        let req = ChatRequest {
+            model: "llama3.2".to_string(),
+            messages: vec![ChatMessage::user("hi")],
+            stream: true,
+            keep_alive: ChatOptions::keep_alive_of(Some(&options)),
+            options: Some(options.clone()),
+        };
+        let json = serde_json::to_value(&req).unwrap();
+        assert_eq!(json["keep_alive"], "30m");
+        assert!(json["options"].get("keep_alive").is_none());
+        assert!(json["options"].get("timeout").is_none());
+
+        let default = Duration::from_secs(300);
+        assert_eq!(ChatOptions::timeout_or(Some(&options), default), Duration::from_secs(900));
+        assert_eq!(ChatOptions::timeout_or(None, default), default);
+    }
+
     #[test]
     fn test_ollama_client_new() {
         let client = OllamaClient::new("http://localhost:11434");
@@ -1246,6 +1811,36 @@ mod tests {
         assert!(msg.tool_calls.is_empty());
     }
 
+    #[test]
+    fn test_keep_alive_duration() {
+        assert_eq!(keep_alive_duration("-1"), "-1s");
+        assert_eq!(keep_alive_duration("3600"), "3600s");
+        assert_eq!(keep_alive_duration(" 2h "), "2h");
+    }
+
+    #[test]
+    fn test_model_info() {
+        let info: ModelInfo = serde_json::from_str(
+            r#"{
+                "parameters": "stop \"<|eot_id|>\"\nnum_ctx                        16384",
+                "license": "LLAMA 3.2 COMMUNITY LICENSE AGREEMENT",
+                "details": {"family": "llama", "parameter_size": "8.0B"},
+                "model_info": {"general.architecture": "llama", "general.parameter_count": 8030261248,
+                               "llama.context_length": 131072}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(info.context_length(), Some(131072));
+        assert_eq!(info.num_ctx(), Some(16384));
+        assert_eq!(info.parameter_count(), Some(8030261248));
+        assert_eq!(info.details.family.as_deref(), Some("llama"));
+        assert!(info.license.starts_with("LLAMA"));
+        assert!(info.template.is_empty());
+
+        let bare: ModelInfo = serde_json::from_str("{}").unwrap();
+        assert_eq!((bare.context_length(), bare.num_ctx()), (None, None));
+    }
+
     #[test]
     fn test_retry_config_default() {
         let config = RetryConfig::default();
@@ -1,9 +1,16 @@
-//! Process management for Ollama
+//! Process management for Ollama and background jobs
 
 use anyhow::{Context, Result};
-use std::path::Path;
-use std::process::{Child, Command, Stdio};
-use std::time::Duration;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 
 /// Manages the Ollama process lifecycle
 pub struct OllamaProcess {
@@ -56,29 +63,7 @@ impl OllamaProcess {
     /// Stop the Ollama server
     pub fn stop(&mut self) -> Result<()> {
         if let Some(mut child) = self.child.take() {
-            // Try graceful shutdown first
-            #[cfg(unix)]
-            unsafe {
-                libc::kill(child.id() as i32, libc::SIGTERM);
-            }
-
-            // Wait for graceful shutdown
-            let timeout = Duration::from_secs(5);
-            let start = std::time::Instant::now();
-
-            loop {
-                match child.try_wait() {
-                    Ok(Some(_)) => return Ok(()),
-                    Ok(None) if start.elapsed() < timeout => {
-                        std::thread::sleep(Duration::from_millis(100));
-                    }
-                    _ => break,
-                }
-            }
-
-            // Force kill if still running
-            let _ = child.kill();
-            let _ = child.wait();
+            terminate(&mut child, Duration::from_secs(5));
         }
 
         Ok(())
@@ -216,6 +201,64 @@ pub fn get_memory_info() -> Result<MemoryInfo> {
     anyhow::bail!("get_memory_info not implemented for this platform")
 }
 
+/// Bytes of system memory in use (excluding reclaimable caches)
+#[cfg(target_os = "macos")]
+pub fn memory_used_bytes() -> Result<u64> {
+    let output = Command::new("vm_stat").output().context("Failed to run vm_stat")?;
+    parse_vm_stat(&String::from_utf8_lossy(&output.stdout)).context("Failed to parse vm_stat output")
+}
+
+/// Bytes of system memory in use (excluding reclaimable caches)
+#[cfg(target_os = "linux")]
+pub fn memory_used_bytes() -> Result<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").context("Failed to read /proc/meminfo")?;
+    parse_meminfo(&meminfo).context("Failed to parse /proc/meminfo")
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+pub fn memory_used_bytes() -> Result<u64> {
+    anyhow::bail!("memory_used_bytes not implemented for this platform")
+}
+
+/// Active + wired + compressed pages from `vm_stat` output, in bytes
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn parse_vm_stat(output: &str) -> Option<u64> {
+    let page_size: u64 = output
+        .lines()
+        .next()?
+        .split("page size of ")
+        .nth(1)?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()?;
+    let pages = |label: &str| -> u64 {
+        output
+            .lines()
+            .find(|line| line.starts_with(label))
+            .and_then(|line| line.rsplit(':').next())
+            .and_then(|n| n.trim().trim_end_matches('.').parse().ok())
+            .unwrap_or(0)
+    };
+    let used = pages("Pages active") + pages("Pages wired down") + pages("Pages occupied by compressor");
+    Some(used * page_size)
+}
+
+/// MemTotal - MemAvailable from /proc/meminfo, in bytes
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_meminfo(meminfo: &str) -> Option<u64> {
+    let kb = |label: &str| -> Option<u64> {
+        meminfo
+            .lines()
+            .find(|line| line.starts_with(label))?
+            .split_whitespace()
+            .nth(1)?
+            .parse()
+            .ok()
+    };
+    Some(kb("MemTotal:")?.saturating_sub(kb("MemAvailable:")?) * 1024)
+}
+
 #[derive(Debug, Clone)]
 pub struct MemoryInfo {
     pub total_gb: u64,
@@ -230,3 +273,820 @@ pub enum MemoryPressure {
     Critical,
     Unknown,
 }
+
+/// Lines of output kept for each background job
+pub const JOB_OUTPUT_LINES: usize = 1000;
+
+/// How long a killed job gets to exit before its process group is sent SIGKILL
+const JOB_KILL_GRACE: Duration = Duration::from_secs(3);
+
+/// State of a background job
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobStatus {
+    Running,
+    /// Exited on its own, with its exit code (None if a signal ended it)
+    Exited(Option<i32>),
+    /// Stopped with [`BackgroundJobs::kill`]
+    Killed,
+}
+
+impl fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JobStatus::Running => write!(f, "running"),
+            JobStatus::Exited(Some(code)) => write!(f, "exited (code {})", code),
+            JobStatus::Exited(None) => write!(f, "exited (signal)"),
+            JobStatus::Killed => write!(f, "killed"),
+        }
+    }
+}
+
+/// Snapshot of a background job
+#[derive(Debug, Clone)]
+pub struct JobInfo {
+    pub id: u32,
+    /// PID of the job's process group leader
+    pub pid: Option<u32>,
+    pub command: String,
+    pub working_dir: PathBuf,
+    pub started_at: Instant,
+    pub status: JobStatus,
+    /// Lines of output written so far
+    pub lines: u64,
+}
+
+/// Output lines read from a background job
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobOutput {
+    pub lines: Vec<String>,
+    /// Lines after the cursor that were skipped (dropped from the buffer or
+    /// over the limit)
+    pub skipped: u64,
+    /// Cursor to pass as `since` to read only newer lines
+    pub next: u64,
+}
+
+/// The most recent lines of a job's stdout and stderr, in arrival order
+#[derive(Debug, Default)]
+struct OutputBuffer {
+    lines: VecDeque<String>,
+    /// Lines written since the job started
+    total: u64,
+}
+
+impl OutputBuffer {
+    fn push(&mut self, line: String) {
+        if self.lines.len() == JOB_OUTPUT_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+        self.total += 1;
+    }
+
+    /// The last `limit` lines written after cursor `since`
+    fn read(&self, since: u64, limit: usize) -> JobOutput {
+        let first = self.total - self.lines.len() as u64;
+        let mut skip = (since.clamp(first, self.total) - first) as usize;
+        skip += (self.lines.len() - skip).saturating_sub(limit);
+
+        JobOutput {
+            lines: self.lines.iter().skip(skip).cloned().collect(),
+            skipped: (first + skip as u64).saturating_sub(since),
+            next: self.total,
+        }
+    }
+}
+
+struct Job {
+    /// Kept apart from the child, whose id is gone once it has been reaped
+    pid: Option<u32>,
+    command: String,
+    working_dir: PathBuf,
+    started_at: Instant,
+    status: JobStatus,
+    child: tokio::process::Child,
+    output: Arc<Mutex<OutputBuffer>>,
+}
+
+impl Job {
+    /// Pick up the exit status of a job that has finished
+    fn refresh(&mut self) {
+        if self.status == JobStatus::Running {
+            if let Ok(Some(status)) = self.child.try_wait() {
+                self.status = JobStatus::Exited(status.code());
+            }
+        }
+    }
+
+    fn info(&self, id: u32) -> JobInfo {
+        JobInfo {
+            id,
+            pid: self.pid,
+            command: self.command.clone(),
+            working_dir: self.working_dir.clone(),
+            started_at: self.started_at,
+            status: self.status,
+            lines: lock(&self.output).total,
+        }
+    }
+}
+
+/// Long-running commands (dev servers, watchers) started in the background
+///
+/// Each job runs with `bash -c` in its own process group, so killing it also
+/// stops whatever it spawned. Output is kept in a ring buffer of
+/// [`JOB_OUTPUT_LINES`] lines. Jobs still running when the registry is
+/// dropped are terminated.
+#[derive(Default)]
+pub struct BackgroundJobs {
+    jobs: Mutex<BTreeMap<u32, Job>>,
+    next_id: AtomicU32,
+}
+
+impl BackgroundJobs {
+    /// Create an empty job registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a job
+    ///
+    /// Must be called from within a tokio runtime, which reads the job's
+    /// output.
+    pub fn start(&self, command: &str, working_dir: &Path) -> Result<JobInfo> {
+        let mut cmd = tokio::process::Command::new("bash");
+        cmd.arg("-c")
+            .arg(command)
+            .current_dir(working_dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        #[cfg(unix)]
+        cmd.process_group(0);
+
+        let mut child = cmd
+            .spawn()
+            .with_context(|| format!("Failed to start background job: {}", command))?;
+
+        let output = Arc::new(Mutex::new(OutputBuffer::default()));
+        if let Some(stdout) = child.stdout.take() {
+            tokio::spawn(read_output(stdout, output.clone()));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            tokio::spawn(read_output(stderr, output.clone()));
+        }
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let job = Job {
+            pid: child.id(),
+            command: command.to_string(),
+            working_dir: working_dir.to_path_buf(),
+            started_at: Instant::now(),
+            status: JobStatus::Running,
+            child,
+            output,
+        };
+        let info = job.info(id);
+        lock(&self.jobs).insert(id, job);
+        Ok(info)
+    }
+
+    /// All jobs, oldest first
+    pub fn list(&self) -> Vec<JobInfo> {
+        lock(&self.jobs)
+            .iter_mut()
+            .map(|(id, job)| {
+                job.refresh();
+                job.info(*id)
+            })
+            .collect()
+    }
+
+    /// Look up a job
+    pub fn get(&self, id: u32) -> Option<JobInfo> {
+        lock(&self.jobs).get_mut(&id).map(|job| {
+            job.refresh();
+            job.info(id)
+        })
+    }
+
+    /// The last `limit` output lines after cursor `since` (0 for all kept
+    /// output)
+    pub fn output(&self, id: u32, since: u64, limit: usize) -> Result<(JobInfo, JobOutput)> {
+        let mut jobs = lock(&self.jobs);
+        let job = jobs
+            .get_mut(&id)
+            .ok_or_else(|| anyhow::anyhow!("No background job {}", id))?;
+        job.refresh();
+        let output = lock(&job.output).read(since, limit);
+        Ok((job.info(id), output))
+    }
+
+    /// Stop a job and everything it started
+    ///
+    /// Sends SIGTERM to the job's process group, then SIGKILL if it hasn't
+    /// exited after a few seconds.
+    pub async fn kill(&self, id: u32) -> Result<JobInfo> {
+        let pid = {
+            let mut jobs = lock(&self.jobs);
+            let job = jobs
+                .get_mut(&id)
+                .ok_or_else(|| anyhow::anyhow!("No background job {}", id))?;
+            job.refresh();
+            if job.status != JobStatus::Running {
+                anyhow::bail!("Background job {} is not running ({})", id, job.status);
+            }
+            job.pid
+        };
+
+        if let Some(pid) = pid {
+            signal_group(pid, false);
+            let deadline = Instant::now() + JOB_KILL_GRACE;
+            loop {
+                let exited = lock(&self.jobs)
+                    .get_mut(&id)
+                    .is_none_or(|job| matches!(job.child.try_wait(), Ok(Some(_))));
+                if exited {
+                    break;
+                }
+                if Instant::now() >= deadline {
+                    signal_group(pid, true);
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        }
+
+        let mut jobs = lock(&self.jobs);
+        let job = jobs
+            .get_mut(&id)
+            .ok_or_else(|| anyhow::anyhow!("No background job {}", id))?;
+        job.status = JobStatus::Killed;
+        Ok(job.info(id))
+    }
+
+    /// Terminate every running job
+    pub fn kill_all(&self) {
+        for (_, job) in lock(&self.jobs).iter_mut() {
+            job.refresh();
+            if job.status != JobStatus::Running {
+                continue;
+            }
+            if let Some(pid) = job.pid {
+                signal_group(pid, false);
+            }
+            job.status = JobStatus::Killed;
+        }
+    }
+}
+
+impl Drop for BackgroundJobs {
+    fn drop(&mut self) {
+        self.kill_all();
+    }
+}
+
+/// Copy a job's output into its buffer, line by line
+async fn read_output(reader: impl AsyncRead + Unpin, output: Arc<Mutex<OutputBuffer>>) {
+    let mut reader = BufReader::new(reader);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                let text = String::from_utf8_lossy(&line);
+                lock(&output).push(text.trim_end_matches(['\n', '\r']).to_string());
+            }
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Send SIGTERM (or SIGKILL when `force`) to a process group
+#[cfg(unix)]
+fn signal_group(pgid: u32, force: bool) {
+    let signal = if force { libc::SIGKILL } else { libc::SIGTERM };
+    unsafe {
+        libc::kill(-(pgid as i32), signal);
+    }
+}
+
+#[cfg(not(unix))]
+fn signal_group(_pgid: u32, _force: bool) {}
+
+/// Ask a process to exit (SIGTERM), then kill it if it hasn't after `grace`
+fn terminate(child: &mut Child, grace: Duration) {
+    #[cfg(unix)]
+    unsafe {
+        libc::kill(child.id() as i32, libc::SIGTERM);
+    }
+
+    let start = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => return,
+            Ok(None) if start.elapsed() < grace => std::thread::sleep(Duration::from_millis(100)),
+            _ => break,
+        }
+    }
+
+    // Force kill if still running
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Log size at which the captured Ollama output is rotated
+pub const SERVE_LOG_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Rotated logs kept next to the current one (`ollama.log.1` is the newest)
+const SERVE_LOG_KEEP: usize = 3;
+
+/// How long a supervised Ollama gets to exit after SIGTERM before it is killed
+const SERVE_STOP_GRACE: Duration = Duration::from_secs(10);
+
+/// How often the supervisor checks on Ollama
+const SUPERVISOR_POLL: Duration = Duration::from_millis(200);
+
+/// Set by the supervisor's SIGTERM/SIGINT handler
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Where a supervised Ollama keeps its PID files and captured output
+#[derive(Debug, Clone)]
+pub struct ServeFiles {
+    pub dir: PathBuf,
+}
+
+impl ServeFiles {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// `quant/serve` under the local data directory
+    pub fn default_location() -> Result<Self> {
+        let dir = dirs::data_local_dir().context("No local data directory")?;
+        Ok(Self::new(dir.join("quant").join("serve")))
+    }
+
+    pub fn supervisor_pid_file(&self) -> PathBuf {
+        self.dir.join("supervisor.pid")
+    }
+
+    pub fn ollama_pid_file(&self) -> PathBuf {
+        self.dir.join("ollama.pid")
+    }
+
+    pub fn log_file(&self) -> PathBuf {
+        self.dir.join("ollama.log")
+    }
+
+    /// PID of the running supervisor (a stale PID file is removed)
+    pub fn supervisor_pid(&self) -> Option<u32> {
+        live_pid(&self.supervisor_pid_file())
+    }
+
+    /// PID of the supervised Ollama (a stale PID file is removed)
+    pub fn ollama_pid(&self) -> Option<u32> {
+        live_pid(&self.ollama_pid_file())
+    }
+}
+
+fn live_pid(path: &Path) -> Option<u32> {
+    let pid: u32 = fs::read_to_string(path).ok()?.trim().parse().ok()?;
+    if process_alive(pid) {
+        Some(pid)
+    } else {
+        let _ = fs::remove_file(path);
+        None
+    }
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    // Signal 0 only checks; EPERM means it exists but belongs to someone else
+    let alive = unsafe { libc::kill(pid as i32, 0) == 0 };
+    alive || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+fn process_alive(_pid: u32) -> bool {
+    false
+}
+
+/// A log file that moves to `<name>.1`, `<name>.2`, ... when it grows past
+/// a size limit, dropping the oldest
+pub struct RotatingLog {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: File,
+    size: u64,
+}
+
+impl RotatingLog {
+    /// Open `path` for appending, keeping `keep` rotated files (at least one)
+    pub fn open(path: &Path, max_bytes: u64, keep: usize) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = Self::open_file(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_bytes,
+            keep: keep.max(1),
+            file,
+            size,
+        })
+    }
+
+    fn open_file(path: &Path) -> Result<File> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))
+    }
+
+    pub fn write_line(&mut self, line: &str) -> Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.size > 0 && self.size + len > self.max_bytes {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.size += len;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> Result<()> {
+        for n in (1..self.keep).rev() {
+            let from = rotated_path(&self.path, n);
+            if from.exists() {
+                fs::rename(&from, rotated_path(&self.path, n + 1))?;
+            }
+        }
+        fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        self.file = Self::open_file(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+/// The last `n` lines of a file
+pub fn tail_lines(path: &Path, n: usize) -> Result<Vec<String>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut lines = VecDeque::with_capacity(n);
+    for line in std::io::BufReader::new(file).lines() {
+        if lines.len() == n {
+            lines.pop_front();
+        }
+        if n > 0 {
+            lines.push_back(line?);
+        }
+    }
+    Ok(lines.into())
+}
+
+/// When to restart a supervised Ollama that exited on its own
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+    /// Restarts allowed within `window` before giving up (0 never restarts)
+    pub max_restarts: u32,
+    pub window: Duration,
+    /// Delay before a restart, doubled for each earlier crash in the window
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: 5,
+            window: Duration::from_secs(60),
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// Record a crash at `now`; the delay before restarting, or `None` to
+    /// give up. `crashes` holds earlier crash times, oldest first.
+    fn on_crash(&self, crashes: &mut VecDeque<Instant>, now: Instant) -> Option<Duration> {
+        while crashes
+            .front()
+            .is_some_and(|t| now.duration_since(*t) > self.window)
+        {
+            crashes.pop_front();
+        }
+        crashes.push_back(now);
+
+        let recent = crashes.len() as u32;
+        if recent > self.max_restarts {
+            return None;
+        }
+        let factor = 2u32.saturating_pow(recent - 1);
+        Some(self.backoff.saturating_mul(factor).min(self.max_backoff))
+    }
+}
+
+/// Runs `ollama serve`, captures its output and restarts it when it crashes
+///
+/// Meant to run in its own detached process (`quant serve start` spawns
+/// one). The supervisor and Ollama PIDs go to [`ServeFiles`], stdout and
+/// stderr to a [`RotatingLog`]. SIGTERM or SIGINT stops Ollama gracefully
+/// and removes the PID files.
+pub struct Supervisor {
+    files: ServeFiles,
+    /// Environment for `ollama serve`, see [`crate::Config::ollama_env`]
+    env: Vec<(String, String)>,
+    policy: RestartPolicy,
+}
+
+impl Supervisor {
+    pub fn new(files: ServeFiles, env: Vec<(String, String)>) -> Self {
+        Self {
+            files,
+            env,
+            policy: RestartPolicy::default(),
+        }
+    }
+
+    pub fn with_policy(mut self, policy: RestartPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Supervise until stopped or the restart policy gives up (blocking)
+    pub fn run(&self) -> Result<()> {
+        if let Some(pid) = self.files.supervisor_pid() {
+            anyhow::bail!("Ollama is already supervised (PID {})", pid);
+        }
+        fs::create_dir_all(&self.files.dir)
+            .with_context(|| format!("Failed to create {}", self.files.dir.display()))?;
+        fs::write(self.files.supervisor_pid_file(), std::process::id().to_string())?;
+        install_stop_handler();
+
+        let result = RotatingLog::open(&self.files.log_file(), SERVE_LOG_MAX_BYTES, SERVE_LOG_KEEP)
+            .and_then(|log| self.supervise(Arc::new(Mutex::new(log))));
+
+        let _ = fs::remove_file(self.files.ollama_pid_file());
+        let _ = fs::remove_file(self.files.supervisor_pid_file());
+        result
+    }
+
+    fn supervise(&self, log: Arc<Mutex<RotatingLog>>) -> Result<()> {
+        let binary = find_ollama_binary()?;
+        let mut crashes = VecDeque::new();
+
+        loop {
+            let mut child = Command::new(&binary)
+                .arg("serve")
+                .envs(self.env.iter().map(|(key, value)| (key, value)))
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .context("Failed to start Ollama")?;
+            fs::write(self.files.ollama_pid_file(), child.id().to_string())?;
+            note(&log, &format!("started ollama serve (PID {})", child.id()));
+
+            // Not joined: runners Ollama spawns can keep the pipes open
+            if let Some(stdout) = child.stdout.take() {
+                copy_lines(stdout, log.clone());
+            }
+            if let Some(stderr) = child.stderr.take() {
+                copy_lines(stderr, log.clone());
+            }
+
+            let Some(status) = self.wait(&mut child) else {
+                note(&log, "stopped ollama serve");
+                return Ok(());
+            };
+
+            match self.policy.on_crash(&mut crashes, Instant::now()) {
+                Some(delay) => {
+                    note(
+                        &log,
+                        &format!("ollama serve exited ({}), restarting in {:.0?}", status, delay),
+                    );
+                    if sleep_unless_stopped(delay) {
+                        return Ok(());
+                    }
+                }
+                None => {
+                    let message = format!(
+                        "ollama serve exited ({}) {} times within {:?}, giving up",
+                        status,
+                        crashes.len(),
+                        self.policy.window
+                    );
+                    note(&log, &message);
+                    anyhow::bail!(message);
+                }
+            }
+        }
+    }
+
+    /// Wait for Ollama to exit; `None` if a stop was requested and it was
+    /// shut down
+    fn wait(&self, child: &mut Child) -> Option<ExitStatus> {
+        loop {
+            if STOP_REQUESTED.load(Ordering::SeqCst) {
+                terminate(child, SERVE_STOP_GRACE);
+                return None;
+            }
+            match child.try_wait() {
+                Ok(Some(status)) => return Some(status),
+                Ok(None) => std::thread::sleep(SUPERVISOR_POLL),
+                Err(_) => {
+                    terminate(child, SERVE_STOP_GRACE);
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// Ask the running supervisor to stop Ollama and exit, waiting up to
+/// `timeout`; returns its PID, or `None` if none was running
+pub fn stop_supervisor(files: &ServeFiles, timeout: Duration) -> Result<Option<u32>> {
+    let Some(pid) = files.supervisor_pid() else {
+        return Ok(None);
+    };
+
+    #[cfg(unix)]
+    unsafe {
+        libc::kill(pid as i32, libc::SIGTERM);
+    }
+
+    let deadline = Instant::now() + timeout;
+    while process_alive(pid) {
+        if Instant::now() >= deadline {
+            anyhow::bail!("Supervisor (PID {}) did not stop within {:?}", pid, timeout);
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    Ok(Some(pid))
+}
+
+/// A supervisor line in the log, set apart from Ollama's own output
+fn note(log: &Mutex<RotatingLog>, message: &str) {
+    tracing::info!("{}", message);
+    let _ = lock(log).write_line(&format!("[supervisor] {}", message));
+}
+
+/// Copy lines from a pipe into the log on a background thread
+fn copy_lines(reader: impl std::io::Read + Send + 'static, log: Arc<Mutex<RotatingLog>>) {
+    std::thread::spawn(move || {
+        let mut reader = std::io::BufReader::new(reader);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    let text = String::from_utf8_lossy(&line);
+                    let _ = lock(&log).write_line(text.trim_end_matches(['\n', '\r']));
+                }
+            }
+        }
+    });
+}
+
+/// Sleep for `duration`; true if a stop was requested meanwhile
+fn sleep_unless_stopped(duration: Duration) -> bool {
+    let deadline = Instant::now() + duration;
+    while Instant::now() < deadline {
+        if STOP_REQUESTED.load(Ordering::SeqCst) {
+            return true;
+        }
+        std::thread::sleep(SUPERVISOR_POLL);
+    }
+    STOP_REQUESTED.load(Ordering::SeqCst)
+}
+
+#[cfg(unix)]
+fn install_stop_handler() {
+    extern "C" fn on_signal(_signal: libc::c_int) {
+        STOP_REQUESTED.store(true, Ordering::SeqCst);
+    }
+    let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    unsafe {
+        libc::signal(libc::SIGTERM, handler);
+        libc::signal(libc::SIGINT, handler);
+    }
+}
+
+#[cfg(not(unix))]
+fn install_stop_handler() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_vm_stat() {
+        let output = "Mach Virtual Memory Statistics: (page size of 16384 bytes)\n\
+Pages free:                               12345.\n\
+Pages active:                            100000.\n\
+Pages inactive:                           99999.\n\
+Pages wired down:                         50000.\n\
+Pages occupied by compressor:             10000.\n";
+        assert_eq!(parse_vm_stat(output), Some(160_000 * 16384));
+        assert_eq!(parse_vm_stat("garbage"), None);
+    }
+
+    #[test]
+    fn test_parse_meminfo() {
+        let meminfo = "MemTotal:       32000000 kB\nMemFree:         1000000 kB\nMemAvailable:   12000000 kB\n";
+        assert_eq!(parse_meminfo(meminfo), Some(20_000_000 * 1024));
+        assert_eq!(parse_meminfo("MemTotal: 1 kB\n"), None);
+    }
+
+    #[test]
+    fn test_rotating_log() {
+        let dir = std::env::temp_dir().join(format!("llm-core-rotating-log-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("ollama.log");
+        let mut log = RotatingLog::open(&path, 10, 2).unwrap();
+        for line in ["first", "second", "third", "fourth"] {
+            log.write_line(line).unwrap();
+        }
+
+        assert_eq!(tail_lines(&path, 5).unwrap(), vec!["fourth"]);
+        assert_eq!(tail_lines(&rotated_path(&path, 1), 5).unwrap(), vec!["third"]);
+        assert_eq!(tail_lines(&rotated_path(&path, 2), 5).unwrap(), vec!["second"]);
+        assert!(!rotated_path(&path, 3).exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_restart_policy() {
+        let policy = RestartPolicy {
+            max_restarts: 2,
+            window: Duration::from_secs(60),
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        };
+        let mut crashes = VecDeque::new();
+        let start = Instant::now();
+
+        assert_eq!(policy.on_crash(&mut crashes, start), Some(Duration::from_secs(1)));
+        assert_eq!(policy.on_crash(&mut crashes, start + Duration::from_secs(5)), Some(Duration::from_secs(2)));
+        assert_eq!(policy.on_crash(&mut crashes, start + Duration::from_secs(10)), None);
+        // Crashes older than the window no longer count
+        assert_eq!(policy.on_crash(&mut crashes, start + Duration::from_secs(200)), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_output_buffer_read() {
+        let mut buffer = OutputBuffer::default();
+        for i in 0..JOB_OUTPUT_LINES + 5 {
+            buffer.push(format!("line {}", i));
+        }
+
+        let output = buffer.read(0, 2);
+        assert_eq!(output.lines, vec!["line 1003", "line 1004"]);
+        assert_eq!(output.skipped, JOB_OUTPUT_LINES as u64 + 3);
+        assert_eq!(output.next, JOB_OUTPUT_LINES as u64 + 5);
+
+        let output = buffer.read(1003, 10);
+        assert_eq!(output.lines.len(), 2);
+        assert_eq!(output.skipped, 0);
+        assert!(buffer.read(output.next, 10).lines.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_background_job_output_and_kill() {
+        let jobs = BackgroundJobs::new();
+        let job = jobs
+            .start("echo ready; echo oops >&2; sleep 30", Path::new("."))
+            .unwrap();
+        assert_eq!(job.id, 1);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while jobs.get(job.id).unwrap().lines < 2 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let (info, output) = jobs.output(job.id, 0, 10).unwrap();
+        assert_eq!(info.status, JobStatus::Running);
+        assert!(output.lines.contains(&"ready".to_string()));
+        assert!(output.lines.contains(&"oops".to_string()));
+
+        let info = jobs.kill(job.id).await.unwrap();
+        assert_eq!(info.status, JobStatus::Killed);
+        assert!(jobs.kill(job.id).await.is_err());
+        assert!(jobs.output(42, 0, 10).is_err());
+    }
+}
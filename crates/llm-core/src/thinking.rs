@@ -0,0 +1,123 @@
+//! Thinking output of reasoning models
+//!
+//! Reasoning models like deepseek-r1 think before they answer. Ollama sends
+//! that thinking in the message's `thinking` field when it separates it, and
+//! otherwise inline in the content between `<think>` and `</think>`.
+//! [`ThinkingSplitter`] separates inline thinking from the answer as chunks
+//! stream in, so callers can show it apart and keep it out of saved messages.
+
+const OPEN_TAG: &str = "<think>";
+const CLOSE_TAG: &str = "</think>";
+
+/// Thinking and answer text from some model output
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Split {
+    pub thinking: String,
+    pub content: String,
+}
+
+/// Splits streamed content at `<think>` tags, including tags cut across chunks
+#[derive(Debug, Default)]
+pub struct ThinkingSplitter {
+    in_thinking: bool,
+    /// Start of a possible tag, held back until the next chunk
+    pending: String,
+    /// Drop whitespace between a closing tag and the answer
+    trim_answer: bool,
+}
+
+impl ThinkingSplitter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the output so far ends inside a thinking block
+    pub fn in_thinking(&self) -> bool {
+        self.in_thinking
+    }
+
+    /// Split the next chunk of content
+    pub fn push(&mut self, text: &str) -> Split {
+        self.pending.push_str(text);
+        let mut split = Split::default();
+        loop {
+            let tag = if self.in_thinking { CLOSE_TAG } else { OPEN_TAG };
+            if let Some(i) = self.pending.find(tag) {
+                let before = self.pending[..i].to_string();
+                self.pending.drain(..i + tag.len());
+                self.emit(&mut split, &before);
+                self.in_thinking = !self.in_thinking;
+                self.trim_answer = !self.in_thinking;
+                continue;
+            }
+            let keep = (1..tag.len())
+                .rev()
+                .find(|&n| self.pending.ends_with(&tag[..n]))
+                .unwrap_or(0);
+            let ready: String = self.pending.drain(..self.pending.len() - keep).collect();
+            self.emit(&mut split, &ready);
+            return split;
+        }
+    }
+
+    /// Text held back at the end of the output
+    pub fn finish(&mut self) -> Split {
+        let mut split = Split::default();
+        let rest = std::mem::take(&mut self.pending);
+        self.emit(&mut split, &rest);
+        split
+    }
+
+    fn emit(&mut self, split: &mut Split, text: &str) {
+        if self.in_thinking {
+            split.thinking.push_str(text);
+            return;
+        }
+        let text = if self.trim_answer { text.trim_start() } else { text };
+        if !text.is_empty() {
+            self.trim_answer = false;
+            split.content.push_str(text);
+        }
+    }
+}
+
+/// Split a complete response into its thinking and its answer
+pub fn split_thinking(text: &str) -> Split {
+    let mut splitter = ThinkingSplitter::new();
+    let mut split = splitter.push(text);
+    let rest = splitter.finish();
+    split.thinking.push_str(&rest.thinking);
+    split.content.push_str(&rest.content);
+    split
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_thinking() {
+        let split = split_thinking("<think>\nThe user wants 2+2.\n</think>\n\n4");
+        assert_eq!(split.thinking, "\nThe user wants 2+2.\n");
+        assert_eq!(split.content, "4");
+
+        let plain = split_thinking("No tags, 1 < 2");
+        assert_eq!(plain.thinking, "");
+        assert_eq!(plain.content, "No tags, 1 < 2");
+    }
+
+    #[test]
+    fn test_tags_across_chunks() {
+        let mut splitter = ThinkingSplitter::new();
+        let mut split = Split::default();
+        for chunk in ["<thi", "nk>step one", " then two</", "think", ">", "\n", "Answer <", "b>"] {
+            let part = splitter.push(chunk);
+            split.thinking.push_str(&part.thinking);
+            split.content.push_str(&part.content);
+        }
+        assert!(!splitter.in_thinking());
+        split.content.push_str(&splitter.finish().content);
+        assert_eq!(split.thinking, "step one then two");
+        assert_eq!(split.content, "Answer <b>");
+    }
+}
@@ -0,0 +1,145 @@
+//! First-token watchdog for streamed chat requests
+//!
+//! A loaded model occasionally gets into a state where requests hang without
+//! producing anything. [`OllamaClient::open_watched`] gives a request
+//! [`Timeouts::first_token`](crate::Timeouts) to send its first chunk; if it
+//! doesn't, the request is cancelled, the model unloaded and loaded again,
+//! and the request sent once more.
+
+use anyhow::Result;
+use futures::stream::{self, Stream, StreamExt};
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::error::LlmError;
+use crate::ollama::OllamaClient;
+
+/// A model the watchdog unloaded and loaded again
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelRestart {
+    pub model: String,
+    /// How long the stalled request went without a first token
+    pub waited: Duration,
+}
+
+type BoxStream<T> = Pin<Box<dyn Stream<Item = T> + Send>>;
+
+impl OllamaClient {
+    /// Open a stream for `model` with `open`, restarting the model and
+    /// retrying once if no chunk arrives within the first-token timeout
+    ///
+    /// Returns the stream, and the restart if there was one. A second stall
+    /// is an [`LlmError::Stalled`].
+    pub async fn open_watched<T, F, Fut>(&self, model: &str, mut open: F) -> Result<(BoxStream<T>, Option<ModelRestart>)>
+    where
+        T: Send + 'static,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<BoxStream<T>>>,
+    {
+        let timeout = self.timeouts().first_token;
+        if timeout.is_zero() {
+            return Ok((open().await?, None));
+        }
+
+        if let Some(stream) = first_chunk(open(), timeout).await? {
+            return Ok((stream, None));
+        }
+        warn!(model, timeout_secs = timeout.as_secs(), "No first token; reloading the model");
+        if let Err(e) = self.unload_model(model).await {
+            warn!(model, error = %e, "Failed to unload stalled model");
+        }
+        if let Err(e) = self.load_model(model).await {
+            warn!(model, error = %e, "Failed to reload stalled model");
+        }
+
+        match first_chunk(open(), timeout).await? {
+            Some(stream) => {
+                info!(model, "Model recovered after reload");
+                let restart = ModelRestart {
+                    model: model.to_string(),
+                    waited: timeout,
+                };
+                Ok((stream, Some(restart)))
+            }
+            None => Err(LlmError::Stalled {
+                model: model.to_string(),
+                seconds: timeout.as_secs(),
+            }
+            .into()),
+        }
+    }
+}
+
+/// Open a stream and wait for its first item (None if none arrives in
+/// time), putting the item back in front of the rest
+async fn first_chunk<T: Send + 'static>(
+    open: impl Future<Output = Result<BoxStream<T>>>,
+    timeout: Duration,
+) -> Result<Option<BoxStream<T>>> {
+    let opened = tokio::time::timeout(timeout, async {
+        let mut stream = open.await?;
+        let first = stream.next().await;
+        anyhow::Ok((first, stream))
+    })
+    .await;
+
+    match opened {
+        Ok(Ok((first, rest))) => Ok(Some(Box::pin(stream::iter(first).chain(rest)))),
+        Ok(Err(e)) => Err(e),
+        Err(_) => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ollama::Timeouts;
+
+    fn client(first_token: Duration) -> OllamaClient {
+        // Nothing listens here, so unload and reload fail fast
+        OllamaClient::with_timeouts(
+            "http://127.0.0.1:9",
+            Timeouts {
+                first_token,
+                ..Timeouts::default()
+            },
+        )
+    }
+
+    fn ready() -> BoxStream<u32> {
+        Box::pin(stream::iter([1, 2]))
+    }
+
+    fn stalled() -> BoxStream<u32> {
+        Box::pin(stream::pending())
+    }
+
+    #[tokio::test]
+    async fn test_open_watched() {
+        let client = client(Duration::from_millis(50));
+
+        let (stream, restart) = client.open_watched("m", || async { Ok(ready()) }).await.unwrap();
+        assert_eq!(stream.collect::<Vec<_>>().await, [1, 2]);
+        assert!(restart.is_none());
+
+        // Stalls once, then answers
+        let mut attempts = 0;
+        let (stream, restart) = client
+            .open_watched("m", || {
+                attempts += 1;
+                let stream = if attempts == 1 { stalled() } else { ready() };
+                async move { Ok(stream) }
+            })
+            .await
+            .unwrap();
+        assert_eq!(stream.collect::<Vec<_>>().await, [1, 2]);
+        assert_eq!(restart.unwrap().model, "m");
+
+        let Err(err) = client.open_watched("m", || async { Ok(stalled()) }).await else {
+            panic!("expected a stall");
+        };
+        assert!(matches!(LlmError::find(&err), Some(LlmError::Stalled { .. })));
+    }
+}
@@ -46,6 +46,24 @@ struct Args {
     #[arg(long, env = "CTX_SIZE")]
     ctx_size: Option<u32>,
 
+    #[arg(long, env = "TOP_K")]
+    top_k: Option<u32>,
+
+    #[arg(long, env = "MIN_P")]
+    min_p: Option<f32>,
+
+    #[arg(long, env = "PRESENCE_PENALTY")]
+    presence_penalty: Option<f32>,
+
+    #[arg(long, env = "FREQUENCY_PENALTY")]
+    frequency_penalty: Option<f32>,
+
+    #[arg(long, env = "SEED")]
+    seed: Option<i64>,
+
+    #[arg(long)]
+    stop: Vec<String>,
+
     #[arg(required = true, num_args = 1..)]
     prompt: Vec<String>,
 }
@@ -53,6 +71,7 @@ struct Args {
 fn main() -> Result<()> {
     let args = Args::parse();
     let prompt = args.prompt.join(" ");
+    validate(&args)?;
 
     match args.runtime {
         Runtime::Llama => run_llama(&args, &prompt),
@@ -60,6 +79,29 @@ fn main() -> Result<()> {
     }
 }
 
+fn validate(args: &Args) -> Result<()> {
+    let ranges = [
+        ("--temp", args.temp, 0.0, 2.0),
+        ("--top-p", args.top_p, 0.0, 1.0),
+        ("--min-p", args.min_p, 0.0, 1.0),
+        ("--repeat-penalty", args.repeat_penalty, 0.0, 10.0),
+        ("--presence-penalty", args.presence_penalty, -2.0, 2.0),
+        ("--frequency-penalty", args.frequency_penalty, -2.0, 2.0),
+    ];
+    for (flag, value, min, max) in ranges {
+        if let Some(v) = value.filter(|v| !(min..=max).contains(v)) {
+            bail!("{flag} {v} is outside {min} to {max}");
+        }
+    }
+    if args.ctx_size == Some(0) {
+        bail!("--ctx-size must be greater than 0");
+    }
+    if args.stop.iter().any(String::is_empty) {
+        bail!("--stop sequences can't be empty");
+    }
+    Ok(())
+}
+
 fn run_llama(args: &Args, prompt: &str) -> Result<()> {
     let model_path = args
         .model
@@ -100,6 +142,24 @@ fn run_llama(args: &Args, prompt: &str) -> Result<()> {
     if let Some(ctx_size) = args.ctx_size {
         cmd.arg("-c").arg(ctx_size.to_string());
     }
+    if let Some(top_k) = args.top_k {
+        cmd.arg("--top-k").arg(top_k.to_string());
+    }
+    if let Some(min_p) = args.min_p {
+        cmd.arg("--min-p").arg(min_p.to_string());
+    }
+    if let Some(presence_penalty) = args.presence_penalty {
+        cmd.arg("--presence-penalty").arg(presence_penalty.to_string());
+    }
+    if let Some(frequency_penalty) = args.frequency_penalty {
+        cmd.arg("--frequency-penalty").arg(frequency_penalty.to_string());
+    }
+    if let Some(seed) = args.seed {
+        cmd.arg("--seed").arg(seed.to_string());
+    }
+    for stop in &args.stop {
+        cmd.arg("-r").arg(stop);
+    }
 
     let status = cmd.status().context("failed to run llama.cpp")?;
     if !status.success() {
@@ -132,6 +192,24 @@ fn run_exo(args: &Args, prompt: &str) -> Result<()> {
     if let Some(max_tokens) = args.max_tokens {
         body["max_tokens"] = json!(max_tokens);
     }
+    if let Some(top_k) = args.top_k {
+        body["top_k"] = json!(top_k);
+    }
+    if let Some(min_p) = args.min_p {
+        body["min_p"] = json!(min_p);
+    }
+    if let Some(presence_penalty) = args.presence_penalty {
+        body["presence_penalty"] = json!(presence_penalty);
+    }
+    if let Some(frequency_penalty) = args.frequency_penalty {
+        body["frequency_penalty"] = json!(frequency_penalty);
+    }
+    if let Some(seed) = args.seed {
+        body["seed"] = json!(seed);
+    }
+    if !args.stop.is_empty() {
+        body["stop"] = json!(args.stop);
+    }
 
     let client = reqwest::blocking::Client::new();
     let resp = client
@@ -1,27 +1,55 @@
-//! OllamaBar - macOS menu bar app for local LLM management
+//! OllamaBar - menu bar / system tray app for local LLM management
 //!
-//! A native menu bar application that provides:
+//! A native tray application for macOS, Linux and Windows that provides:
 //! - One-click Ollama start/stop
 //! - Model switching
 //! - Tailscale network sharing
 //! - Memory monitoring
+//! - Running quant sessions, and model switches they follow (Unix)
+//! - Preferences window and launch at login (macOS)
 
-#![cfg(target_os = "macos")]
+// No console window next to the tray icon on Windows
+#![cfg_attr(target_os = "windows", windows_subsystem = "windows")]
 
+mod platform;
 mod state;
 mod tray;
 
 use anyhow::Result;
-use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+use std::sync::OnceLock;
+use tracing_subscriber::{fmt, prelude::*, reload, EnvFilter, Registry};
+
+/// Handle for changing the log filter from the preferences window
+static LOG_FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+fn log_filter(level: &str) -> EnvFilter {
+    let mut filter = EnvFilter::from_default_env();
+    for target in ["ollama_bar", "llm_core"] {
+        match format!("{}={}", target, level).parse() {
+            Ok(directive) => filter = filter.add_directive(directive),
+            Err(e) => eprintln!("Invalid log level {:?}: {}", level, e),
+        }
+    }
+    filter
+}
+
+/// Switch the log level of OllamaBar and llm-core
+pub fn set_log_level(level: &str) {
+    if let Some(handle) = LOG_FILTER.get() {
+        if let Err(e) = handle.reload(log_filter(level)) {
+            tracing::warn!("Failed to change log level: {}", e);
+        }
+    }
+}
 
 fn main() -> Result<()> {
     // Initialize logging
+    let (filter, handle) = reload::Layer::new(log_filter("debug"));
     tracing_subscriber::registry()
+        .with(filter)
         .with(fmt::layer())
-        .with(EnvFilter::from_default_env()
-            .add_directive("ollama_bar=debug".parse()?)
-            .add_directive("llm_core=debug".parse()?))
         .init();
+    let _ = LOG_FILTER.set(handle);
 
     tracing::info!("Starting OllamaBar");
 
@@ -30,168 +58,29 @@ fn main() -> Result<()> {
 
 fn run_with_tray() -> Result<()> {
     use crate::state::AppState;
-    use crate::tray::TrayManager;
-    use muda::MenuEvent;
-    use objc2::rc::Retained;
-    use objc2::runtime::{AnyObject, ProtocolObject};
-    use objc2::{declare_class, msg_send_id, mutability, sel, ClassType, DeclaredClass};
-    use objc2_app_kit::{NSApplication, NSApplicationActivationPolicy, NSApplicationDelegate};
-    use objc2_foundation::{
-        MainThreadMarker, NSNotification, NSObject, NSObjectProtocol, NSTimer,
-    };
-    use std::cell::RefCell;
     use std::time::Duration;
 
-    // Thread-local storage for tray manager (main thread only)
-    thread_local! {
-        static TRAY_MANAGER: RefCell<Option<TrayManager>> = const { RefCell::new(None) };
-    }
-
     // Shared state for background refresh
     let state = AppState::new()?;
     let state_for_monitor = state.clone();
+    set_log_level(&state.settings().log_level);
 
-    // Minimal app delegate
-    declare_class!(
-        struct TrayAppDelegate;
-
-        unsafe impl ClassType for TrayAppDelegate {
-            type Super = NSObject;
-            type Mutability = mutability::MainThreadOnly;
-            const NAME: &'static str = "OllamaBarTrayAppDelegate";
-        }
-
-        impl DeclaredClass for TrayAppDelegate {
-            type Ivars = ();
-        }
-
-        unsafe impl NSObjectProtocol for TrayAppDelegate {}
-
-        unsafe impl NSApplicationDelegate for TrayAppDelegate {
-            #[method(applicationDidFinishLaunching:)]
-            fn did_finish_launching(&self, _notification: &NSNotification) {
-                tracing::info!("Application did finish launching");
-            }
-
-            #[method(applicationWillTerminate:)]
-            fn will_terminate(&self, _notification: &NSNotification) {
-                tracing::info!("Application will terminate");
-            }
-        }
-    );
-
-    impl TrayAppDelegate {
-        fn new(mtm: MainThreadMarker) -> Retained<Self> {
-            let this = mtm.alloc::<Self>();
-            let this = this.set_ivars(());
-            unsafe { msg_send_id![super(this), init] }
-        }
-    }
-
-    // Timer delegate for processing events on main thread
-    declare_class!(
-        struct TrayTimerDelegate;
-
-        unsafe impl ClassType for TrayTimerDelegate {
-            type Super = NSObject;
-            type Mutability = mutability::MainThreadOnly;
-            const NAME: &'static str = "OllamaBarTrayTimerDelegate";
-        }
-
-        impl DeclaredClass for TrayTimerDelegate {
-            type Ivars = ();
-        }
-
-        unsafe impl NSObjectProtocol for TrayTimerDelegate {}
-
-        unsafe impl TrayTimerDelegate {
-            #[method(timerFired:)]
-            fn timer_fired(&self, _timer: *mut AnyObject) {
-                // Process menu events on main thread
-                let menu_rx = MenuEvent::receiver();
-
-                while let Ok(event) = menu_rx.try_recv() {
-                    tracing::info!("Menu event: {:?}", event.id);
-
-                    TRAY_MANAGER.with(|tm| {
-                        if let Some(manager) = tm.borrow().as_ref() {
-                            let quit = manager.handle_event(&event);
-                            if quit {
-                                unsafe {
-                                    if let Some(mtm) = MainThreadMarker::new() {
-                                        let app = NSApplication::sharedApplication(mtm);
-                                        app.terminate(None);
-                                    }
-                                }
-                            }
-                        }
-                    });
-                }
-
-                // Update menu/icon
-                TRAY_MANAGER.with(|tm| {
-                    if let Some(manager) = tm.borrow_mut().as_mut() {
-                        let _ = manager.update_menu();
-                        manager.update_icon();
-                    }
-                });
-            }
-        }
-    );
-
-    impl TrayTimerDelegate {
-        fn new(mtm: MainThreadMarker) -> Retained<Self> {
-            let this = mtm.alloc::<Self>();
-            let this = this.set_ivars(());
-            unsafe { msg_send_id![super(this), init] }
-        }
-    }
-
-    // Main thread setup
-    let mtm = MainThreadMarker::new().expect("Must run on main thread");
-    let app = NSApplication::sharedApplication(mtm);
-    app.setActivationPolicy(NSApplicationActivationPolicy::Accessory);
-
-    // Create tray manager and store in thread-local
-    let manager = TrayManager::new(state)?;
-    TRAY_MANAGER.with(|tm| {
-        *tm.borrow_mut() = Some(manager);
+    // Preload the [warmup] models if Ollama is already running
+    let state_for_warmup = state.clone();
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        rt.block_on(state_for_warmup.warm_pool());
     });
 
-    // Create tray icon
-    TRAY_MANAGER.with(|tm| {
-        if let Some(manager) = tm.borrow_mut().as_mut() {
-            if let Err(e) = manager.create_tray() {
-                tracing::error!("Failed to create tray: {}", e);
-            }
+    // Accept quant sessions on the local socket
+    let state_for_sessions = state.clone();
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        if let Err(e) = rt.block_on(state_for_sessions.serve_sessions()) {
+            tracing::warn!("quant session sync unavailable: {}", e);
         }
     });
 
-    // Set up app delegate
-    let delegate = TrayAppDelegate::new(mtm);
-    app.setDelegate(Some(ProtocolObject::from_ref(&*delegate)));
-
-    // Set up timer for event processing (runs on main thread)
-    let timer_delegate = TrayTimerDelegate::new(mtm);
-    unsafe {
-        let _timer: Retained<NSTimer> = msg_send_id![
-            NSTimer::class(),
-            scheduledTimerWithTimeInterval: 1.0f64,
-            target: &*timer_delegate,
-            selector: sel!(timerFired:),
-            userInfo: std::ptr::null::<AnyObject>(),
-            repeats: true
-        ];
-    }
-
-    // Keep timer delegate alive
-    thread_local! {
-        static TIMER_DELEGATE: RefCell<Option<Retained<TrayTimerDelegate>>> = const { RefCell::new(None) };
-    }
-    TIMER_DELEGATE.with(|td| *td.borrow_mut() = Some(timer_delegate));
-
-    tracing::info!("OllamaBar running - check your menu bar");
-
     // Start background monitoring thread (only refreshes AppState, doesn't touch TrayManager)
     std::thread::spawn(move || {
         let rt = tokio::runtime::Runtime::new().unwrap();
@@ -199,12 +88,11 @@ fn run_with_tray() -> Result<()> {
             rt.block_on(async {
                 let _ = state_for_monitor.refresh().await;
             });
-            std::thread::sleep(Duration::from_secs(5));
+            let refresh_secs = state_for_monitor.settings().refresh_secs.max(1);
+            std::thread::sleep(Duration::from_secs(refresh_secs));
         }
     });
 
-    // Run the app (blocks)
-    unsafe { app.run() };
-
-    Ok(())
+    // Tray and event loop for this platform (blocks until Quit)
+    platform::run(state)
 }
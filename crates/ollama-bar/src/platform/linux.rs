@@ -0,0 +1,123 @@
+//! Linux backend: GTK main loop, tray via StatusNotifier/appindicator
+//!
+//! Needs libayatana-appindicator (or libappindicator) and GTK 3 at runtime.
+//! Dialogs, notifications and the clipboard go through the usual desktop
+//! tools (zenity or kdialog, notify-send, wl-copy or xclip or xsel).
+
+use crate::state::AppState;
+use crate::tray::TrayManager;
+use anyhow::{bail, Context, Result};
+use gtk::glib;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+/// Create the tray and run the GTK main loop (blocks)
+pub fn run(state: AppState) -> Result<()> {
+    gtk::init().context("Failed to initialize GTK")?;
+
+    let mut manager = TrayManager::new(state)?;
+    manager.create_tray()?;
+
+    glib::timeout_add_local(Duration::from_secs(1), move || {
+        if manager.tick() {
+            gtk::main_quit();
+            glib::ControlFlow::Break
+        } else {
+            glib::ControlFlow::Continue
+        }
+    });
+
+    tracing::info!("OllamaBar running - check your system tray");
+    gtk::main();
+    Ok(())
+}
+
+pub fn notify(title: &str, body: &str) {
+    let shown = Command::new("notify-send")
+        .args(["--app-name=OllamaBar", title, body])
+        .spawn();
+    if shown.is_err() {
+        tracing::info!("Notification (notify-send not found): {}: {}", title, body);
+    }
+}
+
+/// Copy with whichever clipboard tool is installed
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    let tools: [(&str, &[&str]); 3] = [
+        ("wl-copy", &[]),
+        ("xclip", &["-selection", "clipboard"]),
+        ("xsel", &["--clipboard", "--input"]),
+    ];
+    for (program, args) in tools {
+        if super::pipe_to(program, args, text).is_ok() {
+            return Ok(());
+        }
+    }
+    bail!("No clipboard tool found (install wl-clipboard, xclip or xsel)")
+}
+
+/// Ask for a model name with zenity, falling back to kdialog
+pub fn prompt_model_name() -> Option<String> {
+    let text = "Enter model name to pull (e.g., llama3.2, gemma2:2b):";
+    if which("zenity") {
+        let text = format!("--text={}", text);
+        return super::dialog_output(Command::new("zenity").args(["--entry", "--title=Pull Model", text.as_str()]));
+    }
+    if which("kdialog") {
+        return super::dialog_output(Command::new("kdialog").args(["--title", "Pull Model", "--inputbox", text]));
+    }
+
+    tracing::error!("Install zenity or kdialog to pull models from the tray");
+    None
+}
+
+pub fn open_logs(log_path: &Path) {
+    if log_path.exists() {
+        open_path(log_path);
+    } else {
+        tracing::warn!("Log file doesn't exist yet: {:?}", log_path);
+    }
+}
+
+pub fn open_path(path: &Path) {
+    let _ = Command::new("xdg-open").arg(path).spawn();
+}
+
+/// Run `quant <args>` in a new terminal window, in `dir`
+pub fn open_terminal(dir: &Path, args: &[String]) -> Result<()> {
+    let terminals: [(&str, &[&str]); 4] = [
+        ("x-terminal-emulator", &["-e"]),
+        ("gnome-terminal", &["--"]),
+        ("konsole", &["-e"]),
+        ("xterm", &["-e"]),
+    ];
+    for (program, flags) in terminals {
+        if which(program) {
+            Command::new(program)
+                .args(flags)
+                .arg("quant")
+                .args(args)
+                .current_dir(dir)
+                .spawn()
+                .with_context(|| format!("Failed to run {}", program))?;
+            return Ok(());
+        }
+    }
+    bail!("No terminal emulator found (install gnome-terminal, konsole or xterm)")
+}
+
+/// No native preferences window here: open the config file instead
+pub fn show_preferences(_state: AppState) {
+    match super::settings_path() {
+        Some(path) => open_path(&path),
+        None => tracing::warn!("No config file to open"),
+    }
+}
+
+fn which(program: &str) -> bool {
+    Command::new("which")
+        .arg(program)
+        .output()
+        .is_ok_and(|out| out.status.success())
+}
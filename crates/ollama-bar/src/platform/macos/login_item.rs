@@ -0,0 +1,39 @@
+//! Launch at login through SMAppService (macOS 13+)
+//!
+//! objc2 has no ServiceManagement bindings at our version, so the class is
+//! looked up at runtime. Registration only works for the app bundle.
+
+use anyhow::{bail, Result};
+use objc2::msg_send;
+use objc2::runtime::{AnyClass, AnyObject};
+
+#[link(name = "ServiceManagement", kind = "framework")]
+extern "C" {}
+
+/// Register or unregister the app as a login item
+pub fn set_enabled(enabled: bool) -> Result<()> {
+    let Some(class) = AnyClass::get("SMAppService") else {
+        bail!("Launch at login needs macOS 13 or later");
+    };
+
+    let registered: bool = unsafe {
+        let service: *mut AnyObject = msg_send![class, mainAppService];
+        if service.is_null() {
+            bail!("No login item service for this app");
+        }
+        // Errors are summarized below rather than read from NSError
+        let error: *mut *mut AnyObject = std::ptr::null_mut();
+        if enabled {
+            msg_send![service, registerAndReturnError: error]
+        } else {
+            msg_send![service, unregisterAndReturnError: error]
+        }
+    };
+
+    if !registered {
+        let action = if enabled { "add" } else { "remove" };
+        bail!("Could not {} the login item (run OllamaBar from its app bundle)", action);
+    }
+    tracing::info!("Launch at login: {}", enabled);
+    Ok(())
+}
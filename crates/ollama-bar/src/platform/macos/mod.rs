@@ -0,0 +1,191 @@
+//! macOS backend: AppKit run loop with an NSTimer driving the tray
+
+mod login_item;
+mod notify;
+mod preferences;
+
+use crate::state::AppState;
+use crate::tray::TrayManager;
+use anyhow::Result;
+use objc2::rc::Retained;
+use objc2::runtime::{AnyObject, ProtocolObject};
+use objc2::{declare_class, msg_send_id, mutability, sel, ClassType, DeclaredClass};
+use objc2_app_kit::{NSApplication, NSApplicationActivationPolicy, NSApplicationDelegate};
+use objc2_foundation::{MainThreadMarker, NSNotification, NSObject, NSObjectProtocol, NSTimer};
+use std::cell::RefCell;
+use std::path::Path;
+use std::process::Command;
+
+pub use notify::show as notify;
+pub use preferences::show as show_preferences;
+
+// Thread-local storage for tray manager (main thread only)
+thread_local! {
+    static TRAY_MANAGER: RefCell<Option<TrayManager>> = const { RefCell::new(None) };
+    static TIMER_DELEGATE: RefCell<Option<Retained<TrayTimerDelegate>>> = const { RefCell::new(None) };
+}
+
+// Minimal app delegate
+declare_class!(
+    struct TrayAppDelegate;
+
+    unsafe impl ClassType for TrayAppDelegate {
+        type Super = NSObject;
+        type Mutability = mutability::MainThreadOnly;
+        const NAME: &'static str = "OllamaBarTrayAppDelegate";
+    }
+
+    impl DeclaredClass for TrayAppDelegate {
+        type Ivars = ();
+    }
+
+    unsafe impl NSObjectProtocol for TrayAppDelegate {}
+
+    unsafe impl NSApplicationDelegate for TrayAppDelegate {
+        #[method(applicationDidFinishLaunching:)]
+        fn did_finish_launching(&self, _notification: &NSNotification) {
+            tracing::info!("Application did finish launching");
+        }
+
+        #[method(applicationWillTerminate:)]
+        fn will_terminate(&self, _notification: &NSNotification) {
+            tracing::info!("Application will terminate");
+        }
+    }
+);
+
+impl TrayAppDelegate {
+    fn new(mtm: MainThreadMarker) -> Retained<Self> {
+        let this = mtm.alloc::<Self>();
+        let this = this.set_ivars(());
+        unsafe { msg_send_id![super(this), init] }
+    }
+}
+
+// Timer delegate for processing events on main thread
+declare_class!(
+    struct TrayTimerDelegate;
+
+    unsafe impl ClassType for TrayTimerDelegate {
+        type Super = NSObject;
+        type Mutability = mutability::MainThreadOnly;
+        const NAME: &'static str = "OllamaBarTrayTimerDelegate";
+    }
+
+    impl DeclaredClass for TrayTimerDelegate {
+        type Ivars = ();
+    }
+
+    unsafe impl NSObjectProtocol for TrayTimerDelegate {}
+
+    unsafe impl TrayTimerDelegate {
+        #[method(timerFired:)]
+        fn timer_fired(&self, _timer: *mut AnyObject) {
+            let quit = TRAY_MANAGER.with(|tm| tm.borrow_mut().as_mut().is_some_and(|manager| manager.tick()));
+            if quit {
+                if let Some(mtm) = MainThreadMarker::new() {
+                    let app = NSApplication::sharedApplication(mtm);
+                    unsafe { app.terminate(None) };
+                }
+            }
+        }
+    }
+);
+
+impl TrayTimerDelegate {
+    fn new(mtm: MainThreadMarker) -> Retained<Self> {
+        let this = mtm.alloc::<Self>();
+        let this = this.set_ivars(());
+        unsafe { msg_send_id![super(this), init] }
+    }
+}
+
+/// Create the tray and run the AppKit event loop (blocks)
+pub fn run(state: AppState) -> Result<()> {
+    let mtm = MainThreadMarker::new().expect("Must run on main thread");
+    let app = NSApplication::sharedApplication(mtm);
+    app.setActivationPolicy(NSApplicationActivationPolicy::Accessory);
+
+    // Create tray manager and store in thread-local
+    let mut manager = TrayManager::new(state)?;
+    if let Err(e) = manager.create_tray() {
+        tracing::error!("Failed to create tray: {}", e);
+    }
+    TRAY_MANAGER.with(|tm| *tm.borrow_mut() = Some(manager));
+
+    // Set up app delegate
+    let delegate = TrayAppDelegate::new(mtm);
+    app.setDelegate(Some(ProtocolObject::from_ref(&*delegate)));
+
+    // Set up timer for event processing (runs on main thread)
+    let timer_delegate = TrayTimerDelegate::new(mtm);
+    unsafe {
+        let _timer: Retained<NSTimer> = msg_send_id![
+            NSTimer::class(),
+            scheduledTimerWithTimeInterval: 1.0f64,
+            target: &*timer_delegate,
+            selector: sel!(timerFired:),
+            userInfo: std::ptr::null::<AnyObject>(),
+            repeats: true
+        ];
+    }
+
+    // Keep timer delegate alive
+    TIMER_DELEGATE.with(|td| *td.borrow_mut() = Some(timer_delegate));
+
+    tracing::info!("OllamaBar running - check your menu bar");
+
+    // Run the app (blocks)
+    unsafe { app.run() };
+
+    Ok(())
+}
+
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    super::pipe_to("pbcopy", &[], text)
+}
+
+/// Ask for a model name with an AppleScript dialog
+pub fn prompt_model_name() -> Option<String> {
+    let script = r#"
+        set dialogResult to display dialog "Enter model name to pull (e.g., llama3.2, gemma2:2b):" default answer "" buttons {"Cancel", "Pull"} default button "Pull" with title "Pull Model"
+        if button returned of dialogResult is "Pull" then
+            return text returned of dialogResult
+        else
+            return ""
+        end if
+    "#;
+    super::dialog_output(Command::new("osascript").args(["-e", script]))
+}
+
+/// Open the Ollama log in Console, or Console alone until the log exists
+pub fn open_logs(log_path: &Path) {
+    let mut command = Command::new("open");
+    command.args(["-a", "Console"]);
+    if log_path.exists() {
+        command.arg(log_path);
+    }
+    let _ = command.spawn();
+}
+
+pub fn open_path(path: &Path) {
+    let _ = Command::new("open").arg(path).spawn();
+}
+
+/// Run `quant <args>` in a new Terminal window, in `dir`
+pub fn open_terminal(dir: &Path, args: &[String]) -> Result<()> {
+    // Single-quote for the shell, then escape for the AppleScript string
+    let shell_quote = |s: &str| format!("'{}'", s.replace('\'', r"'\''"));
+    let mut command = format!("cd {} && quant", shell_quote(&dir.to_string_lossy()));
+    for arg in args {
+        command.push(' ');
+        command.push_str(&shell_quote(arg));
+    }
+    let command = command.replace('\\', r"\\").replace('"', r#"\""#);
+    let script = format!(
+        "tell application \"Terminal\"\n do script \"{}\"\n activate\nend tell",
+        command
+    );
+    Command::new("osascript").args(["-e", &script]).spawn()?;
+    Ok(())
+}
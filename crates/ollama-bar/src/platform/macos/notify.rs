@@ -0,0 +1,26 @@
+//! macOS user notifications
+//!
+//! Delivered through NSUserNotificationCenter from the main thread. The
+//! center only exists for a bundled app (`cargo bundle`), so a bare binary
+//! run from a terminal logs the notification instead.
+
+use objc2_foundation::{NSBundle, NSString, NSUserNotification, NSUserNotificationCenter};
+
+/// Post a notification; must be called on the main thread
+// NSUserNotification is deprecated in favour of UserNotifications.framework,
+// which has no bindings at our objc2 version, but is still delivered
+#[allow(deprecated)]
+pub fn show(title: &str, body: &str) {
+    let bundled = unsafe { NSBundle::mainBundle().bundleIdentifier() }.is_some();
+    if !bundled {
+        tracing::info!("Notification (not running as an app bundle): {}: {}", title, body);
+        return;
+    }
+
+    unsafe {
+        let notification = NSUserNotification::new();
+        notification.setTitle(Some(&NSString::from_str(title)));
+        notification.setInformativeText(Some(&NSString::from_str(body)));
+        NSUserNotificationCenter::defaultUserNotificationCenter().deliverNotification(&notification);
+    }
+}
@@ -0,0 +1,288 @@
+//! Preferences window
+//!
+//! Edits the `[menubar]` settings. Saving writes them to the quant user
+//! config and applies them without a restart: the log level and login item
+//! at once, the refresh interval, auto-share and default model from the
+//! next refresh or start.
+
+use crate::state::AppState;
+use llm_core::config::{MenubarConfig, MENUBAR_LOG_LEVELS};
+use objc2::rc::Retained;
+use objc2::runtime::AnyObject;
+use objc2::{declare_class, msg_send_id, mutability, sel, ClassType, DeclaredClass};
+use objc2_app_kit::{
+    NSApplication, NSBackingStoreType, NSButton, NSControlStateValueOff, NSControlStateValueOn, NSPopUpButton,
+    NSTextField, NSView, NSWindow, NSWindowStyleMask,
+};
+use objc2_foundation::{MainThreadMarker, NSObject, NSObjectProtocol, NSPoint, NSRect, NSSize, NSString};
+use std::cell::{OnceCell, RefCell};
+
+/// Default model entry meaning none
+const NO_MODEL: &str = "(none)";
+
+// Layout: label column, control column, row baselines from the top
+const LABEL_X: f64 = 20.0;
+const CONTROL_X: f64 = 230.0;
+const WIDTH: f64 = 440.0;
+const HEIGHT: f64 = 250.0;
+
+thread_local! {
+    // The open window's controller (main thread only)
+    static PREFERENCES: RefCell<Option<Retained<PreferencesController>>> = const { RefCell::new(None) };
+}
+
+struct Controls {
+    window: Retained<NSWindow>,
+    launch_at_login: Retained<NSButton>,
+    refresh_secs: Retained<NSTextField>,
+    default_model: Retained<NSPopUpButton>,
+    auto_share: Retained<NSButton>,
+    log_level: Retained<NSPopUpButton>,
+}
+
+struct Ivars {
+    state: AppState,
+    controls: OnceCell<Controls>,
+}
+
+declare_class!(
+    struct PreferencesController;
+
+    unsafe impl ClassType for PreferencesController {
+        type Super = NSObject;
+        type Mutability = mutability::MainThreadOnly;
+        const NAME: &'static str = "OllamaBarPreferencesController";
+    }
+
+    impl DeclaredClass for PreferencesController {
+        type Ivars = Ivars;
+    }
+
+    unsafe impl NSObjectProtocol for PreferencesController {}
+
+    unsafe impl PreferencesController {
+        #[method(save:)]
+        fn save(&self, _sender: *mut AnyObject) {
+            self.save_settings();
+        }
+
+        #[method(cancel:)]
+        fn cancel(&self, _sender: *mut AnyObject) {
+            self.close_window();
+        }
+
+        #[method(openConfig:)]
+        fn open_config(&self, _sender: *mut AnyObject) {
+            open_config_file();
+        }
+    }
+);
+
+impl PreferencesController {
+    fn new(mtm: MainThreadMarker, state: AppState) -> Retained<Self> {
+        let settings = state.settings();
+        let models = state.available_models();
+
+        let this = mtm.alloc::<Self>().set_ivars(Ivars {
+            state,
+            controls: OnceCell::new(),
+        });
+        let this: Retained<Self> = unsafe { msg_send_id![super(this), init] };
+
+        let controls = unsafe { this.build(mtm, &settings, &models) };
+        let _ = this.ivars().controls.set(controls);
+        this
+    }
+
+    unsafe fn build(&self, mtm: MainThreadMarker, settings: &MenubarConfig, models: &[String]) -> Controls {
+        let target: &AnyObject = self;
+
+        let window = NSWindow::initWithContentRect_styleMask_backing_defer(
+            mtm.alloc::<NSWindow>(),
+            rect(0.0, 0.0, WIDTH, HEIGHT),
+            NSWindowStyleMask::Titled | NSWindowStyleMask::Closable,
+            NSBackingStoreType::NSBackingStoreBuffered,
+            false,
+        );
+        window.setTitle(&NSString::from_str("OllamaBar Preferences"));
+        // The controller owns the window and reuses nothing after close
+        window.setReleasedWhenClosed(false);
+        let content = window.contentView().expect("window has a content view");
+
+        let launch_at_login = checkbox(mtm, "Launch at login", settings.launch_at_login, 200.0);
+        content.addSubview(&launch_at_login);
+
+        add_label(&content, mtm, "Refresh every (seconds)", 165.0);
+        let refresh_secs = NSTextField::textFieldWithString(&NSString::from_str(&settings.refresh_secs.to_string()), mtm);
+        refresh_secs.setFrame(rect(CONTROL_X, 165.0, 80.0, 24.0));
+        content.addSubview(&refresh_secs);
+
+        add_label(&content, mtm, "Default model", 130.0);
+        let mut model_titles = vec![NO_MODEL.to_string()];
+        model_titles.extend(models.iter().cloned());
+        if !settings.default_model.is_empty() && !models.contains(&settings.default_model) {
+            model_titles.push(settings.default_model.clone());
+        }
+        let selected = if settings.default_model.is_empty() { NO_MODEL } else { &settings.default_model };
+        let default_model = popup(mtm, &model_titles, selected, 130.0, 190.0);
+        content.addSubview(&default_model);
+
+        add_label(&content, mtm, "Log level", 95.0);
+        let log_levels: Vec<String> = MENUBAR_LOG_LEVELS.iter().map(|l| l.to_string()).collect();
+        let log_level = popup(mtm, &log_levels, &settings.log_level, 95.0, 120.0);
+        content.addSubview(&log_level);
+
+        let auto_share = checkbox(mtm, "Share via Tailscale automatically", settings.auto_share, 60.0);
+        content.addSubview(&auto_share);
+
+        let buttons = [
+            ("Open Config File", sel!(openConfig:), LABEL_X, 140.0),
+            ("Cancel", sel!(cancel:), 250.0, 80.0),
+            ("Save", sel!(save:), 340.0, 80.0),
+        ];
+        for (title, action, x, width) in buttons {
+            let button = NSButton::buttonWithTitle_target_action(&NSString::from_str(title), Some(target), Some(action), mtm);
+            button.setFrame(rect(x, 15.0, width, 30.0));
+            if title == "Save" {
+                button.setKeyEquivalent(&NSString::from_str("\r"));
+            }
+            content.addSubview(&button);
+        }
+
+        Controls {
+            window,
+            launch_at_login,
+            refresh_secs,
+            default_model,
+            auto_share,
+            log_level,
+        }
+    }
+
+    fn save_settings(&self) {
+        let Some(controls) = self.ivars().controls.get() else {
+            return;
+        };
+        let state = &self.ivars().state;
+
+        let result = unsafe { controls.read() }
+            .map_err(anyhow::Error::msg)
+            .and_then(|settings| apply(state, settings));
+        match result {
+            Ok(()) => controls.window.close(),
+            Err(e) => {
+                tracing::error!("Failed to save settings: {:#}", e);
+                state.notify("Settings not saved", format!("{:#}", e));
+            }
+        }
+    }
+
+    fn close_window(&self) {
+        if let Some(controls) = self.ivars().controls.get() {
+            controls.window.close();
+        }
+    }
+}
+
+impl Controls {
+    /// Settings as entered in the window
+    unsafe fn read(&self) -> Result<MenubarConfig, String> {
+        let refresh = self.refresh_secs.stringValue().to_string();
+        let refresh_secs = refresh
+            .trim()
+            .parse::<u64>()
+            .ok()
+            .filter(|secs| *secs > 0)
+            .ok_or_else(|| format!("Refresh interval must be a whole number of seconds, not \"{}\"", refresh.trim()))?;
+
+        Ok(MenubarConfig {
+            launch_at_login: is_checked(&self.launch_at_login),
+            refresh_secs,
+            default_model: selected_title(&self.default_model)
+                .filter(|model| model != NO_MODEL)
+                .unwrap_or_default(),
+            auto_share: is_checked(&self.auto_share),
+            log_level: selected_title(&self.log_level).unwrap_or_else(|| "info".to_string()),
+        })
+    }
+}
+
+/// Show the preferences window, replacing one already open (main thread only)
+pub fn show(state: AppState) {
+    let Some(mtm) = MainThreadMarker::new() else {
+        tracing::error!("Preferences must be opened on the main thread");
+        return;
+    };
+
+    PREFERENCES.with(|prefs| {
+        if let Some(previous) = prefs.borrow_mut().take() {
+            previous.close_window();
+        }
+
+        let controller = PreferencesController::new(mtm, state);
+        if let Some(controls) = controller.ivars().controls.get() {
+            controls.window.center();
+            // A menu bar app isn't active, so bring the window forward explicitly
+            #[allow(deprecated)]
+            NSApplication::sharedApplication(mtm).activateIgnoringOtherApps(true);
+            controls.window.makeKeyAndOrderFront(None);
+        }
+        *prefs.borrow_mut() = Some(controller);
+    });
+}
+
+/// Save settings and apply the ones that take effect immediately
+fn apply(state: &AppState, settings: MenubarConfig) -> anyhow::Result<()> {
+    let previous = state.settings();
+    if settings.launch_at_login != previous.launch_at_login {
+        super::login_item::set_enabled(settings.launch_at_login)?;
+    }
+
+    let path = state.save_settings(settings.clone())?;
+    tracing::info!("Settings saved to {}", path.display());
+
+    if settings.log_level != previous.log_level {
+        crate::set_log_level(&settings.log_level);
+    }
+    Ok(())
+}
+
+fn open_config_file() {
+    if let Some(path) = crate::platform::settings_path() {
+        super::open_path(&path);
+    }
+}
+
+fn rect(x: f64, y: f64, width: f64, height: f64) -> NSRect {
+    NSRect::new(NSPoint::new(x, y), NSSize::new(width, height))
+}
+
+unsafe fn add_label(content: &NSView, mtm: MainThreadMarker, text: &str, y: f64) {
+    let label = NSTextField::labelWithString(&NSString::from_str(text), mtm);
+    label.setFrame(rect(LABEL_X, y, CONTROL_X - LABEL_X - 10.0, 20.0));
+    content.addSubview(&label);
+}
+
+unsafe fn checkbox(mtm: MainThreadMarker, title: &str, checked: bool, y: f64) -> Retained<NSButton> {
+    let button = NSButton::checkboxWithTitle_target_action(&NSString::from_str(title), None, None, mtm);
+    button.setState(if checked { NSControlStateValueOn } else { NSControlStateValueOff });
+    button.setFrame(rect(LABEL_X, y, WIDTH - 2.0 * LABEL_X, 24.0));
+    button
+}
+
+unsafe fn popup(mtm: MainThreadMarker, titles: &[String], selected: &str, y: f64, width: f64) -> Retained<NSPopUpButton> {
+    let popup = NSPopUpButton::initWithFrame_pullsDown(mtm.alloc::<NSPopUpButton>(), rect(CONTROL_X - 3.0, y - 2.0, width, 26.0), false);
+    for title in titles {
+        popup.addItemWithTitle(&NSString::from_str(title));
+    }
+    popup.selectItemWithTitle(&NSString::from_str(selected));
+    popup
+}
+
+unsafe fn is_checked(button: &NSButton) -> bool {
+    button.state() == NSControlStateValueOn
+}
+
+unsafe fn selected_title(popup: &NSPopUpButton) -> Option<String> {
+    popup.titleOfSelectedItem().map(|title| title.to_string())
+}
@@ -0,0 +1,78 @@
+//! Desktop integration for each platform
+//!
+//! The tray menu, state refresh and event handling live in `tray` and
+//! `state` and are shared. Each backend provides the event loop that drives
+//! them plus the few things tray-icon doesn't cover:
+//!
+//! - `run(state)`: create the tray and run the main loop until Quit
+//! - `notify(title, body)`: show a desktop notification (main thread)
+//! - `copy_to_clipboard(text)`
+//! - `prompt_model_name()`: ask for a model to pull, `None` if cancelled
+//! - `open_logs(path)` and `open_path(path)`
+//! - `open_terminal(dir, args)`: run `quant <args>` in a new terminal window
+//! - `show_preferences(state)`
+//!
+//! macOS uses AppKit directly, Linux a GTK main loop (the tray shows up
+//! through StatusNotifier/appindicator), Windows a Win32 message loop.
+
+use anyhow::{bail, Context, Result};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "macos")]
+pub use macos::*;
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+pub use linux::*;
+
+#[cfg(target_os = "windows")]
+mod windows;
+#[cfg(target_os = "windows")]
+pub use windows::*;
+
+/// The file settings are saved in, or llm.toml before the first save
+pub fn settings_path() -> Option<PathBuf> {
+    llm_core::layered::user_config_path()
+        .filter(|path| path.exists())
+        .or_else(|| llm_core::Config::find_config_path().ok())
+}
+
+/// Run `program` with `text` on stdin, e.g. a clipboard tool
+fn pipe_to(program: &str, args: &[&str], text: &str) -> Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if let Some(stdin) = child.stdin.as_mut() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("{} exited with {}", program, status);
+    }
+    Ok(())
+}
+
+/// Trimmed stdout of a dialog command, `None` if it was cancelled or empty
+fn dialog_output(command: &mut Command) -> Option<String> {
+    match command.output() {
+        Ok(out) if out.status.success() => {
+            let text = String::from_utf8_lossy(&out.stdout).trim().to_string();
+            (!text.is_empty()).then_some(text)
+        }
+        Ok(_) => {
+            tracing::debug!("Dialog cancelled");
+            None
+        }
+        Err(e) => {
+            tracing::error!("Failed to show dialog: {}", e);
+            None
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Windows backend: Win32 message loop with a thread timer driving the tray
+//!
+//! Notifications and the pull dialog go through PowerShell, which every
+//! supported Windows version ships with.
+
+use crate::state::AppState;
+use crate::tray::TrayManager;
+use anyhow::Result;
+use std::os::windows::process::CommandExt;
+use std::path::Path;
+use std::process::Command;
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    DispatchMessageW, GetMessageW, PostQuitMessage, SetTimer, TranslateMessage, MSG, WM_TIMER,
+};
+
+/// How often the timer refreshes the tray, in milliseconds
+const TICK_MS: u32 = 1000;
+
+/// Keeps PowerShell from flashing a console window
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+/// Create the tray and run the message loop (blocks)
+pub fn run(state: AppState) -> Result<()> {
+    let mut manager = TrayManager::new(state)?;
+    manager.create_tray()?;
+
+    // A thread timer (no window) posts WM_TIMER to this thread's queue
+    unsafe { SetTimer(std::ptr::null_mut(), 0, TICK_MS, None) };
+
+    tracing::info!("OllamaBar running - check your notification area");
+
+    let mut msg = MSG::default();
+    // GetMessageW returns 0 on WM_QUIT and -1 on error
+    while unsafe { GetMessageW(&mut msg, std::ptr::null_mut(), 0, 0) } > 0 {
+        if msg.message == WM_TIMER && msg.hwnd.is_null() {
+            if manager.tick() {
+                unsafe { PostQuitMessage(0) };
+            }
+            continue;
+        }
+        unsafe {
+            TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+    Ok(())
+}
+
+/// Show a balloon notification from a short-lived PowerShell tray icon
+pub fn notify(title: &str, body: &str) {
+    let script = format!(
+        "Add-Type -AssemblyName System.Windows.Forms; \
+         $n = New-Object System.Windows.Forms.NotifyIcon; \
+         $n.Icon = [System.Drawing.SystemIcons]::Information; \
+         $n.Visible = $true; \
+         $n.ShowBalloonTip(5000, '{}', '{}', 'Info'); \
+         Start-Sleep -Seconds 6; $n.Dispose()",
+        quote(title),
+        quote(body)
+    );
+    if powershell(&script).spawn().is_err() {
+        tracing::info!("Notification (PowerShell not found): {}: {}", title, body);
+    }
+}
+
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    super::pipe_to("clip", &[], text)
+}
+
+/// Ask for a model name with a Visual Basic input box
+pub fn prompt_model_name() -> Option<String> {
+    let script = "Add-Type -AssemblyName Microsoft.VisualBasic; \
+         [Microsoft.VisualBasic.Interaction]::InputBox(\
+         'Enter model name to pull (e.g., llama3.2, gemma2:2b):', 'Pull Model', '')";
+    super::dialog_output(&mut powershell(script))
+}
+
+pub fn open_logs(log_path: &Path) {
+    if log_path.exists() {
+        let _ = Command::new("notepad").arg(log_path).spawn();
+    } else {
+        tracing::warn!("Log file doesn't exist yet: {:?}", log_path);
+    }
+}
+
+pub fn open_path(path: &Path) {
+    let _ = Command::new("explorer").arg(path).spawn();
+}
+
+/// Run `quant <args>` in a new console window, in `dir`
+pub fn open_terminal(dir: &Path, args: &[String]) -> Result<()> {
+    Command::new("cmd")
+        .args(["/c", "start", "", "/D"])
+        .arg(dir)
+        .args(["cmd", "/k", "quant"])
+        .args(args)
+        .creation_flags(CREATE_NO_WINDOW)
+        .spawn()?;
+    Ok(())
+}
+
+/// No native preferences window here: open the config file instead
+pub fn show_preferences(_state: AppState) {
+    match super::settings_path() {
+        Some(path) => open_path(&path),
+        None => tracing::warn!("No config file to open"),
+    }
+}
+
+fn powershell(script: &str) -> Command {
+    let mut command = Command::new("powershell");
+    command
+        .args(["-NoProfile", "-Command", script])
+        .creation_flags(CREATE_NO_WINDOW);
+    command
+}
+
+/// Escape text for a single-quoted PowerShell string
+fn quote(text: &str) -> String {
+    text.replace('\'', "''")
+}
@@ -1,13 +1,57 @@
 //! Application state management
 
-use llm_core::{Config, OllamaClient, OllamaStatus, TailscaleClient, TailscaleStatus};
+use futures::StreamExt;
+use llm_core::config::MenubarConfig;
+use llm_core::ipc::{IpcHub, SessionInfo};
+use llm_core::{Config, HealthReport, OllamaClient, OllamaStatus, TailscaleClient, TailscaleStatus};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// How often to re-run environment diagnostics (they shell out to df/ollama)
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Longest a pull waits on the stream before checking for cancellation
+const PULL_CANCEL_POLL: Duration = Duration::from_secs(1);
+
+/// An in-flight model pull, shown in the menu
+#[derive(Clone)]
+pub struct PullState {
+    pub model: String,
+    /// Latest status line from Ollama ("pulling manifest", "downloading", ...)
+    pub status: String,
+    pub completed: u64,
+    pub total: u64,
+    cancel: Arc<AtomicBool>,
+}
+
+impl PullState {
+    /// Download progress of the current layer, once its size is known
+    pub fn percent(&self) -> Option<u64> {
+        (self.total > 0).then(|| self.completed.min(self.total) * 100 / self.total)
+    }
+}
+
+/// How a pull ended, when it didn't fail
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PullOutcome {
+    Completed,
+    Cancelled,
+}
+
+/// A user notification waiting to be delivered from the main thread
+pub struct Notification {
+    pub title: String,
+    pub body: String,
+}
 
 /// Shared application state
 #[derive(Clone)]
 pub struct AppState {
     inner: Arc<Mutex<AppStateInner>>,
+    /// quant sessions connected over the local socket
+    hub: IpcHub,
 }
 
 struct AppStateInner {
@@ -28,18 +72,31 @@ struct AppStateInner {
 
     // Remember last used model
     last_model: Option<String>,
+
+    // Models quant sessions used, as last saved
+    saved_recent_models: Vec<String>,
+
+    // Environment diagnostics
+    health: HealthReport,
+    last_health_check: Option<Instant>,
+
+    // Model pull in progress, and notifications queued by background threads
+    pull: Option<PullState>,
+    notifications: Vec<Notification>,
 }
 
 impl AppState {
     pub fn new() -> anyhow::Result<Self> {
-        let config = Config::load()?;
-        let ollama_client = OllamaClient::new(config.ollama_url());
+        // Same layering as the CLI, so `quant config set ollama.port ...` applies here too
+        let config = Config::load_layered()?.into_inner();
+        let ollama_client = config.ollama_client();
         let tailscale_client = TailscaleClient::new();
 
         let memory_total_gb = Config::system_ram_gb()? as f64;
 
         // Load last model from persistent storage
         let last_model = Self::load_last_model();
+        let recent_models = Self::load_recent_models();
 
         Ok(Self {
             inner: Arc::new(Mutex::new(AppStateInner {
@@ -54,7 +111,13 @@ impl AppState {
                 memory_total_gb,
                 tailscale_sharing: false,
                 last_model,
+                saved_recent_models: recent_models.clone(),
+                health: HealthReport::default(),
+                last_health_check: None,
+                pull: None,
+                notifications: Vec::new(),
             })),
+            hub: IpcHub::with_recent_models(recent_models),
         })
     }
 
@@ -74,11 +137,43 @@ impl AppState {
         }
     }
 
+    /// Load the models quant sessions used, most recent first
+    fn load_recent_models() -> Vec<String> {
+        let Some(path) = dirs::cache_dir().map(|d| d.join("ollama-bar").join("recent_models")) else {
+            return Vec::new();
+        };
+        std::fs::read_to_string(path)
+            .map(|s| s.lines().filter(|l| !l.is_empty()).map(String::from).collect())
+            .unwrap_or_default()
+    }
+
+    /// Save the recently used models, one per line
+    fn save_recent_models(models: &[String]) {
+        if let Some(cache_dir) = dirs::cache_dir() {
+            let dir = cache_dir.join("ollama-bar");
+            let _ = std::fs::create_dir_all(&dir);
+            let _ = std::fs::write(dir.join("recent_models"), models.join("\n"));
+        }
+    }
+
+    /// Accept quant sessions on the local socket (blocks the calling runtime)
+    pub async fn serve_sessions(&self) -> anyhow::Result<()> {
+        self.hub.serve(&llm_core::ipc::socket_path()).await
+    }
+
     /// Refresh all status information
     pub async fn refresh(&self) -> anyhow::Result<()> {
-        let (ollama_client, tailscale_client) = {
+        let (ollama_client, tailscale_client, config, health_due) = {
             let inner = self.inner.lock().unwrap();
-            (inner.ollama_client.clone(), inner.tailscale_client.clone())
+            let health_due = inner
+                .last_health_check
+                .is_none_or(|t| t.elapsed() >= HEALTH_CHECK_INTERVAL);
+            (
+                inner.ollama_client.clone(),
+                inner.tailscale_client.clone(),
+                inner.config.clone(),
+                health_due,
+            )
         };
 
         // Check Ollama status
@@ -101,7 +196,28 @@ impl AppState {
         let tailscale_status = tailscale_client.status();
 
         // Check if tailscale serve is actually active
-        let tailscale_sharing = self.is_tailscale_serving();
+        let mut tailscale_sharing = self.is_tailscale_serving();
+
+        // Share automatically once both are up, if configured
+        if config.menubar.auto_share
+            && !tailscale_sharing
+            && ollama_status == OllamaStatus::Running
+            && tailscale_status == TailscaleStatus::Connected
+        {
+            tracing::info!("Auto-sharing Ollama over Tailscale");
+            match Self::set_tailscale_serve(true) {
+                Ok(()) => tailscale_sharing = true,
+                Err(e) => tracing::warn!("Auto-share failed: {}", e),
+            }
+        }
+
+        // Environment diagnostics (throttled), re-run immediately on status changes
+        let status_changed = self.inner.lock().unwrap().ollama_status != ollama_status;
+        let health = if health_due || status_changed {
+            Some(llm_core::health::run_checks(&config).await)
+        } else {
+            None
+        };
 
         // Update state
         {
@@ -121,6 +237,20 @@ impl AppState {
             inner.available_models = available_models;
             inner.memory_used_gb = memory_used;
             inner.tailscale_sharing = tailscale_sharing;
+
+            let recent_models = self.hub.recent_models();
+            if recent_models != inner.saved_recent_models {
+                Self::save_recent_models(&recent_models);
+                inner.saved_recent_models = recent_models;
+            }
+
+            if let Some(health) = health {
+                for check in health.problems() {
+                    tracing::warn!("Health check '{}': {}", check.name, check.detail);
+                }
+                inner.health = health;
+                inner.last_health_check = Some(Instant::now());
+            }
         }
 
         Ok(())
@@ -132,6 +262,10 @@ impl AppState {
         self.inner.lock().unwrap().ollama_status
     }
 
+    pub fn health(&self) -> HealthReport {
+        self.inner.lock().unwrap().health.clone()
+    }
+
     pub fn tailscale_status(&self) -> TailscaleStatus {
         self.inner.lock().unwrap().tailscale_status
     }
@@ -148,6 +282,16 @@ impl AppState {
         self.inner.lock().unwrap().available_models.clone()
     }
 
+    /// quant sessions running now
+    pub fn sessions(&self) -> Vec<SessionInfo> {
+        self.hub.sessions()
+    }
+
+    /// Models quant sessions used, most recent first
+    pub fn recent_models(&self) -> Vec<String> {
+        self.hub.recent_models()
+    }
+
     pub fn memory_info(&self) -> (f64, f64) {
         let inner = self.inner.lock().unwrap();
         (inner.memory_used_gb, inner.memory_total_gb)
@@ -168,6 +312,17 @@ impl AppState {
     }
 
     #[allow(dead_code)]
+    pub fn settings(&self) -> MenubarConfig {
+        self.inner.lock().unwrap().config.menubar.clone()
+    }
+
+    /// Persist new settings to the user config and use them from now on
+    pub fn save_settings(&self, settings: MenubarConfig) -> anyhow::Result<PathBuf> {
+        let path = settings.save()?;
+        self.inner.lock().unwrap().config.menubar = settings;
+        Ok(path)
+    }
+
     pub fn ollama_url(&self) -> String {
         self.inner.lock().unwrap().config.ollama_url()
     }
@@ -176,7 +331,11 @@ impl AppState {
 
     /// Get the path to the Ollama log file
     pub fn ollama_log_path() -> std::path::PathBuf {
-        std::path::PathBuf::from("/tmp/ollama.log")
+        if cfg!(target_os = "windows") {
+            std::env::temp_dir().join("ollama.log")
+        } else {
+            std::path::PathBuf::from("/tmp/ollama.log")
+        }
     }
 
     pub async fn start_ollama(&self) -> anyhow::Result<()> {
@@ -220,6 +379,7 @@ impl AppState {
             tokio::time::sleep(Duration::from_secs(1)).await;
             if client.health_check().await.unwrap_or(false) {
                 tracing::info!("Ollama started successfully");
+                self.warm_pool().await;
                 return Ok(());
             }
         }
@@ -227,6 +387,25 @@ impl AppState {
         anyhow::bail!("Ollama failed to start within 30 seconds")
     }
 
+    /// Load the `[warmup]` models if Ollama is running
+    pub async fn warm_pool(&self) {
+        let (client, pool) = {
+            let inner = self.inner.lock().unwrap();
+            (inner.ollama_client.clone(), inner.config.warm_pool())
+        };
+        if pool.is_empty() || !client.health_check().await.unwrap_or(false) {
+            return;
+        }
+
+        for model in pool {
+            tracing::info!("Warming model: {}", model.name);
+            match client.warm_model(&model.name, Some(&model.keep_alive)).await {
+                Ok(()) => tracing::info!("Model warm: {}", model.name),
+                Err(e) => tracing::warn!("Failed to warm {}: {}", model.name, e),
+            }
+        }
+    }
+
     /// Start Ollama and load a specific model
     pub async fn start_ollama_with_model(&self, model: &str) -> anyhow::Result<()> {
         // First start Ollama
@@ -245,9 +424,11 @@ impl AppState {
         tracing::info!("Stopping Ollama");
 
         // Find and kill ollama process
-        let output = Command::new("pkill")
-            .args(["-f", "ollama serve"])
-            .output()?;
+        let output = if cfg!(target_os = "windows") {
+            Command::new("taskkill").args(["/IM", "ollama.exe", "/F"]).output()?
+        } else {
+            Command::new("pkill").args(["-f", "ollama serve"]).output()?
+        };
 
         if output.status.success() {
             tracing::info!("Ollama stopped");
@@ -263,22 +444,96 @@ impl AppState {
         client.load_model(model).await?;
         tracing::info!("Model loaded: {}", model);
 
+        // Running quant sessions follow the switch
+        self.hub.switch_model(model);
+
         Ok(())
     }
 
-    pub async fn pull_model(&self, model: &str) -> anyhow::Result<()> {
-        let client = self.inner.lock().unwrap().ollama_client.clone();
+    /// Pull a model, tracking progress for the menu until it finishes or
+    /// `cancel_pull` is called. Only one pull runs at a time.
+    pub async fn pull_model(&self, model: &str) -> anyhow::Result<PullOutcome> {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let client = {
+            let mut inner = self.inner.lock().unwrap();
+            if let Some(pull) = &inner.pull {
+                anyhow::bail!("already pulling {}", pull.model);
+            }
+            inner.pull = Some(PullState {
+                model: model.to_string(),
+                status: "starting".to_string(),
+                completed: 0,
+                total: 0,
+                cancel: Arc::clone(&cancel),
+            });
+            inner.ollama_client.clone()
+        };
 
+        let result = self.follow_pull(&client, model, &cancel).await;
+        self.inner.lock().unwrap().pull = None;
+        result
+    }
+
+    async fn follow_pull(
+        &self,
+        client: &OllamaClient,
+        model: &str,
+        cancel: &AtomicBool,
+    ) -> anyhow::Result<PullOutcome> {
         tracing::info!("Pulling model: {}", model);
-        client.pull_model_blocking(model).await?;
+        let mut stream = client.pull_model_stream(model).await?;
+
+        loop {
+            if cancel.load(Ordering::Relaxed) {
+                // Dropping the stream closes the connection, which stops the pull
+                tracing::info!("Pull cancelled: {}", model);
+                return Ok(PullOutcome::Cancelled);
+            }
+            let next = match tokio::time::timeout(PULL_CANCEL_POLL, stream.next()).await {
+                Ok(next) => next,
+                Err(_) => continue,
+            };
+            let Some(progress) = next else {
+                break;
+            };
+            let progress = progress?;
+
+            if let Some(pull) = self.inner.lock().unwrap().pull.as_mut() {
+                pull.status = progress.status;
+                pull.completed = progress.completed;
+                pull.total = progress.total;
+            }
+        }
+
         tracing::info!("Model pulled: {}", model);
+        Ok(PullOutcome::Completed)
+    }
 
-        Ok(())
+    pub fn pull_progress(&self) -> Option<PullState> {
+        self.inner.lock().unwrap().pull.clone()
     }
 
-    pub fn toggle_tailscale_sharing(&self) -> anyhow::Result<()> {
-        use std::process::Command;
+    /// Ask the running pull, if any, to stop
+    pub fn cancel_pull(&self) {
+        if let Some(pull) = &self.inner.lock().unwrap().pull {
+            tracing::info!("Cancelling pull: {}", pull.model);
+            pull.cancel.store(true, Ordering::Relaxed);
+        }
+    }
 
+    /// Queue a notification; the main thread delivers it on its next tick
+    pub fn notify(&self, title: impl Into<String>, body: impl Into<String>) {
+        self.inner.lock().unwrap().notifications.push(Notification {
+            title: title.into(),
+            body: body.into(),
+        });
+    }
+
+    pub fn take_notifications(&self) -> Vec<Notification> {
+        std::mem::take(&mut self.inner.lock().unwrap().notifications)
+    }
+
+    pub fn toggle_tailscale_sharing(&self) -> anyhow::Result<()> {
         let tailscale_status = {
             let inner = self.inner.lock().unwrap();
             inner.tailscale_status
@@ -289,38 +544,31 @@ impl AppState {
         }
 
         let currently_sharing = self.is_tailscale_serving();
+        Self::set_tailscale_serve(!currently_sharing)?;
+        self.inner.lock().unwrap().tailscale_sharing = !currently_sharing;
 
-        if currently_sharing {
-            // Disable tailscale serve
-            tracing::info!("Disabling Tailscale serve");
-            let output = Command::new("tailscale")
-                .args(["serve", "--https=443", "off"])
-                .output()?;
-
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                tracing::error!("Failed to disable tailscale serve: {}", stderr);
-                anyhow::bail!("Failed to disable tailscale serve");
-            }
+        tracing::info!("Tailscale sharing: {}", !currently_sharing);
+        Ok(())
+    }
+
+    /// Turn tailscale serve of the Ollama port on or off
+    fn set_tailscale_serve(enable: bool) -> anyhow::Result<()> {
+        use std::process::Command;
 
-            self.inner.lock().unwrap().tailscale_sharing = false;
+        let (action, args): (&str, &[&str]) = if enable {
+            // Serve port 11434
+            ("enable", &["serve", "--bg", "11434"])
         } else {
-            // Enable tailscale serve on port 11434
-            tracing::info!("Enabling Tailscale serve on port 11434");
-            let output = Command::new("tailscale")
-                .args(["serve", "--bg", "11434"])
-                .output()?;
-
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                tracing::error!("Failed to enable tailscale serve: {}", stderr);
-                anyhow::bail!("Failed to enable tailscale serve");
-            }
+            ("disable", &["serve", "--https=443", "off"])
+        };
+        tracing::info!("Tailscale serve: {}", action);
+        let output = Command::new("tailscale").args(args).output()?;
 
-            self.inner.lock().unwrap().tailscale_sharing = true;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            tracing::error!("Failed to {} tailscale serve: {}", action, stderr);
+            anyhow::bail!("Failed to {} tailscale serve", action);
         }
-
-        tracing::info!("Tailscale sharing: {}", !currently_sharing);
         Ok(())
     }
 
@@ -379,6 +627,22 @@ mod tests {
         assert_eq!(path.to_string_lossy(), "/tmp/ollama.log");
     }
 
+    #[test]
+    fn test_pull_percent() {
+        let mut pull = PullState {
+            model: "llama3.2".to_string(),
+            status: "pulling manifest".to_string(),
+            completed: 0,
+            total: 0,
+            cancel: Arc::new(AtomicBool::new(false)),
+        };
+        assert_eq!(pull.percent(), None);
+
+        pull.completed = 1_500;
+        pull.total = 2_000;
+        assert_eq!(pull.percent(), Some(75));
+    }
+
     #[test]
     fn test_ollama_log_path_is_absolute() {
         let path = AppState::ollama_log_path();
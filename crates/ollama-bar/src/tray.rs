@@ -1,10 +1,16 @@
 //! Tray icon implementation using tray-icon crate
+//!
+//! Platform-agnostic: builds the menu from `AppState` and handles clicks.
+//! The event loop that calls `tick` and the OS integrations (dialogs,
+//! notifications, clipboard) come from `crate::platform`.
 
-use crate::state::AppState;
+use crate::platform;
+use crate::state::{AppState, PullOutcome, PullState};
 use anyhow::Result;
-use llm_core::{OllamaStatus, TailscaleStatus};
+use llm_core::ipc::{SessionInfo, SessionKind};
+use llm_core::{HealthReport, HealthStatus, OllamaStatus, TailscaleStatus};
 use muda::{Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu, CheckMenuItem};
-use tray_icon::{TrayIcon, TrayIconBuilder};
+use tray_icon::{Icon, TrayIcon, TrayIconBuilder};
 
 // Menu item IDs
 const ID_START: &str = "start";
@@ -13,6 +19,7 @@ const ID_RESTART: &str = "restart";
 const ID_TOGGLE_TAILSCALE: &str = "toggle_tailscale";
 const ID_COPY_URL: &str = "copy_url";
 const ID_PULL_MODEL: &str = "pull_model";
+const ID_CANCEL_PULL: &str = "cancel_pull";
 const ID_VIEW_LOGS: &str = "view_logs";
 const ID_SETTINGS: &str = "settings";
 const ID_QUIT: &str = "quit";
@@ -30,6 +37,8 @@ const ACTION_DEBOUNCE_SECS: u64 = 2; // Minimum seconds between same action
 pub struct TrayManager {
     pub state: AppState,
     tray_icon: Option<TrayIcon>,
+    // Status the icon image was drawn for (platforms without a title only)
+    icon_status: Option<OllamaStatus>,
 }
 
 impl TrayManager {
@@ -37,6 +46,7 @@ impl TrayManager {
         Ok(Self {
             state,
             tray_icon: None,
+            icon_status: None,
         })
     }
 
@@ -44,11 +54,15 @@ impl TrayManager {
         let menu = self.build_menu()?;
 
         // Create tray icon with a simple circle
-        let tray = TrayIconBuilder::new()
+        let builder = TrayIconBuilder::new()
             .with_tooltip("OllamaBar")
             .with_title("○")
-            .with_menu(Box::new(menu))
-            .build()?;
+            .with_menu(Box::new(menu));
+        // The title is text next to the icon on macOS and Linux, but Windows
+        // only shows an image, so draw one there
+        #[cfg(target_os = "windows")]
+        let builder = builder.with_icon(status_icon(OllamaStatus::Stopped)?);
+        let tray = builder.build()?;
 
         self.tray_icon = Some(tray);
         tracing::info!("Tray icon created");
@@ -99,6 +113,12 @@ impl TrayManager {
         let mem_item = MenuItem::new(mem_text, false, None);
         menu.append(&mem_item)?;
 
+        // Environment diagnostics (same checks as `quant doctor`)
+        let health = self.state.health();
+        if !health.checks.is_empty() {
+            menu.append(&Self::build_diagnostics_submenu(&health)?)?;
+        }
+
         menu.append(&PredefinedMenuItem::separator())?;
 
         // Start/Stop actions
@@ -145,9 +165,39 @@ impl TrayManager {
             menu.append(&PredefinedMenuItem::separator())?;
         }
 
+        // quant chat and agent sessions
+        let sessions = self.state.sessions();
+        if !sessions.is_empty() {
+            menu.append(&Self::build_sessions_submenu(&sessions)?)?;
+        }
+
         // Model switching submenu
         if !models.is_empty() {
             let model_submenu = Submenu::new("Switch Model", true);
+
+            // Models quant sessions used lately, for quick switching
+            let recent: Vec<String> = self
+                .state
+                .recent_models()
+                .into_iter()
+                .filter(|m| models.contains(m))
+                .collect();
+            if !recent.is_empty() {
+                model_submenu.append(&MenuItem::new("Recent", false, None))?;
+                for model in &recent {
+                    let is_current = current_model.as_ref() == Some(model);
+                    let item = CheckMenuItem::with_id(
+                        format!("model:{}", model),
+                        format!("  {}", model),
+                        true,
+                        is_current,
+                        None,
+                    );
+                    model_submenu.append(&item)?;
+                }
+                model_submenu.append(&PredefinedMenuItem::separator())?;
+            }
+
             for model in &models {
                 let is_current = current_model.as_ref() == Some(model);
                 let item = CheckMenuItem::with_id(
@@ -197,7 +247,11 @@ impl TrayManager {
         menu.append(&PredefinedMenuItem::separator())?;
 
         // Footer
-        let pull_item = MenuItem::with_id(ID_PULL_MODEL, "Pull Model...", true, None);
+        let pull = self.state.pull_progress();
+        if let Some(pull) = &pull {
+            menu.append(&Self::build_pull_submenu(pull)?)?;
+        }
+        let pull_item = MenuItem::with_id(ID_PULL_MODEL, "Pull Model...", pull.is_none(), None);
         menu.append(&pull_item)?;
 
         let logs_item = MenuItem::with_id(ID_VIEW_LOGS, "View Logs", true, None);
@@ -219,7 +273,71 @@ impl TrayManager {
         Ok(menu)
     }
 
-    pub fn update_menu(&mut self) -> Result<()> {
+    fn build_diagnostics_submenu(health: &HealthReport) -> Result<Submenu> {
+        let problems = health.problems().count();
+        let title = if problems == 0 {
+            "Diagnostics".to_string()
+        } else {
+            let plural = if problems == 1 { "" } else { "s" };
+            format!("{} Diagnostics ({} issue{})", health.overall().symbol(), problems, plural)
+        };
+
+        let submenu = Submenu::new(title, true);
+        for check in &health.checks {
+            let text = format!("{} {}: {}", check.status.symbol(), check.name, check.detail);
+            submenu.append(&MenuItem::new(text, false, None))?;
+        }
+        Ok(submenu)
+    }
+
+    fn build_sessions_submenu(sessions: &[SessionInfo]) -> Result<Submenu> {
+        let submenu = Submenu::new(format!("quant Sessions ({})", sessions.len()), true);
+        for session in sessions {
+            let kind = match session.kind {
+                SessionKind::Chat => "Chat",
+                SessionKind::Agent => "Agent",
+            };
+            let mut text = format!("{} · {}", kind, session.model);
+            if let Some(title) = &session.title {
+                let title: String = title.chars().take(40).collect();
+                text.push_str(&format!(" · {}", title));
+            }
+            // Only saved sessions can be reopened
+            let item = MenuItem::with_id(format!("session:{}", session.pid), text, session.resumable, None);
+            submenu.append(&item)?;
+        }
+        Ok(submenu)
+    }
+
+    fn build_pull_submenu(pull: &PullState) -> Result<Submenu> {
+        let title = match pull.percent() {
+            Some(percent) => format!("⇣ Pulling {} ({}%)", pull.model, percent),
+            None => format!("⇣ Pulling {}", pull.model),
+        };
+
+        let submenu = Submenu::new(title, true);
+        submenu.append(&MenuItem::new(format!("Status: {}", pull.status), false, None))?;
+        if pull.total > 0 {
+            let bytes = format!(
+                "{:.2} / {:.2} GB",
+                pull.completed as f64 / 1e9,
+                pull.total as f64 / 1e9
+            );
+            submenu.append(&MenuItem::new(bytes, false, None))?;
+        }
+        submenu.append(&PredefinedMenuItem::separator())?;
+        submenu.append(&MenuItem::with_id(ID_CANCEL_PULL, "Cancel Pull", true, None))?;
+        Ok(submenu)
+    }
+
+    /// Post notifications queued by background threads (main thread only)
+    fn deliver_notifications(&self) {
+        for notification in self.state.take_notifications() {
+            platform::notify(&notification.title, &notification.body);
+        }
+    }
+
+    fn update_menu(&mut self) -> Result<()> {
         if let Some(tray) = &self.tray_icon {
             let menu = self.build_menu()?;
             tray.set_menu(Some(Box::new(menu)));
@@ -227,11 +345,18 @@ impl TrayManager {
         Ok(())
     }
 
-    pub fn update_icon(&self) {
+    fn update_icon(&mut self) {
         if let Some(tray) = &self.tray_icon {
             let status = self.state.ollama_status();
             let sharing = self.state.tailscale_sharing();
 
+            if cfg!(target_os = "windows") && self.icon_status != Some(status) {
+                match status_icon(status).and_then(|icon| Ok(tray.set_icon(Some(icon))?)) {
+                    Ok(()) => self.icon_status = Some(status),
+                    Err(e) => tracing::warn!("Failed to draw tray icon: {}", e),
+                }
+            }
+
             let icon = match (status, sharing) {
                 (OllamaStatus::Running, true) => "●↗",
                 (OllamaStatus::Running, false) => "●",
@@ -240,7 +365,19 @@ impl TrayManager {
                 (OllamaStatus::Error, _) => "⊘",
             };
 
-            tray.set_title(Some(icon));
+            // Badge the icon when an environment check is failing
+            let health = self.state.health();
+            let title = match health.overall() {
+                HealthStatus::Ok => icon.to_string(),
+                badge => format!("{}{}", icon, badge.symbol()),
+            };
+            tray.set_title(Some(title));
+
+            let tooltip = match health.problems().next() {
+                Some(check) => format!("OllamaBar - {}: {}", check.name, check.detail),
+                None => "OllamaBar".to_string(),
+            };
+            let _ = tray.set_tooltip(Some(tooltip));
         }
     }
 
@@ -260,8 +397,26 @@ impl TrayManager {
         true
     }
 
+    /// Handle queued menu clicks, then refresh the menu and icon and post
+    /// notifications; returns true on Quit (main thread only)
+    pub fn tick(&mut self) -> bool {
+        while let Ok(event) = MenuEvent::receiver().try_recv() {
+            tracing::info!("Menu event: {:?}", event.id);
+            if self.handle_event(&event) {
+                return true;
+            }
+        }
+
+        self.deliver_notifications();
+        if let Err(e) = self.update_menu() {
+            tracing::warn!("Failed to update menu: {}", e);
+        }
+        self.update_icon();
+        false
+    }
+
     /// Handle a single menu event, returns true if should quit
-    pub fn handle_event(&self, event: &MenuEvent) -> bool {
+    fn handle_event(&self, event: &MenuEvent) -> bool {
         let id_str = event.id.0.as_str();
         tracing::info!("=== MENU EVENT ID: '{}' ===", id_str);
 
@@ -283,6 +438,7 @@ impl TrayManager {
             ID_TOGGLE_TAILSCALE => self.handle_toggle_tailscale(),
             ID_COPY_URL => self.handle_copy_url(),
             ID_PULL_MODEL => self.handle_pull_model(),
+            ID_CANCEL_PULL => self.state.cancel_pull(),
             ID_VIEW_LOGS => self.handle_view_logs(),
             ID_SETTINGS => self.handle_settings(),
             ID_QUIT => return true,
@@ -298,6 +454,10 @@ impl TrayManager {
                 let model = id.strip_prefix("start_with:").unwrap();
                 self.handle_start_with_model(model);
             }
+            id if id.starts_with("session:") => {
+                let pid = id.strip_prefix("session:").unwrap();
+                self.handle_open_session(pid);
+            }
             _ => {}
         }
 
@@ -305,6 +465,12 @@ impl TrayManager {
     }
 
     fn handle_start(&self) {
+        let default_model = self.state.settings().default_model;
+        if !default_model.is_empty() {
+            self.handle_start_with_model(&default_model);
+            return;
+        }
+
         tracing::info!("Starting Ollama...");
         let state = self.state.clone();
         std::thread::spawn(move || {
@@ -388,6 +554,19 @@ impl TrayManager {
         });
     }
 
+    /// Resume a quant session in a new terminal window
+    fn handle_open_session(&self, pid: &str) {
+        let Some(session) = self.state.sessions().into_iter().find(|s| s.pid.to_string() == pid) else {
+            tracing::warn!("Session {} has ended", pid);
+            return;
+        };
+        tracing::info!("Opening session {} in {:?}", session.id, session.working_dir);
+        if let Err(e) = platform::open_terminal(&session.working_dir, &session.resume_args()) {
+            tracing::error!("Failed to open terminal: {}", e);
+            self.state.notify("Couldn't open session", e.to_string());
+        }
+    }
+
     fn handle_repull_model(&self, model: &str) {
         tracing::info!("=== RE-PULL MODEL REQUESTED: '{}' ===", model);
         // Clear previous error
@@ -395,22 +574,27 @@ impl TrayManager {
             *err = None;
         }
 
+        self.spawn_pull(model.to_string());
+    }
+
+    /// Pull a model in the background; progress shows in the menu and the
+    /// result as a notification
+    fn spawn_pull(&self, model: String) {
         let state = self.state.clone();
-        let model = model.to_string();
         std::thread::spawn(move || {
             let rt = tokio::runtime::Runtime::new().unwrap();
             rt.block_on(async {
-                tracing::info!("Re-pulling model: {}", model);
                 match state.pull_model(&model).await {
-                    Ok(()) => {
-                        tracing::info!("Model re-pull completed: {}", model);
+                    Ok(PullOutcome::Completed) => {
+                        tracing::info!("Model pull completed: {}", model);
+                        state.notify("Model pulled", format!("{} is ready to use", model));
+                    }
+                    Ok(PullOutcome::Cancelled) => {
+                        state.notify("Pull cancelled", format!("Stopped pulling {}", model));
                     }
                     Err(e) => {
-                        let err_msg = format!("Pull failed for {}: {}", model, e);
-                        tracing::error!("{}", err_msg);
-                        if let Ok(mut last_err) = LAST_MODEL_ERROR.lock() {
-                            *last_err = Some(err_msg);
-                        }
+                        tracing::error!("Failed to pull model {}: {}", model, e);
+                        state.notify("Pull failed", format!("{}: {}", model, e));
                     }
                 }
             });
@@ -427,107 +611,51 @@ impl TrayManager {
     fn handle_copy_url(&self) {
         if let Some(url) = self.state.tailscale_serve_url() {
             tracing::info!("Copying URL: {}", url);
-            use std::io::Write;
-            if let Ok(mut child) = std::process::Command::new("pbcopy")
-                .stdin(std::process::Stdio::piped())
-                .spawn()
-            {
-                if let Some(stdin) = child.stdin.as_mut() {
-                    let _ = stdin.write_all(url.as_bytes());
-                }
-                let _ = child.wait();
+            if let Err(e) = platform::copy_to_clipboard(&url) {
+                tracing::error!("Failed to copy URL: {}", e);
             }
         }
     }
 
     fn handle_pull_model(&self) {
         tracing::info!("Pull model requested");
-
-        // Show macOS dialog to get model name using osascript
-        let script = r#"
-            set dialogResult to display dialog "Enter model name to pull (e.g., llama3.2, gemma2:2b):" default answer "" buttons {"Cancel", "Pull"} default button "Pull" with title "Pull Model"
-            if button returned of dialogResult is "Pull" then
-                return text returned of dialogResult
-            else
-                return ""
-            end if
-        "#;
-
-        let output = std::process::Command::new("osascript")
-            .args(["-e", script])
-            .output();
-
-        match output {
-            Ok(out) if out.status.success() => {
-                let model_name = String::from_utf8_lossy(&out.stdout).trim().to_string();
-                if !model_name.is_empty() {
-                    tracing::info!("Pulling model: {}", model_name);
-                    let state = self.state.clone();
-                    std::thread::spawn(move || {
-                        let rt = tokio::runtime::Runtime::new().unwrap();
-                        rt.block_on(async {
-                            match state.pull_model(&model_name).await {
-                                Ok(()) => {
-                                    tracing::info!("Model pull completed: {}", model_name);
-                                    // Show success notification
-                                    let _ = std::process::Command::new("osascript")
-                                        .args(["-e", &format!(
-                                            r#"display notification "Model {} pulled successfully" with title "OllamaBar""#,
-                                            model_name
-                                        )])
-                                        .spawn();
-                                }
-                                Err(e) => {
-                                    tracing::error!("Failed to pull model: {}", e);
-                                    // Show error notification
-                                    let _ = std::process::Command::new("osascript")
-                                        .args(["-e", &format!(
-                                            r#"display notification "Failed to pull {}: {}" with title "OllamaBar""#,
-                                            model_name, e
-                                        )])
-                                        .spawn();
-                                }
-                            }
-                        });
-                    });
-                }
-            }
-            Ok(_) => {
-                // User cancelled or dialog failed
-                tracing::debug!("Pull model dialog cancelled");
-            }
-            Err(e) => {
-                tracing::error!("Failed to show pull model dialog: {}", e);
-            }
+        if let Some(model_name) = platform::prompt_model_name() {
+            self.spawn_pull(model_name);
         }
     }
 
     fn handle_view_logs(&self) {
-        use crate::state::AppState;
-
         let log_path = AppState::ollama_log_path();
         tracing::info!("Opening logs at: {:?}", log_path);
-
-        if log_path.exists() {
-            // Open with Console app for nice log viewing
-            let _ = std::process::Command::new("open")
-                .args(["-a", "Console"])
-                .arg(&log_path)
-                .spawn();
-        } else {
-            // Log file doesn't exist yet - show a message
-            tracing::warn!("Log file doesn't exist yet: {:?}", log_path);
-            // Open Console app anyway so user can see it when logs appear
-            let _ = std::process::Command::new("open")
-                .args(["-a", "Console"])
-                .spawn();
-        }
+        platform::open_logs(&log_path);
     }
 
     fn handle_settings(&self) {
         tracing::info!("Opening settings...");
-        if let Ok(config_path) = llm_core::Config::find_config_path() {
-            let _ = std::process::Command::new("open").arg(config_path).spawn();
+        platform::show_preferences(self.state.clone());
+    }
+}
+
+/// A filled circle in the status colour, for trays that show no title
+fn status_icon(status: OllamaStatus) -> Result<Icon> {
+    const SIZE: u32 = 32;
+    let (r, g, b) = match status {
+        OllamaStatus::Running => (52, 199, 89),
+        OllamaStatus::Starting => (255, 159, 10),
+        OllamaStatus::Stopped => (142, 142, 147),
+        OllamaStatus::Error => (255, 59, 48),
+    };
+
+    let center = (SIZE as f64 - 1.0) / 2.0;
+    let radius = SIZE as f64 / 2.0 - 2.0;
+    let mut rgba = Vec::with_capacity((SIZE * SIZE * 4) as usize);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let distance = ((x as f64 - center).powi(2) + (y as f64 - center).powi(2)).sqrt();
+            // One pixel of antialiasing at the edge
+            let alpha = (radius - distance + 0.5).clamp(0.0, 1.0);
+            rgba.extend_from_slice(&[r, g, b, (alpha * 255.0) as u8]);
         }
     }
+    Ok(Icon::from_rgba(rgba, SIZE, SIZE)?)
 }
@@ -0,0 +1,132 @@
+//! Compaction of tool-heavy iterations
+//!
+//! Once the model has seen the results of an iteration with many tool calls,
+//! the verbose tool-result messages are replaced in the working history by a
+//! short structured summary. The full output stays in the agent transcript,
+//! which is what gets saved to the session file.
+
+use llm_core::{ChatMessageWithTools, Role};
+
+/// Results shorter than this are left as-is
+const MIN_COMPACT_CHARS: usize = 400;
+
+/// Number of leading lines kept as a preview
+const PREVIEW_LINES: usize = 3;
+
+/// Maximum characters kept in the preview
+const PREVIEW_CHARS: usize = 200;
+
+/// Marker at the start of compacted results
+pub const COMPACTED_MARKER: &str = "[compacted]";
+
+/// A tool result message recorded for later compaction
+#[derive(Debug, Clone)]
+pub struct ToolResultRecord {
+    /// Index of the tool message in the working history
+    pub index: usize,
+    /// Name of the tool that produced the result
+    pub tool: String,
+    /// Whether the tool succeeded
+    pub success: bool,
+}
+
+/// Build a compact summary of a tool result
+pub fn summarize_tool_result(tool: &str, success: bool, content: &str) -> String {
+    let status = if success { "ok" } else { "failed" };
+    let lines = content.lines().count();
+
+    let mut preview: String = content
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .take(PREVIEW_LINES)
+        .collect::<Vec<_>>()
+        .join("\n");
+    if preview.chars().count() > PREVIEW_CHARS {
+        preview = preview.chars().take(PREVIEW_CHARS).collect::<String>() + "...";
+    }
+
+    format!(
+        "{} tool={} status={} lines={} chars={}\n{}",
+        COMPACTED_MARKER,
+        tool,
+        status,
+        lines,
+        content.len(),
+        preview
+    )
+}
+
+/// Replace recorded tool results with compact summaries
+///
+/// Returns the number of characters removed from the history.
+pub fn compact_tool_results(messages: &mut [ChatMessageWithTools], records: &[ToolResultRecord]) -> usize {
+    let mut saved = 0;
+
+    for record in records {
+        let Some(msg) = messages.get_mut(record.index) else {
+            continue;
+        };
+        if msg.role != Role::Tool
+            || msg.content.len() < MIN_COMPACT_CHARS
+            || msg.content.starts_with(COMPACTED_MARKER)
+        {
+            continue;
+        }
+
+        let summary = summarize_tool_result(&record.tool, record.success, &msg.content);
+        saved += msg.content.len().saturating_sub(summary.len());
+        msg.content = summary;
+    }
+
+    saved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn long_output() -> String {
+        (0..100).map(|i| format!("line {}", i)).collect::<Vec<_>>().join("\n")
+    }
+
+    #[test]
+    fn test_summarize_tool_result() {
+        let summary = summarize_tool_result("grep", true, &long_output());
+        assert!(summary.starts_with("[compacted] tool=grep status=ok lines=100"));
+        assert!(summary.contains("line 0\nline 1\nline 2"));
+        assert!(!summary.contains("line 3"));
+    }
+
+    #[test]
+    fn test_compact_replaces_long_results() {
+        let mut messages = vec![
+            ChatMessageWithTools::tool_result("1", long_output()),
+            ChatMessageWithTools::tool_result("2", "short"),
+        ];
+        let records = vec![
+            ToolResultRecord { index: 0, tool: "file_read".to_string(), success: true },
+            ToolResultRecord { index: 1, tool: "glob".to_string(), success: false },
+        ];
+
+        let saved = compact_tool_results(&mut messages, &records);
+        assert!(saved > 0);
+        assert!(messages[0].content.starts_with(COMPACTED_MARKER));
+        assert_eq!(messages[0].tool_call_id.as_deref(), Some("1"));
+        assert_eq!(messages[1].content, "short");
+
+        // Compacting again is a no-op
+        assert_eq!(compact_tool_results(&mut messages, &records), 0);
+    }
+
+    #[test]
+    fn test_compact_ignores_non_tool_messages() {
+        let mut messages = vec![ChatMessageWithTools {
+            role: Role::Assistant,
+            content: long_output(),
+            tool_calls: None,
+            tool_call_id: None,
+        }];
+        let records = vec![ToolResultRecord { index: 0, tool: "bash".to_string(), success: true }];
+        assert_eq!(compact_tool_results(&mut messages, &records), 0);
+    }
+}
@@ -0,0 +1,241 @@
+//! Failure reports for runs that end without finishing the task
+//!
+//! When a run stops on an error, the iteration limit or repeated tool
+//! failures, the report says what was tried, which tools failed and with
+//! what, and what to do next. It is printed in place of the bare error and
+//! saved with the session.
+
+use serde::{Deserialize, Serialize};
+
+use super::plan::StepStatus;
+use super::state::AgentState;
+
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[31m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// Most recent tool errors included in a report
+const MAX_LAST_ERRORS: usize = 3;
+
+/// Characters of each tool error kept in a report
+const ERROR_EXCERPT_CHARS: usize = 200;
+
+/// Calls made to one tool during a run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolTally {
+    pub tool: String,
+    pub calls: usize,
+    pub failures: usize,
+}
+
+/// A failed tool call
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolError {
+    pub iteration: usize,
+    pub tool: String,
+    pub error: String,
+}
+
+/// Why a run gave up, and what to try next
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailureReport {
+    /// The error that ended the run
+    pub reason: String,
+    pub iterations: usize,
+    /// Tools called, in order of first use
+    pub attempted: Vec<ToolTally>,
+    /// The last few tool errors, oldest first
+    pub last_errors: Vec<ToolError>,
+    /// First plan step not done, when running a plan
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unfinished_step: Option<String>,
+    pub next_steps: Vec<String>,
+}
+
+impl FailureReport {
+    /// Build a report for a run that ended in an error; `None` if it didn't
+    pub fn from_state(state: &AgentState, max_iterations: usize) -> Option<Self> {
+        let reason = state.error.clone()?;
+
+        let mut attempted: Vec<ToolTally> = Vec::new();
+        for call in &state.tool_calls {
+            let index = match attempted.iter().position(|t| t.tool == call.tool) {
+                Some(index) => index,
+                None => {
+                    attempted.push(ToolTally { tool: call.tool.clone(), calls: 0, failures: 0 });
+                    attempted.len() - 1
+                }
+            };
+            attempted[index].calls += 1;
+            if call.error.is_some() {
+                attempted[index].failures += 1;
+            }
+        }
+
+        let errors: Vec<ToolError> = state
+            .tool_calls
+            .iter()
+            .filter_map(|call| {
+                call.error.as_ref().map(|error| ToolError {
+                    iteration: call.iteration,
+                    tool: call.tool.clone(),
+                    error: excerpt(error),
+                })
+            })
+            .collect();
+        let last_errors = errors[errors.len().saturating_sub(MAX_LAST_ERRORS)..].to_vec();
+
+        let unfinished_step = state.plan.as_ref().and_then(|plan| {
+            plan.steps
+                .iter()
+                .position(|s| s.status != StepStatus::Done)
+                .map(|i| format!("{}. {}", i + 1, plan.steps[i].description))
+        });
+
+        let mut report = Self {
+            reason,
+            iterations: state.iteration,
+            attempted,
+            last_errors,
+            unfinished_step,
+            next_steps: Vec::new(),
+        };
+        report.next_steps = report.suggest(max_iterations, state.error_hint.as_deref());
+        Some(report)
+    }
+
+    /// Next steps for the kind of failure; `hint` comes from a classified LLM error
+    fn suggest(&self, max_iterations: usize, hint: Option<&str>) -> Vec<String> {
+        let mut steps = Vec::new();
+        let reason = self.reason.as_str();
+
+        if reason.contains("maximum iterations") {
+            steps.push(format!(
+                "Raise --max-iterations (was {}) or split the task into smaller ones",
+                max_iterations
+            ));
+            if self.attempted.iter().all(|t| t.failures == 0) && !self.attempted.is_empty() {
+                steps.push("Every tool call succeeded; say in the task when it counts as done".to_string());
+            }
+        } else if reason.contains("consecutive times") {
+            let tool = self.last_errors.last().map(|e| e.tool.as_str()).unwrap_or("the tool");
+            steps.push(format!(
+                "The model repeated the same failing {} call; fix the cause of the error above or give it in the task",
+                tool
+            ));
+        } else if reason.starts_with("LLM error") || reason.starts_with("Stream error") {
+            match hint {
+                Some(hint) => steps.push(hint.to_string()),
+                None => steps.push("Check that Ollama is running and the model is pulled (`quant doctor`)".to_string()),
+            }
+            steps.push("Set agent.fallback_model to switch models after repeated errors".to_string());
+        } else if reason.contains("aborted by user") {
+            return steps;
+        } else if reason.contains("hook") {
+            steps.push("Fix the failing hook or remove it from the hooks config".to_string());
+        }
+
+        if self.last_errors.iter().any(|e| e.error.contains("denied by user")) {
+            steps.push("Some tool calls were denied; run with --auto or trust them when prompted".to_string());
+        }
+        if self.last_errors.iter().any(|e| e.error.starts_with("Tool not found")) {
+            steps.push("The model called tools that don't exist; try a model with better tool support".to_string());
+        }
+        if let Some(step) = &self.unfinished_step {
+            steps.push(format!("Resume the session to continue from plan step {}", step));
+        }
+        steps
+    }
+
+    /// The report for the terminal
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "{}{}Run failed{} after {} iterations: {}\n",
+            BOLD, RED, RESET, self.iterations, self.reason
+        );
+
+        if !self.attempted.is_empty() {
+            let tools: Vec<String> = self
+                .attempted
+                .iter()
+                .map(|t| match t.failures {
+                    0 => format!("{} ×{}", t.tool, t.calls),
+                    failures => format!("{} ×{} ({} failed)", t.tool, t.calls, failures),
+                })
+                .collect();
+            out.push_str(&format!("\n{}Attempted:{} {}\n", BOLD, RESET, tools.join(", ")));
+        }
+
+        if !self.last_errors.is_empty() {
+            out.push_str(&format!("\n{}Last errors:{}\n", BOLD, RESET));
+            for error in &self.last_errors {
+                out.push_str(&format!(
+                    "  {}[{}] {}{} {}\n",
+                    DIM, error.iteration, error.tool, RESET, error.error
+                ));
+            }
+        }
+
+        if let Some(step) = &self.unfinished_step {
+            out.push_str(&format!("\n{}Stopped at plan step:{} {}\n", BOLD, RESET, step));
+        }
+
+        if !self.next_steps.is_empty() {
+            out.push_str(&format!("\n{}Next steps:{}\n", BOLD, RESET));
+            for step in &self.next_steps {
+                out.push_str(&format!("  - {}\n", step));
+            }
+        }
+        out
+    }
+}
+
+/// The start of a tool error, on one line
+fn excerpt(error: &str) -> String {
+    let line = error.split_whitespace().collect::<Vec<_>>().join(" ");
+    if line.chars().count() <= ERROR_EXCERPT_CHARS {
+        return line;
+    }
+    let cut: String = line.chars().take(ERROR_EXCERPT_CHARS).collect();
+    format!("{}...", cut)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_for_max_iterations() {
+        let mut state = AgentState::new();
+        state.iteration = 20;
+        state.record_tool_call("grep", "ok", "3 matches");
+        state.record_tool_call("bash", "failed", "error: could not compile `core`\n\n  --> src/lib.rs");
+        state.record_tool_call("grep", "ok", "1 match");
+        state.mark_error("Agent reached maximum iterations (20)".to_string());
+
+        let report = FailureReport::from_state(&state, 20).unwrap();
+        assert_eq!(
+            report.attempted,
+            vec![
+                ToolTally { tool: "grep".to_string(), calls: 2, failures: 0 },
+                ToolTally { tool: "bash".to_string(), calls: 1, failures: 1 },
+            ]
+        );
+        assert_eq!(report.last_errors[0].error, "error: could not compile `core` --> src/lib.rs");
+        assert!(report.next_steps[0].contains("--max-iterations (was 20)"));
+        assert!(report.render().contains("bash ×1 (1 failed)"));
+
+        assert!(FailureReport::from_state(&AgentState::new(), 20).is_none());
+    }
+
+    #[test]
+    fn test_report_uses_llm_error_hint() {
+        let mut state = AgentState::new();
+        state.mark_error("LLM error: Model 'qwen3:8b' is not installed".to_string());
+        state.error_hint = Some("Pull the model with `quant models pull qwen3:8b`".to_string());
+
+        let report = FailureReport::from_state(&state, 20).unwrap();
+        assert_eq!(report.next_steps[0], "Pull the model with `quant models pull qwen3:8b`");
+    }
+}
@@ -0,0 +1,172 @@
+//! Interrupt handling for running agents
+//!
+//! Pressing Ctrl+C during an agent run requests a pause. The agent finishes the
+//! tool it is currently executing, then prompts for an additional instruction
+//! which is injected as a user message before the next iteration. Pressing
+//! Ctrl+C again while a pause is pending aborts the run: the agent stops at the
+//! next chunk of the response or before the next tool call, and the run ends
+//! the usual way, so the session is saved and hooks run.
+
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+// ANSI colors
+const YELLOW: &str = "\x1b[93m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// What to do after the user has been prompted during a pause
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterruptAction {
+    /// Resume without changes
+    Continue,
+    /// Inject an instruction as a user message, then resume
+    Inject(String),
+    /// Stop the agent run
+    Abort,
+}
+
+impl InterruptAction {
+    /// Parse the line typed at the pause prompt
+    pub fn parse(input: &str) -> Self {
+        let trimmed = input.trim();
+        match trimmed.to_lowercase().as_str() {
+            "" | "c" | "continue" => InterruptAction::Continue,
+            "a" | "abort" | "q" | "quit" => InterruptAction::Abort,
+            _ => InterruptAction::Inject(trimmed.to_string()),
+        }
+    }
+}
+
+/// Listens for Ctrl+C and records pause and abort requests for the agent loop
+pub struct InterruptController {
+    pause_requested: Arc<AtomicBool>,
+    abort_requested: Arc<AtomicBool>,
+    listener: Option<JoinHandle<()>>,
+}
+
+impl InterruptController {
+    /// Create a controller without a signal listener
+    pub fn new() -> Self {
+        Self {
+            pause_requested: Arc::new(AtomicBool::new(false)),
+            abort_requested: Arc::new(AtomicBool::new(false)),
+            listener: None,
+        }
+    }
+
+    /// Start listening for Ctrl+C in the background
+    pub fn listen(mut self) -> Self {
+        let pause_requested = Arc::clone(&self.pause_requested);
+        let abort_requested = Arc::clone(&self.abort_requested);
+
+        self.listener = Some(tokio::spawn(async move {
+            while tokio::signal::ctrl_c().await.is_ok() {
+                if pause_requested.swap(true, Ordering::SeqCst) {
+                    // Second press while a pause is pending: stop the run
+                    abort_requested.store(true, Ordering::SeqCst);
+                    eprintln!("\n{}[Interrupt]{} Aborting the agent run", YELLOW, RESET);
+                    continue;
+                }
+                eprintln!(
+                    "\n{}[Interrupt]{} Pausing after the current step (Ctrl+C again to abort)",
+                    YELLOW, RESET
+                );
+            }
+        }));
+
+        self
+    }
+
+    /// Whether a pause has been requested and not yet handled
+    pub fn is_pause_requested(&self) -> bool {
+        self.pause_requested.load(Ordering::SeqCst)
+    }
+
+    /// Whether the run should stop
+    pub fn is_abort_requested(&self) -> bool {
+        self.abort_requested.load(Ordering::SeqCst)
+    }
+
+    /// Prompt the user for an instruction and clear the pause request
+    pub async fn prompt(&self) -> InterruptAction {
+        println!();
+        println!("{}Agent paused.{}", BOLD, RESET);
+        println!(
+            "{}Type an instruction to inject, press Enter to continue, or 'abort' to stop.{}",
+            DIM, RESET
+        );
+        print!("> ");
+        let _ = io::stdout().flush();
+
+        let mut reader = BufReader::new(tokio::io::stdin());
+        let mut input = String::new();
+        let action = match reader.read_line(&mut input).await {
+            // Ctrl+C at the prompt aborts whatever was typed
+            _ if self.is_abort_requested() => InterruptAction::Abort,
+            Ok(0) | Err(_) => InterruptAction::Continue,
+            Ok(_) => InterruptAction::parse(&input),
+        };
+
+        debug!(action = ?action, "Interrupt prompt answered");
+        self.pause_requested.store(false, Ordering::SeqCst);
+        action
+    }
+}
+
+impl Default for InterruptController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for InterruptController {
+    fn drop(&mut self) {
+        if let Some(handle) = self.listener.take() {
+            handle.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_continue() {
+        assert_eq!(InterruptAction::parse(""), InterruptAction::Continue);
+        assert_eq!(InterruptAction::parse("  \n"), InterruptAction::Continue);
+        assert_eq!(InterruptAction::parse("continue"), InterruptAction::Continue);
+    }
+
+    #[test]
+    fn test_parse_abort() {
+        assert_eq!(InterruptAction::parse("abort"), InterruptAction::Abort);
+        assert_eq!(InterruptAction::parse("Q\n"), InterruptAction::Abort);
+    }
+
+    #[test]
+    fn test_parse_inject() {
+        assert_eq!(
+            InterruptAction::parse("  use the tests in tests/ instead\n"),
+            InterruptAction::Inject("use the tests in tests/ instead".to_string())
+        );
+    }
+
+    #[test]
+    fn test_request_pause() {
+        let controller = InterruptController::new();
+        assert!(!controller.is_pause_requested());
+        controller.pause_requested.store(true, Ordering::SeqCst);
+        assert!(controller.is_pause_requested());
+        assert!(!controller.is_abort_requested());
+        controller.abort_requested.store(true, Ordering::SeqCst);
+        assert!(controller.is_abort_requested());
+    }
+}
@@ -3,7 +3,19 @@
 //! Implements an observe-think-act loop similar to Claude Code.
 
 mod agent_loop;
+mod compaction;
+mod failure;
+mod interrupt;
+mod plan;
+mod replay;
+pub mod shadow;
 mod state;
+mod team;
+mod tool_selection;
 
-pub use agent_loop::AgentLoop;
-pub use state::{AgentConfig, AgentState};
+pub use agent_loop::{run_session_save_hooks, AgentLoop};
+pub use failure::FailureReport;
+pub use plan::Plan;
+pub use replay::{RecordedStep, Replay};
+pub use state::{AgentConfig, AgentSnapshot, AgentState, ModelRestart, ModelSwitch, DEFAULT_MAX_REPEATED_RESPONSES};
+pub use team::{parse_team, RoleConfig, TeamCoordinator};
@@ -0,0 +1,304 @@
+//! Planning mode: explicit task decomposition before execution
+//!
+//! The agent first asks the model for a structured plan (a list of steps with
+//! optional tool hints). Once approved, the plan is included in the system
+//! prompt and progress is tracked as the model reports completed steps.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+// ANSI colors
+const GREEN: &str = "\x1b[92m";
+const YELLOW: &str = "\x1b[93m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// Heading that starts the plan section of the system prompt
+pub const PLAN_HEADING: &str = "## Plan\n";
+
+/// `[step N done]` markers in assistant output
+static STEP_DONE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\[\s*step\s+(\d+)\s+(?:done|complete|completed)\s*\]").expect("valid step marker pattern"));
+
+/// Numbered (`1. ...`) or bulleted (`- ...`) list items
+static LIST_ITEM: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*(?:\d+[.)]|[-*])\s+(.+)$").expect("valid list item pattern"));
+
+/// Progress of a single plan step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    #[default]
+    Pending,
+    InProgress,
+    Done,
+}
+
+/// A single step of a plan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanStep {
+    /// What the step accomplishes
+    pub description: String,
+    /// Tool the model expects to use for this step
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    /// Current status
+    #[serde(default)]
+    pub status: StepStatus,
+}
+
+impl PlanStep {
+    pub fn new(description: impl Into<String>) -> Self {
+        Self {
+            description: description.into(),
+            tool: None,
+            status: StepStatus::Pending,
+        }
+    }
+}
+
+/// An ordered list of steps for completing a task
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Plan {
+    pub steps: Vec<PlanStep>,
+}
+
+/// Prompt used to ask the model for a plan
+pub const PLANNING_PROMPT: &str = r#"Before doing any work, break the task below into a short plan.
+
+Respond with ONLY a JSON array of steps, no other text. Each step has:
+- "step": a one-line description of what to do
+- "tool": the tool you expect to use (optional)
+
+Example:
+[{"step": "Find the config loader", "tool": "grep"}, {"step": "Add the new field", "tool": "multi_edit"}]
+
+Task:"#;
+
+impl Plan {
+    /// Parse a plan from model output
+    ///
+    /// Accepts a JSON array of steps (optionally inside a code block or a
+    /// `{"steps": [...]}` object), falling back to a numbered or bulleted list.
+    pub fn parse(content: &str) -> Option<Self> {
+        #[derive(Deserialize)]
+        struct StepJson {
+            #[serde(alias = "description", alias = "task")]
+            step: String,
+            #[serde(default)]
+            tool: Option<String>,
+        }
+
+        #[derive(Deserialize)]
+        struct PlanJson {
+            steps: Vec<StepJson>,
+        }
+
+        let json = extract_json(content);
+        let parsed = serde_json::from_str::<Vec<StepJson>>(json)
+            .ok()
+            .or_else(|| serde_json::from_str::<PlanJson>(json).ok().map(|p| p.steps));
+
+        let steps: Vec<PlanStep> = match parsed {
+            Some(steps) => steps
+                .into_iter()
+                .filter(|s| !s.step.trim().is_empty())
+                .map(|s| PlanStep {
+                    description: s.step.trim().to_string(),
+                    tool: s.tool.filter(|t| !t.is_empty()),
+                    status: StepStatus::Pending,
+                })
+                .collect(),
+            None => parse_list(content),
+        };
+
+        if steps.is_empty() {
+            None
+        } else {
+            Some(Self { steps })
+        }
+    }
+
+    /// Number of completed steps
+    pub fn completed(&self) -> usize {
+        self.steps.iter().filter(|s| s.status == StepStatus::Done).count()
+    }
+
+    /// Whether all steps are done
+    pub fn is_complete(&self) -> bool {
+        self.completed() == self.steps.len()
+    }
+
+    /// Mark a step (1-based) as done and start the next pending one
+    pub fn mark_done(&mut self, number: usize) -> bool {
+        let Some(step) = number.checked_sub(1).and_then(|i| self.steps.get_mut(i)) else {
+            return false;
+        };
+        if step.status == StepStatus::Done {
+            return false;
+        }
+        step.status = StepStatus::Done;
+        self.start_next();
+        true
+    }
+
+    /// Mark the first pending step as in progress (if none is)
+    pub fn start_next(&mut self) {
+        if self.steps.iter().any(|s| s.status == StepStatus::InProgress) {
+            return;
+        }
+        if let Some(step) = self.steps.iter_mut().find(|s| s.status == StepStatus::Pending) {
+            step.status = StepStatus::InProgress;
+        }
+    }
+
+    /// Update progress from `[step N done]` markers in assistant output
+    ///
+    /// Returns the step numbers that were newly completed.
+    pub fn update_from_output(&mut self, content: &str) -> Vec<usize> {
+        let mut completed = Vec::new();
+        for cap in STEP_DONE.captures_iter(content) {
+            if let Ok(n) = cap[1].parse::<usize>() {
+                if self.mark_done(n) {
+                    completed.push(n);
+                }
+            }
+        }
+        completed
+    }
+
+    /// Render the plan as a terminal checklist
+    pub fn render_checklist(&self) -> String {
+        let mut out = String::new();
+        for (i, step) in self.steps.iter().enumerate() {
+            let (mark, color) = match step.status {
+                StepStatus::Done => ("[x]", GREEN),
+                StepStatus::InProgress => ("[>]", YELLOW),
+                StepStatus::Pending => ("[ ]", DIM),
+            };
+            out.push_str(&format!("  {}{}{} {}. {}", color, mark, RESET, i + 1, step.description));
+            if let Some(ref tool) = step.tool {
+                out.push_str(&format!(" {}({}){}", DIM, tool, RESET));
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Format the plan for inclusion in the system prompt
+    pub fn to_system_context(&self) -> String {
+        let mut out = String::from(PLAN_HEADING);
+        out.push_str("Work through these steps in order:\n");
+        for (i, step) in self.steps.iter().enumerate() {
+            let mark = if step.status == StepStatus::Done { "x" } else { " " };
+            out.push_str(&format!("- [{}] {}. {}", mark, i + 1, step.description));
+            if let Some(ref tool) = step.tool {
+                out.push_str(&format!(" (tool: {})", tool));
+            }
+            out.push('\n');
+        }
+        out.push_str("\nWhen you finish a step, say `[step N done]` (e.g. `[step 1 done]`).\n\n");
+        out
+    }
+}
+
+/// Extract JSON from a markdown code block, or return the trimmed content
+fn extract_json(content: &str) -> &str {
+    let content = content.trim();
+    for pattern in ["```json\n", "```JSON\n", "```\n"] {
+        if let Some(start) = content.find(pattern) {
+            let json_start = start + pattern.len();
+            if let Some(end) = content[json_start..].find("```") {
+                return content[json_start..json_start + end].trim();
+            }
+        }
+    }
+    content
+}
+
+/// Parse numbered (`1. ...`) or bulleted (`- ...`) lines into steps
+fn parse_list(content: &str) -> Vec<PlanStep> {
+    content
+        .lines()
+        .filter_map(|line| LIST_ITEM.captures(line))
+        .map(|cap| PlanStep::new(cap[1].trim()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_json_array() {
+        let plan = Plan::parse(r#"[{"step": "Read config", "tool": "file_read"}, {"step": "Edit it"}]"#).unwrap();
+        assert_eq!(plan.steps.len(), 2);
+        assert_eq!(plan.steps[0].tool.as_deref(), Some("file_read"));
+        assert!(plan.steps[1].tool.is_none());
+    }
+
+    #[test]
+    fn test_parse_code_block_object() {
+        let content = "Here is the plan:\n```json\n{\"steps\": [{\"description\": \"Run tests\", \"tool\": \"bash\"}]}\n```";
+        let plan = Plan::parse(content).unwrap();
+        assert_eq!(plan.steps.len(), 1);
+        assert_eq!(plan.steps[0].description, "Run tests");
+    }
+
+    #[test]
+    fn test_parse_numbered_list() {
+        let plan = Plan::parse("1. Find the bug\n2) Fix it\n- Run tests").unwrap();
+        assert_eq!(plan.steps.len(), 3);
+        assert_eq!(plan.steps[1].description, "Fix it");
+    }
+
+    #[test]
+    fn test_parse_empty() {
+        assert!(Plan::parse("I can't plan this.").is_none());
+        assert!(Plan::parse("[]").is_none());
+    }
+
+    #[test]
+    fn test_progress_tracking() {
+        let mut plan = Plan {
+            steps: vec![PlanStep::new("a"), PlanStep::new("b"), PlanStep::new("c")],
+        };
+        plan.start_next();
+        assert_eq!(plan.steps[0].status, StepStatus::InProgress);
+
+        let done = plan.update_from_output("Finished reading. [step 1 done] [Step 2 Complete]");
+        assert_eq!(done, vec![1, 2]);
+        assert_eq!(plan.steps[2].status, StepStatus::InProgress);
+        assert_eq!(plan.completed(), 2);
+        assert!(!plan.is_complete());
+
+        // Repeated or out-of-range markers are ignored
+        assert!(plan.update_from_output("[step 1 done] [step 9 done]").is_empty());
+        assert!(plan.mark_done(3));
+        assert!(plan.is_complete());
+    }
+
+    #[test]
+    fn test_system_context() {
+        let mut plan = Plan {
+            steps: vec![PlanStep::new("a"), PlanStep::new("b")],
+        };
+        plan.steps[0].tool = Some("grep".to_string());
+        plan.mark_done(1);
+        let ctx = plan.to_system_context();
+        assert!(ctx.contains("- [x] 1. a (tool: grep)"));
+        assert!(ctx.contains("- [ ] 2. b"));
+    }
+
+    #[test]
+    fn test_plan_roundtrip() {
+        let mut plan = Plan {
+            steps: vec![PlanStep::new("a")],
+        };
+        plan.steps[0].tool = Some("bash".to_string());
+        let json = serde_json::to_string(&plan).unwrap();
+        let loaded: Plan = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.steps[0].tool.as_deref(), Some("bash"));
+        assert_eq!(loaded.steps[0].status, StepStatus::Pending);
+    }
+}
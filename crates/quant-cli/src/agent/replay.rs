@@ -0,0 +1,403 @@
+//! Recording agent runs and replaying them without the model
+//!
+//! Each model response and tool outcome of a run is recorded as a
+//! [`RecordedStep`] and saved with the session. `quant sessions replay` feeds
+//! them back to the agent loop in place of Ollama and the tools, so a run can
+//! be stepped through after the fact, and points out where the loop now
+//! decides differently than it did when the run was recorded.
+
+use std::collections::VecDeque;
+use std::io::{stdin, stdout, Write};
+
+use anyhow::Result;
+use futures::stream;
+use llm_core::{ChatChunkMessageWithTools, ChatChunkWithTools, ChatMessageWithTools, ChatStreamWithTools, Role, ToolCall as LlmToolCall};
+use serde::{Deserialize, Serialize};
+
+use crate::tools::router::RouteResult;
+use crate::tools::{ToolCall, ToolResult};
+
+const BOLD: &str = "\x1b[1m";
+const YELLOW: &str = "\x1b[93m";
+const CYAN: &str = "\x1b[96m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// Characters of a response shown ahead of a replayed iteration
+const PREVIEW_CHARS: usize = 200;
+
+/// A model response or tool outcome recorded during an agent run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedStep {
+    /// Agent run within the session, from 1 (set when saved)
+    #[serde(default)]
+    pub run: usize,
+    pub iteration: usize,
+    #[serde(flatten)]
+    pub event: StepEvent,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StepEvent {
+    /// The model's response as it streamed in, before any processing
+    Response {
+        model: String,
+        content: String,
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        thinking: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tool_calls: Vec<LlmToolCall>,
+        /// Error that cut the stream short
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+        prompt_tokens: u32,
+        completion_tokens: u32,
+        total_duration: u64,
+        eval_duration: u64,
+    },
+    /// The request failed before the model answered
+    RequestFailed { error: String },
+    /// How a tool call turned out
+    Tool {
+        name: String,
+        arguments: serde_json::Value,
+        /// `ok`, `failed`, `skipped`, `denied`, `aborted`, `not_found` or `error`
+        status: String,
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        output: String,
+    },
+}
+
+impl StepEvent {
+    /// Record how `call` was routed
+    pub fn tool(call: &ToolCall, result: &RouteResult) -> Self {
+        let (status, output) = match result {
+            RouteResult::Success(r) if r.success => ("ok", r.output.clone()),
+            RouteResult::Success(r) => ("failed", r.output.clone()),
+            RouteResult::Skipped => ("skipped", String::new()),
+            RouteResult::Denied => ("denied", String::new()),
+            RouteResult::Aborted => ("aborted", String::new()),
+            RouteResult::NotFound(name) => ("not_found", name.clone()),
+            RouteResult::Error(e) => ("error", e.clone()),
+        };
+        StepEvent::Tool {
+            name: call.name.clone(),
+            arguments: call.arguments.clone(),
+            status: status.to_string(),
+            output,
+        }
+    }
+}
+
+/// What the user chose before a replayed iteration
+enum StepChoice {
+    Next,
+    Continue,
+    Quit,
+}
+
+/// Recorded steps of one run, handed to the agent loop in order
+pub struct Replay {
+    steps: VecDeque<RecordedStep>,
+    /// Pause before each iteration
+    stepping: bool,
+    /// The user quit the replay
+    stopped: bool,
+    /// Where the loop did something the recording doesn't have
+    divergences: Vec<String>,
+}
+
+impl Replay {
+    pub fn new(steps: Vec<RecordedStep>, stepping: bool) -> Self {
+        Self {
+            steps: steps.into(),
+            stepping,
+            stopped: false,
+            divergences: Vec::new(),
+        }
+    }
+
+    /// Points where the replayed loop and the recording went different ways
+    pub fn divergences(&self) -> &[String] {
+        &self.divergences
+    }
+
+    /// Whether the user quit before the replay ended
+    pub fn stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Recorded steps the replayed loop never asked for
+    pub fn remaining(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Show what the next iteration will get and, when stepping, wait for
+    /// the user; returns false to stop the replay
+    pub fn pause(&mut self, iteration: usize, messages: &[ChatMessageWithTools]) -> Result<bool> {
+        println!("\n{}[Replay] Iteration {}{}", BOLD, iteration, RESET);
+        let next = self.steps.iter().find(|s| !matches!(s.event, StepEvent::Tool { .. }));
+        match next.map(|s| &s.event) {
+            Some(StepEvent::Response { content, tool_calls, .. }) => {
+                let preview: String = content.trim().chars().take(PREVIEW_CHARS).collect();
+                if !preview.is_empty() {
+                    println!("  {}Response:{} {}", DIM, RESET, preview.replace('\n', " "));
+                }
+                for call in tool_calls {
+                    println!("  {}-> {}{} {}", CYAN, call.function.name, RESET, call.function.arguments);
+                }
+            }
+            Some(StepEvent::RequestFailed { error }) => println!("  {}Request failed:{} {}", YELLOW, RESET, error),
+            _ => println!("  {}The recording ends here{}", DIM, RESET),
+        }
+
+        while self.stepping {
+            match prompt_step()? {
+                Some(StepChoice::Next) => break,
+                Some(StepChoice::Continue) => self.stepping = false,
+                Some(StepChoice::Quit) => {
+                    self.stopped = true;
+                    return Ok(false);
+                }
+                None => print_messages(messages),
+            }
+        }
+        Ok(true)
+    }
+
+    /// The recorded response for an iteration, as a stream like Ollama's
+    pub fn open_stream(&mut self, iteration: usize) -> Result<ChatStreamWithTools> {
+        self.skip_unused_tools();
+        let Some(step) = self.steps.pop_front() else {
+            anyhow::bail!("The recording ends before iteration {}", iteration);
+        };
+        if step.iteration != iteration {
+            self.diverge(format!(
+                "Iteration {} replayed the response recorded for iteration {}",
+                iteration, step.iteration
+            ));
+        }
+        let (model, content, thinking, tool_calls, error, usage) = match step.event {
+            StepEvent::Response {
+                model,
+                content,
+                thinking,
+                tool_calls,
+                error,
+                prompt_tokens,
+                completion_tokens,
+                total_duration,
+                eval_duration,
+            } => (model, content, thinking, tool_calls, error, (prompt_tokens, completion_tokens, total_duration, eval_duration)),
+            StepEvent::RequestFailed { error } => anyhow::bail!(error),
+            StepEvent::Tool { .. } => unreachable!("tool steps are skipped above"),
+        };
+
+        let chunk = ChatChunkWithTools {
+            model: model.clone(),
+            message: Some(ChatChunkMessageWithTools {
+                role: Role::Assistant,
+                content,
+                thinking,
+                tool_calls,
+            }),
+            done: false,
+            total_duration: None,
+            prompt_eval_count: None,
+            eval_count: None,
+            eval_duration: None,
+        };
+        let last = match error {
+            Some(error) => Err(anyhow::anyhow!(error)),
+            None => Ok(ChatChunkWithTools {
+                model,
+                message: None,
+                done: true,
+                total_duration: Some(usage.2),
+                prompt_eval_count: Some(usage.0),
+                eval_count: Some(usage.1),
+                eval_duration: Some(usage.3),
+            }),
+        };
+        Ok(Box::pin(stream::iter([Ok(chunk), last])))
+    }
+
+    /// The recorded outcome of a tool call, or an error if the recording has
+    /// a different call next
+    pub fn route(&mut self, iteration: usize, call: &ToolCall) -> RouteResult {
+        let recorded = match self.steps.front() {
+            Some(RecordedStep { iteration: i, event: StepEvent::Tool { name, arguments, .. }, .. })
+                if *i == iteration && *name == call.name && *arguments == call.arguments =>
+            {
+                self.steps.pop_front()
+            }
+            Some(RecordedStep { iteration: i, event: StepEvent::Tool { name, arguments, .. }, .. }) if *i == iteration => {
+                let message = format!("Iteration {} called {} {}; the recording has {} {}", iteration, call.name, call.arguments, name, arguments);
+                self.diverge(message);
+                None
+            }
+            _ => {
+                self.diverge(format!("Iteration {} called {} {}, which the recording doesn't have", iteration, call.name, call.arguments));
+                None
+            }
+        };
+        let Some(RecordedStep { event: StepEvent::Tool { name, status, output, .. }, .. }) = recorded else {
+            return RouteResult::Error("Not in the recording being replayed".to_string());
+        };
+        match status.as_str() {
+            "ok" => RouteResult::Success(ToolResult::success(output)),
+            "failed" => RouteResult::Success(ToolResult::failure(output, "failed")),
+            "skipped" => RouteResult::Skipped,
+            "denied" => RouteResult::Denied,
+            "aborted" => RouteResult::Aborted,
+            "not_found" => RouteResult::NotFound(name),
+            _ => RouteResult::Error(output),
+        }
+    }
+
+    /// Drop tool steps left before the next response, which the replayed
+    /// loop didn't call
+    fn skip_unused_tools(&mut self) {
+        while let Some(RecordedStep { iteration: i, event: StepEvent::Tool { name, arguments, .. }, .. }) = self.steps.front() {
+            let message = format!("Iteration {} didn't call {} {}, which the recording has", i, name, arguments);
+            self.steps.pop_front();
+            self.diverge(message);
+        }
+    }
+
+    fn diverge(&mut self, message: String) {
+        println!("{}[Replay] Diverged:{} {}", YELLOW, RESET, message);
+        self.divergences.push(message);
+    }
+}
+
+/// Ask what to do before the next iteration; `None` asks for the messages
+fn prompt_step() -> Result<Option<StepChoice>> {
+    print!("{}[Enter] step  [m] messages  [c] continue  [q] quit:{} ", DIM, RESET);
+    stdout().flush()?;
+    let mut answer = String::new();
+    if stdin().read_line(&mut answer)? == 0 {
+        return Ok(Some(StepChoice::Continue));
+    }
+    Ok(match answer.trim().to_lowercase().as_str() {
+        "m" => None,
+        "c" => Some(StepChoice::Continue),
+        "q" => Some(StepChoice::Quit),
+        _ => Some(StepChoice::Next),
+    })
+}
+
+/// Print the messages the model would be sent next
+fn print_messages(messages: &[ChatMessageWithTools]) {
+    for (i, msg) in messages.iter().enumerate() {
+        let role = format!("{:?}", msg.role).to_lowercase();
+        println!("\n{}--- {}. [{}] ---{}", DIM, i + 1, role, RESET);
+        if !msg.content.is_empty() {
+            println!("{}", msg.content);
+        }
+        for call in msg.tool_calls.iter().flatten() {
+            println!("{}-> {}{} {}", CYAN, call.function.name, RESET, call.function.arguments);
+        }
+    }
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use serde_json::json;
+
+    fn tool_step(iteration: usize, name: &str, path: &str, status: &str, output: &str) -> RecordedStep {
+        RecordedStep {
+            run: 1,
+            iteration,
+            event: StepEvent::Tool {
+                name: name.to_string(),
+                arguments: json!({ "path": path }),
+                status: status.to_string(),
+                output: output.to_string(),
+            },
+        }
+    }
+
+    fn response_step(iteration: usize, content: &str) -> RecordedStep {
+        RecordedStep {
+            run: 1,
+            iteration,
+            event: StepEvent::Response {
+                model: "llama3.2".to_string(),
+                content: content.to_string(),
+                thinking: String::new(),
+                tool_calls: Vec::new(),
+                error: None,
+                prompt_tokens: 10,
+                completion_tokens: 5,
+                total_duration: 0,
+                eval_duration: 0,
+            },
+        }
+    }
+
+    fn call(name: &str, path: &str) -> ToolCall {
+        ToolCall {
+            name: name.to_string(),
+            arguments: json!({ "path": path }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_follows_recording() {
+        let steps = vec![
+            response_step(1, "Reading"),
+            tool_step(1, "file_read", "a.rs", "ok", "fn a() {}"),
+            tool_step(1, "file_read", "b.rs", "error", "no such file"),
+            response_step(2, "Done"),
+        ];
+        let mut replay = Replay::new(steps, false);
+
+        let chunks: Vec<_> = replay.open_stream(1).unwrap().collect().await;
+        let first = chunks[0].as_ref().unwrap();
+        assert_eq!(first.message.as_ref().unwrap().content, "Reading");
+        assert_eq!(chunks[1].as_ref().unwrap().eval_count, Some(5));
+
+        match replay.route(1, &call("file_read", "a.rs")) {
+            RouteResult::Success(r) => assert_eq!(r.output, "fn a() {}"),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(matches!(replay.route(1, &call("file_read", "b.rs")), RouteResult::Error(e) if e == "no such file"));
+        assert!(replay.open_stream(2).is_ok());
+        assert!(replay.divergences().is_empty());
+        assert!(replay.open_stream(3).is_err());
+    }
+
+    #[test]
+    fn test_replay_reports_divergence() {
+        let steps = vec![
+            response_step(1, "Reading"),
+            tool_step(1, "file_read", "a.rs", "ok", "fn a() {}"),
+            tool_step(1, "file_read", "b.rs", "ok", "fn b() {}"),
+            response_step(2, "Done"),
+        ];
+        let mut replay = Replay::new(steps, false);
+        assert!(replay.open_stream(1).is_ok());
+
+        // A different call gets an error, and the recorded call it replaced
+        // is dropped when the next response is replayed
+        assert!(matches!(replay.route(1, &call("file_read", "c.rs")), RouteResult::Error(_)));
+        assert!(replay.open_stream(2).is_ok());
+        assert_eq!(replay.divergences().len(), 3);
+        assert_eq!(replay.remaining(), 0);
+    }
+
+    #[test]
+    fn test_recorded_step_serialization() {
+        let step = tool_step(2, "shell", "ls", "denied", "");
+        let value = serde_json::to_value(&step).unwrap();
+        assert_eq!(value["kind"], "tool");
+        assert!(value.get("output").is_none());
+        let back: RecordedStep = serde_json::from_value(value).unwrap();
+        assert_eq!(back, step);
+    }
+}
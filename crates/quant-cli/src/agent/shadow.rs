@@ -0,0 +1,256 @@
+//! Shadow evaluation of QUANT.md instruction changes
+//!
+//! When `[agent] shadow_runs` is set and a project's QUANT.md changes, the
+//! next N agent runs also answer the task with the previous instructions.
+//! The shadow answer is a single chat request (no tools are executed), and
+//! both answers are appended to a JSONL comparison report so instruction
+//! edits can be judged against real tasks.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use llm_core::{ChatMessage, OllamaClient};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+/// Instruction history for one project
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShadowState {
+    /// Hash of the instructions seen on the last run
+    pub current_hash: String,
+    /// Instructions seen on the last run
+    pub current: String,
+    /// Instructions before the most recent change
+    pub previous: Option<String>,
+    /// Shadow runs left for the most recent change
+    pub remaining: usize,
+}
+
+impl ShadowState {
+    /// Record the instructions used by this run
+    ///
+    /// Returns the previous instructions when this run should be shadowed.
+    /// A change re-arms `runs` shadow runs; the first run ever only records
+    /// a baseline.
+    pub fn observe(&mut self, instructions: &str, runs: usize) -> Option<String> {
+        let hash = hash_text(instructions);
+        if hash != self.current_hash {
+            if !self.current_hash.is_empty() {
+                self.previous = Some(std::mem::take(&mut self.current));
+                self.remaining = runs;
+            }
+            self.current_hash = hash;
+            self.current = instructions.to_string();
+        }
+
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        self.previous.clone()
+    }
+}
+
+/// Persisted shadow state, one file per project root
+pub struct ShadowTracker {
+    path: PathBuf,
+    pub state: ShadowState,
+}
+
+impl ShadowTracker {
+    /// Load the tracker for a project root
+    pub fn load(project_root: &Path) -> Result<Self> {
+        let path = shadow_dir()?.join(format!("{}.json", &hash_text(&project_root.to_string_lossy())[..16]));
+        let state = match fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_default(),
+            Err(_) => ShadowState::default(),
+        };
+        Ok(Self { path, state })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self.state)?)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+/// One live-vs-shadow comparison
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadowReport {
+    pub timestamp: DateTime<Utc>,
+    pub project: PathBuf,
+    pub model: String,
+    pub task: String,
+    pub instructions_hash: String,
+    pub previous_hash: String,
+    /// Final response of the real run (current instructions, with tools)
+    pub live_response: Option<String>,
+    /// Response generated with the previous instructions, without tools
+    pub shadow_response: String,
+    pub shadow_duration_ms: u64,
+    /// Word-set overlap between the two responses (0.0-1.0)
+    pub similarity: f64,
+    pub live_words: usize,
+    pub shadow_words: usize,
+    /// Shadow runs left for this instruction change
+    pub remaining: usize,
+}
+
+impl ShadowReport {
+    /// One-line summary for terminal output
+    pub fn summary_line(&self) -> String {
+        format!(
+            "similarity {:.0}%, {} vs {} words ({} shadow run{} left)",
+            self.similarity * 100.0,
+            self.live_words,
+            self.shadow_words,
+            self.remaining,
+            if self.remaining == 1 { "" } else { "s" }
+        )
+    }
+
+    /// Append to the comparison log
+    pub fn append_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Inputs for a shadow run, gathered from the finished agent run
+pub struct ShadowRun<'a> {
+    pub project_root: &'a Path,
+    /// QUANT.md content the live run used
+    pub instructions: &'a str,
+    /// The live run's system prompt; its instructions are swapped for the old ones
+    pub system_prompt: String,
+    pub task: &'a str,
+    pub live_response: Option<&'a str>,
+}
+
+/// Shadow the run if its project's instructions changed recently
+///
+/// Returns the report when a shadow response was generated.
+pub async fn shadow_if_changed(
+    client: &OllamaClient,
+    model: &str,
+    run: ShadowRun<'_>,
+    runs: usize,
+) -> Result<Option<ShadowReport>> {
+    let mut tracker = ShadowTracker::load(run.project_root)?;
+    let previous = tracker.state.observe(run.instructions, runs);
+    tracker.save()?;
+
+    let Some(previous) = previous else {
+        debug!("No recent QUANT.md change to shadow");
+        return Ok(None);
+    };
+
+    info!(remaining = tracker.state.remaining, "Shadowing run with previous QUANT.md instructions");
+    let system = run.system_prompt.replacen(run.instructions, &previous, 1);
+    let messages = vec![ChatMessage::system(system), ChatMessage::user(run.task)];
+
+    let start = Instant::now();
+    let response = tokio::time::timeout(Duration::from_secs(300), client.chat(model, &messages, None))
+        .await
+        .context("Shadow request timed out")??;
+    let shadow_response = response.message.content;
+
+    let live = run.live_response.unwrap_or_default();
+    let report = ShadowReport {
+        timestamp: Utc::now(),
+        project: run.project_root.to_path_buf(),
+        model: model.to_string(),
+        task: run.task.to_string(),
+        instructions_hash: hash_text(run.instructions)[..12].to_string(),
+        previous_hash: hash_text(&previous)[..12].to_string(),
+        live_response: run.live_response.map(str::to_string),
+        similarity: similarity(live, &shadow_response),
+        live_words: live.split_whitespace().count(),
+        shadow_words: shadow_response.split_whitespace().count(),
+        shadow_response,
+        shadow_duration_ms: start.elapsed().as_millis() as u64,
+        remaining: tracker.state.remaining,
+    };
+
+    report.append_to(&report_path()?)?;
+    info!(
+        similarity = report.similarity,
+        live_words = report.live_words,
+        shadow_words = report.shadow_words,
+        "Shadow comparison recorded"
+    );
+    Ok(Some(report))
+}
+
+/// Path of the JSONL comparison log
+pub fn report_path() -> Result<PathBuf> {
+    Ok(shadow_dir()?.join("reports.jsonl"))
+}
+
+fn shadow_dir() -> Result<PathBuf> {
+    let base = dirs::data_local_dir().context("Could not find local data directory")?;
+    Ok(base.join("quant").join("shadow"))
+}
+
+fn hash_text(text: &str) -> String {
+    let digest = Sha256::digest(text.as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Jaccard similarity of the lowercase word sets
+fn similarity(a: &str, b: &str) -> f64 {
+    let words = |s: &str| -> HashSet<String> {
+        s.split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(|w| w.to_lowercase())
+            .collect()
+    };
+    let (a, b) = (words(a), words(b));
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f64 / a.union(&b).count() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_observe_arms_after_change() {
+        let mut state = ShadowState::default();
+
+        // First sighting only records a baseline
+        assert_eq!(state.observe("v1", 2), None);
+        assert_eq!(state.observe("v1", 2), None);
+
+        // A change shadows the next two runs with the old instructions
+        assert_eq!(state.observe("v2", 2).as_deref(), Some("v1"));
+        assert_eq!(state.observe("v2", 2).as_deref(), Some("v1"));
+        assert_eq!(state.observe("v2", 2), None);
+
+        // Another change re-arms against the newer baseline
+        assert_eq!(state.observe("v3", 1).as_deref(), Some("v2"));
+        assert_eq!(state.remaining, 0);
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity("Run the tests", "run THE tests."), 1.0);
+        assert_eq!(similarity("alpha beta", "gamma delta"), 0.0);
+        assert!((similarity("a b c", "a b d") - 0.5).abs() < f64::EPSILON);
+        assert_eq!(similarity("", ""), 1.0);
+    }
+}
@@ -1,13 +1,27 @@
 //! Agent state management
 
-use llm_core::ChatMessageWithTools;
+use chrono::{DateTime, Utc};
+use llm_core::{ChatMessageWithTools, RetryConfig, Role};
+use serde::{Deserialize, Serialize};
+
+use super::compaction::{compact_tool_results, ToolResultRecord};
+use super::failure::FailureReport;
+use super::plan::{Plan, PLAN_HEADING};
+use super::replay::{RecordedStep, StepEvent};
+use crate::config::{ModelOptions, ToolOutputConfig, UntrustedConfig, UserConfig};
+use crate::context::{BudgetRatios, ContextAssembly, ContextBudget};
+use crate::resource_sampler::ResourceUsage;
+use crate::response_limit::DEFAULT_MAX_RESPONSE_BYTES;
+use crate::tools::policy::ConfirmPolicy;
+use crate::tools::staging::StagingArea;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
 /// Token usage statistics for tracking LLM consumption
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TokenUsage {
     /// Total prompt/input tokens
     pub prompt_tokens: u32,
@@ -69,6 +83,12 @@ impl TokenUsage {
     }
 }
 
+/// Default number of tool calls in one iteration that triggers compaction
+const DEFAULT_COMPACT_AFTER_TOOL_CALLS: usize = 3;
+
+/// Default number of identical responses in a row before the agent intervenes
+pub const DEFAULT_MAX_REPEATED_RESPONSES: usize = 3;
+
 /// Configuration for the agent
 #[derive(Debug, Clone)]
 pub struct AgentConfig {
@@ -82,8 +102,53 @@ pub struct AgentConfig {
     pub working_dir: PathBuf,
     /// Auto mode (skip confirmations)
     pub auto_mode: bool,
+    /// Simulate Dangerous tools instead of executing them
+    pub dry_run: bool,
+    /// Hold file writes here for review instead of writing them
+    pub staging: Option<Arc<StagingArea>>,
+    /// Answer confirmations from this policy instead of the terminal
+    pub confirm_policy: Option<ConfirmPolicy>,
     /// Whether to print tool executions
     pub verbose: bool,
+    /// Allow pausing with Ctrl+C to inject instructions mid-run
+    pub interruptible: bool,
+    /// How the context window is split between prompt sections
+    pub budget_ratios: BudgetRatios,
+    /// Compact an iteration's tool results once it made at least this many calls (0 = never)
+    pub compact_after_tool_calls: usize,
+    /// Retry policy for failed LLM requests
+    pub retry: RetryConfig,
+    /// Model to switch to when the primary model keeps failing
+    pub fallback_model: Option<String>,
+    /// Role-specific instructions added to the default system prompt
+    pub role_instructions: Option<String>,
+    /// Stop streaming a response once it exceeds this many bytes (0 = unlimited)
+    pub max_response_bytes: usize,
+    /// Cap on the model's context window, in tokens
+    pub max_context_tokens: Option<usize>,
+    /// Sampling temperature for the model (model default if unset)
+    pub temperature: Option<f32>,
+    /// Context window to load the model with, instead of the detected one
+    pub num_ctx: Option<u32>,
+    /// Other sampling options for the model, from its alias or flags
+    pub sampling: ModelOptions,
+    /// Pick up QUANT.md and config file edits between iterations
+    pub hot_reload: bool,
+    /// Nudge the model after this many near-identical responses in a row,
+    /// and stop the run if it repeats again (0 = never)
+    pub max_repeated_responses: usize,
+    /// Show tool output (e.g. a build) line by line while the tool runs
+    pub live_output: bool,
+    /// Tool definitions sent per request; the rest are listed by the
+    /// `list_more_tools` meta-tool (0 = all; unset = prune on small models)
+    pub max_tools: Option<usize>,
+    /// Files already put into the task (explicit context), which smart
+    /// context and the project section leave out
+    pub context: ContextAssembly,
+    /// Prompt-injection defenses for web and MCP tool results
+    pub untrusted: UntrustedConfig,
+    /// Truncation of tool results, per tool
+    pub tool_output: ToolOutputConfig,
 }
 
 impl Default for AgentConfig {
@@ -94,7 +159,28 @@ impl Default for AgentConfig {
             max_iterations: 50,
             working_dir: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
             auto_mode: false,
+            dry_run: false,
+            staging: None,
+            confirm_policy: None,
             verbose: true,
+            interruptible: false,
+            budget_ratios: BudgetRatios::default(),
+            compact_after_tool_calls: DEFAULT_COMPACT_AFTER_TOOL_CALLS,
+            retry: RetryConfig::default(),
+            fallback_model: None,
+            role_instructions: None,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            max_context_tokens: None,
+            temperature: None,
+            num_ctx: None,
+            sampling: ModelOptions::default(),
+            hot_reload: false,
+            max_repeated_responses: DEFAULT_MAX_REPEATED_RESPONSES,
+            live_output: true,
+            max_tools: None,
+            context: ContextAssembly::default(),
+            untrusted: UntrustedConfig::default(),
+            tool_output: ToolOutputConfig::default(),
         }
     }
 }
@@ -107,6 +193,22 @@ impl AgentConfig {
         }
     }
 
+    /// An agent for `model` with the settings of the user's config; each
+    /// entry point adds its own (working dir, auto mode, staging, ...)
+    pub fn from_user_config(model: impl Into<String>, config: &UserConfig) -> Self {
+        Self::new(model)
+            .with_budget_ratios(config.budget)
+            .with_compact_after_tool_calls(config.agent.compact_after_tool_calls)
+            .with_max_repeated_responses(config.agent.max_repeated_responses)
+            .with_live_output(config.agent.live_output)
+            .with_max_tools(config.agent.max_tools)
+            .with_fallback_model(config.agent.fallback_model.clone())
+            .with_max_response_bytes(config.limits.max_response_bytes())
+            .with_max_context_tokens(config.limits.max_context_tokens)
+            .with_untrusted(config.untrusted.clone())
+            .with_tool_output(config.tool_output.clone())
+    }
+
     pub fn with_system_prompt(mut self, prompt: impl Into<String>) -> Self {
         self.system_prompt = Some(prompt.into());
         self
@@ -127,17 +229,173 @@ impl AgentConfig {
         self
     }
 
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn with_staging(mut self, staging: Option<Arc<StagingArea>>) -> Self {
+        self.staging = staging;
+        self
+    }
+
+    pub fn with_confirm_policy(mut self, policy: Option<ConfirmPolicy>) -> Self {
+        self.confirm_policy = policy;
+        self
+    }
+
     pub fn with_verbose(mut self, verbose: bool) -> Self {
         self.verbose = verbose;
         self
     }
+
+    pub fn with_interruptible(mut self, interruptible: bool) -> Self {
+        self.interruptible = interruptible;
+        self
+    }
+
+    pub fn with_budget_ratios(mut self, ratios: BudgetRatios) -> Self {
+        self.budget_ratios = ratios;
+        self
+    }
+
+    pub fn with_compact_after_tool_calls(mut self, calls: usize) -> Self {
+        self.compact_after_tool_calls = calls;
+        self
+    }
+
+    pub fn with_fallback_model(mut self, model: Option<String>) -> Self {
+        self.fallback_model = model.filter(|m| !m.is_empty());
+        self
+    }
+
+    pub fn with_role_instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.role_instructions = Some(instructions.into());
+        self
+    }
+
+    pub fn with_max_response_bytes(mut self, max: usize) -> Self {
+        self.max_response_bytes = max;
+        self
+    }
+
+    pub fn with_max_context_tokens(mut self, max: Option<usize>) -> Self {
+        self.max_context_tokens = max.filter(|&m| m > 0);
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: Option<f32>) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    pub fn with_num_ctx(mut self, num_ctx: Option<u32>) -> Self {
+        self.num_ctx = num_ctx.filter(|&n| n > 0);
+        self
+    }
+
+    pub fn with_sampling(mut self, sampling: ModelOptions) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    pub fn with_hot_reload(mut self, enabled: bool) -> Self {
+        self.hot_reload = enabled;
+        self
+    }
+
+    pub fn with_max_repeated_responses(mut self, max: usize) -> Self {
+        self.max_repeated_responses = max;
+        self
+    }
+
+    pub fn with_live_output(mut self, enabled: bool) -> Self {
+        self.live_output = enabled;
+        self
+    }
+
+    pub fn with_max_tools(mut self, max: Option<usize>) -> Self {
+        self.max_tools = max;
+        self
+    }
+
+    pub fn with_context(mut self, context: ContextAssembly) -> Self {
+        self.context = context;
+        self
+    }
+
+    pub fn with_untrusted(mut self, untrusted: UntrustedConfig) -> Self {
+        self.untrusted = untrusted;
+        self
+    }
+
+    pub fn with_tool_output(mut self, tool_output: ToolOutputConfig) -> Self {
+        self.tool_output = tool_output;
+        self
+    }
+}
+
+/// A switch to the fallback model after repeated LLM errors
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelSwitch {
+    /// Iteration at which the switch happened
+    pub iteration: usize,
+    /// Model that failed
+    pub from: String,
+    /// Model used from then on
+    pub to: String,
+    /// Last error from the failed model
+    pub reason: String,
+}
+
+/// A model reloaded by the first-token watchdog after it stalled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelRestart {
+    /// Iteration at which the model stalled
+    pub iteration: usize,
+    pub model: String,
+    /// Seconds waited for a first token before reloading
+    pub waited_secs: u64,
+}
+
+/// Outcome of one tool call, kept for the failure report
+#[derive(Debug, Clone)]
+pub struct ToolCallRecord {
+    pub iteration: usize,
+    pub tool: String,
+    /// What the model was told when the call didn't succeed
+    pub error: Option<String>,
+}
+
+/// What the model saw at the start of an iteration, saved to sessions so a
+/// run can be replayed step by step (`quant sessions inspect`)
+///
+/// Messages are stored as a delta: the first `shared_messages` come from
+/// the previous snapshot of the same run, followed by `messages`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentSnapshot {
+    /// Agent run within the session, from 1 (set when saved)
+    #[serde(default)]
+    pub run: usize,
+    pub iteration: usize,
+    pub model: String,
+    pub taken_at: DateTime<Utc>,
+    /// Leading messages unchanged since the previous snapshot
+    pub shared_messages: usize,
+    /// Messages after the shared prefix
+    pub messages: Vec<ChatMessageWithTools>,
+    pub failure_tracker: FailureTracker,
+    pub token_usage: TokenUsage,
+    pub compacted_chars: usize,
 }
 
 /// State of the agent during execution
 #[derive(Debug)]
 pub struct AgentState {
-    /// Message history
+    /// Message history sent to the model (tool-heavy iterations are compacted)
     pub messages: Vec<ChatMessageWithTools>,
+    /// Full message history with uncompacted tool results (saved to sessions)
+    pub transcript: Vec<ChatMessageWithTools>,
     /// Current iteration
     pub iteration: usize,
     /// Whether the agent has finished
@@ -146,10 +404,46 @@ pub struct AgentState {
     pub final_response: Option<String>,
     /// Error message (if failed)
     pub error: Option<String>,
+    /// What the user can do about `error`, when Ollama said what went wrong
+    pub error_hint: Option<String>,
     /// Failure tracker for detecting infinite loops
     pub failure_tracker: FailureTracker,
+    /// Tracks the model repeating the same response
+    pub repetition: RepetitionTracker,
     /// Token usage tracking
     pub token_usage: TokenUsage,
+    /// Plan being executed (planning mode)
+    pub plan: Option<Plan>,
+    /// Token budget for each section of the context window
+    pub context_budget: Option<ContextBudget>,
+    /// Tool results recorded during the current iteration
+    iteration_results: Vec<ToolResultRecord>,
+    /// Every tool result so far, oldest first
+    history_results: Vec<ToolResultRecord>,
+    /// Tool results waiting to be compacted once the model has seen them
+    pending_compaction: Vec<ToolResultRecord>,
+    /// Characters removed from the history by compaction
+    pub compacted_chars: usize,
+    /// Model currently in use (changes after a fallback)
+    pub model: String,
+    /// Fallback switches made during the run
+    pub model_switches: Vec<ModelSwitch>,
+    /// Models reloaded after stalling before their first token
+    pub model_restarts: Vec<ModelRestart>,
+    /// Responses cut off at the configured size limit
+    pub truncated_responses: usize,
+    /// Memory and VRAM sampled during the run
+    pub resource_usage: ResourceUsage,
+    /// State at the start of each iteration
+    pub snapshots: Vec<AgentSnapshot>,
+    /// Model responses and tool outcomes, for `quant sessions replay`
+    pub steps: Vec<RecordedStep>,
+    /// Every tool call made, in order
+    pub tool_calls: Vec<ToolCallRecord>,
+    /// What went wrong, when the run ended in an error
+    pub failure: Option<FailureReport>,
+    /// Messages as of the last snapshot, for delta encoding
+    snapshot_base: Vec<ChatMessageWithTools>,
 }
 
 /// Default max consecutive failures before aborting
@@ -159,12 +453,31 @@ impl AgentState {
     pub fn new() -> Self {
         Self {
             messages: Vec::new(),
+            transcript: Vec::new(),
             iteration: 0,
             finished: false,
             final_response: None,
             error: None,
+            error_hint: None,
             failure_tracker: FailureTracker::new(DEFAULT_MAX_CONSECUTIVE_FAILURES),
+            repetition: RepetitionTracker::default(),
             token_usage: TokenUsage::new(),
+            plan: None,
+            context_budget: None,
+            iteration_results: Vec::new(),
+            history_results: Vec::new(),
+            pending_compaction: Vec::new(),
+            compacted_chars: 0,
+            model: String::new(),
+            model_switches: Vec::new(),
+            model_restarts: Vec::new(),
+            truncated_responses: 0,
+            resource_usage: ResourceUsage::default(),
+            snapshots: Vec::new(),
+            steps: Vec::new(),
+            tool_calls: Vec::new(),
+            failure: None,
+            snapshot_base: Vec::new(),
         }
     }
 
@@ -189,9 +502,144 @@ impl AgentState {
     }
 
     pub fn add_message(&mut self, message: ChatMessageWithTools) {
+        self.transcript.push(message.clone());
         self.messages.push(message);
     }
 
+    /// Record the messages and counters the model is about to see
+    pub fn take_snapshot(&mut self) {
+        let shared = self
+            .snapshot_base
+            .iter()
+            .zip(&self.messages)
+            .take_while(|(before, now)| before == now)
+            .count();
+        self.snapshots.push(AgentSnapshot {
+            run: 0,
+            iteration: self.iteration,
+            model: self.model.clone(),
+            taken_at: Utc::now(),
+            shared_messages: shared,
+            messages: self.messages[shared..].to_vec(),
+            failure_tracker: self.failure_tracker.clone(),
+            token_usage: self.token_usage.clone(),
+            compacted_chars: self.compacted_chars,
+        });
+        self.snapshot_base = self.messages.clone();
+    }
+
+    /// Switch to another model, recording why
+    pub fn switch_model(&mut self, to: impl Into<String>, reason: impl Into<String>) {
+        let to = to.into();
+        self.model_switches.push(ModelSwitch {
+            iteration: self.iteration,
+            from: std::mem::replace(&mut self.model, to.clone()),
+            to,
+            reason: reason.into(),
+        });
+    }
+
+    /// Record a model the watchdog reloaded
+    pub fn record_restart(&mut self, restart: &llm_core::ModelRestart) {
+        self.model_restarts.push(ModelRestart {
+            iteration: self.iteration,
+            model: restart.model.clone(),
+            waited_secs: restart.waited.as_secs(),
+        });
+    }
+
+    /// Record that the last message is a tool result, for later compaction
+    pub fn record_tool_result(&mut self, tool: &str, success: bool) {
+        if let Some(index) = self.messages.len().checked_sub(1) {
+            self.iteration_results.push(ToolResultRecord {
+                index,
+                tool: tool.to_string(),
+                success,
+            });
+        }
+    }
+
+    /// Record a model response or tool outcome of this iteration
+    pub fn record_step(&mut self, event: StepEvent) {
+        self.steps.push(RecordedStep {
+            run: 0,
+            iteration: self.iteration,
+            event,
+        });
+    }
+
+    /// Record a finished tool call; `output` is kept unless the status is "ok"
+    pub fn record_tool_call(&mut self, tool: &str, status: &str, output: &str) {
+        self.tool_calls.push(ToolCallRecord {
+            iteration: self.iteration,
+            tool: tool.to_string(),
+            error: (status != "ok").then(|| output.to_string()),
+        });
+    }
+
+    /// Queue this iteration's tool results for compaction if it was tool-heavy
+    pub fn end_iteration(&mut self, compact_after_tool_calls: usize) {
+        let results = std::mem::take(&mut self.iteration_results);
+        self.history_results.extend(results.iter().cloned());
+        if compact_after_tool_calls > 0 && results.len() >= compact_after_tool_calls {
+            self.pending_compaction.extend(results);
+        }
+    }
+
+    /// Compact queued tool results, returning the number of characters removed
+    pub fn compact_pending(&mut self) -> usize {
+        let records = std::mem::take(&mut self.pending_compaction);
+        let saved = compact_tool_results(&mut self.messages, &records);
+        self.compacted_chars += saved;
+        saved
+    }
+
+    /// Compact the oldest tool results the model has already seen until the
+    /// history after the system prompt fits in `budget` tokens, returning the
+    /// number of characters removed
+    pub fn fit_history(&mut self, budget: usize, count_tokens: impl Fn(&str) -> usize) -> usize {
+        let mut tokens: usize = self
+            .messages
+            .iter()
+            .filter(|m| m.role != Role::System)
+            .map(|m| count_tokens(&m.content))
+            .sum();
+        // Results after the last response haven't been seen yet
+        let seen = self.messages.iter().rposition(|m| m.role == Role::Assistant).unwrap_or(0);
+
+        let mut saved = 0;
+        for record in self.history_results.iter().filter(|r| r.index < seen) {
+            if tokens <= budget {
+                break;
+            }
+            let Some(before) = self.messages.get(record.index).map(|m| count_tokens(&m.content)) else {
+                continue;
+            };
+            let removed = compact_tool_results(&mut self.messages, std::slice::from_ref(record));
+            if removed > 0 {
+                tokens = tokens - before + count_tokens(&self.messages[record.index].content);
+                saved += removed;
+            }
+        }
+        self.compacted_chars += saved;
+        saved
+    }
+
+    /// Rewrite the plan section of the system prompt so the model sees
+    /// which steps are done
+    pub fn refresh_plan(&mut self) {
+        let Some(ref plan) = self.plan else {
+            return;
+        };
+        let Some(system) = self.messages.iter_mut().find(|m| m.role == Role::System) else {
+            return;
+        };
+        if let Some(start) = system.content.rfind(PLAN_HEADING) {
+            system.content.truncate(start);
+            system.content.push_str(&plan.to_system_context());
+        }
+    }
+
     pub fn mark_finished(&mut self, response: String) {
         self.finished = true;
         self.final_response = Some(response);
@@ -205,6 +653,48 @@ impl AgentState {
     pub fn increment_iteration(&mut self) {
         self.iteration += 1;
     }
+
+    /// Machine-readable summary of the run, used for JSON output
+    pub fn run_summary(&self) -> serde_json::Value {
+        let tool_calls: usize = self
+            .transcript
+            .iter()
+            .filter_map(|m| m.tool_calls.as_ref())
+            .map(|calls| calls.len())
+            .sum();
+        let status = if self.error.is_some() {
+            "error"
+        } else if self.finished {
+            "completed"
+        } else {
+            "incomplete"
+        };
+
+        serde_json::json!({
+            "status": status,
+            "model": self.model,
+            "iterations": self.iteration,
+            "tool_calls": tool_calls,
+            "final_response": self.final_response,
+            "error": self.error,
+            "failure": self.failure,
+            "usage": {
+                "prompt_tokens": self.token_usage.prompt_tokens,
+                "completion_tokens": self.token_usage.completion_tokens,
+                "total_tokens": self.token_usage.total_tokens(),
+                "llm_calls": self.token_usage.call_count,
+                "duration_ms": self.token_usage.total_duration.as_millis() as u64,
+            },
+            "plan": self.plan.as_ref().map(|p| serde_json::json!({
+                "completed": p.completed(),
+                "total": p.steps.len(),
+            })),
+            "model_switches": self.model_switches,
+            "model_restarts": self.model_restarts,
+            "truncated_responses": self.truncated_responses,
+            "resources": (!self.resource_usage.is_empty()).then(|| self.resource_usage.to_json()),
+        })
+    }
 }
 
 impl Default for AgentState {
@@ -214,7 +704,7 @@ impl Default for AgentState {
 }
 
 /// Tracks consecutive failures for tool calls to detect infinite loops
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct FailureTracker {
     /// Map from tool call signature to consecutive failure count
     failures: HashMap<String, ConsecutiveFailure>,
@@ -224,7 +714,7 @@ pub struct FailureTracker {
     max_consecutive: usize,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConsecutiveFailure {
     pub count: usize,
     pub last_error: String,
@@ -285,6 +775,84 @@ impl FailureTracker {
     pub fn failure_count(&self, signature: &str) -> usize {
         self.failures.get(signature).map_or(0, |f| f.count)
     }
+
+    /// Signatures with outstanding failures, sorted
+    pub fn failures(&self) -> Vec<(&str, &ConsecutiveFailure)> {
+        let mut failures: Vec<_> = self.failures.iter().map(|(sig, f)| (sig.as_str(), f)).collect();
+        failures.sort_by_key(|(sig, _)| *sig);
+        failures
+    }
+}
+
+/// What to do about a repeated response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepeatAction {
+    /// Carry on
+    None,
+    /// Tell the model it is repeating itself
+    Nudge,
+    /// Still repeating after a nudge; stop the run
+    Finish,
+}
+
+/// Detects a model giving the same response every iteration without finishing
+///
+/// Responses are compared by their words, ignoring case, punctuation and
+/// numbers, so "Step 3: reading the file..." and "Step 4: Reading the file"
+/// count as the same. Responses without text (tool calls only) are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct RepetitionTracker {
+    /// Normalized text of the last response
+    last: Option<String>,
+    /// How many times in a row `last` was seen
+    count: usize,
+    /// Whether the current streak has already been nudged
+    nudged: bool,
+}
+
+impl RepetitionTracker {
+    fn normalize(content: &str) -> String {
+        content
+            .split(|c: char| !c.is_alphabetic())
+            .filter(|w| !w.is_empty())
+            .map(|w| w.to_lowercase())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Record a response; returns how many times in a row it has been seen
+    pub fn record(&mut self, content: &str) -> usize {
+        let normalized = Self::normalize(content);
+        if normalized.is_empty() {
+            self.last = None;
+            self.count = 0;
+        } else if self.last.as_deref() == Some(normalized.as_str()) {
+            self.count += 1;
+        } else {
+            self.last = Some(normalized);
+            self.count = 1;
+            self.nudged = false;
+        }
+        self.count
+    }
+
+    /// Record a response and decide whether to intervene (`max` of 0 disables)
+    pub fn check(&mut self, content: &str, max: usize) -> RepeatAction {
+        let count = self.record(content);
+        if max == 0 || count < max {
+            RepeatAction::None
+        } else if self.nudged {
+            RepeatAction::Finish
+        } else {
+            self.nudged = true;
+            RepeatAction::Nudge
+        }
+    }
+
+    /// Length of the current streak of identical responses
+    pub fn count(&self) -> usize {
+        self.count
+    }
 }
 
 #[cfg(test)]
@@ -292,6 +860,84 @@ mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn test_run_summary() {
+        let mut state = AgentState::new();
+        state.model = "llama3.2".to_string();
+        state.increment_iteration();
+        state.record_tokens(100, 20, 2_000_000_000, 1_000_000_000);
+        state.add_message(ChatMessageWithTools {
+            role: llm_core::Role::Assistant,
+            content: String::new(),
+            tool_calls: Some(vec![llm_core::ToolCall {
+                id: "1".to_string(),
+                function: llm_core::FunctionCall {
+                    name: "glob".to_string(),
+                    arguments: json!({}),
+                },
+            }]),
+            tool_call_id: None,
+        });
+        state.mark_finished("Done".to_string());
+
+        let summary = state.run_summary();
+        assert_eq!(summary["status"], "completed");
+        assert_eq!(summary["tool_calls"], 1);
+        assert_eq!(summary["usage"]["total_tokens"], 120);
+        assert_eq!(summary["usage"]["duration_ms"], 2000);
+        assert_eq!(summary["final_response"], "Done");
+        assert!(summary["plan"].is_null());
+
+        state.mark_error("boom".to_string());
+        assert_eq!(state.run_summary()["status"], "error");
+    }
+
+    #[test]
+    fn test_snapshots_store_message_deltas() {
+        let mut state = AgentState::new();
+        state.add_message(ChatMessageWithTools::tool_result("system", "prompt"));
+        state.increment_iteration();
+        state.take_snapshot();
+
+        state.add_message(ChatMessageWithTools::tool_result("1", "output"));
+        state.increment_iteration();
+        state.take_snapshot();
+
+        // Compaction rewrites an earlier message, so only the prefix before it is shared
+        state.messages[1].content = "[compacted]".to_string();
+        state.add_message(ChatMessageWithTools::tool_result("2", "more"));
+        state.increment_iteration();
+        state.take_snapshot();
+
+        let counts: Vec<(usize, usize)> = state
+            .snapshots
+            .iter()
+            .map(|s| (s.shared_messages, s.messages.len()))
+            .collect();
+        assert_eq!(counts, vec![(0, 1), (1, 1), (1, 2)]);
+        assert_eq!(state.snapshots[2].iteration, 3);
+    }
+
+    #[test]
+    fn test_repetition_tracker_nudges_then_finishes() {
+        let mut tracker = RepetitionTracker::default();
+        assert_eq!(tracker.check("Step 1: I will read the file.", 3), RepeatAction::None);
+        assert_eq!(tracker.check("Step 2: I will read the file", 3), RepeatAction::None);
+        assert_eq!(tracker.check("step 3 - i will READ the file!", 3), RepeatAction::Nudge);
+        assert_eq!(tracker.check("Step 4: I will read the file.", 3), RepeatAction::Finish);
+
+        // A different response starts a new streak; tool-only responses reset it
+        assert_eq!(tracker.check("Now editing main.rs", 3), RepeatAction::None);
+        assert_eq!(tracker.record(""), 0);
+        assert_eq!(tracker.record("Now editing main.rs"), 1);
+
+        // Disabled
+        let mut tracker = RepetitionTracker::default();
+        for _ in 0..5 {
+            assert_eq!(tracker.check("same", 0), RepeatAction::None);
+        }
+    }
+
     #[test]
     fn test_failure_tracker_success_resets() {
         let mut tracker = FailureTracker::new(3);
@@ -422,6 +1068,115 @@ mod tests {
         assert!(summary.contains("1 calls"));
     }
 
+    #[test]
+    fn test_agent_state_compacts_tool_heavy_iterations() {
+        let mut state = AgentState::new();
+        let long = "x\n".repeat(500);
+
+        for i in 0..3 {
+            state.add_message(ChatMessageWithTools::tool_result(i.to_string(), long.clone()));
+            state.record_tool_result("grep", true);
+        }
+        state.end_iteration(3);
+
+        // Nothing changes until the model has seen the results
+        assert_eq!(state.messages[0].content, long);
+        assert!(state.compact_pending() > 0);
+        assert!(state.messages.iter().all(|m| m.content.starts_with("[compacted]")));
+
+        // Full detail is kept in the transcript
+        assert!(state.transcript.iter().all(|m| m.content == long));
+    }
+
+    #[test]
+    fn test_agent_state_skips_light_iterations() {
+        let mut state = AgentState::new();
+        let long = "x\n".repeat(500);
+
+        state.add_message(ChatMessageWithTools::tool_result("1", long.clone()));
+        state.record_tool_result("grep", true);
+        state.end_iteration(3);
+
+        assert_eq!(state.compact_pending(), 0);
+        assert_eq!(state.messages[0].content, long);
+    }
+
+    #[test]
+    fn test_agent_state_fits_history_to_budget() {
+        let mut state = AgentState::new();
+        let long = "x\n".repeat(500);
+        let assistant = |content: &str| ChatMessageWithTools {
+            role: Role::Assistant,
+            content: content.to_string(),
+            tool_calls: None,
+            tool_call_id: None,
+        };
+        let chars = |text: &str| text.len();
+
+        for i in 0..3 {
+            state.add_message(assistant("calling grep"));
+            state.add_message(ChatMessageWithTools::tool_result(i.to_string(), long.clone()));
+            state.record_tool_result("grep", true);
+            state.end_iteration(0);
+        }
+
+        // Within budget, nothing changes
+        assert_eq!(state.fit_history(10_000, chars), 0);
+
+        // Over it, the oldest results go first, and only as many as needed
+        assert!(state.fit_history(2_200, chars) > 0);
+        assert!(state.messages[1].content.starts_with("[compacted]"));
+        assert_eq!(state.messages[3].content, long);
+
+        // The latest result hasn't been seen by the model and is kept
+        state.fit_history(0, chars);
+        assert!(state.messages[3].content.starts_with("[compacted]"));
+        assert_eq!(state.messages[5].content, long);
+        assert!(state.transcript.iter().filter(|m| m.role == Role::Tool).all(|m| m.content == long));
+    }
+
+    #[test]
+    fn test_agent_state_refreshes_plan() {
+        let mut state = AgentState::new();
+        let mut plan = Plan::parse("1. Find the bug\n2. Fix it").unwrap();
+        state.add_message(ChatMessageWithTools {
+            role: Role::System,
+            content: format!("You are an agent.\n\n{}", plan.to_system_context()),
+            tool_calls: None,
+            tool_call_id: None,
+        });
+        plan.mark_done(1);
+        state.plan = Some(plan);
+
+        state.refresh_plan();
+        let system = &state.messages[0].content;
+        assert!(system.starts_with("You are an agent.\n\n## Plan\n"));
+        assert!(system.contains("- [x] 1. Find the bug"));
+        assert!(system.contains("- [ ] 2. Fix it"));
+        assert_eq!(system.matches(PLAN_HEADING).count(), 1);
+    }
+
+    #[test]
+    fn test_agent_state_switch_model() {
+        let mut state = AgentState::new();
+        state.model = "primary".to_string();
+        state.increment_iteration();
+
+        state.switch_model("fallback", "HTTP 500");
+        assert_eq!(state.model, "fallback");
+        assert_eq!(state.model_switches.len(), 1);
+        assert_eq!(state.model_switches[0].from, "primary");
+        assert_eq!(state.model_switches[0].iteration, 1);
+    }
+
+    #[test]
+    fn test_agent_config_fallback_model() {
+        let config = AgentConfig::new("a").with_fallback_model(Some(String::new()));
+        assert!(config.fallback_model.is_none());
+        let config = AgentConfig::new("a").with_fallback_model(Some("b".to_string()));
+        assert_eq!(config.fallback_model.as_deref(), Some("b"));
+    }
+
     #[test]
     fn test_agent_state_token_tracking() {
         let mut state = AgentState::new();
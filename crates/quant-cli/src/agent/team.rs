@@ -0,0 +1,413 @@
+//! Multi-agent teams with named roles
+//!
+//! A team is an ordered list of role-specialized agents (e.g. planner, coder,
+//! reviewer). A coordinator runs each role in turn, sharing a scratchpad of
+//! everything the previous roles produced. When a reviewer asks for changes,
+//! the coordinator hands the work back to the role before it, up to a
+//! maximum number of review rounds.
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use llm_core::OllamaClient;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::tools::builtin::create_default_registry;
+use crate::tools::router::ToolRouter;
+use crate::tools::security::TerminalConfirmation;
+
+use super::agent_loop::AgentLoop;
+use super::failure::FailureReport;
+use super::state::{AgentConfig, AgentState};
+
+// ANSI colors
+const BOLD: &str = "\x1b[1m";
+const YELLOW: &str = "\x1b[93m";
+const RESET: &str = "\x1b[0m";
+
+/// Phrase a reviewer uses to send work back
+pub const CHANGES_REQUESTED: &str = "CHANGES REQUESTED";
+
+/// User-defined role from config (`[roles.<name>]`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoleConfig {
+    /// Instructions for this role
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// Model override for this role
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Tools this role may use (all tools if unset)
+    #[serde(default)]
+    pub tools: Option<Vec<String>>,
+}
+
+/// A role-specialized agent in a team
+#[derive(Debug, Clone)]
+pub struct TeamRole {
+    /// Role name (e.g. "coder")
+    pub name: String,
+    /// Instructions added to the agent's system prompt
+    pub instructions: String,
+    /// Model override (uses the team model if unset)
+    pub model: Option<String>,
+    /// Allowed tools (all tools if unset)
+    pub tools: Option<Vec<String>>,
+}
+
+impl TeamRole {
+    /// Look up a built-in role
+    pub fn builtin(name: &str) -> Option<Self> {
+        let (instructions, tools): (&str, Option<&[&str]>) = match name {
+            "planner" => (
+                "You are the planner. Investigate the codebase and break the task into concrete steps \
+                 for the coder. Do not modify files. End with a numbered list of steps.",
+                Some(&[
+                    "file_read", "glob", "grep", "outline", "find_definition", "find_references",
+                    "diagnostics", "git", "rust_docs", "dependency_info",
+                ]),
+            ),
+            "coder" => (
+                "You are the coder. Implement the task following the plan in the scratchpad. \
+                 If a review requested changes, address every point. Summarize what you changed.",
+                None,
+            ),
+            "tester" => (
+                "You are the tester. Write or update tests for the changes described in the scratchpad \
+                 and run them. Report which tests pass or fail.",
+                None,
+            ),
+            "reviewer" => (
+                "You are the reviewer. Review the changes described in the scratchpad by reading the \
+                 affected files. Do not modify files. If anything must be fixed, start your response \
+                 with CHANGES REQUESTED and list the fixes; otherwise start with APPROVED.",
+                Some(&[
+                    "file_read", "glob", "grep", "outline", "find_definition", "find_references",
+                    "diagnostics", "git", "rust_docs", "dependency_info",
+                ]),
+            ),
+            _ => return None,
+        };
+
+        Some(Self {
+            name: name.to_string(),
+            instructions: instructions.to_string(),
+            model: None,
+            tools: tools.map(|t| t.iter().map(|s| s.to_string()).collect()),
+        })
+    }
+
+    /// Build a role from config, layered over the built-in role of the same name
+    pub fn from_config(name: &str, config: &RoleConfig) -> Self {
+        let mut role = Self::builtin(name).unwrap_or_else(|| Self {
+            name: name.to_string(),
+            instructions: format!("You are the {}.", name),
+            model: None,
+            tools: None,
+        });
+        if let Some(ref prompt) = config.system_prompt {
+            role.instructions = prompt.clone();
+        }
+        if config.model.is_some() {
+            role.model = config.model.clone();
+        }
+        if config.tools.is_some() {
+            role.tools = config.tools.clone();
+        }
+        role
+    }
+
+    /// Whether this role reviews the work of the one before it: the built-in
+    /// reviewer, or a role told to answer with CHANGES REQUESTED
+    pub fn is_reviewer(&self) -> bool {
+        self.name == "reviewer" || self.instructions.contains(CHANGES_REQUESTED)
+    }
+
+    /// Whether a review starts with CHANGES REQUESTED (markdown emphasis or
+    /// a heading marker in front is fine)
+    pub fn requests_changes(output: &str) -> bool {
+        output
+            .trim_start_matches(|c: char| c.is_whitespace() || "*_#>".contains(c))
+            .to_uppercase()
+            .starts_with(CHANGES_REQUESTED)
+    }
+}
+
+/// Parse a comma-separated team spec like "planner,coder,reviewer"
+pub fn parse_team(spec: &str, custom: &HashMap<String, RoleConfig>) -> Result<Vec<TeamRole>> {
+    let mut roles = Vec::new();
+    for name in spec.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        let role = match custom.get(name) {
+            Some(config) => TeamRole::from_config(name, config),
+            None => match TeamRole::builtin(name) {
+                Some(role) => role,
+                None => bail!(
+                    "Unknown role '{}'. Built-in roles: planner, coder, tester, reviewer (or define [roles.{}] in config)",
+                    name,
+                    name
+                ),
+            },
+        };
+        roles.push(role);
+    }
+
+    if roles.is_empty() {
+        bail!("Team must have at least one role");
+    }
+    Ok(roles)
+}
+
+/// An entry written by a role
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScratchpadEntry {
+    pub role: String,
+    pub content: String,
+}
+
+/// Shared notes passed between roles
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Scratchpad {
+    pub entries: Vec<ScratchpadEntry>,
+}
+
+impl Scratchpad {
+    pub fn add(&mut self, role: impl Into<String>, content: impl Into<String>) {
+        self.entries.push(ScratchpadEntry {
+            role: role.into(),
+            content: content.into(),
+        });
+    }
+
+    /// Format the scratchpad for inclusion in a role's task
+    pub fn to_context(&self) -> String {
+        if self.entries.is_empty() {
+            return String::new();
+        }
+        let mut out = String::from("## Team Scratchpad\n");
+        for entry in &self.entries {
+            out.push_str(&format!("\n### {}\n{}\n", entry.role, entry.content.trim()));
+        }
+        out
+    }
+}
+
+/// Result of a team run
+#[derive(Debug)]
+pub struct TeamOutcome {
+    /// Notes written by each role
+    pub scratchpad: Scratchpad,
+    /// Agent state for each role run, in order
+    pub runs: Vec<(String, AgentState)>,
+    /// Number of review rounds that sent work back
+    pub review_rounds: usize,
+}
+
+impl TeamOutcome {
+    /// Final response of the last role
+    pub fn final_response(&self) -> Option<&str> {
+        self.runs.last().and_then(|(_, s)| s.final_response.as_deref())
+    }
+
+    /// First error from any role
+    pub fn error(&self) -> Option<&str> {
+        self.runs.iter().find_map(|(_, s)| s.error.as_deref())
+    }
+
+    /// Failure report of the role that failed
+    pub fn failure(&self) -> Option<(&str, &FailureReport)> {
+        self.runs
+            .iter()
+            .find_map(|(role, s)| s.failure.as_ref().map(|f| (role.as_str(), f)))
+    }
+}
+
+/// Coordinates role agents working on one task
+pub struct TeamCoordinator {
+    client: OllamaClient,
+    roles: Vec<TeamRole>,
+    base_config: AgentConfig,
+    max_review_rounds: usize,
+}
+
+impl TeamCoordinator {
+    pub fn new(client: OllamaClient, roles: Vec<TeamRole>, base_config: AgentConfig) -> Self {
+        Self {
+            client,
+            roles,
+            base_config,
+            max_review_rounds: 2,
+        }
+    }
+
+    pub fn with_max_review_rounds(mut self, rounds: usize) -> Self {
+        self.max_review_rounds = rounds;
+        self
+    }
+
+    /// Run the team on a task
+    pub async fn run(&self, task: &str) -> Result<TeamOutcome> {
+        let mut outcome = TeamOutcome {
+            scratchpad: Scratchpad::default(),
+            runs: Vec::new(),
+            review_rounds: 0,
+        };
+
+        let mut index = 0;
+        while index < self.roles.len() {
+            let role = &self.roles[index];
+            if self.base_config.verbose {
+                println!();
+                println!("{}== {} =={}", BOLD, role.name, RESET);
+            }
+            info!(role = %role.name, "Running team role");
+
+            let role_task = format!("## Task\n{}\n\n{}", task, outcome.scratchpad.to_context());
+            let state = self.build_agent(role).run(&role_task).await?;
+
+            let response = state
+                .final_response
+                .clone()
+                .or_else(|| state.error.clone())
+                .unwrap_or_default();
+            outcome.scratchpad.add(&role.name, &response);
+
+            let failed = state.error.is_some();
+            outcome.runs.push((role.name.clone(), state));
+            if failed {
+                break;
+            }
+
+            // Send work back to the previous role when a review asks for changes
+            if index > 0
+                && role.is_reviewer()
+                && TeamRole::requests_changes(&response)
+                && outcome.review_rounds < self.max_review_rounds
+            {
+                outcome.review_rounds += 1;
+                info!(role = %role.name, round = outcome.review_rounds, "Review requested changes");
+                if self.base_config.verbose {
+                    println!(
+                        "{}[Team]{} {} requested changes (round {}/{})",
+                        YELLOW, RESET, role.name, outcome.review_rounds, self.max_review_rounds
+                    );
+                }
+                index -= 1;
+                continue;
+            }
+
+            index += 1;
+        }
+
+        Ok(outcome)
+    }
+
+    /// Create the agent for a role with its model and toolset
+    fn build_agent(&self, role: &TeamRole) -> AgentLoop {
+        let mut registry = create_default_registry();
+        if let Some(ref tools) = role.tools {
+            registry.retain(tools);
+        }
+        let confirmation = if self.base_config.auto_mode {
+            TerminalConfirmation::auto()
+        } else {
+            TerminalConfirmation::new()
+        };
+        let mut router = ToolRouter::new(registry, confirmation);
+        if let Some(ref policy) = self.base_config.confirm_policy {
+            router.set_confirmation(policy.clone());
+        }
+
+        let mut config = self.base_config.clone().with_role_instructions(&role.instructions);
+        if let Some(ref model) = role.model {
+            // The team model's alias options don't carry over to another model
+            if *model != config.model {
+                config.temperature = None;
+                config.num_ctx = None;
+            }
+            config.model = model.clone();
+        }
+
+        AgentLoop::new(self.client.clone(), router, config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_team_builtin() {
+        let roles = parse_team("planner, coder,reviewer", &HashMap::new()).unwrap();
+        let names: Vec<_> = roles.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["planner", "coder", "reviewer"]);
+        assert!(roles[0].tools.as_ref().unwrap().contains(&"grep".to_string()));
+        assert!(roles[1].tools.is_none());
+    }
+
+    #[test]
+    fn test_parse_team_unknown_role() {
+        let err = parse_team("planner,poet", &HashMap::new()).unwrap_err();
+        assert!(err.to_string().contains("Unknown role 'poet'"));
+        assert!(parse_team(" , ", &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_parse_team_custom_role() {
+        let mut custom = HashMap::new();
+        custom.insert(
+            "coder".to_string(),
+            RoleConfig {
+                system_prompt: None,
+                model: Some("qwen2.5-coder:14b".to_string()),
+                tools: None,
+            },
+        );
+        custom.insert(
+            "docs".to_string(),
+            RoleConfig {
+                system_prompt: Some("You write documentation.".to_string()),
+                model: None,
+                tools: Some(vec!["file_write".to_string()]),
+            },
+        );
+
+        let roles = parse_team("coder,docs", &custom).unwrap();
+        assert_eq!(roles[0].model.as_deref(), Some("qwen2.5-coder:14b"));
+        assert!(roles[0].instructions.contains("You are the coder"));
+        assert_eq!(roles[1].instructions, "You write documentation.");
+        assert_eq!(roles[1].tools, Some(vec!["file_write".to_string()]));
+    }
+
+    #[test]
+    fn test_requests_changes() {
+        assert!(TeamRole::requests_changes("Changes requested:\n1. Fix the test"));
+        assert!(TeamRole::requests_changes("\n**CHANGES REQUESTED**\n- Handle the error"));
+        assert!(TeamRole::requests_changes("## Changes Requested"));
+        assert!(!TeamRole::requests_changes("APPROVED. Looks good."));
+        assert!(!TeamRole::requests_changes("APPROVED: no changes requested."));
+        assert!(!TeamRole::requests_changes("I addressed the changes requested in the review."));
+
+        assert!(TeamRole::builtin("reviewer").unwrap().is_reviewer());
+        assert!(!TeamRole::builtin("coder").unwrap().is_reviewer());
+        assert!(!TeamRole::builtin("tester").unwrap().is_reviewer());
+        let custom = RoleConfig {
+            system_prompt: Some("Audit security. Start with CHANGES REQUESTED if anything is unsafe.".to_string()),
+            ..RoleConfig::default()
+        };
+        assert!(TeamRole::from_config("auditor", &custom).is_reviewer());
+    }
+
+    #[test]
+    fn test_scratchpad_context() {
+        let mut pad = Scratchpad::default();
+        assert!(pad.to_context().is_empty());
+
+        pad.add("planner", "1. Do it\n");
+        pad.add("coder", "Done");
+        let ctx = pad.to_context();
+        assert!(ctx.starts_with("## Team Scratchpad"));
+        assert!(ctx.contains("### planner\n1. Do it\n"));
+        assert!(ctx.contains("### coder\nDone"));
+    }
+}
@@ -0,0 +1,245 @@
+//! Pruning the tool definitions sent to the model
+//!
+//! Every tool definition costs prompt tokens on every request, which adds up
+//! on models with small context windows. When pruning is on, each request
+//! offers the core tools, the tools already used in the run, and the tools
+//! whose name and description best match the task and the latest message.
+//! The `list_more_tools` meta-tool lists the rest and unlocks them.
+
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+
+/// Name of the meta-tool answering for the tools left out
+pub const LIST_MORE_TOOLS: &str = "list_more_tools";
+
+/// Tools offered whenever they are registered
+const CORE_TOOLS: &[&str] = &["file_read", "file_write", "grep", "glob", "bash"];
+
+/// Context windows (tokens) at or below which tools are pruned by default
+pub const SMALL_CONTEXT_WINDOW: usize = 16384;
+
+/// Tools offered per request on small models when `agent.max_tools` is unset
+pub const DEFAULT_MAX_TOOLS: usize = 8;
+
+/// Words too common to say which tool a request needs
+const STOP_WORDS: &[&str] = &[
+    "the", "and", "for", "with", "this", "that", "these", "those", "you", "your", "are", "was", "were", "can",
+    "could", "should", "would", "will", "please", "from", "into", "then", "them", "they", "what", "when", "where",
+    "which", "how", "why", "all", "any", "some", "not", "but", "its", "has", "have", "had", "does", "did", "use",
+    "using", "make", "need", "want", "also", "just", "about", "there", "here", "out",
+];
+
+/// The tools offered to the model during one agent run
+#[derive(Debug, Clone)]
+pub struct ToolSelection {
+    max_tools: usize,
+    task_keywords: Vec<String>,
+    /// Used or unlocked during the run; always offered from then on
+    pinned: BTreeSet<String>,
+    offered: Vec<String>,
+}
+
+impl ToolSelection {
+    /// Select up to `max_tools` tools per request for a task
+    pub fn new(task: &str, max_tools: usize) -> Self {
+        Self {
+            max_tools,
+            task_keywords: keywords(task),
+            pinned: BTreeSet::new(),
+            offered: Vec::new(),
+        }
+    }
+
+    /// Choose the tools for the next request from every registered tool
+    /// (name and description), given the latest message of the run
+    pub fn select(&mut self, tools: &[(String, String)], recent: &str) -> &[String] {
+        let mut keywords = self.task_keywords.clone();
+        keywords.extend(keywords_of(recent).filter(|k| !self.task_keywords.contains(k)));
+
+        let mut offered: Vec<String> = tools
+            .iter()
+            .filter(|(name, _)| CORE_TOOLS.contains(&name.as_str()) || self.pinned.contains(name))
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        let mut scored: Vec<(f32, &String)> = tools
+            .iter()
+            .filter(|(name, _)| !offered.contains(name))
+            .map(|(name, description)| (score(&keywords, name, description), name))
+            .filter(|(score, _)| *score > 0.0)
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+
+        let room = self.max_tools.saturating_sub(offered.len());
+        offered.extend(scored.into_iter().take(room).map(|(_, name)| name.clone()));
+        offered.sort();
+        self.offered = offered;
+        &self.offered
+    }
+
+    /// Tools chosen by the last [`ToolSelection::select`]
+    pub fn offered(&self) -> &[String] {
+        &self.offered
+    }
+
+    /// Keep offering a tool for the rest of the run
+    pub fn pin(&mut self, name: &str) {
+        if name != LIST_MORE_TOOLS {
+            self.pinned.insert(name.to_string());
+        }
+    }
+
+    /// Answer a `list_more_tools` call: list the tools left out, or unlock
+    /// the ones named in `names`
+    pub fn list_more(&mut self, tools: &[(String, String)], args: &Value) -> String {
+        let names: Vec<&str> = args
+            .get("names")
+            .and_then(|v| v.as_array())
+            .map(|names| names.iter().filter_map(|n| n.as_str()).collect())
+            .unwrap_or_default();
+
+        if names.is_empty() {
+            let hidden: Vec<String> = tools
+                .iter()
+                .filter(|(name, _)| !self.offered.contains(name))
+                .map(|(name, description)| format!("- {}: {}", name, description))
+                .collect();
+            if hidden.is_empty() {
+                return "All tools are already available".to_string();
+            }
+            return format!(
+                "More tools:\n{}\n\nCall {} with names to make them available.",
+                hidden.join("\n"),
+                LIST_MORE_TOOLS
+            );
+        }
+
+        let (known, unknown): (Vec<&str>, Vec<&str>) =
+            names.into_iter().partition(|n| tools.iter().any(|(name, _)| name == n));
+        for name in &known {
+            self.pin(name);
+        }
+
+        let mut reply = String::new();
+        if !known.is_empty() {
+            reply.push_str(&format!("Now available: {}", known.join(", ")));
+        }
+        if !unknown.is_empty() {
+            if !reply.is_empty() {
+                reply.push('\n');
+            }
+            reply.push_str(&format!("Unknown tools: {}", unknown.join(", ")));
+        }
+        reply
+    }
+
+    /// Description and JSON schema of the meta-tool
+    pub fn meta_tool() -> (&'static str, Value) {
+        (
+            "List tools not shown here (with no arguments), or make the named tools available for the next step.",
+            json!({
+                "type": "object",
+                "properties": {
+                    "names": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Tools to make available"
+                    }
+                }
+            }),
+        )
+    }
+}
+
+/// How well a tool's name and description match the keywords: name matches
+/// count three times as much as description matches
+fn score(keywords: &[String], name: &str, description: &str) -> f32 {
+    let name_words: Vec<String> = name.split('_').map(str::to_lowercase).collect();
+    let description_words: Vec<String> = keywords_of(description).collect();
+
+    keywords
+        .iter()
+        .map(|keyword| {
+            if name_words.iter().any(|w| similar(w, keyword)) {
+                3.0
+            } else if description_words.iter().any(|w| similar(w, keyword)) {
+                1.0
+            } else {
+                0.0
+            }
+        })
+        .sum()
+}
+
+/// Same word, or sharing a 4-character stem ("tests" and "testing")
+fn similar(a: &str, b: &str) -> bool {
+    a == b || a.chars().zip(b.chars()).take_while(|(x, y)| x == y).count() >= 4
+}
+
+fn keywords(text: &str) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    for word in keywords_of(text) {
+        if !words.contains(&word) {
+            words.push(word);
+        }
+    }
+    words
+}
+
+fn keywords_of(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.len() >= 3)
+        .map(str::to_lowercase)
+        .filter(|w| !STOP_WORDS.contains(&w.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tools() -> Vec<(String, String)> {
+        [
+            ("file_read", "Read the contents of a file"),
+            ("bash", "Execute a bash command"),
+            ("grep", "Search file contents with a regex"),
+            ("web_fetch", "Fetch a web page by URL and return its text"),
+            ("git_diff", "Show the diff of uncommitted changes in the git repository"),
+            ("git_commit", "Commit staged changes with a message"),
+            ("process_start", "Start a long-running command such as a dev server in the background"),
+            ("lsp_definition", "Find where a symbol is defined using the language server"),
+        ]
+        .into_iter()
+        .map(|(n, d)| (n.to_string(), d.to_string()))
+        .collect()
+    }
+
+    #[test]
+    fn test_select_matches_task() {
+        let mut selection = ToolSelection::new("Commit the changes to git with a good message", 6);
+        let offered = selection.select(&tools(), "").to_vec();
+        assert!(offered.contains(&"git_commit".to_string()));
+        assert!(offered.contains(&"git_diff".to_string()));
+        assert!(offered.contains(&"bash".to_string()));
+        assert!(!offered.contains(&"web_fetch".to_string()));
+        assert!(offered.len() <= 6);
+
+        // The latest message can bring in other tools
+        let offered = selection.select(&tools(), "The docs are at this URL, fetch them").to_vec();
+        assert!(offered.contains(&"web_fetch".to_string()));
+    }
+
+    #[test]
+    fn test_list_more_unlocks_tools() {
+        let mut selection = ToolSelection::new("read main.rs", 3);
+        selection.select(&tools(), "");
+        assert!(!selection.offered().contains(&"process_start".to_string()));
+
+        let listing = selection.list_more(&tools(), &json!({}));
+        assert!(listing.contains("- process_start: Start a long-running command"));
+        assert!(!listing.contains("- file_read"));
+
+        let reply = selection.list_more(&tools(), &json!({ "names": ["process_start", "nope"] }));
+        assert_eq!(reply, "Now available: process_start\nUnknown tools: nope");
+        assert!(selection.select(&tools(), "").contains(&"process_start".to_string()));
+    }
+}
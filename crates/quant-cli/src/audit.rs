@@ -0,0 +1,464 @@
+//! Append-only audit log of tool calls
+//!
+//! Every tool call that goes through the [`ToolRouter`](crate::tools::router::ToolRouter)
+//! is recorded as one JSON line in `<data dir>/quant/audit.jsonl`: when, by
+//! which user on which host, the tool, a SHA-256 hash of its arguments, how
+//! it ended and how long it ran. Arguments are hashed rather than stored, so
+//! the log holds no file contents or secrets but can still confirm a known
+//! call. `quant audit tail` and `quant audit search` read it back.
+//!
+//! `[audit] syslog = true` also sends each record to the system log, and
+//! `[audit] max_calls_per_minute` holds back tool calls beyond that rate.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration as ChronoDuration, Local, NaiveDate, Utc};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::config::AuditConfig;
+use crate::tools::router::RouteResult;
+use crate::tools::{SecurityLevel, ToolCall};
+
+/// Window the rate limit counts calls in
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// How a tool call ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditStatus {
+    Ok,
+    /// The tool ran and reported a failure
+    Failed,
+    /// Not run; a dry run returned a simulated result
+    Simulated,
+    Denied,
+    Skipped,
+    Aborted,
+    NotFound,
+    /// The tool couldn't be run
+    Error,
+}
+
+impl AuditStatus {
+    fn of(result: &RouteResult, simulated: bool) -> Self {
+        match result {
+            RouteResult::Success(_) if simulated => AuditStatus::Simulated,
+            RouteResult::Success(r) if r.success => AuditStatus::Ok,
+            RouteResult::Success(_) => AuditStatus::Failed,
+            RouteResult::Denied => AuditStatus::Denied,
+            RouteResult::Skipped => AuditStatus::Skipped,
+            RouteResult::Aborted => AuditStatus::Aborted,
+            RouteResult::NotFound(_) => AuditStatus::NotFound,
+            RouteResult::Error(_) => AuditStatus::Error,
+        }
+    }
+}
+
+impl fmt::Display for AuditStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = serde_json::to_value(self).ok().and_then(|v| v.as_str().map(str::to_string));
+        write!(f, "{}", name.unwrap_or_default())
+    }
+}
+
+/// One line of the audit log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub timestamp: DateTime<Utc>,
+    pub user: String,
+    pub host: String,
+    /// PID of the quant process
+    pub pid: u32,
+    pub working_dir: PathBuf,
+    pub tool: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<SecurityLevel>,
+    /// SHA-256 of the arguments as compact JSON
+    pub args_sha256: String,
+    pub status: AuditStatus,
+    /// Time spent running the tool (not waiting for confirmation)
+    pub duration_ms: u64,
+}
+
+impl AuditRecord {
+    /// A record of a call made now by this process
+    pub fn new(
+        tool_call: &ToolCall,
+        level: Option<SecurityLevel>,
+        working_dir: &Path,
+        status: AuditStatus,
+        duration: Duration,
+    ) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            user: current_user(),
+            host: current_host(),
+            pid: std::process::id(),
+            working_dir: working_dir.to_path_buf(),
+            tool: tool_call.name.clone(),
+            level,
+            args_sha256: hash_args(&tool_call.arguments),
+            status,
+            duration_ms: duration.as_millis() as u64,
+        }
+    }
+
+    /// One line for a terminal
+    pub fn summary(&self) -> String {
+        format!(
+            "{}  {}@{}  {:<14} {:<10} {:>7}ms  {}  {}",
+            self.timestamp.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+            self.user,
+            self.host,
+            self.tool,
+            self.status,
+            self.duration_ms,
+            &self.args_sha256[..self.args_sha256.len().min(12)],
+            self.working_dir.display()
+        )
+    }
+}
+
+/// SHA-256 of a call's arguments, as compact JSON with sorted keys
+pub fn hash_args(args: &Value) -> String {
+    format!("{:x}", Sha256::digest(args.to_string().as_bytes()))
+}
+
+fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+fn current_host() -> String {
+    nix::unistd::gethostname()
+        .ok()
+        .and_then(|h| h.into_string().ok())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Path of the audit log
+pub fn audit_log_path() -> Result<PathBuf> {
+    let data_dir = dirs::data_local_dir()
+        .or_else(dirs::data_dir)
+        .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+
+    Ok(data_dir.join("quant").join("audit.jsonl"))
+}
+
+/// Appends records to the audit log and enforces the rate limit
+pub struct AuditLog {
+    path: PathBuf,
+    syslog: bool,
+    max_calls_per_minute: usize,
+    /// Start times of the calls in the current rate window
+    recent: Mutex<VecDeque<Instant>>,
+}
+
+/// This process's audit log, from `[audit]`
+static GLOBAL: Lazy<Option<AuditLog>> = Lazy::new(|| {
+    // Unit tests route plenty of tool calls; keep them out of the user's log
+    if cfg!(test) {
+        return None;
+    }
+    let config = crate::config::UserConfig::load().unwrap_or_default().audit;
+    if !config.enabled {
+        return None;
+    }
+    match audit_log_path() {
+        Ok(path) => Some(AuditLog::new(path, &config)),
+        Err(e) => {
+            warn!(error = %e, "Audit log disabled");
+            None
+        }
+    }
+});
+
+/// The audit log, unless `[audit] enabled = false`
+pub fn global() -> Option<&'static AuditLog> {
+    GLOBAL.as_ref()
+}
+
+impl AuditLog {
+    pub fn new(path: PathBuf, config: &AuditConfig) -> Self {
+        Self {
+            path,
+            syslog: config.syslog,
+            max_calls_per_minute: config.max_calls_per_minute,
+            recent: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Wait until another call fits within `max_calls_per_minute`
+    pub async fn throttle(&self) {
+        if self.max_calls_per_minute == 0 {
+            return;
+        }
+        loop {
+            let wait = {
+                let mut recent = self.recent.lock();
+                let now = Instant::now();
+                while recent.front().is_some_and(|t| now.duration_since(*t) >= RATE_WINDOW) {
+                    recent.pop_front();
+                }
+                if recent.len() < self.max_calls_per_minute {
+                    recent.push_back(now);
+                    return;
+                }
+                RATE_WINDOW - now.duration_since(recent[0])
+            };
+            warn!(wait_secs = wait.as_secs(), "Tool call rate limit reached");
+            eprintln!(
+                "\x1b[93m[Rate limit]\x1b[0m {} tool calls in the last minute; waiting {}s",
+                self.max_calls_per_minute,
+                wait.as_secs().max(1)
+            );
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Record how a routed call ended
+    pub fn record(
+        &self,
+        tool_call: &ToolCall,
+        level: Option<SecurityLevel>,
+        working_dir: &Path,
+        result: &RouteResult,
+        simulated: bool,
+        duration: Duration,
+    ) {
+        let status = AuditStatus::of(result, simulated);
+        let record = AuditRecord::new(tool_call, level, working_dir, status, duration);
+        if let Err(e) = self.append(&record) {
+            warn!(error = %e, "Failed to write audit record");
+        }
+    }
+
+    /// Append a record, and send it to syslog if configured
+    pub fn append(&self, record: &AuditRecord) -> Result<()> {
+        let line = serde_json::to_string(record)?;
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // One write per line; appends are atomic, so processes can share the log
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        file.write_all(format!("{}\n", line).as_bytes())?;
+
+        if self.syslog {
+            send_syslog(&line);
+        }
+        Ok(())
+    }
+}
+
+/// Send a line to the local syslog daemon (user facility, info level)
+#[cfg(unix)]
+fn send_syslog(line: &str) {
+    use std::os::unix::net::UnixDatagram;
+
+    let Ok(socket) = UnixDatagram::unbound() else {
+        return;
+    };
+    let message = format!("<14>quant[{}]: {}", std::process::id(), line);
+    // Linux and macOS sockets
+    for path in ["/dev/log", "/var/run/syslog"] {
+        if socket.send_to(message.as_bytes(), path).is_ok() {
+            return;
+        }
+    }
+    debug!("No syslog socket to send the audit record to");
+}
+
+#[cfg(not(unix))]
+fn send_syslog(_line: &str) {}
+
+/// Read every record in the log, skipping lines that don't parse
+pub fn read_records(path: &Path) -> Result<Vec<AuditRecord>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(parse_records(&content))
+}
+
+/// Records in JSON lines, skipping lines that don't parse
+pub fn parse_records(content: &str) -> Vec<AuditRecord> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                debug!(error = %e, "Skipping malformed audit record");
+                None
+            }
+        })
+        .collect()
+}
+
+/// Conditions for `quant audit search`; unset ones match everything
+#[derive(Debug, Default)]
+pub struct AuditFilter {
+    /// Text in the tool, user, host, working directory or argument hash
+    pub query: Option<String>,
+    pub tool: Option<String>,
+    pub status: Option<AuditStatus>,
+    pub since: Option<DateTime<Utc>>,
+}
+
+impl AuditFilter {
+    pub fn matches(&self, record: &AuditRecord) -> bool {
+        if self.tool.as_ref().is_some_and(|tool| *tool != record.tool) {
+            return false;
+        }
+        if self.status.is_some_and(|status| status != record.status) {
+            return false;
+        }
+        if self.since.is_some_and(|since| record.timestamp < since) {
+            return false;
+        }
+        match self.query {
+            Some(ref query) => {
+                let query = query.to_lowercase();
+                [
+                    record.tool.as_str(),
+                    record.user.as_str(),
+                    record.host.as_str(),
+                    record.args_sha256.as_str(),
+                    &record.working_dir.to_string_lossy(),
+                ]
+                .iter()
+                .any(|field| field.to_lowercase().contains(&query))
+            }
+            None => true,
+        }
+    }
+}
+
+/// Parse a status name such as `denied`
+pub fn parse_status(s: &str) -> Result<AuditStatus> {
+    serde_json::from_value(Value::String(s.to_lowercase())).with_context(|| format!("Unknown status `{}`", s))
+}
+
+/// Parse a start time: an age such as `30m`, `2h` or `7d`, a date
+/// (`2026-10-01`, local midnight) or an RFC 3339 time
+pub fn parse_since(s: &str) -> Result<DateTime<Utc>> {
+    let s = s.trim();
+    if let Some(unit) = s.chars().last().filter(|c| c.is_ascii_alphabetic()) {
+        if let Ok(n) = s[..s.len() - 1].parse::<i64>() {
+            let age = match unit {
+                'm' => ChronoDuration::minutes(n),
+                'h' => ChronoDuration::hours(n),
+                'd' => ChronoDuration::days(n),
+                _ => anyhow::bail!("Unknown unit in `{}`; use m, h or d", s),
+            };
+            return Ok(Utc::now() - age);
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        let midnight = date.and_hms_opt(0, 0, 0).expect("midnight is valid");
+        return midnight
+            .and_local_timezone(Local)
+            .earliest()
+            .map(|t| t.with_timezone(&Utc))
+            .with_context(|| format!("No local midnight on {}", s));
+    }
+    DateTime::parse_from_rfc3339(s)
+        .map(|t| t.with_timezone(&Utc))
+        .with_context(|| format!("Invalid time `{}`; use e.g. 2h, 7d or 2026-10-01", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::ToolResult;
+    use serde_json::json;
+
+    fn call(name: &str) -> ToolCall {
+        ToolCall {
+            name: name.to_string(),
+            arguments: json!({ "command": "cargo test", "timeout": 60 }),
+        }
+    }
+
+    #[test]
+    fn test_append_and_search() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let log = AuditLog::new(path.clone(), &AuditConfig::default());
+
+        let ok = RouteResult::Success(ToolResult::success("done"));
+        log.record(&call("bash"), Some(SecurityLevel::Dangerous), dir.path(), &ok, false, Duration::from_millis(1500));
+        log.record(&call("bash"), Some(SecurityLevel::Dangerous), dir.path(), &RouteResult::Denied, false, Duration::ZERO);
+        log.record(&call("file_write"), None, dir.path(), &ok, true, Duration::ZERO);
+        fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"not json\n").unwrap();
+
+        let records = read_records(&path).unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].status, AuditStatus::Ok);
+        assert_eq!(records[0].duration_ms, 1500);
+        assert_eq!(records[0].args_sha256, hash_args(&call("bash").arguments));
+        assert_eq!(records[1].status, AuditStatus::Denied);
+        assert_eq!(records[2].status, AuditStatus::Simulated);
+
+        let filter = AuditFilter {
+            tool: Some("bash".to_string()),
+            status: Some(parse_status("denied").unwrap()),
+            ..AuditFilter::default()
+        };
+        assert_eq!(records.iter().filter(|r| filter.matches(r)).count(), 1);
+        let filter = AuditFilter {
+            query: Some(records[2].args_sha256[..8].to_string()),
+            since: Some(parse_since("1h").unwrap()),
+            ..AuditFilter::default()
+        };
+        assert_eq!(records.iter().filter(|r| filter.matches(r)).count(), 3);
+    }
+
+    #[test]
+    fn test_hash_args_ignores_key_order() {
+        let a = json!({ "path": "a.txt", "content": "x" });
+        let b: Value = serde_json::from_str(r#"{"content":"x","path":"a.txt"}"#).unwrap();
+        assert_eq!(hash_args(&a), hash_args(&b));
+        assert_ne!(hash_args(&a), hash_args(&json!({ "path": "b.txt", "content": "x" })));
+    }
+
+    #[test]
+    fn test_parse_since() {
+        let two_hours = parse_since("2h").unwrap();
+        assert!((Utc::now() - two_hours - ChronoDuration::hours(2)).num_seconds().abs() < 5);
+        assert!(parse_since("2026-10-01").is_ok());
+        assert!(parse_since("2026-10-01T12:00:00Z").is_ok());
+        assert!(parse_since("2w").is_err());
+        assert!(parse_since("yesterday").is_err());
+        assert!(parse_status("sometimes").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_throttle() {
+        let config = AuditConfig {
+            max_calls_per_minute: 2,
+            ..AuditConfig::default()
+        };
+        let log = AuditLog::new(PathBuf::from("/nonexistent"), &config);
+        log.throttle().await;
+        log.throttle().await;
+        // A third call in the same minute waits
+        let third = tokio::time::timeout(Duration::from_millis(50), log.throttle()).await;
+        assert!(third.is_err());
+    }
+}
@@ -0,0 +1,65 @@
+//! System clipboard access
+//!
+//! Uses arboard, which talks to the platform clipboard directly (AppKit on
+//! macOS, X11 or the Wayland data-control protocol on Linux, the Win32
+//! clipboard on Windows). On Linux the copying process serves the text, so
+//! the REPL keeps its [`Clipboard`] open for as long as it runs.
+
+use anyhow::{Context, Result};
+pub use arboard::Clipboard;
+
+use crate::code_block::code_blocks;
+
+/// Put text on the clipboard, opening it in `clipboard` on first use
+pub fn copy(clipboard: &mut Option<Clipboard>, text: &str) -> Result<()> {
+    let clipboard = match clipboard {
+        Some(clipboard) => clipboard,
+        None => clipboard.insert(Clipboard::new().context("Could not open the clipboard")?),
+    };
+    clipboard.set_text(text).context("Could not copy to the clipboard")
+}
+
+/// Read text from the clipboard
+pub fn paste() -> Result<String> {
+    Clipboard::new()
+        .context("Could not open the clipboard")?
+        .get_text()
+        .context("Clipboard does not hold text")
+}
+
+/// The fenced code blocks of a response joined for `/copy code`, with how
+/// many there were
+pub fn code_to_copy(text: &str) -> Option<(String, usize)> {
+    let blocks = code_blocks(text);
+    if blocks.is_empty() {
+        return None;
+    }
+    let code = blocks.iter().map(|b| b.code.as_str()).collect::<Vec<_>>().join("\n\n");
+    Some((code, blocks.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_to_copy_joins_blocks() {
+        let reply = "Install it:\n```bash\ncargo add arboard\n```\nThen:\n\n  ```rust\n  let x = 1;\n  ```\nDone.";
+        let (code, count) = code_to_copy(reply).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(code, "cargo add arboard\n\n  let x = 1;");
+    }
+
+    #[test]
+    fn test_code_to_copy_edge_cases() {
+        // Prose only, or only empty blocks
+        assert_eq!(code_to_copy("No code in this answer."), None);
+        assert_eq!(code_to_copy("```\n\n```\n```python\n```"), None);
+
+        // A response cut off inside a block still copies what it has
+        assert_eq!(code_to_copy("```python\nprint(1)\nprint(2)"), Some(("print(1)\nprint(2)".to_string(), 1)));
+
+        // Windows line endings don't end up in the copied code
+        assert_eq!(code_to_copy("```sh\r\nls\r\n```\r\n"), Some(("ls".to_string(), 1)));
+    }
+}
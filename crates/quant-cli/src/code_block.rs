@@ -0,0 +1,119 @@
+//! Fenced code blocks in model responses
+//!
+//! `/copy code` in the REPL copies the fenced blocks of the assistant's
+//! reply, and `/runlast` takes the last one and runs it. Shell blocks run
+//! as they are; blocks in a few scripting languages are fed to their
+//! interpreter through a heredoc, so either way the result is a single bash
+//! command for the bash or sandbox tool.
+
+/// Delimits a script fed to an interpreter on stdin
+const HEREDOC_MARKER: &str = "QUANT_CODE_BLOCK";
+
+/// Interpreters for the languages `/runlast` can run, by fence tag
+const INTERPRETERS: &[(&[&str], &str)] = &[
+    (&["python", "py", "python3"], "python3 -"),
+    (&["javascript", "js", "node"], "node -"),
+    (&["ruby", "rb"], "ruby -"),
+    (&["perl", "pl"], "perl -"),
+];
+
+/// Tags (or no tag) of blocks that are already shell commands
+const SHELL_TAGS: &[&str] = &["", "sh", "bash", "shell", "zsh", "console", "terminal"];
+
+/// A fenced code block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeBlock {
+    /// Language tag after the opening fence (lowercase, may be empty)
+    pub lang: String,
+    pub code: String,
+}
+
+impl CodeBlock {
+    /// The bash command that runs this block, if its language is supported
+    pub fn command(&self) -> Option<String> {
+        if SHELL_TAGS.contains(&self.lang.as_str()) {
+            // Drop "$ " prompts from pasted terminal sessions
+            let code = self
+                .code
+                .lines()
+                .map(|line| line.strip_prefix("$ ").unwrap_or(line))
+                .collect::<Vec<_>>()
+                .join("\n");
+            return Some(code);
+        }
+
+        INTERPRETERS
+            .iter()
+            .find(|(tags, _)| tags.contains(&self.lang.as_str()))
+            .map(|(_, interpreter)| {
+                format!(
+                    "{} <<'{marker}'\n{}\n{marker}",
+                    interpreter,
+                    self.code,
+                    marker = HEREDOC_MARKER
+                )
+            })
+    }
+}
+
+/// The fenced code blocks in a response, in order; an unclosed final block
+/// counts (the response may have been cut off)
+pub fn code_blocks(text: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut open: Option<(String, Vec<&str>)> = None;
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        match open.take() {
+            None => {
+                if let Some(tag) = trimmed.strip_prefix("```") {
+                    let lang = tag.split_whitespace().next().unwrap_or("").to_lowercase();
+                    open = Some((lang, Vec::new()));
+                }
+            }
+            Some((lang, lines)) if trimmed.trim_end() == "```" => {
+                blocks.push(CodeBlock { lang, code: lines.join("\n") });
+            }
+            Some((lang, mut lines)) => {
+                lines.push(line);
+                open = Some((lang, lines));
+            }
+        }
+    }
+
+    if let Some((lang, lines)) = open {
+        blocks.push(CodeBlock { lang, code: lines.join("\n") });
+    }
+    blocks.retain(|block| !block.code.trim().is_empty());
+    blocks
+}
+
+/// The last fenced code block in a response
+pub fn last_code_block(text: &str) -> Option<CodeBlock> {
+    code_blocks(text).pop()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_last_code_block() {
+        let reply = "First:\n```python\nprint(1)\n```\nThen:\n```bash\n$ ls -la\necho done\n```\nThat's it.";
+        let block = last_code_block(reply).unwrap();
+        assert_eq!(block.lang, "bash");
+        assert_eq!(block.command().unwrap(), "ls -la\necho done");
+
+        let block = last_code_block("```Python\nimport sys\nprint(sys.argv)").unwrap();
+        assert_eq!(block.lang, "python");
+        assert_eq!(
+            block.command().unwrap(),
+            "python3 - <<'QUANT_CODE_BLOCK'\nimport sys\nprint(sys.argv)\nQUANT_CODE_BLOCK"
+        );
+
+        assert_eq!(last_code_block("```rust\nfn main() {}\n```").unwrap().command(), None);
+        assert_eq!(last_code_block("No code here"), None);
+        assert_eq!(last_code_block("```\n\n```"), None);
+        assert_eq!(code_blocks(reply).len(), 2);
+    }
+}
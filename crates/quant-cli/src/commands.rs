@@ -5,17 +5,23 @@
 use anyhow::{Context, Result};
 use futures::StreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
-use llm_core::{ChatMessage, Config, OllamaClient, OllamaStatus};
+use llm_core::process::{stop_supervisor, RestartPolicy, ServeFiles, Supervisor};
+use llm_core::{ChatMessage, Config, HealthStatus, OllamaClient, OllamaStatus, PullProgress, WarmModel};
 use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::time::Duration;
 
 use crate::agent::{AgentConfig, AgentLoop};
+use crate::config::ModelOptions;
 use crate::context::ContextManager;
+use crate::intent::{Intent, IntentGuess};
+use crate::model_alias::resolve_model;
 use crate::tools::builtin::create_default_registry;
+use crate::tools::policy::ConfirmPolicy;
 use crate::tools::router::ToolRouter;
 use crate::tools::security::TerminalConfirmation;
+use crate::transcript::{self, StdinFormat};
 
 // ANSI color codes
 const GREEN: &str = "\x1b[92m";
@@ -27,6 +33,15 @@ const BOLD: &str = "\x1b[1m";
 const DIM: &str = "\x1b[2m";
 const RESET: &str = "\x1b[0m";
 
+/// Characters of a session summary shown in `quant sessions list`
+const SESSION_SUMMARY_CHARS: usize = 100;
+
+/// How long `serve start` waits for Ollama to answer
+const SERVE_START_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long `serve stop` waits for the supervisor to shut Ollama down
+const SERVE_STOP_TIMEOUT: Duration = Duration::from_secs(15);
+
 fn print_status(ok: bool, msg: &str) {
     let icon = if ok {
         format!("{}✓{}", GREEN, RESET)
@@ -38,8 +53,8 @@ fn print_status(ok: bool, msg: &str) {
 
 /// Show Ollama status and system info
 pub async fn status() -> Result<()> {
-    let config = Config::load().context("Failed to load llm.toml")?;
-    let client = OllamaClient::new(config.ollama_url());
+    let config = crate::config::load_llm_config().context("Failed to load llm.toml")?;
+    let client = config.ollama_client();
 
     println!("{}Ollama Status{}", BOLD, RESET);
     println!("  Endpoint: {}", config.ollama_url());
@@ -85,15 +100,25 @@ pub async fn status() -> Result<()> {
     }
 
     // Show running models
-    match client.list_running().await {
-        Ok(running) if !running.is_empty() => {
-            println!("\n{}Running Models{}", BOLD, RESET);
-            for m in running {
-                let vram_gb = m.size_vram as f64 / (1024.0 * 1024.0 * 1024.0);
-                println!("  - {} (VRAM: {:.1} GB)", m.name, vram_gb);
+    let running = client.list_running().await.unwrap_or_default();
+    if !running.is_empty() {
+        println!("\n{}Running Models{}", BOLD, RESET);
+        for m in &running {
+            let vram_gb = m.size_vram as f64 / (1024.0 * 1024.0 * 1024.0);
+            println!("  - {} (VRAM: {:.1} GB)", m.name, vram_gb);
+        }
+    }
+
+    // Show which [warmup] models are loaded
+    let pool = config.warm_pool();
+    if !pool.is_empty() {
+        println!("\n{}Warm Pool{}", BOLD, RESET);
+        for model in &pool {
+            match running.iter().find(|m| is_same_model(&m.name, &model.name)) {
+                Some(m) => print_status(true, &format!("{} (loaded, expires: {})", model.name, m.expires_at)),
+                None => print_status(false, &format!("{} (not loaded; run {}quant models warm{})", model.name, BLUE, RESET)),
             }
         }
-        _ => {}
     }
 
     // System info
@@ -109,8 +134,8 @@ pub async fn status() -> Result<()> {
 
 /// Health check with retries
 pub async fn health(timeout_secs: u64) -> Result<()> {
-    let config = Config::load().context("Failed to load llm.toml")?;
-    let client = OllamaClient::new(config.ollama_url());
+    let config = crate::config::load_llm_config().context("Failed to load llm.toml")?;
+    let client = config.ollama_client();
 
     let pb = ProgressBar::new(timeout_secs);
     pb.set_style(
@@ -140,10 +165,47 @@ pub async fn health(timeout_secs: u64) -> Result<()> {
     anyhow::bail!("Ollama did not become ready within timeout")
 }
 
+/// Diagnose the local environment (models volume, disk, port, versions)
+pub async fn doctor() -> Result<()> {
+    let config = crate::config::load_llm_config().context("Failed to load llm.toml")?;
+    let report = llm_core::health::run_checks(&config).await;
+
+    println!("{}Environment Health{}", BOLD, RESET);
+    for check in &report.checks {
+        let color = match check.status {
+            HealthStatus::Ok => GREEN,
+            HealthStatus::Warning => YELLOW,
+            HealthStatus::Error => RED,
+        };
+        println!(
+            "  {}{}{} {:<12} {}",
+            color,
+            check.status.symbol(),
+            RESET,
+            check.name,
+            check.detail
+        );
+    }
+
+    match report.overall() {
+        HealthStatus::Ok => {
+            println!("
+{}✓{} No problems found", GREEN, RESET);
+            Ok(())
+        }
+        HealthStatus::Warning => {
+            println!("
+{}⚠{} {} warning(s)", YELLOW, RESET, report.problems().count());
+            Ok(())
+        }
+        HealthStatus::Error => anyhow::bail!("{} check(s) failed", report.problems().count()),
+    }
+}
+
 /// List available models
 pub async fn models_list() -> Result<()> {
-    let config = Config::load().context("Failed to load llm.toml")?;
-    let client = OllamaClient::new(config.ollama_url());
+    let config = crate::config::load_llm_config().context("Failed to load llm.toml")?;
+    let client = config.ollama_client();
 
     // Show local GGUF files
     println!("{}Local GGUF Files{}", BOLD, RESET);
@@ -190,8 +252,8 @@ pub async fn models_list() -> Result<()> {
 
 /// Pull a model from Ollama registry
 pub async fn models_pull(name: &str) -> Result<()> {
-    let config = Config::load().context("Failed to load llm.toml")?;
-    let client = OllamaClient::new(config.ollama_url());
+    let config = crate::config::load_llm_config().context("Failed to load llm.toml")?;
+    let client = config.ollama_client();
 
     // Check Ollama is running
     if !client.health_check().await.unwrap_or(false) {
@@ -206,43 +268,101 @@ pub async fn models_pull(name: &str) -> Result<()> {
         .await
         .context("Failed to start model pull")?;
 
-    let pb = ProgressBar::new(100);
-    pb.set_style(
-        ProgressStyle::default_bar()
-            .template("{spinner:.cyan} {msg} [{bar:30.cyan/dim}] {percent}%")
-            .unwrap()
-            .progress_chars("=>-"),
+    let mut bar = PullBar::new(name);
+    while let Some(progress) = stream.next().await {
+        bar.update(&progress?);
+    }
+    bar.finish();
+    println!("{}✓{} Pulled {}", GREEN, RESET, name);
+
+    Ok(())
+}
+
+/// Offer to pull a model that isn't installed, then carry on
+///
+/// Only asks when `interactive`; otherwise a missing model fails with the
+/// not-installed error and its `quant models pull` hint.
+pub async fn ensure_model(client: &OllamaClient, model: &str, interactive: bool) -> Result<()> {
+    let mut bar: Option<PullBar> = None;
+    let pulled = client
+        .ensure_model(
+            model,
+            |size| interactive && confirm_pull(model, size),
+            |progress| bar.get_or_insert_with(|| PullBar::new(model)).update(progress),
+        )
+        .await?;
+
+    if pulled {
+        if let Some(bar) = bar {
+            bar.finish();
+        }
+        eprintln!("{}✓{} Pulled {}", GREEN, RESET, model);
+    }
+    Ok(())
+}
+
+/// Ask on stderr (stdout may be piped) whether to pull a missing model
+fn confirm_pull(model: &str, size: Option<u64>) -> bool {
+    let size = match size {
+        Some(bytes) => format!("~{:.1} GB", bytes as f64 / (1024.0 * 1024.0 * 1024.0)),
+        None => "size unknown".to_string(),
+    };
+    eprint!(
+        "{}Model {} is not installed.{} Pull it now ({})? [y/N] ",
+        YELLOW, model, RESET, size
     );
-    pb.set_message(name.to_string());
+    let _ = io::stderr().flush();
 
-    let mut last_status = String::new();
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
 
-    while let Some(progress) = stream.next().await {
-        let progress = progress?;
+/// Progress bar for a streaming model pull
+struct PullBar {
+    pb: ProgressBar,
+    name: String,
+    last_status: String,
+}
 
+impl PullBar {
+    fn new(name: &str) -> Self {
+        let pb = ProgressBar::new(100);
+        pb.set_style(
+            ProgressStyle::default_bar()
+                .template("{spinner:.cyan} {msg} [{bar:30.cyan/dim}] {percent}%")
+                .unwrap()
+                .progress_chars("=>-"),
+        );
+        pb.set_message(name.to_string());
+        Self { pb, name: name.to_string(), last_status: String::new() }
+    }
+
+    fn update(&mut self, progress: &PullProgress) {
         // Update status message if changed
-        if progress.status != last_status {
-            last_status = progress.status.clone();
-            pb.set_message(format!("{}: {}", name, progress.status));
+        if progress.status != self.last_status {
+            self.last_status = progress.status.clone();
+            self.pb.set_message(format!("{}: {}", self.name, progress.status));
         }
 
         // Update progress bar if we have total/completed info
         if progress.total > 0 {
             let percent = (progress.completed as f64 / progress.total as f64 * 100.0) as u64;
-            pb.set_position(percent);
+            self.pb.set_position(percent);
         }
     }
 
-    pb.finish_and_clear();
-    println!("{}✓{} Pulled {}", GREEN, RESET, name);
-
-    Ok(())
+    fn finish(self) {
+        self.pb.finish_and_clear();
+    }
 }
 
 /// Remove a model
 pub async fn models_rm(name: &str) -> Result<()> {
-    let config = Config::load().context("Failed to load llm.toml")?;
-    let client = OllamaClient::new(config.ollama_url());
+    let config = crate::config::load_llm_config().context("Failed to load llm.toml")?;
+    let client = config.ollama_client();
 
     println!("Removing {}...", name);
     client.delete_model(name).await?;
@@ -253,8 +373,8 @@ pub async fn models_rm(name: &str) -> Result<()> {
 
 /// Show running/loaded models
 pub async fn models_ps() -> Result<()> {
-    let config = Config::load().context("Failed to load llm.toml")?;
-    let client = OllamaClient::new(config.ollama_url());
+    let config = crate::config::load_llm_config().context("Failed to load llm.toml")?;
+    let client = config.ollama_client();
 
     let running = client.list_running().await?;
 
@@ -272,16 +392,176 @@ pub async fn models_ps() -> Result<()> {
     Ok(())
 }
 
+/// Load models and keep them loaded: `names`, or the `[warmup]` pool
+///
+/// Named models in the pool keep its keep-alive; `keep_alive` overrides it.
+pub async fn models_warm(names: Vec<String>, keep_alive: Option<String>) -> Result<()> {
+    let config = crate::config::load_llm_config().context("Failed to load llm.toml")?;
+    let client = config.ollama_client();
+
+    if !client.health_check().await.unwrap_or(false) {
+        anyhow::bail!("Ollama is not running. Start with: quant serve start");
+    }
+
+    let pool = config.warm_pool();
+    let mut models = if names.is_empty() {
+        if pool.is_empty() {
+            anyhow::bail!("No models to warm. Name them, or list them under [warmup] in the config");
+        }
+        pool
+    } else {
+        let aliases = crate::config::UserConfig::load().unwrap_or_default().aliases.models;
+        let mut models = Vec::new();
+        for name in names {
+            let name = resolve_model(&client, &aliases, &name).await?.model;
+            let keep_alive = match pool.iter().find(|m| m.name == name) {
+                Some(pooled) => pooled.keep_alive.clone(),
+                None => config.ollama.keep_alive.clone(),
+            };
+            models.push(WarmModel { name, keep_alive });
+        }
+        models
+    };
+    if let Some(keep_alive) = keep_alive {
+        models.iter_mut().for_each(|m| m.keep_alive = keep_alive.clone());
+    }
+
+    let failed = warm_models(&client, &models, true).await;
+    if failed > 0 {
+        anyhow::bail!("{} of {} models failed to load", failed, models.len());
+    }
+    Ok(())
+}
+
+/// Load each model with its keep-alive, with a spinner if `progress`;
+/// returns how many failed
+async fn warm_models(client: &OllamaClient, models: &[WarmModel], progress: bool) -> usize {
+    let mut failed = 0;
+    for model in models {
+        let spinner = if progress { ProgressBar::new_spinner() } else { ProgressBar::hidden() };
+        spinner.set_style(ProgressStyle::default_spinner().template("{spinner:.cyan} {msg}").unwrap());
+        spinner.set_message(format!("Loading {}...", model.name));
+        spinner.enable_steady_tick(Duration::from_millis(100));
+
+        let result = client.warm_model(&model.name, Some(&model.keep_alive)).await;
+        spinner.finish_and_clear();
+        match result {
+            Ok(()) => {
+                let running = client.list_running().await.unwrap_or_default();
+                let vram = running
+                    .iter()
+                    .find(|m| is_same_model(&m.name, &model.name))
+                    .map(|m| format!(", {:.1} GB VRAM", m.size_vram as f64 / (1024.0 * 1024.0 * 1024.0)))
+                    .unwrap_or_default();
+                println!("{}✓{} {} loaded{} {}({}){}", GREEN, RESET, model.name, vram, DIM, describe_keep_alive(&model.keep_alive), RESET);
+            }
+            Err(e) => {
+                println!("{}✗{} {}: {:#}", RED, RESET, model.name, e);
+                failed += 1;
+            }
+        }
+    }
+    failed
+}
+
+/// Whether Ollama's `listed` name is the model `name` (untagged means `:latest`)
+fn is_same_model(listed: &str, name: &str) -> bool {
+    listed == name || listed.strip_suffix(":latest") == Some(name)
+}
+
+fn describe_keep_alive(keep_alive: &str) -> String {
+    match keep_alive.trim() {
+        "" => "kept loaded for Ollama's default time".to_string(),
+        k if k.starts_with('-') => "kept loaded while Ollama runs".to_string(),
+        k => format!("kept loaded for {}", k),
+    }
+}
+
+/// Lines of a model's license shown by `models show`
+const LICENSE_PREVIEW_LINES: usize = 10;
+
+/// Show an installed model's metadata, prompt template and license
+pub async fn models_show(name: &str) -> Result<()> {
+    let config = crate::config::load_llm_config().context("Failed to load llm.toml")?;
+    let client = config.ollama_client();
+
+    let info = client.show_model(name).await?;
+    let installed = client
+        .list_models()
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .find(|m| m.name == name || m.name == format!("{}:latest", name));
+
+    println!("{}{}{}", BOLD, name, RESET);
+    let field = |label: &str, value: Option<String>| {
+        if let Some(value) = value.filter(|v| !v.is_empty()) {
+            println!("  {:<16}{}", format!("{}:", label), value);
+        }
+    };
+    field("Family", info.details.family.clone());
+    field(
+        "Parameters",
+        info.details
+            .parameter_size
+            .clone()
+            .or_else(|| info.parameter_count().map(|n| format!("{:.1}B", n as f64 / 1e9))),
+    );
+    field("Quantization", info.details.quantization_level.clone());
+    field("Format", info.details.format.clone());
+    field(
+        "Context length",
+        info.context_length().map(|length| match info.num_ctx() {
+            Some(num_ctx) => format!("{} (num_ctx {})", length, num_ctx),
+            None => length.to_string(),
+        }),
+    );
+    field(
+        "Path",
+        crate::model_store::find_blob(name, Some(&config.ollama.ollama_home)).map(|path| path.display().to_string()),
+    );
+    field("Size", installed.as_ref().map(|m| m.size_human()));
+    field("Modified", installed.map(|m| m.modified_at));
+
+    let section = |title: &str, text: &str| {
+        if !text.trim().is_empty() {
+            println!("\n{}{}{}", BOLD, title, RESET);
+            for line in text.trim_end().lines() {
+                println!("  {}", line);
+            }
+        }
+    };
+    section("Modelfile parameters", &info.parameters);
+    section("System prompt", &info.system);
+    section("Template", &info.template);
+
+    let license: Vec<&str> = info.license.trim().lines().collect();
+    if license.len() > LICENSE_PREVIEW_LINES {
+        section("License", &license[..LICENSE_PREVIEW_LINES].join("\n"));
+        println!("  {}... {} more lines{}", DIM, license.len() - LICENSE_PREVIEW_LINES, RESET);
+    } else {
+        section("License", &info.license);
+    }
+
+    Ok(())
+}
+
 /// Start Ollama server
+///
+/// In the background, Ollama runs under a detached `quant serve supervise`
+/// process that captures its output and restarts it if it crashes.
 pub async fn serve_start(foreground: bool) -> Result<()> {
-    let config = Config::load().context("Failed to load llm.toml")?;
+    let config = crate::config::load_llm_config().context("Failed to load llm.toml")?;
 
     // Check if already running
-    let client = OllamaClient::new(config.ollama_url());
+    let client = config.ollama_client();
     if client.health_check().await.unwrap_or(false) {
         println!("Ollama is already running");
         return Ok(());
     }
+    if crate::service::is_installed() {
+        anyhow::bail!("Ollama is installed as a service but not responding; check `quant serve logs`");
+    }
 
     println!("Starting Ollama...");
     println!("  OLLAMA_HOME={}", config.ollama.ollama_home.display());
@@ -290,45 +570,100 @@ pub async fn serve_start(foreground: bool) -> Result<()> {
         config.ollama.host, config.ollama.port
     );
 
-    let mut cmd = Command::new("ollama");
-    cmd.arg("serve")
-        .env(
-            "OLLAMA_HOST",
-            format!("{}:{}", config.ollama.host, config.ollama.port),
-        )
-        .env("OLLAMA_HOME", &config.ollama.ollama_home);
-
     if foreground {
+        // Warm the pool once Ollama is up
+        let pool = config.warm_pool();
+        if !pool.is_empty() {
+            let client = client.clone();
+            tokio::spawn(async move {
+                if wait_until_ready(&client, SERVE_START_TIMEOUT).await {
+                    warm_models(&client, &pool, false).await;
+                }
+            });
+        }
+
         // Run in foreground
-        let status = cmd.status().context("Failed to start Ollama")?;
+        let status = Command::new("ollama")
+            .arg("serve")
+            .envs(config.ollama_env())
+            .status()
+            .context("Failed to start Ollama")?;
         if !status.success() {
             anyhow::bail!("Ollama exited with error");
         }
-    } else {
-        // Run in background
-        cmd.stdout(std::process::Stdio::null())
-            .stderr(std::process::Stdio::null())
-            .spawn()
-            .context("Failed to start Ollama")?;
+        return Ok(());
+    }
 
-        // Wait for it to be ready
-        tokio::time::sleep(Duration::from_secs(2)).await;
+    let files = ServeFiles::default_location()?;
+    if let Some(pid) = files.supervisor_pid() {
+        println!("Ollama is already starting (supervisor PID {})", pid);
+        return Ok(());
+    }
 
-        if client.health_check().await.unwrap_or(false) {
-            println!("{}Ollama started successfully{}", GREEN, RESET);
-        } else {
-            println!(
-                "{}Ollama started but not yet responding - check logs{}",
-                YELLOW, RESET
-            );
+    // Run in background, in its own process group so it outlives this shell
+    let mut cmd = Command::new(std::env::current_exe().context("Failed to find the quant binary")?);
+    cmd.args(["serve", "supervise"])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    let supervisor = cmd.spawn().context("Failed to start the Ollama supervisor")?;
+
+    if wait_until_ready(&client, SERVE_START_TIMEOUT).await {
+        println!("{}Ollama started successfully{}", GREEN, RESET);
+        let pool = config.warm_pool();
+        if !pool.is_empty() {
+            println!("Warming {} model(s)...", pool.len());
+            warm_models(&client, &pool, true).await;
         }
+    } else {
+        println!(
+            "{}Ollama started but not yet responding - check `quant serve logs`{}",
+            YELLOW, RESET
+        );
     }
+    println!(
+        "{}  Supervisor PID {}, logs in {}{}",
+        DIM,
+        supervisor.id(),
+        files.log_file().display(),
+        RESET
+    );
 
     Ok(())
 }
 
+/// Poll Ollama until it answers, for up to `timeout`
+async fn wait_until_ready(client: &OllamaClient, timeout: Duration) -> bool {
+    let start = std::time::Instant::now();
+    while start.elapsed() < timeout {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        if client.health_check().await.unwrap_or(false) {
+            return true;
+        }
+    }
+    client.health_check().await.unwrap_or(false)
+}
+
 /// Stop Ollama server
 pub async fn serve_stop() -> Result<()> {
+    if crate::service::is_installed() {
+        anyhow::bail!("Ollama runs as a service and would restart; use `quant serve uninstall` to stop it");
+    }
+
+    // A supervised Ollama is shut down by its supervisor, which would
+    // otherwise restart it
+    let files = ServeFiles::default_location()?;
+    let stopped = tokio::task::spawn_blocking(move || stop_supervisor(&files, SERVE_STOP_TIMEOUT)).await??;
+    if stopped.is_some() {
+        println!("{}Ollama stopped{}", GREEN, RESET);
+        return Ok(());
+    }
+
     // Try to find and kill ollama process
     #[cfg(unix)]
     {
@@ -353,6 +688,100 @@ pub async fn serve_stop() -> Result<()> {
     Ok(())
 }
 
+/// Print the supervised Ollama's captured output, optionally following it
+pub async fn serve_logs(follow: bool, lines: usize) -> Result<()> {
+    let mut path = ServeFiles::default_location()?.log_file();
+    if crate::service::is_installed() {
+        if cfg!(target_os = "linux") {
+            let lines = lines.to_string();
+            let mut args = vec!["--user", "-u", crate::service::SYSTEMD_UNIT, "-n", &lines];
+            if follow {
+                args.push("-f");
+            }
+            Command::new("journalctl").args(args).status().context("Failed to run journalctl")?;
+            return Ok(());
+        }
+        path = crate::service::launchd_log_path()?;
+    }
+    if !path.exists() {
+        anyhow::bail!(
+            "No Ollama log at {} (logs are captured when Ollama is started with `quant serve start`)",
+            path.display()
+        );
+    }
+
+    for line in llm_core::process::tail_lines(&path, lines)? {
+        println!("{}", line);
+    }
+    if !follow {
+        return Ok(());
+    }
+
+    // Poll for new output; a shorter file means the log was rotated
+    let mut offset = std::fs::metadata(&path)?.len();
+    loop {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let len = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        if len < offset {
+            offset = 0;
+        }
+        if len > offset {
+            let mut file = std::fs::File::open(&path)?;
+            std::io::Seek::seek(&mut file, std::io::SeekFrom::Start(offset))?;
+            let mut new = Vec::new();
+            file.read_to_end(&mut new)?;
+            offset += new.len() as u64;
+            io::stdout().write_all(&new)?;
+            io::stdout().flush()?;
+        }
+    }
+}
+
+/// Install Ollama as a launchd agent or systemd user service
+pub fn serve_install() -> Result<()> {
+    let config = crate::config::load_llm_config().context("Failed to load llm.toml")?;
+    let files = ServeFiles::default_location()?;
+    if files.supervisor_pid().is_some() {
+        anyhow::bail!("Ollama is running under `quant serve start`; run `quant serve stop` first");
+    }
+
+    let env = config.ollama_env();
+    let path = crate::service::install(&env)?;
+    println!("{}Installed Ollama service{}", GREEN, RESET);
+    println!("  {}", path.display());
+    for (key, value) in &env {
+        println!("  {}={}", key, value);
+    }
+    if cfg!(target_os = "linux") {
+        println!(
+            "{}  User services start at login; run `loginctl enable-linger` to start at boot{}",
+            DIM, RESET
+        );
+    }
+    Ok(())
+}
+
+/// Remove the service installed by `serve install`
+pub fn serve_uninstall() -> Result<()> {
+    if crate::service::uninstall()? {
+        println!("{}Ollama service removed{}", GREEN, RESET);
+    } else {
+        println!("No Ollama service installed");
+    }
+    Ok(())
+}
+
+/// Supervise Ollama in this process until stopped (run by `serve start`)
+pub async fn serve_supervise() -> Result<()> {
+    let config = crate::config::load_llm_config().context("Failed to load llm.toml")?;
+    let policy = RestartPolicy {
+        max_restarts: config.ollama.max_restarts,
+        ..RestartPolicy::default()
+    };
+    let supervisor = Supervisor::new(ServeFiles::default_location()?, config.ollama_env()).with_policy(policy);
+    tokio::task::spawn_blocking(move || supervisor.run()).await?
+}
+
 /// Restart Ollama server
 pub async fn serve_restart() -> Result<()> {
     serve_stop().await?;
@@ -362,8 +791,8 @@ pub async fn serve_restart() -> Result<()> {
 
 /// Import local GGUF files into Ollama
 pub async fn import() -> Result<()> {
-    let config = Config::load().context("Failed to load llm.toml")?;
-    let client = OllamaClient::new(config.ollama_url());
+    let config = crate::config::load_llm_config().context("Failed to load llm.toml")?;
+    let client = config.ollama_client();
 
     if !client.health_check().await.unwrap_or(false) {
         println!("{}Ollama is not running{}", RED, RESET);
@@ -454,7 +883,7 @@ pub async fn import() -> Result<()> {
 
 /// Auto-select best model based on system RAM
 pub async fn select(json: bool) -> Result<()> {
-    let config = Config::load().context("Failed to load llm.toml")?;
+    let config = crate::config::load_llm_config().context("Failed to load llm.toml")?;
     let ram = Config::system_ram_gb()?;
 
     let model = config.auto_select_model()?;
@@ -475,7 +904,7 @@ pub async fn select(json: bool) -> Result<()> {
 
 /// Generate .env.local for Aider
 pub async fn env(output_path: &str) -> Result<()> {
-    let config = Config::load().context("Failed to load llm.toml")?;
+    let config = crate::config::load_llm_config().context("Failed to load llm.toml")?;
     let ram = Config::system_ram_gb().unwrap_or(0);
     let model = config.auto_select_model().unwrap_or_else(|_| config.models.coding.clone());
 
@@ -496,30 +925,61 @@ pub async fn env(output_path: &str) -> Result<()> {
     Ok(())
 }
 
-/// One-shot query
-pub async fn ask(
-    prompt: &str,
-    model: Option<String>,
-    stdin: bool,
-    context_path: Option<String>,
-    json_output: bool,
-    system: Option<String>,
-    temperature: Option<f32>,
-    max_tokens: Option<i32>,
-    no_newline: bool,
-) -> Result<()> {
-    use llm_core::ChatOptions;
+/// Flags of `quant ask`
+pub struct AskOptions {
+    pub model: Option<String>,
+    pub stdin: bool,
+    pub paste: bool,
+    pub stdin_format: StdinFormat,
+    pub context_path: Option<String>,
+    pub json_output: bool,
+    pub system: Option<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<i32>,
+    pub no_newline: bool,
+    pub intent: Intent,
+    pub sampling: ModelOptions,
+}
 
-    let config = Config::load().context("Failed to load llm.toml")?;
-    let client = OllamaClient::new(config.ollama_url());
+/// One-shot query
+pub async fn ask(prompt: &str, options: AskOptions) -> Result<()> {
+    use llm_core::{ChatOptions, Role};
+
+    let AskOptions {
+        model,
+        stdin,
+        paste,
+        stdin_format,
+        context_path,
+        json_output,
+        system,
+        temperature,
+        max_tokens,
+        no_newline,
+        intent,
+        sampling,
+    } = options;
+
+    let config = crate::config::load_llm_config().context("Failed to load llm.toml")?;
+    let client = config.ollama_client();
 
     // Check Ollama is running
     if !client.health_check().await.unwrap_or(false) {
         anyhow::bail!("Ollama is not running. Start with: quant serve start");
     }
 
-    // Select model
-    let model = model.unwrap_or_else(|| config.models.coding.clone());
+    // Flags win over alias options, which win over the [ask] config section
+    let settings = crate::config::load_settings()?;
+    let resolved = match model.or_else(|| settings.user.ask.default_model.clone()) {
+        Some(name) => Some(resolve_model(&client, &settings.user.aliases.models, &name).await?),
+        None => None,
+    };
+    let model_options = sampling.or(&resolved.as_ref().map(|r| r.options.clone()).unwrap_or_default());
+    let model = resolved.map(|r| r.model);
+    let temperature = temperature.or(model_options.temperature).or(settings.user.ask.temperature);
+    let max_tokens = max_tokens.or(settings.user.ask.max_tokens);
+    let system = system.or(model_options.system_prompt.clone());
+    let num_ctx = model_options.num_ctx;
 
     // Build prompt
     let mut full_prompt = String::new();
@@ -534,37 +994,72 @@ pub async fn ask(
         }
     }
 
-    // Add stdin content if requested
-    if stdin {
-        let mut stdin_content = String::new();
-        io::stdin().read_to_string(&mut stdin_content)?;
-        if !stdin_content.is_empty() {
-            full_prompt.push_str("```\n");
-            full_prompt.push_str(&stdin_content);
-            full_prompt.push_str("\n```\n\n");
+    // Read stdin (or the clipboard with --paste): raw text goes into the
+    // prompt, a chat transcript becomes history
+    let mut history = Vec::new();
+    if stdin || paste || stdin_format == StdinFormat::Chat {
+        let stdin_content = if paste {
+            crate::clipboard::paste()?
+        } else {
+            let mut content = String::new();
+            io::stdin().read_to_string(&mut content)?;
+            content
+        };
+        match stdin_format {
+            StdinFormat::Chat => {
+                history = transcript::parse(&stdin_content).context("Failed to parse chat transcript from stdin")?;
+            }
+            StdinFormat::Text if !stdin_content.is_empty() => {
+                full_prompt.push_str("```\n");
+                full_prompt.push_str(&stdin_content);
+                full_prompt.push_str("\n```\n\n");
+            }
+            StdinFormat::Text => {}
         }
     }
 
-    // Add the actual prompt
-    full_prompt.push_str(prompt);
-
     // Build messages
     let mut messages = Vec::new();
     if let Some(sys) = system {
         messages.push(ChatMessage::system(sys));
     }
-    messages.push(ChatMessage::user(full_prompt));
+    messages.extend(history);
+
+    if stdin_format == StdinFormat::Text || !prompt.is_empty() {
+        full_prompt.push_str(prompt);
+        messages.push(ChatMessage::user(full_prompt));
+    } else if !full_prompt.is_empty() {
+        // No prompt: attach context to the transcript's final user turn
+        if let Some(last) = messages.iter_mut().rev().find(|m| m.role == Role::User) {
+            last.content = format!("{}{}", full_prompt, last.content);
+        }
+    }
+
+    if stdin_format == StdinFormat::Chat && messages.last().map(|m| &m.role) != Some(&Role::User) {
+        anyhow::bail!("Chat transcript must end with a user message (or pass a prompt)");
+    }
+
+    // Without a model, pick the coding or chat model based on the request
+    let (model, guess) = match model {
+        Some(model) => (model, None),
+        None => {
+            let request = messages.last().map(|m| m.content.as_str()).unwrap_or_default();
+            let (model, guess) = model_for_intent(&settings, intent, request)?;
+            (model, Some(guess))
+        }
+    };
+    ensure_model(&client, &model, crate::tools::security::is_interactive()).await?;
 
     // Build options
-    let options = if temperature.is_some() || max_tokens.is_some() {
-        Some(ChatOptions {
-            temperature,
-            num_predict: max_tokens,
-            ..Default::default()
-        })
-    } else {
-        None
+    let mut options = ChatOptions {
+        temperature,
+        num_predict: max_tokens,
+        num_ctx,
+        ..Default::default()
     };
+    model_options.apply_sampling(&mut options);
+    options.validate()?;
+    let options = Some(options);
 
     if json_output {
         // Non-streaming for JSON output (with timeout)
@@ -575,32 +1070,46 @@ pub async fn ask(
         .await
         .context("Request timed out after 5 minutes")??;
 
+        let split = llm_core::split_thinking(&response.message.content);
         let output = serde_json::json!({
             "model": response.model,
-            "response": response.message.content,
+            "response": split.content,
+            "thinking": (!split.thinking.is_empty()).then(|| split.thinking.trim()),
             "eval_count": response.eval_count,
             "eval_duration_ms": response.eval_duration / 1_000_000,
+            "intent": guess.map(|g| serde_json::json!({
+                "intent": g.intent.to_string(),
+                "reason": g.reason,
+            })),
         });
         println!("{}", serde_json::to_string_pretty(&output)?);
     } else {
-        // Streaming output (with timeout on initial connection)
-        let mut stream = tokio::time::timeout(
-            Duration::from_secs(60),
-            client.chat_stream(&model, &messages, options),
-        )
-        .await
-        .context("Connection timed out after 60 seconds")??;
+        // Streaming output; a model that stalls before its first token is
+        // reloaded and asked again
+        let (mut stream, restart) = client
+            .open_watched(&model, || client.chat_stream(&model, &messages, options.clone()))
+            .await?;
+        if let Some(restart) = restart {
+            eprintln!(
+                "{}[Watchdog]{} {} sent nothing for {}s; reloaded it and retried",
+                YELLOW,
+                RESET,
+                restart.model,
+                restart.waited.as_secs()
+            );
+        }
 
         let stream_timeout = Duration::from_secs(120); // 2 min between chunks
+        let mut display = crate::thinking::ThinkingDisplay::new("");
         while let Ok(Some(chunk)) =
             tokio::time::timeout(stream_timeout, stream.next()).await
         {
             let chunk = chunk?;
             if let Some(msg) = &chunk.message {
-                print!("{}", msg.content);
-                io::stdout().flush()?;
+                display.push(&msg.thinking, &msg.content)?;
             }
         }
+        display.finish()?;
         if !no_newline {
             println!();
         }
@@ -609,58 +1118,278 @@ pub async fn ask(
     Ok(())
 }
 
-// Context management commands
+/// Choose between the llm.toml coding and chat models for a request
+///
+/// Falls back to the other model when the chosen one isn't configured.
+fn model_for_intent(settings: &crate::config::Settings, intent: Intent, request: &str) -> Result<(String, IntentGuess)> {
+    let guess = match intent {
+        Intent::Auto => crate::intent::classify(request),
+        fixed => IntentGuess {
+            intent: fixed,
+            reason: "set with --intent".to_string(),
+        },
+    };
+    let models = &settings.llm.models;
+    let (preferred, other) = match guess.intent {
+        Intent::Chat => (&models.chat, &models.coding),
+        _ => (&models.coding, &models.chat),
+    };
+    let model = [preferred, other]
+        .into_iter()
+        .find(|m| !m.is_empty())
+        .cloned()
+        .context("No model configured. Pass --model or set ask.default_model")?;
 
-/// Add files/directories to context
-pub async fn context_add(paths: &[String]) -> Result<()> {
-    let mut ctx_manager = ContextManager::new()?;
+    tracing::info!(intent = %guess.intent, reason = %guess.reason, model = %model, "Chose model from prompt intent");
+    Ok((model, guess))
+}
 
-    for path in paths {
-        ctx_manager.add(path)?;
-        println!("Added: {}", path);
+/// Suggest a shell command for a natural-language request, then run/edit/abort
+pub async fn do_command(request: &str, model: Option<String>, no_history: bool) -> Result<()> {
+    use crate::shell::{self, HistoryFile};
+    use crate::tools::security::{is_interactive, ConfirmationHandler, ConfirmationResult};
+    use crate::tools::{SecurityLevel, ToolCall};
+    use llm_core::ChatOptions;
+
+    if request.trim().is_empty() {
+        anyhow::bail!("Describe what you want to do, e.g. quant do \"find files over 100MB\"");
     }
 
-    ctx_manager.save()?;
-    Ok(())
-}
+    let config = crate::config::load_llm_config().context("Failed to load llm.toml")?;
+    let client = config.ollama_client();
+    if !client.health_check().await.unwrap_or(false) {
+        anyhow::bail!("Ollama is not running. Start with: quant serve start");
+    }
 
-/// List current context files
-pub async fn context_list() -> Result<()> {
-    let ctx_manager = ContextManager::new()?;
-    let files = ctx_manager.list();
+    let settings = crate::config::load_settings()?;
+    let model = model
+        .or_else(|| settings.coding_model().map(String::from))
+        .context("No model configured. Pass --model or set ask.default_model")?;
 
-    if files.is_empty() {
-        println!("No files in context");
-        println!("Add files with: quant context add <path>");
+    let shell_path = shell::user_shell();
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let messages = vec![
+        ChatMessage::system(shell::system_prompt(shell::shell_name(&shell_path), &cwd)),
+        ChatMessage::user(request),
+    ];
+    let options = ChatOptions {
+        temperature: Some(0.2),
+        ..Default::default()
+    };
+
+    let spinner = ProgressBar::new_spinner();
+    spinner.set_style(ProgressStyle::default_spinner().template("{spinner:.cyan} {msg}").unwrap());
+    spinner.set_message("Thinking...");
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    let response = tokio::time::timeout(Duration::from_secs(120), client.chat(&model, &messages, Some(options)))
+        .await
+        .context("Request timed out after 2 minutes")??;
+    spinner.finish_and_clear();
+
+    let suggestion = shell::parse_suggestion(&response.message.content)?;
+
+    // Piped: print just the command so it can be captured or eval'd
+    if !is_interactive() {
+        println!("{}", suggestion.command);
         return Ok(());
     }
 
-    println!("{}Context Files{}", BOLD, RESET);
-    for file in &files {
-        println!("  {}", file);
+    if !suggestion.explanation.is_empty() {
+        println!("{}{}{}", DIM, suggestion.explanation, RESET);
     }
 
-    // Show token usage
-    if let Ok((tokens, max_tokens, is_truncated)) = ctx_manager.token_status() {
+    let mut command = suggestion.command;
+    loop {
+        let risk = shell::assess_risk(&command);
         println!();
-        let usage_pct = (tokens as f64 / max_tokens as f64 * 100.0) as u32;
-        let status = if is_truncated {
-            format!("{}(truncated){}", RED, RESET)
-        } else if usage_pct > 80 {
-            format!("{}(approaching limit){}", YELLOW, RESET)
-        } else {
-            String::new()
-        };
-        println!(
-            "{}Tokens:{} ~{} / {} ({}%) {}",
-            BOLD, RESET, tokens, max_tokens, usage_pct, status
-        );
-    }
-
-    Ok(())
-}
+        println!("  {}{}${} {}", BOLD, CYAN, RESET, command);
+        match risk {
+            SecurityLevel::Safe => {}
+            SecurityLevel::Moderate => println!("  {}[{}] may modify files or use the network{}", YELLOW, risk, RESET),
+            SecurityLevel::Dangerous => println!("  {}[{}] destructive or privileged command{}", RED, risk, RESET),
+        }
+        println!();
+        print!("[r]un / [e]dit / [a]bort: ");
+        io::stdout().flush()?;
 
-/// Remove files from context
+        let mut choice = String::new();
+        io::stdin().read_line(&mut choice)?;
+        match choice.trim().to_lowercase().as_str() {
+            "r" | "run" | "y" | "yes" | "" => {}
+            "e" | "edit" => {
+                let mut editor = rustyline::DefaultEditor::new()?;
+                match editor.readline_with_initial("$ ", (&command, "")) {
+                    Ok(edited) if !edited.trim().is_empty() => command = edited.trim().to_string(),
+                    Ok(_) => {}
+                    Err(_) => {
+                        println!("{}Aborted{}", DIM, RESET);
+                        return Ok(());
+                    }
+                }
+                continue;
+            }
+            _ => {
+                println!("{}Aborted{}", DIM, RESET);
+                return Ok(());
+            }
+        }
+
+        // Destructive commands get the same confirmation as the bash tool
+        if risk == SecurityLevel::Dangerous {
+            let call = ToolCall {
+                name: "bash".to_string(),
+                arguments: serde_json::json!({ "command": command }),
+            };
+            let result = crate::tools::security::TerminalConfirmation::new()
+                .confirm(&call, risk, Some(&format!("$ {}", command)))
+                .await;
+            if result != ConfirmationResult::Approved {
+                println!("{}Aborted{}", DIM, RESET);
+                return Ok(());
+            }
+        }
+        break;
+    }
+
+    if !no_history {
+        if let Some(history) = HistoryFile::detect(&shell_path) {
+            match history.append(&command, chrono::Utc::now().timestamp()) {
+                Ok(()) => tracing::debug!(path = %history.path().display(), "Recorded command in shell history"),
+                Err(e) => tracing::warn!(error = %e, "Failed to record command in shell history"),
+            }
+        }
+    }
+
+    let status = Command::new(&shell_path)
+        .arg("-c")
+        .arg(&command)
+        .current_dir(&cwd)
+        .status()
+        .with_context(|| format!("Failed to run {}", shell_path))?;
+    if !status.success() {
+        anyhow::bail!("Command exited with {}", status);
+    }
+    Ok(())
+}
+
+/// Build a completer for the coding model, checking Ollama is reachable
+async fn completer(model: Option<String>) -> Result<crate::complete::Completer> {
+    let config = crate::config::load_llm_config().context("Failed to load llm.toml")?;
+    let client = config.ollama_client();
+    if !client.health_check().await.unwrap_or(false) {
+        anyhow::bail!("Ollama is not running. Start with: quant serve start");
+    }
+    let model = model.unwrap_or_else(|| config.models.coding.clone());
+    Ok(crate::complete::Completer::new(client, model))
+}
+
+/// Complete a single fill-in-the-middle request read from stdin
+pub async fn complete(model: Option<String>, json_output: bool) -> Result<()> {
+    use crate::complete::CompletionRequest;
+
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input)?;
+    let request = CompletionRequest::from_input(&input)?;
+
+    let response = completer(model).await?.complete(&request).await?;
+    if json_output {
+        println!("{}", serde_json::to_string(&response)?);
+    } else {
+        print!("{}", response.completion);
+        io::stdout().flush()?;
+    }
+    Ok(())
+}
+
+/// Serve completions on a Unix socket, keeping the model warm
+pub async fn complete_serve(model: Option<String>, socket: Option<PathBuf>) -> Result<()> {
+    use crate::complete::{default_socket_path, serve, SERVER_KEEP_ALIVE};
+
+    let completer = completer(model).await?.with_keep_alive(SERVER_KEEP_ALIVE);
+    let socket = socket.unwrap_or_else(default_socket_path);
+
+    println!("{}Completion server{}", BOLD, RESET);
+    println!("  Model: {}", completer.model());
+    println!("  Socket: {}", socket.display());
+    println!("{}Send one JSON request per line: {{\"prefix\": ..., \"suffix\": ...}}. Ctrl+C to stop.{}", DIM, RESET);
+
+    serve(completer, &socket).await
+}
+
+// Context management commands
+
+/// Add files/directories to context
+pub async fn context_add(paths: &[String]) -> Result<()> {
+    let mut ctx_manager = ContextManager::new()?;
+
+    for path in paths {
+        ctx_manager.add(path)?;
+        println!("Added: {}", path);
+    }
+
+    ctx_manager.save()?;
+    Ok(())
+}
+
+/// List current context files
+pub async fn context_list() -> Result<()> {
+    let ctx_manager = ContextManager::new()?;
+    let files = ctx_manager.list();
+    let repos = crate::context::repos::RepoStore::load()?;
+
+    if !repos.repos().is_empty() {
+        println!("{}Context Repos{} (read-only, searched by smart context)", BOLD, RESET);
+        for repo in repos.repos() {
+            println!("  {}  {}{}{}", repo.label(), DIM, &repo.commit[..repo.commit.len().min(12)], RESET);
+        }
+        println!();
+    }
+
+    if files.is_empty() {
+        println!("No files in context");
+        println!("Add files with: quant context add <path>");
+        return Ok(());
+    }
+
+    println!("{}Context Files{}", BOLD, RESET);
+    for file in &files {
+        println!("  {}", file);
+    }
+
+    // Show the files that make it into prompts, once duplicates are left out
+    let mut assembly = crate::context::ContextAssembly::new();
+    if ctx_manager.build_context_with(&mut assembly).is_ok() && !assembly.duplicates().is_empty() {
+        println!();
+        println!("{}Included:{} {}", BOLD, RESET, assembly.summary());
+        for file in assembly.files() {
+            println!("  {}  {}~{} tokens{}", file.path.display(), DIM, file.tokens, RESET);
+        }
+        for path in assembly.duplicates() {
+            println!("  {}skipped duplicate: {}{}", DIM, path.display(), RESET);
+        }
+    }
+
+    // Show token usage
+    if let Ok((tokens, max_tokens, is_truncated)) = ctx_manager.token_status() {
+        println!();
+        let usage_pct = (tokens as f64 / max_tokens as f64 * 100.0) as u32;
+        let status = if is_truncated {
+            format!("{}(truncated){}", RED, RESET)
+        } else if usage_pct > 80 {
+            format!("{}(approaching limit){}", YELLOW, RESET)
+        } else {
+            String::new()
+        };
+        println!(
+            "{}Tokens:{} ~{} / {} ({}%) {}",
+            BOLD, RESET, tokens, max_tokens, usage_pct, status
+        );
+    }
+
+    Ok(())
+}
+
+/// Remove files from context
 pub async fn context_rm(paths: &[String]) -> Result<()> {
     let mut ctx_manager = ContextManager::new()?;
 
@@ -673,6 +1402,30 @@ pub async fn context_rm(paths: &[String]) -> Result<()> {
     Ok(())
 }
 
+/// Clone a remote repo as read-only context
+pub fn context_add_repo(url: &str, git_ref: Option<&str>) -> Result<()> {
+    let mut store = crate::context::repos::RepoStore::load()?;
+    println!("{}Cloning {}...{}", DIM, url, RESET);
+    let repo = store.add(url, git_ref)?;
+    println!(
+        "{}Added:{} {} at {} ({})",
+        GREEN,
+        RESET,
+        repo.label(),
+        &repo.commit[..repo.commit.len().min(12)],
+        store.checkout(&repo).display()
+    );
+    Ok(())
+}
+
+/// Remove a context repo and its checkout
+pub fn context_rm_repo(name: &str) -> Result<()> {
+    let mut store = crate::context::repos::RepoStore::load()?;
+    let repo = store.remove(name)?;
+    println!("Removed: {}", repo.label());
+    Ok(())
+}
+
 /// Clear all context
 pub async fn context_clear() -> Result<()> {
     let mut ctx_manager = ContextManager::new()?;
@@ -684,55 +1437,17 @@ pub async fn context_clear() -> Result<()> {
 
 /// Load/warm up a model
 pub async fn run(model: Option<String>) -> Result<()> {
-    let config = Config::load().context("Failed to load llm.toml")?;
-    let client = OllamaClient::new(config.ollama_url());
-
-    // Check Ollama is running
-    if !client.health_check().await.unwrap_or(false) {
-        anyhow::bail!("Ollama is not running. Start with: quant serve start");
-    }
-
-    // Select model
+    let config = crate::config::load_llm_config().context("Failed to load llm.toml")?;
     let model = model.unwrap_or_else(|| config.models.coding.clone());
-
-    // Check if already loaded
-    let running = client.list_running().await.unwrap_or_default();
-    if running.iter().any(|m| m.name == model) {
-        println!("Model {} is already loaded", model);
-        return Ok(());
-    }
-
-    // Show loading spinner
-    let spinner = ProgressBar::new_spinner();
-    spinner.set_style(
-        ProgressStyle::default_spinner()
-            .template("{spinner:.cyan} {msg}")
-            .unwrap(),
-    );
-    spinner.set_message(format!("Loading {}...", model));
-    spinner.enable_steady_tick(std::time::Duration::from_millis(100));
-
-    // Load the model by sending a minimal request
-    client.load_model(&model).await?;
-
-    spinner.finish_with_message(format!("{}✓{} Model {} loaded", GREEN, RESET, model));
-
-    // Show VRAM usage
-    if let Ok(running) = client.list_running().await {
-        for m in running {
-            if m.name == model {
-                let vram_gb = m.size_vram as f64 / (1024.0 * 1024.0 * 1024.0);
-                println!("  VRAM: {:.1} GB", vram_gb);
-            }
-        }
+    if model.is_empty() {
+        anyhow::bail!("No model given and models.coding is not set in llm.toml");
     }
-
-    Ok(())
+    models_warm(vec![model], None).await
 }
 
 /// Show detailed version and system info
 pub async fn info() -> Result<()> {
-    let config = Config::load().ok();
+    let config = crate::config::try_load_llm_config();
 
     println!("{}quant{} - Unified CLI for local LLM management", BOLD, RESET);
     println!("Version: {}", env!("CARGO_PKG_VERSION"));
@@ -799,8 +1514,12 @@ pub async fn config_init() -> Result<()> {
 }
 
 /// Show current configuration
-pub async fn config_show() -> Result<()> {
-    use crate::config::UserConfig;
+pub async fn config_show(sources: bool) -> Result<()> {
+    use crate::config::{EditMode, UserConfig};
+
+    if sources {
+        return config_show_sources();
+    }
 
     let path = UserConfig::config_path()?;
 
@@ -810,10 +1529,16 @@ pub async fn config_show() -> Result<()> {
         return Ok(());
     }
 
-    let config = UserConfig::load()?;
+    let resolver = crate::config::ConfigResolver::discover()?;
+    let config = resolver.user_config()?;
 
     println!("{}User Configuration{}", BOLD, RESET);
     println!("  Path: {}", path.display());
+    let sources: Vec<String> = resolver.sources().map(|s| s.to_string()).collect();
+    println!("  Layers (highest precedence first): {}", sources.join(", "));
+    if let Some(profile) = crate::config::active_profile() {
+        println!("  Profile: {}", profile);
+    }
     println!();
 
     println!("{}[repl]{}", BLUE, RESET);
@@ -825,6 +1550,8 @@ pub async fn config_show() -> Result<()> {
     }
     println!("  auto_save = {}", config.repl.auto_save);
     println!("  history_size = {}", config.repl.history_size);
+    println!("  edit_mode = \"{}\"", if config.repl.edit_mode == EditMode::Vi { "vi" } else { "emacs" });
+    println!("  project_history = {}", config.repl.project_history);
     println!("  theme = \"{}\"", config.repl.theme);
     println!();
 
@@ -842,14 +1569,48 @@ pub async fn config_show() -> Result<()> {
 
     if !config.aliases.models.is_empty() {
         println!("{}[aliases.models]{}", BLUE, RESET);
-        for (alias, model) in &config.aliases.models {
-            println!("  {} = \"{}\"", alias, model);
+        for (alias, target) in &config.aliases.models {
+            let options = target.options();
+            let mut fields = vec![format!("model = \"{}\"", target.model())];
+            if let Some(temperature) = options.temperature {
+                fields.push(format!("temperature = {}", temperature));
+            }
+            if let Some(num_ctx) = options.num_ctx {
+                fields.push(format!("num_ctx = {}", num_ctx));
+            }
+            if let Some(ref system) = options.system_prompt {
+                fields.push(format!("system_prompt = {:?}", system));
+            }
+            match fields.len() {
+                1 => println!("  {} = \"{}\"", alias, target.model()),
+                _ => println!("  {} = {{ {} }}", alias, fields.join(", ")),
+            }
         }
     }
 
     Ok(())
 }
 
+/// Print every resolved value with its source
+fn config_show_sources() -> Result<()> {
+    let resolver = crate::config::ConfigResolver::discover()?;
+    let settings = resolver.effective()?;
+    let merged = resolver.merged();
+    let provenance: Vec<(&str, String)> = settings
+        .provenance()
+        .map(|(key, source)| (key, source.to_string()))
+        .collect();
+    let width = provenance.iter().map(|(key, _)| key.len()).max().unwrap_or(0);
+
+    for (key, source) in &provenance {
+        let value = llm_core::layered::lookup(&merged, key)
+            .map(|v| v.to_string())
+            .unwrap_or_default();
+        println!("{:<width$} = {}  {}# {}{}", key, value, DIM, source, RESET, width = width);
+    }
+    Ok(())
+}
+
 /// Print config file path
 pub async fn config_path() -> Result<()> {
     use crate::config::UserConfig;
@@ -860,6 +1621,58 @@ pub async fn config_path() -> Result<()> {
     Ok(())
 }
 
+/// Print a resolved config value; the source goes to stderr so stdout stays scriptable
+pub async fn config_get(key: &str) -> Result<()> {
+    use crate::config::ConfigResolver;
+
+    let resolver = ConfigResolver::discover()?;
+    let Some((value, source)) = resolver.get(key) else {
+        anyhow::bail!("`{}` is not set", key);
+    };
+
+    match value {
+        toml::Value::String(s) => println!("{}", s),
+        toml::Value::Table(t) => print!("{}", toml::to_string_pretty(&t)?),
+        other => println!("{}", other),
+    }
+    eprintln!("{}from {}{}", DIM, source, RESET);
+
+    Ok(())
+}
+
+/// Set a config value in the user or project config file
+pub async fn config_set(key: &str, value: &str, project: bool) -> Result<()> {
+    use crate::config::{find_project_config, set_in_file, ConfigResolver, UserConfig, PROJECT_CONFIG};
+
+    let path = if project {
+        let cwd = std::env::current_dir()?;
+        find_project_config(&cwd).unwrap_or_else(|| cwd.join(PROJECT_CONFIG))
+    } else {
+        let path = UserConfig::config_path()?;
+        if !path.exists() {
+            UserConfig::create_default()?;
+        }
+        path
+    };
+
+    let stored = set_in_file(&path, key, value)?;
+    println!("{}Set:{} {} = {} in {}", GREEN, RESET, key, stored, path.display());
+
+    // A higher-precedence layer may still win
+    if let Ok(resolver) = ConfigResolver::discover() {
+        if let Some((effective, source)) = resolver.get(key) {
+            if effective != stored {
+                println!(
+                    "{}Note:{} {} overrides this (effective value: {})",
+                    YELLOW, RESET, source, effective
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
 /// Edit config file
 pub async fn config_edit() -> Result<()> {
     use crate::config::UserConfig;
@@ -900,26 +1713,54 @@ pub async fn config_edit() -> Result<()> {
     Ok(())
 }
 
+/// Flags of `quant agent`
+pub struct AgentOptions {
+    pub model: Option<String>,
+    pub system: Option<String>,
+    pub auto: bool,
+    pub max_iterations: usize,
+    pub quiet: bool,
+    pub resume: Option<String>,
+    pub no_save: bool,
+    pub plan: bool,
+    pub worktree: bool,
+    pub dry_run: bool,
+    pub review: bool,
+    pub confirm_policy: Option<PathBuf>,
+    pub structured: bool,
+    pub sampling: ModelOptions,
+}
+
 /// Run agent with autonomous task execution
-pub async fn agent(
-    task: &str,
-    model: Option<String>,
-    system: Option<String>,
-    auto: bool,
-    max_iterations: usize,
-    quiet: bool,
-    resume: Option<String>,
-    no_save: bool,
-) -> Result<()> {
+pub async fn agent(task: &str, options: AgentOptions) -> Result<()> {
     use crate::session::{Session, SessionStore};
+    use crate::worktree::Worktree;
+
+    let AgentOptions {
+        model,
+        system,
+        auto,
+        max_iterations,
+        quiet,
+        resume,
+        no_save,
+        plan,
+        worktree,
+        dry_run,
+        review,
+        confirm_policy,
+        structured,
+        sampling,
+    } = options;
+
+    // Structured mode replaces all interactive output with log events and a JSON summary
+    let quiet = quiet || structured;
+    let confirm_policy = confirm_policy.as_deref().map(ConfirmPolicy::load).transpose()?;
 
     // Load config, fall back to defaults
-    let (config, _) = match Config::try_load() {
-        Some(cfg) => (cfg, None),
-        None => (Config::default_minimal(), Some("Using default config")),
-    };
+    let config = crate::config::load_llm_config_or_default();
 
-    let client = OllamaClient::new(config.ollama_url());
+    let client = config.ollama_client();
 
     // Check Ollama is running
     if !client.health_check().await.unwrap_or(false) {
@@ -930,14 +1771,29 @@ pub async fn agent(
         );
     }
 
+    let user_config = crate::config::UserConfig::load().unwrap_or_default();
+
     // Determine model
-    let model = model.unwrap_or_else(|| {
+    let resolved = match model {
+        Some(name) => Some(resolve_model(&client, &user_config.aliases.models, &name).await?),
+        None => None,
+    };
+    // Flags win over alias options
+    let alias_options = sampling.or(&resolved.as_ref().map(|r| r.options.clone()).unwrap_or_default());
+    let model = resolved.map(|r| r.model).unwrap_or_else(|| {
         if !config.models.coding.is_empty() {
             config.models.coding.clone()
         } else {
             "llama3.2".to_string()
         }
     });
+    let system = system.or(alias_options.system_prompt.clone());
+    ensure_model(&client, &model, !quiet && crate::tools::security::is_interactive()).await?;
+
+    // With --worktree the agent works in its own checkout of HEAD
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let worktree = if worktree { Some(Worktree::create(&cwd, task)?) } else { None };
+    let working_dir = worktree.as_ref().map(|w| w.map_dir(&cwd)).unwrap_or_else(|| cwd.clone());
 
     // Handle session resume
     let session_store = SessionStore::new()?;
@@ -951,21 +1807,47 @@ pub async fn agent(
         Session::new(&model, working_dir)
     };
 
+    // An explicit policy takes over from the config's auto-approve
+    let auto = auto || (user_config.agent.auto_approve && confirm_policy.is_none());
+    let from_policy = confirm_policy.is_some();
+
+    // With --review, file writes wait in a staging area for the review at the end
+    let interactive = !structured && crate::tools::security::is_interactive();
+    if review && !interactive {
+        anyhow::bail!("--review needs an interactive terminal");
+    }
+    let staging = ((review || user_config.agent.review_writes) && interactive && !dry_run)
+        .then(|| std::sync::Arc::new(crate::tools::staging::StagingArea::new()));
+
     // Create tool registry and router
-    let registry = create_default_registry();
+    let mut registry = create_default_registry();
+    if let Some(ref tools) = user_config.agent.tools {
+        registry.retain(tools);
+    }
     let confirmation = if auto {
         TerminalConfirmation::auto()
     } else {
         TerminalConfirmation::new()
     };
-    let router = ToolRouter::new(registry, confirmation);
+    let mut router = ToolRouter::new(registry, confirmation);
+    if let Some(ref policy) = confirm_policy {
+        router.set_confirmation(policy.clone());
+    }
 
     // Configure the agent
-    let agent_config = AgentConfig::new(&model)
+    let agent_config = AgentConfig::from_user_config(&model, &user_config)
         .with_max_iterations(max_iterations)
-        .with_working_dir(std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")))
+        .with_working_dir(working_dir.clone())
         .with_auto_mode(auto)
-        .with_verbose(!quiet);
+        .with_dry_run(dry_run)
+        .with_staging(staging.clone())
+        .with_confirm_policy(confirm_policy)
+        .with_verbose(!quiet)
+        .with_interruptible(!quiet && crate::tools::security::is_interactive())
+        .with_temperature(alias_options.temperature)
+        .with_num_ctx(alias_options.num_ctx)
+        .with_sampling(alias_options)
+        .with_hot_reload(true);
 
     let agent_config = if let Some(sys) = system {
         agent_config.with_system_prompt(sys)
@@ -974,16 +1856,32 @@ pub async fn agent(
     };
 
     // Create and run the agent (with MCP support)
-    let agent = AgentLoop::new_with_mcp(client, router, agent_config).await?;
+    let shadow_client = client.clone();
+    let mut agent = AgentLoop::new_with_mcp(client.clone(), router, agent_config).await?;
 
     if !quiet {
         println!("{}Agent Mode{}", BOLD, RESET);
         println!("  Model: {}", model);
         println!("  Task: {}", task);
         println!("  Auto mode: {}", if auto { "yes" } else { "no" });
+        if from_policy {
+            println!("  Confirmations: answered by the policy file");
+        }
+        if dry_run {
+            println!("  {}Dry run: file writes and commands are simulated, not executed{}", YELLOW, RESET);
+        }
+        if staging.is_some() {
+            println!("  Review: file writes are held until you review them at the end");
+        }
         if resume.is_some() {
             println!("  Session: {}", session.id);
         }
+        if let Some(ref worktree) = worktree {
+            println!("  Worktree: {} {}({}){}", worktree.path.display(), DIM, worktree.branch, RESET);
+            if worktree.source_is_dirty() {
+                println!("  {}Uncommitted changes in your checkout are not in the worktree{}", YELLOW, RESET);
+            }
+        }
 
         // Show MCP info if servers are running
         {
@@ -997,32 +1895,147 @@ pub async fn agent(
         println!();
     }
 
-    let state = agent.run(task).await?;
+    // Inline `@server:resource` references to MCP resources
+    let resources = {
+        let mcp_manager = agent.mcp_manager();
+        let manager = mcp_manager.lock().await;
+        crate::mcp::expand_resource_refs(&manager, task).await?
+    };
+    let full_task = if resources.is_empty() {
+        task.to_string()
+    } else {
+        format!("{}---\n\n{}", resources, task)
+    };
+
+    // Planning mode: reuse an unfinished plan from the session, or ask for a new one
+    let plan = match session.plan.clone().filter(|p| !p.is_complete()) {
+        Some(existing) => Some(existing),
+        None if plan => Some(agent.create_plan(&full_task).await?),
+        None => None,
+    };
+
+    if let Some(ref p) = plan {
+        if structured {
+            tracing::info!(steps = p.steps.len(), "Executing plan");
+        } else {
+            println!("{}Plan{}", BOLD, RESET);
+            print!("{}", p.render_checklist());
+            println!();
+        }
+
+        if !auto && !structured && crate::tools::security::is_interactive() {
+            print!("Execute this plan? [y/n] ");
+            io::stdout().flush()?;
+            let mut answer = String::new();
+            io::stdin().read_line(&mut answer)?;
+            if !matches!(answer.trim().to_lowercase().as_str(), "" | "y" | "yes") {
+                println!("{}Plan rejected, exiting.{}", YELLOW, RESET);
+                agent.shutdown_mcp().await;
+                if let Some(ref worktree) = worktree {
+                    worktree.remove(false)?;
+                }
+                return Ok(());
+            }
+        }
+    }
+
+    // Log each message as it happens so a crash doesn't lose the run
+    if !no_save {
+        agent.set_session_log(session_store.open_log(&session)?);
+    }
+
+    // Show the run in OllamaBar's menu while it lasts, if OllamaBar is running
+    let mut bar = llm_core::ipc::IpcClient::connect_default().await;
+    if let Some(ref mut bar) = bar {
+        let info = llm_core::ipc::SessionInfo {
+            pid: std::process::id(),
+            kind: llm_core::ipc::SessionKind::Agent,
+            id: session.id.clone(),
+            model: model.clone(),
+            working_dir: working_dir.clone(),
+            title: Some(task.to_string()),
+            resumable: !no_save,
+        };
+        if let Err(e) = bar.report(&info).await {
+            tracing::debug!(error = %e, "Failed to report the session to OllamaBar");
+        }
+    }
+
+    let state = agent.run_with_plan(&full_task, plan).await?;
+    crate::lsp::shutdown_all().await;
+
+    // Compare against the previous QUANT.md instructions after recent edits
+    if user_config.agent.shadow_runs > 0 {
+        if let Some((root, instructions, system_prompt)) = agent.project_instructions() {
+            let run = crate::agent::shadow::ShadowRun {
+                project_root: root,
+                instructions,
+                system_prompt,
+                task: &full_task,
+                live_response: state.final_response.as_deref(),
+            };
+            match crate::agent::shadow::shadow_if_changed(&shadow_client, &model, run, user_config.agent.shadow_runs).await {
+                Ok(Some(report)) if !quiet => {
+                    println!("{}Shadow (previous QUANT.md):{} {}", DIM, RESET, report.summary_line());
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!(error = %e, "Shadow evaluation failed"),
+            }
+        }
+    }
+    if let Err(e) = crate::context::save_token_cache() {
+        tracing::debug!(error = %e, "Failed to save token count cache");
+    }
 
     // Shutdown MCP servers
     agent.shutdown_mcp().await;
 
-    // Save session messages
-    for msg in &state.messages {
+    // Save session messages (full transcript, including compacted tool results)
+    for msg in &state.transcript {
         session.add_message(msg.clone());
     }
-
-    // Generate a summary from the final response
-    if let Some(ref response) = state.final_response {
-        let summary = if response.len() > 100 {
-            format!("{}...", &response[..97])
-        } else {
-            response.clone()
-        };
-        session.set_summary(summary);
+    if state.plan.is_some() {
+        session.plan = state.plan.clone();
+    }
+    session.record_model_switches(&state.model_switches);
+    session.record_model_restarts(&state.model_restarts);
+    session.record_run(&state.snapshots, &state.steps);
+    session.failure = state.failure.clone();
+
+    // Summarize the run (skipped for --no-save, where nothing keeps it)
+    if let Some(response) = state.final_response.as_ref().filter(|_| !no_save) {
+        let summary_model = user_config.agent.summary_model(&model);
+        session.summarize(&client, summary_model, task, response).await;
     }
 
     // Save session (unless --no-save)
-    if !no_save {
-        session_store.save(&session)?;
+    if let Some(log) = agent.take_session_log() {
+        let session_path = log.finish(&session_store, &session)?;
+        let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        crate::agent::run_session_save_hooks(&cwd, &session.id, &session_path).await;
         if !quiet {
             println!("{}Session saved:{} {}", DIM, RESET, session.id);
         }
+        crate::search::refresh_default_index();
+    }
+
+    if structured {
+        let mut summary = state.run_summary();
+        summary["task"] = serde_json::json!(task);
+        summary["session_id"] = serde_json::json!(if no_save { None } else { Some(&session.id) });
+        if let Some(ref worktree) = worktree {
+            let changed = worktree.has_changes()?;
+            if !changed {
+                worktree.remove(false)?;
+            }
+            summary["worktree"] = serde_json::json!({
+                "path": worktree.path,
+                "branch": worktree.branch,
+                "kept": changed,
+            });
+        }
+        println!("{}", serde_json::to_string(&summary)?);
+        return Ok(());
     }
 
     // Print results
@@ -1032,9 +2045,18 @@ pub async fn agent(
         println!("{}", response);
     }
 
-    if let Some(error) = state.error {
+    if let Some(ref p) = state.plan {
+        println!();
+        println!("{}Plan Progress ({}/{}){}", BOLD, p.completed(), p.steps.len(), RESET);
+        print!("{}", p.render_checklist());
+    }
+
+    if let Some(ref failure) = state.failure {
         println!();
-        println!("{}Error:{} {}", RED, RESET, error);
+        print!("{}", failure.render());
+        if !no_save {
+            println!("  - Continue with `quant sessions resume {}`", session.id);
+        }
     }
 
     if !quiet {
@@ -1045,32 +2067,284 @@ pub async fn agent(
         );
     }
 
-    Ok(())
-}
-
-/// List saved sessions
-pub async fn sessions_list(project_only: bool, json: bool) -> Result<()> {
-    use crate::session::SessionStore;
+    if let Some(ref staging) = staging {
+        crate::review::review_staged(staging, &working_dir)?;
+    }
 
-    let store = SessionStore::new()?;
+    if let Some(ref worktree) = worktree {
+        finish_worktree(worktree, task, !quiet && crate::tools::security::is_interactive())?;
+    }
 
-    let sessions = if project_only {
-        let cwd = std::env::current_dir()?;
-        store.find_by_project(&cwd)?
-    } else {
-        store.list()?
-    };
+    Ok(())
+}
 
-    if json {
-        println!("{}", serde_json::to_string_pretty(&sessions)?);
+/// Offer to review, commit, or discard what the agent changed in its worktree
+///
+/// A worktree without changes is removed. Non-interactive runs keep the
+/// worktree for the user to deal with later.
+fn finish_worktree(worktree: &crate::worktree::Worktree, task: &str, interactive: bool) -> Result<()> {
+    let kept = format!(
+        "Kept worktree at {} (branch {}); remove it with `git worktree remove`",
+        worktree.path.display(),
+        worktree.branch
+    );
+    if !worktree.has_changes()? {
+        worktree.remove(false)?;
+        println!("{}No changes; removed the worktree{}", DIM, RESET);
         return Ok(());
     }
 
-    if sessions.is_empty() {
-        println!("No saved sessions found.");
-        if project_only {
-            println!("{}Tip:{} Use `quant sessions list` to see all sessions.", DIM, RESET);
-        }
+    println!();
+    println!("{}Changes on {}{}", BOLD, worktree.branch, RESET);
+    println!("{}", worktree.diff(true)?);
+    if !interactive {
+        println!("{}{}{}", DIM, kept, RESET);
+        return Ok(());
+    }
+
+    loop {
+        print!("[d]iff, [c]ommit, [p]ull request, [k]eep, or discard [x]? ");
+        io::stdout().flush()?;
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer)?;
+        match answer.trim().to_lowercase().as_str() {
+            "d" | "diff" => println!("{}", worktree.diff(false)?),
+            "c" | "commit" => {
+                worktree.commit(&crate::worktree::commit_message(task))?;
+                worktree.remove(true)?;
+                println!(
+                    "{}Committed to {}{}; merge it with `git merge {}`",
+                    GREEN, worktree.branch, RESET, worktree.branch
+                );
+                return Ok(());
+            }
+            "p" | "pr" => {
+                let message = crate::worktree::commit_message(task);
+                worktree.commit(&message)?;
+                match worktree.open_pr(&message) {
+                    Ok(url) => println!("{}Opened pull request:{} {}", GREEN, RESET, url),
+                    Err(e) => println!("{}Committed to {} but could not open a pull request: {:#}{}", YELLOW, worktree.branch, e, RESET),
+                }
+                worktree.remove(true)?;
+                return Ok(());
+            }
+            "" | "k" | "keep" => {
+                println!("{}", kept);
+                return Ok(());
+            }
+            "x" | "discard" => {
+                worktree.remove(false)?;
+                println!("{}Discarded the changes and branch {}{}", DIM, worktree.branch, RESET);
+                return Ok(());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Flags of `quant agent --team`
+pub struct TeamOptions {
+    pub model: Option<String>,
+    pub auto: bool,
+    pub max_iterations: usize,
+    pub quiet: bool,
+    pub no_save: bool,
+    pub dry_run: bool,
+    pub confirm_policy: Option<PathBuf>,
+    pub structured: bool,
+    pub review: bool,
+}
+
+/// Run a team of role-specialized agents on one task
+pub async fn agent_team(task: &str, team: &str, options: TeamOptions) -> Result<()> {
+    use crate::agent::{parse_team, TeamCoordinator};
+    use crate::session::{Session, SessionStore};
+
+    let TeamOptions { model, auto, max_iterations, quiet, no_save, dry_run, confirm_policy, structured, review } = options;
+    let quiet = quiet || structured;
+    let confirm_policy = confirm_policy.as_deref().map(ConfirmPolicy::load).transpose()?;
+
+    let user_config = crate::config::UserConfig::load().unwrap_or_default();
+    let roles = parse_team(team, &user_config.roles)?;
+    // An explicit policy takes over from the config's auto-approve
+    let auto = auto || (user_config.agent.auto_approve && confirm_policy.is_none());
+
+    // Every role writes to the same staging area, reviewed once at the end
+    let interactive = !structured && crate::tools::security::is_interactive();
+    if review && !interactive {
+        anyhow::bail!("--review needs an interactive terminal");
+    }
+    let staging = ((review || user_config.agent.review_writes) && interactive && !dry_run)
+        .then(|| std::sync::Arc::new(crate::tools::staging::StagingArea::new()));
+    let working_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+
+    let config = crate::config::load_llm_config_or_default();
+    let client = config.ollama_client();
+
+    if !client.health_check().await.unwrap_or(false) {
+        anyhow::bail!(
+            "Ollama is not running.\nStart with: {}quant serve start{}",
+            BLUE,
+            RESET
+        );
+    }
+
+    let resolved = match model {
+        Some(name) => Some(resolve_model(&client, &user_config.aliases.models, &name).await?),
+        None => None,
+    };
+    let alias_options = resolved.as_ref().map(|r| r.options.clone()).unwrap_or_default();
+    let model = resolved.map(|r| r.model).unwrap_or_else(|| {
+        if !config.models.coding.is_empty() {
+            config.models.coding.clone()
+        } else {
+            "llama3.2".to_string()
+        }
+    });
+
+    let agent_config = AgentConfig::from_user_config(&model, &user_config)
+        .with_max_iterations(max_iterations)
+        .with_working_dir(working_dir.clone())
+        .with_auto_mode(auto)
+        .with_dry_run(dry_run)
+        .with_staging(staging.clone())
+        .with_confirm_policy(confirm_policy)
+        .with_verbose(!quiet)
+        .with_temperature(alias_options.temperature)
+        .with_num_ctx(alias_options.num_ctx)
+        .with_sampling(alias_options);
+
+    if !quiet {
+        println!("{}Team Mode{}", BOLD, RESET);
+        println!("  Model: {}", model);
+        println!("  Task: {}", task);
+        if dry_run {
+            println!("  {}Dry run: file writes and commands are simulated, not executed{}", YELLOW, RESET);
+        }
+        if staging.is_some() {
+            println!("  Review: file writes are held until you review them at the end");
+        }
+        for role in &roles {
+            let model = role.model.as_deref().unwrap_or("default");
+            let tools = role.tools.as_ref().map(|t| t.join(", ")).unwrap_or_else(|| "all".to_string());
+            println!("  {}{}{} {}(model: {}, tools: {}){}", BOLD, role.name, RESET, DIM, model, tools, RESET);
+        }
+    }
+
+    let coordinator = TeamCoordinator::new(client.clone(), roles, agent_config)
+        .with_max_review_rounds(user_config.agent.max_review_rounds);
+    let outcome = coordinator.run(task).await?;
+    crate::lsp::shutdown_all().await;
+    if let Err(e) = crate::context::save_token_cache() {
+        tracing::debug!(error = %e, "Failed to save token count cache");
+    }
+
+    // Save every role's transcript to one session
+    let mut session = Session::new(&model, std::env::current_dir().ok());
+    for (_, state) in &outcome.runs {
+        for msg in &state.transcript {
+            session.add_message(msg.clone());
+        }
+        session.record_model_switches(&state.model_switches);
+        session.record_model_restarts(&state.model_restarts);
+        session.record_run(&state.snapshots, &state.steps);
+    }
+    session.failure = outcome.failure().map(|(_, failure)| failure.clone());
+    if let Some(response) = outcome.final_response().filter(|_| !no_save) {
+        let summary_model = user_config.agent.summary_model(&model);
+        session.summarize(&client, summary_model, task, response).await;
+    }
+
+    if !no_save {
+        let store = SessionStore::new()?;
+        let session_path = store.save(&session)?;
+        let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        crate::agent::run_session_save_hooks(&cwd, &session.id, &session_path).await;
+        if !quiet {
+            println!("{}Session saved:{} {}", DIM, RESET, session.id);
+        }
+        crate::search::refresh_default_index();
+    }
+
+    if structured {
+        let runs: Vec<serde_json::Value> = outcome
+            .runs
+            .iter()
+            .map(|(role, state)| {
+                let mut run = state.run_summary();
+                run["role"] = serde_json::json!(role);
+                run
+            })
+            .collect();
+        let summary = serde_json::json!({
+            "status": if outcome.error().is_some() { "error" } else { "completed" },
+            "task": task,
+            "team": team,
+            "review_rounds": outcome.review_rounds,
+            "final_response": outcome.final_response(),
+            "error": outcome.error(),
+            "runs": runs,
+            "session_id": if no_save { None } else { Some(&session.id) },
+        });
+        println!("{}", serde_json::to_string(&summary)?);
+        return Ok(());
+    }
+
+    if let Some(response) = outcome.final_response() {
+        println!();
+        println!("{}Final Response:{}", BOLD, RESET);
+        println!("{}", response);
+    }
+
+    if let Some((role, failure)) = outcome.failure() {
+        println!();
+        println!("{}[{}]{}", BOLD, role, RESET);
+        print!("{}", failure.render());
+    }
+
+    if !quiet {
+        let iterations: usize = outcome.runs.iter().map(|(_, s)| s.iteration).sum();
+        println!();
+        println!(
+            "{}Completed {} role runs ({} review rounds, {} iterations){}",
+            GREEN,
+            outcome.runs.len(),
+            outcome.review_rounds,
+            iterations,
+            RESET
+        );
+    }
+
+    if let Some(ref staging) = staging {
+        crate::review::review_staged(staging, &working_dir)?;
+    }
+
+    Ok(())
+}
+
+/// List saved sessions
+pub async fn sessions_list(project_only: bool, json: bool) -> Result<()> {
+    use crate::session::SessionStore;
+
+    let store = SessionStore::new()?;
+
+    let sessions = if project_only {
+        let cwd = std::env::current_dir()?;
+        store.find_by_project(&cwd)?
+    } else {
+        store.list()?
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&sessions)?);
+        return Ok(());
+    }
+
+    if sessions.is_empty() {
+        println!("No saved sessions found.");
+        if project_only {
+            println!("{}Tip:{} Use `quant sessions list` to see all sessions.", DIM, RESET);
+        }
         return Ok(());
     }
 
@@ -1092,12 +2366,12 @@ pub async fn sessions_list(project_only: bool, json: bool) -> Result<()> {
             project
         );
         if let Some(summary) = &s.summary {
-            let truncated = if summary.len() > 60 {
-                format!("{}...", &summary[..57])
-            } else {
-                summary.clone()
-            };
-            println!("    {}{}{}", DIM, truncated, RESET);
+            let truncated: String = summary.chars().take(SESSION_SUMMARY_CHARS).collect();
+            let ellipsis = if truncated.len() < summary.len() { "..." } else { "" };
+            println!("    {}{}{}{}", DIM, truncated, ellipsis, RESET);
+        }
+        if !s.tags.is_empty() {
+            println!("    {}{}{}", CYAN, format_tags(&s.tags), RESET);
         }
     }
 
@@ -1107,6 +2381,11 @@ pub async fn sessions_list(project_only: bool, json: bool) -> Result<()> {
     Ok(())
 }
 
+/// e.g. "#auth #tests"
+fn format_tags(tags: &[String]) -> String {
+    tags.iter().map(|tag| format!("#{}", tag)).collect::<Vec<_>>().join(" ")
+}
+
 /// Show details of a session
 pub async fn sessions_show(id: &str) -> Result<()> {
     use crate::session::SessionStore;
@@ -1123,11 +2402,34 @@ pub async fn sessions_show(id: &str) -> Result<()> {
         println!("  Project: {}", root.display());
     }
     println!("  Messages: {}", session.message_count());
+    if let Some(ref conv) = session.source_conversation {
+        println!("  From conversation: {}", conv);
+    }
+    for switch in &session.model_switches {
+        println!(
+            "  Fallback: {} -> {} at iteration {} ({})",
+            switch.from, switch.to, switch.iteration, switch.reason
+        );
+    }
+    for restart in &session.model_restarts {
+        println!(
+            "  Reloaded: {} at iteration {} (no reply for {}s)",
+            restart.model, restart.iteration, restart.waited_secs
+        );
+    }
 
     if let Some(ref summary) = session.summary {
         println!();
         println!("{}Summary:{}", BOLD, RESET);
         println!("  {}", summary);
+        if !session.tags.is_empty() {
+            println!("  {}{}{}", CYAN, format_tags(&session.tags), RESET);
+        }
+    }
+
+    if let Some(ref failure) = session.failure {
+        println!();
+        print!("{}", failure.render());
     }
 
     println!();
@@ -1145,6 +2447,208 @@ pub async fn sessions_show(id: &str) -> Result<()> {
     Ok(())
 }
 
+/// Dump the agent state recorded at an iteration, or list the recorded iterations
+pub async fn sessions_inspect(id: &str, iteration: Option<usize>, run: Option<usize>, json: bool) -> Result<()> {
+    use crate::session::SessionStore;
+
+    let store = SessionStore::new()?;
+    let session = store.load(id)?;
+    if session.snapshots.is_empty() {
+        anyhow::bail!("Session {} has no recorded agent iterations", id);
+    }
+
+    let Some(iteration) = iteration else {
+        if json {
+            let listing: Vec<_> = session
+                .snapshots
+                .iter()
+                .map(|s| {
+                    serde_json::json!({
+                        "run": s.run,
+                        "iteration": s.iteration,
+                        "model": s.model,
+                        "taken_at": s.taken_at,
+                        "total_tokens": s.token_usage.total_tokens(),
+                        "failures": s.failure_tracker.failures().len(),
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&listing)?);
+            return Ok(());
+        }
+        println!("{}Recorded iterations:{} {}", BOLD, RESET, session.id);
+        for snapshot in &session.snapshots {
+            let messages = session
+                .snapshot_at(Some(snapshot.run), snapshot.iteration)
+                .map(|(_, m)| m.len())
+                .unwrap_or(0);
+            println!(
+                "  run {} iteration {:>3}  {}  {} messages, {} tokens so far{}",
+                snapshot.run,
+                snapshot.iteration,
+                snapshot.model,
+                messages,
+                snapshot.token_usage.total_tokens(),
+                match snapshot.failure_tracker.failures().len() {
+                    0 => String::new(),
+                    n => format!(", {} failing call(s)", n),
+                }
+            );
+        }
+        println!("\nUse: quant sessions inspect {} --iteration <n>", session.id);
+        return Ok(());
+    };
+
+    let (snapshot, messages) = session.snapshot_at(run, iteration).with_context(|| {
+        let run = run.unwrap_or_else(|| session.last_run());
+        format!("No snapshot for iteration {} of run {} in session {}", iteration, run, id)
+    })?;
+
+    if json {
+        let failures: serde_json::Map<String, serde_json::Value> = snapshot
+            .failure_tracker
+            .failures()
+            .into_iter()
+            .map(|(sig, f)| (sig.to_string(), serde_json::json!({"count": f.count, "last_error": f.last_error})))
+            .collect();
+        let dump = serde_json::json!({
+            "session": session.id,
+            "run": snapshot.run,
+            "iteration": snapshot.iteration,
+            "model": snapshot.model,
+            "taken_at": snapshot.taken_at,
+            "token_usage": snapshot.token_usage,
+            "compacted_chars": snapshot.compacted_chars,
+            "failures": failures,
+            "messages": messages,
+        });
+        println!("{}", serde_json::to_string_pretty(&dump)?);
+        return Ok(());
+    }
+
+    println!(
+        "{}Session {} run {} iteration {}{}",
+        BOLD, session.id, snapshot.run, snapshot.iteration, RESET
+    );
+    println!("  Model: {}", snapshot.model);
+    println!("  Taken: {}", snapshot.taken_at.format("%Y-%m-%d %H:%M:%S"));
+    println!("  {}", snapshot.token_usage.summary());
+    if snapshot.compacted_chars > 0 {
+        println!("  Compacted: {} chars of tool output", snapshot.compacted_chars);
+    }
+    for (sig, failure) in snapshot.failure_tracker.failures() {
+        println!("  {}Failing:{} {} x{} ({})", YELLOW, RESET, sig, failure.count, failure.last_error);
+    }
+
+    println!();
+    println!("{}Messages the model saw ({}):{}", BOLD, messages.len(), RESET);
+    for (i, msg) in messages.iter().enumerate() {
+        let role = format!("{:?}", msg.role).to_lowercase();
+        match msg.tool_call_id {
+            Some(ref call_id) => println!("\n{}--- {}. [{} {}] ---{}", DIM, i + 1, role, call_id, RESET),
+            None => println!("\n{}--- {}. [{}] ---{}", DIM, i + 1, role, RESET),
+        }
+        if !msg.content.is_empty() {
+            println!("{}", msg.content);
+        }
+        for call in msg.tool_calls.iter().flatten() {
+            println!("{}-> {}{} {}", CYAN, call.function.name, RESET, call.function.arguments);
+        }
+    }
+
+    Ok(())
+}
+
+/// Re-run a recorded agent run through the agent loop, with its recorded
+/// responses and tool results in place of the model and the tools
+///
+/// Fails when the loop now takes a different path than the recording, so
+/// replays can back regression tests.
+pub async fn sessions_replay(id: &str, run: Option<usize>, step: bool) -> Result<()> {
+    use crate::agent::Replay;
+    use crate::session::SessionStore;
+    use llm_core::Role;
+
+    let store = SessionStore::new()?;
+    let session = store.load(id)?;
+    let run = run.unwrap_or_else(|| session.last_run());
+    let steps = session.steps_of(Some(run));
+    if steps.is_empty() {
+        anyhow::bail!("Run {} of session {} has no recorded responses to replay", run, id);
+    }
+    let (snapshot, messages) = session
+        .snapshot_at(Some(run), 1)
+        .with_context(|| format!("Run {} of session {} has no recorded first iteration", run, id))?;
+    let system = messages.iter().find(|m| m.role == Role::System).map(|m| m.content.clone());
+    let task = messages
+        .iter()
+        .find(|m| m.role == Role::User)
+        .map(|m| m.content.clone())
+        .with_context(|| format!("Run {} of session {} has no task", run, id))?;
+    let iterations = steps.iter().map(|s| s.iteration).max().unwrap_or(1);
+
+    let user_config = crate::config::UserConfig::load().unwrap_or_default();
+    let client = crate::config::load_llm_config_or_default().ollama_client();
+    let mut registry = create_default_registry();
+    if let Some(ref tools) = user_config.agent.tools {
+        registry.retain(tools);
+    }
+    let router = ToolRouter::new(registry, TerminalConfirmation::auto());
+    let working_dir = session
+        .project_root
+        .clone()
+        .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
+
+    // The settings that steer the loop come from the current config, so a
+    // changed setting shows up as a divergence
+    let mut agent_config = AgentConfig::from_user_config(&snapshot.model, &user_config)
+        .with_max_iterations(iterations)
+        .with_working_dir(working_dir)
+        .with_fallback_model(None);
+    if let Some(system) = system {
+        agent_config = agent_config.with_system_prompt(system);
+    }
+
+    println!("{}Replay{}", BOLD, RESET);
+    println!("  Session: {} (run {})", session.id, run);
+    println!("  Model: {}", snapshot.model);
+    println!("  Task: {}", task.lines().next().unwrap_or_default());
+    println!("  {}Nothing is sent to the model and no tools run{}", DIM, RESET);
+
+    let mut agent = AgentLoop::new(client, router, agent_config);
+    agent.set_replay(Replay::new(steps, step && crate::tools::security::is_interactive()));
+    let state = agent.run(&task).await?;
+    let replay = agent.take_replay().expect("set above");
+
+    if let Some(ref response) = state.final_response {
+        println!();
+        println!("{}Final Response:{}", BOLD, RESET);
+        println!("{}", response);
+    }
+    if let Some(ref error) = state.error {
+        println!();
+        println!("{}Ended with:{} {}", YELLOW, RESET, error);
+    }
+
+    println!();
+    if replay.divergences().is_empty() && replay.remaining() == 0 {
+        println!("{}Replay matched the recording ({} iterations){}", GREEN, state.iteration, RESET);
+        return Ok(());
+    }
+    if replay.stopped() {
+        println!("{}Replay stopped at iteration {}{}", DIM, state.iteration, RESET);
+        return Ok(());
+    }
+    println!("{}Replay diverged from the recording:{}", YELLOW, RESET);
+    for divergence in replay.divergences() {
+        println!("  - {}", divergence);
+    }
+    if replay.remaining() > 0 {
+        println!("  - {} recorded step(s) were never reached", replay.remaining());
+    }
+    anyhow::bail!("Replay of session {} diverged from the recording", id)
+}
+
 /// Delete a session
 pub async fn sessions_rm(id: &str) -> Result<()> {
     use crate::session::SessionStore;
@@ -1190,14 +2694,1153 @@ pub async fn sessions_resume(id: &str, auto: bool) -> Result<()> {
     }
 
     // Run agent with resumed session
-    agent(
-        task,
-        Some(session.model.clone()),
-        None,
+    let options = AgentOptions {
+        model: Some(session.model.clone()),
+        system: None,
         auto,
-        50,
-        false,
-        Some(session_id),
-        false,
-    ).await
+        max_iterations: 50,
+        quiet: false,
+        resume: Some(session_id),
+        no_save: false,
+        plan: false,
+        worktree: false,
+        dry_run: false,
+        review: false,
+        confirm_policy: None,
+        structured: false,
+        sampling: ModelOptions::default(),
+    };
+    agent(task, options).await
+}
+
+/// Find the project root and its dependency manifests
+fn discover_dependencies() -> Result<(PathBuf, Vec<crate::deps::DependencyManifest>)> {
+    let cwd = std::env::current_dir()?;
+    let root = crate::project::ProjectContext::discover(&cwd)
+        .map(|p| p.root)
+        .unwrap_or(cwd);
+    let manifests = crate::deps::discover(&root);
+    if manifests.is_empty() {
+        anyhow::bail!("No Cargo.toml, package.json, or pyproject.toml found in {}", root.display());
+    }
+    Ok((root, manifests))
+}
+
+/// List project dependencies
+pub async fn deps_list(include_dev: bool, json: bool) -> Result<()> {
+    use crate::deps::DependencyKind;
+
+    let (root, manifests) = discover_dependencies()?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&manifests)?);
+        return Ok(());
+    }
+
+    for manifest in &manifests {
+        let rel = manifest.path.strip_prefix(&root).unwrap_or(&manifest.path);
+        println!("{}{}{} {}({}){}", BOLD, rel.display(), RESET, DIM, manifest.ecosystem, RESET);
+        for dep in manifest.dependencies.iter().filter(|d| include_dev || d.kind != DependencyKind::Dev) {
+            let kind = if dep.kind == DependencyKind::Normal {
+                String::new()
+            } else {
+                format!(" {}[{}]{}", DIM, dep.kind, RESET)
+            };
+            println!(
+                "  {:<28} {:<14} {}{}{}{}",
+                dep.name,
+                dep.requirement,
+                GREEN,
+                dep.resolved.as_deref().unwrap_or("-"),
+                RESET,
+                kind
+            );
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+/// Check for outdated dependencies with the native tool of each ecosystem
+pub async fn deps_outdated() -> Result<()> {
+    let (_, manifests) = discover_dependencies()?;
+
+    let mut seen = Vec::new();
+    for manifest in &manifests {
+        // Workspace members share the root's lockfile; check each ecosystem once
+        if seen.contains(&manifest.ecosystem) {
+            continue;
+        }
+        seen.push(manifest.ecosystem);
+
+        let dir = manifest.path.parent().unwrap_or(Path::new("."));
+        let Some((program, args)) = crate::deps::outdated_command(manifest.ecosystem) else {
+            println!("{}Skipping {}:{} native tool not found", YELLOW, manifest.ecosystem, RESET);
+            continue;
+        };
+
+        println!("{}{} $ {} {}{}", BOLD, manifest.ecosystem, program, args.join(" "), RESET);
+        let output = crate::deps::run_native(dir, &program, &args)?;
+        if output.trim().is_empty() {
+            println!("  {}All dependencies are up to date{}", GREEN, RESET);
+        } else {
+            println!("{}", output.trim_end());
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+/// Explain where a dependency is declared and why it is present
+pub async fn deps_explain(name: &str) -> Result<()> {
+    let (root, manifests) = discover_dependencies()?;
+    let found = crate::deps::find(&manifests, name);
+
+    if found.is_empty() {
+        println!("{}{} is not a direct dependency{}", YELLOW, name, RESET);
+    } else {
+        println!("{}Declared in:{}", BOLD, RESET);
+        for (manifest, dep) in &found {
+            let rel = manifest.path.strip_prefix(&root).unwrap_or(&manifest.path);
+            println!(
+                "  {} ({}): {} -> {}",
+                rel.display(),
+                dep.kind,
+                dep.requirement,
+                dep.resolved.as_deref().unwrap_or("unresolved")
+            );
+        }
+    }
+
+    // Ask the native tool for the dependency path (covers transitive deps too)
+    let ecosystem = found.first().map(|(m, _)| m.ecosystem).unwrap_or(manifests[0].ecosystem);
+    if let Some((program, args)) = crate::deps::explain_command(ecosystem, name) {
+        println!();
+        println!("{}$ {} {}{}", DIM, program, args.join(" "), RESET);
+        let output = crate::deps::run_native(&root, &program, &args)?;
+        println!("{}", output.trim_end());
+    }
+
+    Ok(())
+}
+
+/// List processes started by quant that are still running
+pub async fn ps(kill: bool, json: bool) -> Result<()> {
+    let runs = crate::procs::load_runs()?;
+
+    if kill {
+        let orphaned: Vec<_> = runs.into_iter().filter(|r| !r.owner_alive()).collect();
+        let stopped = crate::procs::stop_runs(&orphaned)?;
+        println!("Stopped {} process(es)", stopped);
+        return Ok(());
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&runs)?);
+        return Ok(());
+    }
+
+    if runs.is_empty() {
+        println!("No processes started by quant are running");
+        return Ok(());
+    }
+
+    let pgids: Vec<u32> = runs.iter().flat_map(|r| r.processes.iter().map(|p| p.pid)).collect();
+    let members = crate::procs::group_commands(&pgids);
+    for run in &runs {
+        let owner = if run.owner_alive() {
+            format!("{}running{}", GREEN, RESET)
+        } else {
+            format!("{}exited{}", YELLOW, RESET)
+        };
+        println!(
+            "{}quant {}{} ({}, started {})",
+            BOLD,
+            run.pid,
+            RESET,
+            owner,
+            run.started_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+        );
+        for process in &run.processes {
+            println!("  {:<5} {:>7}  {}", process.kind, process.pid, process.command);
+            for (pid, _, command) in members.iter().filter(|(pid, pgid, _)| *pgid == process.pid && *pid != process.pid) {
+                println!("  {}        {:>7}  {}{}", DIM, pid, command, RESET);
+            }
+        }
+    }
+    if runs.iter().any(|r| !r.owner_alive()) {
+        println!();
+        println!("{}Stop processes from exited runs with `quant ps --kill`{}", DIM, RESET);
+    }
+
+    Ok(())
+}
+
+/// Generate a starter QUANT.md and `.quant/` scaffolding at the project root
+pub fn init(force: bool) -> Result<()> {
+    let cwd = std::env::current_dir()?;
+    let project = crate::project::ProjectContext::discover(&cwd).context("Could not determine the project root")?;
+
+    let quant_md = project.root.join("QUANT.md");
+    // Only the project's own file counts; inherited ones are left alone
+    let own_dirs = [project.root.clone(), project.root.join(".quant")];
+    let existing = project.quant_files.iter().find(|f| f.path.parent().is_some_and(|dir| own_dirs.iter().any(|d| d == dir)));
+    if let Some(existing) = existing.filter(|_| !force) {
+        anyhow::bail!(
+            "{} already exists; pass --force to replace QUANT.md",
+            existing.path.display()
+        );
+    }
+
+    println!("{}Detected:{} {} project {}", BOLD, RESET, project.project_type, project.name);
+    std::fs::write(&quant_md, project.generate_quant_md())
+        .with_context(|| format!("Failed to write {}", quant_md.display()))?;
+    println!("  {}✓{} {}", GREEN, RESET, quant_md.display());
+
+    for (relative, content) in project.scaffold_files() {
+        let path = project.root.join(relative);
+        if path.exists() {
+            println!("  {}-{} {} {}(exists){}", DIM, RESET, path.display(), DIM, RESET);
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+        println!("  {}✓{} {}", GREEN, RESET, path.display());
+    }
+
+    println!(
+        "\nEdit QUANT.md to describe the project; copy {}.example to {} to replace the built-in conventions.",
+        crate::project::SYSTEM_PROMPT_OVERRIDE,
+        crate::project::SYSTEM_PROMPT_OVERRIDE
+    );
+    Ok(())
+}
+
+/// List the QUANT.md files for the current directory, or print the merged context
+pub fn project_context(show: bool) -> Result<()> {
+    let cwd = std::env::current_dir()?;
+    let project = crate::project::ProjectContext::discover(&cwd).context("Could not determine the project root")?;
+
+    if show {
+        print!("{}", project.to_system_context());
+        return Ok(());
+    }
+
+    println!("{}Project:{} {} ({})", BOLD, RESET, project.name, project.root.display());
+    if project.quant_files.is_empty() {
+        println!("No QUANT.md found");
+        println!("{}Run `quant init` to create one{}", DIM, RESET);
+        return Ok(());
+    }
+
+    println!("{}QUANT.md files{} (later ones take precedence)", BOLD, RESET);
+    for (i, file) in project.quant_files.iter().enumerate() {
+        println!("  {:>2}. {}", i + 1, file.path.display());
+    }
+    if let Some(global) = crate::project::global_quant_file_path().filter(|p| !p.exists()) {
+        println!("{}Instructions for every project go in {}{}", DIM, global.display(), RESET);
+    }
+    println!("{}Use --show to print the merged context{}", DIM, RESET);
+    Ok(())
+}
+
+/// List the hooks that apply here and where each was defined
+pub fn hooks_list(json: bool) -> Result<()> {
+    let cwd = std::env::current_dir()?;
+    let project = crate::project::ProjectContext::discover(&cwd);
+    let manager = crate::hooks::HookManager::discover(&cwd, project.as_ref());
+    let hooks = manager.hooks();
+
+    if json {
+        let hooks: Vec<serde_json::Value> = hooks
+            .iter()
+            .map(|hook| {
+                serde_json::json!({
+                    "name": hook.name,
+                    "event": hook.event.as_str(),
+                    "command": hook.command,
+                    "action": hook.action.map(|a| a.as_str()),
+                    "tool_filter": hook.tool_filter,
+                    "enabled": hook.enabled,
+                    "abort_on_failure": hook.abort_on_failure,
+                    "origin": hook.origin.as_ref().map(|o| o.kind()),
+                    "path": hook.origin.as_ref().map(|o| o.path()),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&hooks)?);
+        return Ok(());
+    }
+
+    if hooks.is_empty() {
+        println!("No hooks configured");
+        println!(
+            "{}Define them in QUANT.md front matter or as [[hooks]] in the user or project config{}",
+            DIM, RESET
+        );
+        return Ok(());
+    }
+
+    println!("{}Hooks{} (in the order they run)", BOLD, RESET);
+    for hook in hooks {
+        let status = if hook.enabled {
+            format!("{}enabled{}", GREEN, RESET)
+        } else {
+            format!("{}disabled{}", DIM, RESET)
+        };
+        let filter = hook.tool_filter.as_ref().map(|t| format!(" [{}]", t)).unwrap_or_default();
+        let action = hook.action.map(|a| format!(" ({})", a.as_str())).unwrap_or_default();
+        println!("  {}{}{} {}{}{}  {}", BOLD, hook.name, RESET, hook.event.as_str(), filter, action, status);
+        if let Some(ref origin) = hook.origin {
+            println!("    {}{}{}", DIM, origin, RESET);
+        }
+    }
+    Ok(())
+}
+
+/// Re-save every session, conversation and the search index encrypted (or
+/// decrypted), then set `storage.encrypt` in the user config to match
+pub fn storage_migrate(encrypt: bool) -> Result<()> {
+    use crate::config::{set_in_file, UserConfig};
+    use crate::conversation::ConversationStore;
+    use crate::search::SearchIndex;
+    use crate::session::SessionStore;
+    use crate::storage::Vault;
+
+    let vault = Vault::new(encrypt);
+    vault.check_key()?;
+    let mut failed = 0;
+
+    let sessions = SessionStore::with_vault(vault.clone())?;
+    let mut session_count = 0;
+    for summary in sessions.list()? {
+        match sessions.load(&summary.id).and_then(|session| sessions.save(&session)) {
+            Ok(_) => session_count += 1,
+            Err(e) => {
+                eprintln!("{}Failed:{} session {}: {:#}", RED, RESET, summary.id, e);
+                failed += 1;
+            }
+        }
+    }
+
+    let conversations = ConversationStore::with_vault(vault.clone())?;
+    let mut conversation_count = 0;
+    for summary in conversations.list()? {
+        match conversations.load(&summary.id).and_then(|conversation| conversations.save(&conversation)) {
+            Ok(_) => conversation_count += 1,
+            Err(e) => {
+                eprintln!("{}Failed:{} conversation {}: {:#}", RED, RESET, summary.id, e);
+                failed += 1;
+            }
+        }
+    }
+
+    // An unreadable index is dropped; the next search rebuilds it
+    let index_path = SearchIndex::default_path()?;
+    if index_path.exists() {
+        match SearchIndex::load(&index_path, &vault) {
+            Ok(index) => index.save(&index_path, &vault)?,
+            Err(_) => std::fs::remove_file(&index_path)?,
+        }
+    }
+
+    let config_path = UserConfig::config_path()?;
+    if !config_path.exists() {
+        UserConfig::create_default()?;
+    }
+    set_in_file(&config_path, "storage.encrypt", if encrypt { "true" } else { "false" })?;
+
+    println!(
+        "{}{}{} {} sessions and {} conversations",
+        GREEN,
+        if encrypt { "Encrypted" } else { "Decrypted" },
+        RESET,
+        session_count,
+        conversation_count
+    );
+    println!("{}Set storage.encrypt = {} in {}{}", DIM, encrypt, config_path.display(), RESET);
+    if encrypt {
+        println!(
+            "{}The key is in the OS keychain (or {}); without it these files cannot be read{}",
+            DIM,
+            crate::storage::KEY_ENV,
+            RESET
+        );
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} files could not be converted", failed);
+    }
+    Ok(())
+}
+
+/// List the bash command patterns the project trusts
+pub fn approvals_list(json: bool) -> Result<()> {
+    let approvals = crate::tools::approvals::Approvals::discover()?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(approvals.commands())?);
+        return Ok(());
+    }
+
+    if approvals.commands().is_empty() {
+        println!("No trusted commands");
+        println!("{}Answer `t` when approving a bash command to trust similar ones{}", DIM, RESET);
+        return Ok(());
+    }
+
+    println!("{}Trusted commands{} ({})", BOLD, RESET, approvals.path().display());
+    for (i, command) in approvals.commands().iter().enumerate() {
+        println!(
+            "  {:>2}. {}  {}{}{}",
+            i + 1,
+            command.pattern,
+            DIM,
+            command.added_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+            RESET
+        );
+    }
+
+    Ok(())
+}
+
+/// Stop trusting a bash command pattern
+pub fn approvals_revoke(pattern: &str) -> Result<()> {
+    let mut approvals = crate::tools::approvals::Approvals::discover()?;
+    let removed = approvals.revoke(pattern)?;
+    println!("Revoked `{}`", removed.pattern);
+    Ok(())
+}
+
+/// Print the latest records of the audit log, then follow it if asked
+pub async fn audit_tail(lines: usize, follow: bool, json: bool) -> Result<()> {
+    use crate::audit::{audit_log_path, parse_records, read_records};
+
+    let path = audit_log_path()?;
+    let records = read_records(&path)?;
+    if records.is_empty() && !follow {
+        println!("No tool calls recorded yet ({})", path.display());
+        return Ok(());
+    }
+    for record in &records[records.len().saturating_sub(lines)..] {
+        print_audit_record(record, json)?;
+    }
+    if !follow {
+        return Ok(());
+    }
+
+    // Poll for lines appended since; a partial last line waits for the next pass
+    let mut offset = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    loop {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let Ok(content) = std::fs::read(&path) else {
+            continue;
+        };
+        if (content.len() as u64) < offset {
+            offset = 0;
+        }
+        let new = &content[offset as usize..];
+        let Some(end) = new.iter().rposition(|&b| b == b'\n') else {
+            continue;
+        };
+        for record in parse_records(&String::from_utf8_lossy(&new[..=end])) {
+            print_audit_record(&record, json)?;
+        }
+        offset += end as u64 + 1;
+    }
+}
+
+/// Print the audit records matching the given conditions
+pub fn audit_search(
+    query: Option<String>,
+    tool: Option<String>,
+    status: Option<String>,
+    since: Option<String>,
+    limit: usize,
+    json: bool,
+) -> Result<()> {
+    use crate::audit::{audit_log_path, parse_since, parse_status, read_records, AuditFilter};
+
+    let filter = AuditFilter {
+        query,
+        tool,
+        status: status.as_deref().map(parse_status).transpose()?,
+        since: since.as_deref().map(parse_since).transpose()?,
+    };
+    let records = read_records(&audit_log_path()?)?;
+    let matches: Vec<_> = records.iter().filter(|r| filter.matches(r)).collect();
+    if matches.is_empty() {
+        println!("No matching tool calls");
+        return Ok(());
+    }
+    for record in &matches[matches.len().saturating_sub(limit)..] {
+        print_audit_record(record, json)?;
+    }
+    if !json && matches.len() > limit {
+        println!("{}{} older matches not shown (--limit){}", DIM, matches.len() - limit, RESET);
+    }
+    Ok(())
+}
+
+fn print_audit_record(record: &crate::audit::AuditRecord, json: bool) -> Result<()> {
+    use crate::audit::AuditStatus;
+
+    if json {
+        println!("{}", serde_json::to_string(record)?);
+        return Ok(());
+    }
+    let color = match record.status {
+        AuditStatus::Ok | AuditStatus::Simulated => "",
+        AuditStatus::Failed | AuditStatus::Error | AuditStatus::NotFound => RED,
+        AuditStatus::Denied | AuditStatus::Skipped | AuditStatus::Aborted => YELLOW,
+    };
+    println!("{}{}{}", color, record.summary(), if color.is_empty() { "" } else { RESET });
+    Ok(())
+}
+
+/// List the loaded tool plugins and the libraries that failed to load
+pub fn plugins_list(json: bool) -> Result<()> {
+    use crate::tools::plugins::{loaded, plugin_dirs};
+
+    let set = loaded();
+    if json {
+        let plugins: Vec<_> = set
+            .plugins
+            .iter()
+            .map(|p| serde_json::json!({ "path": p.path, "manifest": p.manifest }))
+            .collect();
+        let errors: Vec<_> = set
+            .errors
+            .iter()
+            .map(|(path, error)| serde_json::json!({ "path": path, "error": error }))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&serde_json::json!({ "plugins": plugins, "errors": errors }))?);
+        return Ok(());
+    }
+
+    if set.plugins.is_empty() && set.errors.is_empty() {
+        println!("No plugins installed");
+        for dir in plugin_dirs() {
+            println!("{}  Looked in {}{}", DIM, dir.display(), RESET);
+        }
+        return Ok(());
+    }
+    for plugin in &set.plugins {
+        let manifest = &plugin.manifest;
+        println!("{}{}{} {}  {}{}{}", BOLD, manifest.name, RESET, manifest.version, DIM, plugin.path.display(), RESET);
+        for tool in &manifest.tools {
+            let level = serde_json::to_value(tool.security_level)?;
+            println!("  {:<20} {}{:<10}{} {}", tool.name, DIM, level.as_str().unwrap_or(""), RESET, tool.description);
+        }
+    }
+    for (path, error) in &set.errors {
+        println!("{}Failed to load{} {}: {}", YELLOW, RESET, path.display(), error);
+    }
+    Ok(())
+}
+
+/// Start the quant daemon in the background
+pub async fn daemon_start() -> Result<()> {
+    use crate::daemon::{socket_path, DaemonClient};
+
+    if let Some(mut daemon) = DaemonClient::connect_default().await {
+        let status = daemon.status().await?;
+        println!("The quant daemon is already running (PID {})", status.pid);
+        return Ok(());
+    }
+
+    // In its own process group so it outlives this shell
+    let mut cmd = Command::new(std::env::current_exe().context("Failed to find the quant binary")?);
+    cmd.args(["daemon", "run"])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    let mut child = cmd.spawn().context("Failed to start the quant daemon")?;
+
+    let socket = socket_path();
+    for _ in 0..50 {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        if DaemonClient::connect(&socket).await.is_ok() {
+            println!("{}quant daemon started{} (PID {})", GREEN, RESET, child.id());
+            println!("{}  Listening on {}{}", DIM, socket.display(), RESET);
+            return Ok(());
+        }
+        if let Some(status) = child.try_wait()? {
+            anyhow::bail!("The quant daemon exited with {}; run `quant daemon run` to see why", status);
+        }
+    }
+    anyhow::bail!("The quant daemon didn't start listening on {}", socket.display())
+}
+
+/// Stop the quant daemon
+pub async fn daemon_stop() -> Result<()> {
+    match crate::daemon::DaemonClient::connect_default().await {
+        Some(mut daemon) => {
+            daemon.shutdown().await?;
+            println!("{}quant daemon stopped{}", GREEN, RESET);
+        }
+        None => println!("The quant daemon is not running"),
+    }
+    Ok(())
+}
+
+/// Show the daemon's MCP servers, RAG store and warm models
+pub async fn daemon_status(json: bool) -> Result<()> {
+    let Some(mut daemon) = crate::daemon::DaemonClient::connect_default().await else {
+        if json {
+            println!("null");
+        } else {
+            println!("The quant daemon is not running. Start it with: quant daemon start");
+        }
+        return Ok(());
+    };
+    let status = daemon.status().await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&status)?);
+        return Ok(());
+    }
+
+    let started = status.started_at.with_timezone(&chrono::Local);
+    println!("{}quant daemon{} PID {}, running since {}", BOLD, RESET, status.pid, started.format("%Y-%m-%d %H:%M"));
+
+    println!();
+    println!("{}MCP servers:{}", BOLD, RESET);
+    if status.projects.is_empty() {
+        println!("  {}none yet; started by the first agent run in a project{}", DIM, RESET);
+    }
+    for project in &status.projects {
+        println!(
+            "  {}  {} ({} tools) {}idle {}s{}",
+            project.root.display(),
+            project.servers.join(", "),
+            project.tools,
+            DIM,
+            project.idle_secs,
+            RESET
+        );
+    }
+
+    println!();
+    match status.rag_chunks {
+        Some(chunks) => println!("{}RAG store:{} {} chunks loaded", BOLD, RESET, chunks),
+        None => println!("{}RAG store:{} {}not loaded yet{}", BOLD, RESET, DIM, RESET),
+    }
+    match status.last_warm {
+        Some(last) if !status.warm_models.is_empty() => println!(
+            "{}Warm models:{} {} {}(reloaded {}){}",
+            BOLD,
+            RESET,
+            status.warm_models.join(", "),
+            DIM,
+            last.with_timezone(&chrono::Local).format("%H:%M"),
+            RESET
+        ),
+        _ => println!("{}Warm models:{} {}none{}", BOLD, RESET, DIM, RESET),
+    }
+    Ok(())
+}
+
+/// Run the daemon in this process until stopped (run by `daemon start`)
+pub async fn daemon_run() -> Result<()> {
+    let daemon = std::sync::Arc::new(crate::daemon::Daemon::new());
+    daemon.serve(crate::daemon::socket_path()).await
+}
+
+/// Sync sessions, conversations and trusted commands with another machine
+pub async fn sync(peer: Option<String>, include: Vec<String>, exclude: Vec<String>, dry_run: bool) -> Result<()> {
+    use crate::sync::{sync_with_peer, Filter, SyncOptions};
+
+    let config = crate::config::UserConfig::load().unwrap_or_default().sync;
+    let peer = peer
+        .or(config.peer)
+        .context("No peer to sync with; pass --peer <host> or set sync.peer")?;
+    // Filters on the command line replace the configured ones
+    let include = if include.is_empty() { config.include } else { include };
+    let exclude = if exclude.is_empty() { config.exclude } else { exclude };
+
+    let options = SyncOptions {
+        ssh: config.ssh,
+        remote_command: config.remote_command,
+        filter: Filter::new(&include, &exclude)?,
+        dry_run,
+    };
+
+    println!("{}Syncing with {}...{}", DIM, peer, RESET);
+    let report = sync_with_peer(&peer, &options).await?;
+
+    for (path, action) in &report.actions {
+        let color = if action.contains("conflict") { YELLOW } else { CYAN };
+        println!("  {}{:<16}{} {}", color, action, RESET, path);
+    }
+    for (path, error) in &report.failed {
+        println!("  {}failed{}           {}: {}", RED, RESET, path, error);
+    }
+
+    if dry_run {
+        println!(
+            "{}Dry run:{} {} file(s) would change, {} unchanged",
+            BOLD,
+            RESET,
+            report.actions.len(),
+            report.unchanged
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{}Synced with {}{}: {} pulled, {} pushed, {} unchanged ({:.1} KB sent, {:.1} KB received)",
+        GREEN,
+        report.peer_host,
+        RESET,
+        report.pulled,
+        report.pushed,
+        report.unchanged,
+        report.bytes_sent as f64 / 1024.0,
+        report.bytes_received as f64 / 1024.0
+    );
+    if report.conflicts > 0 {
+        println!(
+            "{}{} conflict(s): the newer copy won; the other was kept as *.conflict-<host>-<time>.*{}",
+            YELLOW, report.conflicts, RESET
+        );
+    }
+    if !report.failed.is_empty() {
+        anyhow::bail!("{} file(s) failed to sync", report.failed.len());
+    }
+    Ok(())
+}
+
+/// Start the project's MCP servers, health check them and report their status
+pub async fn mcp_status(json: bool) -> Result<()> {
+    use crate::mcp::{McpManager, ServerState};
+
+    let cwd = std::env::current_dir()?;
+    let servers = crate::project::ProjectContext::discover(&cwd)
+        .and_then(|ctx| ctx.quant_file)
+        .map(|quant_file| quant_file.mcp_servers)
+        .unwrap_or_default();
+
+    let mut manager = McpManager::new();
+    let mut start_errors = std::collections::HashMap::new();
+    for config in servers.iter().filter(|c| c.auto_start) {
+        if let Err(e) = manager.start_server(config.clone()).await {
+            start_errors.insert(config.name.clone(), e.root_cause().to_string());
+        }
+    }
+    manager.supervise().await;
+
+    let tools = manager.discover_tools().await.unwrap_or_default();
+    let rows: Vec<serde_json::Value> = servers
+        .iter()
+        .map(|config| {
+            let state = match (manager.server_state(&config.name), start_errors.get(&config.name)) {
+                (Some(state), _) => state.to_string(),
+                (None, Some(error)) => ServerState::Failed(error.clone()).to_string(),
+                (None, None) => "not started (auto_start: false)".to_string(),
+            };
+            serde_json::json!({
+                "name": config.name,
+                "endpoint": config.endpoint(),
+                "running": manager.is_running(&config.name),
+                "state": state,
+                "tools": tools.iter().filter(|t| t.server_name() == config.name).count(),
+            })
+        })
+        .collect();
+    manager.stop_all().await;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+        return Ok(());
+    }
+
+    if rows.is_empty() {
+        println!("No MCP servers configured");
+        println!("Add them under {}mcp_servers{} in QUANT.md", CYAN, RESET);
+        return Ok(());
+    }
+
+    println!("{}MCP Servers:{}", BOLD, RESET);
+    for row in &rows {
+        let running = row["running"].as_bool().unwrap_or(false);
+        let mut msg = format!("{} - {}", row["name"].as_str().unwrap_or(""), row["state"].as_str().unwrap_or(""));
+        if running {
+            msg.push_str(&format!(" ({} tools)", row["tools"]));
+        }
+        msg.push_str(&format!(" {}{}{}", DIM, row["endpoint"].as_str().unwrap_or(""), RESET));
+        print_status(running, &msg);
+    }
+
+    Ok(())
+}
+
+/// Update or rebuild the session search index
+pub async fn index_sessions(rebuild: bool, json: bool) -> Result<()> {
+    use crate::search::{IndexSource, SearchIndex};
+
+    let path = SearchIndex::default_path()?;
+    let vault = crate::storage::Vault::from_config();
+    let mut index = if rebuild {
+        SearchIndex::new()
+    } else {
+        match SearchIndex::load(&path, &vault) {
+            Ok(index) => index,
+            Err(e) => {
+                if !json {
+                    println!("{}Index unreadable ({}), rebuilding{}", YELLOW, e, RESET);
+                }
+                SearchIndex::new()
+            }
+        }
+    };
+
+    let stats = index.update(&IndexSource::defaults()?);
+    index.save(&path, &vault)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+
+    println!("{}Search index {}{}", BOLD, if rebuild { "rebuilt" } else { "updated" }, RESET);
+    println!("  Documents:     {}", stats.documents);
+    println!("  Chunks:        {}", stats.chunks);
+    println!("  Terms:         {}", stats.terms);
+    println!("  Added:         {}", stats.added);
+    println!("  Updated:       {}", stats.updated);
+    println!("  Stale removed: {}", stats.removed);
+    if stats.failed > 0 {
+        println!("  {}Unreadable:    {}{}", RED, stats.failed, RESET);
+    }
+    println!("{}{}{}", DIM, path.display(), RESET);
+
+    Ok(())
+}
+
+/// Search the session index, refreshing it first
+pub async fn index_search(query: &str, limit: usize) -> Result<()> {
+    use crate::search::DocumentKind;
+
+    let hits = crate::search::search_saved(query, limit)?;
+    if hits.is_empty() {
+        println!("{}No matches for '{}'{}", DIM, query, RESET);
+        return Ok(());
+    }
+
+    for hit in &hits {
+        // Conversations load by id prefix; sessions need the full id
+        let id = match hit.kind {
+            DocumentKind::Conversation => &hit.id[..8.min(hit.id.len())],
+            DocumentKind::Session => hit.id.as_str(),
+        };
+        println!(
+            "{}{}{} {}[{}]{} {} {}({}){}",
+            CYAN,
+            id,
+            RESET,
+            DIM,
+            hit.kind,
+            RESET,
+            hit.title,
+            DIM,
+            hit.updated_at.format("%Y-%m-%d %H:%M"),
+            RESET
+        );
+        println!("  {}", hit.snippet);
+    }
+    println!();
+    println!(
+        "{}Open a conversation with `quant chat --load <id>`, a session with `quant sessions resume <id>`{}",
+        DIM, RESET
+    );
+
+    Ok(())
+}
+
+/// Chunk and embed documents into the RAG store
+pub async fn rag_index(paths: &[String], rebuild: bool, json: bool) -> Result<()> {
+    use crate::rag::RagStore;
+
+    let config = crate::config::load_llm_config().context("Failed to load llm.toml")?;
+    let client = config.ollama_client();
+    if !client.health_check().await.unwrap_or(false) {
+        anyhow::bail!("Ollama is not running. Start with: quant serve start");
+    }
+
+    let settings = crate::config::load_settings()?;
+    let embed_model = settings.user.rag.embed_model.clone();
+    ensure_model(&client, &embed_model, crate::tools::security::is_interactive()).await?;
+
+    let path = RagStore::default_path()?;
+    let mut store = if rebuild { RagStore::new() } else { RagStore::load(&path)? };
+    let paths: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+
+    let stats = crate::rag::index(&mut store, &client, &embed_model, &paths, |file| {
+        if !json {
+            println!("{}Indexing{} {}", DIM, RESET, file.display());
+        }
+    })
+    .await;
+    // Keep what was embedded before a failure
+    store.save(&path)?;
+    let stats = stats?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+
+    println!("{}RAG store updated{} ({})", BOLD, RESET, embed_model);
+    println!("  Indexed:   {}", stats.indexed);
+    println!("  Unchanged: {}", stats.unchanged);
+    println!("  Removed:   {}", stats.removed);
+    println!("  Chunks:    {}", stats.chunks);
+    for (file, error) in &stats.failed {
+        println!("  {}Failed:    {}: {}{}", RED, file.display(), error, RESET);
+    }
+    println!("{}{}{}", DIM, path.display(), RESET);
+
+    Ok(())
+}
+
+/// Answer a question from the RAG store, citing the retrieved chunks
+pub async fn rag_query(question: &str, model: Option<String>, top_k: Option<usize>) -> Result<()> {
+    use crate::rag::{RagHit, RagStore};
+
+    let config = crate::config::load_llm_config().context("Failed to load llm.toml")?;
+    let client = config.ollama_client();
+    if !client.health_check().await.unwrap_or(false) {
+        anyhow::bail!("Ollama is not running. Start with: quant serve start");
+    }
+
+    let settings = crate::config::load_settings()?;
+    let rag = &settings.user.rag;
+    let model = match model.or_else(|| rag.model.clone()).or_else(|| settings.user.ask.default_model.clone()) {
+        Some(model) => model,
+        None => model_for_intent(&settings, Intent::Chat, question)?.0,
+    };
+    ensure_model(&client, &model, crate::tools::security::is_interactive()).await?;
+
+    // The daemon keeps the store loaded; otherwise read it here
+    let top_k = top_k.unwrap_or(rag.top_k).max(1);
+    let matches = match crate::daemon::DaemonClient::connect_default().await {
+        Some(mut daemon) => daemon.rag_search(question, top_k).await?,
+        None => {
+            let store = RagStore::load(&RagStore::default_path()?)?;
+            crate::rag::search_question(&store, &client, question, top_k).await?
+        }
+    };
+    let hits: Vec<RagHit> = matches.iter().map(|m| m.hit()).collect();
+    let messages = vec![ChatMessage::user(crate::rag::build_prompt(question, &hits))];
+
+    let mut stream = tokio::time::timeout(Duration::from_secs(60), client.chat_stream(&model, &messages, None))
+        .await
+        .context("Connection timed out after 60 seconds")??;
+    let stream_timeout = Duration::from_secs(120);
+    while let Ok(Some(chunk)) = tokio::time::timeout(stream_timeout, stream.next()).await {
+        if let Some(msg) = &chunk?.message {
+            print!("{}", msg.content);
+            io::stdout().flush()?;
+        }
+    }
+    println!();
+
+    println!();
+    println!("{}Sources:{}", BOLD, RESET);
+    for (i, hit) in hits.iter().enumerate() {
+        println!("  {}[{}]{} {} {}({:.2}){}", CYAN, i + 1, RESET, hit.source(), DIM, hit.score, RESET);
+    }
+
+    Ok(())
+}
+
+/// List the files in the RAG store
+pub fn rag_list(json: bool) -> Result<()> {
+    use crate::rag::RagStore;
+
+    let path = RagStore::default_path()?;
+    let store = RagStore::load(&path)?;
+    let docs = store.documents();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&docs)?);
+        return Ok(());
+    }
+    if docs.is_empty() {
+        println!("{}Nothing indexed. Add documents with `quant rag index <paths>`{}", DIM, RESET);
+        return Ok(());
+    }
+
+    for doc in &docs {
+        println!(
+            "{} {}({} chunks, {}){}",
+            doc.path.display(),
+            DIM,
+            doc.chunks,
+            doc.indexed_at.format("%Y-%m-%d %H:%M"),
+            RESET
+        );
+    }
+    println!();
+    println!("{}{} files, {} chunks, embedded with {}{}", DIM, docs.len(), store.chunk_count(), store.embed_model(), RESET);
+
+    Ok(())
+}
+
+/// Run a scripted pipeline from a run file
+pub async fn run_file(
+    path: &Path,
+    var_overrides: &[String],
+    model: Option<String>,
+    auto: bool,
+    dry_run: bool,
+    quiet: bool,
+) -> Result<()> {
+    use crate::runfile::{self, RunFile, Runner};
+
+    let file = RunFile::load(path)?;
+    let mut vars = file.vars.clone();
+    vars.extend(runfile::parse_var_overrides(var_overrides)?);
+
+    if dry_run {
+        // Step outputs aren't known yet; show them as placeholders
+        for step in &file.steps {
+            vars.insert(step.name.clone(), format!("{{{{{}}}}}", step.name));
+        }
+        println!("{}{}{}", BOLD, file.name.as_deref().unwrap_or(&path.display().to_string()), RESET);
+        for (i, step) in file.steps.iter().enumerate() {
+            println!();
+            println!("{}[{}] {} ({}){}", CYAN, i + 1, step.name, step.action.kind(), RESET);
+            println!("{}", runfile::interpolate(step.action.text(), &vars)?);
+        }
+        return Ok(());
+    }
+
+    let config = crate::config::load_llm_config_or_default();
+    let client = config.ollama_client();
+    if file.needs_model() && !client.health_check().await.unwrap_or(false) {
+        anyhow::bail!(
+            "Ollama is not running.\nStart with: {}quant serve start{}",
+            BLUE,
+            RESET
+        );
+    }
+
+    let model = model.unwrap_or_else(|| {
+        if !config.models.coding.is_empty() {
+            config.models.coding.clone()
+        } else {
+            "llama3.2".to_string()
+        }
+    });
+    let working_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+
+    let runner = Runner::new(client, model, working_dir)
+        .with_auto(auto)
+        .with_verbose(!quiet);
+    let outcomes = runner.run(&file, &mut vars).await?;
+    crate::lsp::shutdown_all().await;
+    let _ = crate::context::save_token_cache();
+
+    if quiet {
+        if let Some(last) = outcomes.last().filter(|o| o.success) {
+            println!("{}", last.output);
+        }
+    } else {
+        runfile::print_summary(&outcomes, file.steps.len());
+    }
+
+    // The runner stops at the first failure that isn't marked continue_on_error
+    if let Some(last) = outcomes.last() {
+        let step = &file.steps[outcomes.len() - 1];
+        if !last.success && !step.continue_on_error {
+            anyhow::bail!("Step '{}' failed: {}", last.name, last.output);
+        }
+    }
+    Ok(())
+}
+
+/// Flags of `quant eval`
+pub struct EvalOptions {
+    pub models: Vec<String>,
+    pub runs: Option<usize>,
+    pub tasks: Vec<String>,
+    pub json: bool,
+    pub output: Option<PathBuf>,
+    pub quiet: bool,
+    pub sampling: ModelOptions,
+}
+
+/// Run an eval suite against one or more models and print the report
+pub async fn eval(path: &Path, options: EvalOptions) -> Result<()> {
+    use crate::eval::{Evaluator, Suite};
+
+    let EvalOptions { models, runs, tasks, json, output, quiet, sampling } = options;
+    let mut suite = Suite::load(path)?;
+    suite.retain_tasks(&tasks)?;
+    let runs = runs.unwrap_or(suite.runs);
+    if runs == 0 {
+        anyhow::bail!("--runs must be at least 1");
+    }
+
+    let config = crate::config::load_llm_config_or_default();
+    let client = config.ollama_client();
+    if !client.health_check().await.unwrap_or(false) {
+        anyhow::bail!(
+            "Ollama is not running.\nStart with: {}quant serve start{}",
+            BLUE,
+            RESET
+        );
+    }
+
+    let mut names = if models.is_empty() { suite.models.clone() } else { models };
+    if names.is_empty() {
+        if config.models.coding.is_empty() {
+            anyhow::bail!("No models to evaluate: pass --model or list `models` in the suite");
+        }
+        names.push(config.models.coding.clone());
+    }
+
+    // Flags win over alias options, as with `quant agent`
+    let user_config = crate::config::UserConfig::load().unwrap_or_default();
+    let mut candidates = Vec::with_capacity(names.len());
+    for name in &names {
+        let resolved = resolve_model(&client, &user_config.aliases.models, name).await?;
+        ensure_model(&client, &resolved.model, crate::tools::security::is_interactive()).await?;
+        candidates.push((resolved.model, sampling.clone().or(&resolved.options)));
+    }
+
+    if !quiet {
+        eprintln!(
+            "{}Evaluating {} model(s) on {} task(s), {} run(s) each{}",
+            BOLD,
+            candidates.len(),
+            suite.tasks.len(),
+            runs,
+            RESET
+        );
+    }
+    let report = Evaluator::new(client)
+        .with_verbose(!quiet)
+        .run(&suite, &candidates, runs)
+        .await?;
+    crate::lsp::shutdown_all().await;
+    let _ = crate::context::save_token_cache();
+
+    let text = if json { report.to_json()? } else { report.to_markdown() };
+    if let Some(output) = output {
+        std::fs::write(&output, &text).with_context(|| format!("Failed to write {}", output.display()))?;
+        eprintln!("{}✓{} Report written to {}", GREEN, RESET, output.display());
+    }
+    println!("{}", text.trim_end());
+    Ok(())
 }
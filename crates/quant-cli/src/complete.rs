@@ -0,0 +1,295 @@
+//! Inline code completion for editors
+//!
+//! `quant complete --stdin` answers a single fill-in-the-middle request;
+//! `quant complete --serve` keeps the coding model warm behind a Unix socket
+//! for editor plugins. Both speak the same JSON: a request carries the text
+//! before (`prefix`) and after (`suffix`) the cursor, and the response
+//! carries the text to insert.
+//!
+//! Socket protocol: one JSON request per line, one JSON response per line.
+//! An optional `id` is echoed back so clients can pipeline requests.
+
+use anyhow::{Context, Result};
+use llm_core::{ChatOptions, OllamaClient};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tracing::{debug, info, warn};
+
+/// Default generation length for a completion
+const DEFAULT_MAX_TOKENS: i32 = 128;
+
+/// Default sampling temperature (low for predictable code)
+const DEFAULT_TEMPERATURE: f32 = 0.2;
+
+/// How long the server asks Ollama to keep the model loaded between requests
+pub const SERVER_KEEP_ALIVE: &str = "30m";
+
+/// A fill-in-the-middle request
+#[derive(Debug, Clone, Deserialize)]
+pub struct CompletionRequest {
+    /// Text before the cursor
+    pub prefix: String,
+    /// Text after the cursor
+    #[serde(default)]
+    pub suffix: String,
+    /// Model override
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub max_tokens: Option<i32>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub stop: Option<Vec<String>>,
+    /// Opaque request id, echoed in the response
+    #[serde(default)]
+    pub id: Option<Value>,
+}
+
+impl CompletionRequest {
+    /// Parse stdin input: a JSON request, or raw text used as the prefix
+    pub fn from_input(input: &str) -> Result<Self> {
+        if input.trim_start().starts_with('{') {
+            return serde_json::from_str(input).context("Invalid completion request JSON");
+        }
+        Ok(Self {
+            prefix: input.to_string(),
+            suffix: String::new(),
+            model: None,
+            max_tokens: None,
+            temperature: None,
+            stop: None,
+            id: None,
+        })
+    }
+}
+
+/// A completion result
+#[derive(Debug, Clone, Serialize)]
+pub struct CompletionResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    pub completion: String,
+    pub model: String,
+    pub duration_ms: u64,
+}
+
+/// Runs completions against Ollama's generate API
+pub struct Completer {
+    client: OllamaClient,
+    model: String,
+    keep_alive: Option<String>,
+}
+
+impl Completer {
+    pub fn new(client: OllamaClient, model: impl Into<String>) -> Self {
+        Self {
+            client,
+            model: model.into(),
+            keep_alive: None,
+        }
+    }
+
+    /// Ask Ollama to keep the model loaded for this long after each request
+    pub fn with_keep_alive(mut self, keep_alive: impl Into<String>) -> Self {
+        self.keep_alive = Some(keep_alive.into());
+        self
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub async fn complete(&self, req: &CompletionRequest) -> Result<CompletionResponse> {
+        let model = req.model.as_deref().unwrap_or(&self.model);
+        let options = ChatOptions {
+            temperature: Some(req.temperature.unwrap_or(DEFAULT_TEMPERATURE)),
+            num_predict: Some(req.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS)),
+            stop: req.stop.clone(),
+            ..Default::default()
+        };
+        let suffix = (!req.suffix.is_empty()).then_some(req.suffix.as_str());
+
+        let start = Instant::now();
+        let response = self
+            .client
+            .generate(model, &req.prefix, suffix, Some(options), self.keep_alive.as_deref())
+            .await?;
+
+        Ok(CompletionResponse {
+            id: req.id.clone(),
+            completion: trim_suffix_overlap(&response.response, &req.suffix),
+            model: response.model,
+            duration_ms: start.elapsed().as_millis() as u64,
+        })
+    }
+
+    /// Handle one protocol line, always producing a JSON response line
+    pub async fn handle_line(&self, line: &str) -> String {
+        let req = match serde_json::from_str::<CompletionRequest>(line) {
+            Ok(req) => req,
+            Err(e) => return error_line(None, &format!("Invalid request: {}", e)),
+        };
+        match self.complete(&req).await {
+            Ok(resp) => serde_json::to_string(&resp).unwrap_or_default(),
+            Err(e) => error_line(req.id, &format!("{:#}", e)),
+        }
+    }
+}
+
+fn error_line(id: Option<Value>, error: &str) -> String {
+    let mut obj = serde_json::json!({ "error": error });
+    if let Some(id) = id {
+        obj["id"] = id;
+    }
+    obj.to_string()
+}
+
+/// Drop trailing lines of the completion that repeat the start of the suffix
+///
+/// FIM models sometimes regenerate the code after the cursor (typically a
+/// closing brace); inserting it would duplicate that code.
+pub fn trim_suffix_overlap(completion: &str, suffix: &str) -> String {
+    let completion_lines: Vec<&str> = completion.lines().collect();
+    let suffix_lines: Vec<&str> = suffix.lines().skip_while(|l| l.trim().is_empty()).collect();
+
+    let max = completion_lines.len().min(suffix_lines.len());
+    for k in (1..=max).rev() {
+        let tail = &completion_lines[completion_lines.len() - k..];
+        let head = &suffix_lines[..k];
+        let all_match = tail.iter().zip(head).all(|(a, b)| a.trim() == b.trim());
+        if all_match && tail.iter().any(|l| !l.trim().is_empty()) {
+            let kept = &completion_lines[..completion_lines.len() - k];
+            return kept.join("\n").trim_end_matches([' ', '\t']).to_string();
+        }
+    }
+    completion.to_string()
+}
+
+/// Default socket path for the completion server
+pub fn default_socket_path() -> PathBuf {
+    dirs::runtime_dir()
+        .or_else(dirs::cache_dir)
+        .unwrap_or_else(std::env::temp_dir)
+        .join("quant")
+        .join("complete.sock")
+}
+
+/// Serve completions on a Unix socket until interrupted
+pub async fn serve(completer: Completer, socket: &Path) -> Result<()> {
+    use tokio::net::UnixListener;
+
+    if let Some(parent) = socket.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // A leftover socket from a previous run would make bind fail
+    if socket.exists() {
+        std::fs::remove_file(socket).with_context(|| format!("Failed to remove stale socket {}", socket.display()))?;
+    }
+
+    let listener = UnixListener::bind(socket).with_context(|| format!("Failed to bind {}", socket.display()))?;
+    info!(socket = %socket.display(), model = %completer.model, "Completion server listening");
+
+    let completer = Arc::new(completer);
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = match accepted {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        warn!(error = %e, "Failed to accept connection");
+                        continue;
+                    }
+                };
+                let completer = completer.clone();
+                tokio::spawn(async move {
+                    let (reader, mut writer) = stream.into_split();
+                    let mut lines = BufReader::new(reader).lines();
+                    while let Ok(Some(line)) = lines.next_line().await {
+                        if line.trim().is_empty() {
+                            continue;
+                        }
+                        let mut response = completer.handle_line(&line).await;
+                        response.push('\n');
+                        if writer.write_all(response.as_bytes()).await.is_err() {
+                            break;
+                        }
+                    }
+                    debug!("Completion client disconnected");
+                });
+            }
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+
+    let _ = std::fs::remove_file(socket);
+    info!("Completion server stopped");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_from_input() {
+        let req = CompletionRequest::from_input(r#"{"prefix": "fn main() {", "suffix": "}", "id": 7}"#).unwrap();
+        assert_eq!(req.prefix, "fn main() {");
+        assert_eq!(req.suffix, "}");
+        assert_eq!(req.id, Some(serde_json::json!(7)));
+
+        let raw = CompletionRequest::from_input("def add(a, b):\n    ").unwrap();
+        assert_eq!(raw.prefix, "def add(a, b):\n    ");
+        assert!(raw.suffix.is_empty());
+
+        assert!(CompletionRequest::from_input("{ not json").is_err());
+    }
+
+    #[test]
+    fn test_trim_suffix_overlap() {
+        let completion = "    a + b\n}";
+        assert_eq!(trim_suffix_overlap(completion, "\n}\n\nfn main() {}"), "    a + b");
+        // No overlap: unchanged
+        assert_eq!(trim_suffix_overlap("    a + b", "}\n"), "    a + b");
+        assert_eq!(trim_suffix_overlap("x = 1", ""), "x = 1");
+    }
+
+    #[tokio::test]
+    async fn test_serve_round_trip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let socket = dir.path().join("complete.sock");
+        let completer = Completer::new(OllamaClient::new("http://127.0.0.1:9"), "test");
+        let server = tokio::spawn({
+            let socket = socket.clone();
+            async move { serve(completer, &socket).await }
+        });
+
+        let mut stream = None;
+        for _ in 0..50 {
+            if let Ok(s) = tokio::net::UnixStream::connect(&socket).await {
+                stream = Some(s);
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let (reader, mut writer) = stream.expect("server did not start").into_split();
+        writer.write_all(b"not json\n").await.unwrap();
+        let line = BufReader::new(reader).lines().next_line().await.unwrap().unwrap();
+        let value: Value = serde_json::from_str(&line).unwrap();
+        assert!(value["error"].as_str().unwrap().starts_with("Invalid request"));
+
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_handle_line_invalid_request() {
+        let completer = Completer::new(OllamaClient::new("http://127.0.0.1:9"), "test");
+        let line = completer.handle_line("{\"suffix\": \"x\"}").await;
+        let value: Value = serde_json::from_str(&line).unwrap();
+        assert!(value["error"].as_str().unwrap().contains("prefix"));
+    }
+}
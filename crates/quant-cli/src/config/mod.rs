@@ -0,0 +1,1375 @@
+//! User configuration for quant CLI
+//!
+//! Configuration file: ~/.config/quant/config.toml (or platform equivalent).
+//! Values are layered with llm.toml, project config, environment variables
+//! and `--set` flags; see [`resolver`] for the precedence rules.
+
+mod resolver;
+
+pub use llm_core::layered::{find_project_config, user_config_path, PROJECT_CONFIG};
+pub use resolver::{active_profile, set_active_profile, set_cli_overrides, set_in_file, ConfigResolver, Settings};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::agent::{RoleConfig, DEFAULT_MAX_REPEATED_RESPONSES};
+use crate::context::BudgetRatios;
+use crate::response_limit::DEFAULT_MAX_RESPONSE_BYTES;
+use crate::tools::output_limit::{OutputLimit, TruncationStrategy};
+
+/// User configuration for the quant CLI
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct UserConfig {
+    /// REPL configuration
+    #[serde(default)]
+    pub repl: ReplConfig,
+
+    /// Default options for ask command
+    #[serde(default)]
+    pub ask: AskConfig,
+
+    /// Default options for agent runs
+    #[serde(default)]
+    pub agent: AgentSettings,
+
+    /// Aliases for commands/models
+    #[serde(default)]
+    pub aliases: AliasConfig,
+
+    /// How the context window is split between prompt sections
+    #[serde(default)]
+    pub budget: BudgetRatios,
+
+    /// Resource limits for model output
+    #[serde(default)]
+    pub limits: LimitsConfig,
+
+    /// Custom or overridden agent team roles (`[roles.<name>]`)
+    #[serde(default)]
+    pub roles: std::collections::HashMap<String, RoleConfig>,
+
+    /// Named presets selected with `--profile` or `/profile` (`[profiles.<name>]`)
+    #[serde(default)]
+    pub profiles: std::collections::HashMap<String, ProfileConfig>,
+
+    /// Syncing with other machines (`quant sync`)
+    #[serde(default)]
+    pub sync: SyncConfig,
+
+    /// Agent hooks (`[[hooks]]`); the hooks of each file are kept, see [`crate::hooks`]
+    #[serde(default)]
+    pub hooks: Vec<crate::hooks::Hook>,
+
+    /// How sessions and conversations are stored (`[storage]`)
+    #[serde(default)]
+    pub storage: StorageConfig,
+
+    /// Trace export to a self-hosted collector (`[telemetry]`)
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+
+    /// Audit log of tool calls (`[audit]`)
+    #[serde(default)]
+    pub audit: AuditConfig,
+
+    /// Tool plugin libraries (`[plugins]`)
+    #[serde(default)]
+    pub plugins: PluginsConfig,
+
+    /// The agent's `sandbox` tool (`[sandbox]`)
+    #[serde(default)]
+    pub sandbox: SandboxSettings,
+
+    /// Search provider for the agent's `web_search` tool (`[web_search]`)
+    #[serde(default)]
+    pub web_search: WebSearchConfig,
+
+    /// Prompt-injection defenses for web and MCP tool results (`[untrusted]`)
+    #[serde(default)]
+    pub untrusted: UntrustedConfig,
+
+    /// Truncation of tool results (`[tool_output]`)
+    #[serde(default)]
+    pub tool_output: ToolOutputConfig,
+
+    /// Document Q&A with `quant rag` (`[rag]`)
+    #[serde(default)]
+    pub rag: RagConfig,
+
+    /// Context and search settings (`[context]`)
+    #[serde(default)]
+    pub context: ContextSettings,
+
+    /// The background `quant daemon` (`[daemon]`)
+    #[serde(default)]
+    pub daemon: DaemonConfig,
+}
+
+/// REPL-specific configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplConfig {
+    /// Default model for chat
+    #[serde(default)]
+    pub default_model: Option<String>,
+
+    /// Default system prompt
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+
+    /// Sampling temperature for chat (model default if unset)
+    #[serde(default)]
+    pub temperature: Option<f32>,
+
+    /// Auto-save conversations on exit
+    #[serde(default)]
+    pub auto_save: bool,
+
+    /// Show timestamps in conversation history
+    #[serde(default)]
+    pub show_timestamps: bool,
+
+    /// Maximum history entries to keep
+    #[serde(default = "default_history_size")]
+    pub history_size: usize,
+
+    /// Line editing keys (emacs or vi)
+    #[serde(default)]
+    pub edit_mode: EditMode,
+
+    /// Keep input history per project instead of in one file
+    #[serde(default = "default_project_history")]
+    pub project_history: bool,
+
+    /// Color theme (light/dark/auto)
+    #[serde(default = "default_theme")]
+    pub theme: String,
+
+    /// Have a model title each conversation after its first exchange
+    #[serde(default = "default_auto_title")]
+    pub auto_title: bool,
+
+    /// Model for conversation titles and summaries (chat model if unset)
+    #[serde(default)]
+    pub title_model: Option<String>,
+
+    /// Have a model write a summary and tags for each conversation when it is saved
+    #[serde(default = "default_auto_summary")]
+    pub auto_summary: bool,
+
+    /// Show context window usage in the prompt
+    #[serde(default = "default_context_meter")]
+    pub context_meter: bool,
+}
+
+/// Line editing keys of the REPL
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EditMode {
+    #[default]
+    Emacs,
+    Vi,
+}
+
+/// Ask command configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AskConfig {
+    /// Default model for one-shot queries
+    #[serde(default)]
+    pub default_model: Option<String>,
+
+    /// Default temperature
+    #[serde(default)]
+    pub temperature: Option<f32>,
+
+    /// Default max tokens
+    #[serde(default)]
+    pub max_tokens: Option<i32>,
+}
+
+/// Agent configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentSettings {
+    /// Compact an iteration's tool results once it made this many calls (0 disables)
+    #[serde(default = "default_compact_after_tool_calls")]
+    pub compact_after_tool_calls: usize,
+
+    /// Model to switch to when the primary model keeps failing
+    #[serde(default)]
+    pub fallback_model: Option<String>,
+
+    /// How many times a team reviewer may send work back
+    #[serde(default = "default_max_review_rounds")]
+    pub max_review_rounds: usize,
+
+    /// After a QUANT.md change, shadow this many runs with the previous instructions (0 disables)
+    #[serde(default)]
+    pub shadow_runs: usize,
+
+    /// Tools agent runs may use (all tools if unset)
+    #[serde(default)]
+    pub tools: Option<Vec<String>>,
+
+    /// Run tools without asking for confirmation
+    #[serde(default)]
+    pub auto_approve: bool,
+
+    /// Nudge the model after this many identical responses in a row (0 disables)
+    #[serde(default = "default_max_repeated_responses")]
+    pub max_repeated_responses: usize,
+
+    /// Show tool output line by line while the tool runs
+    #[serde(default = "default_live_output")]
+    pub live_output: bool,
+
+    /// Tool definitions sent per request, the rest behind `list_more_tools`
+    /// (0 sends all; unset prunes only on small-context models)
+    #[serde(default)]
+    pub max_tools: Option<usize>,
+
+    /// Have a model write a one-sentence summary and tags for each saved session
+    #[serde(default = "default_auto_summary")]
+    pub auto_summary: bool,
+
+    /// Model for session summaries (the run's model if unset)
+    #[serde(default)]
+    pub summary_model: Option<String>,
+
+    /// Hold file writes for a diff review at the end of interactive runs
+    #[serde(default)]
+    pub review_writes: bool,
+}
+
+impl AgentSettings {
+    /// Model to summarize a session run with `model`, if summaries are on
+    pub fn summary_model<'a>(&'a self, model: &'a str) -> Option<&'a str> {
+        self.auto_summary.then(|| self.summary_model.as_deref().unwrap_or(model))
+    }
+}
+
+/// Resource limits
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LimitsConfig {
+    /// Stop a streamed response once it exceeds this many KB (0 disables)
+    #[serde(default = "default_max_response_kb")]
+    pub max_response_kb: usize,
+
+    /// Cap on the context window used for agent runs, in tokens
+    #[serde(default)]
+    pub max_context_tokens: Option<usize>,
+}
+
+impl LimitsConfig {
+    /// The response limit in bytes (0 = unlimited)
+    pub fn max_response_bytes(&self) -> usize {
+        self.max_response_kb * 1024
+    }
+}
+
+/// Sync settings for `quant sync`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncConfig {
+    /// Machine synced with when `--peer` is not given
+    #[serde(default)]
+    pub peer: Option<String>,
+
+    /// Command used to reach the peer (e.g. "tailscale ssh")
+    #[serde(default = "default_sync_ssh")]
+    pub ssh: String,
+
+    /// quant on the peer, if not on its PATH
+    #[serde(default = "default_sync_remote_command")]
+    pub remote_command: String,
+
+    /// Only sync paths matching one of these globs (e.g. "conversations")
+    #[serde(default)]
+    pub include: Vec<String>,
+
+    /// Never sync paths matching these globs
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+/// Storage settings for saved sessions and conversations
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StorageConfig {
+    /// Encrypt saved sessions, conversations and the search index with a key
+    /// from the OS keychain, see [`crate::storage`]
+    #[serde(default)]
+    pub encrypt: bool,
+}
+
+/// Trace export settings, see [`crate::telemetry`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TelemetryConfig {
+    /// OTLP/HTTP collector to send spans to (nothing is sent if unset)
+    #[serde(default)]
+    pub otlp_endpoint: Option<String>,
+
+    /// Service name the spans are reported under
+    #[serde(default = "default_telemetry_service_name")]
+    pub service_name: String,
+
+    /// Extra HTTP headers for the collector, e.g. for authentication
+    #[serde(default)]
+    pub headers: std::collections::HashMap<String, String>,
+}
+
+/// Audit log settings, see [`crate::audit`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditConfig {
+    /// Record every tool call in the audit log
+    #[serde(default = "default_audit_enabled")]
+    pub enabled: bool,
+
+    /// Also send each record to the system log
+    #[serde(default)]
+    pub syslog: bool,
+
+    /// Hold back tool calls beyond this many per minute (0 = unlimited)
+    #[serde(default)]
+    pub max_calls_per_minute: usize,
+}
+
+/// Plugin settings, see [`crate::tools::plugins`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginsConfig {
+    /// Load tool plugins at startup
+    #[serde(default = "default_plugins_enabled")]
+    pub enabled: bool,
+
+    /// Directories to load plugins from, besides `<data dir>/quant/plugins`
+    #[serde(default)]
+    pub dirs: Vec<PathBuf>,
+}
+
+/// Settings for the `sandbox` tool
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SandboxSettings {
+    /// Backend: firejail, bubblewrap, docker, wasm or none (best installed if unset)
+    #[serde(default)]
+    pub backend: Option<crate::tools::builtin::SandboxBackend>,
+
+    /// WASI modules the wasm backend runs by name, e.g. an interpreter
+    #[serde(default)]
+    pub wasm_modules: std::collections::HashMap<String, PathBuf>,
+}
+
+/// Daemon settings, see [`crate::daemon`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonConfig {
+    /// Reload the [warmup] models this often, in seconds (0 = never)
+    #[serde(default = "default_daemon_warm_interval")]
+    pub warm_interval_secs: u64,
+
+    /// Stop a project's MCP servers after this many idle minutes (0 = never)
+    #[serde(default = "default_daemon_mcp_idle")]
+    pub mcp_idle_mins: u64,
+}
+
+/// Settings for the `web_search` tool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebSearchConfig {
+    /// Backend: duckduckgo, searxng, brave or kagi
+    #[serde(default)]
+    pub provider: crate::tools::builtin::SearchProviderKind,
+
+    /// SearxNG instance to query
+    #[serde(default)]
+    pub searxng_url: Option<String>,
+
+    /// API key for Brave or Kagi (BRAVE_API_KEY / KAGI_API_KEY if unset)
+    #[serde(default)]
+    pub api_key: Option<String>,
+
+    /// Reuse results for the same query for this many hours (0 disables the cache)
+    #[serde(default = "default_search_cache_hours")]
+    pub cache_hours: u64,
+
+    /// Minimum milliseconds between requests to the provider (provider default if unset)
+    #[serde(default)]
+    pub min_interval_ms: Option<u64>,
+}
+
+/// Handling of tool results with third-party content, see [`crate::tools::untrusted`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UntrustedConfig {
+    /// Scan results for prompt injections and delimit them as data
+    #[serde(default = "default_untrusted_enabled")]
+    pub enabled: bool,
+
+    /// Ask before sending a result that looks like an injection to the model
+    #[serde(default)]
+    pub confirm: bool,
+
+    /// More tools whose results are untrusted, besides web and MCP tools
+    #[serde(default)]
+    pub tools: Vec<String>,
+}
+
+/// Truncation of tool results, see [`crate::tools::output_limit`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolOutputConfig {
+    /// Part of an over-long result that is kept: head, tail or middle
+    #[serde(default)]
+    pub strategy: TruncationStrategy,
+
+    /// Characters returned to the model (0 for no limit)
+    #[serde(default = "default_tool_output_max_chars")]
+    pub max_chars: usize,
+
+    /// Tokens returned to the model, counted with its tokenizer (0 for no limit)
+    #[serde(default)]
+    pub max_tokens: usize,
+
+    /// Save results over this many characters to a file and return a preview (0 disables)
+    #[serde(default = "default_tool_output_store_over")]
+    pub store_over: usize,
+
+    /// Overrides for single tools, by tool name
+    #[serde(default)]
+    pub tools: std::collections::HashMap<String, ToolOutputOverride>,
+}
+
+/// `[tool_output]` settings of one tool; unset ones are inherited
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolOutputOverride {
+    #[serde(default)]
+    pub strategy: Option<TruncationStrategy>,
+    #[serde(default)]
+    pub max_chars: Option<usize>,
+    #[serde(default)]
+    pub max_tokens: Option<usize>,
+    #[serde(default)]
+    pub store_over: Option<usize>,
+}
+
+impl ToolOutputConfig {
+    /// The limits that apply to results of `tool`
+    pub fn limit_for(&self, tool: &str) -> OutputLimit {
+        let tool = self.tools.get(tool).cloned().unwrap_or_default();
+        OutputLimit {
+            strategy: tool.strategy.unwrap_or(self.strategy),
+            max_chars: tool.max_chars.unwrap_or(self.max_chars),
+            max_tokens: tool.max_tokens.unwrap_or(self.max_tokens),
+            store_over: tool.store_over.unwrap_or(self.store_over),
+        }
+    }
+}
+
+/// Settings for `quant rag`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RagConfig {
+    /// Ollama model used to embed chunks and questions
+    #[serde(default = "default_rag_embed_model")]
+    pub embed_model: String,
+
+    /// Chunks retrieved for each question
+    #[serde(default = "default_rag_top_k")]
+    pub top_k: usize,
+
+    /// Model that answers (ask.default_model or the llm.toml chat model if unset)
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+/// Settings for context selection and the search tools
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextSettings {
+    /// Leave out files matched by .gitignore and .quantignore, see
+    /// [`crate::ignore_files`]
+    #[serde(default = "default_ignore_files")]
+    pub ignore_files: bool,
+
+    /// Search directories with ripgrep (`rg`) in the grep tool when it is
+    /// installed
+    #[serde(default = "default_ripgrep")]
+    pub ripgrep: bool,
+
+    /// Tokenizer files (`tokenizer.json` or `.gguf`) by model name, for
+    /// counting tokens of models not found automatically, see
+    /// [`crate::context::model_tokenizer`]
+    #[serde(default)]
+    pub tokenizers: std::collections::HashMap<String, PathBuf>,
+}
+
+/// A named configuration preset
+///
+/// Each field expands to the config keys it stands for, so a profile is
+/// just another layer in the [`resolver`] precedence order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProfileConfig {
+    /// Model for chat, ask and agent runs
+    #[serde(default)]
+    pub model: Option<String>,
+
+    /// Sampling temperature for chat and ask
+    #[serde(default)]
+    pub temperature: Option<f32>,
+
+    /// System prompt for chat
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+
+    /// Tools agent runs may use
+    #[serde(default)]
+    pub tools: Option<Vec<String>>,
+
+    /// Run agent tools without confirmation
+    #[serde(default)]
+    pub auto_approve: Option<bool>,
+
+    /// Cap on the agent context window, in tokens
+    #[serde(default)]
+    pub max_context_tokens: Option<usize>,
+
+    /// Stop responses beyond this many KB
+    #[serde(default)]
+    pub max_response_kb: Option<usize>,
+}
+
+impl ProfileConfig {
+    /// The config keys this profile sets
+    pub fn overrides(&self) -> Vec<(&'static str, toml::Value)> {
+        use toml::Value;
+
+        let mut keys = Vec::new();
+        if let Some(ref model) = self.model {
+            for key in ["repl.default_model", "ask.default_model", "models.chat", "models.coding"] {
+                keys.push((key, Value::String(model.clone())));
+            }
+        }
+        if let Some(temperature) = self.temperature {
+            for key in ["repl.temperature", "ask.temperature"] {
+                keys.push((key, Value::Float(temperature.into())));
+            }
+        }
+        if let Some(ref prompt) = self.system_prompt {
+            keys.push(("repl.system_prompt", Value::String(prompt.clone())));
+        }
+        if let Some(ref tools) = self.tools {
+            let tools = tools.iter().cloned().map(Value::String).collect();
+            keys.push(("agent.tools", Value::Array(tools)));
+        }
+        if let Some(auto) = self.auto_approve {
+            keys.push(("agent.auto_approve", Value::Boolean(auto)));
+        }
+        if let Some(tokens) = self.max_context_tokens {
+            keys.push(("limits.max_context_tokens", Value::Integer(tokens as i64)));
+        }
+        if let Some(kb) = self.max_response_kb {
+            keys.push(("limits.max_response_kb", Value::Integer(kb as i64)));
+        }
+        keys
+    }
+}
+
+/// Model and command aliases
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct AliasConfig {
+    /// Model aliases (e.g., "code" -> "deepseek-coder:6.7b")
+    #[serde(default)]
+    pub models: std::collections::HashMap<String, ModelAlias>,
+}
+
+/// What a model alias expands to: a model name, or a table with the model
+/// and default options for it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ModelAlias {
+    Model(String),
+    WithOptions {
+        model: String,
+        #[serde(flatten)]
+        options: ModelOptions,
+    },
+}
+
+impl ModelAlias {
+    /// The model the alias stands for
+    pub fn model(&self) -> &str {
+        match self {
+            Self::Model(model) | Self::WithOptions { model, .. } => model,
+        }
+    }
+
+    /// Default options used with the model
+    pub fn options(&self) -> ModelOptions {
+        match self {
+            Self::Model(_) => ModelOptions::default(),
+            Self::WithOptions { options, .. } => options.clone(),
+        }
+    }
+}
+
+/// Default options of a model alias; command-line flags override them
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelOptions {
+    /// Sampling temperature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+
+    /// Context window to load the model with, in tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+
+    /// System prompt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_p: Option<f32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat_penalty: Option<f32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+
+    /// Fixed seed, for repeatable output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+
+    /// Most tokens to generate per response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_predict: Option<i32>,
+
+    /// Stop generating at any of these sequences
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+
+    /// How long Ollama keeps the model loaded afterwards (e.g. "30m")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<String>,
+}
+
+impl ModelOptions {
+    /// These options, with the unset ones taken from `defaults`
+    pub fn or(self, defaults: &ModelOptions) -> Self {
+        Self {
+            temperature: self.temperature.or(defaults.temperature),
+            num_ctx: self.num_ctx.or(defaults.num_ctx),
+            system_prompt: self.system_prompt.or_else(|| defaults.system_prompt.clone()),
+            top_p: self.top_p.or(defaults.top_p),
+            top_k: self.top_k.or(defaults.top_k),
+            min_p: self.min_p.or(defaults.min_p),
+            repeat_penalty: self.repeat_penalty.or(defaults.repeat_penalty),
+            presence_penalty: self.presence_penalty.or(defaults.presence_penalty),
+            frequency_penalty: self.frequency_penalty.or(defaults.frequency_penalty),
+            seed: self.seed.or(defaults.seed),
+            num_predict: self.num_predict.or(defaults.num_predict),
+            stop: if self.stop.is_empty() { defaults.stop.clone() } else { self.stop },
+            keep_alive: self.keep_alive.or_else(|| defaults.keep_alive.clone()),
+        }
+    }
+
+    /// Set the sampling options on `options`; temperature and num_ctx are
+    /// left to callers, which merge them with their own defaults
+    pub fn apply_sampling(&self, options: &mut llm_core::ChatOptions) {
+        options.top_p = self.top_p.or(options.top_p);
+        options.top_k = self.top_k.or(options.top_k);
+        options.min_p = self.min_p.or(options.min_p);
+        options.repeat_penalty = self.repeat_penalty.or(options.repeat_penalty);
+        options.presence_penalty = self.presence_penalty.or(options.presence_penalty);
+        options.frequency_penalty = self.frequency_penalty.or(options.frequency_penalty);
+        options.seed = self.seed.or(options.seed);
+        options.num_predict = self.num_predict.or(options.num_predict);
+        if !self.stop.is_empty() {
+            options.stop = Some(self.stop.clone());
+        }
+        options.keep_alive = self.keep_alive.clone().or(options.keep_alive.take());
+    }
+}
+
+fn default_history_size() -> usize {
+    1000
+}
+
+fn default_project_history() -> bool {
+    true
+}
+
+fn default_theme() -> String {
+    "auto".to_string()
+}
+
+fn default_auto_title() -> bool {
+    true
+}
+
+fn default_context_meter() -> bool {
+    true
+}
+
+fn default_compact_after_tool_calls() -> usize {
+    3
+}
+
+fn default_max_review_rounds() -> usize {
+    2
+}
+
+fn default_max_repeated_responses() -> usize {
+    DEFAULT_MAX_REPEATED_RESPONSES
+}
+
+fn default_auto_summary() -> bool {
+    true
+}
+
+fn default_live_output() -> bool {
+    true
+}
+
+fn default_max_response_kb() -> usize {
+    DEFAULT_MAX_RESPONSE_BYTES / 1024
+}
+
+fn default_sync_ssh() -> String {
+    "ssh".to_string()
+}
+
+fn default_sync_remote_command() -> String {
+    "quant".to_string()
+}
+
+fn default_telemetry_service_name() -> String {
+    "quant".to_string()
+}
+
+fn default_audit_enabled() -> bool {
+    true
+}
+
+fn default_plugins_enabled() -> bool {
+    true
+}
+
+fn default_daemon_warm_interval() -> u64 {
+    240
+}
+
+fn default_daemon_mcp_idle() -> u64 {
+    30
+}
+
+fn default_search_cache_hours() -> u64 {
+    24
+}
+
+fn default_tool_output_max_chars() -> usize {
+    50000
+}
+
+fn default_tool_output_store_over() -> usize {
+    200000
+}
+
+fn default_untrusted_enabled() -> bool {
+    true
+}
+
+fn default_rag_embed_model() -> String {
+    "nomic-embed-text".to_string()
+}
+
+fn default_rag_top_k() -> usize {
+    5
+}
+
+fn default_ignore_files() -> bool {
+    true
+}
+
+fn default_ripgrep() -> bool {
+    true
+}
+
+impl Default for ReplConfig {
+    fn default() -> Self {
+        Self {
+            default_model: None,
+            system_prompt: None,
+            temperature: None,
+            auto_save: false,
+            show_timestamps: false,
+            history_size: default_history_size(),
+            edit_mode: EditMode::default(),
+            project_history: default_project_history(),
+            theme: default_theme(),
+            auto_title: default_auto_title(),
+            title_model: None,
+            auto_summary: default_auto_summary(),
+            context_meter: default_context_meter(),
+        }
+    }
+}
+
+impl Default for AgentSettings {
+    fn default() -> Self {
+        Self {
+            compact_after_tool_calls: default_compact_after_tool_calls(),
+            fallback_model: None,
+            max_review_rounds: default_max_review_rounds(),
+            shadow_runs: 0,
+            tools: None,
+            auto_approve: false,
+            max_repeated_responses: default_max_repeated_responses(),
+            live_output: default_live_output(),
+            max_tools: None,
+            auto_summary: default_auto_summary(),
+            summary_model: None,
+            review_writes: false,
+        }
+    }
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_response_kb: default_max_response_kb(),
+            max_context_tokens: None,
+        }
+    }
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        Self {
+            peer: None,
+            ssh: default_sync_ssh(),
+            remote_command: default_sync_remote_command(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: None,
+            service_name: default_telemetry_service_name(),
+            headers: std::collections::HashMap::new(),
+        }
+    }
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_audit_enabled(),
+            syslog: false,
+            max_calls_per_minute: 0,
+        }
+    }
+}
+
+impl Default for PluginsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_plugins_enabled(),
+            dirs: Vec::new(),
+        }
+    }
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            warm_interval_secs: default_daemon_warm_interval(),
+            mcp_idle_mins: default_daemon_mcp_idle(),
+        }
+    }
+}
+
+impl Default for WebSearchConfig {
+    fn default() -> Self {
+        Self {
+            provider: Default::default(),
+            searxng_url: None,
+            api_key: None,
+            cache_hours: default_search_cache_hours(),
+            min_interval_ms: None,
+        }
+    }
+}
+
+impl Default for UntrustedConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_untrusted_enabled(),
+            confirm: false,
+            tools: Vec::new(),
+        }
+    }
+}
+
+impl Default for ToolOutputConfig {
+    fn default() -> Self {
+        Self {
+            strategy: TruncationStrategy::default(),
+            max_chars: default_tool_output_max_chars(),
+            max_tokens: 0,
+            store_over: default_tool_output_store_over(),
+            tools: std::collections::HashMap::new(),
+        }
+    }
+}
+
+impl Default for RagConfig {
+    fn default() -> Self {
+        Self {
+            embed_model: default_rag_embed_model(),
+            top_k: default_rag_top_k(),
+            model: None,
+        }
+    }
+}
+
+impl Default for ContextSettings {
+    fn default() -> Self {
+        Self {
+            ignore_files: default_ignore_files(),
+            ripgrep: default_ripgrep(),
+            tokenizers: std::collections::HashMap::new(),
+        }
+    }
+}
+
+/// Load llm.toml with overrides from the other config layers
+pub fn load_llm_config() -> Result<llm_core::Config> {
+    ConfigResolver::discover()?.llm_config()
+}
+
+/// Like [`load_llm_config`], but `None` when there is no usable llm.toml
+pub fn try_load_llm_config() -> Option<llm_core::Config> {
+    load_llm_config().ok()
+}
+
+/// llm.toml settings with overrides, falling back to minimal defaults
+pub fn load_llm_config_or_default() -> llm_core::Config {
+    ConfigResolver::discover()
+        .and_then(|resolver| resolver.llm_config_or_default())
+        .unwrap_or_else(|_| llm_core::Config::default_minimal())
+}
+
+/// Every setting with all layers applied, and where each value came from
+pub fn load_settings() -> Result<llm_core::EffectiveConfig<Settings>> {
+    ConfigResolver::discover()?.effective()
+}
+
+impl UserConfig {
+    /// Load user configuration with all layers applied
+    pub fn load() -> Result<Self> {
+        ConfigResolver::discover()?.user_config()
+    }
+
+    /// Save configuration to default location
+    #[allow(dead_code)]
+    pub fn save(&self) -> Result<PathBuf> {
+        let path = Self::config_path()?;
+
+        // Create directory if needed
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let content = toml::to_string_pretty(self)?;
+        fs::write(&path, content)?;
+
+        Ok(path)
+    }
+
+    /// Get the configuration file path
+    pub fn config_path() -> Result<PathBuf> {
+        let config_dir = dirs::config_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not determine config directory"))?;
+
+        Ok(config_dir.join("quant").join("config.toml"))
+    }
+
+    /// Create a default configuration file with comments
+    pub fn create_default() -> Result<PathBuf> {
+        let path = Self::config_path()?;
+
+        if path.exists() {
+            anyhow::bail!("Config file already exists: {}", path.display());
+        }
+
+        // Create directory if needed
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let default_config = r#"# quant CLI configuration
+# Location: ~/.config/quant/config.toml
+
+[repl]
+# Default model for interactive chat (uses llm.toml chat model if not set)
+# default_model = "deepseek-coder:6.7b"
+
+# Default system prompt for all conversations
+# system_prompt = "You are a helpful coding assistant."
+
+# Sampling temperature for chat (model default if unset)
+# temperature = 0.7
+
+# Auto-save conversations on exit
+auto_save = false
+
+# Show timestamps in conversation history
+show_timestamps = false
+
+# Maximum history entries to keep
+history_size = 1000
+
+# Line editing keys: "emacs" or "vi"
+edit_mode = "emacs"
+
+# Keep input history per project, so histories don't mix across repos;
+# slash commands are shared by every project and found with Ctrl-R
+project_history = true
+
+# Color theme: "light", "dark", or "auto"
+theme = "auto"
+
+# Title conversations with a model after the first exchange
+auto_title = true
+
+# Model for conversation titles and summaries; a small one keeps this fast
+# (uses the chat model if not set)
+# title_model = "llama3.2:3b"
+
+# When a conversation is saved, have the title model write a one-sentence
+# summary and a few tags for it, shown by /load
+auto_summary = true
+
+# Show how much of the model's context window the conversation uses in the
+# prompt, e.g. [3.1k/8k]; /compact is suggested once it passes 80%
+context_meter = true
+
+[ask]
+# Default model for one-shot queries (uses llm.toml coding model if not set)
+# default_model = "deepseek-coder:6.7b"
+
+# Default temperature (0.0-2.0)
+# temperature = 0.7
+
+# Default max tokens
+# max_tokens = 4096
+
+[agent]
+# Summarize tool results of iterations with at least this many tool calls
+# once the model has seen them (0 disables; full output stays in the session)
+compact_after_tool_calls = 3
+
+# Model to switch to if the primary model keeps failing (timeouts, OOM, 5xx)
+# fallback_model = "llama3.2:3b"
+
+# In team mode, how many times a reviewer may send work back to the previous role
+max_review_rounds = 2
+
+# When the model gives the same response this many iterations in a row, ask it
+# to wrap up; stop the run if it repeats once more (0 disables)
+max_repeated_responses = 3
+
+# Print the output of long-running tools (builds, tests) as it arrives
+live_output = true
+
+# After editing QUANT.md, also answer the next N agent tasks with the previous
+# instructions (no tools run) and log a comparison report (0 disables)
+# shadow_runs = 3
+
+# Send at most this many tool definitions per request; the model can list and
+# unlock the rest with list_more_tools (0 sends all; unset prunes to 8 on
+# models with a context window of 16k tokens or less)
+# max_tools = 8
+
+# After each run, have a model write a one-sentence summary and a few tags for
+# the saved session (false keeps the start of the final response instead)
+auto_summary = true
+
+# Model for session summaries; a small one is plenty (default: the run's model)
+# summary_model = "llama3.2:3b"
+
+# Hold file writes from interactive agent runs until you accept each file's
+# diff at the end of the run (or with /review in chat), like --review
+# review_writes = true
+
+[limits]
+# Stop generation once a single response exceeds this many KB; the stored
+# response is truncated and marked (0 disables)
+max_response_kb = 1024
+
+# Cap the context window for agent runs, in tokens (model limit if unset)
+# max_context_tokens = 16384
+
+[budget]
+# Share of the model's context window for each section (normalized).
+# A response reserve is kept aside based on the model's limits.
+# system = 0.10
+# project = 0.10
+# smart_context = 0.30
+# history = 0.50
+
+# Agent team roles for `quant agent --team`. Built-in roles (planner, coder,
+# tester, reviewer) can be overridden and new roles added.
+# [roles.coder]
+# model = "qwen2.5-coder:14b"
+# system_prompt = "You are the coder. Implement the plan from the scratchpad."
+# tools = ["file_read", "file_write", "multi_edit", "bash"]
+
+# Named presets, selected with `quant --profile <name>` or `/profile <name>`.
+# Fields: model, temperature, system_prompt, tools, auto_approve,
+# max_context_tokens, max_response_kb
+# [profiles.fast]
+# model = "llama3.2:3b"
+# temperature = 0.3
+# max_context_tokens = 8192
+#
+# [profiles.careful]
+# model = "qwen2.5-coder:32b"
+# temperature = 0.1
+# tools = ["file_read", "grep", "glob"]
+
+[sync]
+# Machine `quant sync` syncs sessions, conversations and trusted commands
+# with (any host ssh can reach, e.g. a tailscale name)
+# peer = "workstation"
+# Command used to reach it; quant must be installed there too
+# ssh = "ssh"
+# remote_command = "quant"
+# Globs over "<category>/<file>", or a bare category name
+# include = ["conversations", "sessions"]
+# exclude = ["approvals"]
+
+[storage]
+# Encrypt saved sessions, conversations and the search index with a key kept
+# in the OS keychain (or QUANT_STORAGE_KEY). Encrypted files are always read;
+# `quant storage encrypt` converts the ones already saved.
+encrypt = false
+
+[web_search]
+# Backend for the agent's web_search tool: "duckduckgo" (no key needed),
+# "searxng" (your own instance, with the json format enabled), or the "brave"
+# and "kagi" APIs (keys from BRAVE_API_KEY / KAGI_API_KEY, or api_key here).
+# Can be set per project in .quant/config.toml.
+provider = "duckduckgo"
+# searxng_url = "http://localhost:8888"
+# Reuse results for the same query for this many hours (0 disables)
+cache_hours = 24
+# Minimum milliseconds between requests (default depends on the provider)
+# min_interval_ms = 2000
+
+[untrusted]
+# Results of web_fetch, web_search, rust_docs and MCP tools come from third
+# parties. Scan them for prompt injections (instructions aimed at the model,
+# invisible Unicode, base64-encoded text) and delimit them as data, with a
+# warning when something was found
+enabled = true
+# Ask before sending a result that looks like an injection to the model
+# (withheld when nobody can be asked; --auto sends it)
+confirm = false
+# More tools whose results are untrusted
+# tools = ["bash"]
+
+[tool_output]
+# What the model gets of a long tool result: the "head", the "tail" or both
+# ends ("middle"), cut to max_chars characters and, if set, max_tokens tokens
+# counted with the model's tokenizer (0 for no limit)
+strategy = "head"
+max_chars = 50000
+max_tokens = 0
+# Results over this many characters are saved to a temp file; the model gets a
+# preview and the path, and reads the rest with file_read or grep (0 disables)
+store_over = 200000
+# Per tool overrides
+# [tool_output.tools.bash]
+# strategy = "tail"
+# max_tokens = 4000
+
+[rag]
+# Ollama model that embeds documents for `quant rag index` and `quant rag query`
+embed_model = "nomic-embed-text"
+# Chunks retrieved for each question
+top_k = 5
+# Model that answers (ask.default_model or the llm.toml chat model if unset)
+# model = "llama3.2"
+
+[context]
+# Leave files matched by .gitignore and .quantignore out of context and out of
+# the glob and grep tools. .quantignore uses gitignore syntax and can re-include
+# (`!pattern`) files git ignores.
+ignore_files = true
+# The grep tool searches with ripgrep (rg) when it is installed, and in-process
+# otherwise; set to false to always search in-process
+ripgrep = true
+
+# Tokens of local models are counted with the model's own tokenizer, read from
+# its GGUF in [models.local] or the Ollama model store, or from tokenizer.json
+# in the Hugging Face cache. Point models found neither way at a file here:
+# [context.tokenizers]
+# "my-model:7b" = "/path/to/my-model/tokenizer.json"
+
+[telemetry]
+# Send tracing spans (agent runs and iterations, LLM requests, tool calls) to
+# your own OpenTelemetry collector over OTLP/HTTP, e.g. Jaeger or Grafana
+# Tempo. Nothing leaves the machine unless an endpoint is set.
+# otlp_endpoint = "http://localhost:4318"
+# service_name = "quant"
+# headers = { authorization = "Bearer ..." }
+
+[audit]
+# Record every tool call (time, user, tool, a hash of its arguments, result
+# and duration) in an append-only log; see `quant audit tail` and `search`
+enabled = true
+# Also send each record to the system log
+syslog = false
+# Hold back tool calls beyond this many per minute (0 = unlimited)
+max_calls_per_minute = 0
+
+[plugins]
+# Load agent tools from plugin libraries in <data dir>/quant/plugins and the
+# directories below; `quant plugins list` shows what was found
+enabled = true
+# dirs = ["/opt/quant-plugins"]
+
+[sandbox]
+# Backend of the agent's sandbox tool: firejail, bubblewrap, docker, wasm or
+# none; the first of firejail, bubblewrap and docker installed if unset.
+# wasm runs WASI modules with no network and only the working directory
+# visible (needs quant built with `--features wasm`).
+# backend = "wasm"
+# Modules the wasm backend runs by name, e.g. `python script.py`
+# wasm_modules = { python = "/opt/wasm/python.wasm" }
+
+[daemon]
+# `quant daemon start` keeps MCP servers, the RAG store and the [warmup]
+# models loaded between commands. Reload the warm models this often
+# (seconds, 0 = never)
+warm_interval_secs = 240
+# Stop a project's MCP servers after this many idle minutes (0 = never)
+mcp_idle_mins = 30
+
+# Agent hooks for every project, with the same fields as in QUANT.md.
+# A project hook with the same name replaces one defined here.
+# [[hooks]]
+# name = "notify-done"
+# event = "agent_finish"
+# command = "notify-send quant \"$QUANT_TASK finished\""
+
+[aliases.models]
+# Model aliases for quick access, usable wherever a model name is
+# (e.g. quant ask -m code). A table also sets default options for the model.
+# code = "deepseek-coder:6.7b"
+# chat = "glm4:9b"
+# fast = { model = "llama3.2:3b", temperature = 0.2, num_ctx = 8192, system_prompt = "Answer briefly." }
+# Tables take Ollama's other sampling options too: top_p, top_k, min_p,
+# repeat_penalty, presence_penalty, frequency_penalty, seed, num_predict,
+# stop (a list) and keep_alive (e.g. "30m")
+"#;
+
+        fs::write(&path, default_config)?;
+
+        Ok(path)
+    }
+
+    /// Resolve a model name (check aliases first)
+    #[allow(dead_code)]
+    pub fn resolve_model(&self, name: &str) -> String {
+        self.aliases
+            .models
+            .get(name)
+            .map_or_else(|| name.to_string(), |alias| alias.model().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_config() {
+        let config = UserConfig::default();
+        assert!(!config.repl.auto_save);
+        assert_eq!(config.repl.history_size, 1000);
+        assert_eq!(config.agent.compact_after_tool_calls, 3);
+        assert_eq!(config.limits.max_response_bytes(), 1024 * 1024);
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let toml = r#"
+[repl]
+default_model = "test-model"
+auto_save = true
+
+[ask]
+temperature = 0.8
+
+[aliases.models]
+code = "deepseek-coder:6.7b"
+fast = { model = "llama3.2:3b", temperature = 0.2, num_ctx = 8192 }
+
+[agent]
+fallback_model = "llama3.2:3b"
+
+[budget]
+smart_context = 0.4
+
+[limits]
+max_response_kb = 0
+
+[tool_output]
+max_tokens = 8000
+
+[tool_output.tools.bash]
+strategy = "tail"
+max_chars = 20000
+
+[roles.reviewer]
+model = "qwen2.5-coder:14b"
+tools = ["file_read", "grep"]
+"#;
+
+        let config: UserConfig = toml::from_str(toml).unwrap();
+        assert_eq!(config.repl.default_model, Some("test-model".to_string()));
+        assert!(config.repl.auto_save);
+        assert_eq!(config.ask.temperature, Some(0.8));
+        assert_eq!(
+            config.resolve_model("code"),
+            "deepseek-coder:6.7b".to_string()
+        );
+        let fast = &config.aliases.models["fast"];
+        assert_eq!(fast.model(), "llama3.2:3b");
+        assert_eq!(fast.options().temperature, Some(0.2));
+        assert_eq!(fast.options().num_ctx, Some(8192));
+        assert!(fast.options().system_prompt.is_none());
+        assert_eq!(config.aliases.models["code"].options(), ModelOptions::default());
+        assert_eq!(config.agent.fallback_model.as_deref(), Some("llama3.2:3b"));
+        assert_eq!(config.budget.smart_context, 0.4);
+        assert_eq!(config.budget.history, 0.5);
+        assert_eq!(
+            config.tool_output.limit_for("bash"),
+            OutputLimit { strategy: TruncationStrategy::Tail, max_chars: 20000, max_tokens: 8000, store_over: 200000 }
+        );
+        assert_eq!(config.tool_output.limit_for("grep").max_chars, 50000);
+        assert_eq!(config.limits.max_response_bytes(), 0);
+        let reviewer = &config.roles["reviewer"];
+        assert_eq!(reviewer.model.as_deref(), Some("qwen2.5-coder:14b"));
+        assert_eq!(reviewer.tools.as_ref().map(|t| t.len()), Some(2));
+        assert!(reviewer.system_prompt.is_none());
+    }
+}
@@ -0,0 +1,436 @@
+//! Layered configuration resolution
+//!
+//! Settings are merged from several sources. Later sources override earlier
+//! ones, key by key:
+//!
+//! 1. Built-in defaults
+//! 2. `llm.toml` (current directory or a parent)
+//! 3. User config (`~/.config/quant/config.toml`)
+//! 4. Project config (`.quant/config.toml` in the current directory or a parent)
+//! 5. The active profile (`--profile <name>` or `QUANT_PROFILE`), whose
+//!    `[profiles.<name>]` fields expand to the keys they stand for
+//! 6. Environment variables named `QUANT_<SECTION>__<KEY>`, e.g.
+//!    `QUANT_ASK__TEMPERATURE=0.2` sets `ask.temperature`
+//! 7. `--set <key>=<value>` on the command line
+//!
+//! All sources share one key space, so the user or project config can also
+//! override llm.toml settings such as `ollama.host`. Explicit command flags
+//! like `--model` still win over every layer. The merge itself lives in
+//! [`llm_core::layered`], which ollama-bar uses for the same files.
+
+use anyhow::{Context, Result};
+use llm_core::layered::{deserialize, insert_key, lookup, merge, parse_value, write_key, LayeredConfig, LLM_SECTIONS};
+use llm_core::{Config, ConfigError, ConfigPaths, ConfigSource, EffectiveConfig};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::{OnceLock, RwLock};
+use toml::{Table, Value};
+
+use super::{ProfileConfig, UserConfig};
+
+/// Top-level sections of the quant config file
+const USER_SECTIONS: &[&str] = &["repl", "ask", "agent", "aliases", "budget", "limits", "roles", "profiles", "sync", "hooks", "storage", "telemetry", "audit", "plugins", "sandbox", "web_search", "untrusted", "tool_output", "rag", "context", "daemon"];
+
+/// `--set` overrides from the command line, recorded once at startup
+static CLI_OVERRIDES: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Profile applied on top of the config files; the REPL can switch it at runtime
+static ACTIVE_PROFILE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Record `--set key=value` overrides for every later resolution
+pub fn set_cli_overrides(overrides: Vec<String>) {
+    let _ = CLI_OVERRIDES.set(overrides);
+}
+
+/// Select the profile for every later resolution (`None` for no profile)
+pub fn set_active_profile(profile: Option<String>) {
+    if let Ok(mut active) = ACTIVE_PROFILE.write() {
+        *active = profile.filter(|p| !p.is_empty());
+    }
+}
+
+pub fn active_profile() -> Option<String> {
+    ACTIVE_PROFILE.read().ok().and_then(|active| active.clone())
+}
+
+/// Every setting quant reads, from llm.toml and the quant config alike
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Settings {
+    #[serde(flatten)]
+    pub llm: Config,
+    #[serde(flatten)]
+    pub user: UserConfig,
+}
+
+impl Settings {
+    /// Model for chat: `repl.default_model`, else `models.chat`
+    pub fn chat_model(&self) -> Option<&str> {
+        self.user
+            .repl
+            .default_model
+            .as_deref()
+            .or(Some(self.llm.models.chat.as_str()))
+            .filter(|m| !m.is_empty())
+    }
+
+    /// Model for one-shot coding queries: `ask.default_model`, else `models.coding`
+    pub fn coding_model(&self) -> Option<&str> {
+        self.user
+            .ask
+            .default_model
+            .as_deref()
+            .or(Some(self.llm.models.coding.as_str()))
+            .filter(|m| !m.is_empty())
+    }
+}
+
+/// Merged view over every configuration source
+#[derive(Debug)]
+pub struct ConfigResolver {
+    layered: LayeredConfig,
+}
+
+impl ConfigResolver {
+    /// Resolve from the standard files, the active profile, the environment, and `--set` flags
+    pub fn discover() -> Result<Self> {
+        let cli = CLI_OVERRIDES.get().map(Vec::as_slice).unwrap_or_default();
+        let profile = active_profile();
+        Self::from_sources(&ConfigPaths::discover(), profile.as_deref(), std::env::vars(), cli)
+    }
+
+    /// Resolve from explicit sources, validating every layer
+    pub fn from_sources(
+        paths: &ConfigPaths,
+        profile: Option<&str>,
+        env: impl IntoIterator<Item = (String, String)>,
+        cli: &[String],
+    ) -> Result<Self> {
+        let mut layered = LayeredConfig::with_defaults(default_table()?);
+        layered.push_files(paths)?;
+
+        if let Some(name) = profile {
+            let values = profile_layer(&layered, name)?;
+            layered.push(ConfigSource::Profile(name.to_string()), values);
+        }
+
+        layered.push_env(env)?;
+
+        for arg in cli {
+            let source = ConfigSource::Cli(arg.clone());
+            let Some((key, raw)) = arg.split_once('=') else {
+                return Err(ConfigError {
+                    source,
+                    key: arg.clone(),
+                    message: "expected KEY=VALUE".to_string(),
+                }
+                .into());
+            };
+            layered.push_key(source, key.trim(), parse_value(raw.trim()))?;
+        }
+
+        let known: Vec<&str> = USER_SECTIONS.iter().chain(LLM_SECTIONS).copied().collect();
+        layered.validate(Some(&known), check_schemas)?;
+        Ok(Self { layered })
+    }
+
+    /// All layers merged
+    pub fn merged(&self) -> Table {
+        self.layered.merged()
+    }
+
+    /// Resolved value of a dotted key and the highest-precedence source setting it
+    pub fn get(&self, key: &str) -> Option<(Value, &ConfigSource)> {
+        self.layered.get(key)
+    }
+
+    /// Names of the profiles defined across all config files
+    pub fn profile_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = lookup(&self.merged(), "profiles")
+            .and_then(Value::as_table)
+            .map(|profiles| profiles.keys().cloned().collect())
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    /// Sources that contributed, highest precedence first
+    pub fn sources(&self) -> impl Iterator<Item = &ConfigSource> {
+        self.layered.sources()
+    }
+
+    /// Every setting, with the source of each value
+    pub fn effective(&self) -> Result<EffectiveConfig<Settings>> {
+        self.layered.effective()
+    }
+
+    pub fn user_config(&self) -> Result<UserConfig> {
+        deserialize(&self.merged()).map_err(|e| anyhow::anyhow!("Invalid configuration: {}", e))
+    }
+
+    /// llm.toml settings with overrides applied; fails when there is no llm.toml
+    pub fn llm_config(&self) -> Result<Config> {
+        if !self.has_llm_toml() {
+            anyhow::bail!("llm.toml not found in current directory or parents");
+        }
+        self.llm_config_or_default()
+    }
+
+    /// llm.toml settings with overrides applied, on top of minimal defaults
+    pub fn llm_config_or_default(&self) -> Result<Config> {
+        deserialize(&self.merged()).map_err(|e| anyhow::anyhow!("Invalid configuration: {}", e))
+    }
+
+    pub fn has_llm_toml(&self) -> bool {
+        self.layered.has_source(|s| matches!(s, ConfigSource::LlmToml(_)))
+    }
+}
+
+/// Expand a profile defined in the layers read so far into its config keys
+fn profile_layer(layered: &LayeredConfig, name: &str) -> Result<Table, ConfigError> {
+    let source = ConfigSource::Profile(name.to_string());
+    let key = format!("profiles.{}", name);
+    let error = |message: String| ConfigError {
+        source: source.clone(),
+        key: key.clone(),
+        message,
+    };
+    let profile: ProfileConfig = layered
+        .merged()
+        .get("profiles")
+        .and_then(Value::as_table)
+        .and_then(|profiles| profiles.get(name))
+        .ok_or_else(|| error("no such profile in any config file".to_string()))?
+        .clone()
+        .try_into()
+        .map_err(|e: toml::de::Error| error(e.message().to_string()))?;
+
+    let mut values = Table::new();
+    for (key, value) in profile.overrides() {
+        insert_key(&mut values, key, value).map_err(error)?;
+    }
+    Ok(values)
+}
+
+/// Set a key in a config file, keeping its comments and layout
+///
+/// The value is parsed as TOML when possible (`true`, `0.7`, `[1, 2]`) and
+/// treated as a string otherwise. Returns the stored value.
+pub fn set_in_file(path: &Path, key: &str, raw: &str) -> Result<Value> {
+    let content = if path.exists() {
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?
+    } else {
+        String::new()
+    };
+    let value = parse_value(raw);
+
+    // Validate the file as it would be after the change
+    let source = ConfigSource::User(path.to_path_buf());
+    let mut values: Table = toml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?;
+    let error = |message: String| ConfigError {
+        source: source.clone(),
+        key: key.to_string(),
+        message,
+    };
+    let section = key.split('.').next().unwrap_or_default();
+    if !key.contains('.') || !(USER_SECTIONS.contains(&section) || LLM_SECTIONS.contains(&section)) {
+        return Err(error("unknown key; keys look like `section.name`, e.g. `ask.temperature`".to_string()).into());
+    }
+    insert_key(&mut values, key, value.clone()).map_err(error)?;
+    let mut merged = default_table()?;
+    merge(&mut merged, &values);
+    check_schemas(&merged).map_err(error)?;
+
+    write_key(path, key, &value)?;
+    Ok(value)
+}
+
+fn default_table() -> Result<Table> {
+    let mut table = Table::try_from(UserConfig::default()).context("Failed to encode default config")?;
+    let llm = Table::try_from(Config::default_minimal()).context("Failed to encode default llm.toml")?;
+    merge(&mut table, &llm);
+    Ok(table)
+}
+
+fn check_schemas(table: &Table) -> Result<(), String> {
+    deserialize::<UserConfig>(table)?;
+    deserialize::<Config>(table)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    fn write(dir: &TempDir, name: &str, content: &str) -> PathBuf {
+        let path = dir.path().join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_precedence() {
+        let dir = TempDir::new().unwrap();
+        let paths = ConfigPaths {
+            llm: None,
+            user: Some(write(&dir, "user.toml", "[ask]\ntemperature = 0.5\nmax_tokens = 100\n[repl]\nauto_save = true\n")),
+            project: Some(write(&dir, "project.toml", "[ask]\ntemperature = 0.6\n")),
+        };
+        let resolver = ConfigResolver::from_sources(
+            &paths,
+            None,
+            env(&[("QUANT_ASK__MAX_TOKENS", "200"), ("QUANT_LOG_FORMAT", "json")]),
+            &["ask.max_tokens=300".to_string()],
+        )
+        .unwrap();
+
+        let config = resolver.user_config().unwrap();
+        assert_eq!(config.ask.temperature, Some(0.6));
+        assert_eq!(config.ask.max_tokens, Some(300));
+        assert!(config.repl.auto_save);
+        assert_eq!(config.repl.history_size, 1000);
+
+        let (value, source) = resolver.get("ask.temperature").unwrap();
+        assert_eq!(value, Value::Float(0.6));
+        assert_eq!(source, &ConfigSource::Project(dir.path().join("project.toml")));
+        let (_, source) = resolver.get("repl.history_size").unwrap();
+        assert_eq!(source, &ConfigSource::Default);
+        assert!(resolver.get("repl.default_model").is_none());
+
+        // Without the --set flag the environment wins
+        let resolver = ConfigResolver::from_sources(&paths, None, env(&[("QUANT_ASK__MAX_TOKENS", "200")]), &[]).unwrap();
+        let (value, source) = resolver.get("ask.max_tokens").unwrap();
+        assert_eq!(value, Value::Integer(200));
+        assert_eq!(source, &ConfigSource::Env("QUANT_ASK__MAX_TOKENS".to_string()));
+    }
+
+    #[test]
+    fn test_user_config_overrides_llm_toml() {
+        let dir = TempDir::new().unwrap();
+        let llm = Table::try_from(Config::default_minimal()).unwrap();
+        let paths = ConfigPaths {
+            llm: Some(write(&dir, "llm.toml", &toml::to_string(&llm).unwrap())),
+            user: Some(write(&dir, "user.toml", "[ollama]\nport = 11500\n")),
+            project: None,
+        };
+        let resolver = ConfigResolver::from_sources(&paths, None, Vec::new(), &[]).unwrap();
+        assert_eq!(resolver.llm_config().unwrap().ollama_url(), "http://127.0.0.1:11500");
+
+        let without = ConfigResolver::from_sources(&ConfigPaths::default(), None, Vec::new(), &[]).unwrap();
+        assert!(without.llm_config().is_err());
+        assert_eq!(without.llm_config_or_default().unwrap().ollama.port, 11434);
+    }
+
+    #[test]
+    fn test_profile_layer() {
+        let dir = TempDir::new().unwrap();
+        let paths = ConfigPaths {
+            user: Some(write(
+                &dir,
+                "user.toml",
+                "[repl]\ndefault_model = \"llama3.2\"\n\n[profiles.careful]\nmodel = \"qwen2.5-coder:32b\"\ntemperature = 0.1\ntools = [\"file_read\"]\n",
+            )),
+            ..Default::default()
+        };
+
+        let resolver = ConfigResolver::from_sources(&paths, Some("careful"), Vec::new(), &[]).unwrap();
+        let config = resolver.user_config().unwrap();
+        assert_eq!(config.repl.default_model.as_deref(), Some("qwen2.5-coder:32b"));
+        assert_eq!(config.repl.temperature, Some(0.1));
+        assert_eq!(config.agent.tools, Some(vec!["file_read".to_string()]));
+        assert_eq!(resolver.llm_config_or_default().unwrap().models.coding, "qwen2.5-coder:32b");
+        assert_eq!(resolver.profile_names(), vec!["careful".to_string()]);
+
+        // Environment still beats the profile
+        let resolver =
+            ConfigResolver::from_sources(&paths, Some("careful"), env(&[("QUANT_REPL__TEMPERATURE", "0.9")]), &[]).unwrap();
+        let (value, _) = resolver.get("repl.temperature").unwrap();
+        assert_eq!(value, Value::Float(0.9));
+
+        let err = ConfigResolver::from_sources(&paths, Some("fast"), Vec::new(), &[])
+            .unwrap_err()
+            .downcast::<ConfigError>()
+            .unwrap();
+        assert_eq!(err.source, ConfigSource::Profile("fast".to_string()));
+    }
+
+    #[test]
+    fn test_validation_points_at_file_and_key() {
+        let dir = TempDir::new().unwrap();
+        let user = write(&dir, "user.toml", "[repl]\nauto_save = true\n\n[ask]\ntemperature = \"hot\"\n");
+        let paths = ConfigPaths {
+            user: Some(user.clone()),
+            ..Default::default()
+        };
+        let err = ConfigResolver::from_sources(&paths, None, Vec::new(), &[]).unwrap_err();
+        let err = err.downcast::<ConfigError>().unwrap();
+        assert_eq!(err.source, ConfigSource::User(user));
+        assert_eq!(err.key, "ask.temperature");
+
+        let err = ConfigResolver::from_sources(&ConfigPaths::default(), None, env(&[("QUANT_LIMITS__MAX_RESPONSE_KB", "lots")]), &[])
+            .unwrap_err()
+            .downcast::<ConfigError>()
+            .unwrap();
+        assert_eq!(err.source, ConfigSource::Env("QUANT_LIMITS__MAX_RESPONSE_KB".to_string()));
+        assert_eq!(err.key, "limits.max_response_kb");
+
+        let err = ConfigResolver::from_sources(&ConfigPaths::default(), None, Vec::new(), &["askk.temperature=1".to_string()])
+            .unwrap_err()
+            .downcast::<ConfigError>()
+            .unwrap();
+        assert_eq!(err.key, "askk");
+        assert_eq!(err.message, "unknown section");
+    }
+
+    #[test]
+    fn test_set_in_file_keeps_comments() {
+        let dir = TempDir::new().unwrap();
+        let path = write(&dir, "config.toml", "# my settings\n[ask]\n# sampling\ntemperature = 0.7\n");
+
+        set_in_file(&path, "ask.temperature", "0.2").unwrap();
+        set_in_file(&path, "repl.default_model", "llama3.2:3b").unwrap();
+        set_in_file(&path, "roles.coder.tools", "[\"bash\"]").unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.contains("# my settings"));
+        assert!(content.contains("# sampling\ntemperature = 0.2"));
+        let config: UserConfig = toml::from_str(&content).unwrap();
+        assert_eq!(config.repl.default_model.as_deref(), Some("llama3.2:3b"));
+        assert_eq!(config.roles["coder"].tools.as_deref(), Some(&["bash".to_string()][..]));
+
+        let err = set_in_file(&path, "ask.temperature", "warm").unwrap_err();
+        assert_eq!(err.downcast::<ConfigError>().unwrap().key, "ask.temperature");
+        assert!(set_in_file(&path, "temperature", "1").is_err());
+        assert!(fs::read_to_string(&path).unwrap().contains("temperature = 0.2"));
+    }
+
+    #[test]
+    fn test_effective_settings() {
+        let dir = TempDir::new().unwrap();
+        let user = write(&dir, "user.toml", "[models]\nchat = \"glm4:9b\"\n\n[ask]\ndefault_model = \"qwen2.5-coder:7b\"\n");
+        let paths = ConfigPaths {
+            user: Some(user.clone()),
+            ..Default::default()
+        };
+        let resolver =
+            ConfigResolver::from_sources(&paths, None, Vec::new(), &["repl.history_size=50".to_string()]).unwrap();
+        let settings = resolver.effective().unwrap();
+
+        assert_eq!(settings.chat_model(), Some("glm4:9b"));
+        assert_eq!(settings.coding_model(), Some("qwen2.5-coder:7b"));
+        assert_eq!(settings.user.repl.history_size, 50);
+        assert_eq!(settings.source("models.chat"), Some(&ConfigSource::User(user.clone())));
+        assert_eq!(settings.source("ask.default_model"), Some(&ConfigSource::User(user)));
+        assert_eq!(
+            settings.source("repl.history_size"),
+            Some(&ConfigSource::Cli("repl.history_size=50".to_string()))
+        );
+        assert_eq!(settings.source("ollama.port"), Some(&ConfigSource::Default));
+    }
+}
@@ -0,0 +1,191 @@
+//! Deduplicated assembly of context files from several sources
+//!
+//! Explicit context, smart context and the project's QUANT.md files can
+//! name the same file, under different paths or as copies. A
+//! `ContextAssembly` records every file put into a prompt by canonical path
+//! and content hash, so each source can skip what another already included,
+//! and reports the final set and its token cost once.
+
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use super::tokenizer::count_tokens_for_model;
+use crate::project::ProjectContext;
+
+/// Where a context file came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextSource {
+    /// QUANT.md instructions embedded in the project section
+    Project,
+    /// Added with `quant context add` or `/context add`
+    Explicit,
+    /// Auto-selected for the task
+    Smart,
+}
+
+impl std::fmt::Display for ContextSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Project => write!(f, "project"),
+            Self::Explicit => write!(f, "explicit"),
+            Self::Smart => write!(f, "smart"),
+        }
+    }
+}
+
+/// A file included in the prompt
+#[derive(Debug, Clone)]
+pub struct AssembledFile {
+    pub path: PathBuf,
+    pub source: ContextSource,
+    pub tokens: usize,
+}
+
+/// The set of files included in a prompt, across all sources
+#[derive(Debug, Clone, Default)]
+pub struct ContextAssembly {
+    files: Vec<AssembledFile>,
+    paths: HashSet<PathBuf>,
+    hashes: HashSet<String>,
+    /// Files skipped because they were already included
+    duplicates: Vec<PathBuf>,
+}
+
+impl ContextAssembly {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether `path`, or a file with the same content, is already included
+    pub fn contains(&self, path: &Path, content: &str) -> bool {
+        self.paths.contains(&canonical(path)) || self.hashes.contains(&content_hash(content))
+    }
+
+    /// Whether `path` itself is already included
+    pub fn contains_path(&self, path: &Path) -> bool {
+        self.paths.contains(&canonical(path))
+    }
+
+    /// Check a file before including it; a duplicate is recorded as skipped
+    pub fn is_new(&mut self, path: &Path, content: &str) -> bool {
+        if self.contains(path, content) {
+            self.duplicates.push(path.to_path_buf());
+            return false;
+        }
+        true
+    }
+
+    /// Record a file as included, with the tokens it takes up in the prompt
+    pub fn add(&mut self, path: &Path, content: &str, source: ContextSource, tokens: usize) {
+        let path = canonical(path);
+        self.paths.insert(path.clone());
+        self.hashes.insert(content_hash(content));
+        self.files.push(AssembledFile { path, source, tokens });
+    }
+
+    /// Record the QUANT.md files the project section embeds
+    pub fn add_project(&mut self, project: &ProjectContext, model: &str) {
+        for file in &project.quant_files {
+            if !self.contains(&file.path, &file.content) {
+                let tokens = count_tokens_for_model(&file.content, model);
+                self.add(&file.path, &file.content, ContextSource::Project, tokens);
+            }
+        }
+    }
+
+    pub fn files(&self) -> &[AssembledFile] {
+        &self.files
+    }
+
+    pub fn duplicates(&self) -> &[PathBuf] {
+        &self.duplicates
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Total tokens of the included files
+    pub fn token_count(&self) -> usize {
+        self.files.iter().map(|f| f.tokens).sum()
+    }
+
+    /// One line describing the final set, e.g.
+    /// `3 files, ~2400 tokens (1 project, 2 smart), 1 duplicate skipped`
+    pub fn summary(&self) -> String {
+        let by_source: Vec<String> = [ContextSource::Project, ContextSource::Explicit, ContextSource::Smart]
+            .into_iter()
+            .filter_map(|source| {
+                let count = self.files.iter().filter(|f| f.source == source).count();
+                (count > 0).then(|| format!("{} {}", count, source))
+            })
+            .collect();
+
+        let mut summary = format!(
+            "{} file{}, ~{} tokens",
+            self.files.len(),
+            if self.files.len() == 1 { "" } else { "s" },
+            self.token_count()
+        );
+        if !by_source.is_empty() {
+            summary.push_str(&format!(" ({})", by_source.join(", ")));
+        }
+        if !self.duplicates.is_empty() {
+            let count = self.duplicates.len();
+            summary.push_str(&format!(", {} duplicate{} skipped", count, if count == 1 { "" } else { "s" }));
+        }
+        summary
+    }
+}
+
+/// The path with symlinks and `..` resolved, or as given if it can't be
+fn canonical(path: &Path) -> PathBuf {
+    crate::paths::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_dedupes_by_path_and_content() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let main = dir.path().join("main.rs");
+        let copy = dir.path().join("copy.rs");
+        fs::write(&main, "fn main() {}").unwrap();
+        fs::write(&copy, "fn main() {}").unwrap();
+
+        let mut assembly = ContextAssembly::new();
+        assert!(assembly.is_new(&main, "fn main() {}"));
+        assembly.add(&main, "fn main() {}", ContextSource::Explicit, 5);
+
+        // Same file by another path, and a copy with the same content
+        assert!(!assembly.is_new(&dir.path().join(".").join("main.rs"), "fn main() {}"));
+        assert!(!assembly.is_new(&copy, "fn main() {}"));
+        assert!(assembly.is_new(&copy, "fn other() {}"));
+
+        assert_eq!(assembly.files().len(), 1);
+        assert_eq!(assembly.duplicates().len(), 2);
+        assert!(assembly.contains_path(&main));
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut assembly = ContextAssembly::new();
+        assert_eq!(assembly.summary(), "0 files, ~0 tokens");
+
+        assembly.add(Path::new("/nonexistent/QUANT.md"), "rules", ContextSource::Project, 10);
+        assembly.add(Path::new("/nonexistent/a.rs"), "a", ContextSource::Smart, 20);
+        assembly.add(Path::new("/nonexistent/b.rs"), "b", ContextSource::Smart, 30);
+        assert!(!assembly.is_new(Path::new("/nonexistent/a.rs"), "a"));
+
+        assert_eq!(assembly.token_count(), 60);
+        assert_eq!(assembly.summary(), "3 files, ~60 tokens (1 project, 2 smart), 1 duplicate skipped");
+    }
+}
@@ -0,0 +1,215 @@
+//! Context budget allocation
+//!
+//! Splits a model's context window between the system prompt, project
+//! context, smart context, and conversation history, keeping a reserve for
+//! the response. Shares are proportional to configurable ratios and any
+//! space left unused by fixed sections is handed to smart context.
+
+use serde::{Deserialize, Serialize};
+
+use super::ModelLimits;
+
+/// Relative weights for each section of the context window
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BudgetRatios {
+    /// Base system prompt (instructions, tool list)
+    #[serde(default = "default_system_ratio")]
+    pub system: f32,
+    /// Project context (QUANT.md, project info)
+    #[serde(default = "default_project_ratio")]
+    pub project: f32,
+    /// Auto-selected relevant files
+    #[serde(default = "default_smart_context_ratio")]
+    pub smart_context: f32,
+    /// Conversation and tool history
+    #[serde(default = "default_history_ratio")]
+    pub history: f32,
+}
+
+fn default_system_ratio() -> f32 {
+    0.10
+}
+
+fn default_project_ratio() -> f32 {
+    0.10
+}
+
+fn default_smart_context_ratio() -> f32 {
+    0.30
+}
+
+fn default_history_ratio() -> f32 {
+    0.50
+}
+
+impl Default for BudgetRatios {
+    fn default() -> Self {
+        Self {
+            system: default_system_ratio(),
+            project: default_project_ratio(),
+            smart_context: default_smart_context_ratio(),
+            history: default_history_ratio(),
+        }
+    }
+}
+
+impl BudgetRatios {
+    /// Sum of all ratios (negative values count as zero)
+    fn total(&self) -> f32 {
+        self.system.max(0.0) + self.project.max(0.0) + self.smart_context.max(0.0) + self.history.max(0.0)
+    }
+}
+
+/// Token budget for each section of the context window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContextBudget {
+    /// Tokens for the base system prompt
+    pub system_prompt: usize,
+    /// Tokens for project context
+    pub project_context: usize,
+    /// Tokens for smart context files
+    pub smart_context: usize,
+    /// Tokens for conversation history; agents compact old tool results to
+    /// stay within it
+    pub history: usize,
+    /// Tokens kept free for the model's response
+    pub response_reserve: usize,
+}
+
+impl ContextBudget {
+    /// Split a model's context window according to the given ratios
+    pub fn allocate(limits: &ModelLimits, ratios: &BudgetRatios) -> Self {
+        let response_reserve = limits.response_reserve.min(limits.context_window);
+        let available = limits.context_window - response_reserve;
+
+        let total = ratios.total();
+        if total <= 0.0 {
+            // Degenerate config: give everything to history
+            return Self {
+                system_prompt: 0,
+                project_context: 0,
+                smart_context: 0,
+                history: available,
+                response_reserve,
+            };
+        }
+
+        let share = |ratio: f32| (available as f64 * (ratio.max(0.0) / total) as f64).floor() as usize;
+
+        Self {
+            system_prompt: share(ratios.system),
+            project_context: share(ratios.project),
+            smart_context: share(ratios.smart_context),
+            history: share(ratios.history),
+            response_reserve,
+        }
+    }
+
+    /// Hand tokens the system prompt and project context don't need to smart context
+    ///
+    /// Sections that overrun their budget take the excess from smart context.
+    pub fn rebalance(&mut self, system_used: usize, project_used: usize) {
+        let fixed_budget = self.system_prompt + self.project_context;
+        let fixed_used = system_used + project_used;
+
+        if fixed_used <= fixed_budget {
+            self.smart_context += fixed_budget - fixed_used;
+        } else {
+            self.smart_context = self.smart_context.saturating_sub(fixed_used - fixed_budget);
+        }
+        self.system_prompt = system_used;
+        self.project_context = project_used;
+    }
+
+    /// One-line description for logging and display
+    pub fn summary(&self) -> String {
+        format!(
+            "system {} | project {} | smart context {} | history {} | response {}",
+            self.system_prompt,
+            self.project_context,
+            self.smart_context,
+            self.history,
+            self.response_reserve
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(window: usize, response: usize) -> ModelLimits {
+        ModelLimits {
+            context_window: window,
+            system_reserve: 0,
+            response_reserve: response,
+        }
+    }
+
+    #[test]
+    fn test_allocate_proportional() {
+        let budget = ContextBudget::allocate(&limits(10_000, 2_000), &BudgetRatios::default());
+        assert_eq!(budget.response_reserve, 2_000);
+        assert_eq!(budget.system_prompt, 800);
+        assert_eq!(budget.project_context, 800);
+        assert_eq!(budget.smart_context, 2_400);
+        assert_eq!(budget.history, 4_000);
+        assert!(budget.system_prompt + budget.project_context + budget.smart_context + budget.history <= 8_000);
+    }
+
+    #[test]
+    fn test_allocate_scales_with_model() {
+        let ratios = BudgetRatios::default();
+        let small = ContextBudget::allocate(&ModelLimits::from_name("llama2"), &ratios);
+        let large = ContextBudget::allocate(&ModelLimits::from_name("qwen2.5-coder"), &ratios);
+        assert!(large.smart_context > small.smart_context);
+    }
+
+    #[test]
+    fn test_allocate_normalizes_ratios() {
+        let ratios = BudgetRatios {
+            system: 1.0,
+            project: 1.0,
+            smart_context: 1.0,
+            history: 1.0,
+        };
+        let budget = ContextBudget::allocate(&limits(4_000, 0), &ratios);
+        assert_eq!(budget.smart_context, 1_000);
+        assert_eq!(budget.history, 1_000);
+    }
+
+    #[test]
+    fn test_allocate_zero_ratios() {
+        let ratios = BudgetRatios {
+            system: 0.0,
+            project: 0.0,
+            smart_context: 0.0,
+            history: 0.0,
+        };
+        let budget = ContextBudget::allocate(&limits(4_000, 1_000), &ratios);
+        assert_eq!(budget.history, 3_000);
+        assert_eq!(budget.smart_context, 0);
+    }
+
+    #[test]
+    fn test_rebalance_gives_surplus_to_smart_context() {
+        let mut budget = ContextBudget::allocate(&limits(10_000, 2_000), &BudgetRatios::default());
+        budget.rebalance(300, 100);
+        assert_eq!(budget.smart_context, 2_400 + 1_200);
+        assert_eq!(budget.system_prompt, 300);
+    }
+
+    #[test]
+    fn test_rebalance_overrun_shrinks_smart_context() {
+        let mut budget = ContextBudget::allocate(&limits(10_000, 2_000), &BudgetRatios::default());
+        budget.rebalance(2_000, 600);
+        assert_eq!(budget.smart_context, 2_400 - 1_000);
+    }
+
+    #[test]
+    fn test_ratios_from_toml() {
+        let ratios: BudgetRatios = toml::from_str("smart_context = 0.5").unwrap();
+        assert_eq!(ratios.smart_context, 0.5);
+        assert_eq!(ratios.history, 0.50);
+    }
+}
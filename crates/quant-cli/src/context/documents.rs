@@ -0,0 +1,313 @@
+//! Text extraction for documents added as context
+//!
+//! PDFs, Word documents and HTML pages can't be put into a prompt as they
+//! are, so their text is extracted first: PDFs page by page with
+//! `--- Page N ---` markers, docx paragraph by paragraph, and HTML without
+//! markup, scripts or styles. Extracted text is cached by file content, so
+//! a large PDF is only parsed once however often it is sent.
+
+use anyhow::{bail, Context, Result};
+use quick_xml::events::Event;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Bump to invalidate cached extractions when the extractors change
+const EXTRACTOR_VERSION: u32 = 1;
+
+/// Most decompressed content read from one PDF page
+const MAX_PAGE_BYTES: usize = 64 * 1024 * 1024;
+
+/// Document formats with a text extractor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentKind {
+    Pdf,
+    Docx,
+    Html,
+}
+
+impl DocumentKind {
+    /// The kind of document at `path`, by extension (None for plain text)
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "pdf" => Some(Self::Pdf),
+            "docx" => Some(Self::Docx),
+            "html" | "htm" | "xhtml" => Some(Self::Html),
+            _ => None,
+        }
+    }
+
+    /// Extract the text of a document of this kind
+    pub fn extract(&self, bytes: &[u8]) -> Result<String> {
+        match self {
+            Self::Pdf => extract_pdf(bytes),
+            Self::Docx => extract_docx(bytes),
+            Self::Html => Ok(crate::tools::builtin::html_to_text(&String::from_utf8_lossy(bytes))),
+        }
+    }
+}
+
+/// Read a context file as text, extracting it if it is a document
+pub fn read_text(path: &Path) -> Result<String> {
+    match DocumentKind::from_path(path) {
+        Some(kind) => DocumentCache::new().extract(path, kind),
+        None => fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+/// On-disk cache of extracted document text, keyed by file content
+pub struct DocumentCache {
+    /// Where extractions are stored (none if there is no cache directory)
+    dir: Option<PathBuf>,
+}
+
+impl DocumentCache {
+    pub fn new() -> Self {
+        Self {
+            dir: dirs::cache_dir().map(|dir| dir.join("quant").join("documents")),
+        }
+    }
+
+    /// A cache in `dir`
+    #[cfg(test)]
+    pub fn with_dir(dir: PathBuf) -> Self {
+        Self { dir: Some(dir) }
+    }
+
+    /// The text of the document at `path`, from the cache if it hasn't changed
+    pub fn extract(&self, path: &Path, kind: DocumentKind) -> Result<String> {
+        let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let cache_path = self.dir.as_ref().map(|dir| dir.join(format!("{}.txt", cache_key(&bytes))));
+
+        if let Some(text) = cache_path.as_ref().and_then(|p| fs::read_to_string(p).ok()) {
+            debug!(path = %path.display(), "Using cached document text");
+            return Ok(text);
+        }
+
+        let text = kind
+            .extract(&bytes)
+            .with_context(|| format!("Failed to extract text from {}", path.display()))?;
+
+        if let Some(cache_path) = cache_path {
+            let written = cache_path
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::write(&cache_path, &text));
+            if let Err(e) = written {
+                debug!(error = %e, "Failed to cache document text");
+            }
+        }
+        Ok(text)
+    }
+}
+
+impl Default for DocumentCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Cache file name for a document's content
+fn cache_key(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(EXTRACTOR_VERSION.to_le_bytes());
+    hasher.update(bytes);
+    format!("{:x}", hasher.finalize())
+}
+
+/// Text of each page of a PDF, under a marker with its page number
+fn extract_pdf(bytes: &[u8]) -> Result<String> {
+    let document = lopdf::Document::load_mem(bytes).context("Not a readable PDF")?;
+    if document.is_encrypted() {
+        bail!("PDF is password protected");
+    }
+
+    let mut text = String::new();
+    let mut found_text = false;
+    for page in document.get_pages().into_keys() {
+        let page_text = document.extract_text_with_limit(&[page], MAX_PAGE_BYTES).unwrap_or_else(|e| {
+            debug!(page, error = %e, "Failed to extract PDF page");
+            String::new()
+        });
+        let page_text = page_text.trim();
+        found_text |= !page_text.is_empty();
+
+        text.push_str(&format!("--- Page {} ---\n", page));
+        text.push_str(page_text);
+        text.push_str("\n\n");
+    }
+
+    if !found_text {
+        bail!("PDF has no text layer (scanned pages need OCR first)");
+    }
+    Ok(text.trim_end().to_string())
+}
+
+/// Text of a Word document, one line per paragraph
+fn extract_docx(bytes: &[u8]) -> Result<String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).context("Not a readable docx file")?;
+    let mut xml = String::new();
+    archive
+        .by_name("word/document.xml")
+        .context("docx file has no word/document.xml")?
+        .read_to_string(&mut xml)?;
+
+    let mut reader = quick_xml::Reader::from_str(&xml);
+    let mut text = String::new();
+    let mut in_text = false;
+    loop {
+        match reader.read_event().context("Invalid docx XML")? {
+            Event::Start(e) if e.local_name().as_ref() == "t" => in_text = true,
+            Event::End(e) => match e.local_name().as_ref() {
+                "t" => in_text = false,
+                "p" => text.push('\n'),
+                _ => {}
+            },
+            Event::Empty(e) => match e.local_name().as_ref() {
+                "tab" => text.push('\t'),
+                "br" | "cr" => text.push('\n'),
+                _ => {}
+            },
+            Event::Text(e) if in_text => text.push_str(&e.xml10_content()),
+            Event::GeneralRef(e) if in_text => {
+                if let Some(c) = e.resolve_char_ref()? {
+                    text.push(c);
+                } else if let Some(entity) = quick_xml::escape::resolve_predefined_entity(&e.xml10_content()) {
+                    text.push_str(entity);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(text.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn sample_pdf(pages: &[&str]) -> Vec<u8> {
+        use lopdf::content::{Content, Operation};
+        use lopdf::{dictionary, Document, Object, Stream};
+
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Courier",
+        });
+        let resources_id = doc.add_object(dictionary! { "Font" => dictionary! { "F1" => font_id } });
+
+        let mut kids = Vec::new();
+        for text in pages {
+            let content = Content {
+                operations: vec![
+                    Operation::new("BT", vec![]),
+                    Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                    Operation::new("Td", vec![100.into(), 600.into()]),
+                    Operation::new("Tj", vec![Object::string_literal(*text)]),
+                    Operation::new("ET", vec![]),
+                ],
+            };
+            let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+            let page_id = doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "Contents" => content_id,
+            });
+            kids.push(page_id.into());
+        }
+
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Count" => kids.len() as i64,
+                "Kids" => kids,
+                "Resources" => resources_id,
+                "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog_id);
+
+        let mut bytes = Vec::new();
+        doc.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn sample_docx(body: &str) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut writer = zip::ZipWriter::new(Cursor::new(&mut bytes));
+        writer.start_file("word/document.xml", zip::write::SimpleFileOptions::default()).unwrap();
+        write!(
+            writer,
+            r#"<?xml version="1.0"?><w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body>{}</w:body></w:document>"#,
+            body
+        )
+        .unwrap();
+        writer.finish().unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_document_kind() {
+        assert_eq!(DocumentKind::from_path(Path::new("report.PDF")), Some(DocumentKind::Pdf));
+        assert_eq!(DocumentKind::from_path(Path::new("notes.docx")), Some(DocumentKind::Docx));
+        assert_eq!(DocumentKind::from_path(Path::new("page.htm")), Some(DocumentKind::Html));
+        assert_eq!(DocumentKind::from_path(Path::new("main.rs")), None);
+        assert_eq!(DocumentKind::from_path(Path::new("Makefile")), None);
+    }
+
+    #[test]
+    fn test_extract_pdf_pages() {
+        let text = extract_pdf(&sample_pdf(&["First page", "Second page"])).unwrap();
+        assert!(text.starts_with("--- Page 1 ---\nFirst page"));
+        assert!(text.contains("--- Page 2 ---\nSecond page"));
+        assert!(extract_pdf(b"not a pdf").is_err());
+    }
+
+    #[test]
+    fn test_extract_docx() {
+        let docx = sample_docx(
+            "<w:p><w:r><w:t>Quarterly</w:t></w:r><w:r><w:t xml:space=\"preserve\"> report &amp; plan</w:t></w:r></w:p>\
+             <w:p><w:r><w:t>Revenue</w:t><w:tab/><w:t>up</w:t></w:r></w:p>",
+        );
+        assert_eq!(extract_docx(&docx).unwrap(), "Quarterly report & plan\nRevenue\tup");
+    }
+
+    #[test]
+    fn test_extract_html() {
+        let html = b"<html><head><style>p { color: red }</style></head><body><h1>Title</h1><p>Body text</p></body></html>";
+        let text = DocumentKind::Html.extract(html).unwrap();
+        assert!(text.contains("Title"));
+        assert!(text.contains("Body text"));
+        assert!(!text.contains("color"));
+    }
+
+    #[test]
+    fn test_cached_extraction() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.docx");
+        fs::write(&path, sample_docx("<w:p><w:r><w:t>Cached</w:t></w:r></w:p>")).unwrap();
+
+        let cache = DocumentCache::with_dir(dir.path().join("cache"));
+        assert_eq!(cache.extract(&path, DocumentKind::Docx).unwrap(), "Cached");
+        assert_eq!(fs::read_dir(dir.path().join("cache")).unwrap().count(), 1);
+
+        // Served from the cache, keyed by content
+        let key = cache_key(&fs::read(&path).unwrap());
+        fs::write(dir.path().join("cache").join(format!("{}.txt", key)), "From cache").unwrap();
+        assert_eq!(cache.extract(&path, DocumentKind::Docx).unwrap(), "From cache");
+
+        fs::write(&path, sample_docx("<w:p><w:r><w:t>Changed</w:t></w:r></w:p>")).unwrap();
+        assert_eq!(cache.extract(&path, DocumentKind::Docx).unwrap(), "Changed");
+    }
+}
@@ -0,0 +1,221 @@
+//! Tokenizer metadata from GGUF model files
+//!
+//! GGUF files (llama.cpp and Ollama model weights) start with a table of
+//! metadata key/values, including the model's vocabulary under
+//! `tokenizer.ggml.*`. Only that header is read; tensor data is never
+//! touched.
+
+use anyhow::{bail, Context, Result};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"GGUF";
+
+/// Longest string or array accepted, to fail fast on corrupt files
+const MAX_LEN: u64 = 1 << 26;
+
+/// The vocabulary of a GGUF model
+#[derive(Debug, Default)]
+pub struct GgufVocab {
+    /// Tokenizer kind: `gpt2` (byte-level BPE) or `llama` (SentencePiece)
+    pub model: String,
+    pub tokens: Vec<String>,
+    /// SentencePiece piece scores (`llama`)
+    pub scores: Vec<f32>,
+    /// BPE merges as `"left right"` (`gpt2`)
+    pub merges: Vec<String>,
+}
+
+/// Read the tokenizer vocabulary from a GGUF file's metadata
+pub fn read_vocab(path: &Path) -> Result<GgufVocab> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut reader = BufReader::new(file);
+
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        bail!("{} is not a GGUF file", path.display());
+    }
+    let version = read_u32(&mut reader)?;
+    if version < 2 {
+        bail!("Unsupported GGUF version {}", version);
+    }
+    let _tensor_count = read_u64(&mut reader)?;
+    let kv_count = read_u64(&mut reader)?;
+
+    let mut vocab = GgufVocab::default();
+    for _ in 0..kv_count {
+        let key = read_string(&mut reader)?;
+        let value_type = read_u32(&mut reader)?;
+        match key.as_str() {
+            "tokenizer.ggml.model" if value_type == TYPE_STRING => vocab.model = read_string(&mut reader)?,
+            "tokenizer.ggml.tokens" if value_type == TYPE_ARRAY => vocab.tokens = read_string_array(&mut reader)?,
+            "tokenizer.ggml.merges" if value_type == TYPE_ARRAY => vocab.merges = read_string_array(&mut reader)?,
+            "tokenizer.ggml.scores" if value_type == TYPE_ARRAY => vocab.scores = read_f32_array(&mut reader)?,
+            _ => skip_value(&mut reader, value_type)?,
+        }
+    }
+
+    if vocab.model.is_empty() || vocab.tokens.is_empty() {
+        bail!("{} has no tokenizer metadata", path.display());
+    }
+    Ok(vocab)
+}
+
+const TYPE_F32: u32 = 6;
+const TYPE_STRING: u32 = 8;
+const TYPE_ARRAY: u32 = 9;
+
+/// Size in bytes of a fixed-size value type
+fn fixed_size(value_type: u32) -> Option<u64> {
+    match value_type {
+        0 | 1 | 7 => Some(1),
+        2 | 3 => Some(2),
+        4..=6 => Some(4),
+        10..=12 => Some(8),
+        _ => None,
+    }
+}
+
+fn skip_value(reader: &mut impl Read, value_type: u32) -> Result<()> {
+    match value_type {
+        TYPE_STRING => {
+            let len = read_len(reader)?;
+            skip(reader, len)
+        }
+        TYPE_ARRAY => {
+            let item_type = read_u32(reader)?;
+            let len = read_len(reader)?;
+            match fixed_size(item_type) {
+                Some(size) => skip(reader, len * size),
+                None => (0..len).try_for_each(|_| skip_value(reader, item_type)),
+            }
+        }
+        other => match fixed_size(other) {
+            Some(size) => skip(reader, size),
+            None => bail!("Unknown GGUF value type {}", other),
+        },
+    }
+}
+
+fn skip(reader: &mut impl Read, len: u64) -> Result<()> {
+    let skipped = std::io::copy(&mut reader.take(len), &mut std::io::sink())?;
+    if skipped < len {
+        bail!("Unexpected end of GGUF file");
+    }
+    Ok(())
+}
+
+fn read_string_array(reader: &mut impl Read) -> Result<Vec<String>> {
+    let item_type = read_u32(reader)?;
+    let len = read_len(reader)?;
+    if item_type != TYPE_STRING {
+        bail!("Expected a GGUF string array");
+    }
+    (0..len).map(|_| read_string(reader)).collect()
+}
+
+fn read_f32_array(reader: &mut impl Read) -> Result<Vec<f32>> {
+    let item_type = read_u32(reader)?;
+    let len = read_len(reader)?;
+    if item_type != TYPE_F32 {
+        bail!("Expected a GGUF f32 array");
+    }
+    (0..len)
+        .map(|_| {
+            let mut buf = [0u8; 4];
+            reader.read_exact(&mut buf)?;
+            Ok(f32::from_le_bytes(buf))
+        })
+        .collect()
+}
+
+fn read_string(reader: &mut impl Read) -> Result<String> {
+    let len = read_len(reader)?;
+    let mut buf = vec![0u8; len as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+fn read_len(reader: &mut impl Read) -> Result<u64> {
+    let len = read_u64(reader)?;
+    if len > MAX_LEN {
+        bail!("GGUF length {} out of range", len);
+    }
+    Ok(len)
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+/// A GGUF header with the given metadata, for tests
+#[cfg(test)]
+pub(crate) fn write_test_file(path: &Path, model: &str, tokens: &[&str], scores: &[f32], merges: &[&str]) {
+    fn string(out: &mut Vec<u8>, s: &str) {
+        out.extend((s.len() as u64).to_le_bytes());
+        out.extend(s.as_bytes());
+    }
+    fn strings(out: &mut Vec<u8>, key: &str, items: &[&str]) {
+        string(out, key);
+        out.extend(TYPE_ARRAY.to_le_bytes());
+        out.extend(TYPE_STRING.to_le_bytes());
+        out.extend((items.len() as u64).to_le_bytes());
+        items.iter().for_each(|s| string(out, s));
+    }
+
+    let mut out = Vec::new();
+    out.extend(MAGIC);
+    out.extend(3u32.to_le_bytes());
+    out.extend(0u64.to_le_bytes());
+    out.extend(5u64.to_le_bytes());
+
+    // A key quant doesn't use, to be skipped
+    string(&mut out, "general.context_length");
+    out.extend(4u32.to_le_bytes());
+    out.extend(4096u32.to_le_bytes());
+
+    string(&mut out, "tokenizer.ggml.model");
+    out.extend(TYPE_STRING.to_le_bytes());
+    string(&mut out, model);
+    strings(&mut out, "tokenizer.ggml.tokens", tokens);
+    strings(&mut out, "tokenizer.ggml.merges", merges);
+
+    string(&mut out, "tokenizer.ggml.scores");
+    out.extend(TYPE_ARRAY.to_le_bytes());
+    out.extend(TYPE_F32.to_le_bytes());
+    out.extend((scores.len() as u64).to_le_bytes());
+    scores.iter().for_each(|s| out.extend(s.to_le_bytes()));
+
+    std::fs::write(path, out).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_vocab() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("model.gguf");
+        write_test_file(&path, "gpt2", &["a", "b", "ab"], &[0.0, 0.0, -1.0], &["a b"]);
+
+        let vocab = read_vocab(&path).unwrap();
+        assert_eq!(vocab.model, "gpt2");
+        assert_eq!(vocab.tokens, ["a", "b", "ab"]);
+        assert_eq!(vocab.merges, ["a b"]);
+        assert_eq!(vocab.scores, [0.0, 0.0, -1.0]);
+
+        std::fs::write(&path, b"not a model").unwrap();
+        assert!(read_vocab(&path).is_err());
+    }
+}
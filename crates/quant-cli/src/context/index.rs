@@ -76,7 +76,7 @@ impl FileMetadata {
 }
 
 /// Compute SHA256 hash of content
-fn compute_hash(content: &str) -> String {
+pub(super) fn compute_hash(content: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(content.as_bytes());
     format!("{:x}", hasher.finalize())
@@ -8,7 +8,10 @@ use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use super::assembly::{ContextAssembly, ContextSource};
+use super::documents::{self, DocumentCache, DocumentKind};
 use super::tokenizer::{count_tokens, Tokenizer};
+use crate::ignore_files::IgnoreRules;
 
 /// Default include patterns for code files
 pub const DEFAULT_INCLUDE: &[&str] = &[
@@ -126,8 +129,14 @@ impl ContextManager {
     }
 
     /// Add a file or directory to the context
+    ///
+    /// Documents (PDF, docx, HTML) are extracted now, so one that can't be
+    /// read is reported here rather than silently left out of prompts.
     pub fn add(&mut self, path: &str) -> Result<()> {
         let path = self.normalize_path(path)?;
+        if let Some(kind) = DocumentKind::from_path(Path::new(&path)) {
+            DocumentCache::new().extract(Path::new(&path), kind)?;
+        }
         self.files.insert(path);
         Ok(())
     }
@@ -160,6 +169,12 @@ impl ContextManager {
 
     /// Build context string from current files
     pub fn build_context(&self) -> Result<String> {
+        self.build_context_with(&mut ContextAssembly::new())
+    }
+
+    /// Build context string from current files, skipping those `assembly`
+    /// already includes and recording the ones added
+    pub fn build_context_with(&self, assembly: &mut ContextAssembly) -> Result<String> {
         let mut context = String::new();
         let max_tokens = self.config.max_tokens;
 
@@ -198,7 +213,10 @@ impl ContextManager {
                 break;
             }
 
-            if let Ok(content) = fs::read_to_string(&file) {
+            if let Ok(content) = documents::read_text(&file) {
+                if !assembly.is_new(&file, &content) {
+                    continue;
+                }
                 let file_header = format!("## {}\n\n```\n", file.display());
                 let file_footer = "\n```\n\n";
 
@@ -227,7 +245,9 @@ impl ContextManager {
                 }
                 context.push_str(file_footer);
 
-                current_tokens = self.tokenizer.count_tokens(&context);
+                let tokens = self.tokenizer.count_tokens(&context);
+                assembly.add(&file, &content, ContextSource::Explicit, tokens.saturating_sub(current_tokens));
+                current_tokens = tokens;
             }
         }
 
@@ -245,6 +265,8 @@ impl ContextManager {
         if p.is_dir() {
             self.collect_files_from_dir(p, &mut all_files)?;
         } else if p.is_file() {
+            // Fail on a document that can't be extracted instead of leaving it out
+            documents::read_text(p)?;
             all_files.push(p.to_path_buf());
         }
 
@@ -267,7 +289,7 @@ impl ContextManager {
                 break;
             }
 
-            if let Ok(content) = fs::read_to_string(&file) {
+            if let Ok(content) = documents::read_text(&file) {
                 let file_header = format!("## {}\n\n```\n", file.display());
                 let content_tokens = self.tokenizer.count_tokens(&content);
                 let header_tokens = self.tokenizer.count_tokens(&file_header);
@@ -360,30 +382,27 @@ impl ContextManager {
             std::env::current_dir()?.join(p)
         };
 
-        absolute
-            .canonicalize()
+        crate::paths::canonicalize(&absolute)
             .map(|p| p.to_string_lossy().to_string())
             .context("Failed to resolve path")
     }
 
     fn collect_files_from_dir(&self, dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
-        let dir_str = dir.to_string_lossy();
-
+        let ignore_rules = IgnoreRules::for_dir(dir);
         for pattern in &self.config.include {
-            let full_pattern = format!("{}/{}", dir_str, pattern);
+            let full_pattern = crate::paths::glob_under(dir, pattern);
 
             for entry in glob(&full_pattern).context("Invalid glob pattern")? {
                 if let Ok(path) = entry {
-                    let _path_str = path.to_string_lossy();
                     let excluded = self.config.exclude.iter().any(|exc| {
-                        let exc_pattern = format!("{}/{}", dir_str, exc);
+                        let exc_pattern = crate::paths::glob_under(dir, exc);
                         glob(&exc_pattern)
                             .ok()
                             .map(|mut g| g.any(|e| e.ok().map(|p| p == path).unwrap_or(false)))
                             .unwrap_or(false)
                     });
 
-                    if !excluded && path.is_file() {
+                    if !excluded && path.is_file() && !ignore_rules.is_ignored(&path, false) {
                         files.push(path);
                     }
                 }
@@ -2,10 +2,16 @@
 //!
 //! This module provides:
 //! - **ContextManager**: Explicit file/directory management for prompts
+//! - **ContextAssembly**: Dedupes files across explicit, smart and project context
+//! - **Documents**: Text extraction from PDF, docx and HTML files (cached)
 //! - **SmartContextSelector**: Auto-selects relevant files based on query analysis
-//! - **Tokenizer**: Accurate token counting using tiktoken
+//! - **Tokenizer**: Accurate token counting using tiktoken, or a local model's own tokenizer
 //! - **FileIndex**: Cached file metadata for efficient access
 //! - **EmbeddingEngine**: Semantic search using embeddings (optional)
+//! - **ContextBudget**: Splits the context window between prompt sections
+//! - **ModelLimits**: Context windows, detected from Ollama or guessed by name
+//! - **FileOutline**: Symbol maps of source files (functions, types, classes)
+//! - **RepoStore**: Remote git repositories cloned as read-only context
 //!
 //! # Architecture
 //!
@@ -15,6 +21,7 @@
 //! │  - Keyword extraction from query                        │
 //! │  - Name-based file matching                             │
 //! │  - Content-based file matching (grep)                   │
+//! │  - Symbol matching (code outlines)                      │
 //! │  - Semantic matching (embeddings, optional)             │
 //! │  - Ranking and token-aware truncation                   │
 //! └─────────────┬───────────────┬───────────────────────────┘
@@ -31,13 +38,14 @@
 //! # Usage
 //!
 //! ```rust,ignore
-//! use quant_cli::context::{SmartContextSelector, SmartContext};
+//! use quant_cli::context::{ContextAssembly, SmartContextSelector, SmartContext};
 //!
 //! let mut selector = SmartContextSelector::new(project_root)
 //!     .with_max_tokens(8000)
 //!     .with_model("gpt-4");
 //!
-//! let context = selector.select_context("implement authentication")?;
+//! let mut assembly = ContextAssembly::new();
+//! let context = selector.select_context("implement authentication", &mut assembly)?;
 //!
 //! if !context.is_empty() {
 //!     println!("Selected {} files ({} tokens)",
@@ -50,7 +58,15 @@
 //!
 //! - `embeddings`: Enables semantic search using fastembed
 
+pub mod assembly;
+pub mod budget;
+pub mod documents;
+pub mod gguf;
 pub mod manager;
+pub mod model_info;
+pub mod model_tokenizer;
+pub mod outline;
+pub mod repos;
 pub mod smart;
 pub mod tokenizer;
 pub mod index;
@@ -59,9 +75,12 @@ pub mod index;
 pub mod embeddings;
 
 // Re-exports
+pub use assembly::ContextAssembly;
+pub use budget::{BudgetRatios, ContextBudget};
 pub use manager::{ContextConfig, ContextManager, DEFAULT_MAX_TOKENS};
+pub use outline::FileOutline;
 pub use smart::{SmartContext, SmartContextFile, SmartContextSelector};
-pub use tokenizer::{count_tokens, count_tokens_for_model, truncate_to_tokens, Tokenizer, TokenizerType};
+pub use tokenizer::{count_tokens_for_model, save_token_cache, Tokenizer};
 pub use index::{FileIndex, FileMetadata, IndexStats};
 
 #[cfg(feature = "embeddings")]
@@ -78,8 +97,32 @@ pub struct ModelLimits {
 }
 
 impl ModelLimits {
-    /// Get limits for a model by name
+    /// Get limits for a model, from its context window as last detected
+    /// from Ollama (see [`model_info`]) or else guessed from its name
     pub fn for_model(model: &str) -> Self {
+        match model_info::cached_window(model) {
+            Some(window) => Self::for_window(window),
+            None => Self::from_name(model),
+        }
+    }
+
+    /// Limits for a known context window, reserving more in larger windows
+    pub fn for_window(context_window: usize) -> Self {
+        let (system_reserve, response_reserve) = match context_window {
+            100_000.. => (8000, 4000),
+            32_768.. => (4000, 4000),
+            8192.. => (2000, 2000),
+            _ => (1000, 1000),
+        };
+        Self {
+            context_window,
+            system_reserve,
+            response_reserve,
+        }
+    }
+
+    /// Guess limits from a model's name
+    pub fn from_name(model: &str) -> Self {
         let model_lower = model.to_lowercase();
 
         // GPT-4 variants
@@ -180,6 +223,8 @@ impl ModelLimits {
 pub struct AdaptiveContext {
     /// Model-specific limits
     limits: ModelLimits,
+    /// The model's tokenizer
+    tokenizer: Tokenizer,
     /// Current token usage
     used_tokens: usize,
 }
@@ -189,6 +234,7 @@ impl AdaptiveContext {
     pub fn for_model(model: &str) -> Self {
         Self {
             limits: ModelLimits::for_model(model),
+            tokenizer: Tokenizer::new(model),
             used_tokens: 0,
         }
     }
@@ -203,11 +249,23 @@ impl AdaptiveContext {
         self.used_tokens += tokens;
     }
 
+    /// Add the tokens of `text` to usage, returning them
+    pub fn add_text(&mut self, text: &str) -> usize {
+        let tokens = self.tokenizer.count_tokens(text);
+        self.add_usage(tokens);
+        tokens
+    }
+
     /// Check if we can fit more content
     pub fn can_fit(&self, tokens: usize) -> bool {
         self.remaining() >= tokens
     }
 
+    /// Check if `text` fits in the remaining tokens
+    pub fn can_fit_text(&self, text: &str) -> bool {
+        self.can_fit(self.tokenizer.count_tokens(text))
+    }
+
     /// Get usage percentage
     pub fn usage_percent(&self) -> f32 {
         let available = self.limits.available_for_context();
@@ -224,17 +282,21 @@ mod tests {
 
     #[test]
     fn test_model_limits() {
-        let gpt4 = ModelLimits::for_model("gpt-4");
+        let gpt4 = ModelLimits::from_name("gpt-4");
         assert_eq!(gpt4.context_window, 8192);
 
-        let gpt4_turbo = ModelLimits::for_model("gpt-4-turbo");
+        let gpt4_turbo = ModelLimits::from_name("gpt-4-turbo");
         assert_eq!(gpt4_turbo.context_window, 128000);
 
-        let claude = ModelLimits::for_model("claude-3-sonnet");
+        let claude = ModelLimits::from_name("claude-3-sonnet");
         assert_eq!(claude.context_window, 200000);
 
-        let llama = ModelLimits::for_model("llama3.2");
+        let llama = ModelLimits::from_name("llama3.2");
         assert_eq!(llama.context_window, 8192);
+
+        let detected = ModelLimits::for_window(131072);
+        assert_eq!((detected.system_reserve, detected.response_reserve), (8000, 4000));
+        assert_eq!(ModelLimits::for_window(4096).available_for_context(), 2096);
     }
 
     #[test]
@@ -244,5 +306,11 @@ mod tests {
         assert!(ctx.can_fit(1000));
         ctx.add_usage(1000);
         assert!(ctx.remaining() < ctx.limits.available_for_context());
+
+        let before = ctx.remaining();
+        let tokens = ctx.add_text("fn main() { println!(\"hello\"); }");
+        assert!(tokens > 0);
+        assert_eq!(ctx.remaining(), before - tokens);
+        assert!(ctx.can_fit_text("short"));
     }
 }
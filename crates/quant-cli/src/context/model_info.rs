@@ -0,0 +1,125 @@
+//! Context windows detected from Ollama
+//!
+//! `/api/show` reports the context length a model was trained with and any
+//! `num_ctx` its Modelfile sets. The window found is cached on disk by model
+//! name, so [`ModelLimits::for_model`](super::ModelLimits::for_model) can use
+//! it without a request, and refreshed whenever a session starts with the
+//! model.
+
+use anyhow::Result;
+use llm_core::{ModelInfo, OllamaClient};
+use once_cell::sync::Lazy;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::debug;
+
+/// How long to wait for `/api/show` before using the cached window
+const SHOW_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Detected context windows by model name, loaded from disk on first use
+static WINDOWS: Lazy<RwLock<HashMap<String, usize>>> =
+    Lazy::new(|| RwLock::new(default_path().map(|path| load(&path)).unwrap_or_default()));
+
+/// Default location in the cache directory
+pub fn default_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|d| d.join("quant").join("context_windows.json"))
+}
+
+/// The last detected context window of `model`
+pub fn cached_window(model: &str) -> Option<usize> {
+    WINDOWS.read().get(&key(model)).copied()
+}
+
+/// Ask Ollama for `model`'s context window and cache it, falling back to
+/// the cached window if Ollama doesn't answer
+pub async fn detect(client: &OllamaClient, model: &str) -> Option<usize> {
+    let info = match tokio::time::timeout(SHOW_TIMEOUT, client.show_model(model)).await {
+        Ok(Ok(info)) => info,
+        Ok(Err(e)) => {
+            debug!(model, error = %e, "Failed to get model details");
+            return cached_window(model);
+        }
+        Err(_) => {
+            debug!(model, "Timed out getting model details");
+            return cached_window(model);
+        }
+    };
+
+    let window = window_of(&info)?;
+    let previous = WINDOWS.write().insert(key(model), window);
+    if previous != Some(window) {
+        debug!(model, window, "Detected context window");
+        if let Some(path) = default_path() {
+            if let Err(e) = save(&path, &WINDOWS.read()) {
+                debug!(error = %e, "Failed to save context windows");
+            }
+        }
+    }
+    Some(window)
+}
+
+/// The window Ollama loads the model with: the Modelfile's `num_ctx`, or
+/// the length the model was trained with
+fn window_of(info: &ModelInfo) -> Option<usize> {
+    info.num_ctx().or_else(|| info.context_length()).map(|tokens| tokens as usize)
+}
+
+/// Cache key of a model name; untagged names mean `:latest`
+fn key(model: &str) -> String {
+    let name = model.rsplit('/').next().unwrap_or(model);
+    if name.contains(':') {
+        model.to_string()
+    } else {
+        format!("{}:latest", model)
+    }
+}
+
+fn load(path: &Path) -> HashMap<String, usize> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save(path: &Path, windows: &HashMap<String, usize>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(windows)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_of() {
+        let info: ModelInfo =
+            serde_json::from_str(r#"{"model_info": {"qwen2.context_length": 32768}}"#).unwrap();
+        assert_eq!(window_of(&info), Some(32768));
+
+        let info: ModelInfo = serde_json::from_str(
+            r#"{"parameters": "num_ctx 8192", "model_info": {"qwen2.context_length": 32768}}"#,
+        )
+        .unwrap();
+        assert_eq!(window_of(&info), Some(8192));
+        assert_eq!(window_of(&ModelInfo::default()), None);
+    }
+
+    #[test]
+    fn test_key_and_persistence() {
+        assert_eq!(key("llama3.2"), "llama3.2:latest");
+        assert_eq!(key("qwen2.5-coder:7b"), "qwen2.5-coder:7b");
+        assert_eq!(key("localhost:5000/team/model"), "localhost:5000/team/model:latest");
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("windows.json");
+        let windows = HashMap::from([(key("llama3.2"), 131072)]);
+        save(&path, &windows).unwrap();
+        assert_eq!(load(&path), windows);
+        assert!(load(&dir.path().join("missing.json")).is_empty());
+    }
+}
@@ -0,0 +1,226 @@
+//! The model's own tokenizer, for local models
+//!
+//! tiktoken's cl100k_base vocabulary miscounts llama- and qwen-family models,
+//! sometimes by a quarter. A model's actual tokenizer is looked up by model
+//! name, in order:
+//!
+//! 1. `[context.tokenizers]` in the config, mapping a model name to a
+//!    `tokenizer.json` or `.gguf` file
+//! 2. The GGUF file of a `[models.local]` entry in llm.toml
+//! 3. The model's blob in the Ollama model store
+//! 4. `tokenizer.json` in the Hugging Face hub cache, for `org/name` models
+//!
+//! Models with none of these keep the tiktoken estimate. Lookups, found or
+//! not, are done once per model per process.
+
+use anyhow::{anyhow, bail, Result};
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokenizers::models::bpe::{Vocab, BPE};
+use tokenizers::models::unigram::Unigram;
+use tokenizers::pre_tokenizers::byte_level::ByteLevel;
+use tokenizers::pre_tokenizers::metaspace::{Metaspace, PrependScheme};
+use tracing::debug;
+
+use super::gguf;
+
+/// Tokenizers loaded so far by model name (None if the model has none)
+static LOADED: Lazy<DashMap<String, Option<Arc<ModelTokenizer>>>> = Lazy::new(DashMap::new);
+
+/// A tokenizer loaded from a model's tokenizer files
+pub struct ModelTokenizer {
+    /// Where it was loaded from
+    source: PathBuf,
+    inner: tokenizers::Tokenizer,
+}
+
+impl ModelTokenizer {
+    /// Load a `tokenizer.json` or the vocabulary of a GGUF file
+    pub fn load(path: &Path) -> Result<Self> {
+        let inner = if is_gguf(path) {
+            from_gguf(path)?
+        } else {
+            tokenizers::Tokenizer::from_file(path).map_err(|e| anyhow!("Failed to load {}: {}", path.display(), e))?
+        };
+        Ok(Self {
+            source: path.to_path_buf(),
+            inner,
+        })
+    }
+
+    pub fn source(&self) -> &Path {
+        &self.source
+    }
+
+    /// Number of tokens in `text`, or None if it can't be encoded
+    pub fn count(&self, text: &str) -> Option<usize> {
+        self.inner.encode(text, false).ok().map(|encoding| encoding.len())
+    }
+
+    /// The longest prefix of `text` of at most `max_tokens` tokens
+    pub fn truncate(&self, text: &str, max_tokens: usize) -> Option<String> {
+        let encoding = self.inner.encode(text, false).ok()?;
+        if encoding.len() <= max_tokens {
+            return Some(text.to_string());
+        }
+        let mut end = match max_tokens {
+            0 => 0,
+            n => encoding.get_offsets()[n - 1].1.min(text.len()),
+        };
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        Some(text[..end].to_string())
+    }
+}
+
+/// The tokenizer of `model`, if one can be found
+pub fn for_model(model: &str) -> Option<Arc<ModelTokenizer>> {
+    if let Some(loaded) = LOADED.get(model) {
+        return loaded.clone();
+    }
+
+    let tokenizer = locate(model).and_then(|path| match ModelTokenizer::load(&path) {
+        Ok(tokenizer) => {
+            debug!(model, path = %path.display(), "Loaded model tokenizer");
+            Some(Arc::new(tokenizer))
+        }
+        Err(e) => {
+            debug!(model, error = %e, "Failed to load model tokenizer");
+            None
+        }
+    });
+    LOADED.insert(model.to_string(), tokenizer.clone());
+    tokenizer
+}
+
+/// Find the tokenizer file for `model`
+fn locate(model: &str) -> Option<PathBuf> {
+    let configured = crate::config::UserConfig::load()
+        .map(|config| config.context.tokenizers)
+        .unwrap_or_default();
+    if let Some(path) = lookup(&configured, model) {
+        return Some(path.clone());
+    }
+
+    let llm_config = crate::config::try_load_llm_config();
+    if let Some(config) = &llm_config {
+        let local = config.models.local.values().find(|m| m.name == model || base_name(&m.name) == model);
+        if let Some(local) = local {
+            let path = config.ollama.models_path.join(&local.file);
+            if path.is_file() {
+                return Some(path);
+            }
+        }
+    }
+
+    let ollama_home = llm_config.map(|config| config.ollama.ollama_home);
+    crate::model_store::find_blob(model, ollama_home.as_deref()).or_else(|| hf_cached(model))
+}
+
+/// The entry for `model` with or without its tag
+fn lookup<'a>(map: &'a HashMap<String, PathBuf>, model: &str) -> Option<&'a PathBuf> {
+    map.get(model).or_else(|| map.get(base_name(model)))
+}
+
+/// A model name without its `:tag`
+fn base_name(model: &str) -> &str {
+    model.split_once(':').map_or(model, |(name, _)| name)
+}
+
+/// Whether `path` is a GGUF file (Ollama blobs have no extension)
+fn is_gguf(path: &Path) -> bool {
+    use std::io::Read;
+    let mut magic = [0u8; 4];
+    std::fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .is_ok_and(|_| &magic == b"GGUF")
+}
+
+/// `tokenizer.json` of an `org/name` model in the Hugging Face hub cache
+fn hf_cached(model: &str) -> Option<PathBuf> {
+    let (org, name) = model.split_once('/')?;
+    let hub = match std::env::var_os("HF_HOME") {
+        Some(home) => PathBuf::from(home).join("hub"),
+        None => dirs::home_dir()?.join(".cache").join("huggingface").join("hub"),
+    };
+    let snapshots = hub.join(format!("models--{}--{}", org, base_name(name))).join("snapshots");
+    std::fs::read_dir(snapshots)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path().join("tokenizer.json"))
+        .find(|path| path.is_file())
+}
+
+/// Build a tokenizer from a GGUF file's vocabulary
+fn from_gguf(path: &Path) -> Result<tokenizers::Tokenizer> {
+    let vocab = gguf::read_vocab(path)?;
+    match vocab.model.as_str() {
+        // Byte-level BPE (llama 3, qwen, deepseek, ...)
+        "gpt2" => {
+            let ids: Vocab = vocab.tokens.into_iter().zip(0..).collect();
+            let merges = vocab
+                .merges
+                .iter()
+                .filter_map(|merge| merge.split_once(' '))
+                .map(|(left, right)| (left.to_string(), right.to_string()))
+                .collect();
+            let bpe = BPE::builder()
+                .vocab_and_merges(ids, merges)
+                .build()
+                .map_err(|e| anyhow!("Invalid BPE vocabulary: {}", e))?;
+            let mut tokenizer = tokenizers::Tokenizer::new(bpe);
+            tokenizer.with_pre_tokenizer(Some(ByteLevel::new(false, true, true)));
+            Ok(tokenizer)
+        }
+        // SentencePiece (llama 2, mistral, gemma, ...)
+        "llama" => {
+            if vocab.scores.len() != vocab.tokens.len() {
+                bail!("GGUF vocabulary has {} tokens but {} scores", vocab.tokens.len(), vocab.scores.len());
+            }
+            let unk_id = vocab.tokens.iter().position(|t| t == "<unk>");
+            let byte_fallback = vocab.tokens.iter().any(|t| t == "<0x00>");
+            let pieces = vocab.tokens.into_iter().zip(vocab.scores.into_iter().map(f64::from)).collect();
+            let unigram =
+                Unigram::from(pieces, unk_id, byte_fallback).map_err(|e| anyhow!("Invalid vocabulary: {}", e))?;
+            let mut tokenizer = tokenizers::Tokenizer::new(unigram);
+            tokenizer.with_pre_tokenizer(Some(Metaspace::new('▁', PrependScheme::First, false)));
+            Ok(tokenizer)
+        }
+        other => bail!("Unsupported GGUF tokenizer '{}'", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gguf_bpe_tokenizer() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("model.gguf");
+        let tokens = ["h", "e", "l", "o", "Ġ", "he", "ll", "hell", "hello", "Ġhello"];
+        let merges = ["h e", "l l", "he ll", "hell o", "Ġ hello"];
+        gguf::write_test_file(&path, "gpt2", &tokens, &[0.0; 10], &merges);
+
+        let tokenizer = ModelTokenizer::load(&path).unwrap();
+        assert_eq!(tokenizer.count("hello hello"), Some(2));
+        assert_eq!(tokenizer.count("hell"), Some(1));
+        assert_eq!(tokenizer.truncate("hello hello", 1).unwrap(), "hello");
+    }
+
+    #[test]
+    fn test_gguf_sentencepiece_tokenizer() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("model.gguf");
+        let tokens = ["<unk>", "▁", "a", "b", "▁ab", "ab"];
+        let scores = [0.0, -1.0, -2.0, -2.0, -0.5, -1.0];
+        gguf::write_test_file(&path, "llama", &tokens, &scores, &[]);
+
+        let tokenizer = ModelTokenizer::load(&path).unwrap();
+        assert_eq!(tokenizer.count("ab ab"), Some(2));
+    }
+}
@@ -0,0 +1,465 @@
+//! Code outlines (symbol maps)
+//!
+//! Extracts the functions, types, and classes declared in a source file so
+//! the agent can see a file's shape without reading all of it. Files are
+//! parsed with their tree-sitter grammar; declarations are read from the
+//! syntax tree, so methods are reported under their enclosing type and
+//! nothing in comments, strings or function bodies is mistaken for one.
+
+use std::path::Path;
+use tree_sitter::{Node, Parser};
+
+/// Supported source languages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    Tsx,
+    Go,
+    Java,
+    C,
+    Cpp,
+}
+
+impl Language {
+    /// Detect language from a file extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "rs" => Some(Self::Rust),
+            "py" => Some(Self::Python),
+            "js" | "jsx" | "mjs" | "cjs" => Some(Self::JavaScript),
+            "ts" | "mts" | "cts" => Some(Self::TypeScript),
+            "tsx" => Some(Self::Tsx),
+            "go" => Some(Self::Go),
+            "java" => Some(Self::Java),
+            "c" | "h" => Some(Self::C),
+            "cpp" | "cc" | "cxx" | "hpp" | "hh" => Some(Self::Cpp),
+            _ => None,
+        }
+    }
+
+    fn grammar(&self) -> tree_sitter::Language {
+        match self {
+            Self::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Self::Python => tree_sitter_python::LANGUAGE.into(),
+            Self::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            Self::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Self::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            Self::Go => tree_sitter_go::LANGUAGE.into(),
+            Self::Java => tree_sitter_java::LANGUAGE.into(),
+            Self::C => tree_sitter_c::LANGUAGE.into(),
+            Self::Cpp => tree_sitter_cpp::LANGUAGE.into(),
+        }
+    }
+}
+
+/// Kind of declared symbol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function,
+    Method,
+    Struct,
+    Enum,
+    Trait,
+    Impl,
+    Class,
+    Interface,
+    Type,
+    Const,
+    Module,
+    Macro,
+}
+
+impl SymbolKind {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Function => "fn",
+            Self::Method => "method",
+            Self::Struct => "struct",
+            Self::Enum => "enum",
+            Self::Trait => "trait",
+            Self::Impl => "impl",
+            Self::Class => "class",
+            Self::Interface => "interface",
+            Self::Type => "type",
+            Self::Const => "const",
+            Self::Module => "mod",
+            Self::Macro => "macro",
+        }
+    }
+
+    /// Whether members declared inside this symbol are methods
+    fn is_container(&self) -> bool {
+        matches!(self, Self::Impl | Self::Trait | Self::Class | Self::Interface | Self::Struct | Self::Enum)
+    }
+
+    fn is_function(&self) -> bool {
+        matches!(self, Self::Function | Self::Method)
+    }
+}
+
+/// A symbol declared in a file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub kind: SymbolKind,
+    pub name: String,
+    /// 1-based line of the declaration
+    pub line: usize,
+    /// Nesting depth (0 = top level)
+    pub depth: usize,
+}
+
+/// Extract the symbols declared in source code
+pub fn extract_symbols(content: &str, language: Language) -> Vec<Symbol> {
+    let mut parser = Parser::new();
+    if let Err(e) = parser.set_language(&language.grammar()) {
+        tracing::warn!(?language, error = %e, "Failed to load grammar");
+        return Vec::new();
+    }
+    let Some(tree) = parser.parse(content, None) else {
+        return Vec::new();
+    };
+
+    let mut symbols = Vec::new();
+    collect(tree.root_node(), content.as_bytes(), language, 0, None, &mut symbols);
+    symbols
+}
+
+/// Add the declarations under `node` to `symbols`; `parent` is the
+/// innermost enclosing declaration
+fn collect(node: Node, src: &[u8], language: Language, depth: usize, parent: Option<SymbolKind>, symbols: &mut Vec<Symbol>) {
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        let Some((kind, name)) = declaration(child, src, language) else {
+            // Bodies of functions and closures hold no outline-worthy symbols
+            let kind = child.kind();
+            if !(kind.contains("function") || kind.contains("lambda") || kind.contains("closure")) {
+                collect(child, src, language, depth, parent, symbols);
+            }
+            continue;
+        };
+        let kind = if kind == SymbolKind::Function && parent.is_some_and(|p| p.is_container()) {
+            SymbolKind::Method
+        } else {
+            kind
+        };
+        symbols.push(Symbol { kind, name, line: child.start_position().row + 1, depth });
+
+        if !kind.is_function() {
+            let nested = if kind.is_container() || kind == SymbolKind::Module { depth + 1 } else { depth };
+            collect(child, src, language, nested, Some(kind), symbols);
+        }
+    }
+}
+
+/// The kind and name of the symbol `node` declares, if it declares one
+fn declaration(node: Node, src: &[u8], language: Language) -> Option<(SymbolKind, String)> {
+    let text = |node: Node| node.utf8_text(src).ok().map(str::to_string);
+    let field = |name: &str| node.child_by_field_name(name).and_then(text);
+    // Types only count with a body, so forward declarations are left out
+    let with_body = |kind: SymbolKind| Some(kind).filter(|_| node.child_by_field_name("body").is_some());
+
+    let kind = match (language, node.kind()) {
+        (Language::Rust, "function_item" | "function_signature_item") => SymbolKind::Function,
+        (Language::Rust, "struct_item" | "union_item") => SymbolKind::Struct,
+        (Language::Rust, "enum_item") => SymbolKind::Enum,
+        (Language::Rust, "trait_item") => SymbolKind::Trait,
+        (Language::Rust, "type_item") => SymbolKind::Type,
+        (Language::Rust, "const_item" | "static_item") => SymbolKind::Const,
+        (Language::Rust, "mod_item") => with_body(SymbolKind::Module)?,
+        (Language::Rust, "macro_definition") => SymbolKind::Macro,
+        (Language::Rust, "impl_item") => {
+            let target = field("type")?;
+            let name = match field("trait") {
+                Some(trait_name) => format!("{} for {}", trait_name, target),
+                None => target,
+            };
+            return Some((SymbolKind::Impl, name));
+        }
+
+        (Language::Python, "function_definition") => SymbolKind::Function,
+        (Language::Python, "class_definition") => SymbolKind::Class,
+
+        (Language::JavaScript | Language::TypeScript | Language::Tsx, kind) => match kind {
+            "function_declaration" | "generator_function_declaration" => SymbolKind::Function,
+            "method_definition" | "abstract_method_signature" => SymbolKind::Method,
+            "class_declaration" | "abstract_class_declaration" => SymbolKind::Class,
+            "interface_declaration" => SymbolKind::Interface,
+            "type_alias_declaration" => SymbolKind::Type,
+            "enum_declaration" => SymbolKind::Enum,
+            // `const handler = async (req) => { ... }`
+            "variable_declarator" => {
+                let value = node.child_by_field_name("value")?;
+                if !matches!(value.kind(), "arrow_function" | "function_expression" | "function") {
+                    return None;
+                }
+                SymbolKind::Function
+            }
+            _ => return None,
+        },
+
+        (Language::Go, "function_declaration") => SymbolKind::Function,
+        (Language::Go, "method_declaration") => SymbolKind::Method,
+        (Language::Go, "type_alias") => SymbolKind::Type,
+        (Language::Go, "type_spec") => match node.child_by_field_name("type")?.kind() {
+            "struct_type" => SymbolKind::Struct,
+            "interface_type" => SymbolKind::Interface,
+            _ => SymbolKind::Type,
+        },
+
+        (Language::Java, "class_declaration" | "record_declaration") => SymbolKind::Class,
+        (Language::Java, "interface_declaration" | "annotation_type_declaration") => SymbolKind::Interface,
+        (Language::Java, "enum_declaration") => SymbolKind::Enum,
+        (Language::Java, "method_declaration" | "constructor_declaration") => SymbolKind::Method,
+
+        (Language::C | Language::Cpp, kind) => match kind {
+            "function_definition" => {
+                return declarator_name(node.child_by_field_name("declarator")?, src).map(|name| (SymbolKind::Function, name));
+            }
+            "struct_specifier" | "union_specifier" => with_body(SymbolKind::Struct)?,
+            "class_specifier" => with_body(SymbolKind::Class)?,
+            "enum_specifier" => with_body(SymbolKind::Enum)?,
+            "namespace_definition" => SymbolKind::Module,
+            // `typedef struct { ... } Name;` names the struct by its typedef
+            "type_definition" => {
+                let target = node.child_by_field_name("type")?;
+                if target.child_by_field_name("name").is_some() || target.child_by_field_name("body").is_none() {
+                    return None;
+                }
+                let kind = match target.kind() {
+                    "struct_specifier" | "union_specifier" => SymbolKind::Struct,
+                    "enum_specifier" => SymbolKind::Enum,
+                    _ => return None,
+                };
+                return Some((kind, field("declarator")?));
+            }
+            _ => return None,
+        },
+
+        _ => return None,
+    };
+    Some((kind, field("name")?))
+}
+
+/// The declared name inside a C/C++ declarator (`*parse(...)`, `Foo::bar(...)`)
+fn declarator_name(mut node: Node, src: &[u8]) -> Option<String> {
+    loop {
+        match node.kind() {
+            "identifier" | "field_identifier" | "qualified_identifier" | "destructor_name" | "operator_name" => {
+                return node.utf8_text(src).ok().map(str::to_string);
+            }
+            // Reference declarators have no field for what they wrap
+            _ => node = node.child_by_field_name("declarator").or_else(|| node.named_child(node.named_child_count().checked_sub(1)?))?,
+        }
+    }
+}
+
+/// Outline of a single file
+#[derive(Debug, Clone)]
+pub struct FileOutline {
+    pub symbols: Vec<Symbol>,
+    pub line_count: usize,
+}
+
+impl FileOutline {
+    /// Build an outline from file content, if the language is supported
+    pub fn from_content(path: &Path, content: &str) -> Option<Self> {
+        let language = Language::from_path(path)?;
+        Some(Self {
+            symbols: extract_symbols(content, language),
+            line_count: content.lines().count(),
+        })
+    }
+
+    /// Whether any symbol name matches a keyword
+    ///
+    /// Matching ignores case and underscores, so `token_cache` matches
+    /// `TokenCache`. Returns 2 for an exact match, 1 for a partial match, 0
+    /// otherwise.
+    pub fn match_strength(&self, keyword: &str) -> u8 {
+        let normalize = |s: &str| s.to_lowercase().replace('_', "");
+        let keyword = normalize(keyword);
+        if keyword.is_empty() {
+            return 0;
+        }
+
+        let mut best = 0;
+        for symbol in &self.symbols {
+            let name = normalize(&symbol.name);
+            if name == keyword {
+                return 2;
+            }
+            if name.contains(&keyword) {
+                best = 1;
+            }
+        }
+        best
+    }
+
+    /// Render as an indented symbol map
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for symbol in &self.symbols {
+            out.push_str(&format!(
+                "{}{} {} :{}\n",
+                "  ".repeat(symbol.depth + 1),
+                symbol.kind.label(),
+                symbol.name,
+                symbol.line
+            ));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(symbols: &[Symbol]) -> Vec<(SymbolKind, &str, usize)> {
+        symbols.iter().map(|s| (s.kind, s.name.as_str(), s.depth)).collect()
+    }
+
+    #[test]
+    fn test_rust_outline() {
+        let src = r#"
+//! Docs with fn fake() {
+use std::fmt;
+
+pub struct Config {
+    name: String,
+}
+
+pub struct Unit;
+
+impl Config {
+    pub fn new() -> Self {
+        let f = |x| { x };
+        fn helper() {}
+        Self { name: "fn nope() {".into() }
+    }
+
+    pub(crate) async fn load(&self) {}
+}
+
+impl<T: Clone> fmt::Display for Wrapper<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { Ok(()) }
+}
+
+pub const MAX_SIZE: usize = 10;
+
+macro_rules! log {
+    () => {};
+}
+"#;
+        let symbols = extract_symbols(src, Language::Rust);
+        assert_eq!(
+            names(&symbols),
+            vec![
+                (SymbolKind::Struct, "Config", 0),
+                (SymbolKind::Struct, "Unit", 0),
+                (SymbolKind::Impl, "Config", 0),
+                (SymbolKind::Method, "new", 1),
+                (SymbolKind::Method, "load", 1),
+                (SymbolKind::Impl, "fmt::Display for Wrapper<T>", 0),
+                (SymbolKind::Method, "fmt", 1),
+                (SymbolKind::Const, "MAX_SIZE", 0),
+                (SymbolKind::Macro, "log", 0),
+            ]
+        );
+        assert_eq!(symbols[3].line, 12);
+    }
+
+    #[test]
+    fn test_python_outline() {
+        let src = "import os\n\nclass Store:\n    def get(self, key):\n        def inner():\n            pass\n        return 1\n\n    async def put(self):\n        pass\n\ndef main():\n    pass\n";
+        let symbols = extract_symbols(src, Language::Python);
+        assert_eq!(
+            names(&symbols),
+            vec![
+                (SymbolKind::Class, "Store", 0),
+                (SymbolKind::Method, "get", 1),
+                (SymbolKind::Method, "put", 1),
+                (SymbolKind::Function, "main", 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_typescript_outline() {
+        let src = "export interface Props {\n  id: string;\n}\n\nexport class Widget {\n  render(): string {\n    if (x) {\n    }\n    return '';\n  }\n}\n\nexport const useThing = async (id: string) => {\n};\n\nfunction helper() {}\n";
+        let symbols = extract_symbols(src, Language::TypeScript);
+        assert_eq!(
+            names(&symbols),
+            vec![
+                (SymbolKind::Interface, "Props", 0),
+                (SymbolKind::Class, "Widget", 0),
+                (SymbolKind::Method, "render", 1),
+                (SymbolKind::Function, "useThing", 0),
+                (SymbolKind::Function, "helper", 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_go_outline() {
+        let src = "package main\n\ntype Server struct {\n\taddr string\n}\n\nfunc (s *Server) Start() error {\n\treturn nil\n}\n\nfunc main() {\n}\n";
+        let symbols = extract_symbols(src, Language::Go);
+        assert_eq!(
+            names(&symbols),
+            vec![
+                (SymbolKind::Struct, "Server", 0),
+                (SymbolKind::Method, "Start", 0),
+                (SymbolKind::Function, "main", 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_java_outline() {
+        let src = "/* class Fake { */\npublic class Store {\n    private String name = \"void nope() {\";\n\n    public Store() {}\n\n    public <T> List<T> load(int id) {\n        return null;\n    }\n\n    enum State { OPEN; void close() {} }\n}\n";
+        let symbols = extract_symbols(src, Language::Java);
+        assert_eq!(
+            names(&symbols),
+            vec![
+                (SymbolKind::Class, "Store", 0),
+                (SymbolKind::Method, "Store", 1),
+                (SymbolKind::Method, "load", 1),
+                (SymbolKind::Enum, "State", 1),
+                (SymbolKind::Method, "close", 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_c_and_cpp_outline() {
+        let src = "struct point;\ntypedef struct {\n    int x;\n} Vec2;\n\nstatic char *parse(const char *s) {\n    return 0;\n}\n";
+        let symbols = extract_symbols(src, Language::C);
+        assert_eq!(names(&symbols), vec![(SymbolKind::Struct, "Vec2", 0), (SymbolKind::Function, "parse", 0)]);
+
+        let src = "namespace app {\nclass Server {\npublic:\n    void start() {}\n};\n}\n\nint app::Server::port() const { return 80; }\n";
+        let symbols = extract_symbols(src, Language::Cpp);
+        assert_eq!(
+            names(&symbols),
+            vec![
+                (SymbolKind::Module, "app", 0),
+                (SymbolKind::Class, "Server", 1),
+                (SymbolKind::Method, "start", 2),
+                (SymbolKind::Function, "app::Server::port", 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_match_strength_and_render() {
+        let outline = FileOutline::from_content(Path::new("lib.rs"), "pub struct TokenCache {}\nfn token() {}\n").unwrap();
+        assert_eq!(outline.match_strength("token"), 2);
+        assert_eq!(outline.match_strength("token_cache"), 2);
+        assert_eq!(outline.match_strength("cache"), 1);
+        assert_eq!(outline.match_strength("session"), 0);
+        assert_eq!(outline.render(), "  struct TokenCache :1\n  fn token :2\n");
+        assert!(FileOutline::from_content(Path::new("README.md"), "# hi").is_none());
+    }
+}
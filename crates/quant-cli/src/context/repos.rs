@@ -0,0 +1,309 @@
+//! Remote git repositories as read-only context
+//!
+//! `quant context add-repo <url> --ref <ref>` shallow-clones a repository
+//! into the cache, and smart context selection then searches it alongside
+//! the project, so agents can consult a dependency's source without it
+//! being vendored. Checkouts are read-only: the file writing tools refuse
+//! to change them.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use walkdir::WalkDir;
+
+/// A remote repository added as context
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemoteRepo {
+    /// Short name derived from the URL, e.g. "org/lib"
+    pub name: String,
+    pub url: String,
+    /// Branch, tag or commit checked out (the default branch if unset)
+    #[serde(default)]
+    pub git_ref: Option<String>,
+    /// Commit the checkout is at
+    pub commit: String,
+    pub added_at: DateTime<Utc>,
+}
+
+impl RemoteRepo {
+    /// e.g. "org/lib@v1.2"
+    pub fn label(&self) -> String {
+        format!("{}@{}", self.name, self.git_ref.as_deref().unwrap_or("HEAD"))
+    }
+
+    /// Directory of the checkout under the cache
+    fn dir_name(&self) -> String {
+        self.label()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || "-_.@".contains(c) { c } else { '-' })
+            .collect()
+    }
+}
+
+/// The remote repositories added as context, and their checkouts
+#[derive(Debug)]
+pub struct RepoStore {
+    /// Where the list is saved
+    path: PathBuf,
+    /// Where checkouts are cloned
+    cache_dir: PathBuf,
+    repos: Vec<RemoteRepo>,
+}
+
+impl RepoStore {
+    /// Load the repositories added with `quant context add-repo`
+    pub fn load() -> Result<Self> {
+        let state_dir = dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("quant");
+        Self::load_from(state_dir.join("context_repos.json"), default_cache_dir())
+    }
+
+    fn load_from(path: PathBuf, cache_dir: PathBuf) -> Result<Self> {
+        let repos = if path.exists() {
+            let content = fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self { path, cache_dir, repos })
+    }
+
+    pub fn repos(&self) -> &[RemoteRepo] {
+        &self.repos
+    }
+
+    /// Local checkout of a repository
+    pub fn checkout(&self, repo: &RemoteRepo) -> PathBuf {
+        self.cache_dir.join(repo.dir_name())
+    }
+
+    /// Checkouts to search for context (skipping any removed from the cache)
+    pub fn checkouts(&self) -> Vec<PathBuf> {
+        self.repos
+            .iter()
+            .map(|repo| self.checkout(repo))
+            .filter(|dir| dir.is_dir())
+            .collect()
+    }
+
+    /// Shallow-clone a repository at a ref and save it to the list,
+    /// replacing an earlier checkout of the same repository and ref
+    pub fn add(&mut self, url: &str, git_ref: Option<&str>) -> Result<RemoteRepo> {
+        let mut repo = RemoteRepo {
+            name: repo_name(url),
+            url: url.to_string(),
+            git_ref: git_ref.map(str::to_string),
+            commit: String::new(),
+            added_at: Utc::now(),
+        };
+        let checkout = self.checkout(&repo);
+        fs::create_dir_all(&self.cache_dir)
+            .with_context(|| format!("Failed to create {}", self.cache_dir.display()))?;
+
+        // Clone next to the old checkout so a failure leaves it in place
+        let staging = checkout.with_extension("cloning");
+        if staging.exists() {
+            fs::remove_dir_all(&staging)?;
+        }
+        if let Err(e) = shallow_clone(url, git_ref, &staging) {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+        repo.commit = git_output(&staging, &["rev-parse", "HEAD"])?;
+        make_read_only(&staging);
+
+        if checkout.exists() {
+            fs::remove_dir_all(&checkout).with_context(|| format!("Failed to replace {}", checkout.display()))?;
+        }
+        fs::rename(&staging, &checkout)?;
+
+        self.repos.retain(|r| r.label() != repo.label());
+        self.repos.push(repo.clone());
+        self.save()?;
+        Ok(repo)
+    }
+
+    /// Remove a repository, given as its name or `name@ref`, and delete its
+    /// checkout
+    pub fn remove(&mut self, name: &str) -> Result<RemoteRepo> {
+        let matches: Vec<usize> = self
+            .repos
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.label() == name || r.name == name || r.url == name)
+            .map(|(i, _)| i)
+            .collect();
+        let index = match matches[..] {
+            [index] => index,
+            [] => anyhow::bail!("No context repo named '{}'", name),
+            _ => anyhow::bail!("'{}' matches several refs; use <name>@<ref>", name),
+        };
+
+        let repo = self.repos.remove(index);
+        let checkout = self.checkout(&repo);
+        if checkout.exists() {
+            fs::remove_dir_all(&checkout).with_context(|| format!("Failed to delete {}", checkout.display()))?;
+        }
+        self.save()?;
+        Ok(repo)
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&self.repos)?)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+/// Whether a path is inside a remote repository checkout, which tools must
+/// not modify
+pub fn is_repo_path(path: &Path) -> bool {
+    let cache_dir = default_cache_dir();
+    path.starts_with(&cache_dir)
+        || crate::paths::canonicalize(path)
+            .ok()
+            .zip(crate::paths::canonicalize(&cache_dir).ok())
+            .is_some_and(|(path, cache_dir)| path.starts_with(cache_dir))
+}
+
+fn default_cache_dir() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(|| PathBuf::from(".cache"))
+        .join("quant")
+        .join("repos")
+}
+
+/// "https://github.com/org/lib.git" or "git@github.com:org/lib" -> "org/lib"
+fn repo_name(url: &str) -> String {
+    let path = url.trim_end_matches('/').trim_end_matches(".git");
+    let parts: Vec<&str> = path.rsplit(['/', ':']).take(2).filter(|p| !p.is_empty()).collect();
+    match parts[..] {
+        [name, owner] if !owner.contains('.') => format!("{}/{}", owner, name),
+        [name, ..] => name.to_string(),
+        [] => "repo".to_string(),
+    }
+}
+
+/// Clone just the files at `git_ref`; falls back to fetching a commit by
+/// hash, which `--branch` doesn't accept
+fn shallow_clone(url: &str, git_ref: Option<&str>, dest: &Path) -> Result<()> {
+    let dest_str = dest.to_string_lossy();
+    let mut args = vec!["clone", "--quiet", "--depth", "1"];
+    if let Some(git_ref) = git_ref {
+        args.extend(["--branch", git_ref]);
+    }
+    args.extend([url, &dest_str]);
+    let cloned = git(None, &args);
+
+    match (cloned, git_ref) {
+        (Ok(()), _) => Ok(()),
+        (Err(e), None) => Err(e),
+        (Err(_), Some(commit)) => {
+            if dest.exists() {
+                fs::remove_dir_all(dest)?;
+            }
+            fs::create_dir_all(dest)?;
+            git(Some(dest), &["init", "--quiet"])?;
+            git(Some(dest), &["fetch", "--quiet", "--depth", "1", url, commit])
+                .with_context(|| format!("No branch, tag or commit '{}' in {}", commit, url))?;
+            git(Some(dest), &["checkout", "--quiet", "FETCH_HEAD"])
+        }
+    }
+}
+
+fn git(dir: Option<&Path>, args: &[&str]) -> Result<()> {
+    let mut command = Command::new("git");
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let output = command
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        anyhow::bail!("git {} failed: {}", args[0], String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+fn git_output(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        anyhow::bail!("git {} failed: {}", args[0], String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Clear write permission on the checkout's files (directories stay
+/// writable so the checkout can be replaced or deleted)
+fn make_read_only(dir: &Path) {
+    for entry in WalkDir::new(dir).into_iter().flatten().filter(|e| e.file_type().is_file()) {
+        if let Ok(metadata) = entry.metadata() {
+            let mut permissions = metadata.permissions();
+            permissions.set_readonly(true);
+            let _ = fs::set_permissions(entry.path(), permissions);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_repo_name() {
+        assert_eq!(repo_name("https://github.com/org/lib"), "org/lib");
+        assert_eq!(repo_name("https://github.com/org/lib.git/"), "org/lib");
+        assert_eq!(repo_name("git@github.com:org/lib.git"), "org/lib");
+        assert_eq!(repo_name("/srv/git/lib"), "git/lib");
+    }
+
+    #[test]
+    fn test_add_and_remove_repo() {
+        let dir = TempDir::new().unwrap();
+        let origin = dir.path().join("origin");
+        fs::create_dir_all(&origin).unwrap();
+        fs::write(origin.join("lib.rs"), "pub fn parse() {}\n").unwrap();
+        for args in [
+            &["init", "--quiet"][..],
+            &["add", "."],
+            &["-c", "user.name=t", "-c", "user.email=t@t", "commit", "--quiet", "-m", "init"],
+            &["tag", "v1.0"],
+        ] {
+            git(Some(&origin), args).unwrap();
+        }
+
+        let mut store = RepoStore::load_from(dir.path().join("repos.json"), dir.path().join("cache")).unwrap();
+        let url = origin.to_string_lossy().to_string();
+        let repo = store.add(&url, Some("v1.0")).unwrap();
+        assert_eq!(repo.label(), format!("{}@v1.0", repo_name(&url)));
+        assert_eq!(repo.commit.len(), 40);
+        let checkout = store.checkout(&repo);
+        assert!(checkout.join("lib.rs").exists());
+        assert!(fs::metadata(checkout.join("lib.rs")).unwrap().permissions().readonly());
+        assert_eq!(store.checkouts(), vec![checkout.clone()]);
+
+        // Adding again replaces the checkout rather than listing it twice
+        store.add(&url, Some("v1.0")).unwrap();
+        let reloaded = RepoStore::load_from(dir.path().join("repos.json"), dir.path().join("cache")).unwrap();
+        assert_eq!(reloaded.repos().len(), 1);
+
+        assert!(store.add(&url, Some("no-such-ref")).is_err());
+        assert_eq!(store.remove(&repo.label()).unwrap().commit, repo.commit);
+        assert!(!checkout.exists());
+        assert!(store.remove(&repo.label()).is_err());
+    }
+}
@@ -4,15 +4,25 @@
 //! keyword matching and optional embedding-based semantic search.
 
 use anyhow::Result;
+use dashmap::DashMap;
 use glob::glob;
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
 use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
 use tracing::debug;
+use walkdir::WalkDir;
 
-use super::index::FileIndex;
+use super::assembly::{ContextAssembly, ContextSource};
+use super::index::{compute_hash, FileIndex};
 use super::manager::ContextConfig;
+use super::outline::FileOutline;
 use super::tokenizer::{count_tokens, Tokenizer};
+use crate::ignore_files::IgnoreRules;
 
 #[cfg(feature = "embeddings")]
 use super::embeddings::EmbeddingEngine;
@@ -32,8 +42,20 @@ pub struct SmartContextSelector {
     embedding_engine: Option<EmbeddingEngine>,
     /// Tokenizer for accurate counting
     tokenizer: Tokenizer,
+    /// Read-only directories searched after the project (remote repo
+    /// checkouts)
+    extra_roots: Vec<PathBuf>,
+    /// .gitignore/.quantignore rules of the project and each extra root
+    ignore_rules: Vec<IgnoreRules>,
 }
 
+/// Extensions of the files searched by content
+const CODE_EXTENSIONS: &[&str] = &["rs", "py", "ts", "js", "go", "java", "c", "cpp", "h"];
+
+/// Score multiplier for files outside the project, so the project's own
+/// files win ties
+const EXTRA_ROOT_WEIGHT: f32 = 0.5;
+
 impl SmartContextSelector {
     /// Create a new smart context selector
     pub fn new(project_root: PathBuf) -> Self {
@@ -48,6 +70,7 @@ impl SmartContextSelector {
         };
 
         Self {
+            ignore_rules: vec![IgnoreRules::for_dir(&project_root)],
             project_root,
             config: ContextConfig::default(),
             keywords: Vec::new(),
@@ -55,9 +78,18 @@ impl SmartContextSelector {
             #[cfg(feature = "embeddings")]
             embedding_engine,
             tokenizer: Tokenizer::default(),
+            extra_roots: Vec::new(),
         }
     }
 
+    /// Also search these read-only directories (remote repo checkouts)
+    pub fn with_extra_roots(mut self, roots: Vec<PathBuf>) -> Self {
+        self.ignore_rules.truncate(1);
+        self.ignore_rules.extend(roots.iter().map(|root| IgnoreRules::for_dir(root)));
+        self.extra_roots = roots;
+        self
+    }
+
     /// Set the max tokens for context
     pub fn with_max_tokens(mut self, tokens: usize) -> Self {
         self.config.max_tokens = tokens;
@@ -70,8 +102,9 @@ impl SmartContextSelector {
         self
     }
 
-    /// Analyze a query and select relevant files
-    pub fn select_context(&mut self, query: &str) -> Result<SmartContext> {
+    /// Analyze a query and select relevant files, skipping those `assembly`
+    /// already includes and recording the ones selected
+    pub fn select_context(&mut self, query: &str, assembly: &mut ContextAssembly) -> Result<SmartContext> {
         // Extract keywords from the query
         self.keywords = Self::extract_keywords(query);
         debug!(keywords = ?self.keywords, "Extracted keywords from query");
@@ -119,7 +152,12 @@ impl SmartContextSelector {
             }
 
             if let Ok(content) = fs::read_to_string(&path) {
+                if !assembly.is_new(&path, &content) {
+                    debug!(path = %path.display(), "Skipping file already in context");
+                    continue;
+                }
                 let file_tokens = self.tokenizer.count_tokens(&content);
+                let read_only = self.is_extra(&path);
 
                 // Check if we can fit this file
                 if current_tokens + file_tokens + 50 > max_tokens {
@@ -129,15 +167,16 @@ impl SmartContextSelector {
                         let truncated = self
                             .tokenizer
                             .truncate_to_tokens(&content, available_tokens.min(500));
-                        context.add_file(path.clone(), truncated, true);
-                        current_tokens += self
-                            .tokenizer
-                            .count_tokens(context.files.last().map(|f| f.content.as_str()).unwrap_or(""));
+                        let tokens = self.tokenizer.count_tokens(&truncated);
+                        assembly.add(&path, &content, ContextSource::Smart, tokens);
+                        context.push(path.clone(), truncated, true, read_only);
+                        current_tokens += tokens;
                     }
                     continue;
                 }
 
-                context.add_file(path, content, false);
+                assembly.add(&path, &content, ContextSource::Smart, file_tokens + 50);
+                context.push(path, content, false, read_only);
                 current_tokens += file_tokens + 50; // Account for headers
             }
         }
@@ -177,7 +216,7 @@ impl SmartContextSelector {
         // Extract words, filter stop words, and return unique keywords
         let mut keywords: Vec<String> = query
             .to_lowercase()
-            .split(|c: char| !c.is_alphanumeric() && c != '_' && c != '-')
+            .split(|c: char| !is_word_char(c))
             .filter(|s| s.len() >= 3 && !stop_words.contains(*s))
             .map(|s| s.to_string())
             .collect();
@@ -195,25 +234,24 @@ impl SmartContextSelector {
     fn find_files_by_name(&self) -> Result<HashMap<PathBuf, f32>> {
         let mut matches: HashMap<PathBuf, f32> = HashMap::new();
 
-        for keyword in &self.keywords {
+        for (root, keyword) in self.search_roots().flat_map(|root| self.keywords.iter().map(move |k| (root, k))) {
             let patterns = [
-                format!("{}/**/*{}*.rs", self.project_root.display(), keyword),
-                format!("{}/**/*{}*.py", self.project_root.display(), keyword),
-                format!("{}/**/*{}*.ts", self.project_root.display(), keyword),
-                format!("{}/**/*{}*.js", self.project_root.display(), keyword),
-                format!("{}/**/*{}*.go", self.project_root.display(), keyword),
-                format!("{}/**/*{}*.java", self.project_root.display(), keyword),
-                format!("{}/**/*{}*.toml", self.project_root.display(), keyword),
-                format!("{}/**/*{}*.yaml", self.project_root.display(), keyword),
-                format!("{}/**/*{}*.yml", self.project_root.display(), keyword),
-                format!("{}/**/*{}*.md", self.project_root.display(), keyword),
+                crate::paths::glob_under(root, &format!("**/*{}*.rs", keyword)),
+                crate::paths::glob_under(root, &format!("**/*{}*.py", keyword)),
+                crate::paths::glob_under(root, &format!("**/*{}*.ts", keyword)),
+                crate::paths::glob_under(root, &format!("**/*{}*.js", keyword)),
+                crate::paths::glob_under(root, &format!("**/*{}*.go", keyword)),
+                crate::paths::glob_under(root, &format!("**/*{}*.java", keyword)),
+                crate::paths::glob_under(root, &format!("**/*{}*.toml", keyword)),
+                crate::paths::glob_under(root, &format!("**/*{}*.yaml", keyword)),
+                crate::paths::glob_under(root, &format!("**/*{}*.yml", keyword)),
+                crate::paths::glob_under(root, &format!("**/*{}*.md", keyword)),
             ];
 
             for pattern in &patterns {
                 if let Ok(paths) = glob(pattern) {
                     for entry in paths.filter_map(|e| e.ok()) {
-                        let path_str = entry.to_string_lossy();
-                        if self.is_excluded(&path_str) {
+                        if self.is_excluded(&entry) {
                             continue;
                         }
 
@@ -243,57 +281,54 @@ impl SmartContextSelector {
         Ok(matches)
     }
 
-    /// Find files containing keywords in their content
+    /// Find files containing keywords in their content, scanning each
+    /// file once for all keywords and in parallel
     fn find_files_by_content(&self) -> Result<HashMap<PathBuf, f32>> {
-        let mut matches: HashMap<PathBuf, f32> = HashMap::new();
+        if self.keywords.is_empty() {
+            return Ok(HashMap::new());
+        }
 
-        for keyword in &self.keywords {
-            let code_extensions = ["rs", "py", "ts", "js", "go", "java", "c", "cpp", "h"];
+        let keywords = &self.keywords;
+        let matches = self
+            .code_files()
+            .into_par_iter()
+            .filter_map(|path| {
+                let scan = scan_file(&path)?;
+                let score: f32 = keywords.iter().map(|keyword| scan.score(keyword)).sum();
+                (score > 0.0).then_some((path, score))
+            })
+            .collect();
 
-            for ext in &code_extensions {
-                let pattern = format!("{}/**/*.{}", self.project_root.display(), ext);
-                if let Ok(paths) = glob(&pattern) {
-                    for entry in paths.filter_map(|e| e.ok()) {
-                        let path_str = entry.to_string_lossy();
-                        if self.is_excluded(&path_str) {
-                            continue;
-                        }
+        Ok(matches)
+    }
 
-                        if let Ok(content) = fs::read_to_string(&entry) {
-                            let content_lower = content.to_lowercase();
-
-                            let count = content_lower.matches(keyword).count();
-                            if count > 0 {
-                                let base_score = (count as f32).sqrt();
-
-                                let def_patterns = [
-                                    format!("fn {}", keyword),
-                                    format!("def {}", keyword),
-                                    format!("function {}", keyword),
-                                    format!("class {}", keyword),
-                                    format!("struct {}", keyword),
-                                    format!("enum {}", keyword),
-                                    format!("trait {}", keyword),
-                                    format!("impl {}", keyword),
-                                    format!("type {}", keyword),
-                                    format!("const {}", keyword),
-                                ];
-
-                                let def_bonus: f32 = def_patterns
-                                    .iter()
-                                    .filter(|p| content_lower.contains(*p))
-                                    .count() as f32
-                                    * 3.0;
-
-                                *matches.entry(entry).or_insert(0.0) += base_score + def_bonus;
-                            }
-                        }
-                    }
+    /// Source files under the search roots that aren't excluded
+    fn code_files(&self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for root in self.search_roots() {
+            let entries = WalkDir::new(root)
+                .follow_links(true)
+                .into_iter()
+                .filter_entry(|e| {
+                    e.depth() == 0
+                        || !e.file_type().is_dir()
+                        || !crate::paths::IGNORED_DIRS.iter().any(|d| e.file_name() == OsStr::new(d))
+                })
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file());
+
+            for entry in entries {
+                let path = entry.into_path();
+                let is_code = path
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| CODE_EXTENSIONS.contains(&e));
+                if is_code && !self.is_excluded(&path) {
+                    files.push(path);
                 }
             }
         }
-
-        Ok(matches)
+        files
     }
 
     /// Find files using semantic search (embedding similarity)
@@ -326,22 +361,27 @@ impl SmartContextSelector {
         Ok(HashMap::new())
     }
 
-    /// Check if a path should be excluded
-    fn is_excluded(&self, path: &str) -> bool {
-        let excludes = [
-            "/target/",
-            "/node_modules/",
-            "/.git/",
-            "/dist/",
-            "/build/",
-            "/__pycache__/",
-            "/venv/",
-            "/.venv/",
-            "/vendor/",
-            "/.idea/",
-            "/.vscode/",
-        ];
-        excludes.iter().any(|e| path.contains(e))
+    /// The project root followed by the extra roots
+    fn search_roots(&self) -> impl Iterator<Item = &PathBuf> {
+        std::iter::once(&self.project_root).chain(&self.extra_roots)
+    }
+
+    /// Whether a path comes from one of the extra (read-only) roots
+    fn is_extra(&self, path: &Path) -> bool {
+        self.extra_roots.iter().any(|root| path.starts_with(root))
+    }
+
+    /// Check if a path is in a build, dependency or editor directory of
+    /// the root it was found under, or ignored by its ignore files
+    fn is_excluded(&self, path: &Path) -> bool {
+        let Some((relative, rules)) = self
+            .search_roots()
+            .zip(&self.ignore_rules)
+            .find_map(|(root, rules)| Some((path.strip_prefix(root).ok()?, rules)))
+        else {
+            return crate::paths::in_dir_named(path, crate::paths::IGNORED_DIRS);
+        };
+        crate::paths::in_dir_named(relative, crate::paths::IGNORED_DIRS) || rules.is_ignored(path, false)
     }
 
     /// Rank files by combining name, content, and semantic match scores
@@ -368,6 +408,12 @@ impl SmartContextSelector {
             *combined.entry(path).or_insert(0.0) += score;
         }
 
+        for (path, score) in combined.iter_mut() {
+            if self.is_extra(path) {
+                *score *= EXTRA_ROOT_WEIGHT;
+            }
+        }
+
         // Convert to vec and sort by score descending
         let mut ranked: Vec<(PathBuf, f32)> = combined.into_iter().collect();
         ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
@@ -384,11 +430,10 @@ impl SmartContextSelector {
             let mut count = 0;
 
             for ext in &code_extensions {
-                let pattern = format!("{}/**/*.{}", self.project_root.display(), ext);
+                let pattern = crate::paths::glob_under(&self.project_root, &format!("**/*.{}", ext));
                 if let Ok(paths) = glob(&pattern) {
                     for entry in paths.filter_map(|e| e.ok()) {
-                        let path_str = entry.to_string_lossy();
-                        if !self.is_excluded(&path_str) {
+                        if !self.is_excluded(&entry) {
                             index.get(&entry);
                             count += 1;
                         }
@@ -404,6 +449,103 @@ impl SmartContextSelector {
     }
 }
 
+/// Whether `c` can be part of a keyword
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+/// What content search needs from a file
+#[derive(Debug)]
+struct FileScan {
+    /// Lowercased words and how often each occurs
+    words: HashMap<String, usize>,
+    outline: Option<FileOutline>,
+}
+
+impl FileScan {
+    fn new(path: &Path, content: &str) -> Self {
+        let mut words: HashMap<String, usize> = HashMap::new();
+        for word in content.to_lowercase().split(|c: char| !is_word_char(c)) {
+            if !word.is_empty() {
+                *words.entry(word.to_string()).or_insert(0) += 1;
+            }
+        }
+        Self {
+            words,
+            outline: FileOutline::from_content(path, content),
+        }
+    }
+
+    /// Occurrences of `keyword` in the lowercased content. Keywords are
+    /// made of word characters, so no occurrence spans two words.
+    fn count(&self, keyword: &str) -> usize {
+        self.words
+            .iter()
+            .map(|(word, n)| word.matches(keyword).count() * n)
+            .sum()
+    }
+
+    /// Content match score for one keyword
+    fn score(&self, keyword: &str) -> f32 {
+        let count = self.count(keyword);
+        if count == 0 {
+            return 0.0;
+        }
+
+        // Files that declare a matching symbol rank above files that only mention it
+        let def_bonus = match self.outline.as_ref().map(|o| o.match_strength(keyword)) {
+            Some(2) => 6.0,
+            Some(1) => 2.0,
+            _ => 0.0,
+        };
+        (count as f32).sqrt() + def_bonus
+    }
+}
+
+/// A scan with what the file looked like when it was made
+#[derive(Clone)]
+struct CachedScan {
+    len: u64,
+    modified: Option<SystemTime>,
+    content_hash: String,
+    scan: Arc<FileScan>,
+}
+
+/// Scans of the files searched so far, kept across queries
+static SCAN_CACHE: Lazy<DashMap<PathBuf, CachedScan>> = Lazy::new(DashMap::new);
+
+/// Scan a file, reusing the last scan if its size and modification time
+/// are unchanged, or if its content hash is
+fn scan_file(path: &Path) -> Option<Arc<FileScan>> {
+    let metadata = fs::metadata(path).ok()?;
+    let len = metadata.len();
+    let modified = metadata.modified().ok();
+
+    let cached = SCAN_CACHE.get(path).map(|c| c.clone());
+    if let Some(ref cached) = cached {
+        if cached.len == len && cached.modified == modified {
+            return Some(Arc::clone(&cached.scan));
+        }
+    }
+
+    let content = fs::read_to_string(path).ok()?;
+    let content_hash = compute_hash(&content);
+    let scan = match cached {
+        Some(cached) if cached.content_hash == content_hash => cached.scan,
+        _ => Arc::new(FileScan::new(path, &content)),
+    };
+    SCAN_CACHE.insert(
+        path.to_path_buf(),
+        CachedScan {
+            len,
+            modified,
+            content_hash,
+            scan: Arc::clone(&scan),
+        },
+    );
+    Some(scan)
+}
+
 /// Container for smart context results
 #[derive(Debug, Clone)]
 pub struct SmartContext {
@@ -417,10 +559,15 @@ impl SmartContext {
     }
 
     pub fn add_file(&mut self, path: PathBuf, content: String, truncated: bool) {
+        self.push(path, content, truncated, false);
+    }
+
+    fn push(&mut self, path: PathBuf, content: String, truncated: bool, read_only: bool) {
         self.files.push(SmartContextFile {
             path,
             content,
             truncated,
+            read_only,
         });
     }
 
@@ -439,7 +586,11 @@ impl SmartContext {
 
         for file in &self.files {
             let rel_path = file.path.to_string_lossy();
-            context.push_str(&format!("### {}\n\n", rel_path));
+            if file.read_only {
+                context.push_str(&format!("### {} (read-only, from a remote repo)\n\n", rel_path));
+            } else {
+                context.push_str(&format!("### {}\n\n", rel_path));
+            }
             context.push_str("```\n");
             context.push_str(&file.content);
             if file.truncated {
@@ -477,6 +628,8 @@ pub struct SmartContextFile {
     pub path: PathBuf,
     pub content: String,
     pub truncated: bool,
+    /// From a remote repo checkout rather than the project
+    pub read_only: bool,
 }
 
 #[cfg(test)]
@@ -502,6 +655,42 @@ mod tests {
         assert!(keywords.contains(&"tool_router".to_string()));
     }
 
+    #[test]
+    fn test_extra_roots_are_searched_read_only() {
+        let project = tempfile::TempDir::new().unwrap();
+        let repo = tempfile::TempDir::new().unwrap();
+        fs::write(project.path().join("main.rs"), "fn main() { tokenizer::run(); }\n").unwrap();
+        fs::write(repo.path().join("tokenizer.rs"), "pub fn run() {}\n").unwrap();
+
+        let mut selector = SmartContextSelector::new(project.path().to_path_buf())
+            .with_extra_roots(vec![repo.path().to_path_buf()]);
+        let context = selector.select_context("how does tokenizer work", &mut ContextAssembly::new()).unwrap();
+
+        let from_repo = context.files.iter().find(|f| f.path.starts_with(repo.path())).unwrap();
+        assert!(from_repo.read_only);
+        assert!(context.files.iter().any(|f| !f.read_only));
+        assert!(context.to_context_string().contains("(read-only, from a remote repo)"));
+    }
+
+    #[test]
+    fn test_content_scan() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("store.rs");
+        fs::write(&path, "pub struct SessionStore;\n// sessions, session-store and session_id\n").unwrap();
+
+        let scan = scan_file(&path).unwrap();
+        assert_eq!(scan.count("session"), 4);
+        assert_eq!(scan.count("session-store"), 1);
+        assert_eq!(scan.count("missing"), 0);
+        // Declares a symbol containing the keyword
+        assert_eq!(scan.score("session"), 2.0 + 2.0);
+
+        // Unchanged files reuse their scan; changed ones are scanned again
+        assert!(Arc::ptr_eq(&scan, &scan_file(&path).unwrap()));
+        fs::write(&path, "fn unrelated() {}\n").unwrap();
+        assert_eq!(scan_file(&path).unwrap().count("session"), 0);
+    }
+
     #[test]
     fn test_smart_context_empty() {
         let ctx = SmartContext::new();
@@ -1,19 +1,147 @@
 //! Proper tokenization using tiktoken
 //!
 //! Replaces the rough "4 chars per token" estimate with actual tokenization.
+//!
+//! Local models with a tokenizer file of their own (a GGUF or
+//! `tokenizer.json`, see [`super::model_tokenizer`]) are counted with it
+//! instead of the cl100k_base approximation.
+//!
+//! Counts for large texts (system prompts, QUANT.md, context files) are cached
+//! by content hash and persisted to the cache directory, so they are not
+//! re-tokenized on every agent iteration or every run.
 
+use anyhow::{Context, Result};
+use dashmap::DashMap;
 use once_cell::sync::Lazy;
 use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use tiktoken_rs::{cl100k_base, CoreBPE};
+use tracing::{debug, warn};
+
+use super::model_tokenizer::{self, ModelTokenizer};
 
 /// Default fallback estimate when tokenizer unavailable
 const FALLBACK_CHARS_PER_TOKEN: usize = 4;
 
+/// Texts shorter than this are tokenized directly; hashing them isn't worth it
+const CACHE_MIN_BYTES: usize = 512;
+
+/// Maximum token counts kept on disk (most recently used win)
+const CACHE_MAX_ENTRIES: usize = 4096;
+
 /// Global tokenizer (lazy initialized)
 static CL100K_TOKENIZER: Lazy<Mutex<Option<CoreBPE>>> = Lazy::new(|| {
     Mutex::new(cl100k_base().ok())
 });
 
+/// Global token count cache, loaded from disk on first use
+static TOKEN_CACHE: Lazy<TokenCountCache> = Lazy::new(|| match TokenCountCache::default_path() {
+    Some(path) => TokenCountCache::load(&path),
+    None => TokenCountCache::default(),
+});
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct CacheEntry {
+    tokens: usize,
+    /// Unix seconds of last lookup, used to evict old entries on save
+    last_used: u64,
+}
+
+/// Token counts keyed by SHA-256 of the text
+#[derive(Default)]
+pub struct TokenCountCache {
+    entries: DashMap<[u8; 32], CacheEntry>,
+    dirty: AtomicBool,
+}
+
+impl TokenCountCache {
+    /// Default location in the cache directory
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::cache_dir().map(|d| d.join("quant").join("token_counts.bin"))
+    }
+
+    /// Load a cache file, starting empty if it is missing or unreadable
+    pub fn load(path: &Path) -> Self {
+        let cache = Self::default();
+        let Ok(data) = std::fs::read(path) else {
+            return cache;
+        };
+        match bincode::deserialize::<Vec<([u8; 32], CacheEntry)>>(&data) {
+            Ok(entries) => {
+                debug!(entries = entries.len(), "Loaded token count cache");
+                for (key, entry) in entries {
+                    cache.entries.insert(key, entry);
+                }
+            }
+            Err(e) => warn!(error = %e, "Failed to deserialize token count cache"),
+        }
+        cache
+    }
+
+    /// Write the cache to disk if it changed, keeping the most recent entries
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if !self.dirty.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let mut entries: Vec<([u8; 32], CacheEntry)> = self.entries.iter().map(|e| (*e.key(), *e.value())).collect();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.1.last_used));
+        entries.truncate(CACHE_MAX_ENTRIES);
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let data = bincode::serialize(&entries).context("Failed to serialize token count cache")?;
+        std::fs::write(path, data)?;
+        debug!(entries = entries.len(), "Saved token count cache");
+        Ok(())
+    }
+
+    fn key(tokenizer_type: TokenizerType, model: Option<&Path>, text: &str) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update([tokenizer_type as u8]);
+        if let Some(model) = model {
+            hasher.update(model.as_os_str().as_encoded_bytes());
+        }
+        hasher.update(text.as_bytes());
+        hasher.finalize().into()
+    }
+
+    fn get_or_insert_with(
+        &self,
+        tokenizer_type: TokenizerType,
+        model: Option<&Path>,
+        text: &str,
+        count: impl FnOnce() -> usize,
+    ) -> usize {
+        let key = Self::key(tokenizer_type, model, text);
+        let now = chrono::Utc::now().timestamp() as u64;
+        if let Some(mut entry) = self.entries.get_mut(&key) {
+            // Only mark dirty when the recency change is worth persisting
+            if now.saturating_sub(entry.last_used) > 3600 {
+                self.dirty.store(true, Ordering::SeqCst);
+            }
+            entry.last_used = now;
+            return entry.tokens;
+        }
+        let tokens = count();
+        self.entries.insert(key, CacheEntry { tokens, last_used: now });
+        self.dirty.store(true, Ordering::SeqCst);
+        tokens
+    }
+}
+
+/// Persist the global token count cache
+pub fn save_token_cache() -> Result<()> {
+    match TokenCountCache::default_path() {
+        Some(path) => TOKEN_CACHE.save(&path),
+        None => Ok(()),
+    }
+}
+
 /// Tokenizer type for different models
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TokenizerType {
@@ -56,6 +184,8 @@ impl TokenizerType {
 /// Tokenizer for counting tokens in text
 pub struct Tokenizer {
     tokenizer_type: TokenizerType,
+    /// The model's own tokenizer, used instead when found
+    model: Option<Arc<ModelTokenizer>>,
 }
 
 impl Tokenizer {
@@ -63,16 +193,40 @@ impl Tokenizer {
     pub fn new(model: &str) -> Self {
         Self {
             tokenizer_type: TokenizerType::from_model_name(model),
+            model: model_tokenizer::for_model(model),
         }
     }
 
     /// Create a tokenizer with a specific type
     pub fn with_type(tokenizer_type: TokenizerType) -> Self {
-        Self { tokenizer_type }
+        Self {
+            tokenizer_type,
+            model: None,
+        }
+    }
+
+    /// The model tokenizer file in use, if any
+    pub fn model_source(&self) -> Option<&Path> {
+        self.model.as_deref().map(ModelTokenizer::source)
     }
 
     /// Count tokens in the given text
+    ///
+    /// Large texts are looked up in the token count cache first.
     pub fn count_tokens(&self, text: &str) -> usize {
+        let cached = self.model.is_some() || self.tokenizer_type == TokenizerType::Cl100kBase;
+        if cached && text.len() >= CACHE_MIN_BYTES {
+            return TOKEN_CACHE.get_or_insert_with(self.tokenizer_type, self.model_source(), text, || {
+                self.count_tokens_uncached(text)
+            });
+        }
+        self.count_tokens_uncached(text)
+    }
+
+    fn count_tokens_uncached(&self, text: &str) -> usize {
+        if let Some(count) = self.model.as_ref().and_then(|model| model.count(text)) {
+            return count;
+        }
         match self.tokenizer_type {
             TokenizerType::Cl100kBase => {
                 let guard = CL100K_TOKENIZER.lock();
@@ -91,6 +245,9 @@ impl Tokenizer {
 
     /// Truncate text to fit within a token limit
     pub fn truncate_to_tokens(&self, text: &str, max_tokens: usize) -> String {
+        if let Some(truncated) = self.model.as_ref().and_then(|model| model.truncate(text, max_tokens)) {
+            return truncated;
+        }
         match self.tokenizer_type {
             TokenizerType::Cl100kBase => {
                 let guard = CL100K_TOKENIZER.lock();
@@ -215,6 +372,43 @@ mod tests {
         assert_eq!(count, 2);
     }
 
+    #[test]
+    fn test_token_count_cache() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("tokens.bin");
+        let text = "The quick brown fox. ".repeat(50);
+
+        let cache = TokenCountCache::load(&path);
+        let mut calls = 0;
+        let first = cache.get_or_insert_with(TokenizerType::Cl100kBase, None, &text, || {
+            calls += 1;
+            42
+        });
+        let second = cache.get_or_insert_with(TokenizerType::Cl100kBase, None, &text, || {
+            calls += 1;
+            0
+        });
+        assert_eq!((first, second, calls), (42, 42, 1));
+        cache.save(&path).unwrap();
+
+        let reloaded = TokenCountCache::load(&path);
+        assert_eq!(reloaded.entries.len(), 1);
+        assert_eq!(reloaded.get_or_insert_with(TokenizerType::Cl100kBase, None, &text, || 0), 42);
+        // Different tokenizer types don't share entries
+        assert_eq!(reloaded.get_or_insert_with(TokenizerType::Fallback, None, &text, || 7), 7);
+
+        std::fs::write(&path, b"corrupt").unwrap();
+        assert!(TokenCountCache::load(&path).entries.is_empty());
+    }
+
+    #[test]
+    fn test_cached_count_matches_uncached() {
+        let tokenizer = Tokenizer::default();
+        let text = "fn main() { println!(\"hello\"); }\n".repeat(40);
+        assert_eq!(tokenizer.count_tokens(&text), tokenizer.count_tokens_uncached(&text));
+        assert_eq!(tokenizer.count_tokens(&text), tokenizer.count_tokens_uncached(&text));
+    }
+
     #[test]
     fn test_global_functions() {
         let text = "Test text";
@@ -6,11 +6,15 @@
 
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
-use llm_core::{ChatMessage, Role};
+use llm_core::{ChatMessage, ChatOptions, Role};
+use rustyline::history::{FileHistory, History};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::config::ModelOptions;
+use crate::storage::Vault;
+
 /// A saved conversation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Conversation {
@@ -29,6 +33,29 @@ pub struct Conversation {
     pub created_at: DateTime<Utc>,
     /// Last update timestamp
     pub updated_at: DateTime<Utc>,
+    /// Agent sessions launched from this conversation via `/task`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub linked_sessions: Vec<String>,
+    /// Whether the title was written by a model rather than cut from the
+    /// first message
+    #[serde(default)]
+    pub title_generated: bool,
+    /// Token count of each message, in step with `messages` (recounted
+    /// when it isn't, e.g. for conversations saved before it existed)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub message_tokens: Vec<usize>,
+    /// One-sentence summary written by a model when the conversation is saved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// Keywords describing the conversation (generated with the summary)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Number of messages when the summary was written
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub summarized_messages: usize,
+    /// Sampling options set with `/set`
+    #[serde(default, skip_serializing_if = "Sampling::is_empty")]
+    pub sampling: Sampling,
 }
 
 impl Conversation {
@@ -45,18 +72,28 @@ impl Conversation {
             messages: Vec::new(),
             created_at: now,
             updated_at: now,
+            linked_sessions: Vec::new(),
+            title_generated: false,
+            message_tokens: Vec::new(),
+            summary: None,
+            tags: Vec::new(),
+            summarized_messages: 0,
+            sampling: Sampling::default(),
         }
     }
 
     /// Add a message to the conversation
     pub fn add_message(&mut self, message: ChatMessage) {
+        if self.message_tokens.len() == self.messages.len() {
+            self.message_tokens.push(message_tokens(&message, &self.model));
+        }
         self.messages.push(message);
         self.updated_at = Utc::now();
 
         // Update title from first user message
         if self.title == "New conversation" {
             if let Some(first_user) = self.messages.iter().find(|m| m.role == Role::User) {
-                self.title = truncate_title(&first_user.content);
+                self.title = truncate_title(request_text(&first_user.content));
             }
         }
     }
@@ -76,6 +113,167 @@ impl Conversation {
     /// Clear conversation messages
     pub fn clear(&mut self) {
         self.messages.clear();
+        self.message_tokens.clear();
+        self.updated_at = Utc::now();
+    }
+
+    /// Tokens the conversation takes up in the model's context: the system
+    /// prompt plus every message
+    pub fn context_tokens(&mut self) -> usize {
+        if self.message_tokens.len() != self.messages.len() {
+            self.message_tokens = self.messages.iter().map(|m| message_tokens(m, &self.model)).collect();
+        }
+        let system = self
+            .system_prompt
+            .as_deref()
+            .map_or(0, |sys| message_tokens(&ChatMessage::system(sys), &self.model));
+        system + self.message_tokens.iter().sum::<usize>()
+    }
+
+    /// Messages asking a model to summarize all but the last `keep`
+    /// messages, or None if there is nothing older to summarize
+    pub fn compact_request(&self, keep: usize) -> Option<Vec<ChatMessage>> {
+        let end = self.messages.len().checked_sub(keep).filter(|&end| end > 0)?;
+        let transcript: Vec<String> = self.messages[..end]
+            .iter()
+            .map(|m| format!("{}: {}", format!("{:?}", m.role).to_lowercase(), m.content.trim()))
+            .collect();
+
+        Some(vec![
+            ChatMessage::system(COMPACT_PROMPT),
+            ChatMessage::user(transcript.join("\n\n")),
+        ])
+    }
+
+    /// Replace all but the last `keep` messages with a model's reply to
+    /// [`Conversation::compact_request`]; returns how many were replaced
+    pub fn compact(&mut self, summary: &str, keep: usize) -> usize {
+        let end = self.messages.len().saturating_sub(keep);
+        if end == 0 || summary.trim().is_empty() {
+            return 0;
+        }
+
+        let summary = ChatMessage::user(format!("{}\n{}", COMPACT_HEADER, summary.trim()));
+        self.messages.splice(..end, [summary]);
+        self.message_tokens.clear();
+        self.context_tokens();
+        self.updated_at = Utc::now();
+        end
+    }
+
+    /// Build an agent task from the last user request plus up to `history`
+    /// earlier messages, or from `request` when given explicitly
+    pub fn task_prompt(&self, history: usize, request: Option<&str>) -> Option<String> {
+        let (request, prior) = match request {
+            Some(r) => (r.to_string(), &self.messages[..]),
+            None => {
+                let idx = self.messages.iter().rposition(|m| m.role == Role::User)?;
+                (self.messages[idx].content.clone(), &self.messages[..idx])
+            }
+        };
+
+        let prior: Vec<&ChatMessage> = prior.iter().filter(|m| m.role != Role::System).collect();
+        let start = prior.len().saturating_sub(history);
+
+        let mut prompt = request;
+        if start < prior.len() {
+            prompt.push_str("\n\n## Earlier conversation\n");
+            for msg in &prior[start..] {
+                let role = format!("{:?}", msg.role).to_lowercase();
+                let content: String = msg.content.chars().take(TASK_HISTORY_MAX_CHARS).collect();
+                let ellipsis = if content.len() < msg.content.len() { "..." } else { "" };
+                prompt.push_str(&format!("\n{}: {}{}\n", role, content.trim(), ellipsis));
+            }
+        }
+        Some(prompt)
+    }
+
+    /// Messages asking a model to title the conversation from its first
+    /// exchange, once there is one and no title has been generated yet
+    pub fn title_request(&self) -> Option<Vec<ChatMessage>> {
+        if self.title_generated {
+            return None;
+        }
+        let user = self.messages.iter().find(|m| m.role == Role::User)?;
+        let assistant = self.messages.iter().find(|m| m.role == Role::Assistant)?;
+        let excerpt = |text: &str| text.chars().take(TITLE_EXCERPT_CHARS).collect::<String>();
+
+        Some(vec![
+            ChatMessage::system(TITLE_PROMPT),
+            ChatMessage::user(format!(
+                "User: {}\n\nAssistant: {}",
+                excerpt(request_text(&user.content)),
+                excerpt(&assistant.content)
+            )),
+        ])
+    }
+
+    /// Use a model's reply to [`Conversation::title_request`] as the title
+    ///
+    /// Keeps the current title and returns false if the reply has no usable
+    /// text.
+    pub fn set_generated_title(&mut self, reply: &str) -> bool {
+        let Some(line) = reply.lines().map(str::trim).find(|l| !l.is_empty()) else {
+            return false;
+        };
+        let line = line
+            .strip_prefix("Title:")
+            .or_else(|| line.strip_prefix("title:"))
+            .unwrap_or(line);
+        let title = truncate_title(line.trim_matches(|c: char| c.is_whitespace() || "\"'`*#.".contains(c)));
+        if title.is_empty() {
+            return false;
+        }
+
+        self.title = title;
+        self.title_generated = true;
+        self.updated_at = Utc::now();
+        true
+    }
+
+    /// Messages asking a model to summarize the conversation, if it has an
+    /// answer and has changed since it was last summarized
+    pub fn summary_request(&self) -> Option<Vec<ChatMessage>> {
+        if self.summarized_messages == self.messages.len() || !self.messages.iter().any(|m| m.role == Role::Assistant) {
+            return None;
+        }
+
+        // The most recent messages, up to a budget, in order
+        let mut budget = SUMMARY_EXCERPT_CHARS;
+        let mut excerpt = Vec::new();
+        for message in self.messages.iter().rev().filter(|m| matches!(m.role, Role::User | Role::Assistant)) {
+            if budget == 0 {
+                break;
+            }
+            let text: String = request_text(&message.content).chars().take(budget.min(SUMMARY_MESSAGE_CHARS)).collect();
+            budget = budget.saturating_sub(text.chars().count());
+            let role = if message.role == Role::User { "User" } else { "Assistant" };
+            excerpt.push(format!("{}: {}", role, text));
+        }
+        excerpt.reverse();
+
+        Some(vec![
+            ChatMessage::system(SUMMARY_PROMPT),
+            ChatMessage::user(format!("Title: {}\n\n{}", self.title, excerpt.join("\n\n"))),
+        ])
+    }
+
+    /// Use a model's reply to [`Conversation::summary_request`] as the summary and tags
+    ///
+    /// Returns false (and changes nothing) if the reply has no usable text.
+    pub fn set_generated_summary(&mut self, reply: &str) -> bool {
+        let Some((summary, tags)) = crate::session::parse_summary_reply(reply) else {
+            return false;
+        };
+        self.summary = Some(summary);
+        self.tags = tags;
+        self.summarized_messages = self.messages.len();
+        true
+    }
+
+    /// Record an agent session launched from this conversation
+    pub fn link_session(&mut self, session_id: impl Into<String>) {
+        self.linked_sessions.push(session_id.into());
         self.updated_at = Utc::now();
     }
 
@@ -90,15 +288,238 @@ impl Conversation {
     }
 }
 
+/// Tokens each message adds beyond its content (role and delimiters)
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+/// First line of the message holding a compacted conversation
+const COMPACT_HEADER: &str = "[Summary of the earlier conversation]";
+
+const COMPACT_PROMPT: &str = "Summarize the conversation below so it can continue without it. \
+Keep decisions, facts, names, code identifiers and open questions; drop pleasantries and repetition. \
+Reply with the summary only.";
+
+/// Tokens a message takes up in the context
+fn message_tokens(message: &ChatMessage, model: &str) -> usize {
+    crate::context::count_tokens_for_model(&message.content, model) + MESSAGE_OVERHEAD_TOKENS
+}
+
+/// Per-message character cap for history included in `/task` prompts
+const TASK_HISTORY_MAX_CHARS: usize = 2000;
+
+/// Characters of each message shown to the model when generating a title
+const TITLE_EXCERPT_CHARS: usize = 1000;
+
+const TITLE_PROMPT: &str = "Write a short title (at most 6 words) for the conversation below. \
+Reply with the title only, without quotes or punctuation at the end.";
+
+/// Characters of the conversation shown to the model when summarizing it
+const SUMMARY_EXCERPT_CHARS: usize = 3000;
+
+/// Characters of each message shown to the model when summarizing
+const SUMMARY_MESSAGE_CHARS: usize = 800;
+
+const SUMMARY_PROMPT: &str = "Summarize the conversation below: one sentence saying what it was about \
+and what was concluded, and 3 to 5 short lowercase keywords for finding it later. \
+Reply with JSON only: {\"summary\": \"...\", \"tags\": [\"...\"]}";
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+/// Sampling options of a conversation, set with `/set <name> <value>`;
+/// unset ones come from the model alias, the config or the model
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Sampling {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_p: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeat_penalty: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_ctx: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_predict: Option<i32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+}
+
+impl Sampling {
+    /// Names `/set` accepts
+    pub const NAMES: &'static [&'static str] = &[
+        "temperature",
+        "top_p",
+        "top_k",
+        "min_p",
+        "repeat_penalty",
+        "presence_penalty",
+        "frequency_penalty",
+        "seed",
+        "num_ctx",
+        "num_predict",
+        "stop",
+    ];
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Set an option from its text value; an empty value unsets it
+    pub fn set(&mut self, name: &str, value: &str) -> Result<()> {
+        let value = value.trim();
+        let mut next = self.clone();
+        match name {
+            "temperature" => next.temperature = parse_value(value)?,
+            "top_p" => next.top_p = parse_value(value)?,
+            "top_k" => next.top_k = parse_value(value)?,
+            "min_p" => next.min_p = parse_value(value)?,
+            "repeat_penalty" => next.repeat_penalty = parse_value(value)?,
+            "presence_penalty" => next.presence_penalty = parse_value(value)?,
+            "frequency_penalty" => next.frequency_penalty = parse_value(value)?,
+            "seed" => next.seed = parse_value(value)?,
+            "num_ctx" => next.num_ctx = parse_value(value)?,
+            "num_predict" | "max_tokens" => next.num_predict = parse_value(value)?,
+            "stop" => next.stop = parse_stop(value)?,
+            _ => anyhow::bail!("Unknown option `{}`; one of: {}", name, Self::NAMES.join(", ")),
+        }
+        let mut options = ChatOptions::default();
+        next.apply(&mut options);
+        options.validate()?;
+        *self = next;
+        Ok(())
+    }
+
+    /// Each option with its value, if set
+    pub fn values(&self) -> Vec<(&'static str, Option<String>)> {
+        let stop = (!self.stop.is_empty())
+            .then(|| self.stop.iter().map(|s| format!("{:?}", s)).collect::<Vec<_>>().join(" "));
+        vec![
+            ("temperature", self.temperature.map(|v| v.to_string())),
+            ("top_p", self.top_p.map(|v| v.to_string())),
+            ("top_k", self.top_k.map(|v| v.to_string())),
+            ("min_p", self.min_p.map(|v| v.to_string())),
+            ("repeat_penalty", self.repeat_penalty.map(|v| v.to_string())),
+            ("presence_penalty", self.presence_penalty.map(|v| v.to_string())),
+            ("frequency_penalty", self.frequency_penalty.map(|v| v.to_string())),
+            ("seed", self.seed.map(|v| v.to_string())),
+            ("num_ctx", self.num_ctx.map(|v| v.to_string())),
+            ("num_predict", self.num_predict.map(|v| v.to_string())),
+            ("stop", stop),
+        ]
+    }
+
+    /// These options over `defaults` (the model alias's), for an agent
+    pub fn over(&self, defaults: &ModelOptions) -> ModelOptions {
+        let options = ModelOptions {
+            temperature: self.temperature,
+            top_p: self.top_p,
+            top_k: self.top_k,
+            min_p: self.min_p,
+            repeat_penalty: self.repeat_penalty,
+            presence_penalty: self.presence_penalty,
+            frequency_penalty: self.frequency_penalty,
+            seed: self.seed,
+            num_ctx: self.num_ctx,
+            num_predict: self.num_predict,
+            stop: self.stop.clone(),
+            ..Default::default()
+        };
+        options.or(defaults)
+    }
+
+    /// Override `options` with the options that are set
+    pub fn apply(&self, options: &mut ChatOptions) {
+        options.temperature = self.temperature.or(options.temperature);
+        options.top_p = self.top_p.or(options.top_p);
+        options.top_k = self.top_k.or(options.top_k);
+        options.min_p = self.min_p.or(options.min_p);
+        options.repeat_penalty = self.repeat_penalty.or(options.repeat_penalty);
+        options.presence_penalty = self.presence_penalty.or(options.presence_penalty);
+        options.frequency_penalty = self.frequency_penalty.or(options.frequency_penalty);
+        options.seed = self.seed.or(options.seed);
+        options.num_ctx = self.num_ctx.or(options.num_ctx);
+        options.num_predict = self.num_predict.or(options.num_predict);
+        if !self.stop.is_empty() {
+            options.stop = Some(self.stop.clone());
+        }
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(value: &str) -> Result<Option<T>> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value
+        .parse()
+        .map(Some)
+        .map_err(|_| anyhow::anyhow!("Invalid value `{}`", value))
+}
+
+/// Stop sequences: quoted strings or bare words, with `\n` and `\t` escapes
+fn parse_stop(value: &str) -> Result<Vec<String>> {
+    let mut stops = Vec::new();
+    let mut chars = value.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let quote = if c == '"' || c == '\'' { chars.next() } else { None };
+        let mut stop = String::new();
+        let mut closed = quote.is_none();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some('n') => stop.push('\n'),
+                    Some('t') => stop.push('\t'),
+                    Some(other) => stop.push(other),
+                    None => stop.push('\\'),
+                },
+                c if Some(c) == quote => {
+                    closed = true;
+                    break;
+                }
+                c if quote.is_none() && c.is_whitespace() => break,
+                c => stop.push(c),
+            }
+        }
+        if !closed {
+            anyhow::bail!("Unclosed quote in stop sequence");
+        }
+        if stop.is_empty() {
+            anyhow::bail!("Empty stop sequence");
+        }
+        stops.push(stop);
+    }
+    Ok(stops)
+}
+
 /// Manages conversation storage
 pub struct ConversationStore {
     /// Directory where conversations are stored
     dir: PathBuf,
+    /// Seals and opens conversation files
+    vault: Vault,
 }
 
 impl ConversationStore {
     /// Create a new conversation store
     pub fn new() -> Result<Self> {
+        Self::with_vault(Vault::from_config())
+    }
+
+    /// Create a conversation store that saves through `vault`
+    pub fn with_vault(vault: Vault) -> Result<Self> {
         let dir = dirs::data_dir()
             .unwrap_or_else(|| PathBuf::from("."))
             .join("quant")
@@ -106,23 +527,24 @@ impl ConversationStore {
 
         fs::create_dir_all(&dir).context("Failed to create conversations directory")?;
 
-        Ok(Self { dir })
+        Ok(Self { dir, vault })
     }
 
     /// Save a conversation
     pub fn save(&self, conversation: &Conversation) -> Result<PathBuf> {
         let path = self.dir.join(format!("{}.json", conversation.id));
         let content = serde_json::to_string_pretty(conversation)?;
-        fs::write(&path, content)?;
+        fs::write(&path, self.vault.seal(&content)?.as_ref())?;
         Ok(path)
     }
 
     /// Load a conversation by ID
     pub fn load(&self, id: &str) -> Result<Conversation> {
         let path = self.dir.join(format!("{}.json", id));
-        let content = fs::read_to_string(&path)
-            .with_context(|| format!("Conversation not found: {}", id))?;
-        serde_json::from_str(&content).context("Failed to parse conversation")
+        if !path.exists() {
+            anyhow::bail!("Conversation not found: {}", id);
+        }
+        read_conversation_file(&path, &self.vault)
     }
 
     /// Load a conversation by filename (without extension)
@@ -130,8 +552,7 @@ impl ConversationStore {
         // Try exact match first
         let path = self.dir.join(format!("{}.json", name));
         if path.exists() {
-            let content = fs::read_to_string(&path)?;
-            return serde_json::from_str(&content).context("Failed to parse conversation");
+            return read_conversation_file(&path, &self.vault);
         }
 
         // Try partial match
@@ -140,8 +561,7 @@ impl ConversationStore {
             let file_name = entry.file_name();
             let file_str = file_name.to_string_lossy();
             if file_str.starts_with(name) && file_str.ends_with(".json") {
-                let content = fs::read_to_string(entry.path())?;
-                return serde_json::from_str(&content).context("Failed to parse conversation");
+                return read_conversation_file(&entry.path(), &self.vault);
             }
         }
 
@@ -155,16 +575,16 @@ impl ConversationStore {
         for entry in fs::read_dir(&self.dir)?.flatten() {
             let path = entry.path();
             if path.extension().map(|e| e == "json").unwrap_or(false) {
-                if let Ok(content) = fs::read_to_string(&path) {
-                    if let Ok(conv) = serde_json::from_str::<Conversation>(&content) {
-                        summaries.push(ConversationSummary {
-                            id: conv.id,
-                            title: conv.title,
-                            model: conv.model,
-                            message_count: conv.messages.len(),
-                            updated_at: conv.updated_at,
-                        });
-                    }
+                if let Ok(conv) = read_conversation_file(&path, &self.vault) {
+                    summaries.push(ConversationSummary {
+                        id: conv.id,
+                        title: conv.title,
+                        model: conv.model,
+                        message_count: conv.messages.len(),
+                        updated_at: conv.updated_at,
+                        summary: conv.summary,
+                        tags: conv.tags,
+                    });
                 }
             }
         }
@@ -188,6 +608,12 @@ impl ConversationStore {
     }
 }
 
+/// Read a conversation file, opening it if it is encrypted
+pub fn read_conversation_file(path: &Path, vault: &Vault) -> Result<Conversation> {
+    let content = fs::read_to_string(path).context("Failed to read conversation")?;
+    serde_json::from_str(&vault.open(&content)?).context("Failed to parse conversation")
+}
+
 /// Summary of a conversation for listing
 #[derive(Debug, Clone, Serialize)]
 pub struct ConversationSummary {
@@ -196,32 +622,98 @@ pub struct ConversationSummary {
     pub model: String,
     pub message_count: usize,
     pub updated_at: DateTime<Utc>,
+    pub summary: Option<String>,
+    pub tags: Vec<String>,
 }
 
 /// REPL input history manager
+///
+/// Inside a project, what the user types is kept in a file of the project's
+/// own, so histories don't mix across repos. Slash commands go to one file
+/// shared by every project and are offered in each of them.
 pub struct InputHistory {
     /// Path to history file
     path: PathBuf,
+    /// Path to the shared slash command file
+    commands_path: PathBuf,
+    prompts: FileHistory,
+    commands: FileHistory,
 }
 
 impl InputHistory {
-    /// Create a new history manager
-    pub fn new() -> Result<Self> {
-        let dir = dirs::data_dir()
+    /// History for a REPL started in `dir`; `per_project` off keeps one
+    /// global file
+    pub fn for_dir(dir: &Path, per_project: bool, config: rustyline::Config) -> Result<Self> {
+        let data = dirs::data_dir()
             .unwrap_or_else(|| PathBuf::from("."))
             .join("quant");
+        let project = per_project.then(|| crate::project::find_project_root(dir)).flatten();
+        Self::in_data_dir(&data, project.as_deref(), config)
+    }
 
-        fs::create_dir_all(&dir)?;
+    fn in_data_dir(data: &Path, project: Option<&Path>, config: rustyline::Config) -> Result<Self> {
+        let path = match project {
+            Some(root) => data.join("histories").join(project_history_name(root)),
+            None => data.join("history"),
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
 
-        Ok(Self {
-            path: dir.join("history"),
-        })
+        let mut history = Self {
+            path,
+            commands_path: data.join("command_history"),
+            prompts: FileHistory::with_config(config),
+            commands: FileHistory::with_config(config),
+        };
+        // Files that don't exist yet are empty histories
+        let _ = history.prompts.load(&history.path);
+        let _ = history.commands.load(&history.commands_path);
+        Ok(history)
     }
 
     /// Get the history file path
     pub fn path(&self) -> &Path {
         &self.path
     }
+
+    /// Entries for the line editor, oldest first: slash commands, then the
+    /// project's prompts
+    pub fn entries(&self) -> impl Iterator<Item = &String> {
+        self.commands.iter().chain(self.prompts.iter())
+    }
+
+    /// Record an entered line
+    pub fn add(&mut self, line: &str) -> Result<()> {
+        if line.starts_with('/') {
+            self.commands.add(line)?;
+        } else {
+            self.prompts.add(line)?;
+        }
+        Ok(())
+    }
+
+    /// Append this session's lines to the history files, keeping what
+    /// other sessions added meanwhile
+    pub fn save(&mut self) -> Result<()> {
+        self.prompts.append(&self.path)?;
+        self.commands.append(&self.commands_path)?;
+        Ok(())
+    }
+}
+
+/// History file name for a project: its directory name, plus a hash of its
+/// path so projects with the same name don't share one
+fn project_history_name(root: &Path) -> String {
+    use sha2::{Digest, Sha256};
+
+    let name = root
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "root".to_string());
+    let digest = Sha256::digest(root.to_string_lossy().as_bytes());
+    let hash: String = digest[..4].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}", name, hash)
 }
 
 // Helper functions
@@ -247,6 +739,12 @@ fn uuid_v4() -> String {
     )
 }
 
+/// The user's own text in a chat message, without the file and resource
+/// context the REPL puts in front of it
+fn request_text(content: &str) -> &str {
+    content.rsplit_once("\n---\n\n").map_or(content, |(_, request)| request)
+}
+
 /// Truncate content to a reasonable title length
 fn truncate_title(content: &str) -> String {
     let first_line = content.lines().next().unwrap_or(content);
@@ -283,6 +781,193 @@ mod tests {
         assert_eq!(conv.title, "Hello!");
     }
 
+    #[test]
+    fn test_task_prompt() {
+        let mut conv = Conversation::new("test-model".to_string(), None);
+        assert!(conv.task_prompt(4, None).is_none());
+
+        conv.add_message(ChatMessage::user("The tests in parser.rs fail"));
+        conv.add_message(ChatMessage::assistant("Probably the tokenizer change"));
+        conv.add_message(ChatMessage::user("Fix it and run the tests"));
+
+        let prompt = conv.task_prompt(4, None).unwrap();
+        assert!(prompt.starts_with("Fix it and run the tests"));
+        assert!(prompt.contains("user: The tests in parser.rs fail"));
+        assert!(prompt.contains("assistant: Probably the tokenizer change"));
+
+        let prompt = conv.task_prompt(0, None).unwrap();
+        assert_eq!(prompt, "Fix it and run the tests");
+
+        let prompt = conv.task_prompt(1, Some("Bisect the failure")).unwrap();
+        assert!(prompt.starts_with("Bisect the failure"));
+        assert!(prompt.contains("user: Fix it and run the tests"));
+        assert!(!prompt.contains("tokenizer"));
+    }
+
+    #[test]
+    fn test_generated_title() {
+        let mut conv = Conversation::new("test-model".to_string(), None);
+        conv.add_message(ChatMessage::user("## src/lib.rs\nfn main() {}\n---\n\nWhy does this not compile?"));
+        assert_eq!(conv.title, "Why does this not compile?");
+        assert!(conv.title_request().is_none());
+
+        conv.add_message(ChatMessage::assistant("A library crate has no main function."));
+        let request = conv.title_request().unwrap();
+        assert!(request[1].content.starts_with("User: Why does this not compile?"));
+        assert!(!request[1].content.contains("src/lib.rs"));
+
+        assert!(!conv.set_generated_title("  \n"));
+        assert!(conv.set_generated_title("Title: \"Missing main in library crate.\"\nExtra"));
+        assert_eq!(conv.title, "Missing main in library crate");
+        assert!(conv.title_request().is_none());
+    }
+
+    #[test]
+    fn test_generated_summary() {
+        let mut conv = Conversation::new("test-model".to_string(), None);
+        conv.add_message(ChatMessage::user("How do I reverse a Vec?"));
+        assert!(conv.summary_request().is_none());
+
+        conv.add_message(ChatMessage::assistant("Call `v.reverse()`, or `v.iter().rev()` to iterate backwards."));
+        let request = conv.summary_request().unwrap();
+        assert!(request[1].content.contains("User: How do I reverse a Vec?\n\nAssistant: Call `v.reverse()`"));
+
+        assert!(!conv.set_generated_summary(""));
+        assert!(conv.set_generated_summary("{\"summary\": \"Reversing a Vec in Rust.\", \"tags\": [\"Rust\", \"#vec\"]}"));
+        assert_eq!(conv.summary.as_deref(), Some("Reversing a Vec in Rust."));
+        assert_eq!(conv.tags, vec!["rust", "vec"]);
+
+        // Only summarized again once the conversation changes
+        assert!(conv.summary_request().is_none());
+        conv.add_message(ChatMessage::user("And a String?"));
+        assert!(conv.summary_request().is_some());
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = ConversationStore {
+            dir: dir.path().to_path_buf(),
+            vault: Vault::default(),
+        };
+        store.save(&conv).unwrap();
+        let listed = &store.list().unwrap()[0];
+        assert_eq!(listed.summary.as_deref(), Some("Reversing a Vec in Rust."));
+        assert_eq!(listed.tags, vec!["rust", "vec"]);
+    }
+
+    #[test]
+    fn test_context_tokens_and_compact() {
+        let mut conv = Conversation::new("llama3.2".to_string(), Some("Be brief.".to_string()));
+        for i in 0..3 {
+            conv.add_message(ChatMessage::user(format!("Question {} about the borrow checker", i)));
+            conv.add_message(ChatMessage::assistant("A long answer ".repeat(50)));
+        }
+        assert_eq!(conv.message_tokens.len(), 6);
+        let before = conv.context_tokens();
+        assert!(before > 6 * MESSAGE_OVERHEAD_TOKENS);
+
+        // Counts missing from an older save are filled in
+        conv.message_tokens.clear();
+        assert_eq!(conv.context_tokens(), before);
+
+        assert!(conv.compact_request(6).is_none());
+        let request = conv.compact_request(2).unwrap();
+        assert!(request[1].content.contains("Question 1"));
+        assert!(!request[1].content.contains("Question 2"));
+
+        assert_eq!(conv.compact("Asked about borrowing twice.", 2), 4);
+        assert_eq!(conv.len(), 3);
+        assert!(conv.messages[0].content.starts_with(COMPACT_HEADER));
+        assert!(conv.messages[1].content.contains("Question 2"));
+        assert!(conv.context_tokens() < before);
+    }
+
+    #[test]
+    fn test_encrypted_conversation() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let store = ConversationStore {
+            dir: dir.path().to_path_buf(),
+            vault: Vault::with_key([5; 32]),
+        };
+        let mut conv = Conversation::new("test-model".to_string(), None);
+        conv.add_message(ChatMessage::user("my api key is abc123"));
+
+        let path = store.save(&conv).unwrap();
+        assert!(!fs::read_to_string(&path).unwrap().contains("abc123"));
+        assert_eq!(store.load(&conv.id).unwrap().messages[0].content, "my api key is abc123");
+        assert_eq!(store.list().unwrap()[0].message_count, 1);
+    }
+
+    #[test]
+    fn test_project_input_history() {
+        let data = tempfile::TempDir::new().unwrap();
+        let config = rustyline::Config::default();
+        let (app, lib) = (Path::new("/work/app"), Path::new("/work/lib"));
+
+        let mut history = InputHistory::in_data_dir(data.path(), Some(app), config).unwrap();
+        history.add("explain main.rs").unwrap();
+        history.add("/model llama3.2:3b").unwrap();
+        history.save().unwrap();
+
+        let mut history = InputHistory::in_data_dir(data.path(), Some(lib), config).unwrap();
+        assert_ne!(history.path(), data.path().join("histories").join(project_history_name(app)));
+        assert_eq!(history.entries().collect::<Vec<_>>(), ["/model llama3.2:3b"]);
+        history.add("fix the parser").unwrap();
+        history.save().unwrap();
+
+        let history = InputHistory::in_data_dir(data.path(), Some(app), config).unwrap();
+        assert_eq!(history.entries().collect::<Vec<_>>(), ["/model llama3.2:3b", "explain main.rs"]);
+        let history = InputHistory::in_data_dir(data.path(), None, config).unwrap();
+        assert_eq!(history.path(), data.path().join("history"));
+        assert_eq!(history.entries().count(), 1);
+    }
+
+    #[test]
+    fn test_sampling_settings() {
+        let mut conv = Conversation::new("test-model".to_string(), None);
+        let sampling = &mut conv.sampling;
+        sampling.set("temperature", "0.2").unwrap();
+        sampling.set("seed", "42").unwrap();
+        sampling.set("stop", "\"###\" '\\n\\nUser:' END").unwrap();
+        assert!(sampling.set("top_p", "1.5").is_err());
+        assert!(sampling.set("top_k", "many").is_err());
+        assert!(sampling.set("stop", r#""open"#).is_err());
+        assert!(sampling.set("mirostat", "1").is_err());
+        assert!(sampling.set("presence_penalty", "-3").is_err());
+        assert_eq!(sampling.presence_penalty, None);
+        assert_eq!(sampling.stop, ["###", "\n\nUser:", "END"]);
+
+        let mut options = ChatOptions {
+            temperature: Some(0.7),
+            num_ctx: Some(8192),
+            ..Default::default()
+        };
+        sampling.apply(&mut options);
+        assert_eq!(options.temperature, Some(0.2));
+        assert_eq!(options.seed, Some(42));
+        assert_eq!(options.num_ctx, Some(8192));
+        assert_eq!(options.stop.as_ref().map(Vec::len), Some(3));
+
+        // An agent gets every option, over the model alias's
+        let alias = ModelOptions {
+            temperature: Some(0.9),
+            top_k: Some(40),
+            ..Default::default()
+        };
+        let agent = sampling.over(&alias);
+        assert_eq!(agent.temperature, Some(0.2));
+        assert_eq!(agent.top_k, Some(40));
+        assert_eq!(agent.seed, Some(42));
+        assert_eq!(agent.stop.len(), 3);
+
+        // Kept when the conversation is saved and loaded
+        let loaded: Conversation = serde_json::from_str(&serde_json::to_string(&conv).unwrap()).unwrap();
+        assert_eq!(loaded.sampling, conv.sampling);
+
+        conv.sampling.set("seed", "").unwrap();
+        assert_eq!(conv.sampling.seed, None);
+        let json = serde_json::to_value(Conversation::new("m".to_string(), None)).unwrap();
+        assert!(json.get("sampling").is_none());
+    }
+
     #[test]
     fn test_truncate_title() {
         let long = "This is a very long message that should be truncated because it exceeds the maximum title length";
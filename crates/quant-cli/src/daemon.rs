@@ -0,0 +1,716 @@
+//! Background daemon that keeps quant's slow-to-start state loaded
+//!
+//! `quant daemon start` runs `quant daemon run` in the background. The
+//! daemon holds the MCP servers of every project an agent ran in, the RAG
+//! store, and the `[warmup]` models. A scheduler reloads the models every
+//! `[daemon] warm_interval_secs` and stops a project's MCP servers after
+//! `[daemon] mcp_idle_mins` without calls.
+//!
+//! Commands talk to it over a Unix socket ([`socket_path`]) with JSON-RPC
+//! 2.0, one message per line. `quant agent` takes its MCP tools from the
+//! daemon instead of spawning the servers, and `quant rag query` retrieves
+//! from the daemon's store instead of reading it from disk. Without a daemon
+//! both do the work themselves, as before.
+//!
+//! MCP servers run in the daemon's environment, so `${VAR}` in QUANT.md
+//! expands to the variables the daemon was started with.
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::{Mutex, Notify};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+#[cfg(unix)]
+use anyhow::Context;
+#[cfg(unix)]
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+#[cfg(unix)]
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+
+use crate::config::UserConfig;
+use crate::mcp::tools::schema_from_json;
+use crate::mcp::{McpManager, McpServerConfig, PrefixedMcpTool, HEALTH_CHECK_INTERVAL};
+use crate::project::ProjectContext;
+use crate::rag::{RagMatch, RagStore};
+use crate::tools::registry::ToolRegistry;
+use crate::tools::{ParameterSchema, SecurityLevel, Tool, ToolContext, ToolResult};
+
+/// How often the scheduler checks for due work
+const SCHEDULER_TICK: Duration = Duration::from_secs(30);
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+/// Where the daemon listens, next to OllamaBar's socket
+pub fn socket_path() -> PathBuf {
+    llm_core::ipc::socket_path().with_file_name("daemon.sock")
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Request {
+    jsonrpc: String,
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Response {
+    jsonrpc: String,
+    id: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl Response {
+    fn new(id: Value, outcome: Result<Value, RpcError>) -> Self {
+        let (result, error) = match outcome {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ToolsParams {
+    root: PathBuf,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CallParams {
+    root: PathBuf,
+    tool: String,
+    #[serde(default)]
+    args: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RagParams {
+    question: String,
+    top_k: usize,
+}
+
+/// An MCP tool served by the daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteTool {
+    /// Prefixed name, `server_tool`
+    pub name: String,
+    pub description: String,
+    pub security_level: SecurityLevel,
+    pub input_schema: Value,
+}
+
+/// What the daemon holds, for `quant daemon status`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    pub pid: u32,
+    pub started_at: DateTime<Utc>,
+    pub projects: Vec<ProjectPool>,
+    /// Chunks of the RAG store, once a question loaded it
+    pub rag_chunks: Option<usize>,
+    /// Models loaded by the last warmup
+    pub warm_models: Vec<String>,
+    pub last_warm: Option<DateTime<Utc>>,
+}
+
+/// A project's running MCP servers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectPool {
+    pub root: PathBuf,
+    pub servers: Vec<String>,
+    pub tools: usize,
+    /// Seconds since a command last used them
+    pub idle_secs: u64,
+}
+
+struct McpPool {
+    manager: Arc<Mutex<McpManager>>,
+    supervisor: JoinHandle<()>,
+    tools: HashMap<String, Arc<PrefixedMcpTool>>,
+    last_used: Instant,
+}
+
+impl McpPool {
+    fn new() -> Self {
+        let manager = Arc::new(Mutex::new(McpManager::new()));
+        Self {
+            supervisor: McpManager::spawn_supervisor(Arc::clone(&manager), HEALTH_CHECK_INTERVAL),
+            manager,
+            tools: HashMap::new(),
+            last_used: Instant::now(),
+        }
+    }
+
+    async fn stop(self) {
+        self.supervisor.abort();
+        self.manager.lock().await.stop_all().await;
+    }
+}
+
+struct LoadedRag {
+    modified: Option<SystemTime>,
+    store: RagStore,
+}
+
+#[derive(Default)]
+struct WarmState {
+    models: Vec<String>,
+    last: Option<DateTime<Utc>>,
+}
+
+/// The daemon's state, shared by its connections and scheduler
+pub struct Daemon {
+    started_at: DateTime<Utc>,
+    pools: Mutex<HashMap<PathBuf, McpPool>>,
+    rag: Mutex<Option<LoadedRag>>,
+    warm: Mutex<WarmState>,
+    shutdown: Notify,
+}
+
+impl Default for Daemon {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Daemon {
+    pub fn new() -> Self {
+        Self {
+            started_at: Utc::now(),
+            pools: Mutex::new(HashMap::new()),
+            rag: Mutex::new(None),
+            warm: Mutex::new(WarmState::default()),
+            shutdown: Notify::new(),
+        }
+    }
+
+    /// Serve clients on `socket` until one asks the daemon to shut down
+    #[cfg(unix)]
+    pub async fn serve(self: Arc<Self>, socket: PathBuf) -> Result<()> {
+        if UnixStream::connect(&socket).await.is_ok() {
+            bail!("A quant daemon is already listening on {}", socket.display());
+        }
+        if let Some(parent) = socket.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // A leftover socket from a daemon that died would make bind fail
+        if socket.exists() {
+            std::fs::remove_file(&socket)?;
+        }
+        let listener = UnixListener::bind(&socket).with_context(|| format!("Failed to bind {}", socket.display()))?;
+        info!(socket = %socket.display(), "quant daemon listening");
+
+        let scheduler = tokio::spawn(Arc::clone(&self).run_scheduler());
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let (stream, _) = accepted?;
+                    let daemon = Arc::clone(&self);
+                    tokio::spawn(async move { daemon.serve_connection(stream).await });
+                }
+                _ = self.shutdown.notified() => break,
+            }
+        }
+
+        scheduler.abort();
+        for (_, pool) in self.pools.lock().await.drain() {
+            pool.stop().await;
+        }
+        let _ = std::fs::remove_file(&socket);
+        info!("quant daemon stopped");
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub async fn serve(self: Arc<Self>, _socket: PathBuf) -> Result<()> {
+        bail!("The quant daemon needs Unix sockets, which this platform doesn't have")
+    }
+
+    #[cfg(unix)]
+    async fn serve_connection(&self, stream: UnixStream) {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+
+        while let Ok(Some(line)) = lines.next_line().await {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (response, shutdown) = match serde_json::from_str::<Request>(&line) {
+                Ok(request) => {
+                    let shutdown = request.method == "shutdown";
+                    let outcome = self.dispatch(&request.method, request.params).await;
+                    (Response::new(request.id, outcome), shutdown)
+                }
+                Err(e) => {
+                    let error = RpcError {
+                        code: PARSE_ERROR,
+                        message: format!("Invalid request: {}", e),
+                    };
+                    (Response::new(Value::Null, Err(error)), false)
+                }
+            };
+            if write_message(&mut writer, &response).await.is_err() {
+                break;
+            }
+            // Only after the client has its answer
+            if shutdown {
+                self.shutdown.notify_one();
+            }
+        }
+    }
+
+    async fn dispatch(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        debug!(method, "Daemon request");
+        match method {
+            "status" => reply(Ok(self.status().await)),
+            "mcp.tools" => {
+                let params: ToolsParams = parse_params(params)?;
+                reply(self.mcp_tools(&params.root).await)
+            }
+            "mcp.call" => reply(self.mcp_call(parse_params(params)?).await),
+            "rag.search" => reply(self.rag_search(parse_params(params)?).await),
+            "shutdown" => Ok(Value::Null),
+            _ => Err(RpcError {
+                code: METHOD_NOT_FOUND,
+                message: format!("Unknown method: {}", method),
+            }),
+        }
+    }
+
+    async fn status(&self) -> DaemonStatus {
+        let mut projects = Vec::new();
+        for (root, pool) in self.pools.lock().await.iter() {
+            let servers = pool.manager.lock().await.running_servers().iter().map(|s| s.to_string()).collect();
+            projects.push(ProjectPool {
+                root: root.clone(),
+                servers,
+                tools: pool.tools.len(),
+                idle_secs: pool.last_used.elapsed().as_secs(),
+            });
+        }
+        projects.sort_by(|a, b| a.root.cmp(&b.root));
+
+        let rag_chunks = self.rag.lock().await.as_ref().map(|rag| rag.store.chunk_count());
+        let warm = self.warm.lock().await;
+        DaemonStatus {
+            pid: std::process::id(),
+            started_at: self.started_at,
+            projects,
+            rag_chunks,
+            warm_models: warm.models.clone(),
+            last_warm: warm.last,
+        }
+    }
+
+    /// Bring the project's MCP servers in line with its QUANT.md and list their tools
+    async fn mcp_tools(&self, root: &Path) -> Result<Vec<RemoteTool>> {
+        let servers = project_servers(root);
+        let mut pools = self.pools.lock().await;
+        if servers.is_empty() {
+            if let Some(pool) = pools.remove(root) {
+                pool.stop().await;
+            }
+            return Ok(Vec::new());
+        }
+
+        let pool = pools.entry(root.to_path_buf()).or_insert_with(McpPool::new);
+        pool.last_used = Instant::now();
+        let report = pool.manager.lock().await.sync(servers).await;
+        if !report.is_empty() {
+            info!(root = %root.display(), changes = %report.summary(), "Synced MCP servers");
+        }
+        let tools = pool.manager.lock().await.discover_tools().await?;
+        pool.tools = tools.into_iter().map(|tool| (tool.name().to_string(), Arc::new(tool))).collect();
+
+        let mut remote: Vec<RemoteTool> = pool
+            .tools
+            .values()
+            .map(|tool| RemoteTool {
+                name: tool.name().to_string(),
+                description: tool.description().to_string(),
+                security_level: tool.security_level(),
+                input_schema: tool.input_schema().clone(),
+            })
+            .collect();
+        remote.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(remote)
+    }
+
+    async fn pooled_tool(&self, root: &Path, name: &str) -> Option<Arc<PrefixedMcpTool>> {
+        let mut pools = self.pools.lock().await;
+        let pool = pools.get_mut(root)?;
+        pool.last_used = Instant::now();
+        pool.tools.get(name).cloned()
+    }
+
+    async fn mcp_call(&self, params: CallParams) -> Result<ToolResult> {
+        let mut tool = self.pooled_tool(&params.root, &params.tool).await;
+        if tool.is_none() {
+            // The servers may have been stopped while idle
+            self.mcp_tools(&params.root).await?;
+            tool = self.pooled_tool(&params.root, &params.tool).await;
+        }
+        let Some(tool) = tool else {
+            bail!("No MCP tool named {} in {}", params.tool, params.root.display());
+        };
+        // The pool lock isn't held, so calls to other tools run alongside
+        tool.execute(&params.args, &ToolContext::new(params.root)).await
+    }
+
+    async fn rag_search(&self, params: RagParams) -> Result<Vec<RagMatch>> {
+        let path = RagStore::default_path()?;
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+
+        let mut rag = self.rag.lock().await;
+        // Reload after `quant rag index` rewrote the store
+        let stale = rag.as_ref().is_none_or(|loaded| loaded.modified != modified);
+        if stale {
+            let store = RagStore::load(&path)?;
+            *rag = Some(LoadedRag { modified, store });
+        }
+        let store = &rag.as_ref().expect("loaded above").store;
+
+        let client = crate::config::load_llm_config()?.ollama_client();
+        crate::rag::search_question(store, &client, &params.question, params.top_k).await
+    }
+
+    async fn run_scheduler(self: Arc<Self>) {
+        let mut ticker = tokio::time::interval(SCHEDULER_TICK);
+        let mut next_warm = Instant::now();
+        loop {
+            ticker.tick().await;
+            // Read per tick so config edits apply
+            let settings = UserConfig::load().unwrap_or_default().daemon;
+
+            if settings.warm_interval_secs > 0 && Instant::now() >= next_warm {
+                self.warm_models().await;
+                next_warm = Instant::now() + Duration::from_secs(settings.warm_interval_secs);
+            }
+            if settings.mcp_idle_mins > 0 {
+                self.stop_idle_pools(Duration::from_secs(settings.mcp_idle_mins * 60)).await;
+            }
+        }
+    }
+
+    /// Load the [warmup] models again, so they stay loaded
+    async fn warm_models(&self) {
+        let Ok(config) = crate::config::load_llm_config() else {
+            return;
+        };
+        let pool = config.warm_pool();
+        if pool.is_empty() {
+            return;
+        }
+        let client = config.ollama_client();
+        if !client.health_check().await.unwrap_or(false) {
+            debug!("Ollama is not running; skipping warmup");
+            return;
+        }
+
+        let mut warmed = Vec::new();
+        for model in pool {
+            match client.warm_model(&model.name, Some(&model.keep_alive)).await {
+                Ok(()) => warmed.push(model.name),
+                Err(e) => warn!(model = %model.name, error = %e, "Failed to warm model"),
+            }
+        }
+        let mut warm = self.warm.lock().await;
+        warm.models = warmed;
+        warm.last = Some(Utc::now());
+    }
+
+    async fn stop_idle_pools(&self, idle: Duration) {
+        let mut pools = self.pools.lock().await;
+        let roots: Vec<PathBuf> = pools
+            .iter()
+            .filter(|(_, pool)| pool.last_used.elapsed() >= idle)
+            .map(|(root, _)| root.clone())
+            .collect();
+        for root in roots {
+            if let Some(pool) = pools.remove(&root) {
+                info!(root = %root.display(), "Stopping idle MCP servers");
+                pool.stop().await;
+            }
+        }
+    }
+}
+
+/// The MCP servers in the QUANT.md of the project at `root`
+fn project_servers(root: &Path) -> Vec<McpServerConfig> {
+    let mut servers = ProjectContext::discover(root)
+        .and_then(|ctx| ctx.quant_file)
+        .map(|quant_file| quant_file.mcp_servers)
+        .unwrap_or_default();
+    // Run servers in the project, not wherever the daemon was started
+    for server in &mut servers {
+        server.cwd = Some(match server.cwd.take() {
+            Some(cwd) => root.join(cwd),
+            None => root.to_path_buf(),
+        });
+    }
+    servers
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError {
+        code: INVALID_PARAMS,
+        message: format!("Invalid params: {}", e),
+    })
+}
+
+fn reply<T: Serialize>(result: Result<T>) -> Result<Value, RpcError> {
+    result.and_then(|value| Ok(serde_json::to_value(value)?)).map_err(|e| RpcError {
+        code: SERVER_ERROR,
+        message: format!("{:#}", e),
+    })
+}
+
+/// A connection to the daemon
+pub struct DaemonClient {
+    #[cfg(unix)]
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    #[cfg(unix)]
+    writer: OwnedWriteHalf,
+    next_id: u64,
+}
+
+impl DaemonClient {
+    /// Connect to the daemon at `socket`
+    #[cfg(unix)]
+    pub async fn connect(socket: &Path) -> Result<Self> {
+        let stream = UnixStream::connect(socket)
+            .await
+            .with_context(|| format!("Failed to connect to {}", socket.display()))?;
+        let (reader, writer) = stream.into_split();
+        Ok(Self {
+            lines: BufReader::new(reader).lines(),
+            writer,
+            next_id: 0,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub async fn connect(_socket: &Path) -> Result<Self> {
+        bail!("The quant daemon needs Unix sockets, which this platform doesn't have")
+    }
+
+    /// Connect to the daemon if it is running
+    pub async fn connect_default() -> Option<Self> {
+        let socket = socket_path();
+        if !socket.exists() {
+            return None;
+        }
+        match Self::connect(&socket).await {
+            Ok(client) => Some(client),
+            Err(e) => {
+                debug!(error = %e, "quant daemon not reachable");
+                None
+            }
+        }
+    }
+
+    /// Call `method` and wait for its result
+    #[cfg(unix)]
+    pub async fn call<T: DeserializeOwned>(&mut self, method: &str, params: Value) -> Result<T> {
+        self.next_id += 1;
+        let request = Request {
+            jsonrpc: "2.0".to_string(),
+            id: json!(self.next_id),
+            method: method.to_string(),
+            params,
+        };
+        write_message(&mut self.writer, &request).await?;
+
+        let Some(line) = self.lines.next_line().await? else {
+            bail!("The quant daemon closed the connection");
+        };
+        let response: Response = serde_json::from_str(&line).context("Invalid response from the quant daemon")?;
+        if let Some(error) = response.error {
+            bail!("{}", error.message);
+        }
+        Ok(serde_json::from_value(response.result.unwrap_or(Value::Null))?)
+    }
+
+    #[cfg(not(unix))]
+    pub async fn call<T: DeserializeOwned>(&mut self, _method: &str, _params: Value) -> Result<T> {
+        self.next_id += 1;
+        bail!("The quant daemon needs Unix sockets, which this platform doesn't have")
+    }
+
+    pub async fn status(&mut self) -> Result<DaemonStatus> {
+        self.call("status", Value::Null).await
+    }
+
+    /// Start the MCP servers of the project at `root` (or reuse them) and list their tools
+    pub async fn mcp_tools(&mut self, root: &Path) -> Result<Vec<RemoteTool>> {
+        self.call("mcp.tools", json!({ "root": root })).await
+    }
+
+    pub async fn mcp_call(&mut self, root: &Path, tool: &str, args: &Value) -> Result<ToolResult> {
+        self.call("mcp.call", json!({ "root": root, "tool": tool, "args": args })).await
+    }
+
+    pub async fn rag_search(&mut self, question: &str, top_k: usize) -> Result<Vec<RagMatch>> {
+        self.call("rag.search", json!({ "question": question, "top_k": top_k })).await
+    }
+
+    /// Ask the daemon to stop its MCP servers and exit
+    pub async fn shutdown(&mut self) -> Result<()> {
+        self.call("shutdown", Value::Null).await
+    }
+}
+
+#[cfg(unix)]
+async fn write_message(writer: &mut OwnedWriteHalf, message: &impl Serialize) -> Result<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await?;
+    Ok(())
+}
+
+/// An MCP tool that runs on the daemon's servers
+pub struct DaemonMcpTool {
+    daemon: Arc<Mutex<DaemonClient>>,
+    root: PathBuf,
+    spec: RemoteTool,
+}
+
+#[async_trait]
+impl Tool for DaemonMcpTool {
+    fn name(&self) -> &str {
+        &self.spec.name
+    }
+
+    fn description(&self) -> &str {
+        &self.spec.description
+    }
+
+    fn security_level(&self) -> SecurityLevel {
+        self.spec.security_level
+    }
+
+    fn untrusted_output(&self) -> bool {
+        true
+    }
+
+    fn parameters_schema(&self) -> ParameterSchema {
+        schema_from_json(&self.spec.input_schema)
+    }
+
+    async fn execute(&self, args: &Value, _ctx: &ToolContext) -> Result<ToolResult> {
+        self.daemon.lock().await.mcp_call(&self.root, &self.spec.name, args).await
+    }
+}
+
+/// Register the daemon's MCP tools for the project at `root`
+///
+/// Returns the names of the registered tools.
+pub async fn register_mcp_tools(
+    registry: &mut ToolRegistry,
+    daemon: &Arc<Mutex<DaemonClient>>,
+    root: &Path,
+) -> Result<Vec<String>> {
+    let tools = daemon.lock().await.mcp_tools(root).await?;
+    let names = tools.iter().map(|tool| tool.name.clone()).collect();
+    for spec in tools {
+        registry.register(DaemonMcpTool {
+            daemon: Arc::clone(daemon),
+            root: root.to_path_buf(),
+            spec,
+        });
+    }
+    Ok(names)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    async fn start(dir: &Path) -> (PathBuf, JoinHandle<Result<()>>) {
+        let socket = dir.join("daemon.sock");
+        let server = tokio::spawn(Arc::new(Daemon::new()).serve(socket.clone()));
+        for _ in 0..50 {
+            if socket.exists() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        (socket, server)
+    }
+
+    #[tokio::test]
+    async fn test_rpc_round_trip() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let (socket, server) = start(dir.path()).await;
+        let mut client = DaemonClient::connect(&socket).await.unwrap();
+
+        let status = client.status().await.unwrap();
+        assert_eq!(status.pid, std::process::id());
+        assert!(status.projects.is_empty());
+
+        // A project without MCP servers gets no tools and no pool
+        let project = dir.path().join("project");
+        std::fs::create_dir(&project).unwrap();
+        assert!(client.mcp_tools(&project).await.unwrap().is_empty());
+        assert!(client.status().await.unwrap().projects.is_empty());
+
+        let err = client.mcp_call(&project, "files_read", &json!({})).await.unwrap_err();
+        assert!(err.to_string().contains("No MCP tool named files_read"));
+        let err = client.call::<Value>("nope", Value::Null).await.unwrap_err();
+        assert!(err.to_string().contains("Unknown method"));
+        let err = client.call::<Value>("mcp.tools", json!({})).await.unwrap_err();
+        assert!(err.to_string().contains("Invalid params"));
+
+        // A second daemon on the same socket refuses to start
+        let second = Arc::new(Daemon::new()).serve(socket.clone()).await;
+        assert!(second.is_err());
+
+        client.shutdown().await.unwrap();
+        server.await.unwrap().unwrap();
+        assert!(!socket.exists());
+    }
+
+    #[test]
+    fn test_project_servers_run_in_the_project() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            dir.path().join("QUANT.md"),
+            "---\nmcp_servers:\n  - name: files\n    command: mcp-files\n  - name: db\n    command: mcp-db\n    cwd: tools\n---\n# Project\n",
+        )
+        .unwrap();
+
+        let servers = project_servers(dir.path());
+        assert_eq!(servers.len(), 2);
+        assert_eq!(servers[0].cwd.as_deref(), Some(dir.path()));
+        assert_eq!(servers[1].cwd, Some(dir.path().join("tools")));
+    }
+}
@@ -0,0 +1,625 @@
+//! Dependency manifest parsing
+//!
+//! Reads Cargo.toml, package.json, and pyproject.toml (plus their lockfiles)
+//! into a structured list of dependencies with declared requirements and
+//! resolved versions, so the agent can reason about the versions actually in
+//! use. Outdated checks and explanations wrap the ecosystem's native tools.
+
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::debug;
+
+/// Package ecosystem of a manifest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ecosystem {
+    Cargo,
+    Npm,
+    Python,
+}
+
+impl Ecosystem {
+    /// Manifest file name
+    pub fn manifest_file(&self) -> &'static str {
+        match self {
+            Ecosystem::Cargo => "Cargo.toml",
+            Ecosystem::Npm => "package.json",
+            Ecosystem::Python => "pyproject.toml",
+        }
+    }
+}
+
+impl std::fmt::Display for Ecosystem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Ecosystem::Cargo => write!(f, "cargo"),
+            Ecosystem::Npm => write!(f, "npm"),
+            Ecosystem::Python => write!(f, "python"),
+        }
+    }
+}
+
+/// How a dependency is used
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyKind {
+    Normal,
+    Dev,
+    Build,
+    Optional,
+}
+
+impl std::fmt::Display for DependencyKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DependencyKind::Normal => write!(f, "normal"),
+            DependencyKind::Dev => write!(f, "dev"),
+            DependencyKind::Build => write!(f, "build"),
+            DependencyKind::Optional => write!(f, "optional"),
+        }
+    }
+}
+
+/// A declared dependency
+#[derive(Debug, Clone, Serialize)]
+pub struct Dependency {
+    /// Package name
+    pub name: String,
+    /// Version requirement as declared (e.g. "^1.0", "workspace", "path")
+    pub requirement: String,
+    /// Version resolved in the lockfile
+    pub resolved: Option<String>,
+    /// How the dependency is used
+    pub kind: DependencyKind,
+}
+
+/// A parsed manifest and its dependencies
+#[derive(Debug, Clone, Serialize)]
+pub struct DependencyManifest {
+    /// Path to the manifest file
+    pub path: PathBuf,
+    /// Package ecosystem
+    pub ecosystem: Ecosystem,
+    /// Package name from the manifest, if any
+    pub package: Option<String>,
+    /// Declared dependencies
+    pub dependencies: Vec<Dependency>,
+}
+
+impl DependencyManifest {
+    /// Parse the manifest at `path`, resolving versions from a nearby lockfile
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+
+        let mut manifest = match file_name {
+            "Cargo.toml" => parse_cargo_toml(path, &content)?,
+            "package.json" => parse_package_json(path, &content)?,
+            "pyproject.toml" => parse_pyproject(path, &content)?,
+            _ => anyhow::bail!("Unsupported manifest: {}", path.display()),
+        };
+
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let resolved = load_lockfile(dir, manifest.ecosystem);
+        for dep in &mut manifest.dependencies {
+            dep.resolved = resolved.get(&normalize_name(manifest.ecosystem, &dep.name)).cloned();
+        }
+
+        Ok(manifest)
+    }
+}
+
+/// Discover manifests in a project root (including Cargo workspace members)
+pub fn discover(root: &Path) -> Vec<DependencyManifest> {
+    let mut manifests = Vec::new();
+
+    for ecosystem in [Ecosystem::Cargo, Ecosystem::Npm, Ecosystem::Python] {
+        let path = root.join(ecosystem.manifest_file());
+        if !path.is_file() {
+            continue;
+        }
+        match DependencyManifest::load(&path) {
+            Ok(manifest) => manifests.push(manifest),
+            Err(e) => debug!(path = %path.display(), error = %e, "Failed to parse manifest"),
+        }
+        if ecosystem == Ecosystem::Cargo {
+            for member in cargo_workspace_members(root) {
+                if let Ok(manifest) = DependencyManifest::load(&member) {
+                    manifests.push(manifest);
+                }
+            }
+        }
+    }
+
+    manifests
+}
+
+/// Find every declaration of a dependency across manifests
+pub fn find<'a>(manifests: &'a [DependencyManifest], name: &str) -> Vec<(&'a DependencyManifest, &'a Dependency)> {
+    manifests
+        .iter()
+        .flat_map(|m| m.dependencies.iter().map(move |d| (m, d)))
+        .filter(|(m, d)| normalize_name(m.ecosystem, &d.name) == normalize_name(m.ecosystem, name))
+        .collect()
+}
+
+/// Compact dependency summary for the system prompt
+pub fn to_system_context(manifests: &[DependencyManifest], root: &Path, max_per_manifest: usize) -> String {
+    let mut ctx = String::new();
+    for manifest in manifests.iter().filter(|m| !m.dependencies.is_empty()) {
+        let rel = manifest.path.strip_prefix(root).unwrap_or(&manifest.path);
+        ctx.push_str(&format!("{}:\n", rel.display()));
+
+        let entries: Vec<String> = manifest
+            .dependencies
+            .iter()
+            .filter(|d| d.kind != DependencyKind::Dev)
+            .take(max_per_manifest)
+            .map(|d| format!("{} {}", d.name, d.resolved.as_deref().unwrap_or(&d.requirement)))
+            .collect();
+        ctx.push_str(&format!("  {}\n", entries.join(", ")));
+
+        let hidden = manifest.dependencies.iter().filter(|d| d.kind != DependencyKind::Dev).count();
+        if hidden > max_per_manifest {
+            ctx.push_str(&format!("  [{} more]\n", hidden - max_per_manifest));
+        }
+    }
+    ctx
+}
+
+/// Native command used to check for outdated dependencies, if available
+pub fn outdated_command(ecosystem: Ecosystem) -> Option<(String, Vec<String>)> {
+    let (program, args): (&str, &[&str]) = match ecosystem {
+        Ecosystem::Cargo if which::which("cargo-outdated").is_ok() => ("cargo", &["outdated", "--root-deps-only"]),
+        Ecosystem::Cargo => ("cargo", &["update", "--dry-run", "--verbose"]),
+        Ecosystem::Npm => ("npm", &["outdated"]),
+        Ecosystem::Python => ("pip", &["list", "--outdated"]),
+    };
+    which::which(program).ok()?;
+    Some((program.to_string(), args.iter().map(|s| s.to_string()).collect()))
+}
+
+/// Native command that explains why a dependency is present
+pub fn explain_command(ecosystem: Ecosystem, name: &str) -> Option<(String, Vec<String>)> {
+    let (program, args): (&str, Vec<&str>) = match ecosystem {
+        Ecosystem::Cargo => ("cargo", vec!["tree", "--invert", name, "--depth", "2"]),
+        Ecosystem::Npm => ("npm", vec!["explain", name]),
+        Ecosystem::Python => ("pip", vec!["show", name]),
+    };
+    which::which(program).ok()?;
+    Some((program.to_string(), args.iter().map(|s| s.to_string()).collect()))
+}
+
+/// Run a native tool in `dir` and return its combined output
+pub fn run_native(dir: &Path, program: &str, args: &[String]) -> Result<String> {
+    let output = Command::new(program).args(args).current_dir(dir).output()?;
+    let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !stderr.trim().is_empty() {
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(stderr.trim_end());
+    }
+    Ok(text)
+}
+
+/// Names compare case-insensitively in Python, with `-`/`_`/`.` equivalent
+fn normalize_name(ecosystem: Ecosystem, name: &str) -> String {
+    match ecosystem {
+        Ecosystem::Python => name.to_lowercase().replace(['_', '.'], "-"),
+        Ecosystem::Cargo | Ecosystem::Npm => name.to_string(),
+    }
+}
+
+fn parse_cargo_toml(path: &Path, content: &str) -> Result<DependencyManifest> {
+    let parsed: toml::Table = content.parse()?;
+    let package = parsed
+        .get("package")
+        .and_then(|p| p.get("name"))
+        .and_then(|n| n.as_str())
+        .map(String::from);
+
+    let mut dependencies = Vec::new();
+    let sections = [
+        ("dependencies", DependencyKind::Normal),
+        ("dev-dependencies", DependencyKind::Dev),
+        ("build-dependencies", DependencyKind::Build),
+    ];
+
+    for (section, kind) in sections {
+        if let Some(table) = parsed.get(section).and_then(|t| t.as_table()) {
+            collect_cargo_deps(table, kind, &mut dependencies);
+        }
+    }
+    // Workspace-level declarations in a virtual manifest
+    if let Some(table) = parsed
+        .get("workspace")
+        .and_then(|w| w.get("dependencies"))
+        .and_then(|t| t.as_table())
+    {
+        collect_cargo_deps(table, DependencyKind::Normal, &mut dependencies);
+    }
+
+    Ok(DependencyManifest {
+        path: path.to_path_buf(),
+        ecosystem: Ecosystem::Cargo,
+        package,
+        dependencies,
+    })
+}
+
+fn collect_cargo_deps(table: &toml::Table, kind: DependencyKind, out: &mut Vec<Dependency>) {
+    for (name, spec) in table {
+        let (requirement, optional) = match spec {
+            toml::Value::String(v) => (v.clone(), false),
+            toml::Value::Table(t) => {
+                let requirement = if let Some(v) = t.get("version").and_then(|v| v.as_str()) {
+                    v.to_string()
+                } else if t.get("workspace").and_then(|w| w.as_bool()) == Some(true) {
+                    "workspace".to_string()
+                } else if let Some(p) = t.get("path").and_then(|p| p.as_str()) {
+                    format!("path:{}", p)
+                } else if let Some(g) = t.get("git").and_then(|g| g.as_str()) {
+                    format!("git:{}", g)
+                } else {
+                    "*".to_string()
+                };
+                let optional = t.get("optional").and_then(|o| o.as_bool()).unwrap_or(false);
+                (requirement, optional)
+            }
+            _ => continue,
+        };
+        // The `package` key renames a dependency; report the real crate name
+        let name = match spec.get("package").and_then(|p| p.as_str()) {
+            Some(real) => real.to_string(),
+            None => name.clone(),
+        };
+
+        out.push(Dependency {
+            name,
+            requirement,
+            resolved: None,
+            kind: if optional && kind == DependencyKind::Normal { DependencyKind::Optional } else { kind },
+        });
+    }
+}
+
+fn parse_package_json(path: &Path, content: &str) -> Result<DependencyManifest> {
+    let parsed: serde_json::Value = serde_json::from_str(content)?;
+    let package = parsed.get("name").and_then(|n| n.as_str()).map(String::from);
+
+    let mut dependencies = Vec::new();
+    let sections = [
+        ("dependencies", DependencyKind::Normal),
+        ("devDependencies", DependencyKind::Dev),
+        ("optionalDependencies", DependencyKind::Optional),
+    ];
+    for (section, kind) in sections {
+        if let Some(map) = parsed.get(section).and_then(|s| s.as_object()) {
+            for (name, req) in map {
+                dependencies.push(Dependency {
+                    name: name.clone(),
+                    requirement: req.as_str().unwrap_or("*").to_string(),
+                    resolved: None,
+                    kind,
+                });
+            }
+        }
+    }
+
+    Ok(DependencyManifest {
+        path: path.to_path_buf(),
+        ecosystem: Ecosystem::Npm,
+        package,
+        dependencies,
+    })
+}
+
+fn parse_pyproject(path: &Path, content: &str) -> Result<DependencyManifest> {
+    let parsed: toml::Table = content.parse()?;
+    let project = parsed.get("project");
+    let poetry = parsed.get("tool").and_then(|t| t.get("poetry"));
+
+    let package = project
+        .or(poetry)
+        .and_then(|p| p.get("name"))
+        .and_then(|n| n.as_str())
+        .map(String::from);
+
+    let mut dependencies = Vec::new();
+
+    // PEP 621: dependencies = ["requests>=2.0", ...]
+    if let Some(project) = project {
+        if let Some(list) = project.get("dependencies").and_then(|d| d.as_array()) {
+            dependencies.extend(list.iter().filter_map(|v| v.as_str()).map(|s| parse_pep508(s, DependencyKind::Normal)));
+        }
+        if let Some(groups) = project.get("optional-dependencies").and_then(|d| d.as_table()) {
+            for list in groups.values().filter_map(|v| v.as_array()) {
+                dependencies.extend(list.iter().filter_map(|v| v.as_str()).map(|s| parse_pep508(s, DependencyKind::Optional)));
+            }
+        }
+    }
+
+    // Poetry: [tool.poetry.dependencies] name = "^1.0"
+    if let Some(poetry) = poetry {
+        let sections = [
+            (poetry.get("dependencies"), DependencyKind::Normal),
+            (poetry.get("dev-dependencies"), DependencyKind::Dev),
+            (poetry.get("group").and_then(|g| g.get("dev")).and_then(|d| d.get("dependencies")), DependencyKind::Dev),
+        ];
+        for (table, kind) in sections {
+            let Some(table) = table.and_then(|t| t.as_table()) else { continue };
+            for (name, spec) in table.iter().filter(|(n, _)| n.as_str() != "python") {
+                let requirement = match spec {
+                    toml::Value::String(v) => v.clone(),
+                    other => other.get("version").and_then(|v| v.as_str()).unwrap_or("*").to_string(),
+                };
+                dependencies.push(Dependency {
+                    name: name.clone(),
+                    requirement,
+                    resolved: None,
+                    kind,
+                });
+            }
+        }
+    }
+
+    Ok(DependencyManifest {
+        path: path.to_path_buf(),
+        ecosystem: Ecosystem::Python,
+        package,
+        dependencies,
+    })
+}
+
+/// Split a PEP 508 requirement like `requests[socks]>=2.0; python_version>"3"`
+fn parse_pep508(spec: &str, kind: DependencyKind) -> Dependency {
+    let spec = spec.split(';').next().unwrap_or(spec).trim();
+    let end = spec
+        .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_' || c == '.'))
+        .unwrap_or(spec.len());
+    let name = spec[..end].to_string();
+    let rest = spec[end..].trim();
+    let rest = match rest.strip_prefix('[') {
+        Some(r) => r.split_once(']').map(|(_, v)| v.trim()).unwrap_or(""),
+        None => rest,
+    };
+
+    Dependency {
+        name,
+        requirement: if rest.is_empty() { "*".to_string() } else { rest.to_string() },
+        resolved: None,
+        kind,
+    }
+}
+
+/// Resolved versions from the ecosystem's lockfile (searching parent directories)
+fn load_lockfile(dir: &Path, ecosystem: Ecosystem) -> HashMap<String, String> {
+    let candidates: &[&str] = match ecosystem {
+        Ecosystem::Cargo => &["Cargo.lock"],
+        Ecosystem::Npm => &["package-lock.json"],
+        Ecosystem::Python => &["uv.lock", "poetry.lock"],
+    };
+
+    let Some(lock) = dir
+        .ancestors()
+        .flat_map(|d| candidates.iter().map(move |c| d.join(c)))
+        .find(|p| p.is_file())
+    else {
+        return HashMap::new();
+    };
+    let Ok(content) = fs::read_to_string(&lock) else {
+        return HashMap::new();
+    };
+
+    let mut versions = HashMap::new();
+    match ecosystem {
+        // Cargo.lock, uv.lock, and poetry.lock all use [[package]] name/version
+        Ecosystem::Cargo | Ecosystem::Python => {
+            let Ok(parsed) = content.parse::<toml::Table>() else {
+                return versions;
+            };
+            for pkg in parsed.get("package").and_then(|p| p.as_array()).into_iter().flatten() {
+                if let (Some(name), Some(version)) = (
+                    pkg.get("name").and_then(|n| n.as_str()),
+                    pkg.get("version").and_then(|v| v.as_str()),
+                ) {
+                    // Keep the first entry; multiple versions of a crate are rare at the top level
+                    versions
+                        .entry(normalize_name(ecosystem, name))
+                        .or_insert_with(|| version.to_string());
+                }
+            }
+        }
+        Ecosystem::Npm => {
+            let Ok(parsed) = serde_json::from_str::<serde_json::Value>(&content) else {
+                return versions;
+            };
+            if let Some(packages) = parsed.get("packages").and_then(|p| p.as_object()) {
+                for (key, pkg) in packages {
+                    let Some(name) = key.strip_prefix("node_modules/") else { continue };
+                    if name.contains("/node_modules/") {
+                        continue;
+                    }
+                    if let Some(version) = pkg.get("version").and_then(|v| v.as_str()) {
+                        versions.insert(name.to_string(), version.to_string());
+                    }
+                }
+            }
+        }
+    }
+    versions
+}
+
+/// Member manifests listed in a Cargo workspace
+fn cargo_workspace_members(root: &Path) -> Vec<PathBuf> {
+    let Ok(content) = fs::read_to_string(root.join("Cargo.toml")) else {
+        return Vec::new();
+    };
+    let Ok(parsed) = content.parse::<toml::Table>() else {
+        return Vec::new();
+    };
+    let Some(members) = parsed
+        .get("workspace")
+        .and_then(|w| w.get("members"))
+        .and_then(|m| m.as_array())
+    else {
+        return Vec::new();
+    };
+
+    let mut paths = Vec::new();
+    for pattern in members.iter().filter_map(|m| m.as_str()) {
+        let full = root.join(pattern).join("Cargo.toml");
+        let Ok(entries) = glob::glob(&full.to_string_lossy()) else { continue };
+        paths.extend(entries.flatten().filter(|p| p.is_file()));
+    }
+    paths.sort();
+    paths.dedup();
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_cargo_workspace() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            r#"[workspace]
+members = ["crates/*"]
+
+[workspace.dependencies]
+tokio = { version = "1", features = ["full"] }
+"#,
+        )
+        .unwrap();
+        fs::create_dir_all(dir.path().join("crates/app")).unwrap();
+        fs::write(
+            dir.path().join("crates/app/Cargo.toml"),
+            r#"[package]
+name = "app"
+
+[dependencies]
+tokio.workspace = true
+serde = "1.0"
+fastembed = { version = "4", optional = true }
+core-lib = { path = "../core", package = "core" }
+
+[dev-dependencies]
+tempfile = "3"
+"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("Cargo.lock"),
+            "version = 3\n\n[[package]]\nname = \"tokio\"\nversion = \"1.38.0\"\n\n[[package]]\nname = \"serde\"\nversion = \"1.0.203\"\n",
+        )
+        .unwrap();
+
+        let manifests = discover(dir.path());
+        assert_eq!(manifests.len(), 2);
+        assert_eq!(manifests[0].dependencies[0].resolved.as_deref(), Some("1.38.0"));
+
+        let app = &manifests[1];
+        assert_eq!(app.package.as_deref(), Some("app"));
+        let by_name = |n: &str| app.dependencies.iter().find(|d| d.name == n).unwrap();
+        assert_eq!(by_name("tokio").requirement, "workspace");
+        assert_eq!(by_name("serde").resolved.as_deref(), Some("1.0.203"));
+        assert_eq!(by_name("fastembed").kind, DependencyKind::Optional);
+        assert_eq!(by_name("core").requirement, "path:../core");
+        assert_eq!(by_name("tempfile").kind, DependencyKind::Dev);
+
+        let found = find(&manifests, "tokio");
+        assert_eq!(found.len(), 2);
+
+        let ctx = to_system_context(&manifests, dir.path(), 2);
+        assert!(ctx.contains("Cargo.toml:\n  tokio 1.38.0"));
+        assert!(!ctx.contains("tempfile"));
+        assert!(ctx.contains("[2 more]"));
+    }
+
+    #[test]
+    fn test_parse_package_json() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("package.json"),
+            r#"{"name": "web", "dependencies": {"react": "^18.2.0"}, "devDependencies": {"vitest": "^1.0.0"}}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("package-lock.json"),
+            r#"{"packages": {"": {}, "node_modules/react": {"version": "18.3.1"}, "node_modules/a/node_modules/react": {"version": "17.0.0"}}}"#,
+        )
+        .unwrap();
+
+        let manifest = DependencyManifest::load(&dir.path().join("package.json")).unwrap();
+        assert_eq!(manifest.ecosystem, Ecosystem::Npm);
+        assert_eq!(manifest.dependencies[0].resolved.as_deref(), Some("18.3.1"));
+        assert_eq!(manifest.dependencies[1].kind, DependencyKind::Dev);
+        assert!(manifest.dependencies[1].resolved.is_none());
+    }
+
+    #[test]
+    fn test_parse_pyproject() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("pyproject.toml"),
+            r#"[project]
+name = "svc"
+dependencies = ["requests[socks]>=2.31; python_version > '3.8'", "Flask"]
+
+[project.optional-dependencies]
+test = ["pytest>=8"]
+"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("uv.lock"),
+            "version = 1\n\n[[package]]\nname = \"requests\"\nversion = \"2.32.3\"\n\n[[package]]\nname = \"flask\"\nversion = \"3.0.3\"\n",
+        )
+        .unwrap();
+
+        let manifest = DependencyManifest::load(&dir.path().join("pyproject.toml")).unwrap();
+        let deps = &manifest.dependencies;
+        assert_eq!(deps[0].name, "requests");
+        assert_eq!(deps[0].requirement, ">=2.31");
+        assert_eq!(deps[0].resolved.as_deref(), Some("2.32.3"));
+        assert_eq!(deps[1].requirement, "*");
+        assert_eq!(deps[1].resolved.as_deref(), Some("3.0.3"));
+        assert_eq!(deps[2].kind, DependencyKind::Optional);
+    }
+
+    #[test]
+    fn test_parse_poetry() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("pyproject.toml");
+        fs::write(
+            &path,
+            r#"[tool.poetry]
+name = "legacy"
+
+[tool.poetry.dependencies]
+python = "^3.10"
+numpy = { version = "^1.26", optional = true }
+
+[tool.poetry.group.dev.dependencies]
+black = "^24"
+"#,
+        )
+        .unwrap();
+
+        let manifest = DependencyManifest::load(&path).unwrap();
+        assert_eq!(manifest.package.as_deref(), Some("legacy"));
+        assert_eq!(manifest.dependencies.len(), 2);
+        assert_eq!(manifest.dependencies[0].requirement, "^1.26");
+        assert_eq!(manifest.dependencies[1].kind, DependencyKind::Dev);
+    }
+}
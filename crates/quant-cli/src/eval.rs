@@ -0,0 +1,602 @@
+//! Agent task suites for comparing models (`quant eval`)
+//!
+//! A suite is a YAML file of tasks, each run by the agent several times per
+//! model:
+//!
+//! ```yaml
+//! name: python basics
+//! models: [qwen2.5-coder:7b, llama3.2]
+//! runs: 3
+//! tasks:
+//!   - name: fix_add
+//!     setup: printf 'def add(a, b):\n    return a - b\n' > calc.py
+//!     prompt: Fix the bug in calc.py so add returns the sum
+//!     check: python3 -c 'import calc; assert calc.add(2, 3) == 5'
+//! ```
+//!
+//! Every run starts in a fresh temporary directory: `setup` runs there with
+//! `sh -c`, the agent works on `prompt` with every tool call auto-approved,
+//! and the run succeeds when `check` exits with 0. Both scripts get
+//! `QUANT_EVAL_SUITE_DIR` (the suite file's directory, for copying fixtures),
+//! and `check` also gets `QUANT_EVAL_RESPONSE`, the agent's final answer.
+
+use anyhow::{bail, Context, Result};
+use llm_core::OllamaClient;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::agent::{AgentConfig, AgentLoop};
+use crate::config::ModelOptions;
+use crate::tools::builtin::create_default_registry;
+use crate::tools::router::ToolRouter;
+use crate::tools::security::TerminalConfirmation;
+
+// ANSI colors
+const GREEN: &str = "\x1b[92m";
+const YELLOW: &str = "\x1b[93m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// Default iteration limit for a task
+const DEFAULT_MAX_ITERATIONS: usize = 20;
+
+/// Output kept from a failed check, in characters
+const CHECK_OUTPUT_CHARS: usize = 500;
+
+/// A parsed task suite
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Suite {
+    pub name: Option<String>,
+    /// Models to compare when none are given on the command line
+    #[serde(default)]
+    pub models: Vec<String>,
+    /// Runs per task and model
+    #[serde(default = "default_runs")]
+    pub runs: usize,
+    pub max_iterations: Option<usize>,
+    /// Time limit for one agent run, in seconds
+    pub timeout: Option<u64>,
+    pub tasks: Vec<Task>,
+    /// Directory of the suite file
+    #[serde(skip)]
+    pub dir: PathBuf,
+}
+
+/// One task of a suite
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Task {
+    pub name: String,
+    pub prompt: String,
+    /// Shell script that prepares the run's directory
+    pub setup: Option<String>,
+    /// Shell command that exits with 0 when the task was done
+    pub check: String,
+    pub max_iterations: Option<usize>,
+    pub timeout: Option<u64>,
+}
+
+fn default_runs() -> usize {
+    1
+}
+
+impl Suite {
+    /// Load and parse a suite file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read eval suite: {}", path.display()))?;
+        let mut suite =
+            Self::parse(&content).with_context(|| format!("Invalid eval suite: {}", path.display()))?;
+        suite.dir = path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .map_or_else(|| PathBuf::from("."), Path::to_path_buf);
+        suite.dir = suite.dir.canonicalize().unwrap_or(suite.dir);
+        Ok(suite)
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let suite: Self = serde_yaml::from_str(content).context("Failed to parse YAML")?;
+        if suite.tasks.is_empty() {
+            bail!("Suite has no tasks");
+        }
+        if suite.runs == 0 {
+            bail!("`runs` must be at least 1");
+        }
+        for (i, task) in suite.tasks.iter().enumerate() {
+            if task.name.trim().is_empty() {
+                bail!("Task {} has no name", i + 1);
+            }
+            if suite.tasks[..i].iter().any(|t| t.name == task.name) {
+                bail!("Duplicate task name '{}'", task.name);
+            }
+        }
+        Ok(suite)
+    }
+
+    /// Keep only the named tasks
+    pub fn retain_tasks(&mut self, names: &[String]) -> Result<()> {
+        if names.is_empty() {
+            return Ok(());
+        }
+        if let Some(unknown) = names.iter().find(|n| !self.tasks.iter().any(|t| &t.name == *n)) {
+            bail!("No task named '{}' in the suite", unknown);
+        }
+        self.tasks.retain(|t| names.contains(&t.name));
+        Ok(())
+    }
+}
+
+/// Outcome of one run of one task
+#[derive(Debug, Clone, Serialize)]
+pub struct RunResult {
+    pub model: String,
+    pub task: String,
+    pub run: usize,
+    pub success: bool,
+    pub iterations: usize,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub wall_ms: u64,
+    /// Why the setup or the agent failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Output of a failed check
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub check_output: String,
+}
+
+impl RunResult {
+    fn tokens(&self) -> u32 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+/// Runs suite tasks with the agent
+pub struct Evaluator {
+    client: OllamaClient,
+    verbose: bool,
+}
+
+impl Evaluator {
+    pub fn new(client: OllamaClient) -> Self {
+        Self { client, verbose: true }
+    }
+
+    /// Print a line per run on stderr
+    pub fn with_verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Run every task `runs` times with each model and its sampling options
+    pub async fn run(&self, suite: &Suite, models: &[(String, ModelOptions)], runs: usize) -> Result<Report> {
+        let total = models.len() * suite.tasks.len() * runs;
+        let mut results = Vec::with_capacity(total);
+
+        for (model, options) in models {
+            for task in &suite.tasks {
+                for run in 1..=runs {
+                    if self.verbose {
+                        eprint!("[{}/{}] {} {} run {} ... ", results.len() + 1, total, model, task.name, run);
+                    }
+                    let result = self.run_once(suite, task, model, options, run).await?;
+                    if self.verbose {
+                        let (color, label) = if result.success { (GREEN, "ok") } else { (YELLOW, "failed") };
+                        eprintln!(
+                            "{}{}{} {}({} iterations, {} tokens, {:.1}s){}",
+                            color,
+                            label,
+                            RESET,
+                            DIM,
+                            result.iterations,
+                            result.tokens(),
+                            result.wall_ms as f64 / 1000.0,
+                            RESET
+                        );
+                    }
+                    results.push(result);
+                }
+            }
+        }
+
+        Ok(Report {
+            suite: suite.name.clone().unwrap_or_else(|| "eval".to_string()),
+            runs,
+            results,
+        })
+    }
+
+    async fn run_once(
+        &self,
+        suite: &Suite,
+        task: &Task,
+        model: &str,
+        options: &ModelOptions,
+        run: usize,
+    ) -> Result<RunResult> {
+        let dir = tempfile::Builder::new()
+            .prefix("quant-eval-")
+            .tempdir()
+            .context("Failed to create a run directory")?;
+        let mut env = vec![("QUANT_EVAL_SUITE_DIR", suite.dir.display().to_string())];
+        let mut result = RunResult {
+            model: model.to_string(),
+            task: task.name.clone(),
+            run,
+            success: false,
+            iterations: 0,
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            wall_ms: 0,
+            error: None,
+            check_output: String::new(),
+        };
+
+        if let Some(setup) = &task.setup {
+            let (ok, output) = run_script(setup, dir.path(), &env).await?;
+            if !ok {
+                result.error = Some(format!("setup failed: {}", output));
+                return Ok(result);
+            }
+        }
+
+        // The user's agent settings apply, except those that would blur the
+        // comparison: no fallback model and no confirmation prompts
+        let user_config = crate::config::UserConfig::load().unwrap_or_default();
+        let mut registry = create_default_registry();
+        if let Some(ref tools) = user_config.agent.tools {
+            registry.retain(tools);
+        }
+        let router = ToolRouter::new(registry, TerminalConfirmation::auto());
+        let config = AgentConfig::from_user_config(model, &user_config)
+            .with_max_iterations(task.max_iterations.or(suite.max_iterations).unwrap_or(DEFAULT_MAX_ITERATIONS))
+            .with_working_dir(dir.path().to_path_buf())
+            .with_auto_mode(true)
+            .with_verbose(false)
+            .with_live_output(false)
+            // Scores belong to the model under test
+            .with_fallback_model(None)
+            .with_sampling(options.clone());
+
+        let started = Instant::now();
+        let mut agent = AgentLoop::new(self.client.clone(), router, config);
+        let outcome = match task.timeout.or(suite.timeout) {
+            Some(secs) => tokio::time::timeout(Duration::from_secs(secs), agent.run(&task.prompt))
+                .await
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("timed out after {}s", secs))),
+            None => agent.run(&task.prompt).await,
+        };
+        result.wall_ms = started.elapsed().as_millis() as u64;
+
+        let response = match outcome {
+            Ok(state) => {
+                result.iterations = state.iteration;
+                result.prompt_tokens = state.token_usage.prompt_tokens;
+                result.completion_tokens = state.token_usage.completion_tokens;
+                result.error = state.error;
+                state.final_response.unwrap_or_default()
+            }
+            Err(e) => {
+                result.error = Some(format!("{:#}", e));
+                String::new()
+            }
+        };
+
+        env.push(("QUANT_EVAL_RESPONSE", response));
+        let (ok, output) = run_script(&task.check, dir.path(), &env).await?;
+        result.success = ok;
+        if !ok {
+            result.check_output = output;
+        }
+        Ok(result)
+    }
+}
+
+/// Run `script` with `sh -c` in `dir`; whether it succeeded, and its output
+async fn run_script(script: &str, dir: &Path, env: &[(&str, String)]) -> Result<(bool, String)> {
+    let output = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(script)
+        .current_dir(dir)
+        .envs(env.iter().map(|(k, v)| (k, v)))
+        .stdin(std::process::Stdio::null())
+        .output()
+        .await
+        .context("Failed to run shell command")?;
+
+    let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    let text = text.trim();
+    // Keep the end, where errors usually are
+    let skip = text.chars().count().saturating_sub(CHECK_OUTPUT_CHARS);
+    let mut text: String = text.chars().skip(skip).collect();
+    if !output.status.success() && text.is_empty() {
+        text = format!(
+            "exited with {}",
+            output.status.code().map_or("signal".to_string(), |c| c.to_string())
+        );
+    }
+    Ok((output.status.success(), text))
+}
+
+/// Results of an eval, with summaries per model and per task
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub suite: String,
+    pub runs: usize,
+    pub results: Vec<RunResult>,
+}
+
+/// Aggregate over a group of runs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Summary {
+    pub model: String,
+    /// Task name, or `None` for all tasks
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+    pub runs: usize,
+    pub successes: usize,
+    pub success_rate: f64,
+    pub avg_iterations: f64,
+    pub avg_tokens: f64,
+    pub avg_wall_secs: f64,
+}
+
+impl Summary {
+    fn of(model: &str, task: Option<&str>, results: &[&RunResult]) -> Self {
+        let runs = results.len();
+        let avg = |value: &dyn Fn(&RunResult) -> f64| {
+            if runs == 0 {
+                0.0
+            } else {
+                results.iter().map(|r| value(r)).sum::<f64>() / runs as f64
+            }
+        };
+        let successes = results.iter().filter(|r| r.success).count();
+        Self {
+            model: model.to_string(),
+            task: task.map(str::to_string),
+            runs,
+            successes,
+            success_rate: avg(&|r| if r.success { 1.0 } else { 0.0 }),
+            avg_iterations: avg(&|r| r.iterations as f64),
+            avg_tokens: avg(&|r| r.tokens() as f64),
+            avg_wall_secs: avg(&|r| r.wall_ms as f64 / 1000.0),
+        }
+    }
+}
+
+impl Report {
+    fn models(&self) -> Vec<&str> {
+        let mut models: Vec<&str> = Vec::new();
+        for result in &self.results {
+            if !models.contains(&result.model.as_str()) {
+                models.push(&result.model);
+            }
+        }
+        models
+    }
+
+    fn tasks(&self) -> Vec<&str> {
+        let mut tasks: Vec<&str> = Vec::new();
+        for result in &self.results {
+            if !tasks.contains(&result.task.as_str()) {
+                tasks.push(&result.task);
+            }
+        }
+        tasks
+    }
+
+    /// One summary per model over all tasks, best first
+    pub fn model_summaries(&self) -> Vec<Summary> {
+        let mut summaries: Vec<Summary> = self
+            .models()
+            .into_iter()
+            .map(|model| {
+                let runs: Vec<&RunResult> = self.results.iter().filter(|r| r.model == model).collect();
+                Summary::of(model, None, &runs)
+            })
+            .collect();
+        summaries.sort_by(|a, b| {
+            b.success_rate
+                .total_cmp(&a.success_rate)
+                .then(a.avg_wall_secs.total_cmp(&b.avg_wall_secs))
+        });
+        summaries
+    }
+
+    /// One summary per task and model, in suite order
+    pub fn task_summaries(&self) -> Vec<Summary> {
+        let mut groups: HashMap<(&str, &str), Vec<&RunResult>> = HashMap::new();
+        for result in &self.results {
+            groups.entry((&result.task, &result.model)).or_default().push(result);
+        }
+        let mut summaries = Vec::new();
+        for task in self.tasks() {
+            for model in self.models() {
+                if let Some(runs) = groups.get(&(task, model)) {
+                    summaries.push(Summary::of(model, Some(task), runs));
+                }
+            }
+        }
+        summaries
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&serde_json::json!({
+            "suite": self.suite,
+            "runs": self.runs,
+            "models": self.model_summaries(),
+            "tasks": self.task_summaries(),
+            "results": self.results,
+        }))?)
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# Eval: {}\n\n", self.suite);
+        out.push_str(&format!(
+            "{} task(s), {} run(s) per task and model.\n\n",
+            self.tasks().len(),
+            self.runs
+        ));
+        out.push_str(&summary_table(&self.model_summaries()));
+
+        let task_summaries = self.task_summaries();
+        for task in self.tasks() {
+            let rows: Vec<Summary> =
+                task_summaries.iter().filter(|s| s.task.as_deref() == Some(task)).cloned().collect();
+            out.push_str(&format!("\n## {}\n\n", task));
+            out.push_str(&summary_table(&rows));
+        }
+
+        let failures: Vec<&RunResult> = self.results.iter().filter(|r| !r.success).collect();
+        if !failures.is_empty() {
+            out.push_str("\n## Failures\n\n");
+            for failure in failures {
+                let reason = failure
+                    .error
+                    .as_deref()
+                    .unwrap_or(&failure.check_output)
+                    .lines()
+                    .last()
+                    .unwrap_or("check failed");
+                out.push_str(&format!(
+                    "- {} / {} / run {}: {}\n",
+                    failure.model, failure.task, failure.run, reason
+                ));
+            }
+        }
+        out
+    }
+}
+
+fn summary_table(summaries: &[Summary]) -> String {
+    let mut out = String::from("| Model | Success | Avg iterations | Avg tokens | Avg time |\n");
+    out.push_str("|---|---|---|---|---|\n");
+    for s in summaries {
+        out.push_str(&format!(
+            "| {} | {}/{} ({:.0}%) | {:.1} | {:.0} | {:.1}s |\n",
+            s.model,
+            s.successes,
+            s.runs,
+            s.success_rate * 100.0,
+            s.avg_iterations,
+            s.avg_tokens,
+            s.avg_wall_secs
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use llm_core::mock::{MockOllama, MockResponse};
+
+    const SUITE: &str = r#"
+name: basics
+models: [a, b]
+runs: 2
+tasks:
+  - name: answer
+    setup: echo hello > hello.txt
+    prompt: What is the answer?
+    check: test -f hello.txt && test "$QUANT_EVAL_RESPONSE" = 42
+  - name: other
+    prompt: Anything
+    check: "true"
+    max_iterations: 3
+"#;
+
+    fn result(model: &str, task: &str, success: bool, iterations: usize, wall_ms: u64) -> RunResult {
+        RunResult {
+            model: model.to_string(),
+            task: task.to_string(),
+            run: 1,
+            success,
+            iterations,
+            prompt_tokens: 100,
+            completion_tokens: 10,
+            wall_ms,
+            error: None,
+            check_output: if success { String::new() } else { "assertion failed".to_string() },
+        }
+    }
+
+    #[test]
+    fn test_parse_suite() {
+        let mut suite = Suite::parse(SUITE).unwrap();
+        assert_eq!(suite.models, vec!["a", "b"]);
+        assert_eq!(suite.runs, 2);
+        assert_eq!(suite.tasks[1].max_iterations, Some(3));
+        assert!(suite.tasks[1].setup.is_none());
+
+        suite.retain_tasks(&["other".to_string()]).unwrap();
+        assert_eq!(suite.tasks.len(), 1);
+        assert!(suite.retain_tasks(&["missing".to_string()]).is_err());
+
+        assert!(Suite::parse("tasks: []\n").is_err());
+        assert!(Suite::parse("runs: 0\ntasks:\n  - {name: a, prompt: p, check: c}\n").is_err());
+        assert!(Suite::parse("tasks:\n  - {name: a, prompt: p}\n").is_err());
+        assert!(Suite::parse("tasks:\n  - {name: a, prompt: p, check: c, typo: 1}\n").is_err());
+        assert!(Suite::parse("tasks:\n  - {name: a, prompt: p, check: c}\n  - {name: a, prompt: q, check: c}\n").is_err());
+    }
+
+    #[test]
+    fn test_report_summaries_and_markdown() {
+        let report = Report {
+            suite: "basics".to_string(),
+            runs: 2,
+            results: vec![
+                result("slow", "answer", true, 4, 4000),
+                result("slow", "answer", false, 6, 6000),
+                result("fast", "answer", true, 2, 1000),
+                result("fast", "answer", true, 2, 3000),
+            ],
+        };
+
+        let models = report.model_summaries();
+        assert_eq!(models[0].model, "fast");
+        assert_eq!(models[0].success_rate, 1.0);
+        assert_eq!(models[0].avg_wall_secs, 2.0);
+        assert_eq!(models[1].successes, 1);
+        assert_eq!(models[1].avg_iterations, 5.0);
+        assert_eq!(report.task_summaries().len(), 2);
+
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("| fast | 2/2 (100%) | 2.0 | 110 | 2.0s |"));
+        assert!(markdown.contains("## answer"));
+        assert!(markdown.contains("- slow / answer / run 1: assertion failed"));
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["models"][0]["model"], "fast");
+        assert_eq!(json["results"].as_array().unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_run_suite_against_mock_ollama() {
+        let mut suite = Suite::parse(SUITE).unwrap();
+        suite.retain_tasks(&["answer".to_string()]).unwrap();
+        let mock = MockOllama::new([
+            MockResponse::text("42").with_tokens(50, 1),
+            MockResponse::text("41").with_tokens(50, 1),
+        ])
+        .await
+        .unwrap();
+
+        let evaluator = Evaluator::new(mock.client()).with_verbose(false);
+        let report = evaluator.run(&suite, &[("a".to_string(), ModelOptions::default())], 2).await.unwrap();
+
+        assert_eq!(report.results.len(), 2);
+        assert!(report.results[0].success);
+        assert!(!report.results[1].success);
+        assert_eq!(report.results[0].iterations, 1);
+        assert_eq!(report.results[0].prompt_tokens, 50);
+        assert_eq!(report.model_summaries()[0].successes, 1);
+        assert!(mock.prompts().iter().all(|p| p.model() == Some("a")));
+    }
+}
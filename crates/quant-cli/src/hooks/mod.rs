@@ -3,22 +3,101 @@
 //! Provides extensibility through pre/post hooks for:
 //! - Agent start/finish
 //! - Tool execution (before/after)
+//! - Tool output, one line at a time while a tool runs
 //! - Iteration start/end
+//! - The task prompt before it is sent (`pre_prompt`) and the final
+//!   response (`post_response`), which hooks can rewrite
+//! - Saving the session (`session_save`)
+//! - A tool call waiting for the user to confirm it (`confirmation_required`)
 //!
 //! Hooks can be defined in:
-//! - QUANT.md file
-//! - quant.toml config
-//! - Environment variables
+//! - The user config (`~/.config/quant/config.toml`), as `[[hooks]]` tables
+//! - Every QUANT.md that applies, in its front matter
+//! - The project config (`.quant/config.toml`), as `[[hooks]]` tables
+//!
+//! They are loaded in that order, and a hook with the same name as an earlier
+//! one replaces it, so a project can override or disable (`enabled = false`)
+//! a user-wide hook.
+//!
+//! Every hook gets `QUANT_*` environment variables. Hooks with
+//! `json_input: true` also receive a JSON document on stdin (schema version 1):
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "event": "tool_before",
+//!   "hook": "guard-paths",
+//!   "working_dir": "/home/me/project",
+//!   "iteration": 3,
+//!   "tool_name": "bash",
+//!   "tool_args": {"command": "rm -rf ../other"},
+//!   "tool_result": null,
+//!   "tool_success": null,
+//!   "output_line": null,
+//!   "task": "clean up build output",
+//!   "agent_success": null,
+//!   "error": null,
+//!   "recent_tools": [
+//!     {"name": "file_read", "arguments": {"path": "Cargo.toml"}, "success": true, "output": "[package]..."}
+//!   ]
+//! }
+//! ```
+//!
+//! `tool_args` is the parsed argument object, `recent_tools` holds the last
+//! [`HOOK_TOOL_HISTORY`] tool calls (oldest first, output cut to
+//! [`HOOK_HISTORY_OUTPUT_CHARS`] characters), and fields that don't apply to the
+//! event are `null`. A failing `tool_before` hook with `abort_on_failure: true`
+//! blocks the tool call; its output is passed to the model as the reason.
+//!
+//! `tool_output` hooks run once per line a tool prints (`output_line`,
+//! `QUANT_TOOL_OUTPUT_LINE`) while it is still running, so keep them fast and
+//! narrow them with `tool_filter`.
+//!
+//! A hook can print a JSON decision instead of relying on its exit code:
+//!
+//! ```json
+//! {"decision": "block", "reason": "responses must not include secrets"}
+//! {"decision": "modify", "content": "rewritten prompt or response"}
+//! {"decision": "allow"}
+//! ```
+//!
+//! `block` works like a failing hook with `abort_on_failure`. `modify`
+//! replaces the text of `pre_prompt` (`prompt`, `QUANT_PROMPT`) and
+//! `post_response` (`response`, `QUANT_RESPONSE`) hooks; for these two events
+//! any other non-empty stdout replaces the text as well. Each hook sees the
+//! previous one's rewrite. A blocked prompt stops the run, and a blocked
+//! response is sent back to the model with the reason so it can revise it.
+//!
+//! Instead of a `command`, a hook can name a built-in `action`. `notify`
+//! shows a desktop notification describing the event (see [`notify`]), e.g.
+//! on `agent_finish` or `confirmation_required` to get pinged by a
+//! backgrounded agent.
+
+pub mod notify;
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
 use tokio::process::Command;
 use tokio::time::{timeout, Duration};
 use tracing::{debug, info, warn};
 
+use crate::config::{find_project_config, user_config_path};
+use crate::project::ProjectContext;
+
+/// Version of the JSON payload written to `json_input` hooks
+pub const HOOK_PAYLOAD_VERSION: u32 = 1;
+
+/// Number of recent tool calls included in hook payloads
+pub const HOOK_TOOL_HISTORY: usize = 10;
+
+/// Characters of each recent tool's output included in hook payloads
+pub const HOOK_HISTORY_OUTPUT_CHARS: usize = 1000;
+
 /// Hook execution points
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -39,6 +118,16 @@ pub enum HookEvent {
     ToolBeforeNamed,
     /// After a specific tool (use with tool_name filter)
     ToolAfterNamed,
+    /// For each line of output while a tool runs
+    ToolOutput,
+    /// Before the task prompt is sent; stdout can rewrite it
+    PrePrompt,
+    /// After the model's final response; stdout can rewrite it
+    PostResponse,
+    /// After the session is saved
+    SessionSave,
+    /// Before asking the user to confirm a tool call
+    ConfirmationRequired,
 }
 
 impl HookEvent {
@@ -52,8 +141,53 @@ impl HookEvent {
             Self::ToolAfter => "tool_after",
             Self::ToolBeforeNamed => "tool_before_named",
             Self::ToolAfterNamed => "tool_after_named",
+            Self::ToolOutput => "tool_output",
+            Self::PrePrompt => "pre_prompt",
+            Self::PostResponse => "post_response",
+            Self::SessionSave => "session_save",
+            Self::ConfirmationRequired => "confirmation_required",
         }
     }
+
+    /// Whether plain stdout from the hook replaces the prompt or response
+    pub fn rewrites_text(&self) -> bool {
+        matches!(self, Self::PrePrompt | Self::PostResponse)
+    }
+}
+
+/// A decision printed by a hook as a JSON object on stdout
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum HookDecision {
+    /// Carry on unchanged
+    Allow,
+    /// Stop the action, as a failing `abort_on_failure` hook would
+    Block {
+        #[serde(default)]
+        reason: String,
+    },
+    /// Replace the prompt or response with `content`
+    Modify { content: String },
+}
+
+impl HookDecision {
+    /// Parse a decision from hook stdout; `None` unless it is a decision object
+    pub fn parse(stdout: &str) -> Option<Self> {
+        let trimmed = stdout.trim();
+        if !trimmed.starts_with('{') {
+            return None;
+        }
+        serde_json::from_str(trimmed).ok()
+    }
+}
+
+/// Outcome of running the hooks that may rewrite a prompt or response
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rewrite {
+    /// The text after every hook ran (unchanged if none modified it)
+    Text(String),
+    /// A hook blocked it
+    Blocked { hook: String, reason: String },
 }
 
 /// A hook definition
@@ -64,7 +198,11 @@ pub struct Hook {
     /// When to run this hook
     pub event: HookEvent,
     /// Command to execute
+    #[serde(default)]
     pub command: String,
+    /// Built-in action to run instead of a command
+    #[serde(default)]
+    pub action: Option<HookAction>,
     /// Optional: only run for specific tool names
     #[serde(default)]
     pub tool_filter: Option<String>,
@@ -77,6 +215,91 @@ pub struct Hook {
     /// Whether this hook is enabled
     #[serde(default = "default_enabled")]
     pub enabled: bool,
+    /// Write the context as JSON to the hook's stdin (see the module docs)
+    #[serde(default)]
+    pub json_input: bool,
+    /// File the hook was loaded from
+    #[serde(skip)]
+    pub origin: Option<HookOrigin>,
+}
+
+/// Built-in hook actions, which need no shell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookAction {
+    /// Show a desktop notification
+    Notify,
+}
+
+impl HookAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Notify => "notify",
+        }
+    }
+}
+
+/// Where a hook was defined
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookOrigin {
+    UserConfig(PathBuf),
+    QuantMd(PathBuf),
+    ProjectConfig(PathBuf),
+}
+
+impl HookOrigin {
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::UserConfig(path) | Self::QuantMd(path) | Self::ProjectConfig(path) => path,
+        }
+    }
+
+    /// Kind of file, e.g. "user config"
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::UserConfig(_) => "user config",
+            Self::QuantMd(_) => "QUANT.md",
+            Self::ProjectConfig(_) => "project config",
+        }
+    }
+}
+
+impl fmt::Display for HookOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.kind(), self.path().display())
+    }
+}
+
+/// Hooks in QUANT.md front matter
+fn quant_md_hooks(content: &str) -> Vec<Hook> {
+    if !content.starts_with("---") {
+        return Vec::new();
+    }
+    let Some(end) = content[3..].find("---").map(|i| i + 3) else {
+        return Vec::new();
+    };
+
+    #[derive(Deserialize)]
+    struct QuantMdFrontmatter {
+        #[serde(default)]
+        hooks: Vec<Hook>,
+    }
+
+    serde_yaml::from_str::<QuantMdFrontmatter>(&content[3..end])
+        .map(|frontmatter| frontmatter.hooks)
+        .unwrap_or_default()
+}
+
+/// `[[hooks]]` tables in a config file
+fn toml_hooks(content: &str) -> Result<Vec<Hook>> {
+    #[derive(Deserialize)]
+    struct ConfigHooks {
+        #[serde(default)]
+        hooks: Vec<Hook>,
+    }
+
+    let config: ConfigHooks = toml::from_str(content)?;
+    Ok(config.hooks)
 }
 
 fn default_timeout() -> u64 {
@@ -96,18 +319,73 @@ pub struct HookContext {
     pub iteration: Option<usize>,
     /// Tool name (if applicable)
     pub tool_name: Option<String>,
-    /// Tool arguments as JSON (if applicable)
-    pub tool_args: Option<String>,
+    /// Tool arguments (if applicable)
+    pub tool_args: Option<serde_json::Value>,
     /// Tool result (for after hooks)
     pub tool_result: Option<String>,
     /// Whether tool succeeded (for after hooks)
     pub tool_success: Option<bool>,
+    /// Line of output from the running tool (for output hooks)
+    pub output_line: Option<String>,
     /// Task description
     pub task: Option<String>,
     /// Agent finished successfully
     pub agent_success: Option<bool>,
     /// Error message (if any)
     pub error: Option<String>,
+    /// Most recent tool calls, oldest first
+    pub recent_tools: Vec<ToolHistoryEntry>,
+    /// Task prompt about to be sent (for pre_prompt hooks)
+    pub prompt: Option<String>,
+    /// Final model response (for post_response hooks)
+    pub response: Option<String>,
+    /// Saved session ID (for session_save hooks)
+    pub session_id: Option<String>,
+    /// Saved session file (for session_save hooks)
+    pub session_path: Option<PathBuf>,
+}
+
+/// A completed tool call, as reported to hooks
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolHistoryEntry {
+    pub name: String,
+    pub arguments: serde_json::Value,
+    pub success: bool,
+    /// Output, cut to [`HOOK_HISTORY_OUTPUT_CHARS`] characters
+    pub output: String,
+}
+
+impl ToolHistoryEntry {
+    pub fn new(name: &str, arguments: &serde_json::Value, success: bool, output: &str) -> Self {
+        let output = match output.char_indices().nth(HOOK_HISTORY_OUTPUT_CHARS) {
+            Some((end, _)) => format!("{}...[truncated]", &output[..end]),
+            None => output.to_string(),
+        };
+        Self {
+            name: name.to_string(),
+            arguments: arguments.clone(),
+            success,
+            output,
+        }
+    }
+}
+
+/// Record a tool call, keeping the last [`HOOK_TOOL_HISTORY`] entries
+pub fn push_tool_history(history: &mut Vec<ToolHistoryEntry>, entry: ToolHistoryEntry) {
+    history.push(entry);
+    if history.len() > HOOK_TOOL_HISTORY {
+        history.remove(0);
+    }
+}
+
+/// JSON document written to the stdin of `json_input` hooks
+#[derive(Debug, Serialize)]
+pub struct HookPayload<'a> {
+    pub version: u32,
+    pub event: HookEvent,
+    pub hook: &'a str,
+    #[serde(flatten)]
+    pub context: &'a HookContext,
 }
 
 impl Default for HookContext {
@@ -119,9 +397,15 @@ impl Default for HookContext {
             tool_args: None,
             tool_result: None,
             tool_success: None,
+            output_line: None,
             task: None,
             agent_success: None,
             error: None,
+            recent_tools: Vec::new(),
+            prompt: None,
+            response: None,
+            session_id: None,
+            session_path: None,
         }
     }
 }
@@ -141,7 +425,7 @@ impl HookContext {
 
     pub fn with_tool(mut self, name: &str, args: &serde_json::Value) -> Self {
         self.tool_name = Some(name.to_string());
-        self.tool_args = Some(args.to_string());
+        self.tool_args = Some(args.clone());
         self
     }
 
@@ -151,17 +435,51 @@ impl HookContext {
         self
     }
 
+    pub fn with_output_line(mut self, line: &str) -> Self {
+        self.output_line = Some(line.to_string());
+        self
+    }
+
     pub fn with_task(mut self, task: &str) -> Self {
         self.task = Some(task.to_string());
         self
     }
 
+    pub fn with_recent_tools(mut self, history: &[ToolHistoryEntry]) -> Self {
+        self.recent_tools = history.to_vec();
+        self
+    }
+
     pub fn with_agent_result(mut self, success: bool, error: Option<String>) -> Self {
         self.agent_success = Some(success);
         self.error = error;
         self
     }
 
+    pub fn with_prompt(mut self, prompt: &str) -> Self {
+        self.prompt = Some(prompt.to_string());
+        self
+    }
+
+    pub fn with_response(mut self, response: &str) -> Self {
+        self.response = Some(response.to_string());
+        self
+    }
+
+    pub fn with_session(mut self, id: &str, path: &std::path::Path) -> Self {
+        self.session_id = Some(id.to_string());
+        self.session_path = Some(path.to_path_buf());
+        self
+    }
+
+    /// Set the prompt or response an event's hooks may rewrite
+    fn with_text(self, event: HookEvent, text: &str) -> Self {
+        match event {
+            HookEvent::PostResponse => self.with_response(text),
+            _ => self.with_prompt(text),
+        }
+    }
+
     /// Convert to environment variables for subprocess
     pub fn to_env_vars(&self) -> HashMap<String, String> {
         let mut vars = HashMap::new();
@@ -177,23 +495,21 @@ impl HookContext {
         }
 
         if let Some(ref args) = self.tool_args {
-            vars.insert("QUANT_TOOL_ARGS".to_string(), args.clone());
+            vars.insert("QUANT_TOOL_ARGS".to_string(), args.to_string());
         }
 
         if let Some(ref result) = self.tool_result {
-            // Truncate for env var safety
-            let truncated = if result.len() > 4096 {
-                format!("{}...[truncated]", &result[..4096])
-            } else {
-                result.clone()
-            };
-            vars.insert("QUANT_TOOL_RESULT".to_string(), truncated);
+            vars.insert("QUANT_TOOL_RESULT".to_string(), truncate_env_value(result));
         }
 
         if let Some(success) = self.tool_success {
             vars.insert("QUANT_TOOL_SUCCESS".to_string(), success.to_string());
         }
 
+        if let Some(ref line) = self.output_line {
+            vars.insert("QUANT_TOOL_OUTPUT_LINE".to_string(), line.clone());
+        }
+
         if let Some(ref task) = self.task {
             vars.insert("QUANT_TASK".to_string(), task.clone());
         }
@@ -206,10 +522,36 @@ impl HookContext {
             vars.insert("QUANT_ERROR".to_string(), error.clone());
         }
 
+        if let Some(ref prompt) = self.prompt {
+            vars.insert("QUANT_PROMPT".to_string(), truncate_env_value(prompt));
+        }
+
+        if let Some(ref response) = self.response {
+            vars.insert("QUANT_RESPONSE".to_string(), truncate_env_value(response));
+        }
+
+        if let Some(ref id) = self.session_id {
+            vars.insert("QUANT_SESSION_ID".to_string(), id.clone());
+        }
+
+        if let Some(ref path) = self.session_path {
+            vars.insert("QUANT_SESSION_PATH".to_string(), path.display().to_string());
+        }
+
         vars
     }
 }
 
+/// Cut a value to 4 KB for env var safety (the JSON input has it in full)
+fn truncate_env_value(value: &str) -> String {
+    if value.len() > 4096 {
+        let end = (0..=4096).rev().find(|&i| value.is_char_boundary(i)).unwrap_or(0);
+        format!("{}...[truncated]", &value[..end])
+    } else {
+        value.to_string()
+    }
+}
+
 /// Result of running a hook
 #[derive(Debug)]
 pub struct HookResult {
@@ -223,6 +565,21 @@ pub struct HookResult {
     pub error: Option<String>,
     /// Execution time in milliseconds
     pub duration_ms: u64,
+    /// Failed with `abort_on_failure` set, or printed a block decision
+    pub aborts: bool,
+    /// Decision printed as JSON on stdout, or plain stdout of a hook that
+    /// rewrites text (as [`HookDecision::Modify`])
+    pub decision: Option<HookDecision>,
+}
+
+impl HookResult {
+    /// Why the hook stopped the action: its block reason, else its output
+    pub fn block_reason(&self) -> String {
+        match self.decision {
+            Some(HookDecision::Block { ref reason }) if !reason.is_empty() => reason.clone(),
+            _ => self.output.trim().to_string(),
+        }
+    }
 }
 
 /// Hook manager for registering and executing hooks
@@ -236,14 +593,61 @@ impl HookManager {
         Self { hooks: Vec::new() }
     }
 
-    /// Register a hook
+    /// Load hooks from the user config, every QUANT.md that applies, and the
+    /// project config, in that order
+    pub fn discover(working_dir: &Path, project_context: Option<&ProjectContext>) -> Self {
+        let mut origins = Vec::new();
+        if let Some(path) = user_config_path().filter(|path| path.is_file()) {
+            origins.push(HookOrigin::UserConfig(path));
+        }
+        let quant_files = project_context.iter().flat_map(|ctx| &ctx.quant_files);
+        origins.extend(quant_files.map(|quant_file| HookOrigin::QuantMd(quant_file.path.clone())));
+        if let Some(path) = find_project_config(working_dir) {
+            origins.push(HookOrigin::ProjectConfig(path));
+        }
+
+        let mut manager = Self::new();
+        for origin in origins {
+            match manager.load_from(origin.clone()) {
+                Ok(0) => {}
+                Ok(n) => info!(hooks = n, origin = %origin, "Loaded hooks"),
+                Err(e) => warn!(error = %e, origin = %origin, "Failed to load hooks"),
+            }
+        }
+        manager
+    }
+
+    /// Load the hooks defined in a QUANT.md or config file
+    pub fn load_from(&mut self, origin: HookOrigin) -> Result<usize> {
+        let content = std::fs::read_to_string(origin.path())?;
+        let mut hooks = match origin {
+            HookOrigin::QuantMd(_) => quant_md_hooks(&content),
+            HookOrigin::UserConfig(_) | HookOrigin::ProjectConfig(_) => toml_hooks(&content)?,
+        };
+        for hook in &mut hooks {
+            hook.origin = Some(origin.clone());
+        }
+        let count = hooks.len();
+        self.register_all(hooks);
+        Ok(count)
+    }
+
+    /// All registered hooks, including disabled ones
+    pub fn hooks(&self) -> &[Hook] {
+        &self.hooks
+    }
+
+    /// Register a hook, replacing any earlier hook with the same name
     pub fn register(&mut self, hook: Hook) {
         info!(
             name = %hook.name,
             event = hook.event.as_str(),
             "Registered hook"
         );
-        self.hooks.push(hook);
+        match self.hooks.iter_mut().find(|h| h.name == hook.name) {
+            Some(existing) => *existing = hook,
+            None => self.hooks.push(hook),
+        }
     }
 
     /// Register multiple hooks
@@ -254,30 +658,12 @@ impl HookManager {
     }
 
     /// Load hooks from QUANT.md frontmatter
+    #[allow(dead_code)]
     pub fn load_from_quant_md(&mut self, content: &str) -> Result<usize> {
-        // Parse YAML frontmatter if present
-        if !content.starts_with("---") {
-            return Ok(0);
-        }
-
-        let end = content[3..].find("---").map(|i| i + 3);
-        if let Some(end_idx) = end {
-            let yaml_content = &content[3..end_idx];
-
-            #[derive(Deserialize)]
-            struct QuantMdFrontmatter {
-                #[serde(default)]
-                hooks: Vec<Hook>,
-            }
-
-            if let Ok(frontmatter) = serde_yaml::from_str::<QuantMdFrontmatter>(yaml_content) {
-                let count = frontmatter.hooks.len();
-                self.register_all(frontmatter.hooks);
-                return Ok(count);
-            }
-        }
-
-        Ok(0)
+        let hooks = quant_md_hooks(content);
+        let count = hooks.len();
+        self.register_all(hooks);
+        Ok(count)
     }
 
     /// Get hooks for a specific event
@@ -319,7 +705,7 @@ impl HookManager {
 
         for hook in hooks {
             let result = self.run_hook(hook, ctx).await;
-            let should_abort = !result.success && hook.abort_on_failure;
+            let should_abort = result.aborts;
 
             results.push(result);
 
@@ -327,7 +713,7 @@ impl HookManager {
                 warn!(
                     hook = %hook.name,
                     event = event.as_str(),
-                    "Hook failed with abort_on_failure=true, stopping hook chain"
+                    "Hook blocked or failed with abort_on_failure=true, stopping hook chain"
                 );
                 break;
             }
@@ -336,9 +722,45 @@ impl HookManager {
         results
     }
 
+    /// Run the hooks that may rewrite a prompt or response, in order
+    ///
+    /// Each hook sees the text as the previous one left it.
+    pub async fn rewrite(&self, event: HookEvent, ctx: &HookContext, text: &str) -> Rewrite {
+        let mut text = text.to_string();
+        for hook in self.hooks_for_event(event, None) {
+            let result = self.run_hook(hook, &ctx.clone().with_text(event, &text)).await;
+            if result.aborts {
+                warn!(hook = %hook.name, event = event.as_str(), "Hook blocked the text");
+                return Rewrite::Blocked {
+                    reason: result.block_reason(),
+                    hook: result.name,
+                };
+            }
+            if let Some(HookDecision::Modify { content }) = result.decision {
+                debug!(hook = %hook.name, event = event.as_str(), "Hook rewrote the text");
+                text = content;
+            }
+        }
+        Rewrite::Text(text)
+    }
+
     /// Execute a single hook
     async fn run_hook(&self, hook: &Hook, ctx: &HookContext) -> HookResult {
+        if let Some(action) = hook.action {
+            return self.run_action(hook, action, ctx).await;
+        }
         let start = std::time::Instant::now();
+        if hook.command.trim().is_empty() {
+            return HookResult {
+                name: hook.name.clone(),
+                success: false,
+                output: String::new(),
+                error: Some("Hook has neither a command nor an action".to_string()),
+                duration_ms: 0,
+                aborts: hook.abort_on_failure,
+                decision: None,
+            };
+        }
 
         debug!(name = %hook.name, command = %hook.command, "Executing hook");
 
@@ -350,7 +772,21 @@ impl HookManager {
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
 
-        let result = timeout(Duration::from_secs(hook.timeout_secs), cmd.output()).await;
+        let payload = if hook.json_input {
+            cmd.stdin(Stdio::piped());
+            let payload = HookPayload {
+                version: HOOK_PAYLOAD_VERSION,
+                event: hook.event,
+                hook: &hook.name,
+                context: ctx,
+            };
+            serde_json::to_vec(&payload).ok()
+        } else {
+            cmd.stdin(Stdio::null());
+            None
+        };
+
+        let result = timeout(Duration::from_secs(hook.timeout_secs), run_command(cmd, payload)).await;
 
         let duration_ms = start.elapsed().as_millis() as u64;
 
@@ -366,12 +802,19 @@ impl HookManager {
 
                 if output.status.success() {
                     debug!(name = %hook.name, duration_ms, "Hook succeeded");
+                    let decision = HookDecision::parse(&stdout).or_else(|| {
+                        let text = stdout.trim_end_matches('\n');
+                        (hook.event.rewrites_text() && !text.trim().is_empty())
+                            .then(|| HookDecision::Modify { content: text.to_string() })
+                    });
                     HookResult {
                         name: hook.name.clone(),
                         success: true,
                         output: combined,
                         error: None,
                         duration_ms,
+                        aborts: matches!(decision, Some(HookDecision::Block { .. })),
+                        decision,
                     }
                 } else {
                     let code = output.status.code().unwrap_or(-1);
@@ -382,6 +825,8 @@ impl HookManager {
                         output: combined,
                         error: Some(format!("Exit code: {}", code)),
                         duration_ms,
+                        aborts: hook.abort_on_failure,
+                        decision: None,
                     }
                 }
             }
@@ -393,6 +838,8 @@ impl HookManager {
                     output: String::new(),
                     error: Some(format!("Execution error: {}", e)),
                     duration_ms,
+                    aborts: hook.abort_on_failure,
+                    decision: None,
                 }
             }
             Err(_) => {
@@ -403,12 +850,49 @@ impl HookManager {
                     output: String::new(),
                     error: Some(format!("Timed out after {}s", hook.timeout_secs)),
                     duration_ms,
+                    aborts: hook.abort_on_failure,
+                    decision: None,
                 }
             }
         }
     }
 
     /// Check if any hook would abort on failure
+    /// Whether any enabled hook runs for the event (and tool)
+    /// Run a built-in action
+    async fn run_action(&self, hook: &Hook, action: HookAction, ctx: &HookContext) -> HookResult {
+        let start = std::time::Instant::now();
+        debug!(name = %hook.name, action = action.as_str(), "Executing hook action");
+
+        let result = match action {
+            HookAction::Notify => {
+                let (title, body) = notify::message(hook.event, ctx);
+                match timeout(Duration::from_secs(hook.timeout_secs), notify::send(&title, &body)).await {
+                    Ok(result) => result,
+                    Err(_) => Err(anyhow::anyhow!("Timed out after {}s", hook.timeout_secs)),
+                }
+            }
+        };
+
+        let error = result.err().map(|e| format!("{:#}", e));
+        if let Some(ref error) = error {
+            warn!(name = %hook.name, error = %error, "Hook action failed");
+        }
+        HookResult {
+            name: hook.name.clone(),
+            success: error.is_none(),
+            output: String::new(),
+            aborts: error.is_some() && hook.abort_on_failure,
+            error,
+            duration_ms: start.elapsed().as_millis() as u64,
+            decision: None,
+        }
+    }
+
+    pub fn has_hooks(&self, event: HookEvent, tool_name: Option<&str>) -> bool {
+        !self.hooks_for_event(event, tool_name).is_empty()
+    }
+
     pub fn has_aborting_hooks(&self, event: HookEvent) -> bool {
         self.hooks
             .iter()
@@ -416,6 +900,18 @@ impl HookManager {
     }
 }
 
+/// Run a command to completion, writing `stdin` to it first
+async fn run_command(mut cmd: Command, stdin: Option<Vec<u8>>) -> std::io::Result<std::process::Output> {
+    let mut child = cmd.spawn()?;
+    if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        // A hook that exits without reading its input is not an error
+        if let Err(e) = pipe.write_all(&input).await {
+            debug!(error = %e, "Hook did not read its JSON input");
+        }
+    }
+    child.wait_with_output().await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -443,10 +939,13 @@ mod tests {
             name: "test_hook".to_string(),
             event: HookEvent::AgentStart,
             command: "echo 'starting'".to_string(),
+            action: None,
             tool_filter: None,
             timeout_secs: 30,
             abort_on_failure: false,
             enabled: true,
+            json_input: false,
+            origin: None,
         });
 
         assert_eq!(manager.hooks.len(), 1);
@@ -462,10 +961,13 @@ mod tests {
             name: "bash_hook".to_string(),
             event: HookEvent::ToolBefore,
             command: "echo 'before bash'".to_string(),
+            action: None,
             tool_filter: Some("bash".to_string()),
             timeout_secs: 30,
             abort_on_failure: false,
             enabled: true,
+            json_input: false,
+            origin: None,
         });
 
         // Should match when tool_name is "bash"
@@ -483,10 +985,13 @@ mod tests {
             name: "echo_hook".to_string(),
             event: HookEvent::AgentStart,
             command: "echo \"Task: $QUANT_TASK\"".to_string(),
+            action: None,
             tool_filter: None,
             timeout_secs: 5,
             abort_on_failure: false,
             enabled: true,
+            json_input: false,
+            origin: None,
         });
 
         let ctx = HookContext::new(temp_dir.path().to_path_buf())
@@ -508,10 +1013,13 @@ mod tests {
             name: "slow_hook".to_string(),
             event: HookEvent::AgentStart,
             command: "sleep 10".to_string(),
+            action: None,
             tool_filter: None,
             timeout_secs: 1,
             abort_on_failure: false,
             enabled: true,
+            json_input: false,
+            origin: None,
         });
 
         let ctx = HookContext::new(temp_dir.path().to_path_buf());
@@ -545,6 +1053,182 @@ Regular content here
         assert_eq!(manager.hooks.len(), 2);
     }
 
+    #[test]
+    fn test_load_from_config_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let user = temp_dir.path().join("config.toml");
+        std::fs::write(
+            &user,
+            "[ask]\ntemperature = 0.2\n\n[[hooks]]\nname = \"notify\"\nevent = \"agent_finish\"\ncommand = \"notify-send done\"\n\n[[hooks]]\nname = \"log\"\nevent = \"session_save\"\ncommand = \"echo saved\"\n",
+        )
+        .unwrap();
+        let quant_md = temp_dir.path().join("QUANT.md");
+        std::fs::write(&quant_md, "---\nhooks:\n  - name: notify\n    event: agent_finish\n    command: \"true\"\n    enabled: false\n---\n").unwrap();
+
+        let mut manager = HookManager::new();
+        assert_eq!(manager.load_from(HookOrigin::UserConfig(user.clone())).unwrap(), 2);
+        assert_eq!(manager.load_from(HookOrigin::QuantMd(quant_md.clone())).unwrap(), 1);
+
+        let hooks = manager.hooks();
+        assert_eq!(hooks.len(), 2);
+        assert_eq!(hooks[0].name, "notify");
+        assert!(!hooks[0].enabled);
+        assert_eq!(hooks[0].origin, Some(HookOrigin::QuantMd(quant_md)));
+        assert_eq!(hooks[1].origin, Some(HookOrigin::UserConfig(user)));
+
+        let bad = temp_dir.path().join("bad.toml");
+        std::fs::write(&bad, "[[hooks]]\nname = \"x\"\n").unwrap();
+        assert!(manager.load_from(HookOrigin::ProjectConfig(bad)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_notify_action_needs_no_command() {
+        let mut manager = HookManager::new();
+        let quant_md = "---\nhooks:\n  - name: ping\n    event: confirmation_required\n    action: notify\n  - name: empty\n    event: agent_finish\n---\n";
+        assert_eq!(manager.load_from_quant_md(quant_md).unwrap(), 2);
+        assert_eq!(manager.hooks()[0].action, Some(HookAction::Notify));
+        assert_eq!(manager.hooks()[0].event, HookEvent::ConfirmationRequired);
+
+        let temp_dir = TempDir::new().unwrap();
+        let ctx = HookContext::new(temp_dir.path().to_path_buf());
+        let results = manager.run_hooks(HookEvent::AgentFinish, &ctx, None).await;
+        assert!(!results[0].success);
+        assert!(results[0].error.as_ref().unwrap().contains("neither a command nor an action"));
+    }
+
+    #[test]
+    fn test_hook_payload_shape() {
+        let mut history = Vec::new();
+        for i in 0..HOOK_TOOL_HISTORY + 2 {
+            let output = "x".repeat(HOOK_HISTORY_OUTPUT_CHARS + i);
+            push_tool_history(&mut history, ToolHistoryEntry::new("file_read", &serde_json::json!({"path": i}), true, &output));
+        }
+        assert_eq!(history.len(), HOOK_TOOL_HISTORY);
+        assert_eq!(history[0].arguments["path"], 2);
+        assert!(history[0].output.ends_with("...[truncated]"));
+
+        let ctx = HookContext::new(PathBuf::from("/repo"))
+            .with_tool("bash", &serde_json::json!({"command": "ls ../"}))
+            .with_recent_tools(&history[..1]);
+        let payload = HookPayload {
+            version: HOOK_PAYLOAD_VERSION,
+            event: HookEvent::ToolBefore,
+            hook: "guard",
+            context: &ctx,
+        };
+        let json = serde_json::to_value(&payload).unwrap();
+        assert_eq!(json["version"], 1);
+        assert_eq!(json["event"], "tool_before");
+        assert_eq!(json["hook"], "guard");
+        assert_eq!(json["tool_args"]["command"], "ls ../");
+        assert_eq!(json["recent_tools"][0]["name"], "file_read");
+        assert!(json["tool_result"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_json_input_hook_blocks() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = HookManager::new();
+
+        manager.register(Hook {
+            name: "guard".to_string(),
+            event: HookEvent::ToolBefore,
+            command: "if grep -q '\\.\\./'; then echo 'outside repo'; exit 1; fi".to_string(),
+            action: None,
+            tool_filter: Some("bash".to_string()),
+            timeout_secs: 5,
+            abort_on_failure: true,
+            enabled: true,
+            json_input: true,
+            origin: None,
+        });
+
+        let ctx = HookContext::new(temp_dir.path().to_path_buf())
+            .with_tool("bash", &serde_json::json!({"command": "rm -rf ../other"}));
+        let results = manager.run_hooks(HookEvent::ToolBefore, &ctx, Some("bash")).await;
+        assert!(results[0].aborts);
+        assert_eq!(results[0].output.trim(), "outside repo");
+
+        let ctx = HookContext::new(temp_dir.path().to_path_buf())
+            .with_tool("bash", &serde_json::json!({"command": "ls"}));
+        let results = manager.run_hooks(HookEvent::ToolBefore, &ctx, Some("bash")).await;
+        assert!(results[0].success);
+        assert!(!results[0].aborts);
+    }
+
+    #[test]
+    fn test_parse_decision() {
+        assert_eq!(HookDecision::parse(r#"{"decision": "allow"}"#), Some(HookDecision::Allow));
+        assert_eq!(
+            HookDecision::parse("{\"decision\": \"block\"}\n"),
+            Some(HookDecision::Block { reason: String::new() })
+        );
+        assert_eq!(
+            HookDecision::parse(r#"{"decision": "modify", "content": "hi"}"#),
+            Some(HookDecision::Modify { content: "hi".to_string() })
+        );
+        assert_eq!(HookDecision::parse(r#"{"status": "ok"}"#), None);
+        assert_eq!(HookDecision::parse("plain text"), None);
+    }
+
+    #[tokio::test]
+    async fn test_rewrite_chains_hooks() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = HookManager::new();
+        for (name, command) in [
+            ("upper", "printf '%s' \"$QUANT_PROMPT\" | tr a-z A-Z"),
+            ("suffix", "printf '{\"decision\": \"modify\", \"content\": \"%s!\"}' \"$QUANT_PROMPT\""),
+            ("noop", "true"),
+        ] {
+            manager.register(Hook {
+                name: name.to_string(),
+                event: HookEvent::PrePrompt,
+                command: command.to_string(),
+                action: None,
+                tool_filter: None,
+                timeout_secs: 5,
+                abort_on_failure: false,
+                enabled: true,
+                json_input: false,
+                origin: None,
+            });
+        }
+
+        let ctx = HookContext::new(temp_dir.path().to_path_buf());
+        let rewrite = manager.rewrite(HookEvent::PrePrompt, &ctx, "fix it").await;
+        assert_eq!(rewrite, Rewrite::Text("FIX IT!".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_json_block_decision() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut manager = HookManager::new();
+        manager.register(Hook {
+            name: "lint".to_string(),
+            event: HookEvent::PostResponse,
+            command: "echo '{\"decision\": \"block\", \"reason\": \"no TODOs\"}'".to_string(),
+            action: None,
+            tool_filter: None,
+            timeout_secs: 5,
+            abort_on_failure: false,
+            enabled: true,
+            json_input: false,
+            origin: None,
+        });
+
+        let ctx = HookContext::new(temp_dir.path().to_path_buf());
+        let results = manager.run_hooks(HookEvent::PostResponse, &ctx, None).await;
+        assert!(results[0].success);
+        assert!(results[0].aborts);
+        assert_eq!(results[0].block_reason(), "no TODOs");
+
+        let rewrite = manager.rewrite(HookEvent::PostResponse, &ctx, "TODO: later").await;
+        assert_eq!(
+            rewrite,
+            Rewrite::Blocked { hook: "lint".to_string(), reason: "no TODOs".to_string() }
+        );
+    }
+
     #[test]
     fn test_disabled_hooks_not_run() {
         let mut manager = HookManager::new();
@@ -553,10 +1237,13 @@ Regular content here
             name: "disabled_hook".to_string(),
             event: HookEvent::AgentStart,
             command: "echo 'should not run'".to_string(),
+            action: None,
             tool_filter: None,
             timeout_secs: 30,
             abort_on_failure: false,
             enabled: false,
+            json_input: false,
+            origin: None,
         });
 
         assert_eq!(manager.hooks_for_event(HookEvent::AgentStart, None).len(), 0);
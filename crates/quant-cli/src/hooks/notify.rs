@@ -0,0 +1,122 @@
+//! Desktop notifications for the built-in `notify` hook action
+//!
+//! Uses `osascript` on macOS and `notify-send` on Linux. Where neither is
+//! available the terminal bell rings and the message is logged instead.
+
+use anyhow::{bail, Result};
+use std::io::Write;
+use tokio::process::Command;
+use tracing::info;
+
+use super::{HookContext, HookEvent};
+
+/// Longest notification body, in characters
+const MAX_BODY_CHARS: usize = 200;
+
+/// Show a desktop notification
+pub async fn send(title: &str, body: &str) -> Result<()> {
+    let Some(mut cmd) = command(title, body) else {
+        return fallback(title, body);
+    };
+    match cmd.output().await {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => bail!("{}", String::from_utf8_lossy(&output.stderr).trim()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => fallback(title, body),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Title and body describing an event
+pub fn message(event: HookEvent, ctx: &HookContext) -> (String, String) {
+    let task = ctx.task.clone().unwrap_or_default();
+    let (title, body) = match event {
+        HookEvent::AgentFinish if ctx.agent_success == Some(false) => {
+            ("quant: agent failed".to_string(), ctx.error.clone().unwrap_or(task))
+        }
+        HookEvent::AgentFinish => ("quant: agent finished".to_string(), task),
+        HookEvent::ConfirmationRequired => {
+            let tool = ctx.tool_name.as_deref().unwrap_or("tool");
+            let command = ctx.tool_args.as_ref().and_then(|args| args.get("command")).and_then(|c| c.as_str());
+            let body = match command {
+                Some(command) => format!("Allow {}: {}", tool, command),
+                None => format!("Allow {}?", tool),
+            };
+            ("quant: confirmation needed".to_string(), body)
+        }
+        _ => (format!("quant: {}", event.as_str()), task),
+    };
+    let body = match body.char_indices().nth(MAX_BODY_CHARS) {
+        Some((end, _)) => format!("{}...", &body[..end]),
+        None => body,
+    };
+    (title, body)
+}
+
+#[cfg(target_os = "macos")]
+fn command(title: &str, body: &str) -> Option<Command> {
+    let script = format!(
+        "display notification {} with title {}",
+        applescript_string(body),
+        applescript_string(title)
+    );
+    let mut cmd = Command::new("osascript");
+    cmd.arg("-e").arg(script);
+    Some(cmd)
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn command(title: &str, body: &str) -> Option<Command> {
+    let mut cmd = Command::new("notify-send");
+    cmd.arg("--app-name=quant").arg(title).arg(body);
+    Some(cmd)
+}
+
+#[cfg(not(unix))]
+fn command(_title: &str, _body: &str) -> Option<Command> {
+    None
+}
+
+/// Ring the terminal bell when there is no notification tool
+fn fallback(title: &str, body: &str) -> Result<()> {
+    info!(title, body, "Notification (no notifier available)");
+    eprint!("\x07");
+    std::io::stderr().flush()?;
+    Ok(())
+}
+
+/// Quote a string for AppleScript
+#[cfg(any(target_os = "macos", test))]
+fn applescript_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_message() {
+        let ctx = HookContext::new(PathBuf::from("/repo")).with_task("Fix the build");
+        let (title, body) = message(HookEvent::AgentFinish, &ctx.clone().with_agent_result(true, None));
+        assert_eq!(title, "quant: agent finished");
+        assert_eq!(body, "Fix the build");
+
+        let failed = ctx.clone().with_agent_result(false, Some("Max iterations".to_string()));
+        assert_eq!(message(HookEvent::AgentFinish, &failed).0, "quant: agent failed");
+        assert_eq!(message(HookEvent::AgentFinish, &failed).1, "Max iterations");
+
+        let confirm = ctx.with_tool("bash", &serde_json::json!({"command": "rm -rf target"}));
+        let (title, body) = message(HookEvent::ConfirmationRequired, &confirm);
+        assert_eq!(title, "quant: confirmation needed");
+        assert_eq!(body, "Allow bash: rm -rf target");
+
+        let long = HookContext::new(PathBuf::from("/repo")).with_task(&"é".repeat(300));
+        assert_eq!(message(HookEvent::AgentStart, &long).1.chars().count(), MAX_BODY_CHARS + 3);
+    }
+
+    #[test]
+    fn test_applescript_string() {
+        assert_eq!(applescript_string(r#"say "hi" \o/"#), r#""say \"hi\" \\o/""#);
+    }
+}
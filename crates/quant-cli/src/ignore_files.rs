@@ -0,0 +1,141 @@
+//! `.gitignore` and `.quantignore` support for context and search tools
+//!
+//! Files a project ignores (build output, generated code, secrets) are left
+//! out of context and search results, on top of [`crate::paths::IGNORED_DIRS`].
+//! `.quantignore` uses the same syntax and is read after `.gitignore` in the
+//! same directory, so it can also re-include (`!pattern`) what git ignores.
+//! Ignore files in nested directories apply below them, as in git. Turned
+//! off with `[context] ignore_files = false`.
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::{debug, warn};
+
+/// quant's own ignore file, for paths to hide from the model but not git
+pub const QUANT_IGNORE: &str = ".quantignore";
+
+/// Ignore files read in each directory, lowest precedence first
+const IGNORE_FILES: &[&str] = &[".gitignore", QUANT_IGNORE];
+
+/// Ignore rules for the files under a root directory
+pub struct IgnoreRules {
+    root: PathBuf,
+    enabled: bool,
+    /// Rules of each directory visited so far (None if it has no ignore file)
+    matchers: Mutex<HashMap<PathBuf, Option<Gitignore>>>,
+}
+
+impl IgnoreRules {
+    /// Rules for the files under `dir`, read from the root of the git
+    /// repository containing it (or `dir` itself outside one) downwards,
+    /// unless disabled in the config
+    pub fn for_dir(dir: &Path) -> Self {
+        let root = dir.ancestors().find(|d| d.join(".git").exists()).unwrap_or(dir);
+        let enabled = match crate::config::UserConfig::load() {
+            Ok(config) => config.context.ignore_files,
+            Err(e) => {
+                warn!(error = %e, "Failed to load config; respecting ignore files");
+                true
+            }
+        };
+        Self::new(root, enabled)
+    }
+
+    /// Rules under `root`, or none if not `enabled`
+    pub fn new(root: &Path, enabled: bool) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            enabled,
+            matchers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether ignore files are respected at all
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Whether `path` (a directory if `is_dir`) is ignored by an ignore file
+    /// in the root or a directory between it and the path
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+
+        // Deeper ignore files override shallower ones
+        let mut matchers = self.matchers.lock().unwrap_or_else(|e| e.into_inner());
+        let mut ignored = false;
+        let mut dir = self.root.clone();
+        let mut components = relative.components().peekable();
+        loop {
+            let matcher = matchers.entry(dir.clone()).or_insert_with(|| load_matcher(&dir));
+            if let Some(matcher) = matcher {
+                match matcher.matched_path_or_any_parents(path, is_dir) {
+                    Match::Ignore(_) => ignored = true,
+                    Match::Whitelist(_) => ignored = false,
+                    Match::None => {}
+                }
+            }
+            match components.next() {
+                Some(component) if components.peek().is_some() => dir.push(component),
+                _ => break,
+            }
+        }
+        ignored
+    }
+}
+
+/// The rules from the ignore files in `dir`
+fn load_matcher(dir: &Path) -> Option<Gitignore> {
+    let files: Vec<PathBuf> = IGNORE_FILES.iter().map(|name| dir.join(name)).filter(|p| p.is_file()).collect();
+    if files.is_empty() {
+        return None;
+    }
+
+    let mut builder = GitignoreBuilder::new(dir);
+    for file in &files {
+        if let Some(e) = builder.add(file) {
+            debug!(file = %file.display(), error = %e, "Skipping invalid ignore rules");
+        }
+    }
+    builder
+        .build()
+        .inspect_err(|e| debug!(dir = %dir.display(), error = %e, "Failed to build ignore rules"))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_gitignore_and_quantignore() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("gen")).unwrap();
+        fs::create_dir_all(root.join("src/fixtures")).unwrap();
+        fs::write(root.join(".gitignore"), "gen/\n*.log\nkeep.log\n").unwrap();
+        fs::write(root.join(QUANT_IGNORE), "secrets.env\n!keep.log\n").unwrap();
+        fs::write(root.join("src/.gitignore"), "fixtures/\n").unwrap();
+
+        let rules = IgnoreRules::new(root, true);
+        assert!(rules.is_ignored(&root.join("gen/out.rs"), false));
+        assert!(rules.is_ignored(&root.join("gen"), true));
+        assert!(rules.is_ignored(&root.join("debug.log"), false));
+        assert!(rules.is_ignored(&root.join("secrets.env"), false));
+        assert!(rules.is_ignored(&root.join("src/fixtures/data.json"), false));
+        assert!(!rules.is_ignored(&root.join("keep.log"), false));
+        assert!(!rules.is_ignored(&root.join("src/main.rs"), false));
+        assert!(!rules.is_ignored(Path::new("/elsewhere/debug.log"), false));
+
+        let disabled = IgnoreRules::new(root, false);
+        assert!(!disabled.is_ignored(&root.join("gen/out.rs"), false));
+    }
+}
@@ -0,0 +1,169 @@
+//! Prompt intent detection for default model choice
+//!
+//! When no model is given, `quant ask` picks between the coding and chat
+//! models from llm.toml by scoring the prompt with keyword heuristics: code
+//! fences, source-like syntax, file names, and programming vocabulary count
+//! toward coding; prose requests (stories, emails, general questions) count
+//! toward chat. Ties go to the coding model, the historical default.
+
+use std::fmt;
+
+/// Which kind of model a prompt calls for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Intent {
+    /// Classify the prompt
+    #[default]
+    Auto,
+    /// Use the coding model
+    Coding,
+    /// Use the chat model
+    Chat,
+}
+
+impl fmt::Display for Intent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Auto => write!(f, "auto"),
+            Self::Coding => write!(f, "coding"),
+            Self::Chat => write!(f, "chat"),
+        }
+    }
+}
+
+/// A classification and the cues behind it
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntentGuess {
+    /// `Coding` or `Chat`
+    pub intent: Intent,
+    /// Human-readable explanation, for logs and JSON output
+    pub reason: String,
+}
+
+/// Words that suggest a programming task
+const CODING_WORDS: &[&str] = &[
+    "function", "method", "class", "struct", "enum", "trait", "interface", "compile", "compiler", "bug", "debug",
+    "refactor", "implement", "regex", "sql", "query", "api", "endpoint", "stack trace", "traceback", "exception",
+    "panic", "segfault", "unit test", "test case", "variable", "algorithm", "script", "bash", "shell", "git",
+    "docker", "json", "yaml", "cargo", "npm", "python", "rust", "javascript", "typescript", "golang", "java",
+    "c++", "html", "css", "code", "syntax", "lint", "borrow checker", "async", "deploy", "kubernetes",
+];
+
+/// Words that suggest conversation or prose
+const CHAT_WORDS: &[&str] = &[
+    "story", "poem", "essay", "email", "letter", "recipe", "advice", "recommend", "opinion", "feel", "history",
+    "summarize this article", "translate", "joke", "trip", "travel", "gift", "meaning of", "who was", "who is",
+    "what is the capital", "tell me about", "how are you", "thank", "birthday", "book", "movie", "song",
+];
+
+/// Source-like fragments
+const CODE_SYNTAX: &[&str] = &[
+    "```", "::", "->", "=>", "();", "){", ") {", "fn ", "def ", "let ", "const ", "import ", "#include", "</",
+    "!=", "==", "&&", "||",
+];
+
+/// File extensions that mark a path as source
+const SOURCE_EXTENSIONS: &[&str] = &[
+    ".rs", ".py", ".js", ".ts", ".tsx", ".go", ".java", ".c", ".cpp", ".h", ".rb", ".sh", ".toml", ".yaml", ".yml",
+    ".json", ".sql", ".swift", ".kt",
+];
+
+/// Classify a prompt as a coding or chat request
+pub fn classify(prompt: &str) -> IntentGuess {
+    let lower = prompt.to_lowercase();
+    let mut coding = Vec::new();
+    let mut chat = Vec::new();
+
+    if let Some(syntax) = CODE_SYNTAX.iter().find(|s| prompt.contains(*s)) {
+        coding.push(format!("code syntax `{}`", syntax.trim()));
+    }
+    if let Some(file) = lower.split_whitespace().find(|word| {
+        let word = word.trim_matches(|c: char| !c.is_alphanumeric() && c != '.' && c != '_' && c != '/');
+        SOURCE_EXTENSIONS.iter().any(|ext| word.len() > ext.len() && word.ends_with(ext))
+    }) {
+        coding.push(format!("file name `{}`", file));
+    }
+    if let Some(ident) = prompt.split(|c: char| !c.is_alphanumeric() && c != '_').find(|w| is_identifier(w)) {
+        coding.push(format!("identifier `{}`", ident));
+    }
+    coding.extend(matching_words(&lower, CODING_WORDS));
+    chat.extend(matching_words(&lower, CHAT_WORDS));
+
+    let (intent, cues) = if chat.len() > coding.len() {
+        (Intent::Chat, chat)
+    } else {
+        (Intent::Coding, coding)
+    };
+    let reason = if cues.is_empty() {
+        "no clear cues; defaulting to the coding model".to_string()
+    } else {
+        cues.join(", ")
+    };
+    IntentGuess { intent, reason }
+}
+
+/// Quoted words from `words` found in `text` on word boundaries
+fn matching_words(text: &str, words: &[&str]) -> Vec<String> {
+    words
+        .iter()
+        .filter(|word| {
+            text.match_indices(*word).any(|(i, _)| {
+                let before = text[..i].chars().next_back();
+                let after = text[i + word.len()..].chars().next();
+                !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+            })
+        })
+        .map(|word| format!("\"{}\"", word))
+        .collect()
+}
+
+/// snake_case or camelCase words, which rarely appear in prose
+fn is_identifier(word: &str) -> bool {
+    let snake = word.contains('_') && word.trim_matches('_').len() > 2;
+    let camel = word
+        .char_indices()
+        .skip(1)
+        .any(|(i, c)| c.is_uppercase() && word[..i].chars().all(char::is_alphanumeric) && word[..i].chars().any(char::is_lowercase));
+    snake || (camel && word.chars().next().is_some_and(char::is_lowercase))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coding_prompts() {
+        for prompt in [
+            "why does this panic with index out of bounds",
+            "write a python function that parses dates",
+            "what does parse_args do in main.rs",
+            "fix: let x = foo();",
+            "rename getUserName everywhere",
+        ] {
+            assert_eq!(classify(prompt).intent, Intent::Coding, "{}", prompt);
+        }
+    }
+
+    #[test]
+    fn test_chat_prompts() {
+        for prompt in [
+            "write a short poem about autumn",
+            "tell me about the history of Rome",
+            "draft an email thanking my team",
+            "recommend a book for a long trip",
+        ] {
+            assert_eq!(classify(prompt).intent, Intent::Chat, "{}", prompt);
+        }
+    }
+
+    #[test]
+    fn test_reason_lists_cues() {
+        let guess = classify("write a story about a rust compiler");
+        // One chat cue against two coding cues
+        assert_eq!(guess.intent, Intent::Coding);
+        assert!(guess.reason.contains("\"rust\""));
+
+        let guess = classify("hello there");
+        assert_eq!(guess.intent, Intent::Coding);
+        assert!(guess.reason.starts_with("no clear cues"));
+    }
+}
@@ -0,0 +1,559 @@
+//! Language server client over stdio
+//!
+//! Speaks JSON-RPC with `Content-Length` framing. A background task reads
+//! server messages, resolving pending requests, storing published
+//! diagnostics, tracking indexing progress, and answering the few
+//! server-to-client requests servers send during startup.
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::{oneshot, Mutex, Notify};
+use tracing::{debug, warn};
+
+use super::LspServerConfig;
+
+/// Timeout for individual requests
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Zero-based line/character position (characters are UTF-16 code units)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Position {
+    pub line: u32,
+    pub character: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// A location in a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub path: PathBuf,
+    pub range: Range,
+}
+
+/// A diagnostic published by the server
+#[derive(Debug, Clone, Deserialize)]
+pub struct Diagnostic {
+    pub range: Range,
+    #[serde(default)]
+    pub severity: Option<u8>,
+    #[serde(default)]
+    pub code: Option<Value>,
+    #[serde(default)]
+    pub source: Option<String>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn severity_label(&self) -> &'static str {
+        match self.severity {
+            Some(1) => "error",
+            Some(2) => "warning",
+            Some(3) => "info",
+            Some(4) => "hint",
+            _ => "diagnostic",
+        }
+    }
+}
+
+type PendingMap = HashMap<u64, oneshot::Sender<Result<Value>>>;
+
+/// State shared with the reader task
+#[derive(Default)]
+struct Shared {
+    pending: parking_lot::Mutex<PendingMap>,
+    diagnostics: parking_lot::Mutex<HashMap<String, Vec<Diagnostic>>>,
+    diagnostics_changed: Notify,
+    /// Number of `$/progress` operations that have begun but not ended
+    active_progress: AtomicUsize,
+    alive: AtomicBool,
+}
+
+/// A running language server
+pub struct LspClient {
+    config: LspServerConfig,
+    stdin: Arc<Mutex<ChildStdin>>,
+    child: Mutex<Child>,
+    shared: Arc<Shared>,
+    next_id: AtomicU64,
+    /// Open documents and their current version
+    open_documents: Mutex<HashMap<String, (i32, String)>>,
+    started_at: Instant,
+}
+
+impl LspClient {
+    /// Spawn the server and perform the initialize handshake
+    pub async fn start(config: LspServerConfig, root: &Path) -> Result<Self> {
+        let mut cmd = Command::new(&config.command);
+        cmd.args(&config.args)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true);
+        #[cfg(unix)]
+        cmd.process_group(0);
+        let mut child = cmd
+            .spawn()
+            .with_context(|| format!("Failed to spawn language server: {}", config.command))?;
+        let command_line = std::iter::once(config.command.as_str())
+            .chain(config.args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ");
+        crate::procs::track(child.id(), crate::procs::ProcessKind::Lsp, &command_line);
+
+        let stdin = child.stdin.take().context("Failed to capture stdin of language server")?;
+        let stdout = child.stdout.take().context("Failed to capture stdout of language server")?;
+
+        let shared = Arc::new(Shared::default());
+        shared.alive.store(true, Ordering::SeqCst);
+        let stdin = Arc::new(Mutex::new(stdin));
+
+        tokio::spawn(read_loop(BufReader::new(stdout), shared.clone(), stdin.clone()));
+
+        let client = Self {
+            config,
+            stdin,
+            child: Mutex::new(child),
+            shared,
+            next_id: AtomicU64::new(1),
+            open_documents: Mutex::new(HashMap::new()),
+            started_at: Instant::now(),
+        };
+
+        let root_uri = path_to_uri(root)?;
+        client
+            .request(
+                "initialize",
+                json!({
+                    "processId": std::process::id(),
+                    "rootUri": root_uri,
+                    "workspaceFolders": [{ "uri": root_uri, "name": root.file_name().and_then(|n| n.to_str()).unwrap_or("root") }],
+                    "capabilities": {
+                        "textDocument": {
+                            "definition": { "linkSupport": true },
+                            "references": {},
+                            "publishDiagnostics": { "relatedInformation": false },
+                            "synchronization": { "didSave": false }
+                        },
+                        "window": { "workDoneProgress": true },
+                        "workspace": { "workspaceFolders": true, "configuration": true }
+                    }
+                }),
+            )
+            .await
+            .context("Language server initialization failed")?;
+        client.notify("initialized", json!({})).await?;
+
+        debug!(server = %client.config.command, root = %root.display(), "Language server started");
+        Ok(client)
+    }
+
+    pub fn command(&self) -> &str {
+        &self.config.command
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.shared.alive.load(Ordering::SeqCst)
+    }
+
+    /// Whether the server is still indexing the project
+    pub fn is_indexing(&self) -> bool {
+        self.shared.active_progress.load(Ordering::SeqCst) > 0
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    /// Wait until background indexing has finished, up to `timeout`
+    pub async fn wait_until_idle(&self, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+        while self.is_indexing() && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Send a request and wait for its result
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        if !self.is_alive() {
+            bail!("Language server {} has exited", self.config.command);
+        }
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        self.shared.pending.lock().insert(id, tx);
+
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        write_message(&mut *self.stdin.lock().await, &message).await?;
+
+        match tokio::time::timeout(REQUEST_TIMEOUT, rx).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => bail!("Language server {} closed the connection", self.config.command),
+            Err(_) => {
+                self.shared.pending.lock().remove(&id);
+                bail!("Language server request '{}' timed out", method)
+            }
+        }
+    }
+
+    /// Send a notification
+    pub async fn notify(&self, method: &str, params: Value) -> Result<()> {
+        let message = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+        write_message(&mut *self.stdin.lock().await, &message).await
+    }
+
+    /// Open a document, or sync its contents if it changed on disk
+    ///
+    /// Returns the document URI.
+    pub async fn sync_document(&self, path: &Path) -> Result<String> {
+        let uri = path_to_uri(path)?;
+        let text = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        let mut open = self.open_documents.lock().await;
+        match open.get_mut(&uri) {
+            Some((_, current)) if *current == text => {}
+            Some((version, current)) => {
+                *version += 1;
+                *current = text.clone();
+                self.shared.diagnostics.lock().remove(&uri);
+                self.notify(
+                    "textDocument/didChange",
+                    json!({
+                        "textDocument": { "uri": uri, "version": *version },
+                        "contentChanges": [{ "text": text }]
+                    }),
+                )
+                .await?;
+            }
+            None => {
+                self.notify(
+                    "textDocument/didOpen",
+                    json!({
+                        "textDocument": {
+                            "uri": uri,
+                            "languageId": self.config.language_id,
+                            "version": 1,
+                            "text": text
+                        }
+                    }),
+                )
+                .await?;
+                open.insert(uri.clone(), (1, text));
+            }
+        }
+        Ok(uri)
+    }
+
+    /// Find where the symbol at `position` is defined
+    pub async fn definition(&self, path: &Path, position: Position) -> Result<Vec<Location>> {
+        let uri = self.sync_document(path).await?;
+        let result = self
+            .request(
+                "textDocument/definition",
+                json!({
+                    "textDocument": { "uri": uri },
+                    "position": { "line": position.line, "character": position.character }
+                }),
+            )
+            .await?;
+        Ok(parse_locations(&result))
+    }
+
+    /// Find all references to the symbol at `position`
+    pub async fn references(&self, path: &Path, position: Position, include_declaration: bool) -> Result<Vec<Location>> {
+        let uri = self.sync_document(path).await?;
+        let result = self
+            .request(
+                "textDocument/references",
+                json!({
+                    "textDocument": { "uri": uri },
+                    "position": { "line": position.line, "character": position.character },
+                    "context": { "includeDeclaration": include_declaration }
+                }),
+            )
+            .await?;
+        Ok(parse_locations(&result))
+    }
+
+    /// Diagnostics for a file, waiting up to `timeout` for the server to publish them
+    pub async fn diagnostics(&self, path: &Path, timeout: Duration) -> Result<Vec<Diagnostic>> {
+        let uri = self.sync_document(path).await?;
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let notified = self.shared.diagnostics_changed.notified();
+            // Servers publish before indexing finishes; wait for the final set
+            if !self.is_indexing() {
+                if let Some(diags) = self.shared.diagnostics.lock().get(&uri) {
+                    return Ok(diags.clone());
+                }
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(self.shared.diagnostics.lock().get(&uri).cloned().unwrap_or_default());
+            }
+            let _ = tokio::time::timeout(remaining.min(Duration::from_millis(250)), notified).await;
+        }
+    }
+
+    /// Shut the server down
+    pub async fn shutdown(&self) {
+        if self.is_alive() {
+            let _ = tokio::time::timeout(Duration::from_secs(2), self.request("shutdown", Value::Null)).await;
+            let _ = self.notify("exit", Value::Null).await;
+        }
+        self.shared.alive.store(false, Ordering::SeqCst);
+        let mut child = self.child.lock().await;
+        let pid = child.id();
+        let _ = child.kill().await;
+        if let Some(pid) = pid {
+            crate::procs::terminate(pid);
+        }
+        crate::procs::untrack(pid);
+    }
+}
+
+/// Read server messages until the stream closes
+async fn read_loop<R>(mut reader: R, shared: Arc<Shared>, stdin: Arc<Mutex<ChildStdin>>)
+where
+    R: AsyncBufRead + Unpin,
+{
+    loop {
+        let message = match read_message(&mut reader).await {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(e) => {
+                warn!(error = %e, "Failed to read from language server");
+                break;
+            }
+        };
+
+        let method = message.get("method").and_then(|m| m.as_str());
+        let id = message.get("id").cloned();
+
+        match (method, id) {
+            // Response to one of our requests
+            (None, Some(id)) => {
+                let Some(id) = id.as_u64() else { continue };
+                if let Some(tx) = shared.pending.lock().remove(&id) {
+                    let result = match message.get("error") {
+                        Some(error) => Err(anyhow::anyhow!(
+                            "Language server error: {}",
+                            error.get("message").and_then(|m| m.as_str()).unwrap_or("unknown")
+                        )),
+                        None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+                    };
+                    let _ = tx.send(result);
+                }
+            }
+            // Request from the server; answer with neutral results so it doesn't stall
+            (Some(method), Some(id)) => {
+                let result = match method {
+                    "workspace/configuration" => {
+                        let items = message
+                            .pointer("/params/items")
+                            .and_then(|i| i.as_array())
+                            .map_or(0, |i| i.len());
+                        Value::Array(vec![Value::Null; items])
+                    }
+                    _ => Value::Null,
+                };
+                let reply = json!({ "jsonrpc": "2.0", "id": id, "result": result });
+                if let Err(e) = write_message(&mut *stdin.lock().await, &reply).await {
+                    debug!(error = %e, "Failed to reply to language server request");
+                }
+            }
+            (Some(method), None) => handle_notification(&shared, method, message.get("params")),
+            (None, None) => {}
+        }
+    }
+
+    shared.alive.store(false, Ordering::SeqCst);
+    // Dropping the senders fails any in-flight requests
+    shared.pending.lock().clear();
+    shared.diagnostics_changed.notify_waiters();
+}
+
+fn handle_notification(shared: &Shared, method: &str, params: Option<&Value>) {
+    let Some(params) = params else { return };
+    match method {
+        "textDocument/publishDiagnostics" => {
+            let Some(uri) = params.get("uri").and_then(|u| u.as_str()) else { return };
+            let diagnostics: Vec<Diagnostic> = params
+                .get("diagnostics")
+                .and_then(|d| serde_json::from_value(d.clone()).ok())
+                .unwrap_or_default();
+            shared.diagnostics.lock().insert(uri.to_string(), diagnostics);
+            shared.diagnostics_changed.notify_waiters();
+        }
+        "$/progress" => match params.pointer("/value/kind").and_then(|k| k.as_str()) {
+            Some("begin") => {
+                shared.active_progress.fetch_add(1, Ordering::SeqCst);
+            }
+            Some("end") => {
+                let _ = shared
+                    .active_progress
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+                shared.diagnostics_changed.notify_waiters();
+            }
+            _ => {}
+        },
+        _ => {}
+    }
+}
+
+/// Read one `Content-Length` framed message; `None` at end of stream
+async fn read_message<R>(reader: &mut R) -> Result<Option<Value>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut content_length: Option<usize> = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = Some(value.trim().parse().context("Invalid Content-Length header")?);
+            }
+        }
+    }
+
+    let mut body = vec![0u8; content_length.unwrap_or(0)];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(serde_json::from_slice(&body).context("Invalid JSON from language server")?))
+}
+
+/// Write one `Content-Length` framed message
+async fn write_message<W>(writer: &mut W, message: &Value) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let body = serde_json::to_vec(message)?;
+    writer
+        .write_all(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes())
+        .await?;
+    writer.write_all(&body).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Parse a definition/references result (Location, Location[], or LocationLink[])
+fn parse_locations(value: &Value) -> Vec<Location> {
+    let items = match value {
+        Value::Array(items) => items.iter().collect(),
+        Value::Object(_) => vec![value],
+        _ => Vec::new(),
+    };
+
+    items
+        .into_iter()
+        .filter_map(|item| {
+            let (uri, range) = match item.get("targetUri") {
+                Some(uri) => (uri, item.get("targetSelectionRange").or_else(|| item.get("targetRange"))?),
+                None => (item.get("uri")?, item.get("range")?),
+            };
+            let path = uri_to_path(uri.as_str()?)?;
+            let range = serde_json::from_value(range.clone()).ok()?;
+            Some(Location { path, range })
+        })
+        .collect()
+}
+
+pub fn path_to_uri(path: &Path) -> Result<String> {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+    url::Url::from_file_path(&absolute)
+        .map(|u| u.to_string())
+        .map_err(|_| anyhow::anyhow!("Invalid file path: {}", absolute.display()))
+}
+
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    url::Url::parse(uri).ok()?.to_file_path().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_message_framing_roundtrip() {
+        let mut buf = Vec::new();
+        write_message(&mut buf, &json!({ "id": 1, "result": "ünïcode" })).await.unwrap();
+        write_message(&mut buf, &json!({ "method": "x" })).await.unwrap();
+
+        let mut reader = BufReader::new(&buf[..]);
+        let first = read_message(&mut reader).await.unwrap().unwrap();
+        assert_eq!(first["result"], "ünïcode");
+        let second = read_message(&mut reader).await.unwrap().unwrap();
+        assert_eq!(second["method"], "x");
+        assert!(read_message(&mut reader).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_locations() {
+        let range = json!({ "start": { "line": 3, "character": 4 }, "end": { "line": 3, "character": 9 } });
+
+        let single = json!({ "uri": "file:///tmp/a.rs", "range": range });
+        let locations = parse_locations(&single);
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].path, PathBuf::from("/tmp/a.rs"));
+        assert_eq!(locations[0].range.start.line, 3);
+
+        let links = json!([{ "targetUri": "file:///tmp/b.rs", "targetRange": range, "targetSelectionRange": range }]);
+        assert_eq!(parse_locations(&links)[0].path, PathBuf::from("/tmp/b.rs"));
+
+        assert!(parse_locations(&Value::Null).is_empty());
+    }
+
+    #[test]
+    fn test_notifications_update_state() {
+        let shared = Shared::default();
+        handle_notification(&shared, "$/progress", Some(&json!({ "token": "t", "value": { "kind": "begin" } })));
+        assert_eq!(shared.active_progress.load(Ordering::SeqCst), 1);
+        handle_notification(&shared, "$/progress", Some(&json!({ "token": "t", "value": { "kind": "end" } })));
+        handle_notification(&shared, "$/progress", Some(&json!({ "token": "t", "value": { "kind": "end" } })));
+        assert_eq!(shared.active_progress.load(Ordering::SeqCst), 0);
+
+        handle_notification(
+            &shared,
+            "textDocument/publishDiagnostics",
+            Some(&json!({
+                "uri": "file:///tmp/a.rs",
+                "diagnostics": [{
+                    "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 1 } },
+                    "severity": 1,
+                    "message": "mismatched types"
+                }]
+            })),
+        );
+        let diags = shared.diagnostics.lock();
+        assert_eq!(diags["file:///tmp/a.rs"][0].severity_label(), "error");
+    }
+}
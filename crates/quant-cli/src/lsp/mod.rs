@@ -0,0 +1,177 @@
+//! Language Server Protocol integration
+//!
+//! Spawns language servers (rust-analyzer, pyright, typescript-language-server,
+//! gopls) on demand and keeps them warm for the rest of the process, so
+//! repeated definition/reference/diagnostic queries don't pay the indexing
+//! cost again.
+//!
+//! Servers are keyed by command and project root: a file is routed to the
+//! server for its language, rooted at the nearest project manifest.
+
+pub mod client;
+
+pub use client::{LspClient, Location, Position};
+
+use anyhow::{bail, Result};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::info;
+
+/// How to launch a language server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LspServerConfig {
+    pub command: String,
+    pub args: Vec<String>,
+    /// LSP language identifier sent with opened documents
+    pub language_id: String,
+    /// Files whose presence marks a project root
+    pub root_markers: Vec<String>,
+    /// Install hint shown when the command is missing
+    pub install_hint: String,
+}
+
+impl LspServerConfig {
+    fn new(command: &str, args: &[&str], language_id: &str, root_markers: &[&str], install_hint: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            args: args.iter().map(|s| s.to_string()).collect(),
+            language_id: language_id.to_string(),
+            root_markers: root_markers.iter().map(|s| s.to_string()).collect(),
+            install_hint: install_hint.to_string(),
+        }
+    }
+
+    /// The default server for a file, based on its extension
+    pub fn for_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?;
+        let config = match ext {
+            "rs" => Self::new("rust-analyzer", &[], "rust", &["Cargo.toml"], "rustup component add rust-analyzer"),
+            "py" | "pyi" => Self::new(
+                "pyright-langserver",
+                &["--stdio"],
+                "python",
+                &["pyproject.toml", "setup.py", "setup.cfg", "requirements.txt"],
+                "npm install -g pyright",
+            ),
+            "ts" | "tsx" | "js" | "jsx" | "mjs" | "cjs" => Self::new(
+                "typescript-language-server",
+                &["--stdio"],
+                match ext {
+                    "ts" => "typescript",
+                    "tsx" => "typescriptreact",
+                    "jsx" => "javascriptreact",
+                    _ => "javascript",
+                },
+                &["tsconfig.json", "jsconfig.json", "package.json"],
+                "npm install -g typescript typescript-language-server",
+            ),
+            "go" => Self::new("gopls", &[], "go", &["go.mod"], "go install golang.org/x/tools/gopls@latest"),
+            _ => return None,
+        };
+        Some(config)
+    }
+
+    /// Project root for a file: the nearest ancestor containing a root marker
+    ///
+    /// Rust files resolve to the enclosing Cargo workspace so a single server
+    /// covers every member crate. Falls back to `default_root`.
+    pub fn find_root(&self, file: &Path, default_root: &Path) -> PathBuf {
+        let mut nearest: Option<PathBuf> = None;
+        for dir in file.ancestors().skip(1) {
+            let Some(marker) = self.root_markers.iter().find(|m| dir.join(m).is_file()) else {
+                continue;
+            };
+            if nearest.is_none() {
+                nearest = Some(dir.to_path_buf());
+                if self.language_id != "rust" {
+                    break;
+                }
+            }
+            if self.language_id == "rust" {
+                let is_workspace = std::fs::read_to_string(dir.join(marker))
+                    .map(|c| c.lines().any(|l| l.trim() == "[workspace]"))
+                    .unwrap_or(false);
+                if is_workspace {
+                    return dir.to_path_buf();
+                }
+            }
+        }
+        nearest.unwrap_or_else(|| default_root.to_path_buf())
+    }
+}
+
+/// Running servers, keyed by (command, root)
+type ServerMap = HashMap<(String, PathBuf), Arc<LspClient>>;
+
+static SERVERS: Lazy<Mutex<ServerMap>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Get a warm server for `file`, starting one if needed
+pub async fn client_for(file: &Path, working_dir: &Path) -> Result<Arc<LspClient>> {
+    let Some(config) = LspServerConfig::for_path(file) else {
+        bail!("No language server configured for {}", file.display());
+    };
+    if which::which(&config.command).is_err() {
+        bail!(
+            "Language server '{}' not found on PATH. Install it with: {}",
+            config.command,
+            config.install_hint
+        );
+    }
+
+    let root = config.find_root(file, working_dir);
+    let key = (config.command.clone(), root.clone());
+
+    let mut servers = SERVERS.lock().await;
+    if let Some(client) = servers.get(&key) {
+        if client.is_alive() {
+            return Ok(client.clone());
+        }
+        info!(server = %config.command, "Restarting exited language server");
+    }
+
+    let client = Arc::new(LspClient::start(config, &root).await?);
+    servers.insert(key, client.clone());
+    Ok(client)
+}
+
+/// Shut down every running server
+pub async fn shutdown_all() {
+    let servers: Vec<Arc<LspClient>> = SERVERS.lock().await.drain().map(|(_, c)| c).collect();
+    for client in servers {
+        client.shutdown().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_for_path() {
+        assert_eq!(LspServerConfig::for_path(Path::new("src/main.rs")).unwrap().command, "rust-analyzer");
+        assert_eq!(LspServerConfig::for_path(Path::new("app.tsx")).unwrap().language_id, "typescriptreact");
+        assert!(LspServerConfig::for_path(Path::new("README.md")).is_none());
+    }
+
+    #[test]
+    fn test_find_root_prefers_cargo_workspace() {
+        let dir = TempDir::new().unwrap();
+        let member = dir.path().join("crates/core");
+        fs::create_dir_all(member.join("src")).unwrap();
+        fs::write(dir.path().join("Cargo.toml"), "[workspace]\nmembers = [\"crates/*\"]\n").unwrap();
+        fs::write(member.join("Cargo.toml"), "[package]\nname = \"core\"\n").unwrap();
+
+        let file = member.join("src/lib.rs");
+        let config = LspServerConfig::for_path(&file).unwrap();
+        assert_eq!(config.find_root(&file, Path::new("/fallback")), dir.path());
+
+        let py = dir.path().join("tools/script.py");
+        let config = LspServerConfig::for_path(&py).unwrap();
+        assert_eq!(config.find_root(&py, Path::new("/fallback")), Path::new("/fallback"));
+    }
+}
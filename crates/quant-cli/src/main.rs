@@ -3,20 +3,51 @@
 //! Provides a Claude Code-like experience for local LLMs via Ollama.
 
 mod agent;
+mod audit;
+mod clipboard;
+mod code_block;
 mod commands;
+mod complete;
 mod config;
 mod context;
 mod conversation;
+mod daemon;
+mod deps;
+mod eval;
 mod hooks;
+mod ignore_files;
+mod intent;
+mod lsp;
 mod mcp;
+mod model_alias;
+mod model_store;
+mod paths;
+mod procs;
 mod progress;
 mod project;
+mod rag;
 mod repl;
+mod repl_helper;
+mod resource_sampler;
+mod response_limit;
+mod review;
+mod runfile;
+mod search;
+mod service;
 mod session;
+mod shell;
+mod storage;
+mod sync;
+mod telemetry;
+mod thinking;
 mod tools;
+mod transcript;
+mod worktree;
 
 use anyhow::Result;
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use tracing_subscriber::prelude::*;
 use tracing_subscriber::EnvFilter;
 
 #[derive(Debug, Parser)]
@@ -28,10 +59,110 @@ struct Cli {
     #[arg(short, long, global = true)]
     verbose: bool,
 
+    /// Log format; `json` emits structured events without ANSI (default: json when CI=true)
+    #[arg(long, global = true, value_enum, env = "QUANT_LOG_FORMAT")]
+    log_format: Option<LogFormat>,
+
+    /// Config profile to apply (`[profiles.<name>]`)
+    #[arg(long, global = true, env = "QUANT_PROFILE")]
+    profile: Option<String>,
+
+    /// Override a config value for this run, e.g. `--set ask.temperature=0.2` (repeatable)
+    #[arg(long = "set", global = true, value_name = "KEY=VALUE")]
+    set: Vec<String>,
+
+    /// Collapse the thinking of reasoning models (e.g. deepseek-r1) into one line
+    #[arg(long, global = true)]
+    no_thinking: bool,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum LogFormat {
+    /// Human-readable output with colors and spinners
+    Text,
+    /// Structured JSON log events on stderr and a JSON summary on stdout
+    Json,
+}
+
+impl LogFormat {
+    /// Explicit format, or JSON when running under CI
+    fn resolve(explicit: Option<Self>) -> Self {
+        explicit.unwrap_or_else(|| {
+            let ci = std::env::var("CI").map(|v| v == "true" || v == "1").unwrap_or(false);
+            if ci {
+                Self::Json
+            } else {
+                Self::Text
+            }
+        })
+    }
+}
+
+/// Ollama sampling options; they override the model alias's
+#[derive(Debug, clap::Args)]
+struct SamplingArgs {
+    /// Keep the likeliest tokens up to this total probability (0-1)
+    #[arg(long)]
+    top_p: Option<f32>,
+
+    /// Sample from the k likeliest tokens only
+    #[arg(long)]
+    top_k: Option<u32>,
+
+    /// Drop tokens less likely than this fraction of the likeliest one (0-1)
+    #[arg(long)]
+    min_p: Option<f32>,
+
+    /// Penalize repeating recent tokens (1.0 = off)
+    #[arg(long)]
+    repeat_penalty: Option<f32>,
+
+    /// Penalize tokens that already appeared (-2 to 2)
+    #[arg(long)]
+    presence_penalty: Option<f32>,
+
+    /// Penalize tokens by how often they appeared (-2 to 2)
+    #[arg(long)]
+    frequency_penalty: Option<f32>,
+
+    /// Fixed seed, for repeatable output
+    #[arg(long)]
+    seed: Option<i64>,
+
+    /// Stop generating at this sequence (repeatable)
+    #[arg(long, value_name = "TEXT")]
+    stop: Vec<String>,
+
+    /// Context window to load the model with, in tokens
+    #[arg(long)]
+    num_ctx: Option<u32>,
+
+    /// How long Ollama keeps the model loaded afterwards (e.g. 30m)
+    #[arg(long)]
+    keep_alive: Option<String>,
+}
+
+impl SamplingArgs {
+    fn into_options(self) -> config::ModelOptions {
+        config::ModelOptions {
+            top_p: self.top_p,
+            top_k: self.top_k,
+            min_p: self.min_p,
+            repeat_penalty: self.repeat_penalty,
+            presence_penalty: self.presence_penalty,
+            frequency_penalty: self.frequency_penalty,
+            seed: self.seed,
+            stop: self.stop,
+            num_ctx: self.num_ctx,
+            keep_alive: self.keep_alive,
+            ..Default::default()
+        }
+    }
+}
+
 #[derive(Debug, Subcommand)]
 enum Commands {
     /// Start interactive chat REPL
@@ -62,6 +193,15 @@ enum Commands {
         #[arg(long)]
         stdin: bool,
 
+        /// Read input from the system clipboard, as with --stdin
+        #[arg(long, conflicts_with = "stdin")]
+        paste: bool,
+
+        /// How to interpret stdin: raw text, or a chat transcript
+        /// (`user:`/`assistant:` tagged lines or JSONL messages)
+        #[arg(long, value_enum, default_value = "text")]
+        stdin_format: transcript::StdinFormat,
+
         /// Add context from directory
         #[arg(short, long)]
         context: Option<String>,
@@ -85,6 +225,51 @@ enum Commands {
         /// Don't print newline after response
         #[arg(short = 'n', long)]
         no_newline: bool,
+
+        /// Without --model, use the coding or chat model from llm.toml
+        /// (`auto` picks based on the prompt)
+        #[arg(long, value_enum, default_value = "auto")]
+        intent: intent::Intent,
+
+        #[command(flatten)]
+        sampling: SamplingArgs,
+    },
+
+    /// Suggest a shell command for a task, then run, edit, or abort
+    Do {
+        /// What you want to do, in plain language
+        request: Vec<String>,
+
+        /// Model to use
+        #[arg(short, long)]
+        model: Option<String>,
+
+        /// Don't record accepted commands in shell history
+        #[arg(long)]
+        no_history: bool,
+    },
+
+    /// Fill-in-the-middle code completion for editor integrations
+    Complete {
+        /// Read one request from stdin (JSON with prefix/suffix, or raw prefix text)
+        #[arg(long, conflicts_with = "serve")]
+        stdin: bool,
+
+        /// Run a long-lived completion server on a Unix socket
+        #[arg(long)]
+        serve: bool,
+
+        /// Socket path for --serve
+        #[arg(long, requires = "serve")]
+        socket: Option<PathBuf>,
+
+        /// Model to use (default: coding model)
+        #[arg(short, long)]
+        model: Option<String>,
+
+        /// Output the full JSON response instead of the completion text
+        #[arg(long)]
+        json: bool,
     },
 
     /// Show Ollama status and system info
@@ -115,6 +300,9 @@ enum Commands {
         timeout: u64,
     },
 
+    /// Diagnose models volume, disk space, port conflicts, and version mismatch
+    Doctor,
+
     /// Import local GGUF files into Ollama
     Import,
 
@@ -132,7 +320,7 @@ enum Commands {
         output: String,
     },
 
-    /// Load/warm up a model (makes subsequent queries faster)
+    /// Load/warm up a model (makes subsequent queries faster; same as `models warm`)
     Run {
         /// Model to load
         #[arg(short, long)]
@@ -187,6 +375,92 @@ enum Commands {
         /// Don't save this session
         #[arg(long)]
         no_save: bool,
+
+        /// Produce a step-by-step plan for approval before executing
+        #[arg(long)]
+        plan: bool,
+
+        /// Run a team of role agents (e.g. "planner,coder,reviewer")
+        #[arg(long, value_name = "ROLES", conflicts_with_all = ["plan", "resume"])]
+        team: Option<String>,
+
+        /// Work in a temporary git worktree and branch, leaving this checkout untouched
+        #[arg(long, conflicts_with = "team")]
+        worktree: bool,
+
+        /// Simulate file writes, commands and other dangerous tools instead of running them
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Hold file writes until you review their diffs at the end of the run
+        #[arg(long, conflicts_with_all = ["team", "dry_run"])]
+        review: bool,
+
+        /// Answer confirmations from a YAML policy file instead of the terminal (for CI)
+        #[arg(long, value_name = "FILE", conflicts_with = "auto")]
+        confirm_policy: Option<PathBuf>,
+
+        #[command(flatten)]
+        sampling: SamplingArgs,
+    },
+
+    /// Run a scripted pipeline of ask, agent, and shell steps from a .quant file
+    RunFile {
+        /// Path to the run file (YAML, or Markdown with fenced step blocks)
+        file: PathBuf,
+
+        /// Set or override a variable (key=value, repeatable)
+        #[arg(long = "var", value_name = "KEY=VALUE")]
+        vars: Vec<String>,
+
+        /// Default model for ask and agent steps
+        #[arg(short, long)]
+        model: Option<String>,
+
+        /// Auto-approve tool executions in agent steps
+        #[arg(long)]
+        auto: bool,
+
+        /// Print the steps with variables resolved, without running them
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Only print the final step's output
+        #[arg(short, long)]
+        quiet: bool,
+    },
+
+    /// Benchmark models on a suite of agent tasks and report success rates
+    Eval {
+        /// Task suite (YAML)
+        suite: PathBuf,
+
+        /// Model to evaluate (repeatable; default: the suite's `models`, else the coding model)
+        #[arg(short, long = "model")]
+        models: Vec<String>,
+
+        /// Runs per task and model (default: the suite's `runs`)
+        #[arg(short = 'n', long)]
+        runs: Option<usize>,
+
+        /// Only run the named task (repeatable)
+        #[arg(long = "task")]
+        tasks: Vec<String>,
+
+        /// Print the report as JSON instead of markdown
+        #[arg(long)]
+        json: bool,
+
+        /// Also write the report to this file
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Don't print a progress line per run
+        #[arg(short, long)]
+        quiet: bool,
+
+        #[command(flatten)]
+        sampling: SamplingArgs,
     },
 
     /// Manage conversation sessions
@@ -194,6 +468,113 @@ enum Commands {
         #[command(subcommand)]
         action: SessionAction,
     },
+
+    /// Inspect project dependencies
+    Deps {
+        #[command(subcommand)]
+        action: DepsAction,
+    },
+
+    /// Maintain the search index over sessions and conversations
+    Index {
+        #[command(subcommand)]
+        action: IndexAction,
+    },
+
+    /// Ask questions about your documents, answered with citations
+    Rag {
+        #[command(subcommand)]
+        action: RagAction,
+    },
+
+    /// List processes quant started that are still running
+    Ps {
+        /// Stop the ones left behind by quant runs that have exited
+        #[arg(long)]
+        kill: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Generate a starter QUANT.md and .quant/ directory for this project
+    Init {
+        /// Overwrite an existing QUANT.md
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Inspect the project context given to agents
+    Project {
+        #[command(subcommand)]
+        action: ProjectAction,
+    },
+
+    /// Inspect the agent hooks from QUANT.md and the config files
+    Hooks {
+        #[command(subcommand)]
+        action: HooksAction,
+    },
+
+    /// Encrypt or decrypt saved sessions and conversations
+    Storage {
+        #[command(subcommand)]
+        action: StorageAction,
+    },
+
+    /// Check the MCP servers configured in QUANT.md
+    Mcp {
+        #[command(subcommand)]
+        action: McpAction,
+    },
+
+    /// Manage the bash commands this project trusts without asking
+    Approvals {
+        #[command(subcommand)]
+        action: ApprovalAction,
+    },
+
+    /// Read the audit log of agent tool calls
+    Audit {
+        #[command(subcommand)]
+        action: AuditAction,
+    },
+
+    /// Inspect the tool plugins loaded from the plugins directories
+    Plugins {
+        #[command(subcommand)]
+        action: PluginsAction,
+    },
+
+    /// Keep MCP servers, the RAG store and warm models loaded in a background daemon
+    Daemon {
+        #[command(subcommand)]
+        action: DaemonAction,
+    },
+
+    /// Sync sessions, conversations and trusted commands with another machine over SSH
+    Sync {
+        /// Host to sync with (default: sync.peer from config)
+        #[arg(long)]
+        peer: Option<String>,
+
+        /// Only sync paths matching this glob, e.g. "conversations" (repeatable)
+        #[arg(long)]
+        include: Vec<String>,
+
+        /// Skip paths matching this glob (repeatable)
+        #[arg(long)]
+        exclude: Vec<String>,
+
+        /// Show what would be transferred without changing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Answer sync requests on stdin/stdout (run by the other machine)
+        #[arg(long, hide = true)]
+        serve: bool,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -201,11 +582,30 @@ enum ConfigAction {
     /// Create default config file
     Init,
     /// Show current configuration
-    Show,
+    Show {
+        /// List every resolved value with the file, variable, or flag it came from
+        #[arg(long)]
+        sources: bool,
+    },
     /// Print config file path
     Path,
     /// Edit config file (opens in $EDITOR)
     Edit,
+    /// Print a resolved value and where it came from
+    Get {
+        /// Dotted key, e.g. `ask.temperature`
+        key: String,
+    },
+    /// Set a value in the user config (or the project config with --project)
+    Set {
+        /// Dotted key, e.g. `ask.temperature`
+        key: String,
+        /// Value, parsed as TOML when possible (`0.2`, `true`, `["a", "b"]`)
+        value: String,
+        /// Write to .quant/config.toml in the current project
+        #[arg(long)]
+        project: bool,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -224,6 +624,19 @@ enum ModelAction {
     },
     /// Show running/loaded models
     Ps,
+    /// Show an installed model's details, template and license
+    Show {
+        /// Model name
+        name: String,
+    },
+    /// Load models and keep them loaded (default: the [warmup] models)
+    Warm {
+        /// Models to load
+        names: Vec<String>,
+        /// How long to keep them loaded (e.g. 30m, 2h, -1 for as long as Ollama runs)
+        #[arg(long)]
+        keep_alive: Option<String>,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -238,6 +651,23 @@ enum ServeAction {
     Stop,
     /// Restart Ollama server
     Restart,
+    /// Show Ollama's output captured by the supervisor
+    Logs {
+        /// Keep printing new output
+        #[arg(short = 'f', long)]
+        follow: bool,
+
+        /// Number of lines to show
+        #[arg(short = 'n', long, default_value = "50")]
+        lines: usize,
+    },
+    /// Install Ollama as a login service (launchd on macOS, systemd on Linux)
+    Install,
+    /// Stop and remove the service installed with `serve install`
+    Uninstall,
+    /// Run Ollama under supervision (started by `serve start`)
+    #[command(hide = true)]
+    Supervise,
 }
 
 #[derive(Debug, Subcommand)]
@@ -256,6 +686,20 @@ enum ContextAction {
     },
     /// Clear all context
     Clear,
+    /// Shallow-clone a git repo as read-only context for smart selection
+    AddRepo {
+        /// Repository URL
+        url: String,
+
+        /// Branch, tag or commit (default branch if omitted)
+        #[arg(long = "ref")]
+        git_ref: Option<String>,
+    },
+    /// Remove a repo added with add-repo and delete its checkout
+    RmRepo {
+        /// Repo name (e.g. org/lib), name@ref or URL
+        name: String,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -275,11 +719,52 @@ enum SessionAction {
         /// Session ID
         id: String,
     },
+    /// Show the agent state recorded at an iteration
+    Inspect {
+        /// Session ID
+        id: String,
+
+        /// Iteration to dump (lists the recorded iterations if omitted)
+        #[arg(short, long)]
+        iteration: Option<usize>,
+
+        /// Agent run within the session (default: the latest)
+        #[arg(long)]
+        run: Option<usize>,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Re-run a recorded agent run with its recorded responses and tool
+    /// results, stepping through its iterations
+    Replay {
+        /// Session ID
+        id: String,
+
+        /// Agent run within the session (default: the latest)
+        #[arg(long)]
+        run: Option<usize>,
+
+        /// Run to the end without pausing between iterations
+        #[arg(long = "continue")]
+        no_step: bool,
+    },
     /// Delete a session
     Rm {
         /// Session ID
         id: String,
     },
+    /// Full-text search saved sessions and conversations, best matches first
+    Search {
+        /// Search terms (all must match)
+        #[arg(required = true)]
+        query: Vec<String>,
+
+        /// Maximum results
+        #[arg(short = 'n', long, default_value = "10")]
+        limit: usize,
+    },
     /// Resume a session (alias for `agent --resume`)
     Resume {
         /// Session ID (or "latest" for most recent)
@@ -291,19 +776,271 @@ enum SessionAction {
     },
 }
 
+#[derive(Debug, Subcommand)]
+enum DepsAction {
+    /// List declared dependencies with resolved versions
+    List {
+        /// Include dev dependencies
+        #[arg(long)]
+        dev: bool,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check for newer versions using the ecosystem's native tool
+    Outdated,
+    /// Explain where a dependency is declared and why it is present
+    Explain {
+        /// Dependency name
+        name: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ProjectAction {
+    /// List the QUANT.md files that apply here, in order of precedence
+    Context {
+        /// Print the effective merged context instead
+        #[arg(long)]
+        show: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum HooksAction {
+    /// List every hook with where it was defined and whether it is enabled
+    List {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum RagAction {
+    /// Chunk and embed files and directories (text, code, PDF, docx, HTML)
+    Index {
+        /// Files or directories to index
+        #[arg(required = true)]
+        paths: Vec<String>,
+
+        /// Discard the existing store and embed everything again
+        #[arg(long)]
+        rebuild: bool,
+
+        /// Output stats as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Answer a question from the indexed documents
+    Query {
+        /// The question
+        #[arg(required = true)]
+        question: Vec<String>,
+
+        /// Model that answers (default: rag.model)
+        #[arg(short, long)]
+        model: Option<String>,
+
+        /// Chunks to retrieve (default: rag.top_k)
+        #[arg(short = 'k', long)]
+        top_k: Option<usize>,
+    },
+    /// List indexed files
+    List {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum StorageAction {
+    /// Encrypt saved sessions, conversations and the search index, and turn on `storage.encrypt`
+    Encrypt,
+    /// Decrypt them again and turn off `storage.encrypt`
+    Decrypt,
+}
+
+#[derive(Debug, Subcommand)]
+enum McpAction {
+    /// Start each server, health check it and report its status
+    Status {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ApprovalAction {
+    /// List trusted command patterns
+    List {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Stop trusting a command pattern
+    Revoke {
+        /// Pattern (e.g. "cargo test *") or its number from `approvals list`
+        pattern: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum AuditAction {
+    /// Show the latest tool calls
+    Tail {
+        /// Number of records to show
+        #[arg(short = 'n', long, default_value = "20")]
+        lines: usize,
+
+        /// Keep printing new records as they are written
+        #[arg(short, long)]
+        follow: bool,
+
+        /// Output as JSON lines
+        #[arg(long)]
+        json: bool,
+    },
+    /// Find tool calls by text, tool, status or time
+    Search {
+        /// Text in the tool, user, host, working directory or argument hash
+        query: Option<String>,
+
+        /// Only calls of this tool
+        #[arg(long)]
+        tool: Option<String>,
+
+        /// Only calls that ended this way (ok, failed, denied, ...)
+        #[arg(long)]
+        status: Option<String>,
+
+        /// Only calls since this time (e.g. 2h, 7d or 2026-10-01)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Show at most this many of the latest matches
+        #[arg(short = 'n', long, default_value = "100")]
+        limit: usize,
+
+        /// Output as JSON lines
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum PluginsAction {
+    /// List the plugins found, their tools, and libraries that failed to load
+    List {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum DaemonAction {
+    /// Start the daemon in the background
+    Start,
+    /// Stop the daemon and the MCP servers it runs
+    Stop,
+    /// Show what the daemon holds
+    Status {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Run the daemon in this process (started by `daemon start`)
+    #[command(hide = true)]
+    Run,
+}
+
+#[derive(Debug, Subcommand)]
+enum IndexAction {
+    /// Update the index over saved sessions and conversations
+    Sessions {
+        /// Discard the existing index and rebuild from scratch
+        #[arg(long)]
+        rebuild: bool,
+
+        /// Output stats as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Search the index
+    Search {
+        /// Search terms (all must match)
+        #[arg(required = true)]
+        query: Vec<String>,
+
+        /// Maximum results
+        #[arg(short = 'n', long, default_value = "10")]
+        limit: usize,
+    },
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    config::set_active_profile(cli.profile);
+    config::set_cli_overrides(cli.set);
+    thinking::set_hidden(cli.no_thinking);
+
+    // Spans go to an OTLP collector only when one is configured
+    let telemetry_config = config::UserConfig::load().map(|c| c.telemetry).unwrap_or_default();
+    let (otlp_layer, telemetry, telemetry_error) = match telemetry::layer(&telemetry_config, cli.verbose) {
+        Ok(Some((layer, telemetry))) => (Some(layer), Some(telemetry), None),
+        Ok(None) => (None, None, None),
+        Err(e) => (None, None, Some(e)),
+    };
+
     // Setup logging
-    let filter = if cli.verbose {
-        EnvFilter::new("debug")
+    let log_format = LogFormat::resolve(cli.log_format);
+    let structured = log_format == LogFormat::Json;
+    if structured {
+        // Agent events are logged at info; keep stdout free for the JSON summary
+        let filter = if cli.verbose {
+            EnvFilter::new("debug")
+        } else {
+            EnvFilter::new("warn,quant=info")
+        };
+        let fmt = tracing_subscriber::fmt::layer()
+            .json()
+            .with_ansi(false)
+            .with_writer(std::io::stderr)
+            .with_filter(filter);
+        tracing_subscriber::registry().with(otlp_layer).with(fmt).init();
     } else {
-        EnvFilter::new("warn")
-    };
-    tracing_subscriber::fmt().with_env_filter(filter).init();
+        let filter = if cli.verbose {
+            EnvFilter::new("debug")
+        } else {
+            EnvFilter::new("warn")
+        };
+        let fmt = tracing_subscriber::fmt::layer().with_filter(filter);
+        tracing_subscriber::registry().with(otlp_layer).with(fmt).init();
+    }
+    if let Some(e) = telemetry_error {
+        tracing::warn!("Telemetry disabled: {:#}", e);
+    }
+
+    // Config loading falls back to defaults on errors; surface them once up front
+    if let Err(e) = config::ConfigResolver::discover() {
+        tracing::warn!("{:#}", e);
+    }
+
+    // Offer to clean up after a crashed run before starting servers again
+    if matches!(
+        cli.command,
+        None | Some(Commands::Chat { .. }) | Some(Commands::Agent { .. }) | Some(Commands::RunFile { .. })
+    ) {
+        procs::check_orphans();
+    }
 
-    match cli.command {
+    let result = match cli.command {
         Some(Commands::Chat { model, system, load }) => {
             repl::run(model, system, load).await
         }
@@ -311,26 +1048,44 @@ async fn main() -> Result<()> {
             prompt,
             model,
             stdin,
+            paste,
+            stdin_format,
             context,
             json,
             system,
             temperature,
             max_tokens,
             no_newline,
+            intent,
+            sampling,
         }) => {
-            let prompt_text = prompt.join(" ");
-            commands::ask(
-                &prompt_text,
+            let options = commands::AskOptions {
                 model,
                 stdin,
-                context,
-                json,
+                paste,
+                stdin_format,
+                context_path: context,
+                json_output: json,
                 system,
                 temperature,
                 max_tokens,
                 no_newline,
-            )
-            .await
+                intent,
+                sampling: sampling.into_options(),
+            };
+            commands::ask(&prompt.join(" "), options).await
+        }
+        Some(Commands::Do { request, model, no_history }) => {
+            commands::do_command(&request.join(" "), model, no_history).await
+        }
+        Some(Commands::Complete { stdin, serve, socket, model, json }) => {
+            if serve {
+                commands::complete_serve(model, socket).await
+            } else if stdin {
+                commands::complete(model, json).await
+            } else {
+                anyhow::bail!("Pass --stdin for a single completion or --serve to start the server")
+            }
         }
         Some(Commands::Status) => commands::status().await,
         Some(Commands::Models { action }) => match action {
@@ -338,19 +1093,28 @@ async fn main() -> Result<()> {
             ModelAction::Pull { name } => commands::models_pull(&name).await,
             ModelAction::Rm { name } => commands::models_rm(&name).await,
             ModelAction::Ps => commands::models_ps().await,
+            ModelAction::Show { name } => commands::models_show(&name).await,
+            ModelAction::Warm { names, keep_alive } => commands::models_warm(names, keep_alive).await,
         },
         Some(Commands::Serve { action }) => match action {
             ServeAction::Start { foreground } => commands::serve_start(foreground).await,
             ServeAction::Stop => commands::serve_stop().await,
             ServeAction::Restart => commands::serve_restart().await,
+            ServeAction::Logs { follow, lines } => commands::serve_logs(follow, lines).await,
+            ServeAction::Install => commands::serve_install(),
+            ServeAction::Uninstall => commands::serve_uninstall(),
+            ServeAction::Supervise => commands::serve_supervise().await,
         },
         Some(Commands::Context { action }) => match action {
             ContextAction::Add { paths } => commands::context_add(&paths).await,
             ContextAction::List => commands::context_list().await,
             ContextAction::Rm { paths } => commands::context_rm(&paths).await,
             ContextAction::Clear => commands::context_clear().await,
+            ContextAction::AddRepo { url, git_ref } => commands::context_add_repo(&url, git_ref.as_deref()),
+            ContextAction::RmRepo { name } => commands::context_rm_repo(&name),
         },
         Some(Commands::Health { timeout }) => commands::health(timeout).await,
+        Some(Commands::Doctor) => commands::doctor().await,
         Some(Commands::Import) => commands::import().await,
         Some(Commands::Select { json }) => commands::select(json).await,
         Some(Commands::Env { output }) => commands::env(&output).await,
@@ -358,9 +1122,11 @@ async fn main() -> Result<()> {
         Some(Commands::Info) => commands::info().await,
         Some(Commands::Config { action }) => match action {
             ConfigAction::Init => commands::config_init().await,
-            ConfigAction::Show => commands::config_show().await,
+            ConfigAction::Show { sources } => commands::config_show(sources).await,
             ConfigAction::Path => commands::config_path().await,
             ConfigAction::Edit => commands::config_edit().await,
+            ConfigAction::Get { key } => commands::config_get(&key).await,
+            ConfigAction::Set { key, value, project } => commands::config_set(&key, &value, project).await,
         },
         Some(Commands::Completions { shell }) => {
             use clap::CommandFactory;
@@ -379,19 +1145,145 @@ async fn main() -> Result<()> {
             quiet,
             resume,
             no_save,
+            plan,
+            team,
+            worktree,
+            dry_run,
+            review,
+            confirm_policy,
+            sampling,
         }) => {
             let task_text = task.join(" ");
-            commands::agent(&task_text, model, system, auto, max_iterations, quiet, resume, no_save).await
+            if let Some(team) = team {
+                let options = commands::TeamOptions {
+                    model,
+                    auto,
+                    max_iterations,
+                    quiet,
+                    no_save,
+                    dry_run,
+                    confirm_policy,
+                    structured,
+                    review,
+                };
+                return commands::agent_team(&task_text, &team, options).await;
+            }
+            let options = commands::AgentOptions {
+                model,
+                system,
+                auto,
+                max_iterations,
+                quiet,
+                resume,
+                no_save,
+                plan,
+                worktree,
+                dry_run,
+                review,
+                confirm_policy,
+                structured,
+                sampling: sampling.into_options(),
+            };
+            commands::agent(&task_text, options).await
+        }
+        Some(Commands::RunFile { file, vars, model, auto, dry_run, quiet }) => {
+            commands::run_file(&file, &vars, model, auto, dry_run, quiet).await
+        }
+        Some(Commands::Eval { suite, models, runs, tasks, json, output, quiet, sampling }) => {
+            let options = commands::EvalOptions {
+                models,
+                runs,
+                tasks,
+                json,
+                output,
+                quiet,
+                sampling: sampling.into_options(),
+            };
+            commands::eval(&suite, options).await
         }
         Some(Commands::Sessions { action }) => match action {
             SessionAction::List { project, json } => commands::sessions_list(project, json).await,
             SessionAction::Show { id } => commands::sessions_show(&id).await,
+            SessionAction::Inspect { id, iteration, run, json } => {
+                commands::sessions_inspect(&id, iteration, run, json).await
+            }
+            SessionAction::Replay { id, run, no_step } => commands::sessions_replay(&id, run, !no_step).await,
             SessionAction::Rm { id } => commands::sessions_rm(&id).await,
+            SessionAction::Search { query, limit } => commands::index_search(&query.join(" "), limit).await,
             SessionAction::Resume { id, auto } => commands::sessions_resume(&id, auto).await,
         }
+        Some(Commands::Deps { action }) => match action {
+            DepsAction::List { dev, json } => commands::deps_list(dev, json).await,
+            DepsAction::Outdated => commands::deps_outdated().await,
+            DepsAction::Explain { name } => commands::deps_explain(&name).await,
+        },
+        Some(Commands::Index { action }) => match action {
+            IndexAction::Sessions { rebuild, json } => commands::index_sessions(rebuild, json).await,
+            IndexAction::Search { query, limit } => commands::index_search(&query.join(" "), limit).await,
+        }
+        Some(Commands::Rag { action }) => match action {
+            RagAction::Index { paths, rebuild, json } => commands::rag_index(&paths, rebuild, json).await,
+            RagAction::Query { question, model, top_k } => commands::rag_query(&question.join(" "), model, top_k).await,
+            RagAction::List { json } => commands::rag_list(json),
+        },
+        Some(Commands::Ps { kill, json }) => commands::ps(kill, json).await,
+        Some(Commands::Project { action }) => match action {
+            ProjectAction::Context { show } => commands::project_context(show),
+        },
+        Some(Commands::Hooks { action }) => match action {
+            HooksAction::List { json } => commands::hooks_list(json),
+        },
+        Some(Commands::Storage { action }) => match action {
+            StorageAction::Encrypt => commands::storage_migrate(true),
+            StorageAction::Decrypt => commands::storage_migrate(false),
+        },
+        Some(Commands::Mcp { action }) => match action {
+            McpAction::Status { json } => commands::mcp_status(json).await,
+        }
+        Some(Commands::Init { force }) => commands::init(force),
+        Some(Commands::Approvals { action }) => match action {
+            ApprovalAction::List { json } => commands::approvals_list(json),
+            ApprovalAction::Revoke { pattern } => commands::approvals_revoke(&pattern),
+        }
+        Some(Commands::Audit { action }) => match action {
+            AuditAction::Tail { lines, follow, json } => commands::audit_tail(lines, follow, json).await,
+            AuditAction::Search { query, tool, status, since, limit, json } => {
+                commands::audit_search(query, tool, status, since, limit, json)
+            }
+        }
+        Some(Commands::Plugins { action }) => match action {
+            PluginsAction::List { json } => commands::plugins_list(json),
+        }
+        Some(Commands::Daemon { action }) => match action {
+            DaemonAction::Start => commands::daemon_start().await,
+            DaemonAction::Stop => commands::daemon_stop().await,
+            DaemonAction::Status { json } => commands::daemon_status(json).await,
+            DaemonAction::Run => commands::daemon_run().await,
+        }
+        Some(Commands::Sync { peer, include, exclude, dry_run, serve }) => {
+            if serve {
+                sync::serve_stdio().await
+            } else {
+                commands::sync(peer, include, exclude, dry_run).await
+            }
+        }
         None => {
             // Default to chat REPL when no command specified
             repl::run(None, None, None).await
         }
+    };
+
+    procs::finish();
+    if let Some(telemetry) = telemetry {
+        telemetry.shutdown();
+    }
+    if let Err(e) = result {
+        // Same output as returning the error, plus what to do about it
+        eprintln!("Error: {:?}", e);
+        if let Some(hint) = llm_core::LlmError::find(&e).and_then(llm_core::LlmError::hint) {
+            eprintln!("\nHint: {}", hint);
+        }
+        std::process::exit(1);
     }
+    Ok(())
 }
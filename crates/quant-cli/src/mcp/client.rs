@@ -6,6 +6,7 @@ use super::transport::{JsonRpcRequest, JsonRpcResponse, McpTransport};
 use anyhow::{bail, Context, Result};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -180,6 +181,50 @@ pub struct ReadResourceResult {
     pub contents: Vec<ResourceContent>,
 }
 
+/// MCP prompt template argument
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct McpPromptArgument {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// MCP prompt template definition from server
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct McpPrompt {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub arguments: Vec<McpPromptArgument>,
+}
+
+/// Prompt list result
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListPromptsResult {
+    pub prompts: Vec<McpPrompt>,
+    #[serde(default)]
+    pub next_cursor: Option<String>,
+}
+
+/// A message produced by a prompt template
+#[derive(Debug, Clone, Deserialize)]
+pub struct PromptMessage {
+    pub role: String,
+    pub content: ToolResultContent,
+}
+
+/// Get prompt result
+#[derive(Debug, Clone, Deserialize)]
+pub struct GetPromptResult {
+    #[serde(default)]
+    pub description: Option<String>,
+    pub messages: Vec<PromptMessage>,
+}
+
 /// MCP Client
 pub struct McpClient {
     transport: Arc<Mutex<Box<dyn McpTransport>>>,
@@ -375,6 +420,60 @@ impl McpClient {
         Ok(result)
     }
 
+    /// List available prompt templates
+    pub async fn list_prompts(&self) -> Result<Vec<McpPrompt>> {
+        if !self.initialized {
+            bail!("MCP client not initialized");
+        }
+
+        // Check if server supports prompts
+        if let Some(caps) = &self.server_capabilities {
+            if caps.prompts.is_none() {
+                return Ok(Vec::new());
+            }
+        }
+
+        let mut prompts = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let params = cursor.as_ref().map(|c| serde_json::json!({ "cursor": c }));
+
+            let result: ListPromptsResult = self
+                .request("prompts/list", params)
+                .await
+                .context("Failed to list MCP prompts")?;
+
+            prompts.extend(result.prompts);
+
+            if result.next_cursor.is_none() {
+                break;
+            }
+            cursor = result.next_cursor;
+        }
+
+        Ok(prompts)
+    }
+
+    /// Render a prompt template with the given arguments
+    pub async fn get_prompt(&self, name: &str, arguments: &HashMap<String, String>) -> Result<GetPromptResult> {
+        if !self.initialized {
+            bail!("MCP client not initialized");
+        }
+
+        let params = serde_json::json!({
+            "name": name,
+            "arguments": arguments
+        });
+
+        let result: GetPromptResult = self
+            .request("prompts/get", Some(params))
+            .await
+            .with_context(|| format!("Failed to get MCP prompt: {}", name))?;
+
+        Ok(result)
+    }
+
     /// Ping the server
     pub async fn ping(&self) -> Result<()> {
         if !self.initialized {
@@ -392,10 +491,15 @@ impl McpClient {
     }
 
     /// Check if connected
+    ///
+    /// Asks the transport when it isn't busy; a transport in the middle of
+    /// a request counts as connected.
     pub fn is_connected(&self) -> bool {
-        // Note: This is a sync check, actual connection status
-        // might need async verification
         self.initialized
+            && self
+                .transport
+                .try_lock()
+                .map_or(true, |transport| transport.is_connected())
     }
 }
 
@@ -409,6 +513,21 @@ mod tests {
         assert_eq!(info.name, "quant-cli");
     }
 
+    #[test]
+    fn test_get_prompt_result_deserialization() {
+        let json = serde_json::json!({
+            "description": "Review a file",
+            "messages": [
+                {"role": "user", "content": {"type": "text", "text": "Review src/main.rs"}}
+            ]
+        });
+
+        let result: GetPromptResult = serde_json::from_value(json).unwrap();
+        assert_eq!(result.messages.len(), 1);
+        assert_eq!(result.messages[0].role, "user");
+        assert_eq!(result.messages[0].content.text.as_deref(), Some("Review src/main.rs"));
+    }
+
     #[test]
     fn test_initialize_params_serialization() {
         let params = serde_json::json!({
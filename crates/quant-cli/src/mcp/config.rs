@@ -8,12 +8,19 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 
 /// Configuration for an MCP server
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct McpServerConfig {
     /// Unique name for this server (used in tool prefixes)
     pub name: String,
-    /// Command to run the server
+    /// Command to run the server (local servers)
+    #[serde(default)]
     pub command: String,
+    /// Streamable HTTP endpoint (remote servers; used instead of `command`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// HTTP headers sent to remote servers, e.g. `Authorization` (supports ${VAR} expansion)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
     /// Arguments to pass to the command
     #[serde(default)]
     pub args: Vec<String>,
@@ -48,6 +55,8 @@ impl McpServerConfig {
         Self {
             name: name.into(),
             command: command.into(),
+            url: None,
+            headers: HashMap::new(),
             args: Vec::new(),
             env: HashMap::new(),
             cwd: None,
@@ -57,6 +66,16 @@ impl McpServerConfig {
         }
     }
 
+    /// Whether this server is reached over HTTP rather than spawned
+    pub fn is_remote(&self) -> bool {
+        self.url.is_some()
+    }
+
+    /// The URL for remote servers, the command otherwise
+    pub fn endpoint(&self) -> &str {
+        self.url.as_deref().unwrap_or(&self.command)
+    }
+
     /// Add an argument
     pub fn with_arg(mut self, arg: impl Into<String>) -> Self {
         self.args.push(arg.into());
@@ -87,6 +106,12 @@ impl McpServerConfig {
         for value in self.env.values_mut() {
             *value = expand_env_string(value)?;
         }
+        for value in self.headers.values_mut() {
+            *value = expand_env_string(value)?;
+        }
+        if let Some(ref mut url) = self.url {
+            *url = expand_env_string(url)?;
+        }
         Ok(())
     }
 }
@@ -179,6 +204,12 @@ pub fn parse_mcp_servers_from_yaml(yaml_str: &str) -> Result<Vec<McpServerConfig
     let configs: Vec<McpServerConfig> = serde_yaml::from_value(servers)
         .context("Failed to parse mcp_servers configuration")?;
 
+    for config in &configs {
+        if config.command.is_empty() && config.url.is_none() {
+            anyhow::bail!("MCP server '{}' needs either `command` or `url`", config.name);
+        }
+    }
+
     Ok(configs)
 }
 
@@ -208,5 +239,24 @@ mcp_servers:
         assert_eq!(servers[0].name, "github");
         assert_eq!(servers[0].command, "npx");
         assert_eq!(servers[0].args, vec!["-y", "@modelcontextprotocol/server-github"]);
+        assert!(!servers[0].is_remote());
+    }
+
+    #[test]
+    fn test_parse_remote_mcp_server() {
+        let yaml = r#"
+mcp_servers:
+  - name: "linear"
+    url: "https://mcp.linear.app/mcp"
+    headers:
+      Authorization: "Bearer ${LINEAR_TOKEN}"
+"#;
+        let servers = parse_mcp_servers_from_yaml(yaml).unwrap();
+        assert!(servers[0].is_remote());
+        assert_eq!(servers[0].endpoint(), "https://mcp.linear.app/mcp");
+        assert_eq!(servers[0].headers["Authorization"], "Bearer ${LINEAR_TOKEN}");
+
+        let missing = "mcp_servers:\n  - name: nothing\n";
+        assert!(parse_mcp_servers_from_yaml(missing).is_err());
     }
 }
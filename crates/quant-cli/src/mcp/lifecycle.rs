@@ -1,16 +1,23 @@
 //! MCP server lifecycle management
 //!
 //! Handles starting, stopping, and monitoring MCP server processes.
+//!
+//! [`McpManager::supervise`] pings running servers and restarts ones that
+//! died, backing off exponentially between attempts until the restart
+//! budget is spent. Restarts swap the new connection into the existing
+//! client, so tools registered before the crash keep working.
 
-use super::client::McpClient;
+use super::client::{GetPromptResult, McpClient, McpPromptArgument};
 use super::config::McpServerConfig;
 use super::tools::PrefixedMcpTool;
-use super::transport::StdioTransport;
+use super::transport::{HttpTransport, McpTransport, StdioTransport};
 use anyhow::{bail, Context, Result};
 use std::collections::HashMap;
+use std::fmt;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
 use tokio::time::timeout;
 use tracing::{debug, error, info, warn};
 
@@ -29,6 +36,58 @@ pub enum ServerState {
     ShuttingDown,
 }
 
+impl fmt::Display for ServerState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerState::Stopped => write!(f, "stopped"),
+            ServerState::Starting => write!(f, "starting"),
+            ServerState::Running => write!(f, "running"),
+            ServerState::Failed(error) => write!(f, "failed: {}", error),
+            ServerState::ShuttingDown => write!(f, "shutting down"),
+        }
+    }
+}
+
+/// How often to health check servers in the background
+pub const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How long a ping may take before the server counts as down
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest wait between restart attempts
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Something the supervisor noticed or did
+#[derive(Debug, Clone, PartialEq)]
+pub enum SupervisorEvent {
+    /// A server died or a restart failed
+    Down {
+        server: String,
+        error: String,
+        /// Wait before the next restart attempt (None once the budget is spent)
+        retry_in: Option<Duration>,
+        restarts: u32,
+    },
+    /// A server was brought back
+    Restarted { server: String, attempt: u32 },
+}
+
+impl fmt::Display for SupervisorEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SupervisorEvent::Down { server, error, retry_in: Some(wait), .. } => {
+                write!(f, "{} is down ({}); restarting in {}s", server, error, wait.as_secs().max(1))
+            }
+            SupervisorEvent::Down { server, error, retry_in: None, restarts } => {
+                write!(f, "{} is down ({}); gave up after {} restart(s)", server, error, restarts)
+            }
+            SupervisorEvent::Restarted { server, attempt } => {
+                write!(f, "{} restarted (attempt {})", server, attempt)
+            }
+        }
+    }
+}
+
 /// Information about a running MCP server
 pub struct McpServerHandle {
     /// Server configuration
@@ -41,6 +100,8 @@ pub struct McpServerHandle {
     pub restart_count: u32,
     /// Last error message
     pub last_error: Option<String>,
+    /// When the supervisor will next try to restart a failed server
+    next_restart: Option<Instant>,
 }
 
 impl McpServerHandle {
@@ -52,6 +113,7 @@ impl McpServerHandle {
             state: ServerState::Stopped,
             restart_count: 0,
             last_error: None,
+            next_restart: None,
         }
     }
 }
@@ -64,6 +126,10 @@ pub struct McpManager {
     max_restarts: u32,
     /// Initialization timeout
     init_timeout: Duration,
+    /// Wait before the first restart attempt, doubled for each one after
+    restart_backoff: Duration,
+    /// Supervisor events not yet taken
+    events: Vec<SupervisorEvent>,
 }
 
 impl McpManager {
@@ -73,6 +139,8 @@ impl McpManager {
             servers: HashMap::new(),
             max_restarts: 3,
             init_timeout: Duration::from_secs(30),
+            restart_backoff: Duration::from_secs(1),
+            events: Vec::new(),
         }
     }
 
@@ -88,6 +156,12 @@ impl McpManager {
         self
     }
 
+    /// Set the wait before the first restart attempt
+    pub fn with_restart_backoff(mut self, backoff: Duration) -> Self {
+        self.restart_backoff = backoff;
+        self
+    }
+
     /// Start a single MCP server
     pub async fn start_server(&mut self, mut config: McpServerConfig) -> Result<()> {
         let name = config.name.clone();
@@ -98,18 +172,40 @@ impl McpManager {
             format!("Failed to expand environment variables for MCP server: {}", name)
         })?;
 
-        // Spawn the transport
-        let transport = StdioTransport::spawn(
-            &config.command,
-            &config.args,
-            &config.env,
-            config.cwd.as_deref(),
-        )
-        .await
-        .with_context(|| format!("Failed to spawn MCP server: {}", name))?;
+        let client = Self::connect(&config).await?;
+
+        // Create handle and store
+        let mut handle = McpServerHandle::new(config, client);
+        handle.state = ServerState::Running;
+
+        self.servers.insert(name, handle);
+
+        Ok(())
+    }
+
+    /// Open a transport to a server and initialize a client over it
+    async fn connect(config: &McpServerConfig) -> Result<McpClient> {
+        let name = &config.name;
+
+        // Connect to remote servers over HTTP, spawn local ones
+        let transport: Box<dyn McpTransport> = match config.url {
+            Some(ref url) => Box::new(
+                HttpTransport::new(url).with_headers(config.headers.clone()),
+            ),
+            None => Box::new(
+                StdioTransport::spawn(
+                    &config.command,
+                    &config.args,
+                    &config.env,
+                    config.cwd.as_deref(),
+                )
+                .await
+                .with_context(|| format!("Failed to spawn MCP server: {}", name))?,
+            ),
+        };
 
         // Create client
-        let mut client = McpClient::new(Box::new(transport));
+        let mut client = McpClient::new(transport);
 
         // Initialize with timeout
         let init_timeout = Duration::from_secs(config.timeout_secs);
@@ -132,13 +228,7 @@ impl McpManager {
             }
         }
 
-        // Create handle and store
-        let mut handle = McpServerHandle::new(config, client);
-        handle.state = ServerState::Running;
-
-        self.servers.insert(name, handle);
-
-        Ok(())
+        Ok(client)
     }
 
     /// Start all configured servers
@@ -195,6 +285,56 @@ impl McpManager {
         Ok(())
     }
 
+    /// Bring running servers in line with a new set of configs
+    ///
+    /// Servers no longer configured are stopped, servers whose config changed
+    /// are restarted, and new servers are started. Unchanged servers keep
+    /// running.
+    pub async fn sync(&mut self, configs: Vec<McpServerConfig>) -> McpSyncReport {
+        let mut report = McpSyncReport::default();
+
+        let removed: Vec<String> = self
+            .servers
+            .keys()
+            .filter(|name| !configs.iter().any(|c| c.auto_start && &c.name == *name))
+            .cloned()
+            .collect();
+        for name in removed {
+            if let Err(e) = self.stop_server(&name).await {
+                warn!("Error stopping MCP server {}: {}", name, e);
+            }
+            report.stopped.push(name);
+        }
+
+        for config in configs.into_iter().filter(|c| c.auto_start) {
+            let name = config.name.clone();
+            let restart = match self.servers.get(&name) {
+                Some(handle) => {
+                    // Handles keep the expanded config, so compare against that
+                    let mut expanded = config.clone();
+                    let _ = expanded.expand_env_vars();
+                    if handle.config == expanded && handle.state == ServerState::Running {
+                        continue;
+                    }
+                    let _ = self.stop_server(&name).await;
+                    true
+                }
+                None => false,
+            };
+
+            match self.start_server(config).await {
+                Ok(()) if restart => report.restarted.push(name),
+                Ok(()) => report.started.push(name),
+                Err(e) => {
+                    warn!("Failed to start MCP server {}: {}", name, e);
+                    report.failed.push((name, e.to_string()));
+                }
+            }
+        }
+
+        report
+    }
+
     /// Get all running server names
     pub fn running_servers(&self) -> Vec<&str> {
         self.servers
@@ -299,18 +439,98 @@ impl McpManager {
         results
     }
 
+    /// Check running servers and restart failed ones that are due
+    ///
+    /// Servers whose process exited or that don't answer a ping are marked
+    /// failed and scheduled for a restart. Events are queued for
+    /// [`take_events`](Self::take_events).
+    pub async fn supervise(&mut self) {
+        let now = Instant::now();
+
+        for (name, handle) in &mut self.servers {
+            match handle.state {
+                ServerState::Running => {
+                    let Err(error) = check_server(&handle.client).await else {
+                        continue;
+                    };
+                    warn!("MCP server {} is down: {}", name, error);
+                    handle.state = ServerState::Failed(error.clone());
+                    handle.last_error = Some(error.clone());
+                    let retry_in = schedule_restart(handle, self.max_restarts, self.restart_backoff, now);
+                    self.events.push(SupervisorEvent::Down {
+                        server: name.clone(),
+                        error,
+                        retry_in,
+                        restarts: handle.restart_count,
+                    });
+                }
+                ServerState::Failed(_) if handle.next_restart.is_some_and(|at| at <= now) => {
+                    handle.restart_count += 1;
+                    info!("Restarting MCP server {} (attempt {})", name, handle.restart_count);
+                    match Self::connect(&handle.config).await {
+                        Ok(client) => {
+                            // Swap in place so registered tools use the new connection
+                            *handle.client.lock().await = client;
+                            handle.state = ServerState::Running;
+                            handle.next_restart = None;
+                            self.events.push(SupervisorEvent::Restarted {
+                                server: name.clone(),
+                                attempt: handle.restart_count,
+                            });
+                        }
+                        Err(e) => {
+                            let error = e.root_cause().to_string();
+                            warn!("Failed to restart MCP server {}: {}", name, error);
+                            handle.state = ServerState::Failed(error.clone());
+                            handle.last_error = Some(error.clone());
+                            let retry_in = schedule_restart(handle, self.max_restarts, self.restart_backoff, now);
+                            self.events.push(SupervisorEvent::Down {
+                                server: name.clone(),
+                                error,
+                                retry_in,
+                                restarts: handle.restart_count,
+                            });
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Take the supervisor events queued since the last call
+    pub fn take_events(&mut self) -> Vec<SupervisorEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Supervise the manager's servers every `interval` until the task is aborted
+    pub fn spawn_supervisor(manager: Arc<Mutex<McpManager>>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                manager.lock().await.supervise().await;
+            }
+        })
+    }
+
     /// Get summary of all servers
     pub fn summary(&self) -> Vec<ServerSummary> {
-        self.servers
+        let mut summary: Vec<ServerSummary> = self
+            .servers
             .iter()
             .map(|(name, handle)| ServerSummary {
                 name: name.clone(),
-                command: handle.config.command.clone(),
-                state: format!("{:?}", handle.state),
+                command: handle.config.endpoint().to_string(),
+                state: handle.state.to_string(),
                 restart_count: handle.restart_count,
                 last_error: handle.last_error.clone(),
             })
-            .collect()
+            .collect();
+        summary.sort_by(|a, b| a.name.cmp(&b.name));
+        summary
     }
 
     /// Discover all resources from running servers
@@ -361,11 +581,124 @@ impl McpManager {
         for item in result.contents {
             if let Some(text) = item.text {
                 content.push_str(&text);
+            } else if item.blob.is_some() {
+                let mime = item.mime_type.as_deref().unwrap_or("application/octet-stream");
+                content.push_str(&format!("[Binary content: {}]", mime));
             }
         }
 
         Ok(content)
     }
+
+    /// Discover all prompt templates from running servers
+    pub async fn discover_prompts(&self) -> Vec<McpPromptInfo> {
+        let mut all_prompts = Vec::new();
+
+        for (name, handle) in &self.servers {
+            if handle.state != ServerState::Running {
+                continue;
+            }
+
+            let client = handle.client.lock().await;
+            match client.list_prompts().await {
+                Ok(prompts) => {
+                    for prompt in prompts {
+                        all_prompts.push(McpPromptInfo {
+                            server: name.clone(),
+                            name: prompt.name,
+                            description: prompt.description,
+                            arguments: prompt.arguments,
+                        });
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to list prompts from MCP server {}: {}", name, e);
+                }
+            }
+        }
+
+        all_prompts
+    }
+
+    /// Render a prompt template from a specific server
+    pub async fn get_prompt(
+        &self,
+        server_name: &str,
+        name: &str,
+        arguments: &HashMap<String, String>,
+    ) -> Result<GetPromptResult> {
+        let handle = self.servers.get(server_name)
+            .ok_or_else(|| anyhow::anyhow!("Server not found: {}", server_name))?;
+
+        if handle.state != ServerState::Running {
+            anyhow::bail!("Server {} is not running", server_name);
+        }
+
+        let client = handle.client.lock().await;
+        client.get_prompt(name, arguments).await
+    }
+}
+
+/// Check that a server's process is alive and it answers a ping
+///
+/// A client busy with a request is taken as alive rather than waited on.
+async fn check_server(client: &Arc<Mutex<McpClient>>) -> std::result::Result<(), String> {
+    let Ok(client) = client.try_lock() else {
+        return Ok(());
+    };
+    if !client.is_connected() {
+        return Err("process exited".to_string());
+    }
+    match timeout(PING_TIMEOUT, client.ping()).await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err("ping timed out".to_string()),
+    }
+}
+
+/// Schedule a failed server's next restart, returning the wait, or None
+/// once it has used up its restarts
+fn schedule_restart(handle: &mut McpServerHandle, max_restarts: u32, backoff: Duration, now: Instant) -> Option<Duration> {
+    if handle.restart_count >= max_restarts {
+        handle.next_restart = None;
+        return None;
+    }
+    let wait = backoff
+        .saturating_mul(2u32.saturating_pow(handle.restart_count))
+        .min(MAX_RESTART_BACKOFF);
+    handle.next_restart = Some(now + wait);
+    Some(wait)
+}
+
+/// What [`McpManager::sync`] changed
+#[derive(Debug, Clone, Default)]
+pub struct McpSyncReport {
+    pub started: Vec<String>,
+    pub restarted: Vec<String>,
+    pub stopped: Vec<String>,
+    /// Server name and error
+    pub failed: Vec<(String, String)>,
+}
+
+impl McpSyncReport {
+    pub fn is_empty(&self) -> bool {
+        self.started.is_empty() && self.restarted.is_empty() && self.stopped.is_empty() && self.failed.is_empty()
+    }
+
+    /// One-line description, e.g. "restarted github; stopped fs"
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        for (label, names) in [("started", &self.started), ("restarted", &self.restarted), ("stopped", &self.stopped)] {
+            if !names.is_empty() {
+                parts.push(format!("{} {}", label, names.join(", ")));
+            }
+        }
+        if !self.failed.is_empty() {
+            let names: Vec<&str> = self.failed.iter().map(|(name, _)| name.as_str()).collect();
+            parts.push(format!("failed {}", names.join(", ")));
+        }
+        parts.join("; ")
+    }
 }
 
 /// Information about an MCP resource
@@ -378,6 +711,15 @@ pub struct McpResourceInfo {
     pub mime_type: Option<String>,
 }
 
+/// Information about an MCP prompt template
+#[derive(Debug, Clone)]
+pub struct McpPromptInfo {
+    pub server: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub arguments: Vec<McpPromptArgument>,
+}
+
 impl Default for McpManager {
     fn default() -> Self {
         Self::new()
@@ -420,4 +762,64 @@ mod tests {
         assert_eq!(ServerState::Running, ServerState::Running);
         assert_ne!(ServerState::Running, ServerState::Stopped);
     }
+
+    #[tokio::test]
+    async fn test_sync_reports_failures() {
+        let mut manager = McpManager::new();
+        let broken = McpServerConfig::new("broken", "/nonexistent/mcp-server");
+        let mut manual = McpServerConfig::new("manual", "/nonexistent/mcp-server");
+        manual.auto_start = false;
+
+        let report = manager.sync(vec![broken, manual]).await;
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, "broken");
+        assert!(report.started.is_empty());
+        assert_eq!(report.summary(), "failed broken");
+
+        assert!(manager.sync(Vec::new()).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_supervise_restarts_dead_server() {
+        // Answers initialize, then exits once the client says it's initialized
+        let script = r#"read line; echo '{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2024-11-05","capabilities":{},"serverInfo":{"name":"flaky"}}}'; read line"#;
+        let config = McpServerConfig::new("flaky", "sh").with_args(["-c", script]);
+        let mut manager = McpManager::new()
+            .with_max_restarts(1)
+            .with_restart_backoff(Duration::ZERO);
+        manager.start_server(config).await.unwrap();
+
+        let client = manager.get_client("flaky").unwrap();
+        for _ in 0..50 {
+            if !client.lock().await.is_connected() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        manager.supervise().await;
+        let events = manager.take_events();
+        assert!(matches!(events[..], [SupervisorEvent::Down { retry_in: Some(_), .. }]));
+        assert!(!manager.is_running("flaky"));
+
+        manager.supervise().await;
+        assert_eq!(
+            manager.take_events(),
+            vec![SupervisorEvent::Restarted { server: "flaky".to_string(), attempt: 1 }]
+        );
+        assert!(manager.is_running("flaky"));
+        assert_eq!(manager.summary()[0].restart_count, 1);
+
+        // The restart budget is spent once it dies again
+        for _ in 0..50 {
+            if !client.lock().await.is_connected() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        manager.supervise().await;
+        let events = manager.take_events();
+        assert!(matches!(events[..], [SupervisorEvent::Down { retry_in: None, restarts: 1, .. }]));
+        assert!(events[0].to_string().contains("gave up after 1 restart(s)"));
+    }
 }
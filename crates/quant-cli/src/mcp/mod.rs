@@ -58,6 +58,13 @@
 //! manager.stop_all().await;
 //! ```
 //!
+//! # Resources and prompts
+//!
+//! Server resources can be inlined into a prompt with `@server:resource`
+//! (URI or resource name) and read by agents through the `read_resource`
+//! tool. The REPL lists them with `/mcp resources`, and lists and runs
+//! prompt templates with `/mcp prompts` and `/mcp prompt`.
+//!
 //! # Configuration
 //!
 //! MCP servers can be configured in QUANT.md frontmatter:
@@ -73,20 +80,33 @@
 //!   - name: "filesystem"
 //!     command: "npx"
 //!     args: ["-y", "@modelcontextprotocol/server-filesystem", "./"]
+//!   - name: "linear"
+//!     url: "https://mcp.linear.app/mcp"
+//!     headers:
+//!       Authorization: "Bearer ${LINEAR_TOKEN}"
 //! ---
 //! ```
+//!
+//! Servers with a `url` are reached over the streamable HTTP transport
+//! instead of being spawned.
+//!
+//! # Supervision
+//!
+//! Agent runs and the REPL health check their servers in the background
+//! and restart ones that die; `quant mcp status` reports each server's state.
 
 pub mod client;
 pub mod config;
 pub mod lifecycle;
+pub mod resources;
 pub mod tools;
 pub mod transport;
 pub mod watcher;
 
 // Re-exports
-pub use client::{McpClient, McpResource, McpToolInfo};
 pub use config::{McpConfig, McpServerConfig};
-pub use lifecycle::{McpManager, McpResourceInfo, ServerState, ServerSummary};
+pub use lifecycle::{McpManager, ServerState, HEALTH_CHECK_INTERVAL};
+pub use resources::{expand_resource_refs, ReadResourceTool};
 pub use tools::{McpTool, PrefixedMcpTool};
 pub use transport::{HttpTransport, McpTransport, StdioTransport};
 pub use watcher::{ConfigChangeEvent, ConfigWatcher};
@@ -0,0 +1,242 @@
+//! MCP resources and prompt templates
+//!
+//! Resources reach the model two ways: `@server:resource` references in a
+//! prompt are read up front and inlined as context, and agents get a
+//! `read_resource` tool to fetch them on demand. A resource is named by its
+//! URI or by the name the server lists it under.
+
+use super::lifecycle::{McpManager, McpResourceInfo};
+use crate::tools::{ParameterProperty, ParameterSchema, SecurityLevel, Tool, ToolContext, ToolResult};
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Name of the agent tool for reading MCP resources
+pub const READ_RESOURCE_TOOL: &str = "read_resource";
+
+/// An `@server:resource` reference found in a prompt
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceRef {
+    pub server: String,
+    /// Resource URI or name
+    pub resource: String,
+}
+
+impl std::fmt::Display for ResourceRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "@{}:{}", self.server, self.resource)
+    }
+}
+
+/// Find `@server:resource` references to the given servers
+///
+/// References to unknown servers are left alone, so email addresses and
+/// `@mentions` don't turn into failed reads.
+pub fn parse_resource_refs(text: &str, servers: &[&str]) -> Vec<ResourceRef> {
+    let mut refs: Vec<ResourceRef> = Vec::new();
+
+    for word in text.split_whitespace() {
+        let Some(reference) = word.strip_prefix('@') else {
+            continue;
+        };
+        let reference = reference.trim_end_matches(|c: char| ".,;!?)]}\"'`".contains(c));
+        let Some((server, resource)) = reference.split_once(':') else {
+            continue;
+        };
+        if resource.is_empty() || !servers.contains(&server) {
+            continue;
+        }
+
+        let found = ResourceRef {
+            server: server.to_string(),
+            resource: resource.to_string(),
+        };
+        if !refs.contains(&found) {
+            refs.push(found);
+        }
+    }
+
+    refs
+}
+
+/// Find the listed resource a reference points at, by URI or name
+fn resolve<'a>(resources: &'a [McpResourceInfo], server: Option<&str>, resource: &str) -> Option<&'a McpResourceInfo> {
+    let candidates = || resources.iter().filter(|r| server.is_none_or(|s| r.server == s));
+    candidates()
+        .find(|r| r.uri == resource)
+        .or_else(|| candidates().find(|r| r.name == resource))
+}
+
+/// Read the resources referenced in `text`, formatted as a context block
+///
+/// Returns an empty string when the text has no references. References that
+/// don't match a listed resource are read as URIs, which covers servers
+/// that only advertise resource templates.
+pub async fn expand_resource_refs(manager: &McpManager, text: &str) -> Result<String> {
+    let refs = parse_resource_refs(text, &manager.running_servers());
+    if refs.is_empty() {
+        return Ok(String::new());
+    }
+
+    let resources = manager.discover_resources().await;
+    let mut context = String::new();
+
+    for reference in refs {
+        let uri = resolve(&resources, Some(&reference.server), &reference.resource)
+            .map(|r| r.uri.clone())
+            .unwrap_or_else(|| reference.resource.clone());
+        let content = manager
+            .read_resource(&reference.server, &uri)
+            .await
+            .with_context(|| format!("Failed to read {}", reference))?;
+
+        context.push_str(&format!("## {} ({})\n\n```\n{}\n```\n\n", reference, uri, content.trim_end()));
+    }
+
+    Ok(context)
+}
+
+/// One line per resource, e.g. "github:repo://readme - README (Project readme)"
+pub fn format_resource_list(resources: &[McpResourceInfo]) -> String {
+    resources
+        .iter()
+        .map(|r| {
+            let mut line = format!("{}:{} - {}", r.server, r.uri, r.name);
+            if let Some(ref description) = r.description {
+                line.push_str(&format!(" ({})", description));
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Agent tool that reads resources from the running MCP servers
+pub struct ReadResourceTool {
+    manager: Arc<Mutex<McpManager>>,
+}
+
+impl ReadResourceTool {
+    pub fn new(manager: Arc<Mutex<McpManager>>) -> Self {
+        Self { manager }
+    }
+}
+
+#[async_trait]
+impl Tool for ReadResourceTool {
+    fn name(&self) -> &str {
+        READ_RESOURCE_TOOL
+    }
+
+    fn description(&self) -> &str {
+        "Read a resource (document, file, record) exposed by a connected MCP server. Call without 'uri' to list the available resources."
+    }
+
+    fn security_level(&self) -> SecurityLevel {
+        SecurityLevel::Safe
+    }
+
+    fn untrusted_output(&self) -> bool {
+        true
+    }
+
+    fn parameters_schema(&self) -> ParameterSchema {
+        ParameterSchema::new()
+            .with_property("uri", ParameterProperty::string("Resource URI or name (omit to list resources)"))
+            .with_property("server", ParameterProperty::string("MCP server to read from (default: the server listing the resource)"))
+    }
+
+    async fn execute(&self, args: &Value, _ctx: &ToolContext) -> Result<ToolResult> {
+        let uri = args.get("uri").and_then(|v| v.as_str()).filter(|s| !s.is_empty());
+        let server = args.get("server").and_then(|v| v.as_str()).filter(|s| !s.is_empty());
+
+        let manager = self.manager.lock().await;
+        let resources = manager.discover_resources().await;
+
+        let Some(uri) = uri else {
+            let listed: Vec<McpResourceInfo> = resources
+                .into_iter()
+                .filter(|r| server.is_none_or(|s| r.server == s))
+                .collect();
+            if listed.is_empty() {
+                return Ok(ToolResult::success("No MCP resources available"));
+            }
+            return Ok(ToolResult::success(format_resource_list(&listed)));
+        };
+
+        let (server, uri) = match (resolve(&resources, server, uri), server) {
+            (Some(found), _) => (found.server.clone(), found.uri.clone()),
+            (None, Some(server)) => (server.to_string(), uri.to_string()),
+            (None, None) => {
+                return Ok(ToolResult::error(format!(
+                    "Unknown resource: {}. Call {} without 'uri' to list resources, or pass 'server'.",
+                    uri, READ_RESOURCE_TOOL
+                )));
+            }
+        };
+
+        match manager.read_resource(&server, &uri).await {
+            Ok(content) => Ok(ToolResult::success(content)),
+            Err(e) => Ok(ToolResult::error(format!("{:#}", e))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resource(server: &str, uri: &str, name: &str) -> McpResourceInfo {
+        McpResourceInfo {
+            server: server.to_string(),
+            uri: uri.to_string(),
+            name: name.to_string(),
+            description: None,
+            mime_type: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_resource_refs() {
+        let text = "Compare @docs:api-guide with @github:repo://readme. Mail me@example.com or @docs:api-guide again";
+        let refs = parse_resource_refs(text, &["docs", "github"]);
+
+        assert_eq!(refs.len(), 2);
+        assert_eq!(refs[0].to_string(), "@docs:api-guide");
+        assert_eq!(refs[1].server, "github");
+        assert_eq!(refs[1].resource, "repo://readme");
+
+        assert!(parse_resource_refs("ping @alice: hi", &["alice"]).is_empty());
+        assert!(parse_resource_refs("@unknown:thing", &["docs"]).is_empty());
+    }
+
+    #[test]
+    fn test_resolve_by_uri_or_name() {
+        let resources = vec![
+            resource("docs", "file:///guide.md", "guide"),
+            resource("github", "repo://readme", "README"),
+        ];
+
+        assert_eq!(resolve(&resources, Some("docs"), "guide").unwrap().uri, "file:///guide.md");
+        assert_eq!(resolve(&resources, None, "repo://readme").unwrap().server, "github");
+        assert!(resolve(&resources, Some("docs"), "README").is_none());
+        assert_eq!(
+            format_resource_list(&resources[..1]),
+            "docs:file:///guide.md - guide"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_resource_tool_without_servers() {
+        let tool = ReadResourceTool::new(Arc::new(Mutex::new(McpManager::new())));
+        let ctx = ToolContext::default();
+
+        let result = tool.execute(&serde_json::json!({}), &ctx).await.unwrap();
+        assert_eq!(result.output, "No MCP resources available");
+
+        let result = tool.execute(&serde_json::json!({"uri": "repo://readme"}), &ctx).await.unwrap();
+        assert!(!result.success);
+    }
+}
@@ -63,30 +63,35 @@ impl McpTool {
 
     /// Convert MCP JSON Schema to quant ParameterSchema
     fn convert_schema(&self) -> ParameterSchema {
-        let mut schema = ParameterSchema::new();
-
-        // Extract properties from MCP input_schema
-        if let Some(props) = self.tool_info.input_schema.get("properties") {
-            if let Some(obj) = props.as_object() {
-                for (name, prop_value) in obj {
-                    let prop = convert_json_schema_property(prop_value);
-                    schema.properties.insert(name.clone(), prop);
-                }
-            }
-        }
+        schema_from_json(&self.tool_info.input_schema)
+    }
+}
 
-        // Extract required fields
-        if let Some(required) = self.tool_info.input_schema.get("required") {
-            if let Some(arr) = required.as_array() {
-                schema.required = arr
-                    .iter()
-                    .filter_map(|v| v.as_str().map(String::from))
-                    .collect();
+/// Convert an object's JSON Schema to a ParameterSchema
+pub(crate) fn schema_from_json(input_schema: &Value) -> ParameterSchema {
+    let mut schema = ParameterSchema::new();
+
+    // Extract properties
+    if let Some(props) = input_schema.get("properties") {
+        if let Some(obj) = props.as_object() {
+            for (name, prop_value) in obj {
+                let prop = convert_json_schema_property(prop_value);
+                schema.properties.insert(name.clone(), prop);
             }
         }
+    }
 
-        schema
+    // Extract required fields
+    if let Some(required) = input_schema.get("required") {
+        if let Some(arr) = required.as_array() {
+            schema.required = arr
+                .iter()
+                .filter_map(|v| v.as_str().map(String::from))
+                .collect();
+        }
     }
+
+    schema
 }
 
 /// Convert a JSON Schema property to a ParameterProperty
@@ -149,12 +154,22 @@ impl Tool for McpTool {
         self.security_level
     }
 
+    fn untrusted_output(&self) -> bool {
+        true
+    }
+
     fn parameters_schema(&self) -> ParameterSchema {
         self.convert_schema()
     }
 
     async fn execute(&self, args: &Value, _ctx: &ToolContext) -> Result<ToolResult> {
         let client = self.client.lock().await;
+        if !client.is_connected() {
+            return Ok(ToolResult::error(format!(
+                "MCP server '{}' is down and is being restarted; try again later",
+                self.server_name
+            )));
+        }
 
         // Call the MCP tool with original (unprefixed) name
         let result = client
@@ -207,6 +222,11 @@ impl PrefixedMcpTool {
     pub fn original_name(&self) -> &str {
         &self.inner.tool_info.name
     }
+
+    /// The JSON Schema of the tool's arguments, as the server sent it
+    pub fn input_schema(&self) -> &Value {
+        &self.inner.tool_info.input_schema
+    }
 }
 
 #[async_trait]
@@ -223,6 +243,10 @@ impl Tool for PrefixedMcpTool {
         self.inner.security_level()
     }
 
+    fn untrusted_output(&self) -> bool {
+        true
+    }
+
     fn parameters_schema(&self) -> ParameterSchema {
         self.inner.parameters_schema()
     }
@@ -1,6 +1,6 @@
 //! MCP transport layer
 //!
-//! Supports stdio and HTTP transports for MCP server communication.
+//! Supports stdio and streamable HTTP transports for MCP server communication.
 
 use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
@@ -136,10 +136,19 @@ impl StdioTransport {
             cmd.current_dir(dir);
         }
 
+        #[cfg(unix)]
+        cmd.process_group(0);
+
         let child = cmd
             .spawn()
             .with_context(|| format!("Failed to spawn MCP server: {}", command))?;
 
+        let command_line = std::iter::once(command)
+            .chain(args.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ");
+        crate::procs::track(child.id(), crate::procs::ProcessKind::Mcp, &command_line);
+
         Self::new(child)
     }
 
@@ -155,6 +164,7 @@ impl StdioTransport {
             .context("Failed to read from MCP server")?;
 
         if line.is_empty() {
+            self.connected.store(false, std::sync::atomic::Ordering::SeqCst);
             bail!("MCP server closed connection");
         }
 
@@ -218,7 +228,19 @@ impl McpTransport for StdioTransport {
     }
 
     fn is_connected(&self) -> bool {
-        self.connected.load(std::sync::atomic::Ordering::SeqCst)
+        if !self.connected.load(std::sync::atomic::Ordering::SeqCst) {
+            return false;
+        }
+
+        // Notice a process that exited before anyone tried to talk to it
+        let exited = self
+            .child
+            .try_lock()
+            .is_ok_and(|mut child| matches!(child.try_wait(), Ok(Some(_))));
+        if exited {
+            self.connected.store(false, std::sync::atomic::Ordering::SeqCst);
+        }
+        !exited
     }
 
     async fn close(&mut self) -> Result<()> {
@@ -227,73 +249,446 @@ impl McpTransport for StdioTransport {
 
         // Try to kill the child process
         let mut child = self.child.lock().await;
+        let pid = child.id();
         let _ = child.kill().await;
 
+        // Servers started through npx and the like leave their own children
+        if let Some(pid) = pid {
+            crate::procs::terminate(pid);
+        }
+        crate::procs::untrack(pid);
+
         Ok(())
     }
 }
 
-/// HTTP/SSE transport for remote MCP servers
+/// Header carrying the session assigned by a streamable HTTP server
+const SESSION_HEADER: &str = "mcp-session-id";
+
+/// Header carrying the negotiated protocol version
+const PROTOCOL_VERSION_HEADER: &str = "mcp-protocol-version";
+
+/// Default number of retries for dropped connections and 5xx responses
+const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// One Server-Sent Event
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SseEvent {
+    pub id: Option<String>,
+    pub event: Option<String>,
+    pub data: String,
+}
+
+/// Incremental Server-Sent Events parser
+///
+/// Bytes can arrive split anywhere; complete events are returned as soon as
+/// their terminating blank line is seen.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+    current: SseEvent,
+    has_data: bool,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add bytes from the stream, returning the events they complete
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(bytes);
+        let mut events = Vec::new();
+
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                // Blank line dispatches the event
+                // An id-only event still moves the resume position
+                let event = std::mem::take(&mut self.current);
+                if std::mem::take(&mut self.has_data) || event.id.is_some() {
+                    events.push(event);
+                }
+                continue;
+            }
+            if line.starts_with(':') {
+                continue;
+            }
+
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                None => (line, ""),
+            };
+            match field {
+                "data" => {
+                    if self.has_data {
+                        self.current.data.push('\n');
+                    }
+                    self.current.data.push_str(value);
+                    self.has_data = true;
+                }
+                "event" => self.current.event = Some(value.to_string()),
+                "id" => self.current.id = Some(value.to_string()),
+                _ => {}
+            }
+        }
+
+        events
+    }
+}
+
+/// Why an HTTP exchange failed, which decides whether to retry
+enum HttpFailure {
+    /// The server forgot our session; re-initialize and retry
+    SessionExpired,
+    /// Network error, dropped stream, or 5xx/429; retry with backoff
+    Transient(anyhow::Error),
+    /// Anything else
+    Fatal(anyhow::Error),
+}
+
+impl From<reqwest::Error> for HttpFailure {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_connect() || e.is_timeout() || e.is_request() || e.is_body() {
+            Self::Transient(e.into())
+        } else {
+            Self::Fatal(e.into())
+        }
+    }
+}
+
+impl From<HttpFailure> for anyhow::Error {
+    fn from(failure: HttpFailure) -> Self {
+        match failure {
+            HttpFailure::SessionExpired => anyhow::anyhow!("MCP session expired"),
+            HttpFailure::Transient(e) | HttpFailure::Fatal(e) => e,
+        }
+    }
+}
+
+/// Streamable HTTP transport for remote MCP servers
+///
+/// Every message is POSTed to a single endpoint. The server answers with
+/// plain JSON or with an SSE stream that carries the response. A session id
+/// handed out at initialization is sent on every later request; when the
+/// server expires it (404), the transport replays the initialize handshake
+/// and retries. Dropped SSE streams are resumed with `Last-Event-ID`, and
+/// connection errors and 5xx responses are retried with backoff.
 pub struct HttpTransport {
-    base_url: String,
+    url: String,
     client: reqwest::Client,
+    headers: std::collections::HashMap<String, String>,
+    max_retries: u32,
+    session_id: std::sync::Mutex<Option<String>>,
+    protocol_version: std::sync::Mutex<Option<String>>,
+    /// The initialize request, replayed when the session expires
+    init_request: std::sync::Mutex<Option<JsonRpcRequest>>,
     connected: std::sync::atomic::AtomicBool,
 }
 
 impl HttpTransport {
     /// Create a new HTTP transport
-    pub fn new(base_url: impl Into<String>) -> Self {
+    pub fn new(url: impl Into<String>) -> Self {
+        let client = reqwest::Client::builder()
+            .connect_timeout(std::time::Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
         Self {
-            base_url: base_url.into(),
-            client: reqwest::Client::new(),
+            url: url.into(),
+            client,
+            headers: std::collections::HashMap::new(),
+            max_retries: DEFAULT_MAX_RETRIES,
+            session_id: std::sync::Mutex::new(None),
+            protocol_version: std::sync::Mutex::new(None),
+            init_request: std::sync::Mutex::new(None),
             connected: std::sync::atomic::AtomicBool::new(true),
         }
     }
-}
 
-#[async_trait]
-impl McpTransport for HttpTransport {
-    async fn send_request(&self, request: JsonRpcRequest) -> Result<JsonRpcResponse> {
+    /// Send these headers (e.g. `Authorization`) with every request
+    pub fn with_headers(mut self, headers: std::collections::HashMap<String, String>) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Set how often to retry dropped connections and 5xx responses
+    pub fn with_max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    /// Session id assigned by the server, if any
+    pub fn session_id(&self) -> Option<String> {
+        self.session_id.lock().ok().and_then(|s| s.clone())
+    }
+
+    fn set_session_id(&self, id: Option<String>) {
+        if let Ok(mut session) = self.session_id.lock() {
+            *session = id;
+        }
+    }
+
+    /// Build a request carrying the auth, session and version headers
+    fn request(&self, method: reqwest::Method) -> reqwest::RequestBuilder {
+        let mut builder = self.client.request(method, &self.url);
+        for (key, value) in &self.headers {
+            builder = builder.header(key, value);
+        }
+        if let Some(session) = self.session_id() {
+            builder = builder.header(SESSION_HEADER, session);
+        }
+        if let Some(version) = self.protocol_version.lock().ok().and_then(|v| v.clone()) {
+            builder = builder.header(PROTOCOL_VERSION_HEADER, version);
+        }
+        builder
+    }
+
+    /// Check the status of a response and record any session id it assigns
+    async fn check_response(&self, response: reqwest::Response) -> Result<reqwest::Response, HttpFailure> {
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND && self.session_id().is_some() {
+            return Err(HttpFailure::SessionExpired);
+        }
+        if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(HttpFailure::Transient(anyhow::anyhow!("MCP server returned {}", status)));
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(HttpFailure::Fatal(anyhow::anyhow!(
+                "MCP server returned {}: {}",
+                status,
+                body.trim()
+            )));
+        }
+
+        if let Some(session) = response.headers().get(SESSION_HEADER).and_then(|v| v.to_str().ok()) {
+            self.set_session_id(Some(session.to_string()));
+        }
+        Ok(response)
+    }
+
+    /// POST a message and return the response with the given id
+    async fn exchange(&self, request: &JsonRpcRequest) -> Result<JsonRpcResponse, HttpFailure> {
         let response = self
-            .client
-            .post(&self.base_url)
-            .json(&request)
+            .request(reqwest::Method::POST)
+            .header(reqwest::header::ACCEPT, "application/json, text/event-stream")
+            .json(request)
             .send()
-            .await
-            .context("Failed to send HTTP request to MCP server")?;
+            .await?;
+        let response = self.check_response(response).await?;
 
-        if !response.status().is_success() {
-            bail!(
-                "MCP server returned error status: {}",
-                response.status()
-            );
-        }
+        let is_stream = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"));
 
-        let json_response: JsonRpcResponse = response
-            .json()
-            .await
-            .context("Failed to parse JSON-RPC response from MCP server")?;
+        if is_stream {
+            return self.read_stream(response, request.id).await;
+        }
 
-        Ok(json_response)
+        let value: Value = response.json().await?;
+        find_response(value, request.id)
+            .map_err(HttpFailure::Fatal)?
+            .ok_or_else(|| HttpFailure::Fatal(anyhow::anyhow!("MCP server sent no response to request {}", request.id)))
     }
 
-    async fn send_notification(&self, method: &str, params: Option<Value>) -> Result<()> {
-        let notification = serde_json::json!({
-            "jsonrpc": "2.0",
-            "method": method,
-            "params": params
-        });
+    /// Read an SSE stream until the response to `id` arrives, resuming it
+    /// with `Last-Event-ID` if the connection drops first
+    async fn read_stream(&self, mut response: reqwest::Response, id: u64) -> Result<JsonRpcResponse, HttpFailure> {
+        use futures::StreamExt;
+
+        let mut last_event_id: Option<String> = None;
+        let mut resumes = 0;
 
-        self.client
-            .post(&self.base_url)
-            .json(&notification)
+        loop {
+            let mut parser = SseParser::new();
+            let mut body = response.bytes_stream();
+            while let Some(chunk) = body.next().await {
+                let chunk = match chunk {
+                    Ok(chunk) => chunk,
+                    Err(e) => {
+                        tracing::debug!(error = %e, "MCP event stream interrupted");
+                        break;
+                    }
+                };
+                for event in parser.feed(&chunk) {
+                    if event.id.is_some() {
+                        last_event_id = event.id.clone();
+                    }
+                    if event.data.is_empty() {
+                        continue;
+                    }
+                    let value: Value = match serde_json::from_str(&event.data) {
+                        Ok(value) => value,
+                        Err(e) => {
+                            tracing::debug!(error = %e, "Skipping malformed MCP event");
+                            continue;
+                        }
+                    };
+                    if let Some(found) = find_response(value, id).map_err(HttpFailure::Fatal)? {
+                        return Ok(found);
+                    }
+                }
+            }
+
+            let Some(ref event_id) = last_event_id else {
+                return Err(HttpFailure::Transient(anyhow::anyhow!(
+                    "MCP event stream ended before the response"
+                )));
+            };
+            if resumes >= self.max_retries {
+                return Err(HttpFailure::Transient(anyhow::anyhow!(
+                    "MCP event stream dropped {} times",
+                    resumes + 1
+                )));
+            }
+            resumes += 1;
+            tracing::debug!(last_event_id = %event_id, "Resuming MCP event stream");
+
+            let resumed = self
+                .request(reqwest::Method::GET)
+                .header(reqwest::header::ACCEPT, "text/event-stream")
+                .header("last-event-id", event_id.as_str())
+                .send()
+                .await?;
+            response = self.check_response(resumed).await?;
+        }
+    }
+
+    /// POST a notification, which the server acknowledges without a body
+    async fn notify(&self, notification: &Value) -> Result<(), HttpFailure> {
+        let response = self
+            .request(reqwest::Method::POST)
+            .header(reqwest::header::ACCEPT, "application/json, text/event-stream")
+            .json(notification)
             .send()
-            .await
-            .context("Failed to send notification to MCP server")?;
+            .await?;
+        self.check_response(response).await?;
+        Ok(())
+    }
 
+    /// Replay the initialize handshake after the server expired our session
+    async fn reinitialize(&self) -> Result<()> {
+        let init = self
+            .init_request
+            .lock()
+            .ok()
+            .and_then(|r| r.clone())
+            .context("MCP session expired before initialization")?;
+
+        tracing::info!(url = %self.url, "MCP session expired; re-initializing");
+        self.set_session_id(None);
+        let response = self.exchange(&init).await?;
+        if let Some(error) = response.error {
+            bail!("Failed to re-initialize MCP session: {}", error);
+        }
+        self.notify(&notification_value("notifications/initialized", None)).await?;
         Ok(())
     }
 
+    /// Wait before retry `attempt` (0-based): 250ms, 500ms, 1s, ...
+    async fn backoff(attempt: u32) {
+        let delay = std::time::Duration::from_millis(250 * 2u64.pow(attempt.min(6)));
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Find the response with the given id in a message or batch
+fn find_response(value: Value, id: u64) -> Result<Option<JsonRpcResponse>> {
+    let messages = match value {
+        Value::Array(messages) => messages,
+        message => vec![message],
+    };
+    for message in messages {
+        // Requests and notifications from the server are not handled yet
+        if message.get("method").is_some() || message.get("id").and_then(Value::as_u64) != Some(id) {
+            continue;
+        }
+        let response: JsonRpcResponse =
+            serde_json::from_value(message).context("Failed to parse JSON-RPC response from MCP server")?;
+        return Ok(Some(response));
+    }
+    Ok(None)
+}
+
+fn notification_value(method: &str, params: Option<Value>) -> Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": params
+    })
+}
+
+#[async_trait]
+impl McpTransport for HttpTransport {
+    async fn send_request(&self, request: JsonRpcRequest) -> Result<JsonRpcResponse> {
+        let is_initialize = request.method == "initialize";
+        if is_initialize {
+            self.set_session_id(None);
+            if let Ok(mut init) = self.init_request.lock() {
+                *init = Some(request.clone());
+            }
+        }
+
+        let mut attempt = 0;
+        let mut reinitialized = false;
+        loop {
+            match self.exchange(&request).await {
+                Ok(response) => {
+                    if is_initialize {
+                        let version = response
+                            .result
+                            .as_ref()
+                            .and_then(|r| r.get("protocolVersion"))
+                            .and_then(Value::as_str)
+                            .map(String::from);
+                        if let Ok(mut negotiated) = self.protocol_version.lock() {
+                            *negotiated = version;
+                        }
+                    }
+                    return Ok(response);
+                }
+                Err(HttpFailure::SessionExpired) if !is_initialize && !reinitialized => {
+                    self.reinitialize().await?;
+                    reinitialized = true;
+                }
+                Err(HttpFailure::Transient(e)) if attempt < self.max_retries => {
+                    tracing::debug!(error = %e, attempt, "Retrying MCP request");
+                    Self::backoff(attempt).await;
+                    attempt += 1;
+                }
+                Err(failure) => {
+                    return Err(anyhow::Error::from(failure)
+                        .context(format!("MCP request '{}' to {} failed", request.method, self.url)));
+                }
+            }
+        }
+    }
+
+    async fn send_notification(&self, method: &str, params: Option<Value>) -> Result<()> {
+        let notification = notification_value(method, params);
+
+        let mut attempt = 0;
+        loop {
+            match self.notify(&notification).await {
+                Ok(()) => return Ok(()),
+                Err(HttpFailure::Transient(e)) if attempt < self.max_retries => {
+                    tracing::debug!(error = %e, attempt, "Retrying MCP notification");
+                    Self::backoff(attempt).await;
+                    attempt += 1;
+                }
+                Err(failure) => {
+                    return Err(anyhow::Error::from(failure)
+                        .context(format!("Failed to send notification to MCP server {}", self.url)));
+                }
+            }
+        }
+    }
+
     fn is_connected(&self) -> bool {
         self.connected.load(std::sync::atomic::Ordering::SeqCst)
     }
@@ -301,6 +696,14 @@ impl McpTransport for HttpTransport {
     async fn close(&mut self) -> Result<()> {
         self.connected
             .store(false, std::sync::atomic::Ordering::SeqCst);
+
+        // End the session; servers that don't support this answer 405
+        if self.session_id().is_some() {
+            if let Err(e) = self.request(reqwest::Method::DELETE).send().await {
+                tracing::debug!(error = %e, "Failed to end MCP session");
+            }
+            self.set_session_id(None);
+        }
         Ok(())
     }
 }
@@ -322,4 +725,133 @@ mod tests {
         assert!(json.contains("\"id\":1"));
         assert!(json.contains("\"method\":\"tools/list\""));
     }
+
+    #[test]
+    fn test_sse_parser_split_chunks() {
+        let mut parser = SseParser::new();
+        assert!(parser.feed(b": keep-alive\n\nid: 7\r\nevent: mess").is_empty());
+
+        let events = parser.feed(b"age\ndata: {\"a\":\ndata: 1}\n\ndata: two\n");
+        assert_eq!(
+            events,
+            vec![SseEvent {
+                id: Some("7".to_string()),
+                event: Some("message".to_string()),
+                data: "{\"a\":\n1}".to_string(),
+            }]
+        );
+        assert_eq!(parser.feed(b"\n")[0].data, "two");
+    }
+
+    #[test]
+    fn test_find_response_in_batch() {
+        let batch = serde_json::json!([
+            {"jsonrpc": "2.0", "method": "notifications/progress", "params": {}},
+            {"jsonrpc": "2.0", "id": 2, "result": {}},
+            {"jsonrpc": "2.0", "id": 3, "result": {"ok": true}}
+        ]);
+        let found = find_response(batch, 3).unwrap().unwrap();
+        assert_eq!(found.result.unwrap()["ok"], true);
+        assert!(find_response(serde_json::json!({"jsonrpc": "2.0", "id": 1, "result": {}}), 4)
+            .unwrap()
+            .is_none());
+    }
+
+    /// Serve one scripted HTTP response per connection, recording each request
+    async fn mock_server(responses: Vec<String>) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        use tokio::io::AsyncReadExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut data = Vec::new();
+                let mut buf = [0u8; 4096];
+                loop {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    data.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&data).to_string();
+                    if let Some(header_end) = text.find("\r\n\r\n") {
+                        let length = text
+                            .lines()
+                            .find_map(|l| l.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                            .unwrap_or(0);
+                        if data.len() >= header_end + 4 + length {
+                            requests.push(text);
+                            break;
+                        }
+                    }
+                    if n == 0 {
+                        break;
+                    }
+                }
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.shutdown().await.unwrap();
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    fn http_response(status: &str, headers: &[&str], body: &str) -> String {
+        let mut response = format!("HTTP/1.1 {}\r\nconnection: close\r\ncontent-length: {}\r\n", status, body.len());
+        for header in headers {
+            response.push_str(header);
+            response.push_str("\r\n");
+        }
+        response.push_str("\r\n");
+        response.push_str(body);
+        response
+    }
+
+    #[tokio::test]
+    async fn test_http_transport_session_expiry() {
+        let init_result = r#"{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2025-03-26","capabilities":{},"serverInfo":{"name":"mock"}}}"#;
+        let (url, server) = mock_server(vec![
+            // initialize, answered over SSE with a session id
+            http_response(
+                "200 OK",
+                &["content-type: text/event-stream", "mcp-session-id: first"],
+                &format!("id: 1\ndata: {}\n\n", init_result),
+            ),
+            // the session expires
+            http_response("404 Not Found", &[], ""),
+            // initialize is replayed
+            http_response("200 OK", &["content-type: application/json", "mcp-session-id: second"], init_result),
+            http_response("202 Accepted", &[], ""),
+            // and the original request retried
+            http_response(
+                "200 OK",
+                &["content-type: application/json"],
+                r#"{"jsonrpc":"2.0","id":2,"result":{"tools":[]}}"#,
+            ),
+        ])
+        .await;
+
+        let mut headers = std::collections::HashMap::new();
+        headers.insert("Authorization".to_string(), "Bearer secret".to_string());
+        let transport = HttpTransport::new(url).with_headers(headers).with_max_retries(0);
+
+        let init = transport
+            .send_request(JsonRpcRequest::new(1, "initialize", Some(serde_json::json!({}))))
+            .await
+            .unwrap();
+        assert!(init.result.is_some());
+        assert_eq!(transport.session_id().as_deref(), Some("first"));
+
+        let tools = transport.send_request(JsonRpcRequest::new(2, "tools/list", None)).await.unwrap();
+        assert_eq!(tools.result.unwrap()["tools"], serde_json::json!([]));
+        assert_eq!(transport.session_id().as_deref(), Some("second"));
+
+        let requests: Vec<String> = server.await.unwrap().into_iter().map(|r| r.to_lowercase()).collect();
+        assert!(requests.iter().all(|r| r.contains("authorization: bearer secret")));
+        assert!(!requests[0].contains("mcp-session-id"));
+        assert!(requests[1].contains("mcp-session-id: first"));
+        assert!(requests[1].contains("mcp-protocol-version: 2025-03-26"));
+        assert!(requests[2].contains("\"method\":\"initialize\""));
+        assert!(requests[3].contains("notifications/initialized"));
+        assert!(requests[4].contains("mcp-session-id: second"));
+    }
 }
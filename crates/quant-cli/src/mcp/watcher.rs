@@ -1,6 +1,9 @@
-//! Hot-reload support for QUANT.md changes
+//! Hot-reload support for QUANT.md and config file changes
 //!
-//! Watches for changes to QUANT.md and triggers MCP server reconfiguration.
+//! Watches QUANT.md (hooks, MCP servers) and the quant config files (tool
+//! permissions) so long-running sessions can pick up edits. Directories are
+//! watched rather than the files themselves, since editors often save by
+//! replacing the file.
 
 use anyhow::Result;
 use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
@@ -10,7 +13,7 @@ use std::time::Duration;
 use tracing::{debug, info, warn};
 
 /// Event types for configuration changes
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ConfigChangeEvent {
     /// QUANT.md was modified
     QuantMdModified(PathBuf),
@@ -18,6 +21,21 @@ pub enum ConfigChangeEvent {
     QuantMdCreated(PathBuf),
     /// QUANT.md was deleted
     QuantMdDeleted(PathBuf),
+    /// A watched config file was created, modified, or deleted
+    ConfigFileChanged(PathBuf),
+}
+
+impl ConfigChangeEvent {
+    /// Whether QUANT.md (hooks, MCP servers) changed
+    pub fn is_quant_md(&self) -> bool {
+        !matches!(self, Self::ConfigFileChanged(_))
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::QuantMdModified(p) | Self::QuantMdCreated(p) | Self::QuantMdDeleted(p) | Self::ConfigFileChanged(p) => p,
+        }
+    }
 }
 
 /// Watcher for configuration file changes
@@ -25,7 +43,12 @@ pub struct ConfigWatcher {
     #[allow(dead_code)]
     watcher: RecommendedWatcher,
     receiver: Receiver<Result<Event, notify::Error>>,
+    project_root: PathBuf,
     quant_md_path: Option<PathBuf>,
+    /// Extra files to report as [`ConfigChangeEvent::ConfigFileChanged`]
+    config_files: Vec<PathBuf>,
+    /// Directories currently watched
+    watched: Vec<PathBuf>,
 }
 
 impl ConfigWatcher {
@@ -45,39 +68,72 @@ impl ConfigWatcher {
         Ok(Self {
             watcher,
             receiver: rx,
+            project_root: project_root.to_path_buf(),
             quant_md_path,
+            config_files: Vec::new(),
+            watched: Vec::new(),
         })
     }
 
-    /// Start watching the configuration file
+    /// Also report changes to these files (missing files are picked up when created)
+    pub fn with_config_files(mut self, files: impl IntoIterator<Item = PathBuf>) -> Self {
+        self.config_files.extend(files);
+        self
+    }
+
+    /// Also watch the quant user config and the project config for `working_dir`
+    pub fn with_quant_config(self, working_dir: &Path) -> Self {
+        let project_config = crate::config::find_project_config(working_dir)
+            .unwrap_or_else(|| self.project_root.join(crate::config::PROJECT_CONFIG));
+        let files = llm_core::layered::user_config_path().into_iter().chain([project_config]);
+        self.with_config_files(files)
+    }
+
+    /// Start watching QUANT.md and the config files
     pub fn start(&mut self) -> Result<()> {
-        if let Some(ref path) = self.quant_md_path {
-            self.watcher.watch(path, RecursiveMode::NonRecursive)?;
-            info!(path = ?path, "Started watching QUANT.md for changes");
-        } else {
-            debug!("No QUANT.md found to watch");
+        let mut dirs = vec![self.project_root.clone()];
+        dirs.extend(self.config_files.iter().filter_map(|f| f.parent().map(Path::to_path_buf)));
+        dirs.dedup();
+
+        for dir in dirs {
+            if self.watched.contains(&dir) || !dir.is_dir() {
+                continue;
+            }
+            self.watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+            debug!(dir = ?dir, "Watching for config changes");
+            self.watched.push(dir);
+        }
+
+        match self.quant_md_path {
+            Some(ref path) => info!(path = ?path, "Started watching QUANT.md for changes"),
+            None => debug!("No QUANT.md yet; watching for one to be created"),
         }
         Ok(())
     }
 
     /// Stop watching
     pub fn stop(&mut self) -> Result<()> {
-        if let Some(ref path) = self.quant_md_path {
-            self.watcher.unwatch(path)?;
-            info!("Stopped watching QUANT.md");
+        for dir in self.watched.drain(..) {
+            self.watcher.unwatch(&dir)?;
         }
+        info!("Stopped watching config files");
         Ok(())
     }
 
     /// Check for pending change events (non-blocking)
-    pub fn poll_events(&self) -> Vec<ConfigChangeEvent> {
+    ///
+    /// Repeated events for the same file (one save often produces several)
+    /// are reported once.
+    pub fn poll_events(&mut self) -> Vec<ConfigChangeEvent> {
         let mut events = Vec::new();
 
         while let Ok(result) = self.receiver.try_recv() {
             match result {
                 Ok(event) => {
                     if let Some(change_event) = self.process_event(event) {
-                        events.push(change_event);
+                        if !events.contains(&change_event) {
+                            events.push(change_event);
+                        }
                     }
                 }
                 Err(e) => {
@@ -90,7 +146,7 @@ impl ConfigWatcher {
     }
 
     /// Wait for the next change event (blocking)
-    pub fn wait_for_event(&self) -> Option<ConfigChangeEvent> {
+    pub fn wait_for_event(&mut self) -> Option<ConfigChangeEvent> {
         match self.receiver.recv() {
             Ok(Ok(event)) => self.process_event(event),
             Ok(Err(e)) => {
@@ -102,19 +158,21 @@ impl ConfigWatcher {
     }
 
     /// Process a notify event into a config change event
-    fn process_event(&self, event: Event) -> Option<ConfigChangeEvent> {
-        let quant_md = self.quant_md_path.as_ref()?;
-
-        // Check if this event is for QUANT.md
-        let is_quant_md = event
-            .paths
-            .iter()
-            .any(|p| p.ends_with("QUANT.md") || p.ends_with("quant.md"));
-
-        if !is_quant_md {
+    fn process_event(&mut self, event: Event) -> Option<ConfigChangeEvent> {
+        if !matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_) | EventKind::Remove(_)) {
             return None;
         }
 
+        if let Some(path) = event.paths.iter().find(|p| self.config_files.iter().any(|f| f == *p)) {
+            info!(path = ?path, "Config file changed");
+            return Some(ConfigChangeEvent::ConfigFileChanged(path.clone()));
+        }
+
+        // Check if this event is for QUANT.md in the project root
+        let quant_md = event.paths.iter().find(|p| {
+            p.parent() == Some(self.project_root.as_path()) && (p.ends_with("QUANT.md") || p.ends_with("quant.md"))
+        })?;
+
         match event.kind {
             EventKind::Modify(_) => {
                 info!("QUANT.md modified");
@@ -122,10 +180,12 @@ impl ConfigWatcher {
             }
             EventKind::Create(_) => {
                 info!("QUANT.md created");
+                self.quant_md_path = Some(quant_md.clone());
                 Some(ConfigChangeEvent::QuantMdCreated(quant_md.clone()))
             }
             EventKind::Remove(_) => {
                 info!("QUANT.md deleted");
+                self.quant_md_path = Self::find_quant_md(&self.project_root);
                 Some(ConfigChangeEvent::QuantMdDeleted(quant_md.clone()))
             }
             _ => None,
@@ -181,4 +241,31 @@ mod tests {
         let found = ConfigWatcher::find_quant_md(dir.path());
         assert!(found.is_none());
     }
+
+    #[test]
+    fn test_process_event_filters_paths() {
+        use notify::event::{CreateKind, ModifyKind};
+
+        let dir = TempDir::new().unwrap();
+        let config = dir.path().join(".quant").join("config.toml");
+        let mut watcher = ConfigWatcher::new(dir.path())
+            .unwrap()
+            .with_config_files(vec![config.clone()]);
+        assert!(!watcher.has_quant_md());
+
+        let modify = |path: PathBuf| Event::new(EventKind::Modify(ModifyKind::Any)).add_path(path);
+        assert_eq!(
+            watcher.process_event(modify(config.clone())),
+            Some(ConfigChangeEvent::ConfigFileChanged(config))
+        );
+        assert_eq!(watcher.process_event(modify(dir.path().join("README.md"))), None);
+        assert_eq!(watcher.process_event(modify(dir.path().join("docs").join("QUANT.md"))), None);
+
+        let quant_md = dir.path().join("QUANT.md");
+        let created = Event::new(EventKind::Create(CreateKind::File)).add_path(quant_md.clone());
+        let event = watcher.process_event(created).unwrap();
+        assert!(event.is_quant_md());
+        assert_eq!(event.path(), quant_md.as_path());
+        assert_eq!(watcher.quant_md_path(), Some(quant_md.as_path()));
+    }
 }
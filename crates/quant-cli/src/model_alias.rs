@@ -0,0 +1,141 @@
+//! Resolving model names given to ask, chat, agent and run
+//!
+//! A name is looked up in order as an alias from `[aliases.models]`, an
+//! installed model (`llama3.2` meaning `llama3.2:latest`), then as the start
+//! of exactly one alias or installed model name, so `-m qwen` works when
+//! only one qwen model is installed. Names matching nothing are used as
+//! given, and may then be pulled.
+
+use anyhow::{bail, Result};
+use llm_core::OllamaClient;
+use std::collections::{BTreeMap, HashMap};
+use tracing::debug;
+
+use crate::config::{ModelAlias, ModelOptions};
+
+/// A model name resolved to an installed model or alias target
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedModel {
+    pub model: String,
+    /// The alias it was chosen by, if any
+    pub alias: Option<String>,
+    /// Default options of the alias
+    pub options: ModelOptions,
+}
+
+impl ResolvedModel {
+    fn alias(name: &str, alias: &ModelAlias) -> Self {
+        Self {
+            model: alias.model().to_string(),
+            alias: Some(name.to_string()),
+            options: alias.options(),
+        }
+    }
+
+    fn model(model: &str) -> Self {
+        Self {
+            model: model.to_string(),
+            alias: None,
+            options: ModelOptions::default(),
+        }
+    }
+}
+
+/// Resolve `name` against the aliases and the models installed in Ollama
+pub async fn resolve_model(
+    client: &OllamaClient,
+    aliases: &HashMap<String, ModelAlias>,
+    name: &str,
+) -> Result<ResolvedModel> {
+    if let Some(alias) = aliases.get(name) {
+        return Ok(ResolvedModel::alias(name, alias));
+    }
+    let installed: Vec<String> = match client.list_models().await {
+        Ok(models) => models.into_iter().map(|m| m.name).collect(),
+        Err(e) => {
+            debug!(error = %e, "Failed to list models; using model name as given");
+            Vec::new()
+        }
+    };
+    let resolved = resolve(name, aliases, &installed)?;
+    if resolved.model != name {
+        debug!(name, model = %resolved.model, "Resolved model name");
+    }
+    Ok(resolved)
+}
+
+/// Resolve `name` against `aliases` and the `installed` model names
+pub fn resolve(name: &str, aliases: &HashMap<String, ModelAlias>, installed: &[String]) -> Result<ResolvedModel> {
+    if let Some(alias) = aliases.get(name) {
+        return Ok(ResolvedModel::alias(name, alias));
+    }
+    let latest = format!("{}:latest", name);
+    if name.is_empty() || installed.iter().any(|m| *m == name || *m == latest) {
+        return Ok(ResolvedModel::model(name));
+    }
+
+    // Partial names: candidates by the model they resolve to
+    let mut matches: BTreeMap<&str, ResolvedModel> = BTreeMap::new();
+    for (alias_name, alias) in aliases {
+        if alias_name.starts_with(name) {
+            matches.insert(alias_name, ResolvedModel::alias(alias_name, alias));
+        }
+    }
+    for model in installed {
+        let base = model.rsplit('/').next().unwrap_or(model);
+        if (model.starts_with(name) || base.starts_with(name)) && !matches.values().any(|m| m.model == *model) {
+            matches.insert(model, ResolvedModel::model(model));
+        }
+    }
+
+    if matches.len() > 1 {
+        let candidates: Vec<String> = matches
+            .into_iter()
+            .map(|(matched, resolved)| match resolved.alias {
+                Some(_) => format!("{} (alias of {})", matched, resolved.model),
+                None => matched.to_string(),
+            })
+            .collect();
+        bail!("Model '{}' is ambiguous; it could be: {}", name, candidates.join(", "));
+    }
+    Ok(matches.into_values().next().unwrap_or_else(|| ResolvedModel::model(name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let aliases = HashMap::from([
+            ("code".to_string(), ModelAlias::Model("qwen2.5-coder:7b".to_string())),
+            (
+                "fast".to_string(),
+                ModelAlias::WithOptions {
+                    model: "llama3.2:3b".to_string(),
+                    options: ModelOptions {
+                        temperature: Some(0.2),
+                        ..Default::default()
+                    },
+                },
+            ),
+        ]);
+        let installed: Vec<String> = ["qwen2.5-coder:7b", "qwen2.5:14b", "llama3.2:3b", "library/mistral:latest"]
+            .map(String::from)
+            .to_vec();
+
+        let fast = resolve("fast", &aliases, &installed).unwrap();
+        assert_eq!(fast.model, "llama3.2:3b");
+        assert_eq!(fast.alias.as_deref(), Some("fast"));
+        assert_eq!(fast.options.temperature, Some(0.2));
+
+        assert_eq!(resolve("qwen2.5:14b", &aliases, &installed).unwrap().model, "qwen2.5:14b");
+        assert_eq!(resolve("llama", &aliases, &installed).unwrap().model, "llama3.2:3b");
+        assert_eq!(resolve("mis", &aliases, &installed).unwrap().model, "library/mistral:latest");
+        assert_eq!(resolve("co", &aliases, &installed).unwrap().alias.as_deref(), Some("code"));
+        assert_eq!(resolve("phi3", &aliases, &installed).unwrap(), ResolvedModel::model("phi3"));
+
+        let err = resolve("qwen", &aliases, &installed).unwrap_err().to_string();
+        assert!(err.contains("qwen2.5-coder:7b") && err.contains("qwen2.5:14b"), "{}", err);
+    }
+}
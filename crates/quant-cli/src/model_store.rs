@@ -0,0 +1,78 @@
+//! Installed Ollama models on disk
+//!
+//! Ollama keeps each model as a manifest naming its layers, stored as blobs
+//! by digest. Only the model weights layer (the GGUF) is looked up here.
+
+use std::path::{Path, PathBuf};
+
+/// The GGUF of `model` in the first Ollama model store that has it
+pub fn find_blob(model: &str, ollama_home: Option<&Path>) -> Option<PathBuf> {
+    stores(ollama_home).iter().find_map(|store| blob(store, model))
+}
+
+/// Ollama model store directories, most specific first
+fn stores(ollama_home: Option<&Path>) -> Vec<PathBuf> {
+    let mut stores = Vec::new();
+    if let Some(dir) = std::env::var_os("OLLAMA_MODELS") {
+        stores.push(PathBuf::from(dir));
+    }
+    if let Some(home) = ollama_home {
+        stores.push(home.join("models"));
+    }
+    if let Some(home) = dirs::home_dir() {
+        stores.push(home.join(".ollama").join("models"));
+    }
+    stores
+}
+
+/// The GGUF blob of `model` in an Ollama model store, from its manifest
+fn blob(store: &Path, model: &str) -> Option<PathBuf> {
+    let (name, tag) = model.split_once(':').unwrap_or((model, "latest"));
+    let mut parts: Vec<&str> = name.split('/').collect();
+    if parts.len() == 1 {
+        parts.insert(0, "library");
+    }
+    if parts.len() == 2 {
+        parts.insert(0, "registry.ollama.ai");
+    }
+
+    let mut manifest_path = store.join("manifests");
+    parts.iter().for_each(|part| manifest_path.push(part));
+    manifest_path.push(tag);
+
+    let manifest: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(manifest_path).ok()?).ok()?;
+    let digest = manifest["layers"]
+        .as_array()?
+        .iter()
+        .find(|layer| layer["mediaType"] == "application/vnd.ollama.image.model")?["digest"]
+        .as_str()?;
+    let blob = store.join("blobs").join(digest.replace(':', "-"));
+    blob.is_file().then_some(blob)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blob() {
+        let store = tempfile::TempDir::new().unwrap();
+        let manifest_dir = store.path().join("manifests/registry.ollama.ai/library/qwen2.5-coder");
+        std::fs::create_dir_all(&manifest_dir).unwrap();
+        std::fs::create_dir_all(store.path().join("blobs")).unwrap();
+        std::fs::write(
+            manifest_dir.join("7b"),
+            r#"{"layers":[{"mediaType":"application/vnd.ollama.image.license","digest":"sha256:111"},
+                {"mediaType":"application/vnd.ollama.image.model","digest":"sha256:abc"}]}"#,
+        )
+        .unwrap();
+        std::fs::write(store.path().join("blobs/sha256-abc"), b"GGUF").unwrap();
+
+        assert_eq!(
+            blob(store.path(), "qwen2.5-coder:7b"),
+            Some(store.path().join("blobs/sha256-abc"))
+        );
+        assert_eq!(blob(store.path(), "qwen2.5-coder"), None);
+        assert_eq!(blob(store.path(), "llama3:8b"), None);
+    }
+}
@@ -0,0 +1,168 @@
+//! Path handling that holds up on Windows
+//!
+//! `fs::canonicalize` returns verbatim paths on Windows (`\\?\C:\src`,
+//! `\\?\UNC\server\share`), which glob patterns, child process working
+//! directories and users all choke on, and matching paths as strings
+//! (`contains("/target/")`) misses backslash separators. These helpers
+//! work on path components instead.
+
+use std::ffi::OsStr;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+/// Directories never searched for context or by the search tools
+pub const IGNORED_DIRS: &[&str] = &[
+    "target",
+    "node_modules",
+    ".git",
+    "dist",
+    "build",
+    "__pycache__",
+    "venv",
+    ".venv",
+    "vendor",
+    ".idea",
+    ".vscode",
+];
+
+/// `fs::canonicalize` without the Windows verbatim prefix, so the result
+/// can be displayed, globbed and used as a working directory
+pub fn canonicalize(path: impl AsRef<Path>) -> io::Result<PathBuf> {
+    path.as_ref().canonicalize().map(|p| simplify(&p))
+}
+
+/// Drop the verbatim prefix from a Windows path where that doesn't change
+/// its meaning; other paths are returned as they are
+pub fn simplify(path: &Path) -> PathBuf {
+    match path.to_str() {
+        Some(s) if cfg!(windows) => PathBuf::from(strip_verbatim(s)),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// `\\?\C:\dir` -> `C:\dir` and `\\?\UNC\server\share` -> `\\server\share`
+fn strip_verbatim(path: &str) -> String {
+    if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        return format!(r"\\{}", rest);
+    }
+    match path.strip_prefix(r"\\?\") {
+        Some(rest) if is_drive_path(rest) => rest.to_string(),
+        _ => path.to_string(),
+    }
+}
+
+/// `C:\...` or `C:` alone
+fn is_drive_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 2
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && bytes.get(2).is_none_or(|&b| b == b'\\')
+}
+
+/// Whether `path` lies inside `root`; both are canonicalized, and for a
+/// path that doesn't exist yet, its nearest existing ancestor is
+pub fn is_within(path: &Path, root: &Path) -> bool {
+    let Ok(root) = canonicalize(root) else {
+        return false;
+    };
+    let mut existing = path;
+    let mut rest = Vec::new();
+    while !existing.exists() {
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name);
+                existing = parent;
+            }
+            // `..` past a missing directory, or nothing left to resolve
+            _ => return false,
+        }
+    }
+    let Ok(mut resolved) = canonicalize(existing) else {
+        return false;
+    };
+    resolved.extend(rest.iter().rev());
+    resolved.starts_with(&root)
+}
+
+/// Whether any directory of `path` (not its file name) is one of `names`
+pub fn in_dir_named(path: &Path, names: &[&str]) -> bool {
+    path.parent().is_some_and(|dirs| {
+        dirs.components().any(|c| match c {
+            Component::Normal(name) => names.iter().any(|n| name == OsStr::new(n)),
+            _ => false,
+        })
+    })
+}
+
+/// A glob pattern matching `pattern` under `dir`, with glob characters in
+/// the directory itself (`[`, `*`, `?`) escaped
+pub fn glob_under(dir: &Path, pattern: &str) -> String {
+    let dir = simplify(dir);
+    let dir = glob::Pattern::escape(&dir.to_string_lossy());
+    format!("{}/{}", dir.trim_end_matches(['/', '\\']), pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_strip_verbatim() {
+        assert_eq!(strip_verbatim(r"\\?\C:\src\quant"), r"C:\src\quant");
+        assert_eq!(strip_verbatim(r"\\?\UNC\server\share\repo"), r"\\server\share\repo");
+        // Verbatim paths with no plain equivalent keep their prefix
+        assert_eq!(strip_verbatim(r"\\?\Volume{1234}\dir"), r"\\?\Volume{1234}\dir");
+        assert_eq!(strip_verbatim("/home/me/src"), "/home/me/src");
+    }
+
+    #[test]
+    fn test_in_dir_named() {
+        assert!(in_dir_named(Path::new("proj/target/debug/main.rs"), IGNORED_DIRS));
+        assert!(!in_dir_named(Path::new("proj/src/target.rs"), IGNORED_DIRS));
+        assert!(!in_dir_named(Path::new("proj/src/build"), IGNORED_DIRS));
+        assert!(!in_dir_named(Path::new("proj/targets/a.rs"), IGNORED_DIRS));
+    }
+
+    #[test]
+    fn test_is_within() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().join("root");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+
+        assert!(is_within(&root.join("src"), &root));
+        assert!(is_within(&root.join("new/dir/file.rs"), &root));
+        assert!(!is_within(&root.join("../outside.rs"), &root));
+        assert!(!is_within(&root.join("missing/../../outside.rs"), &root));
+        assert!(!is_within(dir.path(), &root));
+    }
+
+    #[test]
+    fn test_glob_under_escapes_dir() {
+        let dir = TempDir::new().unwrap();
+        let odd = dir.path().join("[work]");
+        std::fs::create_dir_all(&odd).unwrap();
+        std::fs::write(odd.join("main.rs"), "").unwrap();
+
+        let found: Vec<PathBuf> = glob::glob(&glob_under(&odd, "**/*.rs")).unwrap().flatten().collect();
+        assert_eq!(found, vec![odd.join("main.rs")]);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_windows_paths() {
+        let dir = TempDir::new().unwrap();
+        let canonical = canonicalize(dir.path()).unwrap();
+        assert!(!canonical.to_string_lossy().starts_with(r"\\?\"));
+        assert!(is_within(&canonical.join("a.rs"), dir.path()));
+
+        assert!(in_dir_named(Path::new(r"C:\proj\target\debug\main.rs"), IGNORED_DIRS));
+        assert!(in_dir_named(Path::new(r"\\server\share\proj\node_modules\x\index.js"), IGNORED_DIRS));
+        assert!(!in_dir_named(Path::new(r"\\server\share\proj\src\main.rs"), IGNORED_DIRS));
+        assert_eq!(
+            simplify(Path::new(r"\\?\UNC\server\share\proj")),
+            PathBuf::from(r"\\server\share\proj")
+        );
+    }
+}
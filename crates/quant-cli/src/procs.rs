@@ -0,0 +1,543 @@
+//! Processes quant starts, tracked so they can be found after a crash
+//!
+//! MCP servers, language servers, bash tool commands and background jobs
+//! each run in their own process group, recorded in a run manifest
+//! (`<data dir>/quant/runs/<pid>.json`) while they run. A clean exit stops
+//! the servers and removes the manifest, keeping only bash commands that
+//! left background jobs behind.
+//!
+//! When quant crashes the manifest stays. On the next interactive start,
+//! [`check_orphans`] finds manifests whose quant process is gone and offers
+//! to stop what they list; `quant ps` shows everything still running.
+//!
+//! Process IDs are reused, after a reboot especially, so a recorded group
+//! only counts as running when `ps` shows members that started after it was
+//! recorded and run the recorded command; see [`TrackedProcess::is_running`].
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use tracing::{debug, warn};
+
+const YELLOW: &str = "\x1b[93m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// Seconds a process may have started before it was recorded (`ps` start
+/// times are whole seconds, and processes are recorded once spawned)
+const START_SLACK_SECS: i64 = 2;
+
+/// What a tracked process is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProcessKind {
+    Mcp,
+    Lsp,
+    Bash,
+    /// Background job started with the `process_start` tool
+    Job,
+}
+
+impl fmt::Display for ProcessKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProcessKind::Mcp => write!(f, "mcp"),
+            ProcessKind::Lsp => write!(f, "lsp"),
+            ProcessKind::Bash => write!(f, "bash"),
+            ProcessKind::Job => write!(f, "job"),
+        }
+    }
+}
+
+/// A process group started by quant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackedProcess {
+    /// PID of the group leader, which is also the group id
+    pub pid: u32,
+    pub kind: ProcessKind,
+    /// Command line, e.g. "npx -y @modelcontextprotocol/server-github"
+    pub command: String,
+    pub started_at: DateTime<Utc>,
+}
+
+impl TrackedProcess {
+    /// Whether this process group is still the one quant started, judged by
+    /// the live processes in `ps`
+    ///
+    /// Every member must have started after the group was recorded. The
+    /// leader, while it runs, must have started when it was recorded; once
+    /// it exits, its PID can't be reused while the group has members. Some
+    /// member must also run a program named in the recorded command.
+    pub fn is_running(&self, ps: &[PsEntry]) -> bool {
+        let earliest = self.started_at - chrono::Duration::seconds(START_SLACK_SECS);
+        let members: Vec<&PsEntry> = ps.iter().filter(|e| e.pgid == self.pid).collect();
+        !members.is_empty()
+            && members.iter().all(|m| m.started >= earliest)
+            && members
+                .iter()
+                .filter(|m| m.pid == self.pid)
+                .all(|leader| leader.started <= self.started_at + chrono::Duration::seconds(START_SLACK_SECS))
+            && members.iter().any(|m| runs_command(&m.command, &self.command))
+    }
+}
+
+/// Whether a live command line runs one of the programs in a recorded one
+///
+/// Loose on purpose: `npx server` runs as `node .../npx server`, and a bash
+/// job like `cd web && npm run dev &` as `node .../npm-cli.js run dev`.
+fn runs_command(live: &str, recorded: &str) -> bool {
+    recorded
+        .split(|c: char| c.is_whitespace() || "&|;()".contains(c))
+        .filter(|word| !word.starts_with('-') && !word.contains('='))
+        .filter_map(|word| Path::new(word).file_name().and_then(|n| n.to_str()))
+        .filter(|name| name.len() >= 3)
+        .any(|name| live.contains(name))
+}
+
+/// Processes started by one quant process
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunManifest {
+    /// PID of the quant process
+    pub pid: u32,
+    pub started_at: DateTime<Utc>,
+    pub processes: Vec<TrackedProcess>,
+    /// The user chose to leave these processes running; don't ask again
+    #[serde(default)]
+    pub kept: bool,
+}
+
+impl RunManifest {
+    fn new() -> Self {
+        Self {
+            pid: std::process::id(),
+            started_at: Utc::now(),
+            processes: Vec::new(),
+            kept: false,
+        }
+    }
+
+    /// Whether the quant process that wrote this manifest is still running
+    pub fn owner_alive(&self) -> bool {
+        self.pid == std::process::id() || process_alive(self.pid)
+    }
+
+    fn save(&self, dir: &Path) -> Result<()> {
+        let path = dir.join(format!("{}.json", self.pid));
+        if self.processes.is_empty() {
+            if path.exists() {
+                fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+            return Ok(());
+        }
+        fs::create_dir_all(dir)?;
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// This process's manifest
+static CURRENT: Mutex<Option<RunManifest>> = Mutex::new(None);
+
+/// Directory holding the run manifests
+fn runs_dir() -> Result<PathBuf> {
+    // Unit tests spawn servers and commands; keep them out of the user's runs
+    if cfg!(test) {
+        anyhow::bail!("Run manifests are disabled in tests");
+    }
+
+    let data_dir = dirs::data_local_dir()
+        .or_else(dirs::data_dir)
+        .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+
+    Ok(data_dir.join("quant").join("runs"))
+}
+
+/// Apply a change to this process's manifest and save it
+fn update(change: impl FnOnce(&mut RunManifest)) {
+    let Ok(mut current) = CURRENT.lock() else {
+        return;
+    };
+    let manifest = current.get_or_insert_with(RunManifest::new);
+    change(manifest);
+
+    if let Err(e) = runs_dir().and_then(|dir| manifest.save(&dir)) {
+        debug!(error = %e, "Failed to save run manifest");
+    }
+}
+
+/// Record a process group started by quant (`pid` is its leader)
+pub fn track(pid: Option<u32>, kind: ProcessKind, command: &str) {
+    let Some(pid) = pid else {
+        return;
+    };
+    update(|manifest| {
+        manifest.processes.push(TrackedProcess {
+            pid,
+            kind,
+            command: command.to_string(),
+            started_at: Utc::now(),
+        })
+    });
+}
+
+/// Forget a process group
+pub fn untrack(pid: Option<u32>) {
+    let Some(pid) = pid else {
+        return;
+    };
+    update(|manifest| manifest.processes.retain(|p| p.pid != pid));
+}
+
+/// Forget a finished process group unless something in it is still running
+/// (e.g. a job a bash command put in the background)
+pub fn release(pid: Option<u32>) {
+    if pid.is_some_and(group_alive) {
+        debug!(pid, "Command left background processes running");
+        return;
+    }
+    untrack(pid);
+}
+
+/// Stop this run's servers and background jobs and forget processes that
+/// have exited
+///
+/// Called on exit. Bash background jobs that are still running stay in the
+/// manifest.
+pub fn finish() {
+    let Ok(mut current) = CURRENT.lock() else {
+        return;
+    };
+    let Some(mut manifest) = current.take() else {
+        return;
+    };
+
+    for process in manifest.processes.iter().filter(|p| p.kind != ProcessKind::Bash) {
+        terminate(process.pid);
+    }
+    manifest
+        .processes
+        .retain(|p| p.kind == ProcessKind::Bash && group_alive(p.pid));
+
+    if let Err(e) = runs_dir().and_then(|dir| manifest.save(&dir)) {
+        debug!(error = %e, "Failed to save run manifest");
+    }
+}
+
+/// Load every run manifest, dropping processes that have exited
+///
+/// Manifests left with nothing running are deleted.
+pub fn load_runs() -> Result<Vec<RunManifest>> {
+    load_runs_from(&runs_dir()?)
+}
+
+fn load_runs_from(dir: &Path) -> Result<Vec<RunManifest>> {
+    load_runs_with(dir, &list_processes())
+}
+
+fn load_runs_with(dir: &Path, ps: &[PsEntry]) -> Result<Vec<RunManifest>> {
+    let mut runs = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(runs);
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let manifest: RunManifest = match fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| serde_json::from_str(&json).map_err(anyhow::Error::from))
+        {
+            Ok(m) => m,
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Ignoring unreadable run manifest");
+                continue;
+            }
+        };
+
+        // Our own manifest is kept current by `update`
+        if manifest.pid == std::process::id() {
+            continue;
+        }
+
+        let mut live = manifest.clone();
+        live.processes.retain(|p| p.is_running(ps));
+        if live.processes.len() != manifest.processes.len() {
+            live.save(dir)?;
+        }
+        if !live.processes.is_empty() {
+            runs.push(live);
+        }
+    }
+
+    runs.sort_by_key(|r| r.started_at);
+    Ok(runs)
+}
+
+/// Stop the processes of the given runs and delete their manifests
+///
+/// Groups are checked against `ps` once more just before, so a group whose
+/// ID was taken over meanwhile is left alone.
+pub fn stop_runs(runs: &[RunManifest]) -> Result<usize> {
+    let dir = runs_dir()?;
+    let ps = list_processes();
+    let mut stopped = 0;
+    for run in runs {
+        for process in run.processes.iter().filter(|p| p.is_running(&ps)) {
+            terminate(process.pid);
+            stopped += 1;
+        }
+        RunManifest { processes: Vec::new(), ..run.clone() }.save(&dir)?;
+    }
+    Ok(stopped)
+}
+
+/// Offer to stop processes left behind by quant runs that are gone
+///
+/// Asks on a terminal; otherwise only logs a warning pointing at `quant ps`.
+pub fn check_orphans() {
+    let runs = match load_runs() {
+        Ok(runs) => runs,
+        Err(e) => {
+            debug!(error = %e, "Failed to read run manifests");
+            return;
+        }
+    };
+    let orphaned: Vec<RunManifest> = runs.into_iter().filter(|r| !r.kept && !r.owner_alive()).collect();
+    if orphaned.is_empty() {
+        return;
+    }
+
+    let count: usize = orphaned.iter().map(|r| r.processes.len()).sum();
+    if !crate::tools::security::is_interactive() {
+        warn!(processes = count, "Processes from an earlier quant run are still running; see `quant ps`");
+        return;
+    }
+
+    // Show what runs now, which is what would be stopped
+    let ps = list_processes();
+    println!("{}Found {} process(es) left running by an earlier quant run:{}", YELLOW, count, RESET);
+    for run in &orphaned {
+        for process in &run.processes {
+            println!("  {:<5} {:>7}  {}{}{}", process.kind, process.pid, DIM, process.command, RESET);
+            for member in ps.iter().filter(|e| e.pgid == process.pid) {
+                println!("        {:>7}  {}", member.pid, member.command);
+            }
+        }
+    }
+    print!("Stop them? [y/N] ");
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return;
+    }
+
+    if matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+        match stop_runs(&orphaned) {
+            Ok(stopped) => println!("{}Stopped {} process(es){}", DIM, stopped, RESET),
+            Err(e) => warn!(error = %e, "Failed to stop orphaned processes"),
+        }
+    } else {
+        println!("{}Left running; `quant ps` lists them{}", DIM, RESET);
+        let dir = match runs_dir() {
+            Ok(dir) => dir,
+            Err(_) => return,
+        };
+        for run in orphaned {
+            let kept = RunManifest { kept: true, ..run };
+            if let Err(e) = kept.save(&dir) {
+                debug!(error = %e, "Failed to save run manifest");
+            }
+        }
+    }
+}
+
+/// A live process, from `ps`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PsEntry {
+    pub pid: u32,
+    pub pgid: u32,
+    pub started: DateTime<Utc>,
+    pub command: String,
+}
+
+/// Every live process
+pub fn list_processes() -> Vec<PsEntry> {
+    // lstart is printed in the C locale's format, in local time
+    let output = Command::new("ps")
+        .args(["-A", "-o", "pid=,pgid=,lstart=,command="])
+        .env("LC_ALL", "C")
+        .output();
+    match output {
+        Ok(output) => parse_ps(&String::from_utf8_lossy(&output.stdout)),
+        Err(e) => {
+            debug!(error = %e, "Failed to run ps");
+            Vec::new()
+        }
+    }
+}
+
+/// Commands of the live processes in each group, from `ps`
+pub fn group_commands(pgids: &[u32]) -> Vec<(u32, u32, String)> {
+    list_processes()
+        .into_iter()
+        .filter(|e| pgids.contains(&e.pgid))
+        .map(|e| (e.pid, e.pgid, e.command))
+        .collect()
+}
+
+/// Parse `ps -o pid=,pgid=,lstart=,command=` output
+fn parse_ps(output: &str) -> Vec<PsEntry> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pid = fields.next()?.parse().ok()?;
+            let pgid = fields.next()?.parse().ok()?;
+            // e.g. "Sat Oct 17 09:05:02 2026"
+            let lstart = fields.by_ref().take(5).collect::<Vec<_>>().join(" ");
+            let started = NaiveDateTime::parse_from_str(&lstart, "%a %b %d %H:%M:%S %Y").ok()?;
+            let started = Local.from_local_datetime(&started).earliest()?.with_timezone(&Utc);
+            Some(PsEntry { pid, pgid, started, command: fields.collect::<Vec<_>>().join(" ") })
+        })
+        .collect()
+}
+
+/// Whether anything in the process group is still running
+#[cfg(unix)]
+pub fn group_alive(pgid: u32) -> bool {
+    use nix::errno::Errno;
+    use nix::sys::signal::killpg;
+    use nix::unistd::Pid;
+
+    !matches!(killpg(Pid::from_raw(pgid as i32), None), Err(Errno::ESRCH))
+}
+
+#[cfg(not(unix))]
+pub fn group_alive(_pgid: u32) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    use nix::errno::Errno;
+    use nix::sys::signal::kill;
+    use nix::unistd::Pid;
+
+    !matches!(kill(Pid::from_raw(pid as i32), None), Err(Errno::ESRCH))
+}
+
+#[cfg(not(unix))]
+fn process_alive(_pid: u32) -> bool {
+    false
+}
+
+/// Ask every process in the group to exit
+#[cfg(unix)]
+pub fn terminate(pgid: u32) {
+    use nix::sys::signal::{killpg, Signal};
+    use nix::unistd::Pid;
+
+    if let Err(e) = killpg(Pid::from_raw(pgid as i32), Signal::SIGTERM) {
+        debug!(pgid, error = %e, "Failed to signal process group");
+    }
+}
+
+#[cfg(not(unix))]
+pub fn terminate(_pgid: u32) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_ps() {
+        let output = "    1     1 Sat Oct 17 09:05:02 2026 /sbin/init\n  420   420 Mon Nov  2 18:00:00 2026 npx -y @modelcontextprotocol/server-github\n  421   420 Mon Nov  2 18:00:01 2026 node server.js --port 3000\nbogus\n";
+        let processes = parse_ps(output);
+        assert_eq!(processes.len(), 3);
+        assert_eq!((processes[2].pid, processes[2].pgid), (421, 420));
+        assert_eq!(processes[2].command, "node server.js --port 3000");
+        let expected = Local.with_ymd_and_hms(2026, 11, 2, 18, 0, 1).unwrap().with_timezone(&Utc);
+        assert_eq!(processes[2].started, expected);
+    }
+
+    #[test]
+    fn test_is_running_rejects_reused_ids() {
+        let recorded = Utc::now() - chrono::Duration::hours(1);
+        let process = TrackedProcess {
+            pid: 420,
+            kind: ProcessKind::Mcp,
+            command: "npx -y @modelcontextprotocol/server-github".to_string(),
+            started_at: recorded,
+        };
+        let entry = |pid, started, command: &str| PsEntry { pid, pgid: 420, started, command: command.to_string() };
+
+        let ours = [entry(420, recorded, "node /usr/bin/npx -y @modelcontextprotocol/server-github")];
+        assert!(process.is_running(&ours));
+        // A job the leader left behind, started later
+        assert!(process.is_running(&[entry(421, Utc::now(), "node server-github/dist/index.js")]));
+
+        // After a reboot, the ID went to an unrelated group leader
+        assert!(!process.is_running(&[entry(420, Utc::now(), "node /usr/bin/npx -y @modelcontextprotocol/server-github")]));
+        // ...or to something that was already running before
+        assert!(!process.is_running(&[entry(421, recorded - chrono::Duration::hours(2), "npx server-github")]));
+        // The right age, but a different command
+        assert!(!process.is_running(&[entry(420, recorded, "/usr/lib/postgresql/bin/postgres")]));
+        assert!(!process.is_running(&[]));
+    }
+
+    #[test]
+    fn test_runs_command() {
+        assert!(runs_command("node /usr/lib/node_modules/npm/bin/npm-cli.js run dev", "cd web && npm run dev &"));
+        assert!(runs_command("/usr/bin/python3 -m http.server", "python3 -m http.server 8000 &"));
+        assert!(!runs_command("sshd: /usr/sbin/sshd -D", "sleep 30 &"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_load_runs_finds_orphans() {
+        use std::os::unix::process::CommandExt;
+
+        let dir = TempDir::new().unwrap();
+
+        // A quant process that is gone, and a process group it left behind
+        let mut exited = std::process::Command::new("true").spawn().unwrap();
+        let owner = exited.id();
+        exited.wait().unwrap();
+        let mut sleeper = std::process::Command::new("sleep").arg("30").process_group(0).spawn().unwrap();
+
+        let process = |pid| TrackedProcess {
+            pid,
+            kind: ProcessKind::Bash,
+            command: "sleep 30 &".to_string(),
+            started_at: Utc::now(),
+        };
+        let manifest = RunManifest {
+            pid: owner,
+            started_at: Utc::now(),
+            processes: vec![process(sleeper.id()), process(owner)],
+            kept: false,
+        };
+        manifest.save(dir.path()).unwrap();
+
+        let runs = load_runs_from(dir.path()).unwrap();
+        assert_eq!(runs.len(), 1);
+        assert!(!runs[0].owner_alive());
+        assert_eq!(runs[0].processes.len(), 1);
+        assert_eq!(runs[0].processes[0].pid, sleeper.id());
+
+        terminate(sleeper.id());
+        sleeper.wait().unwrap();
+        assert!(!group_alive(sleeper.id()));
+        assert!(load_runs_from(dir.path()).unwrap().is_empty());
+        assert!(fs::read_dir(dir.path()).unwrap().next().is_none());
+    }
+}
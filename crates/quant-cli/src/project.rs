@@ -1,16 +1,62 @@
 //! Project context discovery and QUANT.md support
 //!
 //! Provides Claude Code-like project understanding by:
-//! 1. Discovering QUANT.md project files
+//! 1. Discovering QUANT.md project files: a global one in the quant config
+//!    directory, the repository root's, and nested package-level ones,
+//!    merged with the most specific taking precedence
 //! 2. Auto-detecting project type (Rust, Node, Python, etc.)
 //! 3. Building a project structure summary
 //! 4. Providing relevant context to the LLM
 //! 5. Parsing MCP server configurations from QUANT.md frontmatter
+//! 6. Summarizing declared dependencies and their resolved versions
+//! 7. Picking a system prompt template for the project type, overridable in
+//!    `.quant/prompts/system.md`
+//! 8. Generating a starter QUANT.md and `.quant/` scaffolding (`quant init`)
 
+use crate::context::ContextAssembly;
+use crate::deps::{self, DependencyManifest};
 use crate::mcp::McpServerConfig;
 use std::path::{Path, PathBuf};
 use tracing::{debug, info};
 
+/// Maximum dependencies listed per manifest in the system prompt
+const MAX_DEPS_IN_CONTEXT: usize = 30;
+
+/// Project-local replacement for the built-in prompt template
+pub const SYSTEM_PROMPT_OVERRIDE: &str = ".quant/prompts/system.md";
+
+const RUST_TEMPLATE: &str = "## Rust Conventions
+- Build with `cargo build`; check with `cargo clippy --all-targets -- -D warnings`
+- Run tests with `cargo test` (`cargo test <name>` for one test)
+- Format with `cargo fmt` and match the existing module layout
+- Return `Result` and propagate errors with `?` rather than `unwrap()` outside tests
+- Unit tests live in a `#[cfg(test)] mod tests` at the bottom of the file
+";
+
+const NODE_TEMPLATE: &str = "## Node.js Conventions
+- Install with the lockfile's package manager (npm, yarn or pnpm)
+- Use the scripts in package.json: `npm run build`, `npm test`, `npm run lint`
+- Keep to the project's module system (ESM or CommonJS) and TypeScript settings
+- Prefer async/await over callbacks; don't leave promises unhandled
+- Add dependencies through the package manager, never by editing the lockfile
+";
+
+const PYTHON_TEMPLATE: &str = "## Python Conventions
+- Work inside the project's virtualenv; install with `pip install -e .` or the configured tool (uv, poetry)
+- Run tests with `pytest` (`pytest path/to/test.py::name` for one test)
+- Lint and format with the tools configured in pyproject.toml (ruff, black, mypy)
+- Follow PEP 8 and add type hints to new functions
+- Raise specific exceptions instead of bare `except:` handlers
+";
+
+const GO_TEMPLATE: &str = "## Go Conventions
+- Build with `go build ./...` and vet with `go vet ./...`
+- Run tests with `go test ./...` (`go test -run Name ./pkg` for one test)
+- Format with `gofmt`; keep imports grouped as goimports does
+- Return errors instead of panicking and wrap them with `fmt.Errorf(\"...: %w\", err)`
+- Tests live next to the code in `_test.go` files, table-driven where it fits
+";
+
 /// Project type detection
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ProjectType {
@@ -46,6 +92,17 @@ impl ProjectType {
             ProjectType::Unknown => &[],
         }
     }
+
+    /// Built-in system prompt section with build, test and style conventions
+    pub fn prompt_template(&self) -> Option<&'static str> {
+        match self {
+            ProjectType::Rust => Some(RUST_TEMPLATE),
+            ProjectType::Node => Some(NODE_TEMPLATE),
+            ProjectType::Python => Some(PYTHON_TEMPLATE),
+            ProjectType::Go => Some(GO_TEMPLATE),
+            ProjectType::Java | ProjectType::Unknown => None,
+        }
+    }
 }
 
 impl std::fmt::Display for ProjectType {
@@ -182,6 +239,50 @@ impl QuantFile {
     pub fn has_mcp_servers(&self) -> bool {
         !self.mcp_servers.is_empty()
     }
+
+    /// Merge QUANT.md files ordered from least to most specific
+    ///
+    /// Content is concatenated with a heading per file, MCP servers with the
+    /// same name and context settings are taken from the most specific file,
+    /// and `path` is that file's. A single file is returned unchanged.
+    pub fn merge(mut files: Vec<QuantFile>) -> Option<QuantFile> {
+        if files.len() <= 1 {
+            return files.pop();
+        }
+
+        let mut merged = QuantFile::default();
+        let mut context_config = ContextConfig::default();
+        let mut sections = Vec::new();
+
+        for file in files {
+            sections.push(format!("### {}\n\n{}", file.path.display(), file.content.trim()));
+            merged.instructions.extend(file.instructions);
+            for server in file.mcp_servers {
+                match merged.mcp_servers.iter_mut().find(|s| s.name == server.name) {
+                    Some(existing) => *existing = server,
+                    None => merged.mcp_servers.push(server),
+                }
+            }
+            if let Some(cfg) = file.context_config {
+                context_config.max_tokens = cfg.max_tokens.or(context_config.max_tokens);
+                context_config.include_dependencies = cfg.include_dependencies.or(context_config.include_dependencies);
+            }
+            merged.description = file.description.or(merged.description);
+            merged.frontmatter = file.frontmatter.or(merged.frontmatter);
+            merged.path = file.path;
+        }
+
+        merged.content = sections.join("\n\n");
+        if context_config.max_tokens.is_some() || context_config.include_dependencies.is_some() {
+            merged.context_config = Some(context_config);
+        }
+        Some(merged)
+    }
+}
+
+/// Where QUANT.md instructions for every project live (`~/.config/quant/QUANT.md`)
+pub fn global_quant_file_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("quant").join("QUANT.md"))
 }
 
 /// Project context containing all discovered information
@@ -191,8 +292,11 @@ pub struct ProjectContext {
     pub root: PathBuf,
     /// Detected project type
     pub project_type: ProjectType,
-    /// QUANT.md content if found
+    /// Effective QUANT.md: every file in `quant_files` merged
     pub quant_file: Option<QuantFile>,
+    /// QUANT.md files found, from least to most specific: global, repository
+    /// root, then nested directories down to the project root
+    pub quant_files: Vec<QuantFile>,
     /// Project name (from config file or directory)
     pub name: String,
     /// Key files that exist in the project
@@ -201,6 +305,11 @@ pub struct ProjectContext {
     pub structure: Vec<String>,
     /// Git information if available
     pub git_info: Option<GitInfo>,
+    /// Dependency manifests (Cargo.toml, package.json, pyproject.toml)
+    pub dependencies: Vec<DependencyManifest>,
+    /// Conventions for the system prompt: `.quant/prompts/system.md` if
+    /// present, else the project type's built-in template
+    pub prompt_template: Option<String>,
 }
 
 /// Git repository information
@@ -214,44 +323,74 @@ pub struct GitInfo {
 impl ProjectContext {
     /// Discover project context from a directory
     pub fn discover(start_dir: &Path) -> Option<Self> {
+        Self::discover_with_global(start_dir, global_quant_file_path().as_deref())
+    }
+
+    /// Discover project context, reading global instructions from `global`
+    pub fn discover_with_global(start_dir: &Path, global: Option<&Path>) -> Option<Self> {
         let root = find_project_root(start_dir)?;
         info!(root = %root.display(), "Found project root");
 
         let project_type = detect_project_type(&root);
         debug!(project_type = %project_type, "Detected project type");
 
-        let quant_file = find_quant_file(&root);
-        if quant_file.is_some() {
-            info!("Found QUANT.md");
+        let quant_files = find_quant_files(&root, global);
+        if !quant_files.is_empty() {
+            info!(files = quant_files.len(), "Found QUANT.md");
         }
+        let quant_file = QuantFile::merge(quant_files.clone());
 
         let name = extract_project_name(&root, &project_type);
         let key_files = find_key_files(&root, &project_type);
         let structure = build_structure_summary(&root, &project_type);
         let git_info = get_git_info(&root);
+        let dependencies = deps::discover(&root);
+        let prompt_template = load_prompt_template(&root, &project_type);
 
         Some(Self {
             root,
             project_type,
             quant_file,
+            quant_files,
             name,
             key_files,
             structure,
             git_info,
+            dependencies,
+            prompt_template,
         })
     }
 
     /// Generate a context string for the LLM system prompt
     pub fn to_system_context(&self) -> String {
+        self.to_system_context_with(&ContextAssembly::new())
+    }
+
+    /// Generate the system prompt context, leaving out of the key files
+    /// list those whose content `assembly` already includes
+    pub fn to_system_context_with(&self, assembly: &ContextAssembly) -> String {
         let mut ctx = String::new();
 
         ctx.push_str(&format!("# Project: {}\n", self.name));
         ctx.push_str(&format!("Type: {}\n", self.project_type));
         ctx.push_str(&format!("Root: {}\n\n", self.root.display()));
 
+        // Add conventions for the project type; QUANT.md below can refine them
+        if let Some(ref template) = self.prompt_template {
+            ctx.push_str(template.trim_end());
+            ctx.push_str("\n\n");
+        }
+
         // Add QUANT.md content if present
         if let Some(ref quant) = self.quant_file {
-            ctx.push_str("## Project Instructions (from QUANT.md)\n\n");
+            ctx.push_str("## Project Instructions (from QUANT.md)\n");
+            if self.prompt_template.is_some() {
+                ctx.push_str("These take precedence over the conventions above.\n");
+            }
+            if self.quant_files.len() > 1 {
+                ctx.push_str("Later sections are more specific and take precedence over earlier ones.\n");
+            }
+            ctx.push('\n');
             ctx.push_str(&quant.content);
             ctx.push_str("\n\n");
         }
@@ -279,10 +418,19 @@ impl ProjectContext {
             ctx.push_str("```\n\n");
         }
 
-        // Add key files
-        if !self.key_files.is_empty() {
+        // Add dependency versions so the model doesn't guess them
+        let deps_summary = deps::to_system_context(&self.dependencies, &self.root, MAX_DEPS_IN_CONTEXT);
+        if !deps_summary.is_empty() {
+            ctx.push_str("## Dependencies\n");
+            ctx.push_str(&deps_summary);
+            ctx.push_str("Use the dependency_info tool for details.\n\n");
+        }
+
+        // Add key files not already included in full
+        let key_files: Vec<&PathBuf> = self.key_files.iter().filter(|f| !assembly.contains_path(f)).collect();
+        if !key_files.is_empty() {
             ctx.push_str("## Key Files\n");
-            for file in &self.key_files {
+            for file in key_files {
                 if let Ok(rel) = file.strip_prefix(&self.root) {
                     ctx.push_str(&format!("- {}\n", rel.display()));
                 }
@@ -292,10 +440,150 @@ impl ProjectContext {
 
         ctx
     }
+
+    /// Build, test and lint commands, as (label, command) pairs
+    ///
+    /// Node projects use their package.json scripts; the others get the
+    /// usual commands for their type.
+    pub fn commands(&self) -> Vec<(String, String)> {
+        let fixed: &[(&str, &str)] = match self.project_type {
+            ProjectType::Rust => &[("Build", "cargo build"), ("Test", "cargo test"), ("Lint", "cargo clippy --all-targets")],
+            ProjectType::Python => &[("Install", "pip install -e ."), ("Test", "pytest")],
+            ProjectType::Go => &[("Build", "go build ./..."), ("Test", "go test ./..."), ("Lint", "go vet ./...")],
+            ProjectType::Java if self.root.join("pom.xml").exists() => &[("Build", "mvn package"), ("Test", "mvn test")],
+            ProjectType::Java => &[("Build", "gradle build"), ("Test", "gradle test")],
+            ProjectType::Node => return node_script_commands(&self.root),
+            ProjectType::Unknown => &[],
+        };
+        fixed.iter().map(|(label, cmd)| (label.to_string(), cmd.to_string())).collect()
+    }
+
+    /// Starter QUANT.md describing this project
+    ///
+    /// The frontmatter has empty `hooks` and `mcp_servers` lists with
+    /// commented examples, and the body lists the commands, top-level
+    /// directories and key files found, plus placeholder instructions.
+    pub fn generate_quant_md(&self) -> String {
+        let mut md = String::from(QUANT_MD_FRONTMATTER);
+
+        md.push_str(&format!("\n# {}\n\n", self.name));
+        md.push_str(&format!("A {} project. Describe what it does here.\n\n", self.project_type));
+
+        let dirs: Vec<&String> = self.structure.iter().filter(|l| !l.starts_with(' ') && l.ends_with('/')).collect();
+        if !dirs.is_empty() {
+            md.push_str("## Project Layout\n\n");
+            for dir in dirs {
+                md.push_str(&format!("- `{}`\n", dir));
+            }
+            md.push('\n');
+        }
+
+        md.push_str("## Instructions\n\n");
+        md.push_str("- Follow the existing code style and module layout\n");
+        md.push_str("- Write tests for new functionality\n");
+        md.push_str("- Keep changes focused on the task\n\n");
+
+        let commands = self.commands();
+        if !commands.is_empty() {
+            md.push_str("## Commands\n\n");
+            for (label, cmd) in commands {
+                md.push_str(&format!("- {}: `{}`\n", label, cmd));
+            }
+            md.push('\n');
+        }
+
+        let key_files: Vec<_> = self
+            .key_files
+            .iter()
+            .filter_map(|f| f.strip_prefix(&self.root).ok())
+            .filter(|f| *f != Path::new("QUANT.md"))
+            .collect();
+        if !key_files.is_empty() {
+            md.push_str("## Key Files\n\n");
+            for file in key_files {
+                md.push_str(&format!("- `{}`\n", file.display()));
+            }
+        }
+
+        md
+    }
+
+    /// Files `quant init` creates under `.quant/`, relative to the root
+    pub fn scaffold_files(&self) -> Vec<(PathBuf, String)> {
+        let example = self.project_type.prompt_template().unwrap_or(GENERIC_TEMPLATE);
+        vec![
+            (PathBuf::from(crate::config::PROJECT_CONFIG), PROJECT_CONFIG_TEMPLATE.to_string()),
+            (PathBuf::from(format!("{}.example", SYSTEM_PROMPT_OVERRIDE)), example.to_string()),
+        ]
+    }
+}
+
+/// Frontmatter stub for a generated QUANT.md
+const QUANT_MD_FRONTMATTER: &str = r#"---
+# Shell commands run on agent events (agent_start, tool_before, tool_after, ...)
+hooks: []
+#  - name: format-on-write
+#    event: tool_after
+#    tool_filter: file_write
+#    command: echo "formatted"
+
+# MCP servers whose tools the agent can use
+mcp_servers: []
+#  - name: filesystem
+#    command: npx
+#    args: ["-y", "@modelcontextprotocol/server-filesystem", "."]
+---
+"#;
+
+/// Project config written by `quant init`
+const PROJECT_CONFIG_TEMPLATE: &str = r#"# quant project configuration
+# Overrides the user config (~/.config/quant/config.toml) for this project.
+# Set keys with `quant config set --project KEY VALUE`.
+
+[agent]
+# Tools the agent may use in this project (all if unset)
+# tools = ["file_read", "grep", "glob", "bash"]
+
+[ask]
+# default_model = "qwen2.5-coder:7b"
+
+[web_search]
+# provider = "searxng"
+# searxng_url = "http://localhost:8888"
+
+# Agent hooks, added to those in QUANT.md (see `quant hooks list`)
+# [[hooks]]
+# name = "check"
+# event = "agent_finish"
+# command = "cargo check"
+"#;
+
+/// Example conventions for projects without a built-in template
+const GENERIC_TEMPLATE: &str = "## Conventions
+- Build with `make`
+- Run tests with `make test`
+";
+
+/// `npm run` commands for the build, test and lint scripts in package.json
+fn node_script_commands(root: &Path) -> Vec<(String, String)> {
+    let scripts = std::fs::read_to_string(root.join("package.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|parsed| parsed.get("scripts").cloned());
+    let has_script = |name: &str| scripts.as_ref().and_then(|s| s.get(name)).is_some();
+
+    let mut commands = vec![("Install".to_string(), "npm install".to_string())];
+    for (label, script) in [("Build", "build"), ("Test", "test"), ("Lint", "lint")] {
+        if has_script(script) {
+            let cmd = if script == "test" { "npm test".to_string() } else { format!("npm run {}", script) };
+            commands.push((label.to_string(), cmd));
+        }
+    }
+    commands
 }
 
 /// Find project root by looking for marker files
-fn find_project_root(start: &Path) -> Option<PathBuf> {
+pub fn find_project_root(start: &Path) -> Option<PathBuf> {
     let markers = [
         // Version control
         ".git",
@@ -319,7 +607,7 @@ fn find_project_root(start: &Path) -> Option<PathBuf> {
     let mut current = start.to_path_buf();
 
     // Canonicalize to handle relative paths
-    if let Ok(canonical) = current.canonicalize() {
+    if let Ok(canonical) = crate::paths::canonicalize(&current) {
         current = canonical;
     }
 
@@ -356,7 +644,25 @@ fn detect_project_type(root: &Path) -> ProjectType {
     }
 }
 
-/// Find QUANT.md file in project root or parent directories
+/// QUANT.md files that apply to `root`, least specific first
+///
+/// Directories from the repository root (the nearest one with `.git`) down
+/// to `root` are searched; outside a repository only `root` is. The global
+/// file, if present, comes first.
+fn find_quant_files(root: &Path, global: Option<&Path>) -> Vec<QuantFile> {
+    let dirs: Vec<&Path> = match root.ancestors().position(|dir| dir.join(".git").exists()) {
+        Some(repo_depth) => root.ancestors().take(repo_depth + 1).collect(),
+        None => vec![root],
+    };
+
+    let global = global.and_then(|path| {
+        let content = std::fs::read_to_string(path).ok()?;
+        Some(QuantFile::parse(path.to_path_buf(), content))
+    });
+    global.into_iter().chain(dirs.into_iter().rev().filter_map(find_quant_file)).collect()
+}
+
+/// Find the QUANT.md file in a directory
 fn find_quant_file(root: &Path) -> Option<QuantFile> {
     let candidates = ["QUANT.md", "quant.md", ".quant/instructions.md"];
 
@@ -372,6 +678,18 @@ fn find_quant_file(root: &Path) -> Option<QuantFile> {
     None
 }
 
+/// The project's `.quant/prompts/system.md`, or the built-in template for its type
+fn load_prompt_template(root: &Path, project_type: &ProjectType) -> Option<String> {
+    let path = root.join(SYSTEM_PROMPT_OVERRIDE);
+    match std::fs::read_to_string(&path) {
+        Ok(content) if !content.trim().is_empty() => {
+            debug!(path = %path.display(), "Using project system prompt template");
+            Some(content)
+        }
+        _ => project_type.prompt_template().map(str::to_string),
+    }
+}
+
 /// Extract project name from config files or directory name
 fn extract_project_name(root: &Path, project_type: &ProjectType) -> String {
     match project_type {
@@ -638,4 +956,122 @@ name = "test-project"
         assert_eq!(ctx.project_type, ProjectType::Rust);
         assert!(ctx.quant_file.is_some());
     }
+
+    #[test]
+    fn test_key_files_already_in_context_are_not_listed() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"keys\"\n").unwrap();
+        fs::write(dir.path().join("QUANT.md"), "Be brief").unwrap();
+
+        let ctx = ProjectContext::discover_with_global(dir.path(), None).unwrap();
+        assert!(ctx.to_system_context().contains("- Cargo.toml\n"));
+
+        let mut assembly = ContextAssembly::new();
+        assembly.add_project(&ctx, "llama3.2");
+        assembly.add(&dir.path().join("Cargo.toml"), "[package]", crate::context::assembly::ContextSource::Smart, 5);
+        assert!(!ctx.to_system_context_with(&assembly).contains("- Cargo.toml\n"));
+
+        // QUANT.md is embedded by the project section, so smart context skips it
+        assert!(!assembly.is_new(&dir.path().join("QUANT.md"), "Be brief"));
+    }
+
+    #[test]
+    fn test_hierarchical_quant_md() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("repo");
+        let package = repo.join("packages/web");
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        fs::create_dir_all(&package).unwrap();
+        fs::write(package.join("package.json"), "{}").unwrap();
+
+        let global = dir.path().join("global.md");
+        fs::write(&global, "---\nmcp_servers:\n  - name: docs\n    command: global-docs\n---\n# Global\n\nUse British spelling").unwrap();
+        fs::write(repo.join("QUANT.md"), "# Repo\n\n## Instructions\n- Use pnpm").unwrap();
+        fs::write(
+            package.join("QUANT.md"),
+            "---\nmcp_servers:\n  - name: docs\n    command: web-docs\n---\n# Web\n\n## Instructions\n- Use React",
+        )
+        .unwrap();
+
+        let ctx = ProjectContext::discover_with_global(&package, Some(&global)).unwrap();
+        assert_eq!(ctx.quant_files.len(), 3);
+        let quant = ctx.quant_file.unwrap();
+        assert_eq!(quant.path, package.join("QUANT.md"));
+        assert_eq!(quant.description.as_deref(), Some("Web"));
+        assert_eq!(quant.instructions, vec!["Use pnpm", "Use React"]);
+        assert_eq!(quant.mcp_servers.len(), 1);
+        assert_eq!(quant.mcp_servers[0].command, "web-docs");
+
+        let british = quant.content.find("British").unwrap();
+        let pnpm = quant.content.find("pnpm").unwrap();
+        let react = quant.content.find("React").unwrap();
+        assert!(british < pnpm && pnpm < react);
+    }
+
+    #[test]
+    fn test_quant_md_outside_repo_is_not_inherited() {
+        let dir = TempDir::new().unwrap();
+        let project = dir.path().join("project");
+        fs::create_dir_all(&project).unwrap();
+        fs::write(project.join("Cargo.toml"), "").unwrap();
+        fs::write(dir.path().join("QUANT.md"), "# Somewhere else").unwrap();
+
+        let ctx = ProjectContext::discover_with_global(&project, None).unwrap();
+        assert!(ctx.quant_files.is_empty());
+        assert!(ctx.quant_file.is_none());
+    }
+
+    #[test]
+    fn test_prompt_template_by_project_type() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("go.mod"), "module example.com/app\n").unwrap();
+        fs::write(dir.path().join("QUANT.md"), "# App\n\n## Instructions\n- Use zerolog").unwrap();
+
+        let system = ProjectContext::discover(dir.path()).unwrap().to_system_context();
+        let conventions = system.find("## Go Conventions").unwrap();
+        let instructions = system.find("## Project Instructions").unwrap();
+        assert!(conventions < instructions);
+        assert!(system.contains("go test ./..."));
+        assert!(system.contains("take precedence"));
+    }
+
+    #[test]
+    fn test_prompt_template_override() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"x\"").unwrap();
+        fs::create_dir_all(dir.path().join(".quant/prompts")).unwrap();
+        fs::write(dir.path().join(SYSTEM_PROMPT_OVERRIDE), "## House Rules\n- Use nextest\n").unwrap();
+
+        let system = ProjectContext::discover(dir.path()).unwrap().to_system_context();
+        assert!(system.contains("## House Rules"));
+        assert!(!system.contains("## Rust Conventions"));
+
+        assert!(ProjectType::Java.prompt_template().is_none());
+    }
+
+    #[test]
+    fn test_generate_quant_md() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("package.json"),
+            r#"{"name": "web-app", "scripts": {"build": "tsc", "test": "vitest"}}"#,
+        )
+        .unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+
+        let ctx = ProjectContext::discover(dir.path()).unwrap();
+        let md = ctx.generate_quant_md();
+        assert!(md.contains("# web-app"));
+        assert!(md.contains("- `src/`"));
+        assert!(md.contains("- Build: `npm run build`"));
+        assert!(md.contains("- Test: `npm test`"));
+        assert!(!md.contains("Lint"));
+
+        // The generated file parses back with empty hook and server lists
+        let quant = QuantFile::parse(dir.path().join("QUANT.md"), md.clone());
+        assert!(quant.mcp_servers.is_empty());
+        assert_eq!(quant.description.as_deref(), Some("web-app"));
+        let mut hooks = crate::hooks::HookManager::new();
+        assert_eq!(hooks.load_from_quant_md(&md).unwrap(), 0);
+    }
 }
@@ -0,0 +1,600 @@
+//! Retrieval-augmented Q&A over local documents
+//!
+//! `quant rag index` splits files (text, code, and PDF/docx/HTML through
+//! [`crate::context::documents`]) into overlapping chunks, embeds each chunk
+//! with an Ollama embedding model and keeps the vectors in a store in the
+//! data directory. `quant rag query` embeds the question, retrieves the
+//! closest chunks and asks the model to answer from them, citing each source
+//! by number. Files are re-embedded only when their content changes.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use llm_core::OllamaClient;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+use crate::context::documents::{self, DocumentKind};
+
+/// Store format version; bump when the on-disk layout changes
+const STORE_VERSION: u32 = 1;
+
+/// Maximum characters per chunk
+const CHUNK_CHARS: usize = 1500;
+
+/// Characters repeated from the end of one chunk at the start of the next
+const OVERLAP_CHARS: usize = 200;
+
+/// Chunks sent to the embedding model per request
+const EMBED_BATCH: usize = 16;
+
+/// Extensions of text files picked up when indexing a directory
+const TEXT_EXTENSIONS: &[&str] = &[
+    "md", "markdown", "txt", "rst", "adoc", "org", "csv", "rs", "py", "ts", "tsx", "js", "jsx", "go", "java", "c",
+    "cpp", "h", "hpp", "rb", "swift", "kt", "sh", "toml", "yaml", "yml", "json", "sql",
+];
+
+/// Directories skipped when indexing a directory
+const SKIP_DIRS: &[&str] = &["target", "node_modules", "vendor", "dist", "build", "__pycache__", "venv"];
+
+/// An indexed file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RagDocument {
+    pub path: PathBuf,
+    /// SHA-256 of the file content when indexed
+    hash: String,
+    pub chunks: usize,
+    pub indexed_at: DateTime<Utc>,
+}
+
+/// A piece of a file, with where it came from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Passage {
+    /// PDF page the text is on
+    pub page: Option<u32>,
+    /// First and last line of the text (in the extracted text for documents)
+    pub lines: (usize, usize),
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RagChunk {
+    path: PathBuf,
+    passage: Passage,
+    embedding: Vec<f32>,
+}
+
+/// A chunk retrieved for a question
+#[derive(Debug, Clone)]
+pub struct RagHit<'a> {
+    pub path: &'a Path,
+    pub passage: &'a Passage,
+    pub score: f32,
+}
+
+impl RagHit<'_> {
+    /// Where the chunk came from, e.g. `report.pdf, page 3` or `src/main.rs:10-42`
+    pub fn source(&self) -> String {
+        let path = display_path(self.path);
+        match self.passage.page {
+            Some(page) => format!("{}, page {}", path, page),
+            // Lines of extracted text don't match anything the user can open
+            None if DocumentKind::from_path(self.path).is_some() => path,
+            None => format!("{}:{}-{}", path, self.passage.lines.0, self.passage.lines.1),
+        }
+    }
+}
+
+/// A retrieved chunk with its own copy of the passage, as the daemon sends it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RagMatch {
+    pub path: PathBuf,
+    pub passage: Passage,
+    pub score: f32,
+}
+
+impl RagMatch {
+    pub fn hit(&self) -> RagHit<'_> {
+        RagHit {
+            path: &self.path,
+            passage: &self.passage,
+            score: self.score,
+        }
+    }
+}
+
+/// Result of indexing
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RagIndexStats {
+    /// Files embedded in this run
+    pub indexed: usize,
+    /// Files skipped because they haven't changed
+    pub unchanged: usize,
+    /// Files removed from the store because they no longer exist
+    pub removed: usize,
+    /// Files that could not be read, with the reason
+    pub failed: Vec<(PathBuf, String)>,
+    /// Chunks in the store after indexing
+    pub chunks: usize,
+}
+
+/// Embedded chunks of indexed files
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RagStore {
+    version: u32,
+    /// Model the chunks were embedded with
+    embed_model: String,
+    documents: HashMap<PathBuf, RagDocument>,
+    chunks: Vec<RagChunk>,
+}
+
+impl RagStore {
+    /// Default store location in the data directory
+    pub fn default_path() -> Result<PathBuf> {
+        let data_dir = dirs::data_local_dir()
+            .or_else(dirs::data_dir)
+            .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+        Ok(data_dir.join("quant").join("rag").join("store.bin"))
+    }
+
+    /// Load a store from disk (empty if it doesn't exist)
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::new());
+        }
+        let data = fs::read(path).context("Failed to read RAG store")?;
+        let store: Self = bincode::deserialize(&data).context("RAG store is corrupted; rebuild it with `quant rag index --rebuild`")?;
+        if store.version != STORE_VERSION {
+            anyhow::bail!("RAG store version {} is outdated; rebuild it with `quant rag index --rebuild`", store.version);
+        }
+        Ok(store)
+    }
+
+    /// Save the store to disk
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Write to a temp file first so an interrupted save can't corrupt the store
+        let tmp = path.with_extension("bin.tmp");
+        fs::write(&tmp, bincode::serialize(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn new() -> Self {
+        Self {
+            version: STORE_VERSION,
+            ..Default::default()
+        }
+    }
+
+    /// Indexed files, sorted by path
+    pub fn documents(&self) -> Vec<&RagDocument> {
+        let mut docs: Vec<_> = self.documents.values().collect();
+        docs.sort_by(|a, b| a.path.cmp(&b.path));
+        docs
+    }
+
+    pub fn embed_model(&self) -> &str {
+        &self.embed_model
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Use `model` for embeddings, dropping chunks embedded with another model
+    fn set_embed_model(&mut self, model: &str) {
+        if self.embed_model != model {
+            if !self.chunks.is_empty() {
+                debug!(old = %self.embed_model, new = model, "Embedding model changed, re-indexing");
+            }
+            self.documents.clear();
+            self.chunks.clear();
+            self.embed_model = model.to_string();
+        }
+    }
+
+    fn is_current(&self, path: &Path, hash: &str) -> bool {
+        self.documents.get(path).is_some_and(|doc| doc.hash == hash)
+    }
+
+    /// Replace the chunks of `path`
+    fn insert(&mut self, path: &Path, hash: String, passages: Vec<Passage>, embeddings: Vec<Vec<f32>>) {
+        self.remove(path);
+        self.documents.insert(
+            path.to_path_buf(),
+            RagDocument {
+                path: path.to_path_buf(),
+                hash,
+                chunks: passages.len(),
+                indexed_at: Utc::now(),
+            },
+        );
+        self.chunks.extend(passages.into_iter().zip(embeddings).map(|(passage, embedding)| RagChunk {
+            path: path.to_path_buf(),
+            passage,
+            embedding,
+        }));
+    }
+
+    fn remove(&mut self, path: &Path) {
+        self.chunks.retain(|c| c.path != path);
+        self.documents.remove(path);
+    }
+
+    /// Remove files that no longer exist, returning how many
+    fn prune(&mut self) -> usize {
+        let missing: Vec<PathBuf> = self.documents.keys().filter(|p| !p.exists()).cloned().collect();
+        for path in &missing {
+            self.remove(path);
+        }
+        missing.len()
+    }
+
+    /// The `top_k` chunks most similar to `query`, best first
+    pub fn search(&self, query: &[f32], top_k: usize) -> Vec<RagHit<'_>> {
+        let mut hits: Vec<RagHit> = self
+            .chunks
+            .iter()
+            .map(|chunk| RagHit {
+                path: &chunk.path,
+                passage: &chunk.passage,
+                score: cosine_similarity(query, &chunk.embedding),
+            })
+            .collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(top_k);
+        hits
+    }
+}
+
+/// Embed `question` and retrieve the `top_k` closest chunks of `store`
+pub async fn search_question(store: &RagStore, client: &OllamaClient, question: &str, top_k: usize) -> Result<Vec<RagMatch>> {
+    if store.chunk_count() == 0 {
+        bail!("Nothing indexed yet. Add documents with: quant rag index <paths>");
+    }
+    let query = client
+        .embed(store.embed_model(), &[question.to_string()])
+        .await
+        .with_context(|| format!("Failed to embed the question with {}", store.embed_model()))?;
+    let matches = store
+        .search(&query[0], top_k)
+        .into_iter()
+        .map(|hit| RagMatch {
+            path: hit.path.to_path_buf(),
+            passage: hit.passage.clone(),
+            score: hit.score,
+        })
+        .collect();
+    Ok(matches)
+}
+
+/// Chunk and embed the files under `paths`, skipping unchanged ones
+///
+/// `on_file` is called before each file that is (re-)embedded.
+pub async fn index(
+    store: &mut RagStore,
+    client: &OllamaClient,
+    embed_model: &str,
+    paths: &[PathBuf],
+    mut on_file: impl FnMut(&Path),
+) -> Result<RagIndexStats> {
+    store.set_embed_model(embed_model);
+    let mut stats = RagIndexStats {
+        removed: store.prune(),
+        ..Default::default()
+    };
+
+    for path in collect_files(paths)? {
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                stats.failed.push((path, e.to_string()));
+                continue;
+            }
+        };
+        let hash = format!("{:x}", Sha256::digest(&bytes));
+        if store.is_current(&path, &hash) {
+            stats.unchanged += 1;
+            continue;
+        }
+
+        let text = match documents::read_text(&path) {
+            Ok(text) => text,
+            Err(e) => {
+                stats.failed.push((path, format!("{:#}", e)));
+                continue;
+            }
+        };
+        on_file(&path);
+
+        let passages = split_passages(&text);
+        let mut embeddings = Vec::with_capacity(passages.len());
+        for batch in passages.chunks(EMBED_BATCH) {
+            let inputs: Vec<String> = batch.iter().map(|p| p.text.clone()).collect();
+            embeddings.extend(client.embed(embed_model, &inputs).await?);
+        }
+        store.insert(&path, hash, passages, embeddings);
+        stats.indexed += 1;
+    }
+
+    stats.chunks = store.chunk_count();
+    Ok(stats)
+}
+
+/// The prompt asking the model to answer `question` from `hits`
+pub fn build_prompt(question: &str, hits: &[RagHit]) -> String {
+    let mut prompt = String::from(
+        "Answer the question using only the numbered sources below. Cite the sources you use \
+         as [1], [2], etc. after the sentences that rely on them. If the sources don't contain \
+         the answer, say so.\n\n",
+    );
+    for (i, hit) in hits.iter().enumerate() {
+        prompt.push_str(&format!("[{}] {}\n{}\n\n", i + 1, hit.source(), hit.passage.text.trim()));
+    }
+    prompt.push_str(&format!("Question: {}", question));
+    prompt
+}
+
+/// Files to index under `paths`: given files as they are, directories walked for documents and text files
+fn collect_files(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        let path = crate::paths::canonicalize(path).with_context(|| format!("Cannot index {}", path.display()))?;
+        if path.is_file() {
+            files.push(path);
+            continue;
+        }
+        let entries = walkdir::WalkDir::new(&path).into_iter().filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            entry.depth() == 0 || !(entry.file_type().is_dir() && (name.starts_with('.') || SKIP_DIRS.contains(&name.as_ref())))
+        });
+        for entry in entries.filter_map(|e| e.ok()) {
+            if entry.file_type().is_file() && is_indexable(entry.path()) {
+                files.push(entry.into_path());
+            }
+        }
+    }
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+fn is_indexable(path: &Path) -> bool {
+    DocumentKind::from_path(path).is_some()
+        || path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|ext| TEXT_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Split text into chunks of at most `CHUNK_CHARS` on line breaks, overlapping by
+/// about `OVERLAP_CHARS`. Chunks don't cross the page markers of extracted PDFs.
+pub fn split_passages(text: &str) -> Vec<Passage> {
+    let mut passages = Vec::new();
+    let mut current: Vec<(usize, &str)> = Vec::new();
+    let mut page = None;
+
+    fn flush(passages: &mut Vec<Passage>, lines: &[(usize, &str)], page: Option<u32>) {
+        let text = lines.iter().map(|(_, l)| *l).collect::<Vec<_>>().join("\n");
+        if let (Some(first), Some(last)) = (lines.first(), lines.last()) {
+            if !text.trim().is_empty() {
+                passages.push(Passage {
+                    page,
+                    lines: (first.0, last.0),
+                    text,
+                });
+            }
+        }
+    }
+    let len = |lines: &[(usize, &str)]| lines.iter().map(|(_, l)| l.len() + 1).sum::<usize>();
+
+    for (i, line) in text.lines().enumerate() {
+        let number = i + 1;
+        if let Some(n) = page_marker(line) {
+            flush(&mut passages, &current, page);
+            current.clear();
+            page = Some(n);
+            continue;
+        }
+
+        // Hard-split lines that are longer than a chunk on their own
+        let mut pieces = Vec::new();
+        let mut rest = line;
+        while rest.len() > CHUNK_CHARS {
+            let cut = (0..=CHUNK_CHARS).rev().find(|&i| rest.is_char_boundary(i)).unwrap_or(0);
+            pieces.push(&rest[..cut]);
+            rest = &rest[cut..];
+        }
+        pieces.push(rest);
+
+        for piece in pieces {
+            if !current.is_empty() && len(&current) + piece.len() + 1 > CHUNK_CHARS {
+                flush(&mut passages, &current, page);
+                // Carry the last lines over so a passage split here keeps its context
+                let mut keep = current.len();
+                while keep > 0 && len(&current[keep - 1..]) <= OVERLAP_CHARS {
+                    keep -= 1;
+                }
+                current.drain(..keep);
+                while !current.is_empty() && len(&current) + piece.len() + 1 > CHUNK_CHARS {
+                    current.remove(0);
+                }
+            }
+            current.push((number, piece));
+        }
+    }
+    flush(&mut passages, &current, page);
+    passages
+}
+
+/// The page number of a `--- Page N ---` marker line
+fn page_marker(line: &str) -> Option<u32> {
+    line.strip_prefix("--- Page ")?.strip_suffix(" ---")?.parse().ok()
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.is_empty() || a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let (norm_a, norm_b) = (norm(a), norm(b));
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+/// `path` relative to the current directory when it is under it
+fn display_path(path: &Path) -> String {
+    std::env::current_dir()
+        .ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok().map(Path::to_path_buf))
+        .unwrap_or_else(|| path.to_path_buf())
+        .display()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn passage(text: &str) -> Passage {
+        Passage {
+            page: None,
+            lines: (1, 1),
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_split_passages_overlap() {
+        let text = (1..=100).map(|i| format!("line {:03} {}", i, "x".repeat(40))).collect::<Vec<_>>().join("\n");
+        let passages = split_passages(&text);
+        assert!(passages.len() > 1);
+        assert!(passages.iter().all(|p| p.text.len() <= CHUNK_CHARS));
+        assert_eq!(passages[0].lines.0, 1);
+        assert_eq!(passages.last().unwrap().lines.1, 100);
+        // Each passage starts before the previous one ended
+        for pair in passages.windows(2) {
+            assert!(pair[1].lines.0 <= pair[0].lines.1);
+            assert!(pair[1].lines.0 > pair[0].lines.0);
+        }
+    }
+
+    #[test]
+    fn test_split_passages_pages() {
+        let text = "--- Page 1 ---\nIntro\n\n--- Page 2 ---\nResults\nMore results\n\n--- Page 3 ---\n";
+        let passages = split_passages(text);
+        assert_eq!(passages.len(), 2);
+        assert_eq!(passages[0].page, Some(1));
+        assert_eq!(passages[0].text.trim(), "Intro");
+        assert_eq!(passages[1].page, Some(2));
+        assert_eq!(passages[1].text.trim(), "Results\nMore results");
+        assert_eq!(passages[1].lines, (5, 7));
+    }
+
+    #[test]
+    fn test_split_long_line() {
+        let text = "é".repeat(CHUNK_CHARS);
+        let passages = split_passages(&text);
+        assert!(passages.len() >= 2);
+        assert!(passages.iter().all(|p| p.lines == (1, 1)));
+    }
+
+    #[test]
+    fn test_store_search_and_updates() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.md");
+        let b = dir.path().join("b.md");
+        fs::write(&a, "a").unwrap();
+        fs::write(&b, "b").unwrap();
+
+        let mut store = RagStore::new();
+        store.set_embed_model("embed");
+        store.insert(&a, "h1".into(), vec![passage("apples"), passage("pears")], vec![vec![1.0, 0.0], vec![0.7, 0.7]]);
+        store.insert(&b, "h2".into(), vec![passage("boats")], vec![vec![0.0, 1.0]]);
+
+        let hits = store.search(&[0.0, 1.0], 2);
+        assert_eq!(hits[0].passage.text, "boats");
+        assert_eq!(hits[1].passage.text, "pears");
+        assert!(store.is_current(&a, "h1"));
+        assert!(!store.is_current(&a, "h3"));
+
+        // Re-inserting replaces a file's chunks
+        store.insert(&a, "h3".into(), vec![passage("apricots")], vec![vec![1.0, 0.0]]);
+        assert_eq!(store.chunk_count(), 2);
+
+        fs::remove_file(&b).unwrap();
+        assert_eq!(store.prune(), 1);
+        assert_eq!(store.documents().len(), 1);
+
+        // Switching embedding models starts over
+        store.set_embed_model("other");
+        assert_eq!(store.chunk_count(), 0);
+    }
+
+    #[test]
+    fn test_save_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rag").join("store.bin");
+        let mut store = RagStore::new();
+        store.set_embed_model("embed");
+        store.insert(Path::new("/doc.md"), "h".into(), vec![passage("text")], vec![vec![0.5, 0.5]]);
+        store.save(&path).unwrap();
+
+        let loaded = RagStore::load(&path).unwrap();
+        assert_eq!(loaded.embed_model(), "embed");
+        assert_eq!(loaded.search(&[0.5, 0.5], 1)[0].passage.text, "text");
+
+        fs::write(&path, b"garbage").unwrap();
+        assert!(RagStore::load(&path).is_err());
+    }
+
+    #[test]
+    fn test_collect_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("docs")).unwrap();
+        fs::create_dir_all(dir.path().join("node_modules/pkg")).unwrap();
+        fs::create_dir_all(dir.path().join(".git")).unwrap();
+        fs::write(dir.path().join("docs/guide.md"), "guide").unwrap();
+        fs::write(dir.path().join("docs/report.pdf"), "pdf").unwrap();
+        fs::write(dir.path().join("docs/image.png"), "png").unwrap();
+        fs::write(dir.path().join("node_modules/pkg/index.js"), "js").unwrap();
+        fs::write(dir.path().join(".git/config"), "git").unwrap();
+
+        let files = collect_files(&[dir.path().to_path_buf()]).unwrap();
+        let names: Vec<_> = files.iter().map(|f| f.file_name().unwrap().to_string_lossy().to_string()).collect();
+        assert_eq!(names, vec!["guide.md", "report.pdf"]);
+
+        // Files named directly are indexed whatever their extension
+        let image = dir.path().join("docs/image.png");
+        assert_eq!(collect_files(std::slice::from_ref(&image)).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_build_prompt_cites_sources() {
+        let passage = Passage {
+            page: Some(3),
+            lines: (10, 20),
+            text: "Revenue grew 12%.".to_string(),
+        };
+        let hit = RagHit {
+            path: Path::new("/nonexistent/report.pdf"),
+            passage: &passage,
+            score: 0.9,
+        };
+        assert_eq!(hit.source(), "/nonexistent/report.pdf, page 3");
+        let html = RagHit { path: Path::new("/nonexistent/page.html"), passage: &Passage { page: None, ..passage.clone() }, score: 0.5 };
+        assert_eq!(html.source(), "/nonexistent/page.html");
+        let prompt = build_prompt("How much did revenue grow?", &[hit]);
+        assert!(prompt.contains("[1] /nonexistent/report.pdf, page 3\nRevenue grew 12%."));
+        assert!(prompt.ends_with("Question: How much did revenue grow?"));
+    }
+}
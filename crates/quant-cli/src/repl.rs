@@ -11,30 +11,73 @@ use anyhow::Result;
 // crossterm is available for future terminal features
 use futures::StreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
-use llm_core::{ChatMessage, Config, OllamaClient};
+use llm_core::ipc::{IpcClient, ServerMessage, SessionInfo, SessionKind};
+use llm_core::{ChatMessage, ChatOptions, Config, EffectiveConfig, LlmError, OllamaClient};
 use rustyline::error::ReadlineError;
 use rustyline::history::DefaultHistory;
-use rustyline::{DefaultEditor, Editor};
+use rustyline::Editor;
+use std::collections::HashMap;
 use std::io::{stdout, Write};
 use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
 
 use crate::agent::{AgentConfig, AgentLoop};
-use crate::config::UserConfig;
-use crate::context::ContextManager;
+use crate::code_block::last_code_block;
+use crate::config::{ConfigResolver, EditMode, ModelOptions, Settings, UserConfig};
+use crate::context::{model_info, ContextAssembly, ContextManager, ModelLimits};
 use crate::conversation::{Conversation, ConversationStore, InputHistory};
-use crate::tools::builtin::create_default_registry;
+use crate::repl_helper::ReplHelper;
+use crate::mcp::resources::{format_resource_list, parse_resource_refs};
+use crate::mcp::{
+    expand_resource_refs, ConfigChangeEvent, ConfigWatcher, McpManager, McpServerConfig, ReadResourceTool,
+    HEALTH_CHECK_INTERVAL,
+};
+use crate::model_alias::resolve_model;
+use crate::project::ProjectContext;
+use crate::resource_sampler::ResourceSampler;
+use crate::response_limit::ResponseBuffer;
+use crate::thinking::ThinkingDisplay;
+use crate::tools::builtin::{create_default_registry, SandboxBackend};
 use crate::tools::router::ToolRouter;
 use crate::tools::security::TerminalConfirmation;
+use crate::tools::staging::StagingArea;
+use crate::tools::ToolContext;
 
 // ANSI colors
 const GREEN: &str = "\x1b[92m";
 const BLUE: &str = "\x1b[94m";
 const YELLOW: &str = "\x1b[93m";
+const RED: &str = "\x1b[91m";
 const CYAN: &str = "\x1b[96m";
 const DIM: &str = "\x1b[2m";
 const BOLD: &str = "\x1b[1m";
 const RESET: &str = "\x1b[0m";
 
+/// Messages of earlier conversation included with `/task` by default
+const TASK_DEFAULT_HISTORY: usize = 6;
+
+/// Token limit for a generated conversation title
+const TITLE_MAX_TOKENS: i32 = 24;
+
+/// How long saving waits for a title that is still being generated
+const TITLE_WAIT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// How long saving waits for a conversation summary before saving without one
+const SUMMARY_WAIT: std::time::Duration = std::time::Duration::from_secs(20);
+
+/// Share of the context window (percent) at which the meter turns yellow
+/// and `/compact` is suggested
+const CONTEXT_WARN_PERCENT: usize = 80;
+
+/// Share of the context window (percent) at which the meter turns red
+const CONTEXT_FULL_PERCENT: usize = 95;
+
+/// Recent messages `/compact` keeps as they are by default
+const COMPACT_DEFAULT_KEEP: usize = 2;
+
 /// REPL state
 #[allow(dead_code)]
 struct ReplState {
@@ -54,24 +97,65 @@ struct ReplState {
     auto_save: bool,
     /// Whether agent mode is enabled
     agent_mode: bool,
+    /// Stop streaming a response beyond this many bytes (0 = unlimited)
+    max_response_bytes: usize,
+    /// Sampling temperature (model default if unset)
+    temperature: Option<f32>,
+    /// Default options of the alias the model was chosen by
+    model_options: ModelOptions,
+    /// Reports QUANT.md and config file edits between prompts
+    watcher: Option<ConfigWatcher>,
+    /// MCP servers from QUANT.md, started on first use
+    mcp: Option<Arc<Mutex<McpManager>>>,
+    /// Whether to title conversations with a model after the first exchange
+    auto_title: bool,
+    /// Model for titles and summaries (chat model if unset)
+    title_model: Option<String>,
+    /// Whether to summarize conversations with a model when they are saved
+    auto_summary: bool,
+    /// Title being generated, with the id of the conversation it is for
+    pending_title: Option<(String, JoinHandle<Option<String>>)>,
+    /// Conversation a title was last requested for; failures aren't retried
+    title_requested: Option<String>,
+    /// Whether to show context usage in the prompt
+    context_meter: bool,
+    /// Whether the nearly-full context warning was shown since usage last
+    /// dropped below it
+    context_warned: bool,
+    /// Agent file writes waiting for `/review` (`[agent] review_writes`)
+    staging: Option<Arc<StagingArea>>,
+    /// Connection to OllamaBar, when it is running
+    bar: Option<IpcClient>,
+    /// Session as last reported to OllamaBar
+    bar_session: Option<SessionInfo>,
+    /// Messages in the conversation when it was last saved for OllamaBar
+    bar_saved_len: usize,
+    /// Thinking behind the last response, for `/thinking`
+    last_thinking: String,
+    /// Kept open after `/copy`, as on Linux the copied text is served from here
+    clipboard: Option<crate::clipboard::Clipboard>,
 }
 
 impl ReplState {
     async fn new(model: Option<String>, system: Option<String>) -> Result<Self> {
         // Try to load config, fall back to defaults if missing
-        let (config, config_warning) = match Config::try_load() {
+        let (config, config_warning) = match crate::config::try_load_llm_config() {
             Some(cfg) => (cfg, None),
             None => {
+                let config = crate::config::load_llm_config_or_default();
                 let warning = format!(
-                    "{}Warning:{} llm.toml not found, using defaults (localhost:11434)",
-                    YELLOW, RESET
+                    "{}Warning:{} llm.toml not found, using defaults ({})",
+                    YELLOW,
+                    RESET,
+                    config.ollama_url()
                 );
-                (Config::default_minimal(), Some(warning))
+                (config, Some(warning))
             }
         };
 
-        let user_config = UserConfig::load().unwrap_or_default();
-        let client = OllamaClient::new(config.ollama_url());
+        let settings = crate::config::load_settings().map(EffectiveConfig::into_inner).ok();
+        let user_config = settings.as_ref().map(|s| s.user.clone()).unwrap_or_default();
+        let client = config.ollama_client();
 
         // Check Ollama is running
         if !client.health_check().await.unwrap_or(false) {
@@ -88,12 +172,12 @@ impl ReplState {
         }
 
         // Determine model: CLI arg > user config > llm.toml > first available
-        let model = if let Some(m) = model {
-            m
-        } else if let Some(m) = user_config.repl.default_model.clone() {
-            m
-        } else if !config.models.chat.is_empty() {
-            config.models.chat.clone()
+        let requested = model.or_else(|| settings.as_ref().and_then(Settings::chat_model).map(str::to_string));
+        let mut model_options = ModelOptions::default();
+        let model = if let Some(name) = requested {
+            let resolved = resolve_model(&client, &user_config.aliases.models, &name).await?;
+            model_options = resolved.options;
+            resolved.model
         } else {
             // No config available, try to get first available model from Ollama
             match client.list_models().await {
@@ -114,12 +198,18 @@ impl ReplState {
             }
         };
 
-        // Use system prompt from: CLI arg > user config
-        let system = system.or_else(|| user_config.repl.system_prompt.clone());
+        crate::commands::ensure_model(&client, &model, crate::tools::security::is_interactive()).await?;
+        model_info::detect(&client, &model).await;
+
+        // Use system prompt from: CLI arg > model alias > user config
+        let system = system
+            .or_else(|| model_options.system_prompt.clone())
+            .or_else(|| user_config.repl.system_prompt.clone());
 
         let conversation = Conversation::new(model.clone(), system);
         let context = ContextManager::new()?;
         let store = ConversationStore::new()?;
+        let watcher = start_config_watcher();
 
         Ok(Self {
             client,
@@ -130,9 +220,370 @@ impl ReplState {
             store,
             auto_save: user_config.repl.auto_save,
             agent_mode: false,
+            max_response_bytes: user_config.limits.max_response_bytes(),
+            temperature: user_config.repl.temperature,
+            model_options,
+            watcher,
+            mcp: None,
+            auto_title: user_config.repl.auto_title,
+            title_model: user_config.repl.title_model.clone(),
+            auto_summary: user_config.repl.auto_summary,
+            pending_title: None,
+            title_requested: None,
+            context_meter: user_config.repl.context_meter,
+            context_warned: false,
+            staging: user_config
+                .agent
+                .review_writes
+                .then(|| Arc::new(StagingArea::new())),
+            bar: IpcClient::connect_default().await,
+            bar_session: None,
+            bar_saved_len: 0,
+            last_thinking: String::new(),
+            clipboard: None,
         })
     }
 
+    /// Keep OllamaBar's view of this session current
+    ///
+    /// Applies models picked from the tray menu and reports changes. With
+    /// auto-save on, the conversation is also saved after each exchange so
+    /// the tray can open it in another terminal.
+    async fn sync_bar(&mut self) {
+        let Some(bar) = self.bar.as_mut() else {
+            return;
+        };
+
+        let mut switched = None;
+        for event in bar.take_events() {
+            match event {
+                ServerMessage::SwitchModel { model } if model != self.model => switched = Some(model),
+                ServerMessage::SwitchModel { .. } => {}
+            }
+        }
+        if let Some(model) = switched {
+            self.model = model.clone();
+            self.conversation.model = model.clone();
+            self.model_options = ModelOptions::default();
+            model_info::detect(&self.client, &model).await;
+            println!("{}[OllamaBar]{} Switched to model: {}{}{}", CYAN, RESET, BLUE, model, RESET);
+        }
+
+        if self.auto_save && self.conversation.len() != self.bar_saved_len {
+            if let Err(e) = self.store.save(&self.conversation) {
+                tracing::debug!(error = %e, "Failed to save conversation for OllamaBar");
+            }
+            self.bar_saved_len = self.conversation.len();
+        }
+
+        let session = SessionInfo {
+            pid: std::process::id(),
+            kind: SessionKind::Chat,
+            id: self.conversation.id.clone(),
+            model: self.model.clone(),
+            working_dir: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+            title: (!self.conversation.is_empty()).then(|| self.conversation.title.clone()),
+            resumable: self.auto_save && !self.conversation.is_empty(),
+        };
+        if self.bar_session.as_ref() == Some(&session) {
+            return;
+        }
+        match bar.report(&session).await {
+            Ok(()) => self.bar_session = Some(session),
+            Err(e) => {
+                // OllamaBar quit; carry on without it
+                tracing::debug!(error = %e, "Lost connection to OllamaBar");
+                self.bar = None;
+            }
+        }
+    }
+
+    /// Point out agent writes waiting for `/review`
+    fn print_staged(&self) {
+        if let Some(staging) = self.staging.as_ref().filter(|s| !s.is_empty()) {
+            println!("{}{} file(s) staged; /review to write them{}", YELLOW, staging.len(), RESET);
+        }
+    }
+
+    /// The session's MCP manager, starting the QUANT.md servers on first use
+    async fn mcp_manager(&mut self) -> Arc<Mutex<McpManager>> {
+        if let Some(ref manager) = self.mcp {
+            return Arc::clone(manager);
+        }
+
+        let mut manager = McpManager::new();
+        let servers = configured_mcp_servers();
+        if !servers.is_empty() {
+            println!("{}Starting {} MCP server(s)...{}", DIM, servers.len(), RESET);
+            for name in manager.start_all(servers).await {
+                eprintln!("{}Warning:{} MCP server {} failed to start", YELLOW, RESET, name);
+            }
+        }
+
+        let manager = Arc::new(Mutex::new(manager));
+        // Health checks and restarts run until the REPL exits
+        drop(McpManager::spawn_supervisor(Arc::clone(&manager), HEALTH_CHECK_INTERVAL));
+        self.mcp = Some(Arc::clone(&manager));
+        manager
+    }
+
+    /// Report MCP servers that went down or came back since the last prompt
+    async fn report_mcp_events(&self) {
+        let Some(ref manager) = self.mcp else {
+            return;
+        };
+        for event in manager.lock().await.take_events() {
+            println!("{}[MCP]{} {}", YELLOW, RESET, event);
+        }
+    }
+
+    /// Read the MCP resources referenced with `@server:resource` in `input`
+    async fn resource_context(&mut self, input: &str) -> Result<String> {
+        let servers = configured_mcp_servers();
+        let names: Vec<&str> = servers.iter().map(|s| s.name.as_str()).collect();
+        if parse_resource_refs(input, &names).is_empty() {
+            return Ok(String::new());
+        }
+        let manager = self.mcp_manager().await;
+        let manager = manager.lock().await;
+        expand_resource_refs(&manager, input).await
+    }
+
+    /// Apply settings after the active profile changed
+    fn apply_user_config(&mut self, user_config: &UserConfig) {
+        if let Some(ref name) = user_config.repl.default_model {
+            let (model, options) = match user_config.aliases.models.get(name) {
+                Some(alias) => (alias.model().to_string(), alias.options()),
+                None => (name.clone(), ModelOptions::default()),
+            };
+            self.model = model.clone();
+            self.conversation.model = model;
+            self.model_options = options;
+        }
+        self.conversation.system_prompt = self
+            .model_options
+            .system_prompt
+            .clone()
+            .or_else(|| user_config.repl.system_prompt.clone());
+        self.temperature = user_config.repl.temperature;
+        self.max_response_bytes = user_config.limits.max_response_bytes();
+    }
+
+    /// Report QUANT.md and config edits made since the last prompt
+    ///
+    /// Agent tasks re-read hooks, MCP servers, and tool permissions when they
+    /// start (and between iterations), so here it is enough to say so and to
+    /// refresh the chat settings.
+    fn check_config_changes(&mut self) {
+        let Some(ref mut watcher) = self.watcher else {
+            return;
+        };
+        let events = watcher.poll_events();
+
+        if let Some(event) = events.iter().find(|e| e.is_quant_md()) {
+            let action = match event {
+                ConfigChangeEvent::QuantMdDeleted(_) => "removed",
+                _ => "changed",
+            };
+            println!(
+                "{}[Reload]{} QUANT.md {}; hooks, MCP servers, and instructions apply from the next agent task",
+                CYAN, RESET, action
+            );
+        }
+
+        if let Some(event) = events.iter().find(|e| !e.is_quant_md()) {
+            match UserConfig::load() {
+                Ok(user_config) => {
+                    self.auto_save = user_config.repl.auto_save;
+                    self.temperature = user_config.repl.temperature;
+                    self.auto_title = user_config.repl.auto_title;
+                    self.title_model = user_config.repl.title_model.clone();
+                    self.auto_summary = user_config.repl.auto_summary;
+                    self.context_meter = user_config.repl.context_meter;
+                    self.max_response_bytes = user_config.limits.max_response_bytes();
+                    println!(
+                        "{}[Reload]{} {} changed; settings and tool permissions reloaded",
+                        CYAN,
+                        RESET,
+                        event.path().display()
+                    );
+                }
+                Err(e) => println!("{}[Reload]{} Ignoring config change: {:#}", YELLOW, RESET, e),
+            }
+        }
+    }
+
+    /// Start titling the conversation in the background once it has its
+    /// first exchange
+    fn request_title(&mut self) {
+        if !self.auto_title || self.title_requested.as_ref() == Some(&self.conversation.id) {
+            return;
+        }
+        let Some(messages) = self.conversation.title_request() else {
+            return;
+        };
+
+        let client = self.client.clone();
+        let model = self.title_model.clone().unwrap_or_else(|| self.model.clone());
+        let options = ChatOptions {
+            temperature: Some(0.2),
+            num_predict: Some(TITLE_MAX_TOKENS),
+            ..Default::default()
+        };
+        let handle = tokio::spawn(async move {
+            match client.chat(&model, &messages, Some(options)).await {
+                Ok(response) => Some(llm_core::split_thinking(&response.message.content).content),
+                Err(e) => {
+                    tracing::debug!(model = %model, error = %e, "Failed to generate conversation title");
+                    None
+                }
+            }
+        });
+        self.pending_title = Some((self.conversation.id.clone(), handle));
+        self.title_requested = Some(self.conversation.id.clone());
+    }
+
+    /// Apply a generated title once it is ready; with `wait`, give it a few
+    /// seconds to finish (before saving)
+    async fn apply_title(&mut self, wait: bool) {
+        let ready = self.pending_title.as_ref().is_some_and(|(_, handle)| handle.is_finished());
+        if !ready && !wait {
+            return;
+        }
+        let Some((id, handle)) = self.pending_title.take() else {
+            return;
+        };
+
+        let reply = match tokio::time::timeout(TITLE_WAIT, handle).await {
+            Ok(Ok(reply)) => reply,
+            _ => None,
+        };
+        if let Some(reply) = reply.filter(|_| id == self.conversation.id) {
+            self.conversation.set_generated_title(&reply);
+        }
+    }
+
+    /// Title and summarize the conversation, then save it
+    async fn save_conversation(&mut self) -> Result<PathBuf> {
+        self.apply_title(true).await;
+        self.summarize().await;
+        self.store.save(&self.conversation)
+    }
+
+    /// Have the title model summarize the conversation if it changed since
+    /// its last summary; on failure it is saved with the old one
+    async fn summarize(&mut self) {
+        if !self.auto_summary {
+            return;
+        }
+        let Some(messages) = self.conversation.summary_request() else {
+            return;
+        };
+
+        let model = self.title_model.clone().unwrap_or_else(|| self.model.clone());
+        let options = ChatOptions {
+            temperature: Some(0.2),
+            num_predict: Some(crate::session::SUMMARY_MAX_TOKENS),
+            ..Default::default()
+        };
+        match tokio::time::timeout(SUMMARY_WAIT, self.client.chat(&model, &messages, Some(options))).await {
+            Ok(Ok(reply)) => {
+                if !self.conversation.set_generated_summary(&llm_core::split_thinking(&reply.message.content).content) {
+                    tracing::debug!(model = %model, "Summary model gave no usable summary");
+                }
+            }
+            Ok(Err(e)) => tracing::debug!(model = %model, error = %e, "Failed to generate conversation summary"),
+            Err(_) => tracing::debug!(model = %model, "Timed out generating conversation summary"),
+        }
+    }
+
+    /// The context window set with `/set` or by the model alias, else the
+    /// model's when detected from Ollama, to load it with instead of
+    /// Ollama's default
+    fn num_ctx(&self) -> Option<u32> {
+        self.conversation
+            .sampling
+            .num_ctx
+            .or(self.model_options.num_ctx)
+            .or_else(|| model_info::cached_window(&self.model).map(|window| window as u32))
+    }
+
+    /// Sampling temperature: set with `/set`, else the model alias's, else
+    /// the configured one
+    fn temperature(&self) -> Option<f32> {
+        self.conversation
+            .sampling
+            .temperature
+            .or(self.model_options.temperature)
+            .or(self.temperature)
+    }
+
+    /// Options for a chat request
+    fn chat_options(&self) -> ChatOptions {
+        let mut options = ChatOptions {
+            temperature: self.temperature(),
+            num_ctx: self.num_ctx(),
+            ..Default::default()
+        };
+        self.model_options.apply_sampling(&mut options);
+        self.conversation.sampling.apply(&mut options);
+        options
+    }
+
+    /// Tokens the conversation uses and the model's context window
+    fn context_usage(&mut self) -> (usize, usize) {
+        let window = match self.conversation.sampling.num_ctx.or(self.model_options.num_ctx) {
+            Some(num_ctx) => num_ctx as usize,
+            None => ModelLimits::for_model(&self.model).context_window,
+        };
+        (self.conversation.context_tokens(), window)
+    }
+
+    /// The prompt, led by a context meter such as `[3.1k/8k]`
+    fn prompt(&mut self) -> String {
+        let name = match crate::config::active_profile() {
+            Some(profile) => format!("{}quant[{}]>{} ", CYAN, profile, RESET),
+            None => format!("{}quant>{} ", CYAN, RESET),
+        };
+        if !self.context_meter || self.conversation.is_empty() {
+            return name;
+        }
+
+        let (used, window) = self.context_usage();
+        let color = match used * 100 / window.max(1) {
+            p if p >= CONTEXT_FULL_PERCENT => RED,
+            p if p >= CONTEXT_WARN_PERCENT => YELLOW,
+            _ => DIM,
+        };
+        format!("{}[{}/{}]{} {}", color, format_tokens(used), format_tokens(window), RESET, name)
+    }
+
+    /// Suggest `/compact` once the conversation nears the context window
+    fn warn_context(&mut self) {
+        let (used, window) = self.context_usage();
+        let percent = used * 100 / window.max(1);
+        if percent < CONTEXT_WARN_PERCENT {
+            self.context_warned = false;
+            return;
+        }
+        if self.context_warned {
+            return;
+        }
+
+        self.context_warned = true;
+        println!(
+            "{}[Context]{} {} of {} tokens used ({}%); the model will lose the start of the conversation. \
+             {}/compact{} summarizes older messages",
+            YELLOW,
+            RESET,
+            format_tokens(used),
+            format_tokens(window),
+            percent,
+            CYAN,
+            RESET
+        );
+    }
+
     async fn load_conversation(&mut self, name: &str) -> Result<()> {
         self.conversation = self.store.load_by_name(name)?;
         self.model = self.conversation.model.clone();
@@ -161,16 +612,46 @@ pub async fn run(
     }
 
     // Setup readline
-    let history = InputHistory::new()?;
-    let mut rl: Editor<(), DefaultHistory> = DefaultEditor::new()?;
-    let _ = rl.load_history(history.path());
+    let repl_config = UserConfig::load().unwrap_or_default().repl;
+    let edit_mode = match repl_config.edit_mode {
+        EditMode::Emacs => rustyline::EditMode::Emacs,
+        EditMode::Vi => rustyline::EditMode::Vi,
+    };
+    let history_config = rustyline::Config::builder()
+        .max_history_size(repl_config.history_size)?
+        .auto_add_history(false)
+        .build();
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let mut history = InputHistory::for_dir(&cwd, repl_config.project_history, history_config)?;
+    // Room for the slash commands as well as the project's prompts
+    let editor_config = rustyline::Config::builder()
+        .edit_mode(edit_mode)
+        .max_history_size(repl_config.history_size.saturating_mul(2))?
+        .build();
+    let mut rl: Editor<ReplHelper, DefaultHistory> = Editor::with_config(editor_config)?;
+    let models = match state.client.list_models().await {
+        Ok(models) => models.into_iter().map(|m| m.name).collect(),
+        Err(_) => Vec::new(),
+    };
+    rl.set_helper(Some(ReplHelper::new(models)));
+    for entry in history.entries() {
+        let _ = rl.add_history_entry(entry.as_str());
+    }
 
     // Print welcome message
     print_welcome(&state);
 
     // Main REPL loop
     loop {
-        let prompt = format!("{}quant>{} ", CYAN, RESET);
+        state.check_config_changes();
+        state.report_mcp_events().await;
+        state.apply_title(false).await;
+        state.sync_bar().await;
+
+        let prompt = state.prompt();
+        if let Some(helper) = rl.helper_mut() {
+            helper.set_context_files(state.context.list());
+        }
 
         match rl.readline(&prompt) {
             Ok(line) => {
@@ -182,6 +663,10 @@ pub async fn run(
 
                 // Add to history
                 let _ = rl.add_history_entry(line);
+                let _ = history.add(line);
+
+                // A model picked in OllamaBar while at the prompt applies to this line
+                state.sync_bar().await;
 
                 // Handle slash commands
                 if line.starts_with('/') {
@@ -189,7 +674,7 @@ pub async fn run(
                         Ok(true) => break, // Exit requested
                         Ok(false) => continue,
                         Err(e) => {
-                            eprintln!("{}Error:{} {}", YELLOW, RESET, e);
+                            print_error(&e);
                             continue;
                         }
                     }
@@ -197,8 +682,9 @@ pub async fn run(
 
                 // Send message
                 if let Err(e) = send_message(&mut state, line).await {
-                    eprintln!("{}Error:{} {}", YELLOW, RESET, e);
+                    print_error(&e);
                 }
+                state.warn_context();
             }
             Err(ReadlineError::Interrupted) => {
                 println!("{}^C{}", DIM, RESET);
@@ -215,12 +701,27 @@ pub async fn run(
         }
     }
 
-    // Save history
-    let _ = rl.save_history(history.path());
+    if let Some(ref manager) = state.mcp {
+        manager.lock().await.stop_all().await;
+    }
+
+    // Writes still staged would be lost on exit
+    if let Some(staging) = state.staging.as_ref().filter(|s| !s.is_empty()) {
+        let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        if let Err(e) = crate::review::review_staged(staging, &cwd) {
+            print_error(&e);
+        }
+    }
+
+    // Save history and cached token counts
+    if let Err(e) = history.save() {
+        tracing::debug!(path = %history.path().display(), error = %e, "Failed to save input history");
+    }
+    let _ = crate::context::save_token_cache();
 
     // Auto-save conversation if enabled and has messages
     if state.auto_save && !state.conversation.is_empty() {
-        let path = state.store.save(&state.conversation)?;
+        let path = state.save_conversation().await?;
         println!(
             "{}Saved:{} {}",
             DIM,
@@ -257,6 +758,23 @@ fn print_welcome(state: &ReplState) {
     println!();
 }
 
+/// e.g. 950, 3.1k, 128k
+/// Print a command error, with a suggestion when Ollama told us what went wrong
+fn print_error(e: &anyhow::Error) {
+    eprintln!("{}Error:{} {}", YELLOW, RESET, e);
+    if let Some(hint) = LlmError::find(e).and_then(LlmError::hint) {
+        eprintln!("{}Hint:{} {}", DIM, RESET, hint);
+    }
+}
+
+fn format_tokens(tokens: usize) -> String {
+    match tokens {
+        0..=999 => tokens.to_string(),
+        1000..=9999 => format!("{:.1}k", tokens as f64 / 1000.0),
+        _ => format!("{}k", tokens / 1000),
+    }
+}
+
 fn truncate(s: &str, max: usize) -> String {
     if s.len() <= max {
         format!("{:width$}", s, width = max)
@@ -292,13 +810,25 @@ async fn handle_slash_command(state: &mut ReplState, input: &str) -> Result<bool
             handle_context_command(state, args)?;
             Ok(false)
         }
+        "/compact" => {
+            handle_compact_command(state, args).await?;
+            Ok(false)
+        }
+        "/runlast" => {
+            handle_runlast_command(state).await?;
+            Ok(false)
+        }
+        "/copy" => {
+            handle_copy_command(state, args)?;
+            Ok(false)
+        }
         "/clear" => {
             state.conversation.clear();
             println!("{}Conversation cleared{}", DIM, RESET);
             Ok(false)
         }
         "/save" => {
-            let path = state.store.save(&state.conversation)?;
+            let path = state.save_conversation().await?;
             println!(
                 "{}Saved:{} {}",
                 GREEN,
@@ -308,7 +838,9 @@ async fn handle_slash_command(state: &mut ReplState, input: &str) -> Result<bool
             Ok(false)
         }
         "/load" => {
-            if args.is_empty() {
+            if let Some(query) = args.strip_prefix("search").filter(|q| q.is_empty() || q.starts_with(' ')) {
+                handle_load_search(query.trim())?;
+            } else if args.is_empty() {
                 // List conversations
                 let convs = state.store.list()?;
                 if convs.is_empty() {
@@ -322,6 +854,13 @@ async fn handle_slash_command(state: &mut ReplState, input: &str) -> Result<bool
                             c.title,
                             c.message_count
                         );
+                        if let Some(summary) = &c.summary {
+                            println!("    {}{}{}", DIM, summary, RESET);
+                        }
+                        if !c.tags.is_empty() {
+                            let tags: Vec<String> = c.tags.iter().map(|tag| format!("#{}", tag)).collect();
+                            println!("    {}{}{}", CYAN, tags.join(" "), RESET);
+                        }
                     }
                     println!("\nUse: /load <id-prefix>");
                 }
@@ -344,6 +883,14 @@ async fn handle_slash_command(state: &mut ReplState, input: &str) -> Result<bool
             }
             Ok(false)
         }
+        "/thinking" => {
+            if state.last_thinking.is_empty() {
+                println!("{}The last response had no thinking{}", DIM, RESET);
+            } else {
+                println!("{}{}{}", DIM, state.last_thinking, RESET);
+            }
+            Ok(false)
+        }
         "/history" | "/hist" => {
             if state.conversation.is_empty() {
                 println!("No messages in conversation");
@@ -379,6 +926,14 @@ async fn handle_slash_command(state: &mut ReplState, input: &str) -> Result<bool
             crate::commands::status().await?;
             Ok(false)
         }
+        "/set" => {
+            handle_set_command(state, args)?;
+            Ok(false)
+        }
+        "/settings" => {
+            print_settings(state);
+            Ok(false)
+        }
         "/autosave" => {
             state.auto_save = !state.auto_save;
             println!(
@@ -387,6 +942,28 @@ async fn handle_slash_command(state: &mut ReplState, input: &str) -> Result<bool
             );
             Ok(false)
         }
+        "/task" => {
+            handle_task_command(state, args).await?;
+            Ok(false)
+        }
+        "/review" => {
+            match state.staging {
+                Some(ref staging) => {
+                    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+                    crate::review::review_staged(staging, &cwd)?;
+                }
+                None => println!("{}Agent writes aren't staged; set review_writes = true under [agent]{}", DIM, RESET),
+            }
+            Ok(false)
+        }
+        "/profile" => {
+            handle_profile_command(state, args)?;
+            Ok(false)
+        }
+        "/mcp" => {
+            handle_mcp_command(state, args).await?;
+            Ok(false)
+        }
         "/agent" => {
             state.agent_mode = !state.agent_mode;
             if state.agent_mode {
@@ -423,22 +1000,328 @@ fn print_help() {
     );
     println!("  {}/system{} <prompt>  Set system prompt", CYAN, RESET);
     println!("  {}/clear{}            Clear conversation history", CYAN, RESET);
+    println!("  {}/compact{} [n]      Summarize all but the last n messages (default 2) to free context", CYAN, RESET);
+    println!("  {}/copy{} [code]      Copy the last response (or just its code blocks) to the clipboard", CYAN, RESET);
+    println!("  {}/runlast{}          Run the last code block of the last response and add its output", CYAN, RESET);
     println!("  {}/save{}             Save conversation", CYAN, RESET);
     println!("  {}/load{} [id]        Load conversation (or list saved)", CYAN, RESET);
+    println!("  {}/load search{} <q>  Search saved conversations and sessions", CYAN, RESET);
     println!("  {}/history{}          Show conversation history", CYAN, RESET);
+    println!("  {}/set{} <opt> [val]  Set temperature, top_p, top_k, seed, stop... for this conversation", CYAN, RESET);
+    println!("  {}/settings{}         Show the sampling settings", CYAN, RESET);
+    println!("  {}/thinking{}         Show what a reasoning model thought before its last response", CYAN, RESET);
     println!("  {}/status{}           Show Ollama status", CYAN, RESET);
     println!("  {}/autosave{}         Toggle auto-save on exit", CYAN, RESET);
     println!("  {}/agent{}            Toggle agent mode (tool execution)", CYAN, RESET);
+    println!(
+        "  {}/task{} [n|text]     Run the last request (plus n messages of history) as an agent task",
+        CYAN, RESET
+    );
+    println!("  {}/review{}           Review staged agent writes and choose which to write", CYAN, RESET);
+    println!("  {}/profile{} [name]   Switch config profile (or list; `off` to clear)", CYAN, RESET);
+    println!(
+        "  {}/mcp{} [cmd]        MCP servers, resources, prompts; `prompt <server:name> [k=v]` runs a prompt",
+        CYAN, RESET
+    );
     println!("  {}/exit{}, /quit, /q  Exit the REPL", CYAN, RESET);
     println!();
     println!("{}Tips:{}", DIM, RESET);
     println!("  - Press Ctrl+C to cancel current input");
     println!("  - Press Ctrl+D to exit");
-    println!("  - Use arrow keys to navigate history");
+    println!("  - Use arrow keys to navigate history, Ctrl+R to search it");
+    println!("  - Set `edit_mode = \"vi\"` under [repl] for vi keys");
     println!("  - Use /agent to enable tool calling");
+    println!("  - Use @server:resource to include an MCP resource");
     println!();
 }
 
+/// Copy the assistant's last response, or only its code blocks, to the
+/// system clipboard
+fn handle_copy_command(state: &mut ReplState, args: &str) -> Result<()> {
+    let Some(reply) = state
+        .conversation
+        .messages
+        .iter()
+        .rev()
+        .find(|m| m.role == llm_core::Role::Assistant)
+    else {
+        println!("No response to copy yet");
+        return Ok(());
+    };
+
+    let (text, what) = match args.trim() {
+        "" => (reply.content.trim().to_string(), "response".to_string()),
+        "code" => match crate::clipboard::code_to_copy(&reply.content) {
+            Some((code, count)) => (code, format!("{} code block(s)", count)),
+            None => {
+                println!("The last response has no code blocks");
+                return Ok(());
+            }
+        },
+        _ => anyhow::bail!("Usage: /copy [code]"),
+    };
+
+    crate::clipboard::copy(&mut state.clipboard, &text)?;
+    println!("{}Copied{} {} ({} lines)", GREEN, RESET, what, text.lines().count());
+    Ok(())
+}
+
+/// Run the last fenced code block of the assistant's last response, after
+/// confirmation, and add its output to the conversation
+async fn handle_runlast_command(state: &mut ReplState) -> Result<()> {
+    let Some(reply) = state
+        .conversation
+        .messages
+        .iter()
+        .rev()
+        .find(|m| m.role == llm_core::Role::Assistant)
+    else {
+        println!("No response to run code from yet");
+        return Ok(());
+    };
+    let Some(block) = last_code_block(&reply.content) else {
+        println!("The last response has no code block");
+        return Ok(());
+    };
+    // Prefer the sandbox when one is installed and the config allows it
+    let user_config = UserConfig::load().unwrap_or_default();
+    let mut registry = create_default_registry();
+    if let Some(ref tools) = user_config.agent.tools {
+        registry.retain(tools);
+    }
+    let backend = SandboxBackend::configured();
+
+    // A wasm sandbox runs the block with the module named after its language
+    let wasm = backend == SandboxBackend::Wasm && user_config.sandbox.wasm_modules.contains_key(&block.lang);
+    let (tool, args) = match registry.get("sandbox") {
+        Some(tool) if wasm => (tool, serde_json::json!({ "command": block.lang, "stdin": block.code })),
+        sandbox => {
+            let Some(command) = block.command() else {
+                println!("{}Don't know how to run a `{}` code block{}", YELLOW, block.lang, RESET);
+                return Ok(());
+            };
+            let tool = match sandbox {
+                Some(tool) if !matches!(backend, SandboxBackend::None | SandboxBackend::Wasm) => tool,
+                _ => match registry.get("bash") {
+                    Some(tool) => tool,
+                    None => anyhow::bail!("Neither the sandbox nor the bash tool is enabled (agent.tools)"),
+                },
+            };
+            (tool, serde_json::json!({ "command": command }))
+        }
+    };
+
+    let lang = if block.lang.is_empty() { "sh" } else { block.lang.as_str() };
+    println!("{}```{}{}", DIM, lang, RESET);
+    println!("{}", block.code);
+    println!("{}```{}", DIM, RESET);
+    print!("Run this with {}? [y/N] ", tool.name());
+    stdout().flush()?;
+
+    let mut answer = String::new();
+    BufReader::new(tokio::io::stdin()).read_line(&mut answer).await?;
+    if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+        println!("{}Not run{}", DIM, RESET);
+        return Ok(());
+    }
+
+    let ctx = ToolContext {
+        working_dir: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+        ..Default::default()
+    };
+    let result = tool.execute(&args, &ctx).await?;
+    let mut output = result.output.trim_end().to_string();
+    if let Some(error) = result.error {
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        output.push_str(&error);
+    }
+
+    if result.success {
+        println!("{}[Succeeded]{}", GREEN, RESET);
+    } else {
+        println!("{}[Failed]{}", RED, RESET);
+    }
+    println!("{}", output);
+
+    state.conversation.add_message(ChatMessage::user(format!(
+        "I ran the last code block from your response ({}). {}:\n```\n{}\n```",
+        tool.name(),
+        if result.success { "Output" } else { "It failed" },
+        output
+    )));
+    state.warn_context();
+    Ok(())
+}
+
+/// Summarize older messages into one to free up the context window
+async fn handle_compact_command(state: &mut ReplState, args: &str) -> Result<()> {
+    let keep = match args.trim() {
+        "" => COMPACT_DEFAULT_KEEP,
+        n => n.parse::<usize>().map_err(|_| anyhow::anyhow!("Usage: /compact [messages to keep]"))?,
+    };
+    let Some(messages) = state.conversation.compact_request(keep) else {
+        println!("Nothing to compact: the conversation has {} message(s)", state.conversation.len());
+        return Ok(());
+    };
+
+    let (before, window) = state.context_usage();
+    let spinner = ProgressBar::new_spinner();
+    spinner.set_style(ProgressStyle::default_spinner().template("{spinner:.cyan} {msg}").unwrap());
+    spinner.set_message("Summarizing earlier messages...");
+    spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+
+    let options = ChatOptions {
+        temperature: Some(0.2),
+        num_ctx: state.num_ctx(),
+        ..Default::default()
+    };
+    let response = state.client.chat(&state.model, &messages, Some(options)).await;
+    spinner.finish_and_clear();
+
+    let summary = llm_core::split_thinking(&response?.message.content).content;
+    let replaced = state.conversation.compact(&summary, keep);
+    if replaced == 0 {
+        println!("{}The model returned an empty summary; nothing changed{}", YELLOW, RESET);
+        return Ok(());
+    }
+
+    let (after, _) = state.context_usage();
+    println!(
+        "{}Compacted{} {} message(s) into a summary: {} -> {} of {} tokens",
+        GREEN,
+        RESET,
+        replaced,
+        format_tokens(before),
+        format_tokens(after),
+        format_tokens(window)
+    );
+    state.warn_context();
+    Ok(())
+}
+
+/// Promote the conversation's last request to a full agent run
+///
+/// `/task` includes the last few messages as background, `/task <n>` sets
+/// how many, and `/task <text>` replaces the request with explicit
+/// instructions. The run is saved as a session linked to this conversation.
+async fn handle_task_command(state: &mut ReplState, args: &str) -> Result<()> {
+    use crate::session::{Session, SessionStore};
+
+    let args = args.trim();
+    let (history, request) = match args.parse::<usize>() {
+        Ok(n) => (n, None),
+        Err(_) if args.is_empty() => (TASK_DEFAULT_HISTORY, None),
+        Err(_) => (TASK_DEFAULT_HISTORY, Some(args)),
+    };
+
+    let Some(task) = state.conversation.task_prompt(history, request) else {
+        println!("No request to promote yet. Ask something first, or use /task <instructions>");
+        return Ok(());
+    };
+
+    let preview = task.lines().next().unwrap_or_default();
+    println!("{}Promoting to agent task:{} {}", BOLD, RESET, preview);
+
+    let mut agent = build_agent(state, ContextAssembly::new());
+    let agent_state = agent.run(&task).await?;
+
+    // Record the run as a session linked back to this conversation
+    let mut session = Session::new(&state.model, std::env::current_dir().ok());
+    session.set_name(format!("Task: {}", state.conversation.title));
+    session.source_conversation = Some(state.conversation.id.clone());
+    for msg in &agent_state.transcript {
+        session.add_message(msg.clone());
+    }
+    session.plan = agent_state.plan.clone();
+    session.record_model_switches(&agent_state.model_switches);
+    session.record_model_restarts(&agent_state.model_restarts);
+    session.record_run(&agent_state.snapshots, &agent_state.steps);
+    session.failure = agent_state.failure.clone();
+    if let Some(ref response) = agent_state.final_response {
+        let user_config = UserConfig::load().unwrap_or_default();
+        let summary_model = user_config.agent.summary_model(&state.model);
+        session.summarize(&state.client, summary_model, &task, response).await;
+    }
+    SessionStore::new()?.save(&session)?;
+    crate::search::refresh_default_index();
+
+    // Keep the chat going with the task's outcome
+    state
+        .conversation
+        .add_message(ChatMessage::user(format!("/task {}", preview)));
+    if let Some(ref response) = agent_state.final_response {
+        println!();
+        println!("{}Response:{}", GREEN, RESET);
+        println!("{}", response);
+        state
+            .conversation
+            .add_message(ChatMessage::assistant(response.clone()));
+    }
+    if let Some(ref failure) = agent_state.failure {
+        println!();
+        print!("{}", failure.render());
+    }
+
+    state.conversation.link_session(session.id.clone());
+    state.store.save(&state.conversation)?;
+
+    println!();
+    println!(
+        "{}[Agent task completed in {} iterations | session {}]{}",
+        DIM, agent_state.iteration, session.id, RESET
+    );
+    state.print_staged();
+
+    Ok(())
+}
+
+/// List profiles, or switch to one (`/profile off` clears it)
+fn handle_profile_command(state: &mut ReplState, args: &str) -> Result<()> {
+    let name = args.trim();
+    let active = crate::config::active_profile();
+
+    if name.is_empty() {
+        let names = ConfigResolver::discover()?.profile_names();
+        if names.is_empty() {
+            println!(
+                "No profiles defined. Add [profiles.<name>] to {}",
+                UserConfig::config_path()?.display()
+            );
+            return Ok(());
+        }
+        println!("{}Profiles:{}", BOLD, RESET);
+        for profile in names {
+            let marker = if active.as_deref() == Some(profile.as_str()) { "*" } else { " " };
+            println!("  {} {}", marker, profile);
+        }
+        println!("\nUse: /profile <name> (or /profile off)");
+        return Ok(());
+    }
+
+    let next = (!matches!(name, "off" | "none")).then(|| name.to_string());
+    crate::config::set_active_profile(next.clone());
+    let user_config = match ConfigResolver::discover().and_then(|resolver| resolver.user_config()) {
+        Ok(config) => config,
+        Err(e) => {
+            crate::config::set_active_profile(active);
+            println!("{}Error:{} {:#}", YELLOW, RESET, e);
+            return Ok(());
+        }
+    };
+    state.apply_user_config(&user_config);
+
+    match next {
+        Some(profile) => println!(
+            "{}Profile:{} {} (model: {}{}{})",
+            GREEN, RESET, profile, BLUE, state.model, RESET
+        ),
+        None => println!("{}Profile cleared{} (model: {})", DIM, RESET, state.model),
+    }
+    Ok(())
+}
+
 async fn handle_model_command(state: &mut ReplState, args: &str) -> Result<()> {
     if args.is_empty() {
         println!("Current model: {}{}{}", BLUE, state.model, RESET);
@@ -446,18 +1329,23 @@ async fn handle_model_command(state: &mut ReplState, args: &str) -> Result<()> {
         return Ok(());
     }
 
-    // Check if model exists
-    let models = state.client.list_models().await?;
-    let model_names: Vec<_> = models.iter().map(|m| &m.name).collect();
+    let aliases = UserConfig::load().unwrap_or_default().aliases.models;
+    let resolved = match resolve_model(&state.client, &aliases, args).await {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            print_error(&e);
+            return Ok(());
+        }
+    };
+    let args = resolved.model.as_str();
 
-    if !model_names.contains(&&args.to_string()) {
-        println!(
-            "{}Warning:{} Model '{}' not found locally",
-            YELLOW, RESET, args
-        );
+    // Offer to pull a model that isn't installed
+    if let Err(e) = crate::commands::ensure_model(&state.client, args, true).await {
+        print_error(&e);
+        let models = state.client.list_models().await.unwrap_or_default();
         println!("Available models:");
-        for name in model_names {
-            println!("  - {}", name);
+        for model in models {
+            println!("  - {}", model.name);
         }
         return Ok(());
     }
@@ -468,6 +1356,11 @@ async fn handle_model_command(state: &mut ReplState, args: &str) -> Result<()> {
 
     state.model = args.to_string();
     state.conversation.model = args.to_string();
+    if let Some(ref system) = resolved.options.system_prompt {
+        state.conversation.system_prompt = Some(system.clone());
+    }
+    state.model_options = resolved.options.clone();
+    model_info::detect(&state.client, args).await;
 
     if already_loaded {
         println!("Switched to model: {}{}{}", BLUE, args, RESET);
@@ -563,6 +1456,118 @@ fn handle_context_command(state: &mut ReplState, args: &str) -> Result<()> {
     Ok(())
 }
 
+/// Handle `/mcp` subcommands
+async fn handle_mcp_command(state: &mut ReplState, args: &str) -> Result<()> {
+    let mut parts = args.split_whitespace();
+    let subcmd = parts.next().unwrap_or("");
+    let manager = state.mcp_manager().await;
+
+    match subcmd {
+        "" | "list" | "servers" => {
+            let summary = manager.lock().await.summary();
+            if summary.is_empty() {
+                println!("No MCP servers configured");
+                println!("Add them under {}mcp_servers{} in QUANT.md", CYAN, RESET);
+            } else {
+                println!("{}MCP Servers:{}", BOLD, RESET);
+                for server in summary {
+                    println!("  {} - {} ({})", server.name, server.state, server.command);
+                    if server.restart_count > 0 {
+                        println!("    {}restarted {} time(s){}", DIM, server.restart_count, RESET);
+                    }
+                }
+            }
+        }
+        "resources" => {
+            let server = parts.next();
+            let resources: Vec<_> = manager
+                .lock()
+                .await
+                .discover_resources()
+                .await
+                .into_iter()
+                .filter(|r| server.is_none_or(|s| r.server == s))
+                .collect();
+            if resources.is_empty() {
+                println!("No MCP resources available");
+            } else {
+                println!("{}MCP Resources:{}", BOLD, RESET);
+                for line in format_resource_list(&resources).lines() {
+                    println!("  {}", line);
+                }
+                println!("\nInclude one in a message with: @<server>:<uri or name>");
+            }
+        }
+        "prompts" => {
+            let server = parts.next();
+            let prompts: Vec<_> = manager
+                .lock()
+                .await
+                .discover_prompts()
+                .await
+                .into_iter()
+                .filter(|p| server.is_none_or(|s| p.server == s))
+                .collect();
+            if prompts.is_empty() {
+                println!("No MCP prompts available");
+            } else {
+                println!("{}MCP Prompts:{}", BOLD, RESET);
+                for prompt in prompts {
+                    let args: Vec<String> = prompt
+                        .arguments
+                        .iter()
+                        .map(|a| if a.required { a.name.clone() } else { format!("[{}]", a.name) })
+                        .collect();
+                    print!("  {}{}:{}{}", CYAN, prompt.server, prompt.name, RESET);
+                    if !args.is_empty() {
+                        print!(" {}", args.join(" "));
+                    }
+                    match prompt.description {
+                        Some(description) => println!(" - {}", description),
+                        None => println!(),
+                    }
+                }
+                println!("\nRun one with: /mcp prompt <server>:<name> [arg=value ...]");
+            }
+        }
+        "prompt" => {
+            let Some((server, name)) = parts.next().and_then(|p| p.split_once(':')) else {
+                println!("Usage: /mcp prompt <server>:<name> [arg=value ...]");
+                return Ok(());
+            };
+            let mut arguments = HashMap::new();
+            for pair in parts {
+                match pair.split_once('=') {
+                    Some((key, value)) => {
+                        arguments.insert(key.to_string(), value.to_string());
+                    }
+                    None => anyhow::bail!("Prompt arguments must be key=value, got: {}", pair),
+                }
+            }
+
+            let result = manager.lock().await.get_prompt(server, name, &arguments).await?;
+            let description = result.description.map(|d| format!(" - {}", d)).unwrap_or_default();
+            let text: Vec<String> = result
+                .messages
+                .into_iter()
+                .filter_map(|m| m.content.text)
+                .collect();
+            if text.is_empty() {
+                println!("Prompt {}:{} produced no text", server, name);
+                return Ok(());
+            }
+            println!("{}Prompt {}:{}{}{}", DIM, server, name, description, RESET);
+            send_message(state, &text.join("\n\n")).await?;
+        }
+        _ => {
+            println!("{}Unknown mcp command:{} {}", YELLOW, RESET, subcmd);
+            println!("Usage: /mcp [list|resources|prompts|prompt] [args]");
+        }
+    }
+
+    Ok(())
+}
+
 /// Send a message and stream the response
 async fn send_message(state: &mut ReplState, input: &str) -> Result<()> {
     // Check if agent mode is enabled
@@ -580,6 +1585,13 @@ async fn send_message(state: &mut ReplState, input: &str) -> Result<()> {
         full_message.push_str("\n---\n\n");
     }
 
+    // Add referenced MCP resources
+    let resource_content = state.resource_context(input).await?;
+    if !resource_content.is_empty() {
+        full_message.push_str(&resource_content);
+        full_message.push_str("---\n\n");
+    }
+
     full_message.push_str(input);
 
     // Add to conversation
@@ -602,19 +1614,33 @@ async fn send_message(state: &mut ReplState, input: &str) -> Result<()> {
 
     // Start timing
     let start_time = std::time::Instant::now();
+    let sampler = ResourceSampler::start(state.client.clone());
 
-    // Start streaming
-    let mut stream = state
+    let options = Some(state.chat_options());
+
+    // Start streaming; a model that stalls before its first token is
+    // reloaded and asked again
+    let (mut stream, restart) = state
         .client
-        .chat_stream(&state.model, &messages, None)
+        .open_watched(&state.model, || state.client.chat_stream(&state.model, &messages, options.clone()))
         .await?;
 
     // Clear spinner and start output
     spinner.finish_and_clear();
+    if let Some(restart) = restart {
+        println!(
+            "{}[Watchdog]{} {} sent nothing for {}s; reloaded it and retried",
+            YELLOW,
+            RESET,
+            restart.model,
+            restart.waited.as_secs()
+        );
+    }
     print!("{}", GREEN);
     stdout().flush()?;
 
-    let mut response_content = String::new();
+    let mut response_content = ResponseBuffer::new(state.max_response_bytes);
+    let mut display = ThinkingDisplay::new(GREEN);
     let mut first_token_time: Option<std::time::Duration> = None;
     let mut token_count = 0u32;
     let mut eval_duration: Option<u64> = None;
@@ -623,12 +1649,14 @@ async fn send_message(state: &mut ReplState, input: &str) -> Result<()> {
         let chunk = chunk?;
         if let Some(msg) = &chunk.message {
             // Track time to first token
-            if first_token_time.is_none() && !msg.content.is_empty() {
+            if first_token_time.is_none() && !(msg.content.is_empty() && msg.thinking.is_empty()) {
                 first_token_time = Some(start_time.elapsed());
             }
-            print!("{}", msg.content);
-            stdout().flush()?;
-            response_content.push_str(&msg.content);
+            let answer = display.push(&msg.thinking, &msg.content)?;
+            // Dropping the stream closes the request, which stops generation
+            if !response_content.push(&answer) {
+                break;
+            }
         }
         // Capture final stats from the done message
         if chunk.done {
@@ -641,11 +1669,26 @@ async fn send_message(state: &mut ReplState, input: &str) -> Result<()> {
         }
     }
 
+    let answer = display.finish()?;
+    response_content.push(&answer);
+    state.last_thinking = display.thinking().to_string();
+
     let total_time = start_time.elapsed();
+    let resource_usage = sampler.finish().await;
 
     print!("{}", RESET);
     println!();
 
+    if response_content.is_truncated() {
+        tracing::warn!(limit = state.max_response_bytes, "Response exceeded size limit; stopping generation");
+        println!(
+            "{}[Output limit]{} Response exceeded {} KB; generation stopped and the saved reply was truncated",
+            YELLOW,
+            RESET,
+            state.max_response_bytes / 1024
+        );
+    }
+
     // Show timing metrics (subtle, dimmed)
     let ttft = first_token_time
         .map(|d| format!("{:.1}s", d.as_secs_f64()))
@@ -671,9 +1714,17 @@ async fn send_message(state: &mut ReplState, input: &str) -> Result<()> {
 
     // Only show metrics if we have meaningful data
     if token_count > 0 {
+        let vram = if resource_usage.is_empty() {
+            String::new()
+        } else {
+            format!(
+                " | VRAM peak {:.1} GB",
+                resource_usage.peak_vram_bytes() as f64 / (1024.0 * 1024.0 * 1024.0)
+            )
+        };
         println!(
-            "{}[{} tokens | TTFT: {} | {}]{}\n",
-            DIM, token_count, ttft, tokens_per_sec, RESET
+            "{}[{} tokens | TTFT: {} | {}{}]{}\n",
+            DIM, token_count, ttft, tokens_per_sec, vram, RESET
         );
     } else {
         println!();
@@ -682,36 +1733,132 @@ async fn send_message(state: &mut ReplState, input: &str) -> Result<()> {
     // Add assistant response to conversation
     state
         .conversation
-        .add_message(ChatMessage::assistant(response_content));
+        .add_message(ChatMessage::assistant(response_content.into_string()));
+    state.request_title();
 
     Ok(())
 }
 
-/// Send a message in agent mode with tool execution
-async fn send_message_agent(state: &mut ReplState, input: &str) -> Result<()> {
-    // Build the user message with context
-    let mut full_message = String::new();
+/// `/set <option> [value]`: set a sampling option for this conversation, or
+/// unset it without a value
+fn handle_set_command(state: &mut ReplState, args: &str) -> Result<()> {
+    let args = args.trim();
+    if args.is_empty() {
+        print_settings(state);
+        return Ok(());
+    }
+    let (name, value) = args.split_once(' ').unwrap_or((args, ""));
+    state.conversation.sampling.set(name, value)?;
+    match state.conversation.sampling.values().into_iter().find(|(n, _)| *n == name) {
+        Some((name, Some(value))) => println!("{}{} = {}{}", DIM, name, value, RESET),
+        _ => println!("{}{} unset{}", DIM, name, RESET),
+    }
+    Ok(())
+}
 
-    // Add context if available
-    let context_content = state.context.build_context()?;
-    if !context_content.is_empty() {
-        full_message.push_str(&context_content);
-        full_message.push_str("\n---\n\n");
+/// `/settings`: the sampling options the next message is sent with
+fn print_settings(state: &ReplState) {
+    println!("{}Sampling settings:{}", BOLD, RESET);
+    for (name, value) in state.conversation.sampling.values() {
+        let inherited = match name {
+            "temperature" => state.temperature().map(|t| t.to_string()),
+            "num_ctx" => state.num_ctx().map(|n| n.to_string()),
+            _ => None,
+        };
+        match (value, inherited) {
+            (Some(value), _) => println!("  {:<19}{}", name, value),
+            (None, Some(value)) => println!("  {:<19}{} {}(default){}", name, value, DIM, RESET),
+            (None, None) => println!("  {:<19}{}model default{}", name, DIM, RESET),
+        }
     }
+    println!("{}Change with /set <option> <value>; /set <option> unsets it{}", DIM, RESET);
+}
 
-    full_message.push_str(input);
+/// `/load search <query>`: full-text search saved conversations and sessions
+fn handle_load_search(query: &str) -> Result<()> {
+    use crate::search::DocumentKind;
+
+    if query.is_empty() {
+        println!("Usage: /load search <query>");
+        return Ok(());
+    }
+
+    let hits = crate::search::search_saved(query, 10)?;
+    if hits.is_empty() {
+        println!("{}No matches for '{}'{}", DIM, query, RESET);
+        return Ok(());
+    }
+
+    for hit in &hits {
+        let id = match hit.kind {
+            DocumentKind::Conversation => &hit.id[..8.min(hit.id.len())],
+            DocumentKind::Session => hit.id.as_str(),
+        };
+        println!("  {}{}{} {}[{}]{} {}", CYAN, id, RESET, DIM, hit.kind, RESET, hit.title);
+        println!("    {}{}{}", DIM, hit.snippet, RESET);
+    }
+    println!("\nUse: /load <id-prefix> (sessions: quant sessions resume <id>)");
+    Ok(())
+}
+
+/// MCP servers declared in the current project's QUANT.md
+fn configured_mcp_servers() -> Vec<McpServerConfig> {
+    std::env::current_dir()
+        .ok()
+        .and_then(|cwd| ProjectContext::discover(&cwd))
+        .and_then(|ctx| ctx.quant_file)
+        .map(|quant_file| quant_file.mcp_servers)
+        .unwrap_or_default()
+}
+
+/// Watch QUANT.md and the config files for the current directory
+fn start_config_watcher() -> Option<ConfigWatcher> {
+    let cwd = std::env::current_dir().ok()?;
+    let root = ProjectContext::discover(&cwd).map(|ctx| ctx.root).unwrap_or_else(|| cwd.clone());
+    let mut watcher = ConfigWatcher::new(&root).ok()?.with_quant_config(&cwd);
+    match watcher.start() {
+        Ok(()) => Some(watcher),
+        Err(e) => {
+            tracing::debug!(error = %e, "Config hot reload unavailable");
+            None
+        }
+    }
+}
+
+/// Create an agent with tools for the current model and system prompt;
+/// `context` holds the files already put into its task
+fn build_agent(state: &ReplState, context: ContextAssembly) -> AgentLoop {
+    let user_config = UserConfig::load().unwrap_or_default();
+    let auto = user_config.agent.auto_approve;
 
     // Create tool registry and router
-    let registry = create_default_registry();
-    let confirmation = TerminalConfirmation::new();
+    let mut registry = create_default_registry();
+    if let Some(ref tools) = user_config.agent.tools {
+        registry.retain(tools);
+    }
+    if let Some(ref manager) = state.mcp {
+        registry.register(ReadResourceTool::new(Arc::clone(manager)));
+    }
+    let confirmation = if auto {
+        TerminalConfirmation::auto()
+    } else {
+        TerminalConfirmation::new()
+    };
     let router = ToolRouter::new(registry, confirmation);
 
-    // Configure the agent
-    let agent_config = AgentConfig::new(&state.model)
+    // Configure the agent, sampling like chat mode does
+    let sampling = state.conversation.sampling.over(&state.model_options);
+    let agent_config = AgentConfig::from_user_config(&state.model, &user_config)
         .with_max_iterations(50)
         .with_working_dir(std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")))
-        .with_auto_mode(false)
-        .with_verbose(true);
+        .with_auto_mode(auto)
+        .with_staging(state.staging.clone())
+        .with_verbose(true)
+        .with_temperature(state.temperature())
+        .with_num_ctx(sampling.num_ctx)
+        .with_sampling(sampling)
+        .with_hot_reload(true)
+        .with_context(context);
 
     // Add system prompt if set
     let agent_config = if let Some(ref sys) = state.conversation.system_prompt {
@@ -720,8 +1867,39 @@ async fn send_message_agent(state: &mut ReplState, input: &str) -> Result<()> {
         agent_config
     };
 
+    AgentLoop::new(state.client.clone(), router, agent_config)
+}
+
+/// Send a message in agent mode with tool execution
+async fn send_message_agent(state: &mut ReplState, input: &str) -> Result<()> {
+    // Build the user message with context
+    let mut full_message = String::new();
+
+    // Add context if available, leaving out QUANT.md files the agent's
+    // project section already includes
+    let mut assembly = ContextAssembly::new();
+    if state.conversation.system_prompt.is_none() {
+        if let Some(project) = std::env::current_dir().ok().and_then(|cwd| ProjectContext::discover(&cwd)) {
+            assembly.add_project(&project, &state.model);
+        }
+    }
+    let context_content = state.context.build_context_with(&mut assembly)?;
+    if !context_content.is_empty() {
+        full_message.push_str(&context_content);
+        full_message.push_str("\n---\n\n");
+    }
+
+    // Add referenced MCP resources
+    let resource_content = state.resource_context(input).await?;
+    if !resource_content.is_empty() {
+        full_message.push_str(&resource_content);
+        full_message.push_str("---\n\n");
+    }
+
+    full_message.push_str(input);
+
     // Create and run the agent
-    let agent = AgentLoop::new(state.client.clone(), router, agent_config);
+    let mut agent = build_agent(state, assembly);
     let agent_state = agent.run(&full_message).await?;
 
     // Add user message to conversation history
@@ -739,9 +1917,9 @@ async fn send_message_agent(state: &mut ReplState, input: &str) -> Result<()> {
             .add_message(ChatMessage::assistant(response.clone()));
     }
 
-    if let Some(ref error) = agent_state.error {
+    if let Some(ref failure) = agent_state.failure {
         println!();
-        println!("{}Error:{} {}", YELLOW, RESET, error);
+        print!("{}", failure.render());
     }
 
     println!();
@@ -749,6 +1927,7 @@ async fn send_message_agent(state: &mut ReplState, input: &str) -> Result<()> {
         "{}[Agent completed in {} iterations]{}",
         DIM, agent_state.iteration, RESET
     );
+    state.print_staged();
 
     Ok(())
 }
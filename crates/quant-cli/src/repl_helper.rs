@@ -0,0 +1,236 @@
+//! Tab completion and inline hints for the REPL
+//!
+//! Completes slash commands and their arguments: model names for `/model`,
+//! file paths for `/context add`, context files for `/context rm`, saved
+//! conversation ids for `/load` and profile names for `/profile`. While a
+//! command is being typed, its arguments are hinted in dim text after the
+//! cursor.
+
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::Validator;
+use rustyline::{Context, Helper};
+use std::borrow::Cow;
+
+use crate::config::ConfigResolver;
+use crate::conversation::{ConversationStore, Sampling};
+
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// Slash commands and the arguments they take, as hinted
+const COMMANDS: &[(&str, &str)] = &[
+    ("/help", ""),
+    ("/model", "<name>"),
+    ("/models", ""),
+    ("/context", "add|list|rm|clear [path]"),
+    ("/system", "<prompt>"),
+    ("/clear", ""),
+    ("/compact", "[n]"),
+    ("/copy", "[code]"),
+    ("/runlast", ""),
+    ("/save", ""),
+    ("/load", "[id] | search <query>"),
+    ("/history", ""),
+    ("/thinking", ""),
+    ("/set", "<option> [value]"),
+    ("/settings", ""),
+    ("/status", ""),
+    ("/autosave", ""),
+    ("/agent", ""),
+    ("/task", "[n|text]"),
+    ("/review", ""),
+    ("/profile", "[name|off]"),
+    ("/mcp", "[resources|prompts|prompt <server:name> [k=v]]"),
+    ("/exit", ""),
+    ("/quit", ""),
+];
+
+const CONTEXT_SUBCOMMANDS: &[&str] = &["add", "list", "rm", "clear"];
+const MCP_SUBCOMMANDS: &[&str] = &["list", "resources", "prompts", "prompt"];
+
+/// Characters of a conversation title shown next to its id
+const TITLE_PREVIEW_CHARS: usize = 40;
+
+/// rustyline helper for the REPL prompt
+pub struct ReplHelper {
+    /// Locally available models
+    models: Vec<String>,
+    /// Files currently in context
+    context_files: Vec<String>,
+    conversations: Option<ConversationStore>,
+    paths: FilenameCompleter,
+}
+
+impl ReplHelper {
+    pub fn new(models: Vec<String>) -> Self {
+        Self {
+            models,
+            context_files: Vec::new(),
+            conversations: ConversationStore::new().ok(),
+            paths: FilenameCompleter::new(),
+        }
+    }
+
+    pub fn set_context_files(&mut self, files: Vec<String>) {
+        self.context_files = files;
+    }
+
+    /// Where the completed word starts, and what it may become
+    fn candidates(&self, line: &str) -> (usize, Vec<Pair>) {
+        let Some((command, rest)) = line.split_once(' ') else {
+            let commands = COMMANDS
+                .iter()
+                .filter(|(name, _)| name.starts_with(line))
+                .map(|(name, args)| pair(name, if args.is_empty() { name.to_string() } else { format!("{} ", name) }));
+            return (0, commands.collect());
+        };
+        let start = command.len() + 1;
+
+        match command {
+            "/model" | "/m" => (start, matching(self.models.iter().map(String::as_str), rest)),
+            "/profile" => {
+                let mut names = ConfigResolver::discover().map(|r| r.profile_names()).unwrap_or_default();
+                names.push("off".to_string());
+                (start, matching(names.iter().map(String::as_str), rest))
+            }
+            "/set" if !rest.contains(' ') => (start, matching(Sampling::NAMES.iter().copied(), rest)),
+            "/mcp" if !rest.contains(' ') => (start, matching(MCP_SUBCOMMANDS.iter().copied(), rest)),
+            "/load" if !rest.contains(' ') => (start, self.conversation_candidates(rest)),
+            "/context" | "/ctx" => match rest.split_once(' ') {
+                None => (start, matching(CONTEXT_SUBCOMMANDS.iter().copied(), rest)),
+                Some(("add", _)) => self.paths.complete_path(line, line.len()).unwrap_or((0, Vec::new())),
+                Some(("rm" | "remove", file)) => {
+                    let start = line.len() - file.len();
+                    (start, matching(self.context_files.iter().map(String::as_str), file))
+                }
+                Some(_) => (0, Vec::new()),
+            },
+            _ => (0, Vec::new()),
+        }
+    }
+
+    /// Saved conversations whose id starts with `prefix`, shown with titles
+    fn conversation_candidates(&self, prefix: &str) -> Vec<Pair> {
+        let mut candidates = matching(["search"].into_iter(), prefix);
+        let Some(summaries) = self.conversations.as_ref().and_then(|store| store.list().ok()) else {
+            return candidates;
+        };
+        candidates.extend(summaries.iter().filter(|c| c.id.starts_with(prefix)).map(|c| {
+            let id = &c.id[..c.id.len().min(8)];
+            let title: String = c.title.chars().take(TITLE_PREVIEW_CHARS).collect();
+            Pair {
+                display: format!("{}  {}", id, title),
+                replacement: id.to_string(),
+            }
+        }));
+        candidates
+    }
+
+    /// Arguments of the command being typed, or the rest of its name
+    fn hint_for(&self, line: &str) -> Option<String> {
+        if !line.starts_with('/') {
+            return None;
+        }
+        match line.split_once(' ') {
+            None => {
+                let (name, args) = COMMANDS.iter().find(|(name, _)| name.starts_with(line))?;
+                let args = if args.is_empty() { String::new() } else { format!(" {}", args) };
+                let hint = format!("{}{}", &name[line.len()..], args);
+                (!hint.is_empty()).then_some(hint)
+            }
+            Some((command, "")) => COMMANDS
+                .iter()
+                .find(|(name, args)| *name == command && !args.is_empty())
+                .map(|(_, args)| args.to_string()),
+            Some(_) => None,
+        }
+    }
+}
+
+fn pair(display: &str, replacement: String) -> Pair {
+    Pair {
+        display: display.to_string(),
+        replacement,
+    }
+}
+
+fn matching<'a>(options: impl Iterator<Item = &'a str>, prefix: &str) -> Vec<Pair> {
+    options
+        .filter(|option| option.starts_with(prefix))
+        .map(|option| pair(option, option.to_string()))
+        .collect()
+}
+
+impl Completer for ReplHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        if !line.starts_with('/') {
+            return Ok((0, Vec::new()));
+        }
+        Ok(self.candidates(&line[..pos]))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<String> {
+        if pos < line.len() {
+            return None;
+        }
+        self.hint_for(line)
+    }
+}
+
+impl Highlighter for ReplHelper {
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(format!("{}{}{}", DIM, hint, RESET))
+    }
+}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn helper() -> ReplHelper {
+        ReplHelper {
+            models: vec!["llama3.2:3b".to_string(), "qwen2.5-coder:7b".to_string()],
+            context_files: vec!["src/main.rs".to_string(), "README.md".to_string()],
+            conversations: None,
+            paths: FilenameCompleter::new(),
+        }
+    }
+
+    fn replacements(helper: &ReplHelper, line: &str) -> (usize, Vec<String>) {
+        let (start, pairs) = helper.candidates(line);
+        (start, pairs.into_iter().map(|p| p.replacement).collect())
+    }
+
+    #[test]
+    fn test_complete_commands_and_arguments() {
+        let helper = helper();
+        assert_eq!(replacements(&helper, "/mod"), (0, vec!["/model ".to_string(), "/models".to_string()]));
+        assert_eq!(replacements(&helper, "/model qw"), (7, vec!["qwen2.5-coder:7b".to_string()]));
+        assert_eq!(replacements(&helper, "/context r"), (9, vec!["rm".to_string()]));
+        assert_eq!(replacements(&helper, "/context rm src"), (12, vec!["src/main.rs".to_string()]));
+        assert_eq!(replacements(&helper, "/load se"), (6, vec!["search".to_string()]));
+    }
+
+    #[test]
+    fn test_hints() {
+        let helper = helper();
+        assert_eq!(helper.hint_for("/comp").as_deref(), Some("act [n]"));
+        assert_eq!(helper.hint_for("/model").as_deref(), Some(" <name>"));
+        assert_eq!(helper.hint_for("/model ").as_deref(), Some("<name>"));
+        assert_eq!(helper.hint_for("/model llama"), None);
+        assert_eq!(helper.hint_for("/clear"), None);
+        assert_eq!(helper.hint_for("hello"), None);
+    }
+}
@@ -0,0 +1,245 @@
+//! Memory and VRAM sampling during chat and agent runs
+//!
+//! A background task polls Ollama's `/api/ps` (size of the loaded models and
+//! how much of each sits in VRAM) and the system's used memory at a fixed
+//! interval. The samples are folded into peak and average figures shown with
+//! the run's usage report, which helps when picking a quantization or a
+//! `keep_alive` setting. Each sample is also logged at debug level.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use llm_core::OllamaClient;
+use tokio::task::JoinHandle;
+
+/// How often to sample while a run is in progress
+pub const DEFAULT_SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+
+const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+/// One reading of memory usage
+#[derive(Debug, Clone, Default)]
+pub struct ResourceSample {
+    /// VRAM held by loaded models
+    pub vram_bytes: u64,
+    /// Total size of loaded models (VRAM plus whatever spilled to RAM)
+    pub model_bytes: u64,
+    /// System memory in use, when the platform reports it
+    pub ram_used_bytes: Option<u64>,
+    /// Names of the loaded models
+    pub models: Vec<String>,
+}
+
+/// Peak and average memory usage over a run
+#[derive(Debug, Clone, Default)]
+pub struct ResourceUsage {
+    samples: u64,
+    vram_total: u64,
+    peak_vram: u64,
+    peak_model: u64,
+    ram_samples: u64,
+    ram_total: u64,
+    peak_ram: u64,
+    models: Vec<String>,
+}
+
+impl ResourceUsage {
+    pub fn record(&mut self, sample: &ResourceSample) {
+        self.samples += 1;
+        self.vram_total += sample.vram_bytes;
+        self.peak_vram = self.peak_vram.max(sample.vram_bytes);
+        self.peak_model = self.peak_model.max(sample.model_bytes);
+        if let Some(ram) = sample.ram_used_bytes {
+            self.ram_samples += 1;
+            self.ram_total += ram;
+            self.peak_ram = self.peak_ram.max(ram);
+        }
+        for model in &sample.models {
+            if !self.models.contains(model) {
+                self.models.push(model.clone());
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples == 0
+    }
+
+    pub fn peak_vram_bytes(&self) -> u64 {
+        self.peak_vram
+    }
+
+    pub fn avg_vram_bytes(&self) -> u64 {
+        self.vram_total.checked_div(self.samples).unwrap_or(0)
+    }
+
+    pub fn peak_ram_bytes(&self) -> Option<u64> {
+        (self.ram_samples > 0).then_some(self.peak_ram)
+    }
+
+    pub fn avg_ram_bytes(&self) -> Option<u64> {
+        self.ram_total.checked_div(self.ram_samples)
+    }
+
+    /// Share of the largest loaded model that did not fit in VRAM
+    pub fn peak_spill_bytes(&self) -> u64 {
+        self.peak_model.saturating_sub(self.peak_vram)
+    }
+
+    /// One-line summary, e.g. "VRAM: peak 5.2 GB, avg 4.8 GB | RAM: peak 21.3 GB, avg 20.1 GB"
+    pub fn summary(&self) -> String {
+        let mut parts = vec![format!(
+            "VRAM: peak {:.1} GB, avg {:.1} GB",
+            self.peak_vram_bytes() as f64 / GB,
+            self.avg_vram_bytes() as f64 / GB
+        )];
+        if self.peak_spill_bytes() > 0 {
+            parts.push(format!("{:.1} GB offloaded to RAM", self.peak_spill_bytes() as f64 / GB));
+        }
+        if let (Some(peak), Some(avg)) = (self.peak_ram_bytes(), self.avg_ram_bytes()) {
+            parts.push(format!("RAM: peak {:.1} GB, avg {:.1} GB", peak as f64 / GB, avg as f64 / GB));
+        }
+        parts.push(format!("{} sample(s)", self.samples));
+        parts.join(" | ")
+    }
+
+    /// Figures for the JSON run summary
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "samples": self.samples,
+            "peak_vram_bytes": self.peak_vram_bytes(),
+            "avg_vram_bytes": self.avg_vram_bytes(),
+            "peak_model_bytes": self.peak_model,
+            "peak_ram_bytes": self.peak_ram_bytes(),
+            "avg_ram_bytes": self.avg_ram_bytes(),
+            "models": self.models,
+        })
+    }
+}
+
+/// Samples memory usage in the background until finished or dropped
+pub struct ResourceSampler {
+    client: OllamaClient,
+    usage: Arc<Mutex<ResourceUsage>>,
+    handle: JoinHandle<()>,
+}
+
+impl ResourceSampler {
+    /// Start sampling at [`DEFAULT_SAMPLE_INTERVAL`]
+    pub fn start(client: OllamaClient) -> Self {
+        Self::with_interval(client, DEFAULT_SAMPLE_INTERVAL)
+    }
+
+    pub fn with_interval(client: OllamaClient, interval: Duration) -> Self {
+        let usage = Arc::new(Mutex::new(ResourceUsage::default()));
+        let handle = {
+            let client = client.clone();
+            let usage = Arc::clone(&usage);
+            tokio::spawn(async move {
+                let started = Instant::now();
+                loop {
+                    record(&client, &usage, started).await;
+                    tokio::time::sleep(interval).await;
+                }
+            })
+        };
+        Self { client, usage, handle }
+    }
+
+    /// Stop sampling and return the totals, including one last reading
+    pub async fn finish(self) -> ResourceUsage {
+        self.handle.abort();
+        record(&self.client, &self.usage, Instant::now()).await;
+        self.usage.lock().map(|usage| usage.clone()).unwrap_or_default()
+    }
+}
+
+impl Drop for ResourceSampler {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn record(client: &OllamaClient, usage: &Mutex<ResourceUsage>, started: Instant) {
+    let Some(sample) = take_sample(client).await else {
+        return;
+    };
+    tracing::debug!(
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        vram_bytes = sample.vram_bytes,
+        model_bytes = sample.model_bytes,
+        ram_used_bytes = sample.ram_used_bytes,
+        "resource_sample"
+    );
+    if let Ok(mut usage) = usage.lock() {
+        usage.record(&sample);
+    }
+}
+
+/// Read current usage; `None` when Ollama can't be reached
+async fn take_sample(client: &OllamaClient) -> Option<ResourceSample> {
+    let running = client.list_running().await.ok()?;
+    let ram_used_bytes = tokio::task::spawn_blocking(llm_core::process::memory_used_bytes)
+        .await
+        .ok()
+        .and_then(Result::ok);
+    Some(ResourceSample {
+        vram_bytes: running.iter().map(|m| m.size_vram).sum(),
+        model_bytes: running.iter().map(|m| m.size).sum(),
+        ram_used_bytes,
+        models: running.into_iter().map(|m| m.name).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    fn sample(vram_gb: u64, model_gb: u64, ram_gb: Option<u64>) -> ResourceSample {
+        ResourceSample {
+            vram_bytes: vram_gb * GIB,
+            model_bytes: model_gb * GIB,
+            ram_used_bytes: ram_gb.map(|gb| gb * GIB),
+            models: vec!["qwen2.5-coder:14b".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_usage_peaks_and_averages() {
+        let mut usage = ResourceUsage::default();
+        assert!(usage.is_empty());
+
+        usage.record(&sample(4, 4, Some(20)));
+        usage.record(&sample(8, 10, None));
+        usage.record(&sample(6, 6, Some(24)));
+
+        assert_eq!(usage.peak_vram_bytes(), 8 * GIB);
+        assert_eq!(usage.avg_vram_bytes(), 6 * GIB);
+        assert_eq!(usage.peak_ram_bytes(), Some(24 * GIB));
+        assert_eq!(usage.avg_ram_bytes(), Some(22 * GIB));
+        assert_eq!(usage.peak_spill_bytes(), 2 * GIB);
+        assert_eq!(usage.to_json()["models"], serde_json::json!(["qwen2.5-coder:14b"]));
+        assert_eq!(
+            usage.summary(),
+            "VRAM: peak 8.0 GB, avg 6.0 GB | 2.0 GB offloaded to RAM | RAM: peak 24.0 GB, avg 22.0 GB | 3 sample(s)"
+        );
+    }
+
+    #[test]
+    fn test_usage_without_ram() {
+        let mut usage = ResourceUsage::default();
+        usage.record(&sample(2, 2, None));
+        assert_eq!(usage.peak_ram_bytes(), None);
+        assert_eq!(usage.summary(), "VRAM: peak 2.0 GB, avg 2.0 GB | 1 sample(s)");
+    }
+
+    #[tokio::test]
+    async fn test_sampler_without_ollama() {
+        // Nothing listens on port 9, so every sample is skipped
+        let sampler = ResourceSampler::with_interval(OllamaClient::new("http://127.0.0.1:9"), Duration::from_millis(10));
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(sampler.finish().await.is_empty());
+    }
+}
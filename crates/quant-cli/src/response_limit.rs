@@ -0,0 +1,97 @@
+//! Bounded accumulation of streamed model output
+//!
+//! A runaway generation (the same token repeated for megabytes) would
+//! otherwise grow the in-memory response, the conversation history, and
+//! saved sessions without limit. Callers stop reading the stream once the
+//! buffer is full, which closes the request and makes Ollama stop
+//! generating.
+
+/// Default cap on a single response (1 MiB)
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 1024 * 1024;
+
+/// Accumulates streamed content up to a byte limit
+#[derive(Debug, Default)]
+pub struct ResponseBuffer {
+    content: String,
+    /// 0 means unlimited
+    max_bytes: usize,
+    truncated: bool,
+}
+
+impl ResponseBuffer {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            content: String::new(),
+            max_bytes,
+            truncated: false,
+        }
+    }
+
+    /// Append a chunk; returns false once the limit is hit and the stream should stop
+    ///
+    /// The chunk that crosses the limit is cut at a character boundary and a
+    /// marker is appended so the truncation is visible in stored history.
+    pub fn push(&mut self, chunk: &str) -> bool {
+        if self.truncated {
+            return false;
+        }
+        if self.max_bytes == 0 || self.content.len() + chunk.len() <= self.max_bytes {
+            self.content.push_str(chunk);
+            return true;
+        }
+
+        let mut cut = self.max_bytes.saturating_sub(self.content.len());
+        while !chunk.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        self.content.push_str(&chunk[..cut]);
+        self.content.push_str(&format!(
+            "\n\n[Response truncated: exceeded the {} KB limit]",
+            self.max_bytes / 1024
+        ));
+        self.truncated = true;
+        false
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    pub fn into_string(self) -> String {
+        self.content
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_within_limit() {
+        let mut buf = ResponseBuffer::new(16);
+        assert!(buf.push("hello "));
+        assert!(buf.push("world"));
+        assert!(!buf.is_truncated());
+        assert_eq!(buf.into_string(), "hello world");
+    }
+
+    #[test]
+    fn test_push_truncates_at_char_boundary() {
+        let mut buf = ResponseBuffer::new(5);
+        assert!(buf.push("abc"));
+        // "é" is two bytes; the cut must not split it
+        assert!(!buf.push("déf"));
+        assert!(buf.is_truncated());
+        assert!(!buf.push("more"));
+        let content = buf.into_string();
+        assert!(content.starts_with("abcd\n\n[Response truncated"));
+        assert!(!content.contains("more"));
+    }
+
+    #[test]
+    fn test_zero_is_unlimited() {
+        let mut buf = ResponseBuffer::new(0);
+        assert!(buf.push(&"x".repeat(10_000)));
+        assert_eq!(buf.into_string().len(), 10_000);
+    }
+}
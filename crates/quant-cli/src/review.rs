@@ -0,0 +1,120 @@
+//! Interactive review of staged agent writes
+//!
+//! Shows each file staged by a reviewed agent run (see
+//! [`crate::tools::staging`]) as a colored diff and asks whether to write it
+//! to disk, drop it, or open it in `$EDITOR` first.
+
+use anyhow::{bail, Context, Result};
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::Command;
+
+use crate::tools::dry_run::colorize_diff;
+use crate::tools::staging::{StagedFile, StagingArea};
+
+const GREEN: &str = "\x1b[92m";
+const YELLOW: &str = "\x1b[93m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// What happened to the staged files
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ReviewSummary {
+    pub written: usize,
+    pub rejected: usize,
+}
+
+/// Review every staged file, writing the accepted ones relative to `root`
+pub fn review_staged(staging: &StagingArea, root: &Path) -> Result<ReviewSummary> {
+    let files = staging.take();
+    let mut summary = ReviewSummary::default();
+    if files.is_empty() {
+        println!("{}No staged writes to review{}", DIM, RESET);
+        return Ok(summary);
+    }
+
+    println!();
+    println!("{}Review {} staged file(s){}", BOLD, files.len(), RESET);
+    // Answer for all remaining files, once given
+    let mut rest: Option<bool> = None;
+    for (i, mut file) in files.into_iter().enumerate() {
+        let accept = match rest {
+            Some(accept) => accept,
+            None => loop {
+                println!();
+                println!("{}[{}]{} {}", BOLD, i + 1, RESET, describe(&file, root));
+                println!("{}", colorize_diff(&file.diff(root)));
+                print!("[a]ccept, [r]eject, [e]dit, accept [A]ll, or reject [R]est? ");
+                io::stdout().flush()?;
+                let mut answer = String::new();
+                io::stdin().read_line(&mut answer)?;
+                match answer.trim() {
+                    "" | "a" | "y" => break true,
+                    "r" | "n" => break false,
+                    "A" => {
+                        rest = Some(true);
+                        break true;
+                    }
+                    "R" => {
+                        rest = Some(false);
+                        break false;
+                    }
+                    "e" => match edit(&file) {
+                        Ok(content) => file.content = content,
+                        Err(e) => println!("{}{:#}{}", YELLOW, e, RESET),
+                    },
+                    _ => {}
+                }
+            },
+        };
+
+        if accept {
+            file.write()?;
+            summary.written += 1;
+        } else {
+            summary.rejected += 1;
+        }
+    }
+
+    println!();
+    println!(
+        "{}Wrote {} file(s){}, rejected {}",
+        GREEN, summary.written, RESET, summary.rejected
+    );
+    Ok(summary)
+}
+
+/// File name and whether it is new
+fn describe(file: &StagedFile, root: &Path) -> String {
+    let name = file.path.strip_prefix(root).unwrap_or(&file.path).display().to_string();
+    match file.original {
+        Some(_) => name,
+        None => format!("{} {}(new file){}", name, DIM, RESET),
+    }
+}
+
+/// Open the staged content in `$EDITOR` and return what was saved
+fn edit(file: &StagedFile) -> Result<String> {
+    let extension = file.path.extension().and_then(|e| e.to_str()).unwrap_or("txt");
+    let temp = tempfile::Builder::new()
+        .prefix("quant-review-")
+        .suffix(&format!(".{}", extension))
+        .tempfile()?;
+    std::fs::write(temp.path(), &file.content)?;
+
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let parts: Vec<&str> = editor.split_whitespace().collect();
+    let (cmd, args) = parts.split_first().context("Invalid editor command")?;
+    let status = Command::new(cmd)
+        .args(args)
+        .arg(temp.path())
+        .status()
+        .with_context(|| format!("Failed to open {}", editor))?;
+    if !status.success() {
+        bail!("Editor exited with an error; keeping the staged content");
+    }
+    Ok(std::fs::read_to_string(temp.path())?)
+}
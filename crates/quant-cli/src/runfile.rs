@@ -0,0 +1,796 @@
+//! Scripted pipelines from `.quant` files
+//!
+//! A run file is a sequence of steps (`ask` prompts, `agent` tasks, and
+//! `shell` commands) executed in order. Each step's output is stored as a
+//! variable named after the step, so later steps can use it with `{{name}}`.
+//!
+//! Two formats are accepted. YAML:
+//!
+//! ```yaml
+//! model: llama3.2
+//! vars:
+//!   version: "1.2.0"
+//! steps:
+//!   - name: changes
+//!     shell: git log --oneline v1.1.0..HEAD
+//!   - name: notes
+//!     ask: "Write release notes for {{version}} from:\n{{changes}}"
+//!   - agent: "Add this to CHANGELOG.md:\n{{notes}}"
+//!     auto: true
+//! ```
+//!
+//! Markdown, with optional YAML front matter for settings and vars. Each
+//! fenced block tagged `ask`, `agent`, or `shell` is a step named after the
+//! heading above it; extra words in the tag set flags (`auto`, `continue-on-error`):
+//!
+//! ````markdown
+//! ## changes
+//! ```shell
+//! git log --oneline v1.1.0..HEAD
+//! ```
+//! ````
+//!
+//! Shell steps receive every variable as an environment variable
+//! (`QUANT_VAR_<NAME>`). Their placeholders become references to those
+//! variables (`{{changes}}` is `${QUANT_VAR_CHANGES}`), so the shell expands
+//! values without parsing them as commands; quote them as with any variable.
+
+use anyhow::{bail, Context, Result};
+use futures::StreamExt;
+use llm_core::{ChatMessage, ChatOptions, OllamaClient};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tracing::info;
+
+use crate::agent::{AgentConfig, AgentLoop};
+use crate::config::{ModelOptions, UserConfig};
+use crate::thinking::ThinkingDisplay;
+use crate::tools::builtin::create_default_registry;
+use crate::tools::router::ToolRouter;
+use crate::tools::security::TerminalConfirmation;
+
+// ANSI colors
+const GREEN: &str = "\x1b[92m";
+const YELLOW: &str = "\x1b[93m";
+const CYAN: &str = "\x1b[96m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// Default iteration limit for agent steps
+const DEFAULT_AGENT_ITERATIONS: usize = 50;
+
+/// What a step does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepAction {
+    /// Single prompt to the model
+    Ask(String),
+    /// Autonomous agent task with tools
+    Agent(String),
+    /// Shell command run with `sh -c`
+    Shell(String),
+}
+
+impl StepAction {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            StepAction::Ask(_) => "ask",
+            StepAction::Agent(_) => "agent",
+            StepAction::Shell(_) => "shell",
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        match self {
+            StepAction::Ask(t) | StepAction::Agent(t) | StepAction::Shell(t) => t,
+        }
+    }
+}
+
+/// One step of a run file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    /// Variable name that receives the output
+    pub name: String,
+    pub action: StepAction,
+    /// Model override for ask/agent steps
+    pub model: Option<String>,
+    /// System prompt override for ask steps
+    pub system: Option<String>,
+    pub temperature: Option<f32>,
+    /// Auto-approve tool calls in agent steps
+    pub auto: bool,
+    pub max_iterations: Option<usize>,
+    /// Keep going (with empty output) when the step fails
+    pub continue_on_error: bool,
+}
+
+/// A parsed run file
+#[derive(Debug, Clone, Default)]
+pub struct RunFile {
+    pub name: Option<String>,
+    pub model: Option<String>,
+    pub system: Option<String>,
+    pub vars: BTreeMap<String, String>,
+    pub steps: Vec<Step>,
+}
+
+/// File-level settings shared by the YAML body and Markdown front matter
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawHeader {
+    name: Option<String>,
+    model: Option<String>,
+    system: Option<String>,
+    #[serde(default)]
+    vars: BTreeMap<String, serde_yaml::Value>,
+    steps: Option<Vec<RawStep>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawStep {
+    name: Option<String>,
+    ask: Option<String>,
+    agent: Option<String>,
+    shell: Option<String>,
+    model: Option<String>,
+    system: Option<String>,
+    temperature: Option<f32>,
+    #[serde(default)]
+    auto: bool,
+    max_iterations: Option<usize>,
+    #[serde(default)]
+    continue_on_error: bool,
+}
+
+impl RunFile {
+    /// Load and parse a run file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read run file: {}", path.display()))?;
+        let markdown = matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("md") | Some("markdown")
+        );
+        Self::parse(&content, markdown).with_context(|| format!("Invalid run file: {}", path.display()))
+    }
+
+    /// Parse run file content
+    ///
+    /// Content is treated as YAML when it is a mapping with a `steps` key
+    /// (unless `markdown` is set), and as Markdown otherwise.
+    pub fn parse(content: &str, markdown: bool) -> Result<Self> {
+        if !markdown {
+            if let Ok(header) = serde_yaml::from_str::<RawHeader>(content) {
+                if header.steps.is_some() {
+                    return Self::from_header(header, Vec::new());
+                }
+            }
+            // Surface YAML errors for files that were clearly meant to be YAML
+            if content.lines().any(|l| l.trim_end() == "steps:") {
+                serde_yaml::from_str::<RawHeader>(content).context("Failed to parse YAML run file")?;
+            }
+        }
+        Self::parse_markdown(content)
+    }
+
+    fn parse_markdown(content: &str) -> Result<Self> {
+        let (header, body) = match split_front_matter(content) {
+            Some((front, body)) => (
+                serde_yaml::from_str::<RawHeader>(front).context("Failed to parse front matter")?,
+                body,
+            ),
+            None => (RawHeader::default(), content),
+        };
+        if header.steps.is_some() {
+            bail!("Markdown run files define steps with fenced blocks, not `steps:` in front matter");
+        }
+
+        let mut steps = Vec::new();
+        let mut heading: Option<String> = None;
+        let mut lines = body.lines();
+        while let Some(line) = lines.next() {
+            let trimmed = line.trim_start();
+            if let Some(title) = trimmed.strip_prefix('#') {
+                heading = Some(title.trim_start_matches('#').trim().to_string());
+                continue;
+            }
+            let Some(info) = trimmed.strip_prefix("```") else { continue };
+
+            let mut words = info.split_whitespace();
+            let tag = words.next().unwrap_or("");
+            let flags: Vec<&str> = words.collect();
+
+            // Collect the block body, even for blocks we don't run
+            let mut block = Vec::new();
+            for inner in lines.by_ref() {
+                if inner.trim_start().starts_with("```") {
+                    break;
+                }
+                block.push(inner);
+            }
+            let text = block.join("\n");
+
+            let (ask, agent, shell) = match tag {
+                "ask" => (Some(text), None, None),
+                "agent" => (None, Some(text), None),
+                "shell" | "sh" | "bash" => (None, None, Some(text)),
+                _ => continue,
+            };
+            for flag in &flags {
+                if !matches!(*flag, "auto" | "continue-on-error") {
+                    bail!("Unknown step flag '{}' in ```{}", flag, info.trim());
+                }
+            }
+
+            steps.push(RawStep {
+                name: heading.take().map(|h| slugify(&h)).filter(|h| !h.is_empty()),
+                ask,
+                agent,
+                shell,
+                model: None,
+                system: None,
+                temperature: None,
+                auto: flags.contains(&"auto"),
+                max_iterations: None,
+                continue_on_error: flags.contains(&"continue-on-error"),
+            });
+        }
+
+        Self::from_header(header, steps)
+    }
+
+    fn from_header(header: RawHeader, markdown_steps: Vec<RawStep>) -> Result<Self> {
+        let raw_steps = header.steps.unwrap_or(markdown_steps);
+        if raw_steps.is_empty() {
+            bail!("Run file has no steps");
+        }
+
+        let mut vars = BTreeMap::new();
+        for (key, value) in header.vars {
+            let value = match value {
+                serde_yaml::Value::String(s) => s,
+                serde_yaml::Value::Null => String::new(),
+                other => serde_yaml::to_string(&other)?.trim_end().to_string(),
+            };
+            vars.insert(key, value);
+        }
+
+        let mut steps = Vec::with_capacity(raw_steps.len());
+        for (i, raw) in raw_steps.into_iter().enumerate() {
+            let name = raw.name.unwrap_or_else(|| format!("step_{}", i + 1));
+            if !is_var_name(&name) {
+                bail!("Step name '{}' must contain only letters, digits, and underscores", name);
+            }
+            if steps.iter().any(|s: &Step| s.name == name) {
+                bail!("Duplicate step name '{}'", name);
+            }
+            let action = match (raw.ask, raw.agent, raw.shell) {
+                (Some(t), None, None) => StepAction::Ask(t),
+                (None, Some(t), None) => StepAction::Agent(t),
+                (None, None, Some(t)) => StepAction::Shell(t),
+                _ => bail!("Step '{}' must have exactly one of `ask`, `agent`, or `shell`", name),
+            };
+            steps.push(Step {
+                name,
+                action,
+                model: raw.model,
+                system: raw.system,
+                temperature: raw.temperature,
+                auto: raw.auto,
+                max_iterations: raw.max_iterations,
+                continue_on_error: raw.continue_on_error,
+            });
+        }
+
+        Ok(Self {
+            name: header.name,
+            model: header.model,
+            system: header.system,
+            vars,
+            steps,
+        })
+    }
+
+    /// Whether any step needs the model server
+    pub fn needs_model(&self) -> bool {
+        self.steps.iter().any(|s| !matches!(s.action, StepAction::Shell(_)))
+    }
+}
+
+/// Outcome of one executed step
+#[derive(Debug, Clone)]
+pub struct StepOutcome {
+    pub name: String,
+    pub kind: &'static str,
+    pub success: bool,
+    pub output: String,
+    pub duration_ms: u64,
+}
+
+/// Executes run file steps in order
+pub struct Runner {
+    client: OllamaClient,
+    model: String,
+    working_dir: PathBuf,
+    auto: bool,
+    verbose: bool,
+}
+
+impl Runner {
+    pub fn new(client: OllamaClient, model: impl Into<String>, working_dir: PathBuf) -> Self {
+        Self {
+            client,
+            model: model.into(),
+            working_dir,
+            auto: false,
+            verbose: true,
+        }
+    }
+
+    /// Auto-approve tool calls in every agent step
+    pub fn with_auto(mut self, auto: bool) -> Self {
+        self.auto = auto;
+        self
+    }
+
+    pub fn with_verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Run every step, stopping at the first failure not marked `continue_on_error`
+    ///
+    /// `vars` starts with the file's variables (plus overrides) and ends with
+    /// every step's output.
+    pub async fn run(&self, file: &RunFile, vars: &mut BTreeMap<String, String>) -> Result<Vec<StepOutcome>> {
+        let mut outcomes = Vec::new();
+        let total = file.steps.len();
+
+        for (i, step) in file.steps.iter().enumerate() {
+            let text = match step.action {
+                StepAction::Shell(_) => interpolate_shell(step.action.text(), vars),
+                _ => interpolate(step.action.text(), vars),
+            }
+            .with_context(|| format!("Step '{}'", step.name))?;
+            if self.verbose {
+                println!();
+                println!("{}[{}/{}] {} ({}){}", BOLD, i + 1, total, step.name, step.action.kind(), RESET);
+            }
+            info!(step = %step.name, kind = step.action.kind(), "Running step");
+
+            let started = Instant::now();
+            let result = match step.action {
+                StepAction::Ask(_) => self.run_ask(file, step, &text).await,
+                StepAction::Agent(_) => self.run_agent(file, step, &text).await,
+                StepAction::Shell(_) => self.run_shell(&text, vars).await,
+            };
+            let duration_ms = started.elapsed().as_millis() as u64;
+
+            let (success, output) = match result {
+                Ok(output) => (true, output),
+                Err(e) => {
+                    if self.verbose {
+                        println!("{}Step '{}' failed:{} {:#}", YELLOW, step.name, RESET, e);
+                    }
+                    if !step.continue_on_error {
+                        outcomes.push(StepOutcome {
+                            name: step.name.clone(),
+                            kind: step.action.kind(),
+                            success: false,
+                            output: e.to_string(),
+                            duration_ms,
+                        });
+                        return Ok(outcomes);
+                    }
+                    (false, String::new())
+                }
+            };
+
+            vars.insert(step.name.clone(), output.clone());
+            outcomes.push(StepOutcome {
+                name: step.name.clone(),
+                kind: step.action.kind(),
+                success,
+                output,
+                duration_ms,
+            });
+        }
+
+        Ok(outcomes)
+    }
+
+    /// The step's model, with a model alias resolved, and the options to
+    /// sample it with: the alias's, with the step's temperature over them
+    async fn model_for(&self, file: &RunFile, step: &Step, user_config: &UserConfig) -> Result<(String, ModelOptions)> {
+        let name = step.model.as_deref().or(file.model.as_deref()).unwrap_or(&self.model);
+        let resolved = crate::model_alias::resolve_model(&self.client, &user_config.aliases.models, name).await?;
+        let mut options = resolved.options;
+        options.temperature = step.temperature.or(options.temperature);
+        Ok((resolved.model, options))
+    }
+
+    async fn run_ask(&self, file: &RunFile, step: &Step, prompt: &str) -> Result<String> {
+        let user_config = UserConfig::load().unwrap_or_default();
+        let (model, sampling) = self.model_for(file, step, &user_config).await?;
+        let mut messages = Vec::new();
+        if let Some(system) = step.system.as_ref().or(file.system.as_ref()).or(sampling.system_prompt.as_ref()) {
+            messages.push(ChatMessage::system(system.clone()));
+        }
+        messages.push(ChatMessage::user(prompt));
+        let mut options = ChatOptions {
+            temperature: sampling.temperature,
+            num_ctx: sampling.num_ctx,
+            ..Default::default()
+        };
+        sampling.apply_sampling(&mut options);
+
+        let mut stream = self.client.chat_stream(&model, &messages, Some(options)).await?;
+        let mut display = if self.verbose {
+            ThinkingDisplay::new("")
+        } else {
+            ThinkingDisplay::quiet()
+        };
+        let mut output = String::new();
+        while let Some(chunk) = stream.next().await {
+            if let Some(msg) = chunk?.message {
+                output.push_str(&display.push(&msg.thinking, &msg.content)?);
+            }
+        }
+        output.push_str(&display.finish()?);
+        if self.verbose {
+            println!();
+        }
+        Ok(output.trim().to_string())
+    }
+
+    async fn run_agent(&self, file: &RunFile, step: &Step, task: &str) -> Result<String> {
+        let user_config = UserConfig::load().unwrap_or_default();
+        let (model, sampling) = self.model_for(file, step, &user_config).await?;
+        let auto = self.auto || step.auto || user_config.agent.auto_approve;
+        let confirmation = if auto {
+            TerminalConfirmation::auto()
+        } else {
+            TerminalConfirmation::new()
+        };
+        let mut registry = create_default_registry();
+        if let Some(ref tools) = user_config.agent.tools {
+            registry.retain(tools);
+        }
+        let router = ToolRouter::new(registry, confirmation);
+
+        let mut config = AgentConfig::from_user_config(model, &user_config)
+            .with_max_iterations(step.max_iterations.unwrap_or(DEFAULT_AGENT_ITERATIONS))
+            .with_working_dir(self.working_dir.clone())
+            .with_auto_mode(auto)
+            .with_verbose(self.verbose)
+            .with_temperature(sampling.temperature)
+            .with_num_ctx(sampling.num_ctx);
+        if let Some(ref system) = sampling.system_prompt {
+            config = config.with_system_prompt(system.clone());
+        }
+        let config = config.with_sampling(sampling);
+
+        let mut agent = AgentLoop::new_with_mcp(self.client.clone(), router, config).await?;
+        let state = agent.run(task).await;
+        agent.shutdown_mcp().await;
+        let state = state?;
+
+        if let Some(error) = state.error {
+            bail!(error);
+        }
+        Ok(state.final_response.unwrap_or_default().trim().to_string())
+    }
+
+    async fn run_shell(&self, command: &str, vars: &BTreeMap<String, String>) -> Result<String> {
+        if self.verbose {
+            println!("{}$ {}{}", DIM, command.lines().next().unwrap_or(""), RESET);
+        }
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.arg("-c").arg(command).current_dir(&self.working_dir);
+        for (key, value) in vars {
+            cmd.env(format!("QUANT_VAR_{}", key.to_uppercase()), value);
+        }
+        let output = cmd.output().await.context("Failed to run shell step")?;
+
+        let stdout_text = String::from_utf8_lossy(&output.stdout).trim_end().to_string();
+        if self.verbose && !stdout_text.is_empty() {
+            println!("{}", stdout_text);
+        }
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!(
+                "Command exited with {}: {}",
+                output.status.code().map_or("signal".to_string(), |c| c.to_string()),
+                stderr.trim()
+            );
+        }
+        Ok(stdout_text)
+    }
+}
+
+/// Print a table of step outcomes
+pub fn print_summary(outcomes: &[StepOutcome], total_steps: usize) {
+    println!();
+    println!("{}Summary{}", BOLD, RESET);
+    for outcome in outcomes {
+        let (color, label) = if outcome.success { (GREEN, "ok") } else { (YELLOW, "failed") };
+        println!(
+            "  {}{:<6}{} {} {}({}, {:.1}s){}",
+            color,
+            label,
+            RESET,
+            outcome.name,
+            DIM,
+            outcome.kind,
+            outcome.duration_ms as f64 / 1000.0,
+            RESET
+        );
+    }
+    if outcomes.len() < total_steps {
+        println!("  {}{} step(s) not run{}", CYAN, total_steps - outcomes.len(), RESET);
+    }
+}
+
+/// Replace `{{name}}` and `{{env.NAME}}` placeholders
+pub fn interpolate(template: &str, vars: &BTreeMap<String, String>) -> Result<String> {
+    replace_placeholders(template, |key| match key.strip_prefix("env.") {
+        Some(env_name) => Ok(std::env::var(env_name).unwrap_or_default()),
+        None => match vars.get(key) {
+            Some(value) => Ok(value.clone()),
+            None => bail!("Undefined variable '{{{{{}}}}}'", key),
+        },
+    })
+}
+
+/// Replace placeholders in a shell command with references to the
+/// environment variables holding their values
+///
+/// Values (step outputs from the model included) never become part of the
+/// command text, so they can't inject commands.
+pub fn interpolate_shell(template: &str, vars: &BTreeMap<String, String>) -> Result<String> {
+    replace_placeholders(template, |key| {
+        let name = match key.strip_prefix("env.") {
+            Some(env_name) => env_name.to_string(),
+            None if vars.contains_key(key) => format!("QUANT_VAR_{}", key.to_uppercase()),
+            None => bail!("Undefined variable '{{{{{}}}}}'", key),
+        };
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            bail!("'{{{{{}}}}}' is not a valid environment variable name", key);
+        }
+        Ok(format!("${{{}}}", name))
+    })
+}
+
+fn replace_placeholders(template: &str, mut value: impl FnMut(&str) -> Result<String>) -> Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            bail!("Unclosed '{{{{' in template");
+        };
+        out.push_str(&value(after[..end].trim())?);
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Parse `key=value` overrides from the command line
+pub fn parse_var_overrides(pairs: &[String]) -> Result<BTreeMap<String, String>> {
+    pairs
+        .iter()
+        .map(|pair| {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("Invalid --var '{}', expected key=value", pair))?;
+            Ok((key.trim().to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+fn split_front_matter(content: &str) -> Option<(&str, &str)> {
+    let rest = content.strip_prefix("---")?.strip_prefix('\n').or_else(|| {
+        content.strip_prefix("---\r\n")
+    })?;
+    let end = rest.find("\n---")?;
+    let body = &rest[end + 4..];
+    Some((&rest[..end], body.split_once('\n').map_or("", |(_, b)| b)))
+}
+
+fn slugify(heading: &str) -> String {
+    let slug: String = heading
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    slug.split('_').filter(|s| !s.is_empty()).collect::<Vec<_>>().join("_")
+}
+
+fn is_var_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_yaml() {
+        let file = RunFile::parse(
+            r#"
+model: llama3.2
+vars:
+  version: "1.2.0"
+  count: 3
+steps:
+  - name: changes
+    shell: git log --oneline
+  - ask: "Notes for {{version}}"
+    temperature: 0.2
+  - agent: Update the changelog
+    auto: true
+"#,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(file.model.as_deref(), Some("llama3.2"));
+        assert_eq!(file.vars["count"], "3");
+        assert_eq!(file.steps.len(), 3);
+        assert_eq!(file.steps[0].action, StepAction::Shell("git log --oneline".into()));
+        assert_eq!(file.steps[1].name, "step_2");
+        assert_eq!(file.steps[1].temperature, Some(0.2));
+        assert!(file.steps[2].auto);
+        assert!(file.needs_model());
+    }
+
+    #[test]
+    fn test_parse_yaml_errors() {
+        assert!(RunFile::parse("steps:\n  - ask: a\n    shell: b\n", false).is_err());
+        assert!(RunFile::parse("steps:\n  - name: a\n    ask: x\n  - name: a\n    ask: y\n", false).is_err());
+        assert!(RunFile::parse("steps:\n  - name: bad-name\n    ask: x\n", false).is_err());
+        assert!(RunFile::parse("steps:\n  - ask: x\n    typo: 1\n", false).is_err());
+        assert!(RunFile::parse("steps: []\n", false).is_err());
+    }
+
+    #[test]
+    fn test_parse_markdown() {
+        let content = "---\nmodel: qwen2.5-coder\nvars:\n  target: src\n---\n# Pipeline\n\nIntro text.\n\n## List Files\n```shell\nls {{target}}\n```\n\n```rust\nfn ignored() {}\n```\n\n## Summary\n```ask\nSummarize:\n{{list_files}}\n```\n\n```agent auto continue-on-error\nTidy up\n```\n";
+        let file = RunFile::parse(content, false).unwrap();
+
+        assert_eq!(file.model.as_deref(), Some("qwen2.5-coder"));
+        assert_eq!(file.vars["target"], "src");
+        let names: Vec<&str> = file.steps.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["list_files", "summary", "step_3"]);
+        assert_eq!(file.steps[1].action, StepAction::Ask("Summarize:\n{{list_files}}".into()));
+        assert!(file.steps[2].auto && file.steps[2].continue_on_error);
+
+        assert!(RunFile::parse("```agent fast\nx\n```\n", true).is_err());
+        assert!(RunFile::parse("# Nothing runnable\n", true).is_err());
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let vars = BTreeMap::from([("name".to_string(), "world".to_string())]);
+        assert_eq!(interpolate("Hello {{ name }}!", &vars).unwrap(), "Hello world!");
+        assert_eq!(interpolate("no vars", &vars).unwrap(), "no vars");
+        assert!(interpolate("{{missing}}", &vars).is_err());
+        assert!(interpolate("{{name", &vars).is_err());
+        std::env::set_var("QUANT_RUNFILE_TEST", "env-value");
+        assert_eq!(interpolate("{{env.QUANT_RUNFILE_TEST}}", &vars).unwrap(), "env-value");
+    }
+
+    #[test]
+    fn test_interpolate_shell() {
+        let vars = BTreeMap::from([("notes".to_string(), "$(rm -rf ~)".to_string())]);
+        assert_eq!(interpolate_shell("echo \"{{ notes }}\"", &vars).unwrap(), "echo \"${QUANT_VAR_NOTES}\"");
+        assert_eq!(interpolate_shell("echo {{env.HOME}}", &vars).unwrap(), "echo ${HOME}");
+        assert!(interpolate_shell("{{missing}}", &vars).is_err());
+        assert!(interpolate_shell("{{env.$(id)}}", &vars).is_err());
+    }
+
+    #[test]
+    fn test_parse_var_overrides() {
+        let vars = parse_var_overrides(&["a=1".into(), "b=x=y".into()]).unwrap();
+        assert_eq!(vars["a"], "1");
+        assert_eq!(vars["b"], "x=y");
+        assert!(parse_var_overrides(&["novalue".into()]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_shell_steps_pass_variables() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let file = RunFile::parse(
+            r#"
+vars:
+  greeting: hello
+steps:
+  - name: first
+    shell: echo "{{greeting}} from shell"
+  - name: second
+    shell: printf '%s!' "$QUANT_VAR_FIRST"
+  - name: broken
+    shell: exit 3
+    continue_on_error: true
+  - name: last
+    shell: echo "[{{broken}}] {{second}}"
+"#,
+            false,
+        )
+        .unwrap();
+        assert!(!file.needs_model());
+
+        let runner = Runner::new(OllamaClient::new("http://localhost:1"), "unused", dir.path().to_path_buf())
+            .with_verbose(false);
+        let mut vars = file.vars.clone();
+        let outcomes = runner.run(&file, &mut vars).await.unwrap();
+
+        assert_eq!(outcomes.len(), 4);
+        assert_eq!(vars["first"], "hello from shell");
+        assert_eq!(vars["second"], "hello from shell!");
+        assert!(!outcomes[2].success);
+        assert_eq!(vars["last"], "[] hello from shell!");
+    }
+
+    #[tokio::test]
+    async fn test_model_output_is_not_run_by_shell_steps() {
+        use llm_core::mock::{MockOllama, MockResponse};
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let mock = MockOllama::new([MockResponse::text("v1.2 $(touch pwned) `touch pwned`; touch pwned")]).await.unwrap();
+        let file = RunFile::parse(
+            "steps:\n  - name: notes\n    ask: Write notes\n  - name: echoed\n    shell: echo \"{{notes}}\"\n  - name: unquoted\n    shell: echo {{notes}}\n",
+            false,
+        )
+        .unwrap();
+        let runner = Runner::new(mock.client(), "test-model", dir.path().to_path_buf()).with_verbose(false);
+
+        let mut vars = file.vars.clone();
+        let outcomes = runner.run(&file, &mut vars).await.unwrap();
+        assert!(outcomes.iter().all(|o| o.success));
+        assert_eq!(vars["echoed"], "v1.2 $(touch pwned) `touch pwned`; touch pwned");
+        assert_eq!(vars["unquoted"], vars["echoed"]);
+        assert!(!dir.path().join("pwned").exists());
+    }
+
+    #[tokio::test]
+    async fn test_step_temperature_reaches_agent_steps() {
+        use llm_core::mock::{MockOllama, MockResponse};
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let mock = MockOllama::new([MockResponse::text("asked"), MockResponse::text("done")]).await.unwrap();
+        let file = RunFile::parse(
+            "steps:\n  - name: a\n    ask: Say it\n    temperature: 0.3\n  - name: b\n    agent: Do it\n    temperature: 0.3\n",
+            false,
+        )
+        .unwrap();
+        let runner = Runner::new(mock.client(), "test-model", dir.path().to_path_buf()).with_verbose(false);
+
+        let outcomes = runner.run(&file, &mut BTreeMap::new()).await.unwrap();
+        assert!(outcomes.iter().all(|o| o.success));
+        let prompts = mock.prompts();
+        assert_eq!(prompts.len(), 2);
+        for prompt in &prompts {
+            assert_eq!(prompt.option("temperature").and_then(|t| t.as_f64()), Some(0.3));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_stops_on_failure() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let file = RunFile::parse("steps:\n  - shell: exit 1\n  - shell: echo never\n", false).unwrap();
+        let runner = Runner::new(OllamaClient::new("http://localhost:1"), "unused", dir.path().to_path_buf())
+            .with_verbose(false);
+
+        let outcomes = runner.run(&file, &mut BTreeMap::new()).await.unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert!(!outcomes[0].success);
+        assert!(outcomes[0].output.contains("exited with 1"));
+    }
+}
@@ -0,0 +1,665 @@
+//! Full-text search index over saved sessions and conversations
+//!
+//! Transcripts are split into chunks (one or more per message) and stored
+//! with an inverted term index in the data directory. The index is updated
+//! incrementally after agent runs, using file modification times to skip
+//! unchanged documents, and can be rebuilt from scratch when it is corrupted.
+//! It holds the same text as the transcripts, so it is encrypted along with
+//! them (see [`crate::storage`]).
+//!
+//! That is why the index is its own rather than tantivy or SQLite FTS5: both
+//! keep their data in files they manage themselves (segments, pages and
+//! journals), written in plain text where the [`Vault`] can't seal them short
+//! of SQLCipher and a bundled OpenSSL. One file sealed as a whole can be. It
+//! only covers one user's saved sessions, so it is loaded whole, searched in
+//! memory, and written back (to a temp file, then renamed) only when a
+//! document was added, changed or removed.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tracing::{debug, warn};
+
+use crate::conversation::{Conversation, ConversationStore};
+use crate::session::{Session, SessionStore};
+use crate::storage::Vault;
+
+/// Index format version; bump when the on-disk layout changes
+const INDEX_VERSION: u32 = 1;
+
+/// Maximum characters per chunk
+const CHUNK_CHARS: usize = 1000;
+
+/// Characters of context shown around a match
+const SNIPPET_CHARS: usize = 160;
+
+/// Score added for each query term that appears in a document's title
+const TITLE_BOOST: f32 = 1.0;
+
+/// Kind of indexed document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentKind {
+    Session,
+    Conversation,
+}
+
+impl std::fmt::Display for DocumentKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DocumentKind::Session => write!(f, "session"),
+            DocumentKind::Conversation => write!(f, "conversation"),
+        }
+    }
+}
+
+/// A source document (one saved file)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedDocument {
+    pub id: String,
+    pub kind: DocumentKind,
+    pub title: String,
+    pub updated_at: DateTime<Utc>,
+    /// File modification time (seconds) and size when indexed
+    modified: u64,
+    size: u64,
+}
+
+/// A searchable piece of a document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Chunk {
+    doc_id: String,
+    message_index: usize,
+    text: String,
+}
+
+/// A search result
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub id: String,
+    pub kind: DocumentKind,
+    pub title: String,
+    pub updated_at: DateTime<Utc>,
+    pub message_index: usize,
+    pub snippet: String,
+    pub score: f32,
+}
+
+/// Result of an index update
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IndexStats {
+    /// Documents in the index after the update
+    pub documents: usize,
+    /// Chunks in the index after the update
+    pub chunks: usize,
+    /// Distinct terms in the index
+    pub terms: usize,
+    /// Newly indexed documents
+    pub added: usize,
+    /// Documents re-indexed because their file changed
+    pub updated: usize,
+    /// Stale entries removed because their file was deleted
+    pub removed: usize,
+    /// Files that could not be parsed
+    pub failed: usize,
+}
+
+/// A directory of JSON documents to index
+#[derive(Debug, Clone)]
+pub struct IndexSource {
+    pub kind: DocumentKind,
+    pub dir: PathBuf,
+    /// Opens encrypted documents
+    pub vault: Vault,
+}
+
+impl IndexSource {
+    /// Default sources: saved agent sessions and REPL conversations
+    pub fn defaults() -> Result<Vec<Self>> {
+        Ok(vec![
+            Self {
+                kind: DocumentKind::Session,
+                dir: SessionStore::new()?.dir().to_path_buf(),
+                vault: Vault::default(),
+            },
+            Self {
+                kind: DocumentKind::Conversation,
+                dir: ConversationStore::new()?.dir().to_path_buf(),
+                vault: Vault::default(),
+            },
+        ])
+    }
+}
+
+/// Inverted index over session and conversation transcripts
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SearchIndex {
+    version: u32,
+    documents: HashMap<String, IndexedDocument>,
+    chunks: Vec<Chunk>,
+    /// Term -> chunk indices
+    terms: HashMap<String, Vec<u32>>,
+}
+
+impl SearchIndex {
+    /// Default index location in the data directory
+    pub fn default_path() -> Result<PathBuf> {
+        let data_dir = dirs::data_local_dir()
+            .or_else(dirs::data_dir)
+            .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+        Ok(data_dir.join("quant").join("index").join("sessions.json"))
+    }
+
+    /// Load an index from disk (empty if it doesn't exist)
+    pub fn load(path: &Path, vault: &Vault) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::new());
+        }
+        let content = fs::read_to_string(path).context("Failed to read search index")?;
+        let index: Self = serde_json::from_str(&vault.open(&content)?).context("Search index is corrupted")?;
+        if index.version != INDEX_VERSION {
+            anyhow::bail!("Search index version {} is outdated", index.version);
+        }
+        Ok(index)
+    }
+
+    /// Save the index to disk, sealed if `vault` encrypts
+    pub fn save(&self, path: &Path, vault: &Vault) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Write to a temp file first so an interrupted save can't corrupt the index
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, vault.seal(&serde_json::to_string(self)?)?.as_ref())?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn new() -> Self {
+        Self {
+            version: INDEX_VERSION,
+            ..Default::default()
+        }
+    }
+
+    /// Bring the index up to date with the files in `sources`
+    pub fn update(&mut self, sources: &[IndexSource]) -> IndexStats {
+        let mut stats = IndexStats::default();
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut changed: Vec<(IndexedDocument, Vec<Chunk>)> = Vec::new();
+
+        for source in sources {
+            let Ok(entries) = fs::read_dir(&source.dir) else { continue };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let indexed = match source.kind {
+                    DocumentKind::Session => crate::session::is_session_file(&path),
+                    DocumentKind::Conversation => path.extension().is_some_and(|e| e == "json"),
+                };
+                if !indexed {
+                    continue;
+                }
+                let Some(id) = path.file_stem().and_then(|s| s.to_str()).map(String::from) else {
+                    continue;
+                };
+                let (modified, size) = file_stamp(&path);
+                seen.insert(id.clone());
+
+                if let Some(existing) = self.documents.get(&id) {
+                    if existing.modified == modified && existing.size == size {
+                        continue;
+                    }
+                    stats.updated += 1;
+                } else {
+                    stats.added += 1;
+                }
+
+                match parse_document(source.kind, &id, &path, &source.vault) {
+                    Ok((mut doc, chunks)) => {
+                        doc.modified = modified;
+                        doc.size = size;
+                        changed.push((doc, chunks));
+                    }
+                    Err(e) => {
+                        warn!(path = %path.display(), error = %e, "Failed to index document");
+                        stats.failed += 1;
+                    }
+                }
+            }
+        }
+
+        let stale: Vec<String> = self.documents.keys().filter(|id| !seen.contains(*id)).cloned().collect();
+        stats.removed = stale.len();
+
+        if !stale.is_empty() || !changed.is_empty() {
+            let replaced: HashSet<String> = stale
+                .into_iter()
+                .chain(changed.iter().map(|(d, _)| d.id.clone()))
+                .collect();
+            self.documents.retain(|id, _| !replaced.contains(id));
+            self.chunks.retain(|c| !replaced.contains(&c.doc_id));
+
+            for (doc, chunks) in changed {
+                self.chunks.extend(chunks);
+                self.documents.insert(doc.id.clone(), doc);
+            }
+            self.rebuild_terms();
+        }
+
+        stats.documents = self.documents.len();
+        stats.chunks = self.chunks.len();
+        stats.terms = self.terms.len();
+        stats
+    }
+
+    /// Search for chunks containing all query terms, best matches first
+    ///
+    /// Returns at most one hit per document.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let query_terms: Vec<String> = tokenize(query).into_iter().collect::<HashSet<_>>().into_iter().collect();
+        if query_terms.is_empty() {
+            return Vec::new();
+        }
+
+        // Chunks that contain every term
+        let mut candidates: Option<HashSet<u32>> = None;
+        for term in &query_terms {
+            let postings: HashSet<u32> = self.terms.get(term).map(|p| p.iter().copied().collect()).unwrap_or_default();
+            candidates = Some(match candidates {
+                Some(c) => c.intersection(&postings).copied().collect(),
+                None => postings,
+            });
+        }
+
+        let total = self.chunks.len().max(1) as f32;
+        let mut best: HashMap<&str, (f32, usize)> = HashMap::new();
+        for idx in candidates.unwrap_or_default() {
+            let chunk = &self.chunks[idx as usize];
+            let lower = chunk.text.to_lowercase();
+            let score: f32 = query_terms
+                .iter()
+                .map(|t| {
+                    let tf = lower.matches(t.as_str()).count().max(1) as f32;
+                    let df = self.terms.get(t).map_or(1, |p| p.len()) as f32;
+                    (1.0 + tf.ln()) * (total / df).ln().max(0.1)
+                })
+                .sum();
+            let entry = best.entry(chunk.doc_id.as_str()).or_insert((score, idx as usize));
+            if score > entry.0 {
+                *entry = (score, idx as usize);
+            }
+        }
+
+        let mut hits: Vec<SearchHit> = best
+            .into_iter()
+            .filter_map(|(doc_id, (score, idx))| {
+                let doc = self.documents.get(doc_id)?;
+                let chunk = &self.chunks[idx];
+                let title_terms = tokenize(&doc.title);
+                let in_title = query_terms.iter().filter(|t| title_terms.contains(t)).count();
+                Some(SearchHit {
+                    id: doc.id.clone(),
+                    kind: doc.kind,
+                    title: doc.title.clone(),
+                    updated_at: doc.updated_at,
+                    message_index: chunk.message_index,
+                    snippet: snippet(&chunk.text, &query_terms[0]),
+                    score: score + TITLE_BOOST * in_title as f32,
+                })
+            })
+            .collect();
+
+        hits.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(b.updated_at.cmp(&a.updated_at))
+        });
+        hits.truncate(limit);
+        hits
+    }
+
+    fn rebuild_terms(&mut self) {
+        self.terms.clear();
+        for (i, chunk) in self.chunks.iter().enumerate() {
+            for term in tokenize(&chunk.text).into_iter().collect::<HashSet<_>>() {
+                self.terms.entry(term).or_default().push(i as u32);
+            }
+        }
+    }
+}
+
+/// Load the default index and bring it up to date, rebuilding it if it
+/// can't be loaded
+fn update_default_index() -> Result<(SearchIndex, IndexStats)> {
+    let path = SearchIndex::default_path()?;
+    let vault = Vault::from_config();
+    let mut index = SearchIndex::load(&path, &vault).unwrap_or_else(|e| {
+        warn!(error = %e, "Rebuilding search index");
+        SearchIndex::new()
+    });
+    let stats = index.update(&IndexSource::defaults()?);
+    if stats.added + stats.updated + stats.removed > 0 {
+        index.save(&path, &vault)?;
+    }
+    Ok((index, stats))
+}
+
+/// Update the default index, rebuilding it if it can't be loaded
+///
+/// Called after agent runs; failures are logged rather than surfaced.
+pub fn refresh_default_index() {
+    match update_default_index() {
+        Ok((_, stats)) => debug!(?stats, "Refreshed search index"),
+        Err(e) => warn!(error = %e, "Failed to refresh search index"),
+    }
+}
+
+/// Search saved sessions and conversations, updating the index first
+pub fn search_saved(query: &str, limit: usize) -> Result<Vec<SearchHit>> {
+    let (index, _) = update_default_index()?;
+    Ok(index.search(query, limit))
+}
+
+/// Lowercase alphanumeric terms of at least two characters
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|t| t.chars().count() >= 2)
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+fn file_stamp(path: &Path) -> (u64, u64) {
+    let Ok(meta) = fs::metadata(path) else {
+        return (0, 0);
+    };
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs());
+    (modified, meta.len())
+}
+
+fn parse_document(kind: DocumentKind, id: &str, path: &Path, vault: &Vault) -> Result<(IndexedDocument, Vec<Chunk>)> {
+    let (title, updated_at, texts): (String, DateTime<Utc>, Vec<String>) = match kind {
+        DocumentKind::Session => {
+            let session: Session = crate::session::read_session_file(path, vault)?;
+            let mut texts: Vec<String> = session.messages.iter().map(|m| m.content.clone()).collect();
+            if let Some(summary) = session.summary {
+                texts.push(summary);
+            }
+            if !session.tags.is_empty() {
+                texts.push(session.tags.join(" "));
+            }
+            (session.name, session.updated_at, texts)
+        }
+        DocumentKind::Conversation => {
+            let conversation: Conversation = crate::conversation::read_conversation_file(path, vault)?;
+            let mut texts: Vec<String> = conversation.messages.iter().map(|m| m.content.clone()).collect();
+            if let Some(summary) = conversation.summary {
+                texts.push(summary);
+            }
+            if !conversation.tags.is_empty() {
+                texts.push(conversation.tags.join(" "));
+            }
+            (conversation.title, conversation.updated_at, texts)
+        }
+    };
+
+    let mut chunks = Vec::new();
+    for (message_index, text) in texts.iter().enumerate() {
+        for piece in split_chunks(text) {
+            chunks.push(Chunk {
+                doc_id: id.to_string(),
+                message_index,
+                text: piece,
+            });
+        }
+    }
+
+    let doc = IndexedDocument {
+        id: id.to_string(),
+        kind,
+        title,
+        updated_at,
+        modified: 0,
+        size: 0,
+    };
+    Ok((doc, chunks))
+}
+
+/// Split text into chunks of at most `CHUNK_CHARS`, preferring line breaks
+fn split_chunks(text: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+
+    for line in text.lines() {
+        if !current.is_empty() && current.len() + line.len() + 1 > CHUNK_CHARS {
+            chunks.push(std::mem::take(&mut current));
+        }
+        // Hard-split lines that are longer than a chunk on their own
+        let mut rest = line;
+        while rest.len() > CHUNK_CHARS {
+            let cut = (0..=CHUNK_CHARS).rev().find(|&i| rest.is_char_boundary(i)).unwrap_or(0);
+            chunks.push(rest[..cut].to_string());
+            rest = &rest[cut..];
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(rest);
+    }
+    if !current.trim().is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Text around the first occurrence of `term`
+fn snippet(text: &str, term: &str) -> String {
+    let lower = text.to_lowercase();
+    let flat = |s: &str| s.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    // Lowercasing can change byte offsets for some scripts; fall back to the start
+    let pos = lower.find(term).filter(|_| lower.len() == text.len()).unwrap_or(0);
+    let start = (0..=pos.saturating_sub(SNIPPET_CHARS / 2)).rev().find(|&i| text.is_char_boundary(i)).unwrap_or(0);
+    let end_target = (start + SNIPPET_CHARS).min(text.len());
+    let end = (end_target..=text.len()).find(|&i| text.is_char_boundary(i)).unwrap_or(text.len());
+
+    let mut out = flat(&text[start..end]);
+    if start > 0 {
+        out.insert_str(0, "...");
+    }
+    if end < text.len() {
+        out.push_str("...");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use llm_core::{ChatMessage, ChatMessageWithTools};
+    use tempfile::TempDir;
+
+    fn write_session(dir: &Path, id: &str, messages: &[&str]) {
+        let mut session = Session::new("llama3.2", None);
+        session.id = id.to_string();
+        for m in messages {
+            session.add_message(ChatMessageWithTools::from_message(&ChatMessage::user(*m)));
+        }
+        fs::write(dir.join(format!("{}.json", id)), serde_json::to_string(&session).unwrap()).unwrap();
+    }
+
+    fn sources(dir: &Path) -> Vec<IndexSource> {
+        vec![IndexSource {
+            kind: DocumentKind::Session,
+            dir: dir.to_path_buf(),
+            vault: Vault::default(),
+        }]
+    }
+
+    #[test]
+    fn test_update_and_search() {
+        let dir = TempDir::new().unwrap();
+        write_session(dir.path(), "a", &["Fix the tokenizer cache eviction", "done"]);
+        write_session(dir.path(), "b", &["Add retry to the HTTP client", "The cache is fine"]);
+
+        let mut index = SearchIndex::new();
+        let stats = index.update(&sources(dir.path()));
+        assert_eq!(stats.documents, 2);
+        assert_eq!(stats.added, 2);
+        assert_eq!(stats.chunks, 4);
+
+        let hits = index.search("tokenizer cache", 10);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].id, "a");
+        assert!(hits[0].snippet.contains("tokenizer cache"));
+
+        assert_eq!(index.search("cache", 10).len(), 2);
+        assert!(index.search("nonexistent", 10).is_empty());
+    }
+
+    #[test]
+    fn test_incremental_update_and_stale_entries() {
+        let dir = TempDir::new().unwrap();
+        write_session(dir.path(), "a", &["alpha"]);
+        write_session(dir.path(), "b", &["beta"]);
+
+        let mut index = SearchIndex::new();
+        index.update(&sources(dir.path()));
+
+        // Unchanged files are skipped
+        let stats = index.update(&sources(dir.path()));
+        assert_eq!((stats.added, stats.updated, stats.removed), (0, 0, 0));
+
+        fs::remove_file(dir.path().join("b.json")).unwrap();
+        write_session(dir.path(), "a", &["alpha", "gamma extended"]);
+        fs::write(dir.path().join("broken.json"), "{not json").unwrap();
+
+        let stats = index.update(&sources(dir.path()));
+        assert_eq!(stats.removed, 1);
+        assert_eq!(stats.updated, 1);
+        assert_eq!(stats.failed, 1);
+        assert_eq!(stats.documents, 1);
+        assert!(index.search("beta", 10).is_empty());
+        assert_eq!(index.search("gamma", 10).len(), 1);
+    }
+
+    #[test]
+    fn test_title_matches_rank_first() {
+        let dir = TempDir::new().unwrap();
+        write_session(dir.path(), "body", &["Retry the flaky upload", "upload retried"]);
+        write_session(dir.path(), "titled", &["Retry the flaky upload"]);
+        let path = dir.path().join("titled.json");
+        let mut session: Session = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        session.set_name("Flaky upload retries");
+        fs::write(&path, serde_json::to_string(&session).unwrap()).unwrap();
+
+        let mut index = SearchIndex::new();
+        index.update(&sources(dir.path()));
+
+        let hits = index.search("flaky upload", 10);
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].id, "titled");
+    }
+
+    #[test]
+    fn test_save_load_and_corruption() {
+        let dir = TempDir::new().unwrap();
+        write_session(dir.path(), "a", &["persisted text"]);
+        let path = dir.path().join("index/sessions.json");
+
+        let mut index = SearchIndex::new();
+        index.update(&sources(dir.path()));
+        index.save(&path, &Vault::default()).unwrap();
+
+        let loaded = SearchIndex::load(&path, &Vault::default()).unwrap();
+        assert_eq!(loaded.search("persisted", 5).len(), 1);
+
+        let vault = Vault::with_key([9; 32]);
+        index.save(&path, &vault).unwrap();
+        assert!(!fs::read_to_string(&path).unwrap().contains("persisted"));
+        assert_eq!(SearchIndex::load(&path, &vault).unwrap().search("persisted", 5).len(), 1);
+
+        fs::write(&path, "garbage").unwrap();
+        assert!(SearchIndex::load(&path, &Vault::default()).is_err());
+        assert!(SearchIndex::load(&dir.path().join("missing.json"), &Vault::default()).unwrap().documents.is_empty());
+    }
+
+    #[test]
+    fn test_tokenize() {
+        // Punctuation and hyphens split terms, underscores don't
+        assert_eq!(tokenize("re-index the `search_saved()` fn!"), ["re", "index", "the", "search_saved", "fn"]);
+        // Single characters are dropped, digits are kept
+        assert_eq!(tokenize("a b c v2 42"), ["v2", "42"]);
+        // Non-ASCII letters are lowercased and count by character
+        assert_eq!(tokenize("Über ÄÖ 日本"), ["über", "äö", "日本"]);
+        assert!(tokenize("  -- !! ").is_empty());
+    }
+
+    #[test]
+    fn test_ranking() {
+        let dir = TempDir::new().unwrap();
+        write_session(dir.path(), "once", &["the parser fails on tabs"]);
+        write_session(dir.path(), "often", &["parser parser parser: the parser fails again"]);
+        write_session(dir.path(), "partial", &["the lexer fails on tabs"]);
+        write_session(dir.path(), "later", &["some setup", "then the parser fails with a long trace"]);
+
+        let mut index = SearchIndex::new();
+        index.update(&sources(dir.path()));
+
+        // Every term must match; more occurrences rank higher
+        let hits = index.search("parser fails", 10);
+        let ids: Vec<&str> = hits.iter().map(|h| h.id.as_str()).collect();
+        assert_eq!(ids[0], "often");
+        assert!(!ids.contains(&"partial"));
+        assert_eq!(hits.len(), 3);
+
+        // The hit points at the message that matched
+        assert_eq!(hits.iter().find(|h| h.id == "later").unwrap().message_index, 1);
+
+        // Documents sharing only the common term are left out
+        let hits = index.search("fails tabs", 10);
+        assert_eq!(hits.len(), 2);
+        assert!(hits.iter().all(|h| h.id == "once" || h.id == "partial"));
+
+        // Repeated query terms count once, and the limit applies last
+        assert_eq!(index.search("parser parser", 10)[0].score, index.search("parser", 10)[0].score);
+        assert_eq!(index.search("parser", 1).len(), 1);
+
+        // Nothing searchable in the query
+        assert!(index.search("a ! ?", 10).is_empty());
+    }
+
+    #[test]
+    fn test_ties_rank_newest_first() {
+        let dir = TempDir::new().unwrap();
+        for (id, days) in [("old", 3), ("new", 1), ("mid", 2)] {
+            let mut session = Session::new("llama3.2", None);
+            session.id = id.to_string();
+            session.add_message(ChatMessageWithTools::from_message(&ChatMessage::user("deploy the staging cluster")));
+            session.updated_at = Utc::now() - chrono::Duration::days(days);
+            fs::write(dir.path().join(format!("{}.json", id)), serde_json::to_string(&session).unwrap()).unwrap();
+        }
+
+        let mut index = SearchIndex::new();
+        index.update(&sources(dir.path()));
+        let ids: Vec<String> = index.search("staging", 10).into_iter().map(|h| h.id).collect();
+        assert_eq!(ids, ["new", "mid", "old"]);
+    }
+
+    #[test]
+    fn test_split_chunks() {
+        let long = "word ".repeat(500);
+        let chunks = split_chunks(&format!("intro\n{}", long));
+        assert!(chunks.len() >= 3);
+        assert!(chunks.iter().all(|c| c.len() <= CHUNK_CHARS));
+        assert!(split_chunks("").is_empty());
+    }
+}
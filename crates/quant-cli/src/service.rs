@@ -0,0 +1,232 @@
+//! Installing Ollama as a login service (`quant serve install`)
+//!
+//! Writes a launchd agent (macOS) or a systemd user unit (Linux) that runs
+//! `ollama serve` with the environment from the `[ollama]` config, starts
+//! it, and keeps it running without the menu bar app or a supervisor.
+
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// launchd label of the agent
+pub const LAUNCHD_LABEL: &str = "com.offquant.ollama";
+
+/// Name of the systemd user unit
+pub const SYSTEMD_UNIT: &str = "quant-ollama.service";
+
+/// The service file for this platform
+pub fn service_path() -> Result<PathBuf> {
+    if cfg!(target_os = "macos") {
+        let home = dirs::home_dir().context("No home directory")?;
+        Ok(home.join("Library/LaunchAgents").join(format!("{}.plist", LAUNCHD_LABEL)))
+    } else if cfg!(target_os = "linux") {
+        let config = dirs::config_dir().context("No config directory")?;
+        Ok(config.join("systemd/user").join(SYSTEMD_UNIT))
+    } else {
+        bail!("Service installation needs launchd (macOS) or systemd (Linux)")
+    }
+}
+
+/// Whether the service file exists
+pub fn is_installed() -> bool {
+    service_path().is_ok_and(|path| path.exists())
+}
+
+/// Where launchd sends Ollama's output (systemd uses the journal)
+pub fn launchd_log_path() -> Result<PathBuf> {
+    Ok(llm_core::process::ServeFiles::default_location()?.dir.join("service.log"))
+}
+
+/// Write the service file, then load and start it; returns the file
+pub fn install(env: &[(String, String)]) -> Result<PathBuf> {
+    let ollama = ollama_path()?;
+    let path = service_path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+
+    if cfg!(target_os = "macos") {
+        let log = launchd_log_path()?;
+        if let Some(parent) = log.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Reinstalling replaces the loaded agent
+        if path.exists() {
+            let _ = launchctl_unload(&path);
+        }
+        std::fs::write(&path, launchd_plist(&ollama, env, &log))?;
+        run("launchctl", &["load", "-w", &path.to_string_lossy()])?;
+    } else {
+        std::fs::write(&path, systemd_unit(&ollama, env))?;
+        run("systemctl", &["--user", "daemon-reload"])?;
+        run("systemctl", &["--user", "enable", "--now", SYSTEMD_UNIT])?;
+        // Pick up a changed unit when it was already running
+        run("systemctl", &["--user", "restart", SYSTEMD_UNIT])?;
+    }
+    Ok(path)
+}
+
+/// Stop and remove the service; false if it wasn't installed
+pub fn uninstall() -> Result<bool> {
+    let path = service_path()?;
+    if !path.exists() {
+        return Ok(false);
+    }
+
+    if cfg!(target_os = "macos") {
+        launchctl_unload(&path)?;
+        std::fs::remove_file(&path)?;
+    } else {
+        run("systemctl", &["--user", "disable", "--now", SYSTEMD_UNIT])?;
+        std::fs::remove_file(&path)?;
+        run("systemctl", &["--user", "daemon-reload"])?;
+    }
+    Ok(true)
+}
+
+fn launchctl_unload(path: &Path) -> Result<()> {
+    run("launchctl", &["unload", "-w", &path.to_string_lossy()])
+}
+
+/// Absolute path of the Ollama binary; services don't search PATH
+fn ollama_path() -> Result<PathBuf> {
+    let found = llm_core::process::find_ollama_binary()?;
+    if Path::new(&found).is_absolute() {
+        return Ok(PathBuf::from(found));
+    }
+    let output = Command::new("which").arg(&found).output().context("Failed to run which")?;
+    let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || path.is_empty() {
+        bail!("Could not find the full path of {}", found);
+    }
+    Ok(PathBuf::from(path))
+}
+
+fn run(program: &str, args: &[&str]) -> Result<()> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if !output.status.success() {
+        bail!(
+            "{} {} failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// launchd agent running `ollama serve` at login, restarted if it exits
+pub fn launchd_plist(ollama: &Path, env: &[(String, String)], log: &Path) -> String {
+    let env_entries: String = env
+        .iter()
+        .map(|(key, value)| {
+            format!(
+                "        <key>{}</key>\n        <string>{}</string>\n",
+                xml_escape(key),
+                xml_escape(value)
+            )
+        })
+        .collect();
+    let log = xml_escape(&log.to_string_lossy());
+
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<!-- Installed by `quant serve install`; remove with `quant serve uninstall` -->
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{label}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{ollama}</string>
+        <string>serve</string>
+    </array>
+    <key>EnvironmentVariables</key>
+    <dict>
+{env_entries}    </dict>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <true/>
+    <key>StandardOutPath</key>
+    <string>{log}</string>
+    <key>StandardErrorPath</key>
+    <string>{log}</string>
+</dict>
+</plist>
+"#,
+        label = LAUNCHD_LABEL,
+        ollama = xml_escape(&ollama.to_string_lossy()),
+    )
+}
+
+/// systemd user unit running `ollama serve`, restarted on failure
+pub fn systemd_unit(ollama: &Path, env: &[(String, String)]) -> String {
+    let environment: String = env
+        .iter()
+        .map(|(key, value)| format!("Environment=\"{}={}\"\n", key, systemd_escape(value)))
+        .collect();
+
+    format!(
+        "# Installed by `quant serve install`; remove with `quant serve uninstall`\n\
+         [Unit]\n\
+         Description=Ollama (installed by quant)\n\
+         After=network-online.target\n\
+         \n\
+         [Service]\n\
+         ExecStart={} serve\n\
+         {}\
+         Restart=on-failure\n\
+         RestartSec=3\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+        ollama.display(),
+        environment
+    )
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Escape a value for a double-quoted systemd `Environment=` assignment
+fn systemd_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"").replace('%', "%%")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env() -> Vec<(String, String)> {
+        vec![
+            ("OLLAMA_HOST".to_string(), "127.0.0.1:11434".to_string()),
+            ("OLLAMA_HOME".to_string(), "/Volumes/models & more/ollama".to_string()),
+        ]
+    }
+
+    #[test]
+    fn test_launchd_plist() {
+        let plist = launchd_plist(Path::new("/opt/homebrew/bin/ollama"), &env(), Path::new("/tmp/service.log"));
+        assert!(plist.contains("<string>com.offquant.ollama</string>"));
+        assert!(plist.contains("        <string>/opt/homebrew/bin/ollama</string>\n        <string>serve</string>"));
+        assert!(plist.contains("<key>OLLAMA_HOME</key>\n        <string>/Volumes/models &amp; more/ollama</string>"));
+        assert!(plist.contains("<key>KeepAlive</key>\n    <true/>"));
+    }
+
+    #[test]
+    fn test_systemd_unit() {
+        let unit = systemd_unit(Path::new("/usr/bin/ollama"), &env());
+        assert!(unit.contains("\nExecStart=/usr/bin/ollama serve\n"));
+        assert!(unit.contains("\nEnvironment=\"OLLAMA_HOST=127.0.0.1:11434\"\n"));
+        assert!(unit.contains("\nWantedBy=default.target\n"));
+    }
+}
@@ -1,15 +1,51 @@
 //! Session persistence for conversation history
 //!
 //! Saves and loads conversation sessions to allow resuming work.
+//!
+//! Each session is an append-only event log, `<id>.jsonl`, one JSON object
+//! per line:
+//!
+//! ```json
+//! {"type": "meta", "session": {"id": "...", "name": "...", "model": "...", ...}}
+//! {"type": "message", "message": {"role": "user", "content": "..."}}
+//! {"type": "snapshot", "snapshot": {"run": 1, "iteration": 1, ...}}
+//! {"type": "step", "step": {"run": 1, "iteration": 1, "kind": "response", ...}}
+//! ```
+//!
+//! Messages, snapshots and steps accumulate in order and the last `meta` line holds
+//! the current metadata. A running agent appends each message as it happens
+//! (see [`SessionLog`]), so a crash loses at most the line being written. Once
+//! a session has collected [`COMPACT_AFTER_METAS`] meta lines it is rewritten
+//! with just one. Listing sessions reads the metadata without materializing
+//! message bodies. Sessions saved as a single `<id>.json` document by older
+//! versions are still read, and converted on their next save.
+//!
+//! With `[storage] encrypt = true` each line is sealed on its own (see
+//! [`crate::storage`]), so appending works the same.
 
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
-use llm_core::ChatMessageWithTools;
+use llm_core::{ChatMessage, ChatMessageWithTools, ChatOptions, OllamaClient, Role};
+
+use crate::agent::{AgentSnapshot, FailureReport, ModelRestart, ModelSwitch, Plan, RecordedStep};
+use crate::storage::Vault;
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::PathBuf;
+use std::borrow::Cow;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tracing::{debug, info, warn};
 
+/// Extension of session event logs
+const LOG_EXTENSION: &str = "jsonl";
+
+/// Extension of sessions saved as one JSON document
+const LEGACY_EXTENSION: &str = "json";
+
+/// Meta lines a session log collects before it is rewritten
+pub const COMPACT_AFTER_METAS: usize = 16;
+
 /// Unique session identifier
 pub type SessionId = String;
 
@@ -32,6 +68,33 @@ pub struct Session {
     pub messages: Vec<ChatMessageWithTools>,
     /// Summary of what was accomplished (auto-generated)
     pub summary: Option<String>,
+    /// Keywords describing the session (auto-generated with the summary)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Plan from planning mode (kept for resume)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plan: Option<Plan>,
+    /// Switches to a fallback model after LLM errors
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub model_switches: Vec<ModelSwitch>,
+    /// Models reloaded after stalling before their first token
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub model_restarts: Vec<ModelRestart>,
+    /// REPL conversation this session was promoted from (`/task`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_conversation: Option<String>,
+    /// Agent state at each iteration, for `quant sessions inspect`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub snapshots: Vec<AgentSnapshot>,
+    /// Model responses and tool outcomes, for `quant sessions replay`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<RecordedStep>,
+    /// Why the latest agent run failed, if it did
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<FailureReport>,
+    /// Meta lines in the session's log when it was loaded
+    #[serde(skip)]
+    log_metas: usize,
 }
 
 impl Session {
@@ -49,6 +112,15 @@ impl Session {
             model: model.into(),
             messages: Vec::new(),
             summary: None,
+            tags: Vec::new(),
+            plan: None,
+            model_switches: Vec::new(),
+            model_restarts: Vec::new(),
+            source_conversation: None,
+            snapshots: Vec::new(),
+            steps: Vec::new(),
+            failure: None,
+            log_metas: 0,
         }
     }
 
@@ -70,6 +142,112 @@ impl Session {
         self.updated_at = Utc::now();
     }
 
+    /// Summarize a finished run: with a model, ask it for a one-sentence
+    /// summary and tags; without one, or if that fails, use the start of
+    /// the final response
+    pub async fn summarize(&mut self, client: &OllamaClient, model: Option<&str>, task: &str, response: &str) {
+        if let Some(model) = model {
+            let options = ChatOptions {
+                temperature: Some(0.2),
+                num_predict: Some(SUMMARY_MAX_TOKENS),
+                ..Default::default()
+            };
+            let messages = summary_request(task, response);
+            match tokio::time::timeout(SUMMARY_TIMEOUT, client.chat(model, &messages, Some(options))).await {
+                Ok(Ok(reply)) if self.set_generated_summary(&llm_core::split_thinking(&reply.message.content).content) => return,
+                Ok(Ok(_)) => debug!(model, "Summary model gave no usable summary"),
+                Ok(Err(e)) => debug!(model, error = %e, "Failed to generate session summary"),
+                Err(_) => debug!(model, "Timed out generating session summary"),
+            }
+        }
+
+        let summary: String = response.chars().take(FALLBACK_SUMMARY_CHARS).collect();
+        if summary.len() < response.len() {
+            self.set_summary(format!("{}...", summary.trim_end()));
+        } else {
+            self.set_summary(summary);
+        }
+    }
+
+    /// Use a model's reply to [`summary_request`]: JSON with `summary` and
+    /// `tags`, or failing that, its first line as the summary
+    ///
+    /// Returns false (and changes nothing) if the reply has no usable text.
+    pub fn set_generated_summary(&mut self, reply: &str) -> bool {
+        let Some((summary, tags)) = parse_summary_reply(reply) else {
+            return false;
+        };
+        self.tags = tags;
+        self.set_summary(summary);
+        true
+    }
+
+    /// Record fallback model switches; later runs continue with the last model
+    pub fn record_model_switches(&mut self, switches: &[ModelSwitch]) {
+        if let Some(last) = switches.last() {
+            self.model = last.to.clone();
+            self.model_switches.extend_from_slice(switches);
+            self.updated_at = Utc::now();
+        }
+    }
+
+    /// Record models the watchdog reloaded during a run
+    pub fn record_model_restarts(&mut self, restarts: &[ModelRestart]) {
+        if !restarts.is_empty() {
+            self.model_restarts.extend_from_slice(restarts);
+            self.updated_at = Utc::now();
+        }
+    }
+
+    /// Add an agent run's snapshots and recorded steps, numbering the run
+    /// after earlier ones
+    pub fn record_run(&mut self, snapshots: &[AgentSnapshot], steps: &[RecordedStep]) {
+        if snapshots.is_empty() {
+            return;
+        }
+        let run = self.last_run() + 1;
+        self.snapshots.extend(snapshots.iter().cloned().map(|mut snapshot| {
+            snapshot.run = run;
+            snapshot
+        }));
+        self.steps.extend(steps.iter().cloned().map(|mut step| {
+            step.run = run;
+            step
+        }));
+        self.updated_at = Utc::now();
+    }
+
+    /// Recorded steps of a run (the latest by default)
+    pub fn steps_of(&self, run: Option<usize>) -> Vec<RecordedStep> {
+        let run = run.unwrap_or_else(|| self.last_run());
+        self.steps.iter().filter(|s| s.run == run).cloned().collect()
+    }
+
+    /// Number of the latest agent run with snapshots (0 if none)
+    pub fn last_run(&self) -> usize {
+        self.snapshots.iter().map(|s| s.run).max().unwrap_or(0)
+    }
+
+    /// The snapshot for an iteration (of the latest run by default), with
+    /// the full message list the model saw
+    pub fn snapshot_at(
+        &self,
+        run: Option<usize>,
+        iteration: usize,
+    ) -> Option<(&AgentSnapshot, Vec<ChatMessageWithTools>)> {
+        let run = run.unwrap_or_else(|| self.last_run());
+        let mut messages: Vec<ChatMessageWithTools> = Vec::new();
+
+        for snapshot in self.snapshots.iter().filter(|s| s.run == run) {
+            messages.truncate(snapshot.shared_messages);
+            messages.extend(snapshot.messages.iter().cloned());
+            if snapshot.iteration == iteration {
+                return Some((snapshot, messages));
+            }
+        }
+        None
+    }
+
     /// Get message count (excluding system messages)
     pub fn message_count(&self) -> usize {
         self.messages
@@ -83,40 +261,90 @@ impl Session {
 pub struct SessionStore {
     /// Base directory for session storage
     base_dir: PathBuf,
+    /// Seals and opens session logs
+    vault: Vault,
 }
 
 impl SessionStore {
     /// Create a new session store
     pub fn new() -> Result<Self> {
+        Self::with_vault(Vault::from_config())
+    }
+
+    /// Create a session store that saves through `vault`
+    pub fn with_vault(vault: Vault) -> Result<Self> {
         let base_dir = get_sessions_dir()?;
         fs::create_dir_all(&base_dir).context("Failed to create sessions directory")?;
 
-        Ok(Self { base_dir })
+        Ok(Self { base_dir, vault })
     }
 
-    /// Save a session to disk
+    /// Save a session to disk, rewriting its log in full
     pub fn save(&self, session: &Session) -> Result<PathBuf> {
         let path = self.session_path(&session.id);
+        let tmp = self.base_dir.join(format!(".{}.{}.tmp", session.id, LOG_EXTENSION));
 
-        let json = serde_json::to_string_pretty(session)
-            .context("Failed to serialize session")?;
+        let mut out = Vec::new();
+        write_event(&mut out, &self.vault, &SessionEvent::Meta { session: Cow::Owned(SessionMeta::of(session)) })?;
+        for message in &session.messages {
+            write_event(&mut out, &self.vault, &SessionEvent::Message { message: Cow::Borrowed(message) })?;
+        }
+        for snapshot in &session.snapshots {
+            write_event(&mut out, &self.vault, &SessionEvent::Snapshot { snapshot: Cow::Borrowed(snapshot) })?;
+        }
+        for step in &session.steps {
+            write_event(&mut out, &self.vault, &SessionEvent::Step { step: Cow::Borrowed(step) })?;
+        }
+        fs::write(&tmp, out).context("Failed to write session file")?;
+        fs::rename(&tmp, &path).context("Failed to write session file")?;
 
-        fs::write(&path, json).context("Failed to write session file")?;
+        let legacy = self.legacy_path(&session.id);
+        if legacy.exists() {
+            fs::remove_file(&legacy).context("Failed to remove old session file")?;
+        }
 
         info!(session_id = %session.id, path = %path.display(), "Saved session");
         Ok(path)
     }
 
+    /// Open a session's log for appending, saving the session first if it
+    /// has no log yet
+    pub fn open_log(&self, session: &Session) -> Result<SessionLog> {
+        let path = self.session_path(&session.id);
+        let mut metas = session.log_metas;
+        if !path.exists() {
+            self.save(session)?;
+            metas = 1;
+        }
+        truncate_partial_line(&path)?;
+        let file = OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .context("Failed to open session log")?;
+        Ok(SessionLog {
+            path,
+            file,
+            vault: self.vault.clone(),
+            messages: session.messages.len(),
+            snapshots: session.snapshots.len(),
+            steps: session.steps.len(),
+            transcript: 0,
+            metas,
+        })
+    }
+
     /// Load a session by ID
     pub fn load(&self, id: &str) -> Result<Session> {
         let path = self.session_path(id);
+        let legacy = self.legacy_path(id);
 
-        if !path.exists() {
+        let session = if path.exists() {
+            read_log(&path, &self.vault)?
+        } else if legacy.exists() {
+            read_session_file(&legacy, &self.vault)?
+        } else {
             anyhow::bail!("Session not found: {}", id);
-        }
-
-        let json = fs::read_to_string(&path).context("Failed to read session file")?;
-        let session: Session = serde_json::from_str(&json).context("Failed to parse session")?;
+        };
 
         debug!(session_id = %session.id, messages = session.messages.len(), "Loaded session");
         Ok(session)
@@ -129,11 +357,16 @@ impl SessionStore {
         if let Ok(entries) = fs::read_dir(&self.base_dir) {
             for entry in entries.filter_map(|e| e.ok()) {
                 let path = entry.path();
-                if path.extension().map_or(false, |e| e == "json") {
-                    match self.load_summary(&path) {
-                        Ok(summary) => sessions.push(summary),
-                        Err(e) => warn!(path = %path.display(), error = %e, "Failed to load session summary"),
-                    }
+                if !is_session_file(&path) {
+                    continue;
+                }
+                // A log supersedes an old document left behind with the same ID
+                if path.extension().is_some_and(|e| e == LEGACY_EXTENSION) && path.with_extension(LOG_EXTENSION).exists() {
+                    continue;
+                }
+                match self.load_summary(&path) {
+                    Ok(summary) => sessions.push(summary),
+                    Err(e) => warn!(path = %path.display(), error = %e, "Failed to load session summary"),
                 }
             }
         }
@@ -146,13 +379,18 @@ impl SessionStore {
 
     /// Delete a session
     pub fn delete(&self, id: &str) -> Result<()> {
-        let path = self.session_path(id);
+        let paths: Vec<PathBuf> = [self.session_path(id), self.legacy_path(id)]
+            .into_iter()
+            .filter(|p| p.exists())
+            .collect();
 
-        if !path.exists() {
+        if paths.is_empty() {
             anyhow::bail!("Session not found: {}", id);
         }
 
-        fs::remove_file(&path).context("Failed to delete session file")?;
+        for path in paths {
+            fs::remove_file(&path).context("Failed to delete session file")?;
+        }
         info!(session_id = %id, "Deleted session");
         Ok(())
     }
@@ -160,12 +398,12 @@ impl SessionStore {
     /// Find sessions by project root
     pub fn find_by_project(&self, project_root: &PathBuf) -> Result<Vec<SessionSummary>> {
         let all = self.list()?;
-        let canonical = project_root.canonicalize().ok();
+        let canonical = crate::paths::canonicalize(project_root).ok();
 
         Ok(all
             .into_iter()
             .filter(|s| {
-                s.project_root.as_ref().and_then(|p| p.canonicalize().ok()) == canonical
+                s.project_root.as_ref().and_then(|p| crate::paths::canonicalize(p).ok()) == canonical
             })
             .collect())
     }
@@ -180,26 +418,298 @@ impl SessionStore {
         }
     }
 
+    /// Get the sessions directory path
+    pub fn dir(&self) -> &std::path::Path {
+        &self.base_dir
+    }
+
     fn session_path(&self, id: &str) -> PathBuf {
-        self.base_dir.join(format!("{}.json", id))
+        self.base_dir.join(format!("{}.{}", id, LOG_EXTENSION))
+    }
+
+    fn legacy_path(&self, id: &str) -> PathBuf {
+        self.base_dir.join(format!("{}.{}", id, LEGACY_EXTENSION))
+    }
+
+    /// Summary of a session file, without loading message bodies from logs
+    fn load_summary(&self, path: &Path) -> Result<SessionSummary> {
+        if path.extension().is_some_and(|e| e == LEGACY_EXTENSION) {
+            let session = read_session_file(path, &self.vault)?;
+            let message_count = session.message_count();
+            return Ok(SessionSummary::new(SessionMeta::of(&session), message_count));
+        }
+
+        /// Just enough of an event to count non-system messages
+        #[derive(Deserialize)]
+        struct EventHead {
+            #[serde(rename = "type")]
+            kind: String,
+            message: Option<MessageHead>,
+        }
+
+        #[derive(Deserialize)]
+        struct MessageHead {
+            role: Role,
+        }
+
+        let mut meta = None;
+        let mut message_count = 0;
+        for_each_line(path, &self.vault, |line| {
+            let head: EventHead = serde_json::from_str(line)?;
+            match head.kind.as_str() {
+                "meta" => {
+                    if let SessionEvent::Meta { session } = serde_json::from_str(line)? {
+                        meta = Some(session.into_owned());
+                    }
+                }
+                "message" if head.message.is_some_and(|m| m.role != Role::System) => message_count += 1,
+                _ => {}
+            }
+            Ok(())
+        })?;
+
+        let meta = meta.context("Session log has no metadata")?;
+        Ok(SessionSummary::new(meta, message_count))
+    }
+}
+
+/// Appends a session's messages to its log while an agent runs
+///
+/// Every event is written straight to the file, so the log is current after
+/// each message and tool result.
+pub struct SessionLog {
+    path: PathBuf,
+    file: File,
+    vault: Vault,
+    /// Session messages already in the log
+    messages: usize,
+    /// Session snapshots already in the log
+    snapshots: usize,
+    /// Session steps already in the log
+    steps: usize,
+    /// Messages of the current run's transcript already in the log
+    transcript: usize,
+    /// Meta lines in the log
+    metas: usize,
+}
+
+impl SessionLog {
+    /// Append the transcript messages not yet logged
+    pub fn append_transcript(&mut self, transcript: &[ChatMessageWithTools]) -> Result<()> {
+        for message in transcript.iter().skip(self.transcript) {
+            self.write(&SessionEvent::Message { message: Cow::Borrowed(message) })?;
+            self.transcript += 1;
+            self.messages += 1;
+        }
+        Ok(())
     }
 
-    fn load_summary(&self, path: &PathBuf) -> Result<SessionSummary> {
-        let json = fs::read_to_string(path)?;
-        let session: Session = serde_json::from_str(&json)?;
+    /// Bring the log up to date with `session` and record its metadata,
+    /// compacting the log once it has collected enough meta lines
+    pub fn finish(mut self, store: &SessionStore, session: &Session) -> Result<PathBuf> {
+        if self.metas + 1 >= COMPACT_AFTER_METAS || self.messages > session.messages.len() {
+            drop(self.file);
+            debug!(session_id = %session.id, "Compacting session log");
+            return store.save(session);
+        }
+
+        for message in session.messages.iter().skip(self.messages) {
+            self.write(&SessionEvent::Message { message: Cow::Borrowed(message) })?;
+        }
+        for snapshot in session.snapshots.iter().skip(self.snapshots) {
+            self.write(&SessionEvent::Snapshot { snapshot: Cow::Borrowed(snapshot) })?;
+        }
+        for step in session.steps.iter().skip(self.steps) {
+            self.write(&SessionEvent::Step { step: Cow::Borrowed(step) })?;
+        }
+        self.write(&SessionEvent::Meta { session: Cow::Owned(SessionMeta::of(session)) })?;
+
+        info!(session_id = %session.id, path = %self.path.display(), "Saved session");
+        Ok(self.path)
+    }
+
+    fn write(&mut self, event: &SessionEvent) -> Result<()> {
+        let mut line = Vec::new();
+        write_event(&mut line, &self.vault, event)?;
+        self.file.write_all(&line).context("Failed to append to session log")?;
+        self.file.flush()?;
+        if matches!(event, SessionEvent::Meta { .. }) {
+            self.metas += 1;
+        }
+        Ok(())
+    }
+}
+
+/// One line of a session log
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SessionEvent<'a> {
+    /// Session metadata; the last one in the log is current
+    Meta { session: Cow<'a, SessionMeta> },
+    Message { message: Cow<'a, ChatMessageWithTools> },
+    Snapshot { snapshot: Cow<'a, AgentSnapshot> },
+    Step { step: Cow<'a, RecordedStep> },
+}
 
-        let message_count = session.message_count();
-        Ok(SessionSummary {
-            id: session.id,
-            name: session.name,
+/// Everything in a [`Session`] except its messages, snapshots and steps
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SessionMeta {
+    id: SessionId,
+    name: String,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    project_root: Option<PathBuf>,
+    model: String,
+    summary: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    plan: Option<Plan>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    model_switches: Vec<ModelSwitch>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    model_restarts: Vec<ModelRestart>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source_conversation: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    failure: Option<FailureReport>,
+}
+
+impl SessionMeta {
+    fn of(session: &Session) -> Self {
+        Self {
+            id: session.id.clone(),
+            name: session.name.clone(),
             created_at: session.created_at,
             updated_at: session.updated_at,
-            project_root: session.project_root,
-            model: session.model,
-            message_count,
-            summary: session.summary,
-        })
+            project_root: session.project_root.clone(),
+            model: session.model.clone(),
+            summary: session.summary.clone(),
+            tags: session.tags.clone(),
+            plan: session.plan.clone(),
+            model_switches: session.model_switches.clone(),
+            model_restarts: session.model_restarts.clone(),
+            source_conversation: session.source_conversation.clone(),
+            failure: session.failure.clone(),
+        }
+    }
+
+    fn into_session(
+        self,
+        messages: Vec<ChatMessageWithTools>,
+        snapshots: Vec<AgentSnapshot>,
+        steps: Vec<RecordedStep>,
+        log_metas: usize,
+    ) -> Session {
+        Session {
+            id: self.id,
+            name: self.name,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            project_root: self.project_root,
+            model: self.model,
+            messages,
+            summary: self.summary,
+            tags: self.tags,
+            plan: self.plan,
+            model_switches: self.model_switches,
+            model_restarts: self.model_restarts,
+            source_conversation: self.source_conversation,
+            snapshots,
+            steps,
+            failure: self.failure,
+            log_metas,
+        }
+    }
+}
+
+fn write_event(out: &mut Vec<u8>, vault: &Vault, event: &SessionEvent) -> Result<()> {
+    let json = serde_json::to_string(event).context("Failed to serialize session")?;
+    out.extend_from_slice(vault.seal(&json)?.as_bytes());
+    out.push(b'\n');
+    Ok(())
+}
+
+/// Call `f` with each non-empty line of a log
+///
+/// Sealed lines are opened first. A malformed last line, cut short by a
+/// crash, is skipped.
+fn for_each_line(path: &Path, vault: &Vault, mut f: impl FnMut(&str) -> Result<()>) -> Result<()> {
+    let file = File::open(path).context("Failed to read session file")?;
+    let mut lines = BufReader::new(file).lines().peekable();
+    while let Some(line) = lines.next() {
+        let line = line.context("Failed to read session file")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Err(e) = vault.open(&line).and_then(|line| f(&line)) {
+            if lines.peek().is_none() {
+                warn!(path = %path.display(), error = %e, "Ignoring incomplete last line of session log");
+                break;
+            }
+            return Err(e.context("Failed to parse session"));
+        }
+    }
+    Ok(())
+}
+
+/// Drop an incomplete last line, cut short by a crash, so appends start on a new line
+fn truncate_partial_line(path: &Path) -> Result<()> {
+    let mut file = File::open(path).context("Failed to read session log")?;
+    let len = file.metadata()?.len();
+    let mut last = [b'\n'];
+    if len > 0 {
+        file.seek(SeekFrom::End(-1))?;
+        file.read_exact(&mut last)?;
+    }
+    if last[0] == b'\n' {
+        return Ok(());
+    }
+
+    let content = fs::read(path).context("Failed to read session log")?;
+    let keep = content.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+    warn!(path = %path.display(), bytes = content.len() - keep, "Dropping incomplete last line of session log");
+    let file = OpenOptions::new().write(true).open(path).context("Failed to open session log")?;
+    file.set_len(keep as u64).context("Failed to repair session log")?;
+    Ok(())
+}
+
+/// Read a session log
+fn read_log(path: &Path, vault: &Vault) -> Result<Session> {
+    let mut meta = None;
+    let mut metas = 0;
+    let mut messages = Vec::new();
+    let mut snapshots = Vec::new();
+    let mut steps = Vec::new();
+    for_each_line(path, vault, |line| {
+        match serde_json::from_str(line)? {
+            SessionEvent::Meta { session } => {
+                meta = Some(session.into_owned());
+                metas += 1;
+            }
+            SessionEvent::Message { message } => messages.push(message.into_owned()),
+            SessionEvent::Snapshot { snapshot } => snapshots.push(snapshot.into_owned()),
+            SessionEvent::Step { step } => steps.push(step.into_owned()),
+        }
+        Ok(())
+    })?;
+    let meta = meta.context("Session log has no metadata")?;
+    Ok(meta.into_session(messages, snapshots, steps, metas))
+}
+
+/// Whether a file in the sessions directory holds a session
+pub fn is_session_file(path: &Path) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    !name.starts_with('.') && path.extension().is_some_and(|e| e == LOG_EXTENSION || e == LEGACY_EXTENSION)
+}
+
+/// Read a session from a log or an older single-document file
+pub fn read_session_file(path: &Path, vault: &Vault) -> Result<Session> {
+    if path.extension().is_some_and(|e| e == LOG_EXTENSION) {
+        return read_log(path, vault);
     }
+    let json = fs::read_to_string(path).context("Failed to read session file")?;
+    serde_json::from_str(&json).context("Failed to parse session")
 }
 
 impl Default for SessionStore {
@@ -219,22 +729,39 @@ pub struct SessionSummary {
     pub model: String,
     pub message_count: usize,
     pub summary: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
 }
 
 impl SessionSummary {
+    fn new(meta: SessionMeta, message_count: usize) -> Self {
+        Self {
+            id: meta.id,
+            name: meta.name,
+            created_at: meta.created_at,
+            updated_at: meta.updated_at,
+            project_root: meta.project_root,
+            model: meta.model,
+            message_count,
+            summary: meta.summary,
+            tags: meta.tags,
+        }
+    }
+
     /// Format as a short one-line description
     pub fn short_description(&self) -> String {
         let age = format_age(&self.updated_at);
         let summary = self.summary.as_deref().unwrap_or(&self.name);
-        let truncated = if summary.len() > 50 {
-            format!("{}...", &summary[..47])
+        let truncated = if summary.chars().count() > 50 {
+            format!("{}...", summary.chars().take(47).collect::<String>())
         } else {
             summary.to_string()
         };
 
         format!(
-            "{} ({} msgs, {}, {})",
+            "{} {} ({} msgs, {}, {})",
             self.id,
+            truncated,
             self.message_count,
             self.model,
             age
@@ -242,6 +769,64 @@ impl SessionSummary {
     }
 }
 
+/// Characters of the task and final response shown to the summary model
+const SUMMARY_EXCERPT_CHARS: usize = 1500;
+
+/// Length of the summary taken from the final response when none is generated
+const FALLBACK_SUMMARY_CHARS: usize = 100;
+
+pub const SUMMARY_MAX_TOKENS: i32 = 120;
+
+/// How long to wait for the summary model before falling back
+const SUMMARY_TIMEOUT: Duration = Duration::from_secs(30);
+
+const MAX_TAGS: usize = 5;
+
+const SUMMARY_PROMPT: &str = "Summarize the agent run below: one sentence saying what was done, \
+and 3 to 5 short lowercase keywords for finding it later. \
+Reply with JSON only: {\"summary\": \"...\", \"tags\": [\"...\"]}";
+
+/// The summary and tags in a model's reply to a summary request: JSON with
+/// `summary` and `tags`, or failing that, its first line as the summary
+pub fn parse_summary_reply(reply: &str) -> Option<(String, Vec<String>)> {
+    #[derive(Deserialize)]
+    struct Generated {
+        summary: String,
+        #[serde(default)]
+        tags: Vec<String>,
+    }
+
+    let json = reply.find('{').zip(reply.rfind('}')).filter(|(start, end)| start < end);
+    let generated = json
+        .and_then(|(start, end)| serde_json::from_str::<Generated>(&reply[start..=end]).ok())
+        .or_else(|| {
+            let line = reply.lines().map(str::trim).find(|l| !l.is_empty() && !l.starts_with("```"))?;
+            Some(Generated {
+                summary: line.to_string(),
+                tags: Vec::new(),
+            })
+        })
+        .filter(|g| !g.summary.trim().is_empty())?;
+
+    let tags = generated
+        .tags
+        .iter()
+        .map(|tag| tag.trim().trim_start_matches('#').to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .take(MAX_TAGS)
+        .collect();
+    Some((generated.summary.trim().to_string(), tags))
+}
+
+/// Messages asking a model to summarize a run from its task and final response
+fn summary_request(task: &str, response: &str) -> Vec<ChatMessage> {
+    let excerpt = |text: &str| text.chars().take(SUMMARY_EXCERPT_CHARS).collect::<String>();
+    vec![
+        ChatMessage::system(SUMMARY_PROMPT),
+        ChatMessage::user(format!("Task: {}\n\nFinal response: {}", excerpt(task), excerpt(response))),
+    ]
+}
+
 /// Get the sessions directory
 fn get_sessions_dir() -> Result<PathBuf> {
     let data_dir = dirs::data_local_dir()
@@ -296,13 +881,29 @@ fn format_age(dt: &DateTime<Utc>) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use llm_core::Role;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_set_generated_summary() {
+        let mut session = Session::new("test-model", None);
+        let reply = "Sure!\n```json\n{\"summary\": \"Fixed the flaky login test.\", \"tags\": [\"Tests\", \"#auth\", \" \", \"ci\"]}\n```";
+        assert!(session.set_generated_summary(reply));
+        assert_eq!(session.summary.as_deref(), Some("Fixed the flaky login test."));
+        assert_eq!(session.tags, vec!["tests", "auth", "ci"]);
+
+        assert!(session.set_generated_summary("Added a retry to the HTTP client.\nMore text"));
+        assert_eq!(session.summary.as_deref(), Some("Added a retry to the HTTP client."));
+        assert!(session.tags.is_empty());
+
+        assert!(!session.set_generated_summary("  \n"));
+        assert_eq!(session.summary.as_deref(), Some("Added a retry to the HTTP client."));
+    }
+
     fn create_test_store() -> (SessionStore, TempDir) {
         let dir = TempDir::new().unwrap();
         let store = SessionStore {
             base_dir: dir.path().to_path_buf(),
+            vault: Vault::default(),
         };
         (store, dir)
     }
@@ -362,6 +963,105 @@ mod tests {
         assert!(store.load(&session.id).is_err());
     }
 
+    fn message(role: Role, content: &str) -> ChatMessageWithTools {
+        ChatMessageWithTools {
+            role,
+            content: content.to_string(),
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+
+    #[test]
+    fn test_session_log_appends_as_it_goes() {
+        let (store, dir) = create_test_store();
+        let mut session = Session::new("test-model", None);
+        session.add_message(message(Role::User, "earlier"));
+        store.save(&session).unwrap();
+
+        let mut log = store.open_log(&store.load(&session.id).unwrap()).unwrap();
+        let transcript = vec![message(Role::System, "system"), message(Role::User, "task")];
+        log.append_transcript(&transcript[..1]).unwrap();
+        log.append_transcript(&transcript).unwrap();
+
+        // Readable mid-run, even with a line cut short by a crash
+        let path = dir.path().join(format!("{}.jsonl", session.id));
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"type":"message","message":{"role":"assis"#).unwrap();
+        let loaded = store.load(&session.id).unwrap();
+        assert_eq!(loaded.messages.len(), 3);
+        assert_eq!(store.list().unwrap()[0].message_count, 2);
+
+        // Resuming drops the partial line; finishing appends only what is new
+        let mut log = store.open_log(&loaded).unwrap();
+        log.append_transcript(&[message(Role::Assistant, "done")]).unwrap();
+        let mut session = loaded;
+        session.add_message(message(Role::Assistant, "done"));
+        session.set_summary("Did it");
+        log.finish(&store, &session).unwrap();
+
+        let loaded = store.load(&session.id).unwrap();
+        assert_eq!(loaded.messages.len(), 4);
+        assert_eq!(loaded.summary.as_deref(), Some("Did it"));
+        let summary = &store.list().unwrap()[0];
+        assert_eq!(summary.message_count, 3);
+        assert_eq!(summary.summary.as_deref(), Some("Did it"));
+    }
+
+    #[test]
+    fn test_session_log_compaction() {
+        let (store, dir) = create_test_store();
+        let session = Session::new("test-model", None);
+        store.save(&session).unwrap();
+        for _ in 0..COMPACT_AFTER_METAS * 2 {
+            let session = store.load(&session.id).unwrap();
+            store.open_log(&session).unwrap().finish(&store, &session).unwrap();
+        }
+        let content = fs::read_to_string(dir.path().join(format!("{}.jsonl", session.id))).unwrap();
+        let metas = content.lines().filter(|l| l.starts_with(r#"{"type":"meta""#)).count();
+        assert!(metas < COMPACT_AFTER_METAS, "{} meta lines", metas);
+    }
+
+    #[test]
+    fn test_legacy_session_file() {
+        let (store, dir) = create_test_store();
+        let mut session = Session::new("test-model", None);
+        session.add_message(message(Role::User, "Hello"));
+        let legacy = dir.path().join(format!("{}.json", session.id));
+        fs::write(&legacy, serde_json::to_string(&session).unwrap()).unwrap();
+
+        assert_eq!(store.list().unwrap()[0].message_count, 1);
+        let loaded = store.load(&session.id).unwrap();
+        assert_eq!(loaded.messages.len(), 1);
+
+        store.save(&loaded).unwrap();
+        assert!(!legacy.exists());
+        assert_eq!(store.list().unwrap().len(), 1);
+        assert_eq!(store.load(&session.id).unwrap().messages[0].content, "Hello");
+    }
+
+    #[test]
+    fn test_encrypted_session_log() {
+        let (mut store, dir) = create_test_store();
+        let mut session = Session::new("test-model", None);
+        session.add_message(message(Role::User, "plain secret"));
+        store.save(&session).unwrap();
+
+        // Encryption turned on later: new lines are sealed, old ones still read
+        store.vault = Vault::with_key([3; 32]);
+        let mut log = store.open_log(&session).unwrap();
+        log.append_transcript(&[message(Role::User, "sealed secret")]).unwrap();
+        let loaded = store.load(&session.id).unwrap();
+        assert_eq!(loaded.messages.len(), 2);
+        assert_eq!(store.list().unwrap()[0].message_count, 2);
+
+        store.save(&loaded).unwrap();
+        let content = fs::read_to_string(dir.path().join(format!("{}.jsonl", session.id))).unwrap();
+        assert!(content.lines().all(|l| l.starts_with("quant-sealed:")));
+        assert!(!content.contains("secret"));
+        assert_eq!(store.load(&session.id).unwrap().messages[1].content, "sealed secret");
+    }
+
     #[test]
     fn test_message_count() {
         let mut session = Session::new("test-model", None);
@@ -391,6 +1091,82 @@ mod tests {
         assert_eq!(session.message_count(), 2);
     }
 
+    #[test]
+    fn test_short_description_truncates_by_character() {
+        let mut session = Session::new("test-model", None);
+        session.summary = Some("Übersetzung der Fehlermeldungen ins Deutsche – größtenteils erledigt".to_string());
+
+        let description = SessionSummary::new(SessionMeta::of(&session), 0).short_description();
+        assert!(description.contains("Übersetzung der Fehlermeldungen ins Deutsche – ..."));
+    }
+
+    #[test]
+    fn test_record_model_switches() {
+        let mut session = Session::new("primary", None);
+        session.record_model_switches(&[]);
+        assert_eq!(session.model, "primary");
+
+        session.record_model_switches(&[ModelSwitch {
+            iteration: 2,
+            from: "primary".to_string(),
+            to: "fallback".to_string(),
+            reason: "timeout".to_string(),
+        }]);
+        assert_eq!(session.model, "fallback");
+        assert_eq!(session.model_switches.len(), 1);
+    }
+
+    #[test]
+    fn test_snapshot_at_rebuilds_messages() {
+        let mut state = crate::agent::AgentState::new();
+        let message = |content: &str| ChatMessageWithTools {
+            role: Role::User,
+            content: content.to_string(),
+            tool_calls: None,
+            tool_call_id: None,
+        };
+        for text in ["one", "two", "three"] {
+            state.add_message(message(text));
+            state.increment_iteration();
+            state.take_snapshot();
+        }
+
+        let mut session = Session::new("test-model", None);
+        session.record_run(&state.snapshots, &state.steps);
+        session.record_run(&state.snapshots[..1], &[]);
+        assert_eq!(session.last_run(), 2);
+
+        let (snapshot, messages) = session.snapshot_at(Some(1), 2).unwrap();
+        assert_eq!(snapshot.iteration, 2);
+        let contents: Vec<&str> = messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["one", "two"]);
+
+        // Defaults to the latest run, which only reached iteration 1
+        assert!(session.snapshot_at(None, 2).is_none());
+        assert_eq!(session.snapshot_at(None, 1).unwrap().1.len(), 1);
+    }
+
+    #[test]
+    fn test_steps_saved_with_their_run() {
+        let (store, _dir) = create_test_store();
+        let mut state = crate::agent::AgentState::new();
+        state.increment_iteration();
+        state.take_snapshot();
+        state.steps.push(serde_json::from_str(r#"{"iteration": 1, "kind": "request_failed", "error": "model not found"}"#).unwrap());
+
+        let mut session = Session::new("test-model", None);
+        session.record_run(&state.snapshots, &state.steps);
+        session.record_run(&state.snapshots, &state.steps);
+        let log = store.open_log(&session).unwrap();
+        log.finish(&store, &session).unwrap();
+
+        let loaded = store.load(&session.id).unwrap();
+        assert_eq!(loaded.steps.len(), 2);
+        let steps = loaded.steps_of(None);
+        assert_eq!(steps.len(), 1);
+        assert_eq!((steps[0].run, steps[0].iteration), (2, 1));
+    }
+
     #[test]
     fn test_generate_session_id() {
         let id1 = generate_session_id();
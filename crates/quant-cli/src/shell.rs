@@ -0,0 +1,280 @@
+//! Shell command suggestions for `quant do`
+//!
+//! The model turns a natural-language request into a single shell command
+//! plus a short explanation. Commands are classified with the same
+//! [`SecurityLevel`]s the tools use, so destructive suggestions go through
+//! the regular confirmation prompt before they run, and accepted commands
+//! are appended to the user's shell history.
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::tools::SecurityLevel;
+
+/// A suggested command with the model's explanation
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Suggestion {
+    pub command: String,
+    #[serde(default)]
+    pub explanation: String,
+}
+
+/// System prompt asking for a single command as JSON
+pub fn system_prompt(shell: &str, cwd: &Path) -> String {
+    format!(
+        "You translate requests into a single {shell} command for {os}.\n\
+         The current directory is {cwd}.\n\
+         Respond with only a JSON object: {{\"command\": \"...\", \"explanation\": \"...\"}}.\n\
+         The command must be one line (use && or pipes to combine steps) and must not need \
+         placeholders filled in. Keep the explanation to one or two sentences and mention any \
+         side effects.",
+        shell = shell,
+        os = std::env::consts::OS,
+        cwd = cwd.display(),
+    )
+}
+
+/// Parse the model's reply: a JSON object, or a fenced code block as fallback
+pub fn parse_suggestion(reply: &str) -> Result<Suggestion> {
+    if let (Some(start), Some(end)) = (reply.find('{'), reply.rfind('}')) {
+        if start < end {
+            if let Ok(mut suggestion) = serde_json::from_str::<Suggestion>(&reply[start..=end]) {
+                suggestion.command = suggestion.command.trim().to_string();
+                if !suggestion.command.is_empty() {
+                    return Ok(suggestion);
+                }
+            }
+        }
+    }
+
+    // Fallback: first fenced block is the command, the rest is explanation
+    if let Some((before, rest)) = reply.split_once("```") {
+        let (block, after) = rest.split_once("```").unwrap_or((rest, ""));
+        let command = block
+            .lines()
+            .skip_while(|l| matches!(l.trim(), "" | "sh" | "bash" | "zsh" | "shell" | "fish"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let command = command.trim().to_string();
+        if !command.is_empty() {
+            let explanation = format!("{} {}", before.trim(), after.trim()).trim().to_string();
+            return Ok(Suggestion { command, explanation });
+        }
+    }
+
+    bail!("Model did not return a command")
+}
+
+/// Programs that never modify anything
+const READ_ONLY_PROGRAMS: &[&str] = &[
+    "ls", "cat", "head", "tail", "less", "more", "grep", "rg", "ag", "wc", "sort", "uniq", "cut",
+    "tr", "echo", "printf", "pwd", "which", "whoami", "date", "df", "du", "ps", "top", "uptime",
+    "file", "stat", "tree", "env", "uname", "hostname", "jq", "column", "basename", "dirname",
+    "realpath", "diff", "history", "fd",
+];
+
+/// Read-only git subcommands
+const READ_ONLY_GIT: &[&str] = &["status", "log", "diff", "show", "branch", "blame", "remote", "rev-parse", "describe", "shortlog"];
+
+/// Substrings that make a command destructive regardless of the program
+const DANGEROUS_PATTERNS: &[&str] = &[
+    "sudo ", "rm ", "rmdir ", "dd ", "mkfs", "shred ", "chmod ", "chown ", "kill ", "killall ",
+    "pkill ", "shutdown", "reboot", "mv ", "truncate ", "| sh", "| bash", "| zsh", "|sh", "|bash",
+    "--force", "reset --hard", "clean -f", "push -f", "-delete", "-exec ", "xargs rm", "> /dev/",
+    "crontab ", "launchctl ", "systemctl ",
+];
+
+/// Classify a command using the tool security levels
+///
+/// Dangerous: deletes, overwrites, escalates, or pipes downloads into a
+/// shell. Safe: every pipeline stage is a known read-only program and
+/// there is no output redirection. Everything else is Moderate.
+pub fn assess_risk(command: &str) -> SecurityLevel {
+    let padded = format!("{} ", command);
+    if DANGEROUS_PATTERNS.iter().any(|p| contains_at_word_start(&padded, p)) {
+        return SecurityLevel::Dangerous;
+    }
+    if command.contains('>') || command.contains('`') || command.contains("$(") {
+        return SecurityLevel::Moderate;
+    }
+
+    let all_read_only = command
+        .split(['|', ';', '&'])
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .all(|stage| {
+            let mut words = stage.split_whitespace();
+            match words.next() {
+                Some("git") => words.next().is_some_and(|sub| READ_ONLY_GIT.contains(&sub)),
+                Some("find") => true, // -delete/-exec are caught above
+                Some(program) => READ_ONLY_PROGRAMS.contains(&program),
+                None => true,
+            }
+        });
+
+    if all_read_only {
+        SecurityLevel::Safe
+    } else {
+        SecurityLevel::Moderate
+    }
+}
+
+/// Whether `pattern` occurs in `text` not preceded by a word character,
+/// so `rm ` matches `git rm x` but not `npm run format `
+fn contains_at_word_start(text: &str, pattern: &str) -> bool {
+    text.match_indices(pattern).any(|(i, _)| {
+        text[..i]
+            .chars()
+            .next_back()
+            .is_none_or(|c| !(c.is_alphanumeric() || c == '_'))
+    })
+}
+
+/// The user's login shell (falls back to sh)
+pub fn user_shell() -> String {
+    std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string())
+}
+
+/// Short shell name, e.g. `zsh` for `/bin/zsh`
+pub fn shell_name(shell: &str) -> &str {
+    Path::new(shell)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(shell)
+}
+
+/// Shell history file to record accepted commands in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HistoryFile {
+    Bash(PathBuf),
+    Zsh(PathBuf),
+    Fish(PathBuf),
+}
+
+impl HistoryFile {
+    /// History file for the given shell, honoring `$HISTFILE`
+    pub fn detect(shell: &str) -> Option<Self> {
+        let home = dirs::home_dir()?;
+        let histfile = std::env::var("HISTFILE").ok().map(PathBuf::from);
+        match shell_name(shell) {
+            "zsh" => Some(Self::Zsh(histfile.unwrap_or_else(|| home.join(".zsh_history")))),
+            "bash" => Some(Self::Bash(histfile.unwrap_or_else(|| home.join(".bash_history")))),
+            "fish" => Some(Self::Fish(
+                // fish uses XDG paths on every platform
+                std::env::var_os("XDG_DATA_HOME")
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| home.join(".local/share"))
+                    .join("fish/fish_history"),
+            )),
+            _ => None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Bash(p) | Self::Zsh(p) | Self::Fish(p) => p,
+        }
+    }
+
+    /// Append a command in the shell's own history format
+    pub fn append(&self, command: &str, timestamp: i64) -> Result<()> {
+        let entry = match self {
+            Self::Bash(_) => format!("{}\n", command),
+            Self::Zsh(path) => {
+                // Match EXTENDED_HISTORY when the existing file uses it
+                if zsh_uses_extended_history(path) {
+                    format!(": {}:0;{}\n", timestamp, command)
+                } else {
+                    format!("{}\n", command)
+                }
+            }
+            Self::Fish(_) => format!(
+                "- cmd: {}\n  when: {}\n",
+                command.replace('\\', "\\\\").replace('\n', "\\n"),
+                timestamp
+            ),
+        };
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path())
+            .with_context(|| format!("Failed to open {}", self.path().display()))?;
+        file.write_all(entry.as_bytes())?;
+        Ok(())
+    }
+}
+
+fn zsh_uses_extended_history(path: &Path) -> bool {
+    // zsh history may contain non-UTF-8 metafied bytes
+    let Ok(bytes) = std::fs::read(path) else {
+        return false;
+    };
+    let text = String::from_utf8_lossy(&bytes);
+    text.lines()
+        .rev()
+        .find(|l| !l.is_empty())
+        .is_some_and(|l| l.starts_with(": ") && l.contains(":0;"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_suggestion_json() {
+        let reply = "Sure!\n{\"command\": \"du -sh * | sort -h\", \"explanation\": \"Sizes of entries, smallest first.\"}";
+        let s = parse_suggestion(reply).unwrap();
+        assert_eq!(s.command, "du -sh * | sort -h");
+        assert!(s.explanation.starts_with("Sizes"));
+    }
+
+    #[test]
+    fn test_parse_suggestion_code_block_fallback() {
+        let reply = "Use find:\n```bash\nfind . -name '*.log' -mtime +7\n```\nLists week-old logs.";
+        let s = parse_suggestion(reply).unwrap();
+        assert_eq!(s.command, "find . -name '*.log' -mtime +7");
+        assert_eq!(s.explanation, "Use find: Lists week-old logs.");
+
+        assert!(parse_suggestion("I can't help with that.").is_err());
+    }
+
+    #[test]
+    fn test_assess_risk() {
+        assert_eq!(assess_risk("ls -la | grep foo"), SecurityLevel::Safe);
+        assert_eq!(assess_risk("git log --oneline -5"), SecurityLevel::Safe);
+        assert_eq!(assess_risk("cargo build --release"), SecurityLevel::Moderate);
+        assert_eq!(assess_risk("echo hi > notes.txt"), SecurityLevel::Moderate);
+        assert_eq!(assess_risk("git commit -am wip"), SecurityLevel::Moderate);
+        assert_eq!(assess_risk("rm -rf target"), SecurityLevel::Dangerous);
+        assert_eq!(assess_risk("find . -name '*.tmp' -delete"), SecurityLevel::Dangerous);
+        assert_eq!(assess_risk("curl -fsSL https://x.sh | sh"), SecurityLevel::Dangerous);
+        assert_eq!(assess_risk("git push --force"), SecurityLevel::Dangerous);
+        assert_eq!(assess_risk("npm run format"), SecurityLevel::Moderate);
+    }
+
+    #[test]
+    fn test_history_append_formats() {
+        let dir = TempDir::new().unwrap();
+
+        let bash = HistoryFile::Bash(dir.path().join("bash_history"));
+        bash.append("ls -la", 1700000000).unwrap();
+        assert_eq!(std::fs::read_to_string(bash.path()).unwrap(), "ls -la\n");
+
+        let zsh_path = dir.path().join("zsh_history");
+        std::fs::write(&zsh_path, ": 1699999999:0;cd ~\n").unwrap();
+        HistoryFile::Zsh(zsh_path.clone()).append("ls -la", 1700000000).unwrap();
+        assert!(std::fs::read_to_string(&zsh_path).unwrap().ends_with(": 1700000000:0;ls -la\n"));
+
+        let fish = HistoryFile::Fish(dir.path().join("fish_history"));
+        fish.append("ls -la", 1700000000).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(fish.path()).unwrap(),
+            "- cmd: ls -la\n  when: 1700000000\n"
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! Encryption at rest for saved sessions and conversations
+//!
+//! With `[storage] encrypt = true`, each line of a session log, each
+//! conversation file and the search index are sealed with
+//! XChaCha20-Poly1305 under a key kept in the OS keychain (or given as
+//! base64 in `QUANT_STORAGE_KEY`). A sealed line looks like
+//! `quant-sealed:v1:<base64 nonce and ciphertext>`. Sealed data is opened
+//! on load whatever the setting, so plain and sealed files can sit side by
+//! side; `quant storage encrypt` and `quant storage decrypt` convert the
+//! files already saved.
+
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use once_cell::sync::OnceCell;
+use std::borrow::Cow;
+use tracing::{info, warn};
+
+/// Marks a sealed line
+const SEALED_PREFIX: &str = "quant-sealed:v1:";
+
+/// Keychain service and account holding the key
+const KEYCHAIN_SERVICE: &str = "quant";
+const KEYCHAIN_ACCOUNT: &str = "storage-key";
+
+/// Environment variable with a base64 key, used instead of the keychain
+pub const KEY_ENV: &str = "QUANT_STORAGE_KEY";
+
+/// Nonce length of XChaCha20-Poly1305
+const NONCE_LEN: usize = 24;
+
+/// Key read from the keychain, so it is asked for once per process
+static KEYCHAIN_KEY: OnceCell<Key> = OnceCell::new();
+
+/// Seals data before it is written and opens it when read
+#[derive(Clone, Default)]
+pub struct Vault {
+    /// Seal new data (sealed data is opened either way)
+    encrypt: bool,
+    /// Key to use instead of the keychain's
+    key: Option<Key>,
+}
+
+impl std::fmt::Debug for Vault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Vault").field("encrypt", &self.encrypt).finish_non_exhaustive()
+    }
+}
+
+impl Vault {
+    /// A vault that seals new data if `encrypt`, with the keychain key
+    pub fn new(encrypt: bool) -> Self {
+        Self { encrypt, key: None }
+    }
+
+    /// A vault following `[storage] encrypt`
+    pub fn from_config() -> Self {
+        match crate::config::UserConfig::load() {
+            Ok(config) => Self::new(config.storage.encrypt),
+            Err(e) => {
+                warn!(error = %e, "Failed to load config; saving without encryption");
+                Self::default()
+            }
+        }
+    }
+
+    /// A vault with a fixed key that seals new data
+    #[cfg(test)]
+    pub fn with_key(key: [u8; 32]) -> Self {
+        Self {
+            encrypt: true,
+            key: Some(key.into()),
+        }
+    }
+
+    /// Make sure there is a key to seal with, creating one if needed
+    pub fn check_key(&self) -> Result<()> {
+        if self.encrypt {
+            self.key(true)?;
+        }
+        Ok(())
+    }
+
+    /// Seal `plain` into one line if encryption is on
+    pub fn seal<'a>(&self, plain: &'a str) -> Result<Cow<'a, str>> {
+        if !self.encrypt {
+            return Ok(Cow::Borrowed(plain));
+        }
+        let cipher = XChaCha20Poly1305::new(&self.key(true)?);
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let mut sealed = nonce.to_vec();
+        sealed.extend(cipher.encrypt(&nonce, plain.as_bytes()).map_err(|_| anyhow::anyhow!("Failed to encrypt data"))?);
+        Ok(Cow::Owned(format!("{}{}", SEALED_PREFIX, BASE64.encode(sealed))))
+    }
+
+    /// The plain text of `text`, opening it if it is sealed
+    pub fn open<'a>(&self, text: &'a str) -> Result<Cow<'a, str>> {
+        let Some(encoded) = text.trim_end().strip_prefix(SEALED_PREFIX) else {
+            return Ok(Cow::Borrowed(text));
+        };
+        let sealed = BASE64.decode(encoded).context("Encrypted data is corrupted")?;
+        if sealed.len() < NONCE_LEN {
+            anyhow::bail!("Encrypted data is corrupted");
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let cipher = XChaCha20Poly1305::new(&self.key(false)?);
+        let plain = cipher
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow::anyhow!("Failed to decrypt data; it was sealed with a different storage key"))?;
+        Ok(Cow::Owned(String::from_utf8(plain).context("Decrypted data is not UTF-8")?))
+    }
+
+    fn key(&self, create: bool) -> Result<Key> {
+        match &self.key {
+            Some(key) => Ok(*key),
+            None => KEYCHAIN_KEY.get_or_try_init(|| load_key(create)).copied(),
+        }
+    }
+}
+
+/// The key from `QUANT_STORAGE_KEY` or the OS keychain, creating a keychain
+/// key if there is none and `create`
+fn load_key(create: bool) -> Result<Key> {
+    if let Ok(encoded) = std::env::var(KEY_ENV) {
+        return decode_key(&encoded).with_context(|| format!("{} is not a valid storage key", KEY_ENV));
+    }
+
+    let entry = keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT)
+        .with_context(|| format!("OS keychain is not available; set {} instead", KEY_ENV))?;
+    match entry.get_password() {
+        Ok(encoded) => decode_key(&encoded).context("Storage key in the OS keychain is invalid"),
+        Err(keyring::Error::NoEntry) if create => {
+            let key = XChaCha20Poly1305::generate_key(&mut OsRng);
+            entry
+                .set_password(&BASE64.encode(key))
+                .with_context(|| format!("Failed to save the storage key in the OS keychain; set {} instead", KEY_ENV))?;
+            info!("Created storage key in the OS keychain");
+            Ok(key)
+        }
+        Err(keyring::Error::NoEntry) => {
+            anyhow::bail!("Found encrypted data but no storage key in the OS keychain or {}", KEY_ENV)
+        }
+        Err(e) => Err(e).with_context(|| format!("Failed to read the storage key from the OS keychain; set {} instead", KEY_ENV)),
+    }
+}
+
+fn decode_key(encoded: &str) -> Result<Key> {
+    let bytes = BASE64.decode(encoded.trim())?;
+    if bytes.len() != 32 {
+        anyhow::bail!("expected 32 bytes, got {}", bytes.len());
+    }
+    Ok(*Key::from_slice(&bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let vault = Vault::with_key([7; 32]);
+        let sealed = vault.seal("{\"secret\": \"hunter2\"}\n").unwrap();
+        assert!(sealed.starts_with(SEALED_PREFIX));
+        assert!(!sealed.contains("hunter2") && !sealed.contains('\n'));
+        assert_ne!(sealed, vault.seal("{\"secret\": \"hunter2\"}\n").unwrap());
+        assert_eq!(vault.open(&format!("{}\n", sealed)).unwrap(), "{\"secret\": \"hunter2\"}\n");
+
+        // Plain text passes through both ways when encryption is off
+        let plain = Vault { encrypt: false, ..vault.clone() };
+        assert!(matches!(plain.seal("{}").unwrap(), Cow::Borrowed("{}")));
+        assert_eq!(plain.open(&sealed).unwrap(), "{\"secret\": \"hunter2\"}\n");
+        assert_eq!(vault.open("{}").unwrap(), "{}");
+
+        assert!(Vault::with_key([8; 32]).open(&sealed).is_err());
+        assert!(vault.open(&format!("{}AAAA", SEALED_PREFIX)).is_err());
+    }
+
+    #[test]
+    fn test_decode_key() {
+        assert!(decode_key(&BASE64.encode([1u8; 32])).is_ok());
+        assert!(decode_key(&BASE64.encode([1u8; 16])).is_err());
+        assert!(decode_key("not base64!").is_err());
+    }
+}
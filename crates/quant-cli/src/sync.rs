@@ -0,0 +1,933 @@
+//! Sync sessions, conversations and trusted commands between machines
+//!
+//! `quant sync --peer <host>` runs `quant sync --serve` on the peer over SSH
+//! (a tailscale hostname works; `sync.ssh = "tailscale ssh"` uses Tailscale
+//! SSH instead) and the two sides exchange JSON lines:
+//!
+//! 1. Both sides list their files with a SHA-256 of each.
+//! 2. Files that differ are checked against the hash recorded at the last
+//!    sync with that peer. A file only one side changed is copied over. A
+//!    file both sides changed goes to the newer version (last writer wins),
+//!    and the older one is kept next to it as a conflict copy.
+//! 3. Changed files travel as line deltas against the receiver's copy, so a
+//!    session that grew by a few messages sends only the new lines.
+//!
+//! Trusted commands (`.quant/approvals.toml`) are synced for the current
+//! project, found on the peer at the same path relative to the home
+//! directory. Deletions are not synced: a file removed on one machine is
+//! copied back from the other.
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, Lines};
+use tokio::process::Command;
+use tracing::{debug, warn};
+
+use crate::conversation::ConversationStore;
+use crate::session::SessionStore;
+use crate::tools::approvals::APPROVALS_FILE;
+
+/// Bump when the messages change incompatibly
+const PROTOCOL_VERSION: u32 = 1;
+
+/// Base positions tried when looking for a run of matching lines
+const MAX_DELTA_CANDIDATES: usize = 8;
+
+/// What a synced file is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Sessions,
+    Conversations,
+    Approvals,
+}
+
+impl Category {
+    const ALL: [Category; 3] = [Category::Sessions, Category::Conversations, Category::Approvals];
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.to_string() == name)
+    }
+
+    /// Whether a file in the category's directory is synced
+    fn syncs(&self, name: &str) -> bool {
+        match self {
+            Category::Approvals => name == APPROVALS_FILE,
+            _ => !name.starts_with('.') && !name.ends_with(".tmp"),
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Category::Sessions => write!(f, "sessions"),
+            Category::Conversations => write!(f, "conversations"),
+            Category::Approvals => write!(f, "approvals"),
+        }
+    }
+}
+
+/// A synced file, named `<category>/<file name>`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileEntry {
+    pub path: String,
+    pub size: u64,
+    /// Modification time, milliseconds since the epoch
+    pub modified: u64,
+    /// SHA-256 of the content, hex
+    pub hash: String,
+}
+
+/// Where each category lives on one machine
+#[derive(Debug, Clone, Default)]
+pub struct Roots {
+    dirs: Vec<(Category, PathBuf)>,
+}
+
+impl Roots {
+    /// This machine's directories; `project` is the project directory
+    /// relative to the home directory, for its trusted commands
+    pub fn local(project: Option<&str>) -> Result<Self> {
+        let mut dirs = vec![
+            (Category::Sessions, SessionStore::new()?.dir().to_path_buf()),
+            (Category::Conversations, ConversationStore::new()?.dir().to_path_buf()),
+        ];
+        if let (Some(project), Some(home)) = (project, dirs::home_dir()) {
+            let project = home.join(project);
+            if project.is_dir() {
+                dirs.push((Category::Approvals, project.join(".quant")));
+            }
+        }
+        Ok(Self::new(dirs))
+    }
+
+    pub fn new(dirs: Vec<(Category, PathBuf)>) -> Self {
+        Self { dirs }
+    }
+
+    fn dir(&self, category: Category) -> Option<&Path> {
+        self.dirs.iter().find(|(c, _)| *c == category).map(|(_, d)| d.as_path())
+    }
+
+    /// Every synced file, sorted by path
+    pub fn list(&self) -> Result<Vec<FileEntry>> {
+        let mut files = Vec::new();
+        for (category, dir) in &self.dirs {
+            let Ok(entries) = fs::read_dir(dir) else { continue };
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if !category.syncs(&name) || !entry.file_type().is_ok_and(|t| t.is_file()) {
+                    continue;
+                }
+                let content = fs::read(entry.path()).with_context(|| format!("Failed to read {}", entry.path().display()))?;
+                files.push(FileEntry {
+                    path: format!("{}/{}", category, name),
+                    size: content.len() as u64,
+                    modified: modified_ms(&entry.path()),
+                    hash: hash_content(&content),
+                });
+            }
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(files)
+    }
+
+    /// The local file for a synced path, refusing anything outside the roots
+    fn resolve(&self, path: &str) -> Result<PathBuf> {
+        let (category, name) = path.split_once('/').context("Malformed sync path")?;
+        let category = Category::from_name(category).with_context(|| format!("Unknown sync category: {}", category))?;
+        if name.contains(['/', '\\']) || name == ".." || !category.syncs(name) {
+            anyhow::bail!("Refusing to sync {}", path);
+        }
+        let dir = self.dir(category).with_context(|| format!("Nothing to sync {} with here", category))?;
+        Ok(dir.join(name))
+    }
+}
+
+/// Include/exclude globs over sync paths, e.g. `conversations` or
+/// `sessions/18f*`
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    include: Vec<glob::Pattern>,
+    exclude: Vec<glob::Pattern>,
+}
+
+impl Filter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        let parse = |patterns: &[String]| -> Result<Vec<glob::Pattern>> {
+            patterns
+                .iter()
+                .map(|p| glob::Pattern::new(p).with_context(|| format!("Invalid sync filter: {}", p)))
+                .collect()
+        };
+        Ok(Self {
+            include: parse(include)?,
+            exclude: parse(exclude)?,
+        })
+    }
+
+    /// Whether a path is synced: it matches an include (if there are any)
+    /// and no exclude. A bare category name matches everything in it.
+    pub fn allows(&self, path: &str) -> bool {
+        let matches = |pattern: &glob::Pattern| {
+            pattern.matches(path) || path.split_once('/').is_some_and(|(category, _)| pattern.as_str() == category)
+        };
+        (self.include.is_empty() || self.include.iter().any(matches)) && !self.exclude.iter().any(matches)
+    }
+}
+
+/// Hashes of each file at the last sync with a peer
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SyncState {
+    files: BTreeMap<String, String>,
+}
+
+impl SyncState {
+    fn path(peer: &str) -> Result<PathBuf> {
+        let data_dir = dirs::data_local_dir()
+            .or_else(dirs::data_dir)
+            .ok_or_else(|| anyhow::anyhow!("Could not find data directory"))?;
+        let name: String = peer
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || "-_.".contains(c) { c } else { '_' })
+            .collect();
+        Ok(data_dir.join("quant").join("sync").join(format!("{}.json", name)))
+    }
+
+    fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// What to do with a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Pull,
+    Push,
+    /// Both sides changed; the peer's newer copy wins and ours is kept as
+    /// a conflict copy
+    PullConflict,
+    /// Both sides changed; our newer copy wins and the peer's is kept as a
+    /// conflict copy
+    PushConflict,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Pull => write!(f, "pull"),
+            Action::Push => write!(f, "push"),
+            Action::PullConflict => write!(f, "pull (conflict)"),
+            Action::PushConflict => write!(f, "push (conflict)"),
+        }
+    }
+}
+
+/// Decide what to do with each file that differs between the two sides
+fn plan(local: &[FileEntry], remote: &[FileEntry], base: &SyncState, filter: &Filter) -> Vec<(String, Action)> {
+    let local: HashMap<&str, &FileEntry> = local.iter().map(|f| (f.path.as_str(), f)).collect();
+    let remote: HashMap<&str, &FileEntry> = remote.iter().map(|f| (f.path.as_str(), f)).collect();
+    let mut paths: Vec<&str> = local.keys().chain(remote.keys()).copied().collect();
+    paths.sort_unstable();
+    paths.dedup();
+
+    let mut actions = Vec::new();
+    for path in paths.into_iter().filter(|p| filter.allows(p)) {
+        let action = match (local.get(path), remote.get(path)) {
+            (Some(_), None) => Action::Push,
+            (None, Some(_)) => Action::Pull,
+            (Some(l), Some(r)) if l.hash == r.hash => continue,
+            (Some(l), Some(r)) => match base.files.get(path) {
+                Some(synced) if *synced == l.hash => Action::Pull,
+                Some(synced) if *synced == r.hash => Action::Push,
+                _ if r.modified > l.modified => Action::PullConflict,
+                _ => Action::PushConflict,
+            },
+            (None, None) => continue,
+        };
+        actions.push((path.to_string(), action));
+    }
+    actions
+}
+
+/// Options for a sync run
+#[derive(Debug, Clone)]
+pub struct SyncOptions {
+    /// SSH command, split on whitespace (e.g. "ssh" or "tailscale ssh")
+    pub ssh: String,
+    /// quant on the peer
+    pub remote_command: String,
+    pub filter: Filter,
+    /// Only report what would be transferred
+    pub dry_run: bool,
+}
+
+/// Result of a sync run
+#[derive(Debug, Default, Serialize)]
+pub struct SyncReport {
+    pub peer_host: String,
+    /// Files and what was done with them
+    pub actions: Vec<(String, String)>,
+    pub pulled: usize,
+    pub pushed: usize,
+    pub conflicts: usize,
+    /// Files both sides already had
+    pub unchanged: usize,
+    /// Delta bytes sent and received
+    pub bytes_sent: usize,
+    pub bytes_received: usize,
+    /// Files that failed to transfer, with the error
+    pub failed: Vec<(String, String)>,
+}
+
+/// Sync with a peer over SSH
+pub async fn sync_with_peer(peer: &str, options: &SyncOptions) -> Result<SyncReport> {
+    let mut ssh = options.ssh.split_whitespace();
+    let program = ssh.next().context("sync.ssh is empty")?;
+    let mut child = Command::new(program)
+        .args(ssh)
+        .arg(peer)
+        .arg(format!("{} sync --serve", options.remote_command))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to run {}", options.ssh))?;
+
+    let reader = child.stdout.take().context("No stdout from ssh")?;
+    let writer = child.stdin.take().context("No stdin to ssh")?;
+
+    let project = current_project();
+    let roots = Roots::local(project.as_deref())?;
+    let state_path = SyncState::path(peer)?;
+    let mut state = SyncState::load(&state_path);
+
+    let report = run_client(reader, writer, &roots, project, &mut state, options).await?;
+    if !options.dry_run {
+        state.save(&state_path)?;
+    }
+    let _ = child.wait().await;
+    Ok(report)
+}
+
+/// The current project's directory relative to the home directory
+fn current_project() -> Option<String> {
+    let cwd = std::env::current_dir().ok()?;
+    let root = crate::project::find_project_root(&cwd)?;
+    let relative = root.strip_prefix(dirs::home_dir()?).ok()?;
+    Some(relative.to_string_lossy().to_string())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Request {
+    Hello { version: u32, project: Option<String> },
+    List,
+    /// Line hashes of the peer's copy, to compute a delta against
+    Lines { path: String },
+    Get { path: String, base: Option<Vec<u32>> },
+    Put {
+        path: String,
+        delta: Vec<DeltaOp>,
+        hash: String,
+        modified: u64,
+        /// Keep the peer's current copy as a conflict copy first
+        conflict_copy: bool,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Response {
+    Hello { version: u32, host: String },
+    List { files: Vec<FileEntry> },
+    Lines { hashes: Option<Vec<u32>> },
+    File { delta: Vec<DeltaOp> },
+    Done,
+    Error { message: String },
+}
+
+/// Drive a sync over an established connection
+async fn run_client(
+    reader: impl AsyncRead + Unpin,
+    writer: impl AsyncWrite + Unpin,
+    roots: &Roots,
+    project: Option<String>,
+    state: &mut SyncState,
+    options: &SyncOptions,
+) -> Result<SyncReport> {
+    let mut conn = Connection::new(reader, writer);
+    let mut report = SyncReport::default();
+
+    let Response::Hello { version, host } = conn.call(&Request::Hello { version: PROTOCOL_VERSION, project }).await? else {
+        anyhow::bail!("Unexpected reply to hello");
+    };
+    if version != PROTOCOL_VERSION {
+        anyhow::bail!("Peer speaks sync protocol {} (this quant: {}); update quant on both machines", version, PROTOCOL_VERSION);
+    }
+    report.peer_host = host;
+
+    let Response::List { files: remote } = conn.call(&Request::List).await? else {
+        anyhow::bail!("Unexpected reply to list");
+    };
+    let local = roots.list()?;
+
+    // Files that already match are in sync as of now
+    let remote_hashes: HashMap<&str, &str> = remote.iter().map(|f| (f.path.as_str(), f.hash.as_str())).collect();
+    for file in local.iter().filter(|f| options.filter.allows(&f.path)) {
+        if remote_hashes.get(file.path.as_str()) == Some(&file.hash.as_str()) {
+            report.unchanged += 1;
+            state.files.insert(file.path.clone(), file.hash.clone());
+        }
+    }
+
+    let local_by_path: HashMap<&str, &FileEntry> = local.iter().map(|f| (f.path.as_str(), f)).collect();
+    let remote_by_path: HashMap<&str, &FileEntry> = remote.iter().map(|f| (f.path.as_str(), f)).collect();
+
+    for (path, action) in plan(&local, &remote, state, &options.filter) {
+        report.actions.push((path.clone(), action.to_string()));
+        if options.dry_run {
+            continue;
+        }
+
+        let result = match action {
+            Action::Pull | Action::PullConflict => {
+                let entry = remote_by_path[path.as_str()];
+                pull(&mut conn, roots, entry, action == Action::PullConflict, &report.peer_host, &mut report.bytes_received).await
+            }
+            Action::Push | Action::PushConflict => {
+                let entry = local_by_path[path.as_str()];
+                push(&mut conn, roots, entry, action == Action::PushConflict, &mut report.bytes_sent).await
+            }
+        };
+
+        match result {
+            Ok(hash) => {
+                state.files.insert(path, hash);
+                match action {
+                    Action::Pull => report.pulled += 1,
+                    Action::Push => report.pushed += 1,
+                    Action::PullConflict => {
+                        report.pulled += 1;
+                        report.conflicts += 1;
+                    }
+                    Action::PushConflict => {
+                        report.pushed += 1;
+                        report.conflicts += 1;
+                    }
+                }
+            }
+            Err(e) => {
+                warn!(path = %path, error = %e, "Failed to sync file");
+                report.failed.push((path, format!("{:#}", e)));
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Copy a file from the peer; returns its hash
+async fn pull<R: AsyncRead + Unpin, W: AsyncWrite + Unpin>(
+    conn: &mut Connection<R, W>,
+    roots: &Roots,
+    entry: &FileEntry,
+    conflict_copy: bool,
+    peer_host: &str,
+    received: &mut usize,
+) -> Result<String> {
+    let target = roots.resolve(&entry.path)?;
+    let base = fs::read_to_string(&target).ok();
+
+    // Try a delta against our copy, then the whole file if that doesn't check out
+    let mut attempts = vec![None];
+    if let Some(ref base) = base {
+        attempts.insert(0, Some(line_hashes(base)));
+    }
+    for hashes in attempts {
+        let with_base = hashes.is_some();
+        let Response::File { delta } = conn.call(&Request::Get { path: entry.path.clone(), base: hashes }).await? else {
+            anyhow::bail!("Unexpected reply to get");
+        };
+        *received += delta_size(&delta);
+        let content = apply_delta(base.as_deref().filter(|_| with_base).unwrap_or_default(), &delta)?;
+        if hash_content(content.as_bytes()) != entry.hash {
+            debug!(path = %entry.path, "Delta didn't match; fetching the whole file");
+            continue;
+        }
+
+        if conflict_copy && target.exists() {
+            let copy = conflict_copy_path(&target, &local_host());
+            fs::copy(&target, &copy).with_context(|| format!("Failed to keep conflict copy {}", copy.display()))?;
+        }
+        write_file(&target, &content, entry.modified)?;
+        debug!(path = %entry.path, peer = %peer_host, "Pulled");
+        return Ok(entry.hash.clone());
+    }
+    anyhow::bail!("Content from the peer doesn't match its hash")
+}
+
+/// Copy a file to the peer; returns its hash
+async fn push<R: AsyncRead + Unpin, W: AsyncWrite + Unpin>(
+    conn: &mut Connection<R, W>,
+    roots: &Roots,
+    entry: &FileEntry,
+    conflict_copy: bool,
+    sent: &mut usize,
+) -> Result<String> {
+    let source = roots.resolve(&entry.path)?;
+    let content = fs::read_to_string(&source).with_context(|| format!("Failed to read {}", source.display()))?;
+    let hash = hash_content(content.as_bytes());
+
+    let Response::Lines { hashes } = conn.call(&Request::Lines { path: entry.path.clone() }).await? else {
+        anyhow::bail!("Unexpected reply to lines");
+    };
+    let mut attempts = vec![Vec::new()];
+    if let Some(hashes) = hashes {
+        attempts.insert(0, hashes);
+    }
+
+    let mut last_error = None;
+    for base in attempts {
+        let delta = make_delta(&content, &base);
+        *sent += delta_size(&delta);
+        let request = Request::Put {
+            path: entry.path.clone(),
+            delta,
+            hash: hash.clone(),
+            modified: entry.modified,
+            conflict_copy,
+        };
+        match conn.call(&request).await {
+            Ok(_) => return Ok(hash),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| anyhow::anyhow!("Push failed")))
+}
+
+/// Answer sync requests on stdin/stdout (`quant sync --serve`)
+pub async fn serve_stdio() -> Result<()> {
+    serve(tokio::io::stdin(), tokio::io::stdout(), Roots::local).await
+}
+
+async fn serve(
+    reader: impl AsyncRead + Unpin,
+    writer: impl AsyncWrite + Unpin,
+    roots_for: impl Fn(Option<&str>) -> Result<Roots>,
+) -> Result<()> {
+    let mut conn = Connection::new(reader, writer);
+    let mut roots = Roots::default();
+
+    while let Some(request) = conn.recv::<Request>().await? {
+        let response = match handle(request, &mut roots, &roots_for) {
+            Ok(response) => response,
+            Err(e) => Response::Error {
+                message: format!("{:#}", e),
+            },
+        };
+        conn.send(&response).await?;
+    }
+    Ok(())
+}
+
+fn handle(request: Request, roots: &mut Roots, roots_for: &impl Fn(Option<&str>) -> Result<Roots>) -> Result<Response> {
+    Ok(match request {
+        Request::Hello { project, .. } => {
+            *roots = roots_for(project.as_deref())?;
+            Response::Hello {
+                version: PROTOCOL_VERSION,
+                host: local_host(),
+            }
+        }
+        Request::List => Response::List { files: roots.list()? },
+        Request::Lines { path } => Response::Lines {
+            hashes: fs::read_to_string(roots.resolve(&path)?).ok().map(|c| line_hashes(&c)),
+        },
+        Request::Get { path, base } => {
+            let content = fs::read_to_string(roots.resolve(&path)?)?;
+            Response::File {
+                delta: make_delta(&content, base.as_deref().unwrap_or_default()),
+            }
+        }
+        Request::Put {
+            path,
+            delta,
+            hash,
+            modified,
+            conflict_copy,
+        } => {
+            let target = roots.resolve(&path)?;
+            let base = fs::read_to_string(&target).unwrap_or_default();
+            let content = apply_delta(&base, &delta)?;
+            if hash_content(content.as_bytes()) != hash {
+                anyhow::bail!("Content for {} doesn't match its hash", path);
+            }
+            if conflict_copy && target.exists() {
+                fs::copy(&target, conflict_copy_path(&target, &local_host()))?;
+            }
+            write_file(&target, &content, modified)?;
+            Response::Done
+        }
+    })
+}
+
+/// JSON lines over a pair of pipes
+struct Connection<R, W> {
+    lines: Lines<BufReader<R>>,
+    writer: W,
+}
+
+impl<R: AsyncRead + Unpin, W: AsyncWrite + Unpin> Connection<R, W> {
+    fn new(reader: R, writer: W) -> Self {
+        Self {
+            lines: BufReader::new(reader).lines(),
+            writer,
+        }
+    }
+
+    async fn send(&mut self, message: &impl Serialize) -> Result<()> {
+        let mut line = serde_json::to_string(message)?;
+        line.push('\n');
+        self.writer.write_all(line.as_bytes()).await?;
+        self.writer.flush().await?;
+        Ok(())
+    }
+
+    /// The next message, or None when the other side hung up
+    async fn recv<T: DeserializeOwned>(&mut self) -> Result<Option<T>> {
+        while let Some(line) = self.lines.next_line().await? {
+            // Skip anything a login shell prints before quant starts
+            if !line.starts_with('{') {
+                debug!(line = %line, "Ignoring non-protocol output");
+                continue;
+            }
+            return Ok(Some(serde_json::from_str(&line).context("Malformed sync message")?));
+        }
+        Ok(None)
+    }
+
+    async fn call(&mut self, request: &Request) -> Result<Response> {
+        self.send(request).await?;
+        match self.recv().await? {
+            Some(Response::Error { message }) => anyhow::bail!("Peer: {}", message),
+            Some(response) => Ok(response),
+            None => anyhow::bail!("Peer closed the connection (is quant installed there?)"),
+        }
+    }
+}
+
+/// An edit turning the receiver's copy into the sender's
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum DeltaOp {
+    /// Lines `start..start + len` of the receiver's copy
+    Copy { start: usize, len: usize },
+    Insert { text: String },
+}
+
+/// FNV-1a hash of each line (with its newline)
+fn line_hashes(content: &str) -> Vec<u32> {
+    content.split_inclusive('\n').map(fnv1a).collect()
+}
+
+fn fnv1a(line: &str) -> u32 {
+    line.bytes().fold(0x811c_9dc5, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193))
+}
+
+/// Describe `content` as runs of lines from a base (given by its line
+/// hashes) and inserted text
+fn make_delta(content: &str, base: &[u32]) -> Vec<DeltaOp> {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let hashes: Vec<u32> = lines.iter().map(|l| fnv1a(l)).collect();
+    let mut positions: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (i, hash) in base.iter().enumerate() {
+        positions.entry(*hash).or_default().push(i);
+    }
+
+    let run_len = |start: usize, at: usize| {
+        (0..)
+            .take_while(|k| at + k < hashes.len() && start + k < base.len() && base[start + k] == hashes[at + k])
+            .count()
+    };
+
+    let mut ops: Vec<DeltaOp> = Vec::new();
+    let mut i = 0;
+    let mut next_base = 0;
+    while i < lines.len() {
+        // Prefer continuing where the last copied run ended
+        let candidates = std::iter::once(next_base)
+            .chain(positions.get(&hashes[i]).into_iter().flatten().copied().take(MAX_DELTA_CANDIDATES));
+        let mut best = (0, 0);
+        for start in candidates {
+            let len = run_len(start, i);
+            if len > best.1 {
+                best = (start, len);
+            }
+        }
+
+        match (best, ops.last_mut()) {
+            ((_, 0), Some(DeltaOp::Insert { text })) => {
+                text.push_str(lines[i]);
+                i += 1;
+            }
+            ((_, 0), _) => {
+                ops.push(DeltaOp::Insert { text: lines[i].to_string() });
+                i += 1;
+            }
+            ((start, len), _) => {
+                ops.push(DeltaOp::Copy { start, len });
+                i += len;
+                next_base = start + len;
+            }
+        }
+    }
+    ops
+}
+
+fn apply_delta(base: &str, ops: &[DeltaOp]) -> Result<String> {
+    let lines: Vec<&str> = base.split_inclusive('\n').collect();
+    let mut content = String::with_capacity(base.len());
+    for op in ops {
+        match op {
+            DeltaOp::Copy { start, len } => {
+                let run = lines.get(*start..start + len).context("Delta refers to lines the base doesn't have")?;
+                run.iter().for_each(|line| content.push_str(line));
+            }
+            DeltaOp::Insert { text } => content.push_str(text),
+        }
+    }
+    Ok(content)
+}
+
+/// Rough wire size of a delta
+fn delta_size(ops: &[DeltaOp]) -> usize {
+    ops.iter()
+        .map(|op| match op {
+            DeltaOp::Copy { .. } => 16,
+            DeltaOp::Insert { text } => text.len(),
+        })
+        .sum()
+}
+
+fn hash_content(content: &[u8]) -> String {
+    Sha256::digest(content).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn modified_ms(path: &Path) -> u64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Write a synced file, keeping the sender's modification time so the next
+/// sync compares the right ages
+fn write_file(path: &Path, content: &str, modified_ms: u64) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("sync.tmp");
+    fs::write(&tmp, content).with_context(|| format!("Failed to write {}", tmp.display()))?;
+    let file = fs::File::options().write(true).open(&tmp)?;
+    file.set_modified(UNIX_EPOCH + Duration::from_millis(modified_ms))?;
+    drop(file);
+    fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))
+}
+
+/// `abc.json` -> `abc.conflict-<host>-20261016T101500.json`
+fn conflict_copy_path(path: &Path, host: &str) -> PathBuf {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let mut name = format!("{}.conflict-{}-{}", stem, host, Utc::now().format("%Y%m%dT%H%M%S"));
+    if let Some(ext) = path.extension() {
+        name.push('.');
+        name.push_str(&ext.to_string_lossy());
+    }
+    path.with_file_name(name)
+}
+
+fn local_host() -> String {
+    std::process::Command::new("hostname")
+        .output()
+        .ok()
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+    use tempfile::TempDir;
+
+    fn entry(path: &str, hash: &str, modified: u64) -> FileEntry {
+        FileEntry {
+            path: path.to_string(),
+            size: 0,
+            modified,
+            hash: hash.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_delta_round_trip() {
+        let messages: String = (0..50).map(|i| format!("    \"message {}\",\n", i)).collect();
+        let base = format!("{{\n  \"updated_at\": \"1\",\n  \"messages\": [\n{}  ]\n}}\n", messages);
+        let new = format!("{{\n  \"updated_at\": \"2\",\n  \"messages\": [\n{}    \"reply\"\n  ]\n}}", messages);
+
+        let delta = make_delta(&new, &line_hashes(&base));
+        assert_eq!(apply_delta(&base, &delta).unwrap(), new);
+        assert!(delta.iter().any(|op| matches!(op, DeltaOp::Copy { .. })));
+        assert!(delta_size(&delta) < new.len());
+
+        // No base: the whole file is inserted
+        assert_eq!(make_delta(&new, &[]), vec![DeltaOp::Insert { text: new.clone() }]);
+        assert!(apply_delta("", &[DeltaOp::Copy { start: 0, len: 1 }]).is_err());
+    }
+
+    #[test]
+    fn test_plan() {
+        let filter = Filter::new(&[], &["sessions/skip*".to_string()]).unwrap();
+        let mut base = SyncState::default();
+        base.files.insert("conversations/c.json".to_string(), "old".to_string());
+        base.files.insert("conversations/d.json".to_string(), "old".to_string());
+
+        let local = vec![
+            entry("sessions/mine.json", "1", 0),
+            entry("conversations/c.json", "old", 0),
+            entry("conversations/d.json", "local", 5),
+            entry("conversations/e.json", "same", 0),
+            entry("sessions/skip.json", "x", 0),
+        ];
+        let remote = vec![
+            entry("sessions/theirs.json", "2", 0),
+            entry("conversations/c.json", "remote", 0),
+            entry("conversations/d.json", "remote", 9),
+            entry("conversations/e.json", "same", 0),
+        ];
+
+        let actions = plan(&local, &remote, &base, &filter);
+        assert_eq!(
+            actions,
+            vec![
+                ("conversations/c.json".to_string(), Action::Pull),
+                ("conversations/d.json".to_string(), Action::PullConflict),
+                ("sessions/mine.json".to_string(), Action::Push),
+                ("sessions/theirs.json".to_string(), Action::Pull),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filter_and_resolve() {
+        let filter = Filter::new(&["conversations".to_string()], &[]).unwrap();
+        assert!(filter.allows("conversations/a.json"));
+        assert!(!filter.allows("sessions/a.json"));
+
+        let dir = TempDir::new().unwrap();
+        let roots = Roots::new(vec![(Category::Approvals, dir.path().to_path_buf())]);
+        assert!(roots.resolve("approvals/approvals.toml").is_ok());
+        assert!(roots.resolve("approvals/../secrets").is_err());
+        assert!(roots.resolve("approvals/config.toml").is_err());
+        assert!(roots.resolve("sessions/a.json").is_err());
+    }
+
+    fn set_modified(path: &Path, time: SystemTime) {
+        fs::File::options().write(true).open(path).unwrap().set_modified(time).unwrap();
+    }
+
+    fn test_roots(dir: &TempDir) -> Roots {
+        Roots::new(vec![
+            (Category::Sessions, dir.path().join("sessions")),
+            (Category::Conversations, dir.path().join("conversations")),
+        ])
+    }
+
+    /// Sync two directories through an in-memory pipe
+    async fn sync_dirs(local: &TempDir, remote: &TempDir, state: &mut SyncState) -> SyncReport {
+        let (client_side, server_side) = tokio::io::duplex(64 * 1024);
+        let (server_read, server_write) = tokio::io::split(server_side);
+        let remote_roots = test_roots(remote);
+        let server = tokio::spawn(async move { serve(server_read, server_write, move |_| Ok(remote_roots.clone())).await });
+
+        let options = SyncOptions {
+            ssh: "ssh".to_string(),
+            remote_command: "quant".to_string(),
+            filter: Filter::default(),
+            dry_run: false,
+        };
+        let (client_read, client_write) = tokio::io::split(client_side);
+        let report = run_client(client_read, client_write, &test_roots(local), None, state, &options)
+            .await
+            .unwrap();
+        server.abort();
+        report
+    }
+
+    #[tokio::test]
+    async fn test_sync_over_pipe() {
+        let local = TempDir::new().unwrap();
+        let remote = TempDir::new().unwrap();
+        for dir in [&local, &remote] {
+            fs::create_dir_all(dir.path().join("sessions")).unwrap();
+            fs::create_dir_all(dir.path().join("conversations")).unwrap();
+        }
+
+        let shared = "line one\nline two\nline three\n".repeat(20);
+        fs::write(local.path().join("sessions/mine.json"), "local only\n").unwrap();
+        fs::write(remote.path().join("conversations/theirs.json"), "remote only\n").unwrap();
+        fs::write(local.path().join("conversations/both.json"), format!("{}local edit\n", shared)).unwrap();
+        fs::write(remote.path().join("conversations/both.json"), format!("{}remote edit\n", shared)).unwrap();
+        let now = SystemTime::now();
+        set_modified(&local.path().join("conversations/both.json"), now - Duration::from_secs(60));
+        set_modified(&remote.path().join("conversations/both.json"), now);
+
+        let mut state = SyncState::default();
+        let report = sync_dirs(&local, &remote, &mut state).await;
+        assert!(report.failed.is_empty(), "{:?}", report.failed);
+        assert_eq!((report.pulled, report.pushed, report.conflicts), (2, 1, 1));
+        // Only the differing line of the conflicting file crossed the pipe
+        assert!(report.bytes_received < shared.len());
+
+        // Both sides have every file; the newer remote edit won the conflict
+        let read = |dir: &TempDir, path: &str| fs::read_to_string(dir.path().join(path)).unwrap();
+        assert_eq!(read(&remote, "sessions/mine.json"), "local only\n");
+        assert_eq!(read(&local, "conversations/theirs.json"), "remote only\n");
+        assert!(read(&local, "conversations/both.json").ends_with("remote edit\n"));
+        let copies: Vec<_> = fs::read_dir(local.path().join("conversations"))
+            .unwrap()
+            .flatten()
+            .filter(|e| e.file_name().to_string_lossy().contains(".conflict-"))
+            .collect();
+        assert_eq!(copies.len(), 1);
+        assert!(fs::read_to_string(copies[0].path()).unwrap().ends_with("local edit\n"));
+
+        // An edit on one side since the last sync is copied without conflict
+        // (the conflict copy travels too, so both machines keep it)
+        fs::write(remote.path().join("sessions/mine.json"), "local only\nmore\n").unwrap();
+        let report = sync_dirs(&local, &remote, &mut state).await;
+        assert_eq!((report.pulled, report.pushed, report.conflicts), (1, 1, 0));
+        assert_eq!(read(&local, "sessions/mine.json"), "local only\nmore\n");
+    }
+}
@@ -0,0 +1,95 @@
+//! Optional OpenTelemetry export of tracing spans
+//!
+//! With `[telemetry] otlp_endpoint` set, the spans quant records (agent runs
+//! and iterations, LLM requests, tool calls) are also sent over OTLP/HTTP to
+//! a collector the user runs, such as Jaeger or Grafana Tempo. Without an
+//! endpoint no exporter is built and nothing leaves the machine.
+
+use anyhow::{Context, Result};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig, WithHttpConfig};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use tracing::Level;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use crate::config::TelemetryConfig;
+
+/// Path of the OTLP/HTTP traces endpoint, added to a bare collector URL
+const TRACES_PATH: &str = "/v1/traces";
+
+/// Exports spans until it is shut down
+pub struct Telemetry {
+    provider: SdkTracerProvider,
+}
+
+impl Telemetry {
+    /// Send the spans still queued and stop exporting
+    pub fn shutdown(self) {
+        if let Err(e) = self.provider.shutdown() {
+            tracing::debug!(error = %e, "Failed to flush spans");
+        }
+    }
+}
+
+/// A layer exporting spans to the configured collector, or None without an endpoint
+///
+/// Spans from quant and llm-core are exported at info level, or debug with
+/// `verbose`, whatever the log filter.
+pub fn layer<S>(config: &TelemetryConfig, verbose: bool) -> Result<Option<(impl Layer<S>, Telemetry)>>
+where
+    S: tracing::Subscriber + for<'a> LookupSpan<'a>,
+{
+    let Some(endpoint) = config.otlp_endpoint.as_deref().filter(|e| !e.trim().is_empty()) else {
+        return Ok(None);
+    };
+
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(traces_url(endpoint)?)
+        .with_headers(config.headers.clone())
+        .build()
+        .context("Failed to create OTLP exporter")?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(config.service_name.clone()).build())
+        .build();
+
+    let level = if verbose { Level::DEBUG } else { Level::INFO };
+    let filter = Targets::new().with_target("quant", level).with_target("llm_core", level);
+    let layer = tracing_opentelemetry::layer()
+        .with_tracer(provider.tracer("quant"))
+        .with_filter(filter);
+    Ok(Some((layer, Telemetry { provider })))
+}
+
+/// The traces URL for `endpoint`: as given if it has a path, else the standard one
+fn traces_url(endpoint: &str) -> Result<String> {
+    let url = url::Url::parse(endpoint.trim()).with_context(|| format!("Invalid telemetry.otlp_endpoint: {}", endpoint))?;
+    if url.path() == "/" {
+        Ok(format!("{}{}", url.as_str().trim_end_matches('/'), TRACES_PATH))
+    } else {
+        Ok(url.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traces_url() {
+        assert_eq!(traces_url("http://localhost:4318").unwrap(), "http://localhost:4318/v1/traces");
+        assert_eq!(traces_url("http://tempo:4318/").unwrap(), "http://tempo:4318/v1/traces");
+        assert_eq!(traces_url("https://otel.example.com/custom/traces").unwrap(), "https://otel.example.com/custom/traces");
+        assert!(traces_url("localhost 4318").is_err());
+    }
+
+    #[test]
+    fn test_no_endpoint_no_exporter() {
+        let config = TelemetryConfig::default();
+        assert!(layer::<tracing_subscriber::Registry>(&config, false).unwrap().is_none());
+    }
+}
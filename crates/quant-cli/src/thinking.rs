@@ -0,0 +1,153 @@
+//! Showing the thinking of reasoning models
+//!
+//! Thinking streams dimmed ahead of the answer; with `--no-thinking` it is
+//! collapsed into one line saying how long the model thought. Only the answer
+//! is returned to callers, so thinking never reaches saved messages or the
+//! context budget.
+
+use anyhow::Result;
+use llm_core::ThinkingSplitter;
+use std::io::{stdout, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// Set by `--no-thinking`
+static HIDDEN: AtomicBool = AtomicBool::new(false);
+
+/// Collapse thinking for this process
+pub fn set_hidden(hidden: bool) {
+    HIDDEN.store(hidden, Ordering::Relaxed);
+}
+
+/// Prints a response stream's thinking and answer
+pub struct ThinkingDisplay {
+    splitter: ThinkingSplitter,
+    print: bool,
+    hidden: bool,
+    /// Colour the answer is printed in
+    answer_color: &'static str,
+    thinking: String,
+    /// When thinking began, until the answer starts
+    thinking_since: Option<Instant>,
+}
+
+impl ThinkingDisplay {
+    pub fn new(answer_color: &'static str) -> Self {
+        Self {
+            splitter: ThinkingSplitter::new(),
+            print: true,
+            hidden: HIDDEN.load(Ordering::Relaxed),
+            answer_color,
+            thinking: String::new(),
+            thinking_since: None,
+        }
+    }
+
+    /// Split without printing anything
+    pub fn quiet() -> Self {
+        Self {
+            print: false,
+            ..Self::new("")
+        }
+    }
+
+    /// Print a chunk, given its thinking field and content; returns the
+    /// answer part of it
+    pub fn push(&mut self, thinking: &str, content: &str) -> Result<String> {
+        self.show_thinking(thinking)?;
+        let split = self.splitter.push(content);
+        self.show_thinking(&split.thinking)?;
+        self.show_answer(&split.content)?;
+        Ok(split.content)
+    }
+
+    /// Print what the stream held back at its end; returns its answer part
+    pub fn finish(&mut self) -> Result<String> {
+        let split = self.splitter.finish();
+        self.show_thinking(&split.thinking)?;
+        self.show_answer(&split.content)?;
+        self.close_thinking()?;
+        Ok(split.content)
+    }
+
+    /// Everything the model thought
+    pub fn thinking(&self) -> &str {
+        self.thinking.trim()
+    }
+
+    fn show_thinking(&mut self, text: &str) -> Result<()> {
+        if text.is_empty() {
+            return Ok(());
+        }
+        let first = self.thinking_since.is_none();
+        if first {
+            self.thinking_since = Some(Instant::now());
+        }
+        self.thinking.push_str(text);
+        if self.print && !self.hidden {
+            let text = if first { text.trim_start() } else { text };
+            if first {
+                print!("{}{}", RESET, DIM);
+            }
+            print!("{}", text);
+            stdout().flush()?;
+        }
+        Ok(())
+    }
+
+    fn show_answer(&mut self, text: &str) -> Result<()> {
+        if text.is_empty() {
+            return Ok(());
+        }
+        self.close_thinking()?;
+        if self.print {
+            print!("{}", text);
+            stdout().flush()?;
+        }
+        Ok(())
+    }
+
+    /// End the thinking block, once the answer starts or the stream ends
+    fn close_thinking(&mut self) -> Result<()> {
+        let Some(since) = self.thinking_since.take() else {
+            return Ok(());
+        };
+        if !self.print {
+            return Ok(());
+        }
+        if self.hidden {
+            println!("{}{}[thought for {:.1}s]{}", RESET, DIM, since.elapsed().as_secs_f64(), RESET);
+        } else {
+            println!("{}\n", RESET);
+        }
+        print!("{}", self.answer_color);
+        stdout().flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_answer_kept_apart_from_thinking() {
+        let mut display = ThinkingDisplay::quiet();
+        let mut answer = String::new();
+        for (thinking, content) in [("", "<think>Short"), ("", " question.</think>\n\nYes"), ("", ".")] {
+            answer.push_str(&display.push(thinking, content).unwrap());
+        }
+        answer.push_str(&display.finish().unwrap());
+        assert_eq!(answer, "Yes.");
+        assert_eq!(display.thinking(), "Short question.");
+
+        // Thinking Ollama sends in its own field
+        let mut display = ThinkingDisplay::quiet();
+        assert_eq!(display.push("Check the docs", "").unwrap(), "");
+        assert_eq!(display.push("", "It is 4").unwrap(), "It is 4");
+        assert_eq!(display.thinking(), "Check the docs");
+    }
+}
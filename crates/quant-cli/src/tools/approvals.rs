@@ -0,0 +1,291 @@
+//! Per-project allowlist of trusted bash commands
+//!
+//! When a bash command is approved, the confirmation prompt offers to trust
+//! its normalized pattern: the program and its subcommand, with any
+//! arguments (`cargo test -p core` becomes `cargo test *`). Patterns are kept
+//! in `.quant/approvals.toml` at the project root, and matching commands are
+//! approved without asking.
+//!
+//! Commands that chain, pipe, redirect or substitute are never matched, so a
+//! trusted `cargo test *` can't carry `; rm -rf ~` along with it. Nor are
+//! commands [`assess_risk`] rates Dangerous (`git push *` doesn't cover
+//! `git push --force`), and programs that run other commands (`bash`, `find`,
+//! `sudo`...) get no pattern at all, as their arguments decide what runs.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::SecurityLevel;
+use crate::shell::assess_risk;
+
+/// File under the project's `.quant` directory holding trusted commands
+pub const APPROVALS_FILE: &str = "approvals.toml";
+
+/// Characters that let one command line run more than one command
+const SHELL_OPERATORS: &[char] = &[';', '&', '|', '<', '>', '`', '$', '(', ')', '\n'];
+
+/// Programs whose arguments are themselves commands or scripts to run
+const WRAPPER_PROGRAMS: &[&str] = &[
+    "sh", "bash", "zsh", "fish", "dash", "ksh", "env", "xargs", "sudo", "doas", "su", "find", "eval",
+    "exec", "command", "nohup", "time", "timeout", "nice", "watch", "parallel", "ssh", "python",
+    "python3", "node", "deno", "bun", "perl", "ruby", "php", "lua", "awk", "gawk", "sed", "npx",
+    "bunx", "uvx", "osascript",
+];
+
+/// Programs whose second word picks one of many commands (`cargo test`)
+const MULTI_COMMAND_PROGRAMS: &[&str] = &[
+    "cargo", "git", "npm", "pnpm", "yarn", "go", "docker", "podman", "kubectl", "helm", "rustup",
+    "pip", "pip3", "uv", "poetry", "gh", "terraform", "dotnet", "mvn", "gradle", "brew", "deno",
+    "ollama", "quant",
+];
+
+/// A command pattern approved for the project
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrustedCommand {
+    /// e.g. "cargo test *"
+    pub pattern: String,
+    pub added_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ApprovalsFile {
+    #[serde(default)]
+    commands: Vec<TrustedCommand>,
+}
+
+/// The trusted commands of one project
+#[derive(Debug)]
+pub struct Approvals {
+    path: PathBuf,
+    commands: Vec<TrustedCommand>,
+}
+
+impl Approvals {
+    /// Load the allowlist of the project containing the current directory
+    pub fn discover() -> Result<Self> {
+        let cwd = std::env::current_dir()?;
+        let root = crate::project::find_project_root(&cwd).unwrap_or(cwd);
+        Self::load(root.join(".quant").join(APPROVALS_FILE))
+    }
+
+    /// Load an allowlist file (empty if it doesn't exist yet)
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let commands = if path.exists() {
+            let text = fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+            let file: ApprovalsFile =
+                toml::from_str(&text).with_context(|| format!("Failed to parse {}", path.display()))?;
+            file.commands
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self { path, commands })
+    }
+
+    /// Where the allowlist is stored
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Trusted patterns, oldest first
+    pub fn commands(&self) -> &[TrustedCommand] {
+        &self.commands
+    }
+
+    /// The trusted pattern a command matches, if any
+    pub fn find(&self, command: &str) -> Option<&TrustedCommand> {
+        self.commands.iter().find(|c| pattern_trusts(&c.pattern, command))
+    }
+
+    /// Trust a pattern and save; returns false if it was already trusted
+    pub fn add(&mut self, pattern: &str) -> Result<bool> {
+        if self.commands.iter().any(|c| c.pattern == pattern) {
+            return Ok(false);
+        }
+        self.commands.push(TrustedCommand {
+            pattern: pattern.to_string(),
+            added_at: Utc::now(),
+        });
+        self.save()?;
+        Ok(true)
+    }
+
+    /// Stop trusting a pattern, given as the pattern itself or its 1-based
+    /// number in `quant approvals list`, and save
+    pub fn revoke(&mut self, pattern: &str) -> Result<TrustedCommand> {
+        let index = match pattern.parse::<usize>() {
+            Ok(n) if (1..=self.commands.len()).contains(&n) => n - 1,
+            _ => self
+                .commands
+                .iter()
+                .position(|c| c.pattern == pattern)
+                .ok_or_else(|| anyhow::anyhow!("No trusted command matching '{}'", pattern))?,
+        };
+        let removed = self.commands.remove(index);
+        self.save()?;
+        Ok(removed)
+    }
+
+    fn save(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let file = ApprovalsFile {
+            commands: self.commands.clone(),
+        };
+        fs::write(&self.path, toml::to_string_pretty(&file)?)
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+/// The pattern to trust for a command: its program (and subcommand, for
+/// tools like cargo and git) followed by `*` for any arguments
+///
+/// Returns None for commands that can't be trusted by pattern: ones using
+/// shell operators, starting with a variable assignment, running a wrapper
+/// program, or rated Dangerous.
+pub fn command_pattern(command: &str) -> Option<String> {
+    if command.contains(SHELL_OPERATORS) || assess_risk(command) == SecurityLevel::Dangerous {
+        return None;
+    }
+
+    let mut words = command.split_whitespace();
+    let program = words.next().filter(|w| !w.contains('=') && !is_wrapper(w))?;
+    let mut pattern = program.to_string();
+    let subcommand = words.next().filter(|w| MULTI_COMMAND_PROGRAMS.contains(&program) && is_subcommand(w));
+    if let Some(subcommand) = subcommand {
+        pattern.push(' ');
+        pattern.push_str(subcommand);
+    }
+    pattern.push_str(" *");
+    Some(pattern)
+}
+
+/// Whether a command matches a trusted pattern
+///
+/// A trailing `*` matches any arguments (including none); otherwise the
+/// words must match exactly. A bare `*` matches nothing.
+pub fn pattern_matches(pattern: &str, command: &str) -> bool {
+    if command.contains(SHELL_OPERATORS) {
+        return false;
+    }
+
+    let pattern: Vec<&str> = pattern.split_whitespace().collect();
+    let command: Vec<&str> = command.split_whitespace().collect();
+    match pattern.split_last() {
+        Some((&"*", prefix)) => !prefix.is_empty() && command.starts_with(prefix),
+        Some(_) => pattern == command,
+        None => false,
+    }
+}
+
+/// Whether a command matching `pattern` is trusted to run without asking
+///
+/// Like [`pattern_matches`], except that a `*` never stands in for arguments
+/// that make the command Dangerous, nor for the arguments of a wrapper
+/// program. Exact patterns are trusted as they are.
+pub fn pattern_trusts(pattern: &str, command: &str) -> bool {
+    if !pattern_matches(pattern, command) {
+        return false;
+    }
+    match pattern.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [program, .., "*"] => !is_wrapper(program) && assess_risk(command) != SecurityLevel::Dangerous,
+        _ => true,
+    }
+}
+
+/// Whether a program runs the commands or scripts it's given, by name or path
+fn is_wrapper(program: &str) -> bool {
+    let name = Path::new(program).file_name().and_then(|n| n.to_str()).unwrap_or(program);
+    WRAPPER_PROGRAMS.contains(&name) || name.starts_with("python")
+}
+
+/// A word like `test` or `run-script`, as opposed to a flag, path or value
+fn is_subcommand(word: &str) -> bool {
+    !word.starts_with('-') && word.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == ':')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_command_pattern() {
+        assert_eq!(command_pattern("cargo test -p core").as_deref(), Some("cargo test *"));
+        assert_eq!(command_pattern("npm run build").as_deref(), Some("npm run *"));
+        assert_eq!(command_pattern("ls -la src/").as_deref(), Some("ls *"));
+        assert_eq!(command_pattern("make").as_deref(), Some("make *"));
+        assert_eq!(command_pattern("cargo test && rm -rf target"), None);
+        assert_eq!(command_pattern("echo $(whoami)"), None);
+        assert_eq!(command_pattern("RUST_LOG=debug cargo run"), None);
+
+        // Wrappers run whatever their arguments say
+        assert_eq!(command_pattern("bash build.sh"), None);
+        assert_eq!(command_pattern("/bin/sh -c make"), None);
+        assert_eq!(command_pattern("find . -name x"), None);
+        assert_eq!(command_pattern("python3.12 setup.py"), None);
+        assert_eq!(command_pattern("sudo make install"), None);
+        // Dangerous commands are only ever approved one at a time
+        assert_eq!(command_pattern("rm target"), None);
+        assert_eq!(command_pattern("git push --force origin"), None);
+        // Only multi-command tools have subcommands
+        assert_eq!(command_pattern("git push origin").as_deref(), Some("git push *"));
+        assert_eq!(command_pattern("touch notes").as_deref(), Some("touch *"));
+    }
+
+    #[test]
+    fn test_pattern_trusts() {
+        assert!(pattern_trusts("git push *", "git push origin main"));
+        assert!(!pattern_trusts("git push *", "git push --force origin"));
+        assert!(!pattern_trusts("git push *", "git push -f"));
+        assert!(!pattern_trusts("rm target *", "rm target -rf ~"));
+        assert!(!pattern_trusts("cargo test *", "cargo test; rm -rf ~"));
+
+        // Patterns saved before wrappers were excluded don't match either
+        assert!(!pattern_trusts("bash *", "bash -c 'rm -rf ~'"));
+        assert!(!pattern_trusts("bash *", "bash build.sh"));
+        assert!(!pattern_trusts("find *", "find / -delete"));
+        assert!(!pattern_trusts("find *", "find . -name x"));
+
+        // An exact command stays approved, dangerous or not
+        assert!(pattern_trusts("rm -rf target", "rm -rf target"));
+        assert!(!pattern_trusts("rm -rf target", "rm -rf target ~"));
+    }
+
+    #[test]
+    fn test_pattern_matches() {
+        assert!(pattern_matches("cargo test *", "cargo test"));
+        assert!(pattern_matches("cargo test *", "cargo  test --release -p core"));
+        assert!(!pattern_matches("cargo test *", "cargo build"));
+        assert!(!pattern_matches("cargo test *", "cargo test; rm -rf ~"));
+        assert!(!pattern_matches("cargo test *", "cargo test > /etc/passwd"));
+        assert!(pattern_matches("git status", "git status"));
+        assert!(!pattern_matches("git status", "git status --short"));
+        assert!(!pattern_matches("*", "anything"));
+    }
+
+    #[test]
+    fn test_approvals_add_find_revoke() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(".quant").join(APPROVALS_FILE);
+
+        let mut approvals = Approvals::load(path.clone()).unwrap();
+        assert!(approvals.find("cargo test").is_none());
+        assert!(approvals.add("cargo test *").unwrap());
+        assert!(!approvals.add("cargo test *").unwrap());
+        approvals.add("npm run *").unwrap();
+
+        let mut reloaded = Approvals::load(path).unwrap();
+        assert_eq!(reloaded.commands().len(), 2);
+        assert_eq!(reloaded.find("cargo test -p core").unwrap().pattern, "cargo test *");
+
+        assert_eq!(reloaded.revoke("2").unwrap().pattern, "npm run *");
+        assert_eq!(reloaded.revoke("cargo test *").unwrap().pattern, "cargo test *");
+        assert!(reloaded.revoke("cargo test *").is_err());
+        assert!(reloaded.commands().is_empty());
+    }
+}
@@ -3,12 +3,14 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use serde_json::Value;
-use std::process::Stdio;
-use tokio::process::Command;
+use std::process::{Output, Stdio};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command};
 use tokio::time::{timeout, Duration};
 use tracing::{debug, instrument, warn};
 
-use crate::tools::{ParameterProperty, ParameterSchema, SecurityLevel, Tool, ToolContext, ToolResult};
+use crate::procs::ProcessKind;
+use crate::tools::{dry_run, ParameterProperty, ParameterSchema, SecurityLevel, Tool, ToolContext, ToolResult};
 
 /// Tool for executing bash commands
 pub struct BashTool;
@@ -63,13 +65,13 @@ impl Tool for BashTool {
         }
 
         // P0 Security: Validate working_dir is within ctx.working_dir to prevent path traversal
-        let canonical_working = match working_dir.canonicalize() {
+        let canonical_working = match crate::paths::canonicalize(&working_dir) {
             Ok(p) => p,
             Err(e) => return Ok(ToolResult::error(format!(
                 "Failed to resolve working directory: {}", e
             ))),
         };
-        let canonical_ctx = match ctx.working_dir.canonicalize() {
+        let canonical_ctx = match crate::paths::canonicalize(&ctx.working_dir) {
             Ok(p) => p,
             Err(e) => return Ok(ToolResult::error(format!(
                 "Failed to resolve context directory: {}", e
@@ -106,11 +108,32 @@ impl Tool for BashTool {
         cmd.arg(shell_arg)
             .arg(command)
             .current_dir(&working_dir)
+            .stdin(Stdio::null())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
 
-        // Execute with timeout
-        let result = timeout(Duration::from_secs(timeout_secs), cmd.output()).await;
+        // Run in a process group so the command and any jobs it starts can be
+        // stopped together, and found again if quant crashes
+        #[cfg(unix)]
+        cmd.process_group(0);
+        let child = match cmd.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                warn!(error = %e, "Failed to execute command");
+                return Ok(ToolResult::error(format!("Failed to execute command: {}", e)));
+            }
+        };
+        let pid = child.id();
+        crate::procs::track(pid, ProcessKind::Bash, command);
+
+        // Execute with timeout, streaming output lines as they arrive
+        let result = timeout(Duration::from_secs(timeout_secs), wait_streaming(child, ctx)).await;
+        if result.is_err() {
+            if let Some(pid) = pid {
+                crate::procs::terminate(pid);
+            }
+        }
+        crate::procs::release(pid);
 
         match result {
             Ok(Ok(output)) => {
@@ -174,6 +197,66 @@ impl Tool for BashTool {
             }
         }
     }
+
+    fn simulate(&self, args: &Value, ctx: &ToolContext) -> ToolResult {
+        let Some(command) = args.get("command").and_then(|v| v.as_str()) else {
+            return ToolResult::error("Missing required parameter: command");
+        };
+        let working_dir = args.get("working_dir")
+            .and_then(|v| v.as_str())
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(|| ctx.working_dir.clone());
+        dry_run::command(command, &working_dir)
+    }
+
+    fn preview(&self, args: &Value, _ctx: &ToolContext) -> Option<String> {
+        let mut lines = vec![format!("$ {}", args.get("command")?.as_str()?)];
+        if let Some(dir) = args.get("working_dir").and_then(|v| v.as_str()) {
+            lines.push(format!("in {}", dir));
+        }
+        if let Some(timeout) = args.get("timeout").and_then(|v| v.as_u64()) {
+            lines.push(format!("timeout {}s", timeout));
+        }
+        Some(lines.join("\n"))
+    }
+}
+
+/// Wait for a command to finish, passing each line of its output to the
+/// context's output sink as it is read
+async fn wait_streaming(mut child: Child, ctx: &ToolContext) -> std::io::Result<Output> {
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let (stdout, stderr, status) = tokio::join!(
+        read_lines(stdout, false, ctx),
+        read_lines(stderr, true, ctx),
+        child.wait(),
+    );
+
+    Ok(Output {
+        status: status?,
+        stdout: stdout?,
+        stderr: stderr?,
+    })
+}
+
+/// Read a pipe to the end, emitting it line by line
+async fn read_lines(pipe: Option<impl AsyncRead + Unpin>, stderr: bool, ctx: &ToolContext) -> std::io::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    let Some(pipe) = pipe else {
+        return Ok(output);
+    };
+
+    let mut reader = BufReader::new(pipe);
+    loop {
+        let start = output.len();
+        if reader.read_until(b'\n', &mut output).await? == 0 {
+            break;
+        }
+        let line = String::from_utf8_lossy(&output[start..]);
+        ctx.emit_output(stderr, line.trim_end_matches(['\n', '\r']));
+    }
+
+    Ok(output)
 }
 
 #[cfg(test)]
@@ -182,6 +265,32 @@ mod tests {
     use serde_json::json;
     use tempfile::TempDir;
 
+    #[tokio::test]
+    async fn test_bash_streams_output_lines() {
+        let tool = BashTool;
+        let (sender, mut lines) = tokio::sync::mpsc::unbounded_channel();
+        let ctx = ToolContext::default().with_output(sender);
+        let args = json!({ "command": "echo building; echo warning >&2; printf done" });
+
+        let result = tool.execute(&args, &ctx).await.unwrap();
+        assert!(result.success);
+        assert!(result.output.contains("building"));
+
+        let mut received = Vec::new();
+        while let Ok(line) = lines.try_recv() {
+            received.push((line.stderr, line.text));
+        }
+        received.sort();
+        assert_eq!(
+            received,
+            vec![
+                (false, "building".to_string()),
+                (false, "done".to_string()),
+                (true, "warning".to_string()),
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn test_bash_echo() {
         let tool = BashTool;
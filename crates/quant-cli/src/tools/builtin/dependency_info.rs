@@ -0,0 +1,138 @@
+//! Dependency info tool
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::deps::{self, DependencyManifest};
+use crate::tools::{ParameterProperty, ParameterSchema, SecurityLevel, Tool, ToolContext, ToolResult};
+
+/// Tool for looking up declared and resolved dependency versions
+pub struct DependencyInfoTool;
+
+#[async_trait]
+impl Tool for DependencyInfoTool {
+    fn name(&self) -> &str {
+        "dependency_info"
+    }
+
+    fn description(&self) -> &str {
+        "Show the project's dependencies from Cargo.toml, package.json, or pyproject.toml, with the declared \
+         requirement and the version resolved in the lockfile. Pass a name to look up one dependency."
+    }
+
+    fn security_level(&self) -> SecurityLevel {
+        SecurityLevel::Safe
+    }
+
+    fn parameters_schema(&self) -> ParameterSchema {
+        ParameterSchema::new()
+            .with_property("name", ParameterProperty::string("Dependency name to look up (default: list all)"))
+            .with_property("include_dev", ParameterProperty::boolean("Include dev dependencies when listing (default: false)"))
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext) -> Result<ToolResult> {
+        let name = args.get("name").and_then(|v| v.as_str()).map(str::trim).filter(|s| !s.is_empty());
+        let include_dev = args.get("include_dev").and_then(|v| v.as_bool()).unwrap_or(false);
+
+        let manifests = deps::discover(&ctx.working_dir);
+        if manifests.is_empty() {
+            return Ok(ToolResult::error(format!(
+                "No Cargo.toml, package.json, or pyproject.toml found in {}",
+                ctx.working_dir.display()
+            )));
+        }
+
+        let output = match name {
+            Some(name) => {
+                let found = deps::find(&manifests, name);
+                if found.is_empty() {
+                    return Ok(ToolResult::error(format!(
+                        "'{}' is not a direct dependency. It may be transitive; check the lockfile.",
+                        name
+                    )));
+                }
+                found
+                    .iter()
+                    .map(|(manifest, dep)| {
+                        format!(
+                            "{} ({}, {}): requirement {}, resolved {}",
+                            dep.name,
+                            manifest_label(manifest, ctx),
+                            dep.kind,
+                            dep.requirement,
+                            dep.resolved.as_deref().unwrap_or("unknown")
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            None => format_all(&manifests, ctx, include_dev),
+        };
+
+        Ok(ToolResult::success(output))
+    }
+}
+
+fn manifest_label(manifest: &DependencyManifest, ctx: &ToolContext) -> String {
+    manifest
+        .path
+        .strip_prefix(&ctx.working_dir)
+        .unwrap_or(&manifest.path)
+        .display()
+        .to_string()
+}
+
+fn format_all(manifests: &[DependencyManifest], ctx: &ToolContext, include_dev: bool) -> String {
+    let mut out = String::new();
+    for manifest in manifests {
+        out.push_str(&format!("{} ({})\n", manifest_label(manifest, ctx), manifest.ecosystem));
+        for dep in manifest
+            .dependencies
+            .iter()
+            .filter(|d| include_dev || d.kind != deps::DependencyKind::Dev)
+        {
+            out.push_str(&format!(
+                "  {} {} -> {} [{}]\n",
+                dep.name,
+                dep.requirement,
+                dep.resolved.as_deref().unwrap_or("?"),
+                dep.kind
+            ));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_dependency_info() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"demo\"\n\n[dependencies]\nregex = \"1\"\n\n[dev-dependencies]\ntempfile = \"3\"\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("Cargo.lock"), "[[package]]\nname = \"regex\"\nversion = \"1.10.5\"\n").unwrap();
+
+        let tool = DependencyInfoTool;
+        let ctx = ToolContext::new(dir.path().to_path_buf());
+
+        let result = tool.execute(&json!({ "name": "regex" }), &ctx).await.unwrap();
+        assert!(result.success);
+        assert!(result.output.contains("requirement 1, resolved 1.10.5"));
+
+        let result = tool.execute(&json!({}), &ctx).await.unwrap();
+        assert!(result.output.contains("regex 1 -> 1.10.5 [normal]"));
+        assert!(!result.output.contains("tempfile"));
+
+        let result = tool.execute(&json!({ "name": "aho-corasick" }), &ctx).await.unwrap();
+        assert!(!result.success);
+    }
+}
@@ -1,13 +1,24 @@
 //! File read tool
+//!
+//! Reads a window of lines from a text file. Output is capped both by line
+//! count and by a token budget so large files can be read page by page,
+//! with a hint telling the model where to continue; overlong lines are cut.
 
 use anyhow::Result;
 use async_trait::async_trait;
 use serde_json::Value;
-use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+use super::text_file::{cap_line, is_binary, read_bytes};
+use crate::context::Tokenizer;
 use crate::tools::{ParameterProperty, ParameterSchema, SecurityLevel, Tool, ToolContext, ToolResult};
 
+/// Lines returned when no limit is given
+const DEFAULT_LINE_LIMIT: usize = 2000;
+
+/// Longest line returned; longer ones (minified code) are cut
+const MAX_LINE_BYTES: usize = 2000;
+
 /// Tool for reading file contents
 pub struct FileReadTool;
 
@@ -18,7 +29,8 @@ impl Tool for FileReadTool {
     }
 
     fn description(&self) -> &str {
-        "Read the contents of a file. Returns the file content as text. For binary files, returns an error."
+        "Read the contents of a text file with line numbers. Large files are returned in pages; \
+         use offset and limit to read further. Binary files are rejected."
     }
 
     fn security_level(&self) -> SecurityLevel {
@@ -29,7 +41,7 @@ impl Tool for FileReadTool {
         ParameterSchema::new()
             .with_required("path", ParameterProperty::string("The path to the file to read (absolute or relative to working directory)"))
             .with_property("offset", ParameterProperty::number("Line number to start reading from (1-indexed, default: 1)").with_default(Value::Number(1.into())))
-            .with_property("limit", ParameterProperty::number("Maximum number of lines to read (default: unlimited)"))
+            .with_property("limit", ParameterProperty::number("Maximum number of lines to read (default: 2000)"))
     }
 
     async fn execute(&self, args: &Value, ctx: &ToolContext) -> Result<ToolResult> {
@@ -44,7 +56,9 @@ impl Tool for FileReadTool {
 
         let limit = args.get("limit")
             .and_then(|v| v.as_u64())
-            .map(|v| v as usize);
+            .map(|v| v as usize)
+            .filter(|&v| v > 0)
+            .unwrap_or(DEFAULT_LINE_LIMIT);
 
         // Resolve path relative to working directory
         let path = if PathBuf::from(path_str).is_absolute() {
@@ -53,6 +67,12 @@ impl Tool for FileReadTool {
             ctx.working_dir.join(path_str)
         };
 
+        // Files with writes awaiting review are read as staged
+        if let Some(content) = ctx.staging.as_ref().filter(|s| s.contains(&path)).and_then(|s| s.read(&path)) {
+            let lines: Vec<&str> = content.lines().collect();
+            return Ok(ToolResult::success(read_window(&path, &lines, offset, limit, ctx.max_read_tokens)));
+        }
+
         // Check if file exists
         if !path.exists() {
             return Ok(ToolResult::error(format!("File not found: {}", path.display())));
@@ -63,53 +83,75 @@ impl Tool for FileReadTool {
             return Ok(ToolResult::error(format!("Not a file: {}", path.display())));
         }
 
-        // Read the file
-        let content = match fs::read_to_string(&path) {
-            Ok(c) => c,
+        // Read the file (memory-mapped if large)
+        let bytes = match read_bytes(&path) {
+            Ok(b) => b,
             Err(e) => {
                 return Ok(ToolResult::error(format!("Failed to read file: {}", e)));
             }
         };
 
-        // Apply offset and limit
+        if is_binary(&bytes) {
+            return Ok(ToolResult::error(format!(
+                "Binary file ({} bytes): {}. Only text files can be read.",
+                bytes.len(),
+                path.display()
+            )));
+        }
+
+        let content = String::from_utf8_lossy(&bytes);
         let lines: Vec<&str> = content.lines().collect();
-        let total_lines = lines.len();
-
-        let selected_lines: Vec<_> = lines
-            .into_iter()
-            .skip(offset)
-            .take(limit.unwrap_or(usize::MAX))
-            .enumerate()
-            .map(|(i, line)| format!("{:>6}\t{}", offset + i + 1, line))
-            .collect();
-
-        let output = if selected_lines.is_empty() {
-            format!("File is empty or offset {} exceeds file length ({} lines)", offset + 1, total_lines)
-        } else {
-            let header = format!("File: {} ({} lines total)\n", path.display(), total_lines);
-            header + &selected_lines.join("\n")
-        };
 
-        // Truncate if too long (UTF-8 safe)
-        let output = if output.len() > ctx.max_output_len {
-            // Find a safe truncation point at a char boundary
-            let safe_end = output
-                .char_indices()
-                .take_while(|(idx, _)| *idx < ctx.max_output_len)
-                .last()
-                .map(|(idx, c)| idx + c.len_utf8())
-                .unwrap_or(0);
-            format!(
-                "{}\n\n[Output truncated at {} characters]",
-                &output[..safe_end],
-                safe_end
-            )
-        } else {
-            output
-        };
+        Ok(ToolResult::success(read_window(&path, &lines, offset, limit, ctx.max_read_tokens)))
+    }
+}
 
-        Ok(ToolResult::success(output))
+/// Format up to `limit` lines starting at `offset`, within a token budget
+fn read_window(path: &Path, lines: &[&str], offset: usize, limit: usize, max_tokens: usize) -> String {
+    let total_lines = lines.len();
+    if offset >= total_lines {
+        return format!("File is empty or offset {} exceeds file length ({} lines)", offset + 1, total_lines);
     }
+
+    let tokenizer = Tokenizer::default();
+    let mut used_tokens = 0;
+    let mut selected = Vec::new();
+    let mut truncated_by_tokens = false;
+
+    for (i, line) in lines.iter().enumerate().skip(offset).take(limit) {
+        let formatted = format!("{:>6}\t{}", i + 1, cap_line(line, 0, MAX_LINE_BYTES));
+        let tokens = tokenizer.count_tokens(&formatted) + 1;
+
+        if used_tokens + tokens > max_tokens {
+            truncated_by_tokens = true;
+            if selected.is_empty() {
+                // A single oversized line: return as much of it as fits
+                selected.push(tokenizer.truncate_to_tokens(&formatted, max_tokens));
+            }
+            break;
+        }
+        used_tokens += tokens;
+        selected.push(formatted);
+    }
+
+    let end = offset + selected.len();
+    let mut output = format!("File: {} ({} lines total)\n", path.display(), total_lines);
+    output.push_str(&selected.join("\n"));
+
+    if end < total_lines {
+        let reason = if truncated_by_tokens { " (token limit reached)" } else { "" };
+        output.push_str(&format!(
+            "\n\n[Showing lines {}-{} of {}{}. File has {} more lines; use offset={} to continue]",
+            offset + 1,
+            end,
+            total_lines,
+            reason,
+            total_lines - end,
+            end + 1
+        ));
+    }
+
+    output
 }
 
 #[cfg(test)]
@@ -169,4 +211,58 @@ mod tests {
         assert!(!result.success);
         assert!(result.error.unwrap().contains("File not found"));
     }
+
+    #[tokio::test]
+    async fn test_read_binary_file() {
+        let mut temp = NamedTempFile::new().unwrap();
+        temp.write_all(&[0x89, b'P', b'N', b'G', 0x00, 0x01, 0x02]).unwrap();
+
+        let tool = FileReadTool;
+        let ctx = ToolContext::default();
+        let args = json!({ "path": temp.path().to_str().unwrap() });
+
+        let result = tool.execute(&args, &ctx).await.unwrap();
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("Binary file"));
+    }
+
+    #[tokio::test]
+    async fn test_pagination_hint() {
+        let mut temp = NamedTempFile::new().unwrap();
+        for i in 1..=50 {
+            writeln!(temp, "line {}", i).unwrap();
+        }
+
+        let tool = FileReadTool;
+        let ctx = ToolContext::default();
+        let args = json!({ "path": temp.path().to_str().unwrap(), "limit": 20 });
+
+        let result = tool.execute(&args, &ctx).await.unwrap();
+        assert!(result.output.contains("line 20"));
+        assert!(!result.output.contains("line 21"));
+        assert!(result.output.contains("Showing lines 1-20 of 50"));
+        assert!(result.output.contains("30 more lines; use offset=21"));
+
+        // Reading to the end has no hint
+        let args = json!({ "path": temp.path().to_str().unwrap(), "offset": 21 });
+        let result = tool.execute(&args, &ctx).await.unwrap();
+        assert!(result.output.contains("line 50"));
+        assert!(!result.output.contains("more lines"));
+    }
+
+    #[test]
+    fn test_token_budget_truncation() {
+        let owned: Vec<String> = (1..=500).map(|i| format!("let value_{} = compute({});", i, i)).collect();
+        let lines: Vec<&str> = owned.iter().map(|s| s.as_str()).collect();
+
+        let output = read_window(Path::new("big.rs"), &lines, 0, DEFAULT_LINE_LIMIT, 200);
+        assert!(output.contains("token limit reached"));
+        assert!(!output.contains("value_500"));
+        assert!(Tokenizer::default().count_tokens(&output) < 300);
+
+        // A single huge line is cut rather than dropped
+        let huge = "x ".repeat(5000);
+        let output = read_window(Path::new("min.js"), &[huge.as_str(), "next"], 0, 10, 100);
+        assert!(output.contains("use offset=2"));
+    }
 }
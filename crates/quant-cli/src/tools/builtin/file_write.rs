@@ -6,7 +6,7 @@ use serde_json::Value;
 use std::fs;
 use std::path::PathBuf;
 
-use crate::tools::{ParameterProperty, ParameterSchema, SecurityLevel, Tool, ToolContext, ToolResult};
+use crate::tools::{dry_run, ParameterProperty, ParameterSchema, SecurityLevel, Tool, ToolContext, ToolResult};
 
 /// Tool for writing file contents
 pub struct FileWriteTool;
@@ -52,6 +52,27 @@ impl Tool for FileWriteTool {
             ctx.working_dir.join(path_str)
         };
 
+        if crate::context::repos::is_repo_path(&path) {
+            return Ok(ToolResult::error(format!(
+                "{} is in a read-only remote repo checkout (quant context add-repo)",
+                path.display()
+            )));
+        }
+
+        // Hold the write for review
+        if let Some(ref staging) = ctx.staging {
+            let new = match staging.read(&path) {
+                Some(current) if append => format!("{}{}", current, content),
+                _ => content.to_string(),
+            };
+            staging.stage(&path, new);
+            return Ok(ToolResult::success(format!(
+                "Staged {} ({} bytes); it is written once the user accepts it in review",
+                path.display(),
+                content.len()
+            )));
+        }
+
         // Create parent directories if needed
         if let Some(parent) = path.parent() {
             if !parent.exists() {
@@ -91,6 +112,48 @@ impl Tool for FileWriteTool {
             Err(e) => Ok(ToolResult::error(format!("Failed to write file: {}", e))),
         }
     }
+
+    fn simulate(&self, args: &Value, ctx: &ToolContext) -> ToolResult {
+        match planned_write(args, ctx) {
+            Some((summary, diff)) => dry_run::simulated(format!("Would {}", summary), diff),
+            None => ToolResult::error("Missing required parameter: path or content"),
+        }
+    }
+
+    fn preview(&self, args: &Value, ctx: &ToolContext) -> Option<String> {
+        planned_write(args, ctx).map(|(summary, diff)| format!("{}\n{}", capitalize(&summary), diff))
+    }
+}
+
+/// What a write would do ("create notes.txt (6 bytes)") and its diff
+fn planned_write(args: &Value, ctx: &ToolContext) -> Option<(String, String)> {
+    let path_str = args.get("path").and_then(|v| v.as_str())?;
+    let content = args.get("content").and_then(|v| v.as_str())?;
+    let append = args.get("append").and_then(|v| v.as_bool()).unwrap_or(false);
+
+    let path = ctx.working_dir.join(path_str);
+    let current = match ctx.staging {
+        Some(ref staging) => staging.read(&path),
+        None => fs::read_to_string(&path).ok(),
+    };
+    let new = match (&current, append) {
+        (Some(current), true) => format!("{}{}", current, content),
+        _ => content.to_string(),
+    };
+    let action = match (&current, append) {
+        (None, _) => "create",
+        (Some(_), true) => "append to",
+        (Some(_), false) => "overwrite",
+    };
+    Some((
+        format!("{} {} ({} bytes)", action, path_str, content.len()),
+        dry_run::diff_preview(path_str, current.as_deref(), &new),
+    ))
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    chars.next().map(|c| c.to_uppercase().chain(chars).collect()).unwrap_or_default()
 }
 
 #[cfg(test)]
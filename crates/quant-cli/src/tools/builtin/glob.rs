@@ -6,6 +6,7 @@ use glob::glob as glob_match;
 use serde_json::Value;
 use std::path::PathBuf;
 
+use crate::ignore_files::IgnoreRules;
 use crate::tools::{ParameterProperty, ParameterSchema, SecurityLevel, Tool, ToolContext, ToolResult};
 
 /// Tool for finding files matching a glob pattern
@@ -51,7 +52,7 @@ impl Tool for GlobTool {
         let full_pattern = if PathBuf::from(pattern).is_absolute() {
             pattern.to_string()
         } else {
-            format!("{}/{}", base_path.display(), pattern)
+            crate::paths::glob_under(&base_path, pattern)
         };
 
         // Execute glob
@@ -64,9 +65,11 @@ impl Tool for GlobTool {
 
         let mut matches: Vec<String> = Vec::new();
         let mut errors: Vec<String> = Vec::new();
+        let ignore_rules = IgnoreRules::for_dir(&base_path);
 
         for entry in entries {
             match entry {
+                Ok(path) if ignore_rules.is_ignored(&path, path.is_dir()) => {}
                 Ok(path) => {
                     // Make path relative to working dir if possible
                     let display_path = path
@@ -141,6 +144,24 @@ mod tests {
         assert!(!result.output.contains("README.md"));
     }
 
+    #[tokio::test]
+    async fn test_glob_respects_ignore_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+        fs::create_dir_all(base.join("generated")).unwrap();
+        fs::write(base.join("main.rs"), "// main").unwrap();
+        fs::write(base.join("generated/schema.rs"), "// generated").unwrap();
+        fs::write(base.join("secret.rs"), "// secret").unwrap();
+        fs::write(base.join(".gitignore"), "generated/\n").unwrap();
+        fs::write(base.join(".quantignore"), "secret.rs\n").unwrap();
+
+        let ctx = ToolContext::new(base.to_path_buf());
+        let result = GlobTool.execute(&json!({ "pattern": "**/*.rs" }), &ctx).await.unwrap();
+        assert!(result.output.contains("main.rs"));
+        assert!(!result.output.contains("schema.rs"));
+        assert!(!result.output.contains("secret.rs"));
+    }
+
     #[tokio::test]
     async fn test_glob_no_matches() {
         let temp_dir = TempDir::new().unwrap();
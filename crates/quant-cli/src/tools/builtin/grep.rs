@@ -1,16 +1,215 @@
 //! Grep/search tool
+//!
+//! Directories are searched with ripgrep (`rg --json`) when it is installed,
+//! which is much faster on large repositories, and otherwise by walking the
+//! tree in-process. Both backends take the same parameters, honour the same
+//! ignore files and format results the same way: `path:line:text` for
+//! matches and `path-line-text` for context lines, with `--` between groups.
 
 use anyhow::Result;
 use async_trait::async_trait;
-use regex::Regex;
+use regex::bytes::Regex;
 use serde_json::Value;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
 use tracing::{debug, instrument, warn};
 use walkdir::WalkDir;
 
+use super::text_file::{cap_line, is_binary, read_bytes};
+use crate::ignore_files::IgnoreRules;
 use crate::tools::{ParameterProperty, ParameterSchema, SecurityLevel, Tool, ToolContext, ToolResult};
 
+/// Directories skipped when searching a directory tree
+const SKIPPED_DIRS: &[&str] = &[".git", "node_modules", "target", ".venv"];
+
+/// Files larger than this many KB are skipped unless `max_file_kb` is given
+const DEFAULT_MAX_FILE_KB: u64 = 10 * 1024;
+
+/// Longest matching line shown; longer ones are cut around the match
+const MAX_LINE_BYTES: usize = 300;
+
+/// Skipped files listed by name in the output
+const MAX_SKIPPED_LISTED: usize = 10;
+
+/// Most context lines shown before or after a match
+const MAX_CONTEXT_LINES: usize = 20;
+
+/// Why a file wasn't searched
+enum Skipped {
+    Binary,
+    TooLarge(u64),
+    Unreadable(String),
+}
+
+impl std::fmt::Display for Skipped {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Binary => write!(f, "binary"),
+            Self::TooLarge(size) => write!(f, "{} KB, over the size limit", size / 1024),
+            Self::Unreadable(e) => write!(f, "unreadable: {}", e),
+        }
+    }
+}
+
+/// How a directory is searched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Backend {
+    Ripgrep,
+    Builtin,
+}
+
+impl Backend {
+    /// ripgrep if it is installed, unless turned off with
+    /// `[context] ripgrep = false`
+    fn detect() -> Self {
+        let enabled = crate::config::UserConfig::load().map_or(true, |config| config.context.ripgrep);
+        if enabled && which::which("rg").is_ok() {
+            Self::Ripgrep
+        } else {
+            Self::Builtin
+        }
+    }
+}
+
+/// A search, from the tool's arguments
+struct GrepQuery {
+    pattern: String,
+    regex: Regex,
+    case_insensitive: bool,
+    glob: Option<glob::Pattern>,
+    before: usize,
+    after: usize,
+    limit: usize,
+    max_file_bytes: u64,
+}
+
+impl GrepQuery {
+    /// Parse the tool's other arguments, or explain what is wrong with them
+    fn new(pattern: &str, args: &Value) -> Result<Self, String> {
+        let pattern = pattern.to_string();
+
+        let case_insensitive = args.get("case_insensitive")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let limit = args.get("limit")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as usize)
+            .unwrap_or(50);
+
+        let max_file_bytes = args.get("max_file_kb")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(DEFAULT_MAX_FILE_KB)
+            .saturating_mul(1024);
+
+        // -C sets both sides; -B and -A override it
+        let lines = |key: &str| args.get(key).and_then(|v| v.as_u64()).map(|v| (v as usize).min(MAX_CONTEXT_LINES));
+        let context = lines("context").unwrap_or(0);
+        let before = lines("before").unwrap_or(context);
+        let after = lines("after").unwrap_or(context);
+
+        let regex_pattern = if case_insensitive {
+            format!("(?i){}", pattern)
+        } else {
+            pattern.clone()
+        };
+        let regex = Regex::new(&regex_pattern).map_err(|e| {
+            warn!(pattern = %pattern, error = %e, "Invalid regex pattern");
+            format!("Invalid regex pattern: {}", e)
+        })?;
+
+        let glob = args.get("glob")
+            .and_then(|v| v.as_str())
+            .map(glob::Pattern::new)
+            .transpose()
+            .map_err(|e| format!("Invalid glob pattern: {}", e))?;
+
+        Ok(Self {
+            pattern,
+            regex,
+            case_insensitive,
+            glob,
+            before,
+            after,
+            limit,
+            max_file_bytes,
+        })
+    }
+
+    fn has_context(&self) -> bool {
+        self.before > 0 || self.after > 0
+    }
+}
+
+/// Matches and context lines found so far
+#[derive(Default)]
+struct GrepOutput {
+    lines: Vec<String>,
+    matches: usize,
+    files_searched: usize,
+    skipped: Vec<(PathBuf, Skipped)>,
+    /// Line last added, to tell when a new group starts
+    last: Option<(PathBuf, usize)>,
+}
+
+impl GrepOutput {
+    /// Add a match (`at` is the byte offset of the match in the line) or a
+    /// context line (`at` is None)
+    fn push(&mut self, query: &GrepQuery, path: &Path, line_number: usize, text: &str, at: Option<usize>) {
+        let adjacent = matches!(&self.last, Some((p, n)) if p == path && *n + 1 == line_number);
+        if query.has_context() && !adjacent && !self.lines.is_empty() {
+            self.lines.push("--".to_string());
+        }
+        self.last = Some((path.to_path_buf(), line_number));
+
+        let text = cap_line(text.trim_end_matches(['\n', '\r']), at.unwrap_or(0), MAX_LINE_BYTES);
+        // Without context, leading indentation is just noise
+        let text = if query.has_context() { text.trim_end() } else { text.trim() };
+        let separator = if at.is_some() { ':' } else { '-' };
+        self.lines.push(format!("{}{sep}{}{sep}{}", path.display(), line_number, text, sep = separator));
+        if at.is_some() {
+            self.matches += 1;
+        }
+    }
+
+    fn is_full(&self, query: &GrepQuery) -> bool {
+        self.matches >= query.limit
+    }
+
+    /// The tool's output
+    fn render(&self, query: &GrepQuery, working_dir: &Path, backend: Backend) -> String {
+        let output = if self.matches == 0 {
+            format!("No matches found for '{}' in {} files", query.pattern, self.files_searched)
+        } else {
+            let header = format!(
+                "Found {} matches for '{}' in {} files:\n\n",
+                self.matches, query.pattern, self.files_searched
+            );
+            let truncated = if self.is_full(query) {
+                format!("\n\n[Results truncated at {} matches]", query.limit)
+            } else {
+                String::new()
+            };
+            header + &self.lines.join("\n") + &truncated
+        };
+
+        // ripgrep doesn't say which files it passed over
+        let note = if backend == Backend::Ripgrep && self.matches == 0 {
+            format!(
+                "\n\n[Binary files and files over {} KB were not searched]",
+                query.max_file_bytes / 1024
+            )
+        } else {
+            String::new()
+        };
+
+        output + &skipped_summary(&self.skipped, working_dir) + &note
+    }
+}
+
 /// Tool for searching file contents
 pub struct GrepTool;
 
@@ -21,7 +220,8 @@ impl Tool for GrepTool {
     }
 
     fn description(&self) -> &str {
-        "Search for a pattern in files. Supports regex patterns. Returns matching lines with file paths and line numbers."
+        "Search for a pattern in files. Supports regex patterns. Returns matching lines with file paths and line numbers, \
+         optionally with context lines around each match. Binary files and files over max_file_kb are skipped."
     }
 
     fn security_level(&self) -> SecurityLevel {
@@ -34,168 +234,264 @@ impl Tool for GrepTool {
             .with_property("path", ParameterProperty::string("File or directory to search in (default: working directory)"))
             .with_property("glob", ParameterProperty::string("File pattern to filter (e.g., '*.rs', '*.py')"))
             .with_property("case_insensitive", ParameterProperty::boolean("Case insensitive search (default: false)"))
+            .with_property("before", ParameterProperty::number("Lines of context to show before each match, like grep -B (default: 0)"))
+            .with_property("after", ParameterProperty::number("Lines of context to show after each match, like grep -A (default: 0)"))
+            .with_property("context", ParameterProperty::number("Lines of context on both sides, like grep -C (default: 0)"))
             .with_property("limit", ParameterProperty::number("Maximum number of matches to return (default: 50)").with_default(Value::Number(50.into())))
+            .with_property("max_file_kb", ParameterProperty::number("Skip files larger than this many KB (default: 10240)"))
     }
 
     #[instrument(skip(self, args, ctx), fields(pattern = tracing::field::Empty))]
     async fn execute(&self, args: &Value, ctx: &ToolContext) -> Result<ToolResult> {
-        let pattern_str = args.get("pattern")
+        let pattern = args.get("pattern")
             .and_then(|v| v.as_str())
             .ok_or_else(|| anyhow::anyhow!("Missing required parameter: pattern"))?;
 
         // Record pattern in span (truncate for safety)
-        tracing::Span::current().record("pattern", &pattern_str.chars().take(50).collect::<String>().as_str());
+        tracing::Span::current().record("pattern", pattern.chars().take(50).collect::<String>().as_str());
+
+        let query = match GrepQuery::new(pattern, args) {
+            Ok(query) => query,
+            Err(e) => return Ok(ToolResult::error(e)),
+        };
 
         let search_path = args.get("path")
             .and_then(|v| v.as_str())
             .map(PathBuf::from)
             .unwrap_or_else(|| ctx.working_dir.clone());
 
-        let file_glob = args.get("glob")
-            .and_then(|v| v.as_str());
-
-        let case_insensitive = args.get("case_insensitive")
-            .and_then(|v| v.as_bool())
-            .unwrap_or(false);
-
-        let limit = args.get("limit")
-            .and_then(|v| v.as_u64())
-            .map(|v| v as usize)
-            .unwrap_or(50);
-
-        debug!(path = %search_path.display(), glob = ?file_glob, case_insensitive, limit, "Grep parameters");
-
-        // Compile regex
-        let pattern = if case_insensitive {
-            format!("(?i){}", pattern_str)
+        // Determine if searching a single file or directory
+        let search_path = if search_path.is_absolute() {
+            search_path
         } else {
-            pattern_str.to_string()
+            ctx.working_dir.join(search_path)
         };
 
-        let regex = match Regex::new(&pattern) {
-            Ok(r) => r,
-            Err(e) => {
-                warn!(pattern = %pattern_str, error = %e, "Invalid regex pattern");
-                return Ok(ToolResult::error(format!("Invalid regex pattern: {}", e)));
-            }
-        };
+        Ok(grep(&query, &search_path, &ctx.working_dir, Backend::detect()).await)
+    }
+}
 
-        // Compile file glob pattern if provided
-        let glob_pattern = file_glob.map(|g| glob::Pattern::new(g));
-        if let Some(Err(e)) = &glob_pattern {
-            return Ok(ToolResult::error(format!("Invalid glob pattern: {}", e)));
+/// Run a search with `backend` (single files are always searched in-process)
+async fn grep(query: &GrepQuery, search_path: &Path, working_dir: &Path, backend: Backend) -> ToolResult {
+    debug!(
+        path = %search_path.display(),
+        glob = ?query.glob.as_ref().map(|g| g.as_str()),
+        case_insensitive = query.case_insensitive,
+        limit = query.limit,
+        ?backend,
+        "Grep parameters"
+    );
+
+    let mut output = GrepOutput::default();
+    if search_path.is_file() {
+        if let Err(reason) = search_file(query, search_path, working_dir, &mut output) {
+            return ToolResult::error(format!("Not searched: {} ({})", search_path.display(), reason));
         }
-        let glob_pattern = glob_pattern.transpose().ok().flatten();
+        output.files_searched = 1;
+    } else if !search_path.is_dir() {
+        return ToolResult::error(format!("Path not found: {}", search_path.display()));
+    } else if backend == Backend::Ripgrep {
+        if let Err(e) = search_ripgrep(query, search_path, working_dir, &mut output).await {
+            warn!(error = %e, "ripgrep failed; searching in-process");
+            output = GrepOutput::default();
+            search_dir(query, search_path, working_dir, &mut output);
+            return ToolResult::success(output.render(query, working_dir, Backend::Builtin));
+        }
+    } else {
+        search_dir(query, search_path, working_dir, &mut output);
+    }
 
-        let mut matches: Vec<String> = Vec::new();
-        let mut files_searched = 0;
+    ToolResult::success(output.render(query, working_dir, backend))
+}
 
-        // Determine if searching a single file or directory
-        let search_path = if search_path.is_absolute() {
-            search_path
-        } else {
-            ctx.working_dir.join(search_path)
-        };
+/// Walk a directory, pruning what .gitignore/.quantignore exclude
+fn search_dir(query: &GrepQuery, search_path: &Path, working_dir: &Path, output: &mut GrepOutput) {
+    let ignore_rules = IgnoreRules::for_dir(search_path);
+    for entry in WalkDir::new(search_path)
+        .follow_links(true)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !ignore_rules.is_ignored(e.path(), e.file_type().is_dir()))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+    {
+        let path = entry.path();
+
+        // Skip hidden files and common non-text directories
+        let relative = path.strip_prefix(search_path).unwrap_or(path);
+        if crate::paths::in_dir_named(relative, SKIPPED_DIRS) {
+            continue;
+        }
 
-        if search_path.is_file() {
-            // Search single file
-            search_file(&search_path, &regex, &mut matches, limit, &ctx.working_dir)?;
-            files_searched = 1;
-        } else if search_path.is_dir() {
-            // Walk directory
-            for entry in WalkDir::new(&search_path)
-                .follow_links(true)
-                .into_iter()
-                .filter_map(|e| e.ok())
-                .filter(|e| e.file_type().is_file())
-            {
-                let path = entry.path();
-
-                // Skip hidden files and common non-text directories
-                let path_str = path.to_string_lossy();
-                if path_str.contains("/.git/")
-                    || path_str.contains("/node_modules/")
-                    || path_str.contains("/target/")
-                    || path_str.contains("/.venv/")
-                {
+        // Apply glob filter
+        if let Some(ref glob) = query.glob {
+            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                if !glob.matches(name) {
                     continue;
                 }
+            }
+        }
 
-                // Apply glob filter
-                if let Some(ref glob) = glob_pattern {
-                    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                        if !glob.matches(name) {
-                            continue;
-                        }
-                    }
-                }
+        match search_file(query, path, working_dir, output) {
+            Ok(()) => output.files_searched += 1,
+            Err(reason) => output.skipped.push((path.to_path_buf(), reason)),
+        }
+
+        if output.is_full(query) {
+            break;
+        }
+    }
+}
 
-                search_file(path, &regex, &mut matches, limit, &ctx.working_dir)?;
-                files_searched += 1;
+/// Search a directory with `rg --json`, reading its results until the
+/// limit is reached
+async fn search_ripgrep(query: &GrepQuery, search_path: &Path, working_dir: &Path, output: &mut GrepOutput) -> Result<()> {
+    let ignore_rules = IgnoreRules::for_dir(search_path);
+
+    let mut cmd = Command::new("rg");
+    cmd.arg("--json")
+        .arg("--hidden")
+        .arg("--follow")
+        .arg("--no-require-git")
+        .arg("--max-filesize")
+        .arg(query.max_file_bytes.to_string());
+    if query.case_insensitive {
+        cmd.arg("--ignore-case");
+    }
+    if query.before > 0 {
+        cmd.arg("--before-context").arg(query.before.to_string());
+    }
+    if query.after > 0 {
+        cmd.arg("--after-context").arg(query.after.to_string());
+    }
+    if let Some(ref glob) = query.glob {
+        cmd.arg("--glob").arg(glob.as_str());
+    }
+    for dir in SKIPPED_DIRS {
+        cmd.arg("--glob").arg(format!("!{}/", dir));
+    }
+    if !ignore_rules.enabled() {
+        cmd.arg("--no-ignore");
+    }
+    cmd.arg("--regexp")
+        .arg(&query.pattern)
+        .arg("--")
+        .arg(search_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let mut child = cmd.spawn()?;
+    let stdout = child.stdout.take().ok_or_else(|| anyhow::anyhow!("No output from rg"))?;
+    let mut lines = BufReader::new(stdout).lines();
+    let mut files_with_matches = 0;
+
+    while let Some(line) = lines.next_line().await? {
+        let Ok(event) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        let data = &event["data"];
+        let kind = event["type"].as_str().unwrap_or_default();
+        if kind == "summary" {
+            output.files_searched = data["stats"]["searches"].as_u64().unwrap_or(0) as usize;
+            continue;
+        }
 
-                if matches.len() >= limit {
+        let Some(path) = data["path"]["text"].as_str().map(Path::new) else {
+            continue;
+        };
+        // ripgrep doesn't read .quantignore
+        if ignore_rules.is_ignored(path, false) {
+            continue;
+        }
+        let display = path.strip_prefix(working_dir).unwrap_or(path);
+        let line_number = data["line_number"].as_u64().unwrap_or(0) as usize;
+        let text = data["lines"]["text"].as_str().unwrap_or_default();
+
+        match kind {
+            "begin" => files_with_matches += 1,
+            "match" => {
+                let at = data["submatches"][0]["start"].as_u64().unwrap_or(0) as usize;
+                output.push(query, display, line_number, text, Some(at));
+                if output.is_full(query) {
                     break;
                 }
             }
-        } else {
-            return Ok(ToolResult::error(format!("Path not found: {}", search_path.display())));
+            "context" => output.push(query, display, line_number, text, None),
+            _ => {}
         }
+    }
 
-        let output = if matches.is_empty() {
-            format!(
-                "No matches found for '{}' in {} files",
-                pattern_str, files_searched
-            )
-        } else {
-            let header = format!(
-                "Found {} matches for '{}' in {} files:\n\n",
-                matches.len(),
-                pattern_str,
-                files_searched
-            );
-            let results = matches.join("\n");
-            let truncated = if matches.len() >= limit {
-                format!("\n\n[Results truncated at {} matches]", limit)
-            } else {
-                String::new()
-            };
-            header + &results + &truncated
-        };
+    if output.is_full(query) {
+        // Stopped early, so there is no summary to count searched files from
+        output.files_searched = files_with_matches;
+        return Ok(());
+    }
 
-        Ok(ToolResult::success(output))
+    let result = child.wait_with_output().await?;
+    // Exit code 1 means no matches
+    if !result.status.success() && result.status.code() != Some(1) {
+        anyhow::bail!("rg failed: {}", String::from_utf8_lossy(&result.stderr).trim());
+    }
+    if output.files_searched == 0 {
+        output.files_searched = files_with_matches;
     }
+    Ok(())
 }
 
-fn search_file(
-    path: &std::path::Path,
-    regex: &Regex,
-    matches: &mut Vec<String>,
-    limit: usize,
-    working_dir: &PathBuf,
-) -> Result<()> {
-    // Try to read as text
-    let content = match fs::read_to_string(path) {
-        Ok(c) => c,
-        Err(_) => return Ok(()), // Skip binary files
-    };
-
-    let display_path = path
-        .strip_prefix(working_dir)
-        .map(|p| p.to_path_buf())
-        .unwrap_or_else(|_| path.to_path_buf());
-
-    for (line_num, line) in content.lines().enumerate() {
-        if regex.is_match(line) {
-            matches.push(format!(
-                "{}:{}:{}",
-                display_path.display(),
-                line_num + 1,
-                line.trim()
-            ));
-
-            if matches.len() >= limit {
-                return Ok(());
+/// A note listing the files that weren't searched, so the model knows the
+/// results may be incomplete
+fn skipped_summary(skipped: &[(PathBuf, Skipped)], working_dir: &Path) -> String {
+    if skipped.is_empty() {
+        return String::new();
+    }
+
+    let mut summary = format!("\n\n[Skipped {} file(s) that were not searched:", skipped.len());
+    for (path, reason) in skipped.iter().take(MAX_SKIPPED_LISTED) {
+        let display = path.strip_prefix(working_dir).unwrap_or(path);
+        summary.push_str(&format!("\n  {} ({})", display.display(), reason));
+    }
+    if skipped.len() > MAX_SKIPPED_LISTED {
+        summary.push_str(&format!("\n  ... and {} more", skipped.len() - MAX_SKIPPED_LISTED));
+    }
+    summary.push(']');
+    summary
+}
+
+/// Search one file, memory-mapping it if large; binary files and files
+/// over the size limit are skipped
+fn search_file(query: &GrepQuery, path: &Path, working_dir: &Path, output: &mut GrepOutput) -> std::result::Result<(), Skipped> {
+    let size = fs::metadata(path).map_err(|e| Skipped::Unreadable(e.to_string()))?.len();
+    if size > query.max_file_bytes {
+        return Err(Skipped::TooLarge(size));
+    }
+    let content = read_bytes(path).map_err(|e| Skipped::Unreadable(e.to_string()))?;
+    if is_binary(&content) {
+        return Err(Skipped::Binary);
+    }
+
+    let display_path = path.strip_prefix(working_dir).unwrap_or(path);
+    let text = content.strip_suffix(b"\n").unwrap_or(&content);
+    let lines: Vec<&[u8]> = text.split(|&b| b == b'\n').collect();
+
+    // Context lines after the last match are shown up to this index
+    let mut after_until = 0;
+    // Lines before this index have been shown already
+    let mut shown_until = 0;
+    for (i, line) in lines.iter().enumerate() {
+        if let Some(found) = query.regex.find(line) {
+            if output.is_full(query) {
+                break;
             }
+            for (j, context) in lines.iter().enumerate().take(i).skip(i.saturating_sub(query.before).max(shown_until)) {
+                output.push(query, display_path, j + 1, &String::from_utf8_lossy(context), None);
+            }
+            output.push(query, display_path, i + 1, &String::from_utf8_lossy(line), Some(found.start()));
+            after_until = i + 1 + query.after;
+            shown_until = i + 1;
+        } else if i < after_until {
+            output.push(query, display_path, i + 1, &String::from_utf8_lossy(line), None);
+            shown_until = i + 1;
         }
     }
 
@@ -272,6 +568,57 @@ mod tests {
         assert!(!result.output.contains("test.txt"));
     }
 
+    #[tokio::test]
+    async fn test_grep_skips_binary_and_large_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+
+        fs::write(base.join("app.js"), format!("{}needle{}\n", "x".repeat(5000), "y".repeat(5000))).unwrap();
+        fs::write(base.join("image.png"), b"\x89PNG\0needle").unwrap();
+        fs::write(base.join("big.log"), "needle\n".repeat(4000)).unwrap();
+
+        let ctx = ToolContext::new(base.to_path_buf());
+        let args = json!({ "pattern": "needle", "max_file_kb": 20 });
+        let query = GrepQuery::new("needle", &args).unwrap();
+        let result = grep(&query, base, base, Backend::Builtin).await;
+        assert!(result.success);
+        assert!(result.output.contains("Found 1 matches"));
+        assert!(result.output.contains("[line cut: 10006 bytes]"));
+        assert!(result.output.len() < 1000);
+        assert!(result.output.contains("Skipped 2 file(s)"));
+        assert!(result.output.contains("image.png (binary)"));
+        assert!(result.output.contains("big.log (27 KB, over the size limit)"));
+
+        // Naming a skipped file explains why it wasn't searched
+        let args = json!({ "pattern": "needle", "path": "image.png" });
+        let result = GrepTool.execute(&args, &ctx).await.unwrap();
+        assert!(result.error.unwrap().contains("(binary)"));
+    }
+
+    #[tokio::test]
+    async fn test_grep_context_lines() {
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+        let lines: Vec<String> = (1..=12).map(|i| if i == 3 || i == 10 { format!("match {}", i) } else { format!("line {}", i) }).collect();
+        fs::write(base.join("notes.txt"), lines.join("\n")).unwrap();
+
+        let args = json!({ "pattern": "match", "before": 1, "after": 2 });
+        let query = GrepQuery::new("match", &args).unwrap();
+        let expected = "notes.txt-2-line 2\nnotes.txt:3:match 3\nnotes.txt-4-line 4\nnotes.txt-5-line 5\n--\n\
+                        notes.txt-9-line 9\nnotes.txt:10:match 10\nnotes.txt-11-line 11\nnotes.txt-12-line 12";
+
+        let result = grep(&query, base, base, Backend::Builtin).await;
+        assert!(result.output.contains("Found 2 matches"), "{}", result.output);
+        assert!(result.output.contains(expected), "{}", result.output);
+
+        // ripgrep formats its results the same way
+        if which::which("rg").is_ok() {
+            let result = grep(&query, base, base, Backend::Ripgrep).await;
+            assert!(result.output.contains("Found 2 matches"), "{}", result.output);
+            assert!(result.output.contains(expected), "{}", result.output);
+        }
+    }
+
     #[tokio::test]
     async fn test_grep_no_matches() {
         let temp_dir = TempDir::new().unwrap();
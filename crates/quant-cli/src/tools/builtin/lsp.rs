@@ -0,0 +1,391 @@
+//! Language server tools: definitions, references, and diagnostics
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::lsp::{self, Location, Position};
+use crate::tools::{ParameterProperty, ParameterSchema, SecurityLevel, Tool, ToolContext, ToolResult};
+
+/// Maximum locations listed in one result
+const MAX_LOCATIONS: usize = 100;
+
+/// How long to wait for a freshly started server to finish indexing
+const INDEX_WAIT: Duration = Duration::from_secs(60);
+
+/// How long to wait for diagnostics to be published
+const DIAGNOSTICS_WAIT: Duration = Duration::from_secs(20);
+
+fn position_schema() -> ParameterSchema {
+    ParameterSchema::new()
+        .with_required("path", ParameterProperty::string("File containing the symbol"))
+        .with_property("symbol", ParameterProperty::string("Symbol name to look up (located on `line`, or its first occurrence in the file)"))
+        .with_property("line", ParameterProperty::number("1-based line number of the symbol"))
+        .with_property("column", ParameterProperty::number("1-based column of the symbol (alternative to `symbol`)"))
+}
+
+fn resolve_path(args: &Value, ctx: &ToolContext) -> Result<PathBuf, ToolResult> {
+    let path = args
+        .get("path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| ToolResult::error("Missing required parameter: path"))?;
+    let path = if Path::new(path).is_absolute() {
+        PathBuf::from(path)
+    } else {
+        ctx.working_dir.join(path)
+    };
+    if !path.is_file() {
+        return Err(ToolResult::error(format!("File not found: {}", path.display())));
+    }
+    Ok(path)
+}
+
+/// Resolve the position arguments against the file contents
+fn resolve_position(args: &Value, content: &str) -> Result<Position, String> {
+    let symbol = args.get("symbol").and_then(|v| v.as_str()).filter(|s| !s.is_empty());
+    let line = args.get("line").and_then(|v| v.as_u64()).map(|l| l as usize);
+    let column = args.get("column").and_then(|v| v.as_u64()).map(|c| c as usize);
+    let lines: Vec<&str> = content.lines().collect();
+
+    let (line_idx, char_idx) = match (line, symbol) {
+        (Some(line), _) if line == 0 || line > lines.len() => {
+            return Err(format!("Line {} is out of range (file has {} lines)", line, lines.len()));
+        }
+        (Some(line), Some(symbol)) => {
+            let col = find_word(lines[line - 1], symbol)
+                .ok_or_else(|| format!("'{}' not found on line {}", symbol, line))?;
+            (line - 1, col)
+        }
+        (Some(line), None) => {
+            let text = lines[line - 1];
+            let col = match column {
+                Some(c) => c.saturating_sub(1),
+                None => text.chars().take_while(|c| c.is_whitespace()).count(),
+            };
+            (line - 1, col)
+        }
+        (None, Some(symbol)) => lines
+            .iter()
+            .enumerate()
+            .find_map(|(i, l)| find_word(l, symbol).map(|c| (i, c)))
+            .ok_or_else(|| format!("'{}' not found in file", symbol))?,
+        (None, None) => return Err("Provide `line` and/or `symbol`".to_string()),
+    };
+
+    // LSP columns count UTF-16 code units
+    let character = lines
+        .get(line_idx)
+        .map(|l| l.chars().take(char_idx).map(char::len_utf16).sum::<usize>())
+        .unwrap_or(0);
+
+    Ok(Position {
+        line: line_idx as u32,
+        character: character as u32,
+    })
+}
+
+/// Character index of `word` in `line` at identifier boundaries
+fn find_word(line: &str, word: &str) -> Option<usize> {
+    let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+    line.match_indices(word).find_map(|(byte, _)| {
+        let before = line[..byte].chars().next_back();
+        let after = line[byte + word.len()..].chars().next();
+        if !before.is_some_and(is_ident) && !after.is_some_and(is_ident) {
+            Some(line[..byte].chars().count())
+        } else {
+            None
+        }
+    })
+}
+
+/// Format locations as `path:line:col: source line`
+fn format_locations(locations: &[Location], ctx: &ToolContext) -> String {
+    let mut file_cache: HashMap<&Path, Vec<String>> = HashMap::new();
+    let mut out = String::new();
+
+    for loc in locations.iter().take(MAX_LOCATIONS) {
+        let lines = file_cache.entry(loc.path.as_path()).or_insert_with(|| {
+            std::fs::read_to_string(&loc.path)
+                .map(|c| c.lines().map(String::from).collect())
+                .unwrap_or_default()
+        });
+        let line = loc.range.start.line as usize;
+        let text = lines.get(line).map(|l| l.trim()).unwrap_or("");
+        let display = loc.path.strip_prefix(&ctx.working_dir).unwrap_or(&loc.path);
+        out.push_str(&format!(
+            "{}:{}:{}: {}\n",
+            display.display(),
+            line + 1,
+            loc.range.start.character + 1,
+            text
+        ));
+    }
+
+    if locations.len() > MAX_LOCATIONS {
+        out.push_str(&format!("[{} more locations not shown]\n", locations.len() - MAX_LOCATIONS));
+    }
+    out
+}
+
+/// Start (or reuse) the server for a file and resolve the requested position
+async fn prepare(
+    args: &Value,
+    ctx: &ToolContext,
+) -> Result<Result<(std::sync::Arc<lsp::LspClient>, PathBuf, Position), ToolResult>> {
+    let path = match resolve_path(args, ctx) {
+        Ok(p) => p,
+        Err(result) => return Ok(Err(result)),
+    };
+    let content = std::fs::read_to_string(&path)?;
+    let position = match resolve_position(args, &content) {
+        Ok(p) => p,
+        Err(msg) => return Ok(Err(ToolResult::error(msg))),
+    };
+    let client = match lsp::client_for(&path, &ctx.working_dir).await {
+        Ok(c) => c,
+        Err(e) => return Ok(Err(ToolResult::error(e.to_string()))),
+    };
+    client.wait_until_idle(INDEX_WAIT).await;
+    Ok(Ok((client, path, position)))
+}
+
+fn empty_result(what: &str, client: &lsp::LspClient) -> ToolResult {
+    if client.is_indexing() || client.uptime() < INDEX_WAIT {
+        ToolResult::success(format!(
+            "No {} found. {} may still be indexing the project; retry shortly.",
+            what,
+            client.command()
+        ))
+    } else {
+        ToolResult::success(format!("No {} found.", what))
+    }
+}
+
+/// Tool for jumping to a symbol's definition via the language server
+pub struct FindDefinitionTool;
+
+#[async_trait]
+impl Tool for FindDefinitionTool {
+    fn name(&self) -> &str {
+        "find_definition"
+    }
+
+    fn description(&self) -> &str {
+        "Find where a symbol is defined using the project's language server (rust-analyzer, pyright, \
+         typescript-language-server, gopls). Precise across modules, unlike grep. Give the file and \
+         the symbol name, plus the line where it appears when the name is ambiguous."
+    }
+
+    fn security_level(&self) -> SecurityLevel {
+        SecurityLevel::Safe
+    }
+
+    fn parameters_schema(&self) -> ParameterSchema {
+        position_schema()
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext) -> Result<ToolResult> {
+        let (client, path, position) = match prepare(args, ctx).await? {
+            Ok(prepared) => prepared,
+            Err(result) => return Ok(result),
+        };
+
+        let locations = match client.definition(&path, position).await {
+            Ok(l) => l,
+            Err(e) => return Ok(ToolResult::error(e.to_string())),
+        };
+        if locations.is_empty() {
+            return Ok(empty_result("definition", &client));
+        }
+        Ok(ToolResult::success(format_locations(&locations, ctx)))
+    }
+}
+
+/// Tool for listing every reference to a symbol via the language server
+pub struct FindReferencesTool;
+
+#[async_trait]
+impl Tool for FindReferencesTool {
+    fn name(&self) -> &str {
+        "find_references"
+    }
+
+    fn description(&self) -> &str {
+        "Find all references to a symbol using the project's language server. Use this before renaming \
+         or changing a signature to see every call site. Give the file and symbol name (and line if ambiguous)."
+    }
+
+    fn security_level(&self) -> SecurityLevel {
+        SecurityLevel::Safe
+    }
+
+    fn parameters_schema(&self) -> ParameterSchema {
+        position_schema().with_property(
+            "include_declaration",
+            ParameterProperty::boolean("Include the declaration itself (default: true)"),
+        )
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext) -> Result<ToolResult> {
+        let include_declaration = args.get("include_declaration").and_then(|v| v.as_bool()).unwrap_or(true);
+        let (client, path, position) = match prepare(args, ctx).await? {
+            Ok(prepared) => prepared,
+            Err(result) => return Ok(result),
+        };
+
+        let mut locations = match client.references(&path, position, include_declaration).await {
+            Ok(l) => l,
+            Err(e) => return Ok(ToolResult::error(e.to_string())),
+        };
+        if locations.is_empty() {
+            return Ok(empty_result("references", &client));
+        }
+        locations.sort_by(|a, b| a.path.cmp(&b.path).then(a.range.start.line.cmp(&b.range.start.line)));
+
+        let files = locations.iter().map(|l| &l.path).collect::<std::collections::HashSet<_>>().len();
+        let mut output = format!("{} references in {} files\n", locations.len(), files);
+        output.push_str(&format_locations(&locations, ctx));
+        Ok(ToolResult::success(output))
+    }
+}
+
+/// Tool for reporting compiler/type-checker diagnostics for a file
+pub struct DiagnosticsTool;
+
+#[async_trait]
+impl Tool for DiagnosticsTool {
+    fn name(&self) -> &str {
+        "diagnostics"
+    }
+
+    fn description(&self) -> &str {
+        "Get errors and warnings for a file from the project's language server. Faster than a full \
+         build for checking whether an edit type-checks."
+    }
+
+    fn security_level(&self) -> SecurityLevel {
+        SecurityLevel::Safe
+    }
+
+    fn parameters_schema(&self) -> ParameterSchema {
+        ParameterSchema::new()
+            .with_required("path", ParameterProperty::string("File to check"))
+            .with_property(
+                "severity",
+                ParameterProperty::string("Minimum severity to report (default: warning)")
+                    .with_enum(vec!["error".into(), "warning".into(), "info".into(), "hint".into()]),
+            )
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext) -> Result<ToolResult> {
+        let path = match resolve_path(args, ctx) {
+            Ok(p) => p,
+            Err(result) => return Ok(result),
+        };
+        let max_severity = match args.get("severity").and_then(|v| v.as_str()).unwrap_or("warning") {
+            "error" => 1,
+            "info" => 3,
+            "hint" => 4,
+            _ => 2,
+        };
+
+        let client = match lsp::client_for(&path, &ctx.working_dir).await {
+            Ok(c) => c,
+            Err(e) => return Ok(ToolResult::error(e.to_string())),
+        };
+        // A cold server publishes only after its initial indexing pass
+        let wait = if client.uptime() < INDEX_WAIT { INDEX_WAIT } else { DIAGNOSTICS_WAIT };
+        let diagnostics = match client.diagnostics(&path, wait).await {
+            Ok(d) => d,
+            Err(e) => return Ok(ToolResult::error(e.to_string())),
+        };
+
+        let display = path.strip_prefix(&ctx.working_dir).unwrap_or(&path);
+        let mut shown: Vec<_> = diagnostics
+            .iter()
+            .filter(|d| d.severity.unwrap_or(1) <= max_severity)
+            .collect();
+        if shown.is_empty() {
+            return Ok(ToolResult::success(format!("No diagnostics for {}", display.display())));
+        }
+        shown.sort_by_key(|d| (d.severity.unwrap_or(1), d.range.start.line));
+
+        let mut output = String::new();
+        for d in shown {
+            let code = d
+                .code
+                .as_ref()
+                .map(|c| match c {
+                    Value::String(s) => format!("[{}] ", s),
+                    other => format!("[{}] ", other),
+                })
+                .unwrap_or_default();
+            let source = d.source.as_deref().map(|s| format!(" ({})", s)).unwrap_or_default();
+            output.push_str(&format!(
+                "{}:{}:{}: {}{}: {}{}\n",
+                display.display(),
+                d.range.start.line + 1,
+                d.range.start.character + 1,
+                d.severity_label(),
+                source,
+                code,
+                d.message.lines().next().unwrap_or("")
+            ));
+        }
+        Ok(ToolResult::success(output))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lsp::client::Range;
+    use serde_json::json;
+
+    #[test]
+    fn test_resolve_position() {
+        let content = "use crate::config;\n\nfn load_config() -> Config {\n    let config = Config::default();\n}\n";
+
+        let pos = resolve_position(&json!({ "symbol": "Config", "line": 4 }), content).unwrap();
+        assert_eq!((pos.line, pos.character), (3, 17));
+
+        // First whole-word occurrence, skipping `load_config`
+        let pos = resolve_position(&json!({ "symbol": "config" }), content).unwrap();
+        assert_eq!((pos.line, pos.character), (0, 11));
+
+        let pos = resolve_position(&json!({ "line": 4 }), content).unwrap();
+        assert_eq!(pos.character, 4);
+
+        let pos = resolve_position(&json!({ "line": 3, "column": 4 }), content).unwrap();
+        assert_eq!(pos.character, 3);
+
+        assert!(resolve_position(&json!({ "line": 99 }), content).is_err());
+        assert!(resolve_position(&json!({ "symbol": "missing" }), content).is_err());
+        assert!(resolve_position(&json!({}), content).is_err());
+    }
+
+    #[test]
+    fn test_resolve_position_utf16() {
+        let pos = resolve_position(&json!({ "symbol": "x", "line": 1 }), "let s = \"😀\"; x").unwrap();
+        // The emoji is one char but two UTF-16 code units
+        assert_eq!(pos.character, 14);
+    }
+
+    #[test]
+    fn test_format_locations() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let file = dir.path().join("lib.rs");
+        std::fs::write(&file, "fn a() {}\n    fn b() {}\n").unwrap();
+        let ctx = ToolContext::new(dir.path().to_path_buf());
+
+        let at = |line, character| Position { line, character };
+        let locations = vec![Location {
+            path: file,
+            range: Range { start: at(1, 7), end: at(1, 8) },
+        }];
+        assert_eq!(format_locations(&locations, &ctx), "lib.rs:2:8: fn b() {}\n");
+    }
+}
@@ -1,48 +1,80 @@
 //! Built-in tools for the agent framework
 
 mod bash;
+mod dependency_info;
 mod file_read;
 mod file_write;
 mod git;
 mod glob;
 mod grep;
+mod lsp;
 mod multi_edit;
+mod outline;
+mod process;
+mod rust_docs;
 mod sandbox;
+mod scratch;
+mod text_file;
+mod wasm;
 mod web_fetch;
 mod web_search;
 
 pub use bash::BashTool;
+pub use dependency_info::DependencyInfoTool;
 pub use file_read::FileReadTool;
 pub use file_write::FileWriteTool;
 pub use git::GitTool;
 pub use glob::GlobTool;
 pub use grep::GrepTool;
+pub use lsp::{DiagnosticsTool, FindDefinitionTool, FindReferencesTool};
 pub use multi_edit::MultiEditTool;
+pub use outline::OutlineTool;
+pub use process::{ProcessKillTool, ProcessListTool, ProcessOutputTool, ProcessStartTool};
+pub use rust_docs::RustDocsTool;
 pub use sandbox::{SandboxBackend, SandboxConfig, SandboxTool};
-pub use web_fetch::WebFetchTool;
-pub use web_search::WebSearchTool;
+pub use scratch::ScratchTool;
+pub use web_fetch::{html_to_text, WebFetchTool};
+pub use web_search::{SearchProviderKind, WebSearchTool};
 
 use super::registry::ToolRegistry;
+use llm_core::process::BackgroundJobs;
+use std::sync::Arc;
 
 /// Create a registry with all default tools
 pub fn create_default_registry() -> ToolRegistry {
     let mut registry = ToolRegistry::new();
+    // Background jobs live as long as this registry's agent session
+    let jobs = Arc::new(BackgroundJobs::new());
 
     // Safe tools (no confirmation needed)
     registry.register(FileReadTool);
     registry.register(GlobTool);
     registry.register(GrepTool);
+    registry.register(OutlineTool);
+    registry.register(DependencyInfoTool);
+    registry.register(FindDefinitionTool);
+    registry.register(FindReferencesTool);
+    registry.register(DiagnosticsTool);
+    registry.register(ProcessListTool::new(jobs.clone()));
+    registry.register(ProcessOutputTool::new(jobs.clone()));
 
-    // Moderate tools (network access, git operations)
+    // Moderate tools (network access, git operations, scratch files)
     registry.register(WebFetchTool::new());
-    registry.register(WebSearchTool);
+    registry.register(WebSearchTool::new());
+    registry.register(RustDocsTool);
     registry.register(GitTool::new());
+    registry.register(ProcessKillTool::new(jobs.clone()));
+    registry.register(ScratchTool::new());
 
     // Dangerous tools (write/execute)
     registry.register(FileWriteTool);
     registry.register(MultiEditTool);
     registry.register(BashTool);
     registry.register(SandboxTool::new());
+    registry.register(ProcessStartTool::new(jobs));
+
+    // Tools from plugin libraries
+    crate::tools::plugins::loaded().register(&mut registry);
 
     registry
 }
@@ -54,6 +86,7 @@ pub fn create_safe_registry() -> ToolRegistry {
     registry.register(FileReadTool);
     registry.register(GlobTool);
     registry.register(GrepTool);
+    registry.register(OutlineTool);
 
     registry
 }
@@ -7,10 +7,11 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fs;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use tracing::{debug, info, warn};
 
-use crate::tools::{ParameterProperty, ParameterSchema, SecurityLevel, Tool, ToolContext, ToolResult};
+use crate::tools::{dry_run, ParameterProperty, ParameterSchema, SecurityLevel, Tool, ToolContext, ToolResult};
 
 /// Tool for atomically editing multiple files
 pub struct MultiEditTool;
@@ -109,6 +110,25 @@ impl Tool for MultiEditTool {
             .and_then(|v| v.as_str())
             .unwrap_or("Multi-file edit");
 
+        // Hold the edits for review; they are still all-or-nothing
+        if let Some(ref staging) = ctx.staging {
+            let files = match edit_in_memory(&edits, ctx) {
+                Ok(files) => files,
+                Err(e) => return Ok(ToolResult::error(e)),
+            };
+            let mut staged = Vec::new();
+            for (path, (_, content)) in files {
+                staged.push(format!("  - {} ({} bytes)", path.display(), content.len()));
+                staging.stage(&path, content);
+            }
+            info!(file_count = staged.len(), description, "Staged multi-file edit for review");
+            return Ok(ToolResult::success(format!(
+                "Staged {} file(s); they are written once the user accepts them in review:\n{}",
+                staged.len(),
+                staged.join("\n")
+            )));
+        }
+
         info!(edit_count = edits.len(), description, "Starting atomic multi-file edit");
 
         // Phase 1: Validate all edits and capture backups
@@ -122,20 +142,19 @@ impl Tool for MultiEditTool {
                 ctx.working_dir.join(&edit.path)
             };
 
-            // Validate path is within working directory
-            let canonical_ctx = ctx.working_dir.canonicalize()
-                .map_err(|e| anyhow::anyhow!("Failed to resolve working directory: {}", e))?;
-
-            if path.exists() {
-                let canonical_path = path.canonicalize()
-                    .map_err(|e| anyhow::anyhow!("Failed to resolve path {}: {}", edit.path, e))?;
+            if crate::context::repos::is_repo_path(&path) {
+                return Ok(ToolResult::error(format!(
+                    "{} is in a read-only remote repo checkout (quant context add-repo)",
+                    edit.path
+                )));
+            }
 
-                if !canonical_path.starts_with(&canonical_ctx) {
-                    return Ok(ToolResult::error(format!(
-                        "Path {} is outside working directory",
-                        edit.path
-                    )));
-                }
+            // Validate path is within working directory (new files included)
+            if !crate::paths::is_within(&path, &ctx.working_dir) {
+                return Ok(ToolResult::error(format!(
+                    "Path {} is outside working directory",
+                    edit.path
+                )));
             }
 
             // Check if file exists when old_content is specified
@@ -224,6 +243,81 @@ impl Tool for MultiEditTool {
 
         Ok(ToolResult::success(summary))
     }
+
+    fn simulate(&self, args: &Value, ctx: &ToolContext) -> ToolResult {
+        let edits: Vec<FileEdit> = match args.get("edits").map(|v| serde_json::from_value(v.clone())) {
+            Some(Ok(edits)) => edits,
+            Some(Err(e)) => return ToolResult::error(format!("Invalid edits format: {}", e)),
+            None => return ToolResult::error("Missing required parameter: edits"),
+        };
+        let files = match edit_in_memory(&edits, ctx) {
+            Ok(files) => files,
+            Err(e) => return ToolResult::error(format!("{}; the edit would fail", e)),
+        };
+
+        let diffs: Vec<String> = files
+            .iter()
+            .map(|(path, (original, content))| {
+                let name = path.strip_prefix(&ctx.working_dir).unwrap_or(path).display().to_string();
+                dry_run::diff_preview(&name, original.as_deref(), content)
+            })
+            .collect();
+        dry_run::simulated(format!("Would edit {} file(s)", files.len()), diffs.join("\n"))
+    }
+
+    fn preview(&self, args: &Value, ctx: &ToolContext) -> Option<String> {
+        let edits: Vec<FileEdit> = serde_json::from_value(args.get("edits")?.clone()).ok()?;
+        let files = edit_in_memory(&edits, ctx).ok()?;
+        let mut lines = Vec::new();
+        if let Some(description) = args.get("description").and_then(|v| v.as_str()) {
+            lines.push(description.to_string());
+        }
+        for (path, (original, content)) in &files {
+            let name = path.strip_prefix(&ctx.working_dir).unwrap_or(path).display().to_string();
+            lines.push(dry_run::diff_preview(&name, original.as_deref(), content));
+        }
+        Some(lines.join("\n"))
+    }
+}
+
+/// Apply `edits` to the files' current content (staged content if staging)
+/// without writing anything, returning each file's current and new content
+fn edit_in_memory(
+    edits: &[FileEdit],
+    ctx: &ToolContext,
+) -> std::result::Result<BTreeMap<PathBuf, (Option<String>, String)>, String> {
+    let mut files: BTreeMap<PathBuf, (Option<String>, String)> = BTreeMap::new();
+    for edit in edits {
+        let path = ctx.working_dir.join(&edit.path);
+        if crate::context::repos::is_repo_path(&path) {
+            return Err(format!("{} is in a read-only remote repo checkout (quant context add-repo)", edit.path));
+        }
+        if !crate::paths::is_within(&path, &ctx.working_dir) {
+            return Err(format!("Path {} is outside working directory", edit.path));
+        }
+
+        let (current, content) = files.entry(path).or_insert_with_key(|path| {
+            let current = match ctx.staging {
+                Some(ref staging) => staging.read(path),
+                None => fs::read_to_string(path).ok(),
+            };
+            (current.clone(), current.unwrap_or_default())
+        });
+        match edit.old_content {
+            Some(_) if current.is_none() => {
+                return Err(format!("File {} does not exist but old_content was specified", edit.path));
+            }
+            Some(ref old) if !content.contains(old.as_str()) => {
+                return Err(format!("File {} does not contain expected old_content", edit.path));
+            }
+            Some(ref old) => *content = content.replace(old.as_str(), &edit.new_content),
+            None if current.is_none() && !edit.create_if_missing => {
+                return Err(format!("File {} does not exist and create_if_missing is false", edit.path));
+            }
+            None => *content = edit.new_content.clone(),
+        }
+    }
+    Ok(files)
 }
 
 /// Apply a single edit to a file
@@ -404,4 +498,32 @@ mod tests {
         assert!(!result.success);
         assert!(result.error.unwrap().contains("No edits"));
     }
+
+    #[tokio::test]
+    async fn test_staged_edits() {
+        use crate::tools::builtin::FileReadTool;
+        use crate::tools::staging::StagingArea;
+        use std::sync::Arc;
+
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join("lib.rs");
+        fs::write(&file, "fn old() {}\n").unwrap();
+        let staging = Arc::new(StagingArea::new());
+        let ctx = ToolContext::new(temp_dir.path().to_path_buf()).with_staging(Some(staging.clone()));
+
+        let edit = |old: &str, new: &str| {
+            json!({"edits": [{"path": "lib.rs", "old_content": old, "new_content": new}]})
+        };
+        let result = MultiEditTool.execute(&edit("old", "new"), &ctx).await.unwrap();
+        assert!(result.success, "{:?}", result.error);
+        // Later edits and reads see the staged content; the disk doesn't change
+        assert!(MultiEditTool.execute(&edit("new()", "newer()"), &ctx).await.unwrap().success);
+        let read = FileReadTool.execute(&json!({"path": "lib.rs"}), &ctx).await.unwrap();
+        assert!(read.output.contains("fn newer() {}"));
+        assert_eq!(fs::read_to_string(&file).unwrap(), "fn old() {}\n");
+
+        let staged = staging.take();
+        assert_eq!(staged[0].original.as_deref(), Some("fn old() {}\n"));
+        assert_eq!(staged[0].content, "fn newer() {}\n");
+    }
 }
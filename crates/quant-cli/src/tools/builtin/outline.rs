@@ -0,0 +1,167 @@
+//! Code outline tool
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::context::FileOutline;
+use crate::tools::{ParameterProperty, ParameterSchema, SecurityLevel, Tool, ToolContext, ToolResult};
+
+/// Directories never descended into
+const SKIP_DIRS: &[&str] = &["target", "node_modules", ".git", "dist", "build", "__pycache__", "venv", ".venv", "vendor"];
+
+/// Files larger than this are skipped in directory outlines
+const MAX_FILE_BYTES: u64 = 512 * 1024;
+
+/// Tool for listing the symbols declared in a file or directory
+pub struct OutlineTool;
+
+#[async_trait]
+impl Tool for OutlineTool {
+    fn name(&self) -> &str {
+        "outline"
+    }
+
+    fn description(&self) -> &str {
+        "Show a compact symbol map (functions, structs, classes, traits, methods with line numbers) of a \
+         source file or directory. Use it to understand code structure before reading whole files."
+    }
+
+    fn security_level(&self) -> SecurityLevel {
+        SecurityLevel::Safe
+    }
+
+    fn parameters_schema(&self) -> ParameterSchema {
+        ParameterSchema::new()
+            .with_property("path", ParameterProperty::string("File or directory to outline (default: working directory)"))
+            .with_property("max_files", ParameterProperty::number("Maximum files to outline for a directory (default: 50)").with_default(Value::Number(50.into())))
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext) -> Result<ToolResult> {
+        let path = args.get("path")
+            .and_then(|v| v.as_str())
+            .map(|p| {
+                if PathBuf::from(p).is_absolute() {
+                    PathBuf::from(p)
+                } else {
+                    ctx.working_dir.join(p)
+                }
+            })
+            .unwrap_or_else(|| ctx.working_dir.clone());
+
+        let max_files = args.get("max_files")
+            .and_then(|v| v.as_u64())
+            .map(|v| v as usize)
+            .unwrap_or(50);
+
+        if !path.exists() {
+            return Ok(ToolResult::error(format!("Path not found: {}", path.display())));
+        }
+
+        let files: Vec<PathBuf> = if path.is_file() {
+            vec![path.clone()]
+        } else {
+            source_files(&path)
+        };
+
+        let mut output = String::new();
+        let mut outlined = 0;
+        for file in &files {
+            if outlined >= max_files {
+                break;
+            }
+            let Ok(content) = fs::read_to_string(file) else { continue };
+            let Some(outline) = FileOutline::from_content(file, &content) else { continue };
+            if outline.symbols.is_empty() && files.len() > 1 {
+                continue;
+            }
+
+            let display = file.strip_prefix(&ctx.working_dir).unwrap_or(file);
+            output.push_str(&format!("{} ({} lines)\n", display.display(), outline.line_count));
+            output.push_str(&outline.render());
+            outlined += 1;
+
+            if output.len() > ctx.max_output_len {
+                output.push_str("\n[Outline truncated; narrow the path for more]\n");
+                break;
+            }
+        }
+
+        if outlined == 0 {
+            return Ok(ToolResult::error(format!(
+                "No supported source files found at {}",
+                path.display()
+            )));
+        }
+
+        let remaining = files.len().saturating_sub(outlined);
+        if path.is_dir() && remaining > 0 && outlined >= max_files {
+            output.push_str(&format!("\n[{} more files; raise max_files or narrow the path]\n", remaining));
+        }
+
+        Ok(ToolResult::success(output))
+    }
+}
+
+/// Source files under a directory, sorted, skipping build and dependency dirs
+fn source_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|e| {
+            e.depth() == 0
+                || !e.file_type().is_dir()
+                || !SKIP_DIRS.contains(&e.file_name().to_string_lossy().as_ref())
+        })
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| e.metadata().map(|m| m.len() <= MAX_FILE_BYTES).unwrap_or(false))
+        .map(|e| e.into_path())
+        .filter(|p| crate::context::outline::Language::from_path(p).is_some())
+        .collect();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_outline_directory() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::create_dir_all(dir.path().join("target/debug")).unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "pub struct Engine;\n\nimpl Engine {\n    pub fn run(&self) {}\n}\n").unwrap();
+        fs::write(dir.path().join("src/util.py"), "def helper():\n    pass\n").unwrap();
+        fs::write(dir.path().join("target/debug/build.rs"), "fn hidden() {}\n").unwrap();
+        fs::write(dir.path().join("README.md"), "# Readme\n").unwrap();
+
+        let tool = OutlineTool;
+        let ctx = ToolContext::new(dir.path().to_path_buf());
+
+        let result = tool.execute(&json!({}), &ctx).await.unwrap();
+        assert!(result.success);
+        assert!(result.output.contains("src/lib.rs (5 lines)\n  struct Engine :1\n  impl Engine :3\n    method run :4"));
+        assert!(result.output.contains("fn helper :1"));
+        assert!(!result.output.contains("hidden"));
+
+        let result = tool.execute(&json!({ "max_files": 1 }), &ctx).await.unwrap();
+        assert!(result.output.contains("[1 more files"));
+    }
+
+    #[tokio::test]
+    async fn test_outline_unsupported_file() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("notes.txt"), "hello").unwrap();
+
+        let tool = OutlineTool;
+        let ctx = ToolContext::new(dir.path().to_path_buf());
+        let result = tool.execute(&json!({ "path": "notes.txt" }), &ctx).await.unwrap();
+        assert!(!result.success);
+    }
+}
@@ -0,0 +1,341 @@
+//! Background process tools: start, list, read output from and kill
+//! long-running commands such as dev servers and watchers
+//!
+//! The tools share one [`BackgroundJobs`] registry per tool registry, so
+//! jobs belong to the agent session that started them and are terminated
+//! when it ends. Jobs are also recorded in the run manifest, so they are
+//! stopped on exit and found again if quant crashes.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use llm_core::process::{BackgroundJobs, JobInfo, JobOutput};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+
+use crate::procs::ProcessKind;
+use crate::tools::{dry_run, ParameterProperty, ParameterSchema, SecurityLevel, Tool, ToolContext, ToolResult};
+
+/// Output lines returned when the caller doesn't ask for a number
+const DEFAULT_OUTPUT_LINES: usize = 50;
+
+/// Seconds `process_start` waits for early output (or an early exit)
+const DEFAULT_START_WAIT_SECS: u64 = 2;
+
+/// Start a long-running command in the background
+pub struct ProcessStartTool {
+    jobs: Arc<BackgroundJobs>,
+}
+
+/// List background jobs
+pub struct ProcessListTool {
+    jobs: Arc<BackgroundJobs>,
+}
+
+/// Read recent output of a background job
+pub struct ProcessOutputTool {
+    jobs: Arc<BackgroundJobs>,
+}
+
+/// Kill a background job
+pub struct ProcessKillTool {
+    jobs: Arc<BackgroundJobs>,
+}
+
+impl ProcessStartTool {
+    pub fn new(jobs: Arc<BackgroundJobs>) -> Self {
+        Self { jobs }
+    }
+}
+
+impl ProcessListTool {
+    pub fn new(jobs: Arc<BackgroundJobs>) -> Self {
+        Self { jobs }
+    }
+}
+
+impl ProcessOutputTool {
+    pub fn new(jobs: Arc<BackgroundJobs>) -> Self {
+        Self { jobs }
+    }
+}
+
+impl ProcessKillTool {
+    pub fn new(jobs: Arc<BackgroundJobs>) -> Self {
+        Self { jobs }
+    }
+}
+
+#[async_trait]
+impl Tool for ProcessStartTool {
+    fn name(&self) -> &str {
+        "process_start"
+    }
+
+    fn description(&self) -> &str {
+        "Start a long-running command (dev server, file watcher, etc.) in the background and return its job id. Use process_output to read its output and process_kill to stop it. Use bash for commands that finish on their own."
+    }
+
+    fn security_level(&self) -> SecurityLevel {
+        SecurityLevel::Dangerous
+    }
+
+    fn parameters_schema(&self) -> ParameterSchema {
+        ParameterSchema::new()
+            .with_required("command", ParameterProperty::string("The bash command to run in the background"))
+            .with_property("working_dir", ParameterProperty::string("Working directory for the command (default: current directory)"))
+            .with_property(
+                "wait",
+                ParameterProperty::number("Seconds to wait for initial output before returning (default: 2)")
+                    .with_default(Value::Number(DEFAULT_START_WAIT_SECS.into())),
+            )
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext) -> Result<ToolResult> {
+        let command = args.get("command")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow::anyhow!("Missing required parameter: command"))?;
+        let wait = args.get("wait")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(DEFAULT_START_WAIT_SECS);
+
+        let working_dir = match resolve_working_dir(args, ctx) {
+            Ok(dir) => dir,
+            Err(e) => return Ok(ToolResult::error(e)),
+        };
+
+        let job = match self.jobs.start(command, &working_dir) {
+            Ok(job) => job,
+            Err(e) => return Ok(ToolResult::error(format!("{:#}", e))),
+        };
+        crate::procs::track(job.pid, ProcessKind::Job, command);
+
+        sleep(Duration::from_secs(wait)).await;
+        let (job, output) = self.jobs.output(job.id, 0, DEFAULT_OUTPUT_LINES)?;
+
+        Ok(ToolResult::success(format!(
+            "Started background job {}\n{}",
+            job.id,
+            format_output(&job, &output)
+        )))
+    }
+
+    fn simulate(&self, args: &Value, ctx: &ToolContext) -> ToolResult {
+        let Some(command) = args.get("command").and_then(|v| v.as_str()) else {
+            return ToolResult::error("Missing required parameter: command");
+        };
+        match resolve_working_dir(args, ctx) {
+            Ok(dir) => dry_run::simulated(
+                format!("Would start in the background in {}:", dir.display()),
+                format!("$ {}", command),
+            ),
+            Err(e) => ToolResult::error(e),
+        }
+    }
+
+    fn preview(&self, args: &Value, _ctx: &ToolContext) -> Option<String> {
+        let mut lines = vec![format!("$ {}", args.get("command")?.as_str()?), "in the background".to_string()];
+        if let Some(dir) = args.get("working_dir").and_then(|v| v.as_str()) {
+            lines.push(format!("in {}", dir));
+        }
+        Some(lines.join("\n"))
+    }
+}
+
+#[async_trait]
+impl Tool for ProcessListTool {
+    fn name(&self) -> &str {
+        "process_list"
+    }
+
+    fn description(&self) -> &str {
+        "List the background jobs started with process_start, with their status and how long they have run."
+    }
+
+    fn security_level(&self) -> SecurityLevel {
+        SecurityLevel::Safe
+    }
+
+    fn parameters_schema(&self) -> ParameterSchema {
+        ParameterSchema::new()
+    }
+
+    async fn execute(&self, _args: &Value, _ctx: &ToolContext) -> Result<ToolResult> {
+        let jobs = self.jobs.list();
+        if jobs.is_empty() {
+            return Ok(ToolResult::success("No background jobs"));
+        }
+
+        let lines: Vec<String> = jobs.iter().map(format_job).collect();
+        Ok(ToolResult::success(lines.join("\n")))
+    }
+}
+
+#[async_trait]
+impl Tool for ProcessOutputTool {
+    fn name(&self) -> &str {
+        "process_output"
+    }
+
+    fn description(&self) -> &str {
+        "Read the recent output (stdout and stderr) of a background job. Pass the returned cursor as 'since' to read only new lines."
+    }
+
+    fn security_level(&self) -> SecurityLevel {
+        SecurityLevel::Safe
+    }
+
+    fn parameters_schema(&self) -> ParameterSchema {
+        ParameterSchema::new()
+            .with_required("id", ParameterProperty::number("Job id from process_start"))
+            .with_property("since", ParameterProperty::number("Cursor from an earlier read; only lines after it are returned (default: 0)"))
+            .with_property(
+                "lines",
+                ParameterProperty::number("Maximum number of lines to return, newest last (default: 50)")
+                    .with_default(Value::Number(DEFAULT_OUTPUT_LINES.into())),
+            )
+    }
+
+    async fn execute(&self, args: &Value, _ctx: &ToolContext) -> Result<ToolResult> {
+        let id = job_id(args)?;
+        let since = args.get("since").and_then(|v| v.as_u64()).unwrap_or(0);
+        let limit = args.get("lines")
+            .and_then(|v| v.as_u64())
+            .map(|n| n as usize)
+            .unwrap_or(DEFAULT_OUTPUT_LINES);
+
+        match self.jobs.output(id, since, limit) {
+            Ok((job, output)) => Ok(ToolResult::success(format_output(&job, &output))),
+            Err(e) => Ok(ToolResult::error(e.to_string())),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for ProcessKillTool {
+    fn name(&self) -> &str {
+        "process_kill"
+    }
+
+    fn description(&self) -> &str {
+        "Stop a background job started with process_start, along with any processes it spawned."
+    }
+
+    fn security_level(&self) -> SecurityLevel {
+        SecurityLevel::Moderate
+    }
+
+    fn parameters_schema(&self) -> ParameterSchema {
+        ParameterSchema::new()
+            .with_required("id", ParameterProperty::number("Job id from process_start"))
+    }
+
+    async fn execute(&self, args: &Value, _ctx: &ToolContext) -> Result<ToolResult> {
+        let id = job_id(args)?;
+
+        match self.jobs.kill(id).await {
+            Ok(job) => {
+                crate::procs::untrack(job.pid);
+                Ok(ToolResult::success(format!("Killed background job {}: {}", job.id, job.command)))
+            }
+            Err(e) => Ok(ToolResult::error(e.to_string())),
+        }
+    }
+}
+
+fn job_id(args: &Value) -> Result<u32> {
+    args.get("id")
+        .and_then(|v| v.as_u64())
+        .map(|id| id as u32)
+        .ok_or_else(|| anyhow::anyhow!("Missing required parameter: id"))
+}
+
+/// The requested working directory, which must be inside the context's
+fn resolve_working_dir(args: &Value, ctx: &ToolContext) -> std::result::Result<PathBuf, String> {
+    let working_dir = args.get("working_dir")
+        .and_then(|v| v.as_str())
+        .map(|dir| ctx.working_dir.join(dir))
+        .unwrap_or_else(|| ctx.working_dir.clone());
+
+    let canonical = crate::paths::canonicalize(&working_dir)
+        .map_err(|e| format!("Failed to resolve working directory {}: {}", working_dir.display(), e))?;
+    let allowed = crate::paths::canonicalize(&ctx.working_dir)
+        .map_err(|e| format!("Failed to resolve context directory: {}", e))?;
+    if !canonical.starts_with(&allowed) {
+        return Err(format!(
+            "Path traversal denied: {} is outside allowed directory {}",
+            working_dir.display(),
+            ctx.working_dir.display()
+        ));
+    }
+    Ok(canonical)
+}
+
+/// e.g. "[1] running 2m 5s  npm run dev"
+fn format_job(job: &JobInfo) -> String {
+    let elapsed = job.started_at.elapsed().as_secs();
+    let elapsed = if elapsed >= 60 {
+        format!("{}m {}s", elapsed / 60, elapsed % 60)
+    } else {
+        format!("{}s", elapsed)
+    };
+    format!("[{}] {} {}  {}", job.id, job.status, elapsed, job.command)
+}
+
+fn format_output(job: &JobInfo, output: &JobOutput) -> String {
+    let mut text = format_job(job);
+    if output.skipped > 0 {
+        text.push_str(&format!("\n... {} earlier line(s) not shown", output.skipped));
+    }
+    if output.lines.is_empty() {
+        text.push_str("\n(no new output)");
+    } else {
+        text.push('\n');
+        text.push_str(&output.lines.join("\n"));
+    }
+    text.push_str(&format!("\n[cursor: {}]", output.next));
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_process_tools_start_read_kill() {
+        let jobs = Arc::new(BackgroundJobs::new());
+        let ctx = ToolContext::default();
+
+        let result = ProcessStartTool::new(jobs.clone())
+            .execute(&json!({ "command": "echo listening; sleep 30", "wait": 1 }), &ctx)
+            .await
+            .unwrap();
+        assert!(result.success);
+        assert!(result.output.starts_with("Started background job 1\n[1] running"));
+        assert!(result.output.contains("listening"));
+
+        let list = ProcessListTool::new(jobs.clone()).execute(&json!({}), &ctx).await.unwrap();
+        assert!(list.output.contains("echo listening; sleep 30"));
+
+        let output = ProcessOutputTool::new(jobs.clone())
+            .execute(&json!({ "id": 1, "since": 1 }), &ctx)
+            .await
+            .unwrap();
+        assert!(output.output.contains("(no new output)"));
+
+        let kill = ProcessKillTool::new(jobs.clone());
+        assert!(kill.execute(&json!({ "id": 1 }), &ctx).await.unwrap().success);
+        assert!(!kill.execute(&json!({ "id": 1 }), &ctx).await.unwrap().success);
+        assert_eq!(jobs.list()[0].status.to_string(), "killed");
+    }
+
+    #[test]
+    fn test_resolve_working_dir_rejects_escape() {
+        let ctx = ToolContext::default();
+        assert!(resolve_working_dir(&json!({ "working_dir": ".." }), &ctx).is_err());
+        assert!(resolve_working_dir(&json!({}), &ctx).is_ok());
+    }
+}
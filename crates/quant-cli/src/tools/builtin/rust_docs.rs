@@ -0,0 +1,549 @@
+//! Rust API documentation lookup tool
+//!
+//! Looks up crate and item documentation from a locally built `cargo doc`
+//! output (`target/doc`) or from docs.rs, returning the item signature, a doc
+//! excerpt, and its methods. Versions are taken from the project's Cargo.lock
+//! so the docs match the dependencies actually in use.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use scraper::{Html, Selector};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::debug;
+
+use super::web_fetch::{extract_text_from_element, get_shared_client};
+use crate::tools::{ParameterProperty, ParameterSchema, SecurityLevel, Tool, ToolContext, ToolResult};
+
+/// Maximum characters of doc text returned
+const MAX_DOC_CHARS: usize = 4000;
+
+/// Maximum number of methods or items listed
+const MAX_LISTED: usize = 40;
+
+/// Where documentation is read from
+#[derive(Debug, Clone, PartialEq)]
+enum DocSource {
+    /// `target/doc/<crate>` from `cargo doc`
+    Local(PathBuf),
+    /// `https://docs.rs/<crate>/<version>/<crate>/`
+    DocsRs(String),
+}
+
+impl DocSource {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Local(_) => "local cargo doc",
+            Self::DocsRs(_) => "docs.rs",
+        }
+    }
+
+    /// Fetch a page relative to the crate's doc root
+    async fn fetch(&self, page: &str, ctx: &ToolContext) -> Result<String> {
+        match self {
+            Self::Local(root) => Ok(fs::read_to_string(root.join(page))?),
+            Self::DocsRs(base) => {
+                let url = format!("{}{}", base, page);
+                debug!(%url, "Fetching docs.rs page");
+                let response = get_shared_client()
+                    .get(&url)
+                    .timeout(Duration::from_secs(ctx.http_timeout_secs))
+                    .send()
+                    .await?;
+                if !response.status().is_success() {
+                    anyhow::bail!("HTTP {} for {}", response.status(), url);
+                }
+                Ok(response.text().await?)
+            }
+        }
+    }
+}
+
+/// An entry from a crate's `all.html` item list
+#[derive(Debug, Clone, PartialEq)]
+struct ItemLink {
+    /// Path within the crate, e.g. `sync::Mutex`
+    path: String,
+    /// Page relative to the crate doc root, e.g. `sync/struct.Mutex.html`
+    href: String,
+}
+
+/// Tool for looking up Rust crate documentation
+pub struct RustDocsTool;
+
+#[async_trait]
+impl Tool for RustDocsTool {
+    fn name(&self) -> &str {
+        "rust_docs"
+    }
+
+    fn description(&self) -> &str {
+        "Look up documentation for a Rust crate or item (struct, trait, function, method) from local \
+         `cargo doc` output or docs.rs. Returns the signature, doc excerpt, and available methods. \
+         Use this before calling dependency APIs you are not sure about."
+    }
+
+    fn security_level(&self) -> SecurityLevel {
+        SecurityLevel::Moderate
+    }
+
+    fn untrusted_output(&self) -> bool {
+        true
+    }
+
+    fn parameters_schema(&self) -> ParameterSchema {
+        ParameterSchema::new()
+            .with_required("crate", ParameterProperty::string("Crate name (e.g. 'tokio', 'serde_json')"))
+            .with_property("item", ParameterProperty::string("Item path within the crate (e.g. 'sync::Mutex', 'Mutex', 'Mutex::lock'). Omit for crate overview."))
+            .with_property("version", ParameterProperty::string("Crate version (default: version from Cargo.lock, else latest)"))
+            .with_property("source", ParameterProperty::string("Where to look: auto, local, or docs.rs (default: auto)").with_enum(vec!["auto".into(), "local".into(), "docs.rs".into()]))
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext) -> Result<ToolResult> {
+        let crate_name = args.get("crate")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("Missing required parameter: crate"))?;
+
+        let item = args.get("item")
+            .and_then(|v| v.as_str())
+            .map(|s| normalize_item(crate_name, s))
+            .filter(|s| !s.is_empty());
+
+        let source_pref = args.get("source").and_then(|v| v.as_str()).unwrap_or("auto");
+
+        let version = args.get("version")
+            .and_then(|v| v.as_str())
+            .map(String::from)
+            .or_else(|| lockfile_version(&ctx.working_dir, crate_name))
+            .unwrap_or_else(|| "latest".to_string());
+
+        let dir_name = crate_name.replace('-', "_");
+        let local = local_doc_root(&ctx.working_dir, &dir_name);
+
+        let source = match (source_pref, local) {
+            ("local", None) => {
+                return Ok(ToolResult::error(format!(
+                    "No local docs for '{}'. Run `cargo doc` first or use source=docs.rs.",
+                    crate_name
+                )));
+            }
+            ("local", Some(root)) | ("auto", Some(root)) => DocSource::Local(root),
+            _ => DocSource::DocsRs(format!("https://docs.rs/{}/{}/{}/", crate_name, version, dir_name)),
+        };
+
+        let all_items = match source.fetch("all.html", ctx).await {
+            Ok(html) => parse_all_items(&html),
+            Err(e) => {
+                return Ok(ToolResult::error(format!(
+                    "Failed to load docs for {} {} from {}: {}",
+                    crate_name,
+                    version,
+                    source.label(),
+                    e
+                )));
+            }
+        };
+
+        let header = format!("{} {} ({})", crate_name, version, source.label());
+
+        let Some(item) = item else {
+            let index = source.fetch("index.html", ctx).await.unwrap_or_default();
+            return Ok(ToolResult::success(format_crate_overview(&header, &index, &all_items)));
+        };
+
+        // "Type::method" looks up the type and then the method on its page
+        let (item_path, member) = match find_item(&all_items, &item) {
+            Some(_) => (item.clone(), None),
+            None => match item.rsplit_once("::") {
+                Some((parent, member)) if find_item(&all_items, parent).is_some() => {
+                    (parent.to_string(), Some(member.to_string()))
+                }
+                _ => (item.clone(), None),
+            },
+        };
+
+        let Some(link) = find_item(&all_items, &item_path) else {
+            let suggestions = suggest_items(&all_items, &item);
+            let mut msg = format!("No item '{}' found in {}.", item, header);
+            if !suggestions.is_empty() {
+                msg.push_str(&format!(" Did you mean: {}?", suggestions.join(", ")));
+            }
+            return Ok(ToolResult::error(msg));
+        };
+
+        let page = match source.fetch(&link.href, ctx).await {
+            Ok(html) => html,
+            Err(e) => return Ok(ToolResult::error(format!("Failed to load {}: {}", link.href, e))),
+        };
+
+        let output = match member {
+            Some(member) => match format_member(&header, link, &member, &page) {
+                Some(out) => out,
+                None => {
+                    let methods = list_methods(&page);
+                    return Ok(ToolResult::error(format!(
+                        "'{}' has no member '{}'. Available: {}",
+                        link.path,
+                        member,
+                        methods.join(", ")
+                    )));
+                }
+            },
+            None => format_item(&header, link, &page),
+        };
+
+        Ok(ToolResult::success(output))
+    }
+}
+
+/// Strip a leading `crate_name::` from an item path
+fn normalize_item(crate_name: &str, item: &str) -> String {
+    let item = item.trim();
+    let prefix = format!("{}::", crate_name.replace('-', "_"));
+    item.strip_prefix(&prefix).unwrap_or(item).to_string()
+}
+
+/// Find the version of a crate in the nearest Cargo.lock
+fn lockfile_version(working_dir: &Path, crate_name: &str) -> Option<String> {
+    let lock = working_dir.ancestors().map(|d| d.join("Cargo.lock")).find(|p| p.is_file())?;
+    let content = fs::read_to_string(lock).ok()?;
+    let parsed: toml::Value = toml::from_str(&content).ok()?;
+
+    parsed
+        .get("package")?
+        .as_array()?
+        .iter()
+        .filter(|p| p.get("name").and_then(|n| n.as_str()) == Some(crate_name))
+        .filter_map(|p| p.get("version").and_then(|v| v.as_str()))
+        .max_by(|a, b| compare_versions(a, b))
+        .map(String::from)
+}
+
+/// Compare dotted version strings numerically
+fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    let parts = |v: &str| -> Vec<u64> {
+        v.split(['.', '-', '+']).map(|p| p.parse().unwrap_or(0)).collect()
+    };
+    parts(a).cmp(&parts(b))
+}
+
+/// Locate `target/doc/<crate>` for the project
+fn local_doc_root(working_dir: &Path, dir_name: &str) -> Option<PathBuf> {
+    let target = std::env::var_os("CARGO_TARGET_DIR").map(PathBuf::from);
+    let candidates = target
+        .into_iter()
+        .chain(working_dir.ancestors().map(|d| d.join("target")));
+
+    candidates
+        .map(|t| t.join("doc").join(dir_name))
+        .find(|root| root.join("all.html").is_file())
+}
+
+/// Parse the item list from a crate's `all.html`
+fn parse_all_items(html: &str) -> Vec<ItemLink> {
+    let document = Html::parse_document(html);
+    let Ok(selector) = Selector::parse("ul.all-items li a") else {
+        return Vec::new();
+    };
+
+    document
+        .select(&selector)
+        .filter_map(|a| {
+            let href = a.value().attr("href")?.to_string();
+            let path = a.text().collect::<String>().trim().to_string();
+            (!path.is_empty()).then_some(ItemLink { path, href })
+        })
+        .collect()
+}
+
+/// Find an item by full path, or by its last segment
+fn find_item<'a>(items: &'a [ItemLink], query: &str) -> Option<&'a ItemLink> {
+    items.iter().find(|i| i.path == query).or_else(|| {
+        items.iter().find(|i| i.path.rsplit("::").next() == Some(query))
+    })
+}
+
+/// Items whose path contains the query (case-insensitive)
+fn suggest_items(items: &[ItemLink], query: &str) -> Vec<String> {
+    let needle = query.rsplit("::").next().unwrap_or(query).to_lowercase();
+    items
+        .iter()
+        .filter(|i| i.path.to_lowercase().contains(&needle))
+        .take(10)
+        .map(|i| i.path.clone())
+        .collect()
+}
+
+/// Item kind from a rustdoc page name (`struct.Mutex.html` -> `struct`)
+fn item_kind(href: &str) -> &str {
+    href.rsplit('/').next().and_then(|f| f.split('.').next()).unwrap_or("item")
+}
+
+/// Collapse an element's text into a single-spaced signature
+fn signature_text(element: &scraper::ElementRef) -> String {
+    element
+        .text()
+        .collect::<String>()
+        .lines()
+        .map(str::trim_end)
+        .filter(|l| !l.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Truncate doc text at a line boundary
+fn truncate_doc(text: &str) -> String {
+    if text.len() <= MAX_DOC_CHARS {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    for line in text.lines() {
+        if out.len() + line.len() + 1 > MAX_DOC_CHARS {
+            break;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push_str("[...]");
+    out
+}
+
+/// Names of methods documented on an item page
+fn list_methods(page: &str) -> Vec<String> {
+    let document = Html::parse_document(page);
+    let Ok(selector) = Selector::parse("section.method, section.tymethod") else {
+        return Vec::new();
+    };
+
+    let mut methods: Vec<String> = Vec::new();
+    for section in document.select(&selector) {
+        let Some(id) = section.value().attr("id") else { continue };
+        let Some(name) = id.split_once('.').map(|(_, n)| n.split('-').next().unwrap_or(n)) else {
+            continue;
+        };
+        if !methods.iter().any(|m| m == name) {
+            methods.push(name.to_string());
+        }
+    }
+    methods
+}
+
+/// Format a crate overview: top-level docs plus item counts
+fn format_crate_overview(header: &str, index: &str, items: &[ItemLink]) -> String {
+    let mut out = format!("{}\n", header);
+
+    let document = Html::parse_document(index);
+    if let Ok(selector) = Selector::parse(".docblock") {
+        if let Some(doc) = document.select(&selector).next() {
+            out.push_str("\n## Docs\n");
+            out.push_str(&truncate_doc(&extract_text_from_element(&doc)));
+            out.push('\n');
+        }
+    }
+
+    out.push_str(&format!("\n## Items ({})\n", items.len()));
+    for item in items.iter().take(MAX_LISTED) {
+        out.push_str(&format!("- {} {}\n", item_kind(&item.href), item.path));
+    }
+    if items.len() > MAX_LISTED {
+        out.push_str(&format!("[{} more; query an item for details]\n", items.len() - MAX_LISTED));
+    }
+    out
+}
+
+/// Format an item page: signature, docs, and methods
+fn format_item(header: &str, link: &ItemLink, page: &str) -> String {
+    let document = Html::parse_document(page);
+    let mut out = format!("{} - {} {}\n", header, item_kind(&link.href), link.path);
+
+    if let Ok(selector) = Selector::parse("pre.item-decl, .item-decl pre") {
+        if let Some(decl) = document.select(&selector).next() {
+            out.push_str("\n## Signature\n");
+            out.push_str(&signature_text(&decl));
+            out.push('\n');
+        }
+    }
+
+    if let Ok(selector) = Selector::parse(".docblock") {
+        if let Some(doc) = document.select(&selector).next() {
+            out.push_str("\n## Docs\n");
+            out.push_str(&truncate_doc(&extract_text_from_element(&doc)));
+            out.push('\n');
+        }
+    }
+
+    let methods = list_methods(page);
+    if !methods.is_empty() {
+        out.push_str(&format!("\n## Methods ({})\n", methods.len()));
+        out.push_str(&methods.iter().take(MAX_LISTED).cloned().collect::<Vec<_>>().join(", "));
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Format a single method or associated item from its parent's page
+fn format_member(header: &str, link: &ItemLink, member: &str, page: &str) -> Option<String> {
+    let document = Html::parse_document(page);
+    let details = Selector::parse("details.toggle").ok()?;
+    let code = Selector::parse(".code-header").ok()?;
+    let doc = Selector::parse(".docblock").ok()?;
+    let ids = [format!("method.{}", member), format!("tymethod.{}", member)];
+
+    // Documented methods are wrapped in a toggle with the header and docs
+    for toggle in document.select(&details) {
+        let Some(section) = toggle
+            .select(&Selector::parse("section").ok()?)
+            .next()
+            .filter(|s| s.value().attr("id").is_some_and(|id| ids.iter().any(|i| i == id)))
+        else {
+            continue;
+        };
+
+        let mut out = format!("{} - {}::{}\n", header, link.path, member);
+        if let Some(sig) = section.select(&code).next() {
+            out.push_str("\n## Signature\n");
+            out.push_str(&signature_text(&sig));
+            out.push('\n');
+        }
+        if let Some(docblock) = toggle.select(&doc).next() {
+            out.push_str("\n## Docs\n");
+            out.push_str(&truncate_doc(&extract_text_from_element(&docblock)));
+            out.push('\n');
+        }
+        return Some(out);
+    }
+
+    // Undocumented methods only have a header
+    let section_sel = Selector::parse("section").ok()?;
+    let section = document
+        .select(&section_sel)
+        .find(|s| s.value().attr("id").is_some_and(|id| ids.iter().any(|i| i == id)))?;
+    let sig = section.select(&code).next()?;
+    Some(format!(
+        "{} - {}::{}\n\n## Signature\n{}\n",
+        header,
+        link.path,
+        member,
+        signature_text(&sig)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    const ALL_HTML: &str = r#"<html><body><h3>Structs</h3><ul class="all-items">
+        <li><a href="sync/struct.Mutex.html">sync::Mutex</a></li>
+        <li><a href="sync/struct.MutexGuard.html">sync::MutexGuard</a></li>
+        <li><a href="fn.spawn.html">spawn</a></li>
+        </ul></body></html>"#;
+
+    const MUTEX_HTML: &str = r#"<html><body>
+        <pre class="rust item-decl"><code>pub struct Mutex&lt;T: ?Sized&gt; { /* private fields */ }</code></pre>
+        <details class="toggle top-doc" open><summary>Expand description</summary>
+        <div class="docblock"><p>An asynchronous Mutex-like type.</p><p>Unlike std, it can be held across await points.</p></div></details>
+        <details class="toggle method-toggle" open><summary>
+        <section id="method.new" class="method"><h4 class="code-header">pub fn new(t: T) -&gt; Mutex&lt;T&gt;</h4></section></summary>
+        <div class="docblock"><p>Creates a new lock in an unlocked state.</p></div></details>
+        <details class="toggle method-toggle" open><summary>
+        <section id="method.lock" class="method"><h4 class="code-header">pub async fn lock(&amp;self) -&gt; MutexGuard&lt;'_, T&gt;</h4></section></summary>
+        <div class="docblock"><p>Locks this mutex, causing the current task to yield until the lock has been acquired.</p></div></details>
+        <section id="method.get_mut" class="method"><h4 class="code-header">pub fn get_mut(&amp;mut self) -&gt; &amp;mut T</h4></section>
+        </body></html>"#;
+
+    #[test]
+    fn test_parse_and_find_items() {
+        let items = parse_all_items(ALL_HTML);
+        assert_eq!(items.len(), 3);
+        assert_eq!(find_item(&items, "sync::Mutex").unwrap().href, "sync/struct.Mutex.html");
+        assert_eq!(find_item(&items, "Mutex").unwrap().path, "sync::Mutex");
+        assert!(find_item(&items, "RwLock").is_none());
+        assert_eq!(suggest_items(&items, "mutex"), vec!["sync::Mutex", "sync::MutexGuard"]);
+        assert_eq!(item_kind("fn.spawn.html"), "fn");
+    }
+
+    #[test]
+    fn test_format_item() {
+        let items = parse_all_items(ALL_HTML);
+        let out = format_item("tokio 1.0.0 (docs.rs)", &items[0], MUTEX_HTML);
+        assert!(out.contains("struct sync::Mutex"));
+        assert!(out.contains("pub struct Mutex<T: ?Sized>"));
+        assert!(out.contains("An asynchronous Mutex-like type."));
+        assert!(out.contains("## Methods (3)\nnew, lock, get_mut"));
+    }
+
+    #[test]
+    fn test_format_member() {
+        let items = parse_all_items(ALL_HTML);
+        let out = format_member("tokio", &items[0], "lock", MUTEX_HTML).unwrap();
+        assert!(out.contains("pub async fn lock(&self) -> MutexGuard<'_, T>"));
+        assert!(out.contains("Locks this mutex"));
+        assert!(!out.contains("Creates a new lock"));
+
+        let out = format_member("tokio", &items[0], "get_mut", MUTEX_HTML).unwrap();
+        assert!(out.contains("pub fn get_mut(&mut self) -> &mut T"));
+
+        assert!(format_member("tokio", &items[0], "unlock", MUTEX_HTML).is_none());
+    }
+
+    #[test]
+    fn test_lockfile_version() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("Cargo.lock"),
+            r#"version = 3
+
+[[package]]
+name = "tokio"
+version = "1.9.0"
+
+[[package]]
+name = "tokio"
+version = "1.38.1"
+
+[[package]]
+name = "serde"
+version = "1.0.200"
+"#,
+        )
+        .unwrap();
+        let sub = dir.path().join("crates/app");
+        fs::create_dir_all(&sub).unwrap();
+
+        assert_eq!(lockfile_version(&sub, "tokio").as_deref(), Some("1.38.1"));
+        assert_eq!(lockfile_version(&sub, "serde").as_deref(), Some("1.0.200"));
+        assert!(lockfile_version(&sub, "rand").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_local_docs_lookup() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().join("target/doc/my_crate");
+        fs::create_dir_all(root.join("sync")).unwrap();
+        fs::write(root.join("all.html"), ALL_HTML).unwrap();
+        fs::write(root.join("sync/struct.Mutex.html"), MUTEX_HTML).unwrap();
+
+        let tool = RustDocsTool;
+        let ctx = ToolContext::new(dir.path().to_path_buf());
+        let args = serde_json::json!({ "crate": "my-crate", "item": "my_crate::Mutex::new", "source": "local" });
+
+        let result = tool.execute(&args, &ctx).await.unwrap();
+        assert!(result.success, "{:?}", result.error);
+        assert!(result.output.contains("(local cargo doc)"));
+        assert!(result.output.contains("pub fn new(t: T) -> Mutex<T>"));
+
+        let args = serde_json::json!({ "crate": "my-crate", "item": "Mutx", "source": "local" });
+        let result = tool.execute(&args, &ctx).await.unwrap();
+        assert!(!result.success);
+
+        let args = serde_json::json!({ "crate": "other", "source": "local" });
+        let result = tool.execute(&args, &ctx).await.unwrap();
+        assert!(result.error.unwrap().contains("Run `cargo doc` first"));
+    }
+}
@@ -1,10 +1,12 @@
 //! Sandboxed command execution
 //!
 //! Provides isolated execution environments for running untrusted commands.
-//! Supports multiple backends: firejail, bubblewrap, docker, or native (no sandbox).
+//! Supports multiple backends: firejail, bubblewrap, docker, WASM (WASI
+//! modules, see [`super::wasm`]), or native (no sandbox).
 
 use anyhow::Result;
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::path::PathBuf;
 use std::process::Stdio;
@@ -12,10 +14,12 @@ use tokio::process::Command;
 use tokio::time::{timeout, Duration};
 use tracing::{debug, info, warn};
 
-use crate::tools::{ParameterProperty, ParameterSchema, SecurityLevel, Tool, ToolContext, ToolResult};
+use super::wasm::{self, WasmExit, WasmRun};
+use crate::tools::{dry_run, ParameterProperty, ParameterSchema, SecurityLevel, Tool, ToolContext, ToolResult};
 
-/// Available sandbox backends
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Available sandbox backends, selectable with `sandbox.backend`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SandboxBackend {
     /// No sandboxing (native execution)
     None,
@@ -25,6 +29,8 @@ pub enum SandboxBackend {
     Bubblewrap,
     /// Docker container isolation
     Docker,
+    /// WASI modules run by wasmtime; no shell, no network
+    Wasm,
 }
 
 impl SandboxBackend {
@@ -50,6 +56,16 @@ impl SandboxBackend {
         Self::None
     }
 
+    /// The backend set with `sandbox.backend`, or the best one installed
+    ///
+    /// WASM is never picked on its own, since it can't run shell commands.
+    pub fn configured() -> Self {
+        crate::config::UserConfig::load()
+            .ok()
+            .and_then(|config| config.sandbox.backend)
+            .unwrap_or_else(Self::detect)
+    }
+
     /// Get the display name
     pub fn name(&self) -> &'static str {
         match self {
@@ -57,6 +73,7 @@ impl SandboxBackend {
             Self::Firejail => "firejail",
             Self::Bubblewrap => "bubblewrap",
             Self::Docker => "docker",
+            Self::Wasm => "wasm",
         }
     }
 }
@@ -73,10 +90,10 @@ pub struct SandboxTool {
 }
 
 impl SandboxTool {
-    /// Create a new sandbox tool with auto-detected backend
+    /// Create a new sandbox tool with the configured or auto-detected backend
     pub fn new() -> Self {
         Self {
-            backend: SandboxBackend::detect(),
+            backend: SandboxBackend::configured(),
             docker_image: "alpine:latest".to_string(),
         }
     }
@@ -161,7 +178,87 @@ impl SandboxTool {
                 ]);
                 cmd
             }
+
+            SandboxBackend::Wasm => unreachable!("WASM modules don't run as processes"),
+        }
+    }
+
+    /// Run a WASI module in place of a shell command
+    async fn execute_wasm(&self, command: &str, args: &Value, ctx: &ToolContext, timeout_secs: u64) -> Result<ToolResult> {
+        if args.get("network").and_then(|v| v.as_bool()).unwrap_or(false) {
+            return Ok(ToolResult::error("The wasm sandbox has no network access"));
         }
+
+        // Read per call so project config and config edits apply
+        let config = crate::config::UserConfig::load().unwrap_or_default().sandbox;
+        let (module, argv) = match wasm::resolve(command, &config.wasm_modules, &ctx.working_dir) {
+            Ok(resolved) => resolved,
+            Err(e) => return Ok(ToolResult::error(format!("{:#}", e))),
+        };
+        let request = WasmRun {
+            module,
+            args: argv,
+            stdin: args.get("stdin").and_then(|v| v.as_str()).map(String::from),
+            working_dir: ctx.working_dir.clone(),
+            timeout: Duration::from_secs(timeout_secs),
+            memory_mb: args.get("memory_mb").and_then(|v| v.as_u64()).unwrap_or(256),
+        };
+
+        let output = match tokio::task::spawn_blocking(move || wasm::run(request)).await? {
+            Ok(output) => output,
+            Err(e) => return Ok(ToolResult::error(format!("{:#}", e))),
+        };
+        let combined_output = combine_output(
+            self.backend,
+            &String::from_utf8_lossy(&output.stdout),
+            &String::from_utf8_lossy(&output.stderr),
+            ctx.max_output_len,
+        );
+        Ok(match output.exit {
+            WasmExit::Code(0) => ToolResult::success(combined_output),
+            WasmExit::Code(code) => {
+                ToolResult::failure(combined_output, format!("Sandboxed command exited with code {}", code))
+            }
+            WasmExit::TimedOut => {
+                warn!(timeout_secs, backend = self.backend.name(), "Sandboxed command timed out");
+                ToolResult::error(format!("Sandboxed command timed out after {} seconds", timeout_secs))
+            }
+            WasmExit::Trap(trap) => ToolResult::failure(combined_output, format!("WASM module trapped: {}", trap)),
+        })
+    }
+}
+
+/// Output as shown to the model: the backend, stdout, then stderr, truncated
+fn combine_output(backend: SandboxBackend, stdout: &str, stderr: &str, max_len: usize) -> String {
+    let mut combined_output = String::new();
+    combined_output.push_str(&format!("[sandbox: {}]\n", backend.name()));
+
+    if !stdout.is_empty() {
+        combined_output.push_str(stdout);
+    }
+
+    if !stderr.is_empty() {
+        if !stdout.is_empty() {
+            combined_output.push_str("\n--- stderr ---\n");
+        }
+        combined_output.push_str(stderr);
+    }
+
+    // Truncate if too long
+    if combined_output.len() > max_len {
+        let safe_end = combined_output
+            .char_indices()
+            .take_while(|(idx, _)| *idx < max_len)
+            .last()
+            .map(|(idx, c)| idx + c.len_utf8())
+            .unwrap_or(0);
+        format!(
+            "{}\n\n[Output truncated at {} characters]",
+            &combined_output[..safe_end],
+            safe_end
+        )
+    } else {
+        combined_output
     }
 }
 
@@ -178,7 +275,7 @@ impl Tool for SandboxTool {
     }
 
     fn description(&self) -> &str {
-        "Execute a command in an isolated sandbox environment. Safer than bash for running untrusted code. Supports firejail, bubblewrap, or docker backends."
+        "Execute a command in an isolated sandbox environment. Safer than bash for running untrusted code. Supports firejail, bubblewrap, docker, or wasm backends; wasm runs a WASI module (a .wasm file or a configured interpreter such as `python script.py`) instead of a shell command, with no network."
     }
 
     fn security_level(&self) -> SecurityLevel {
@@ -191,7 +288,8 @@ impl Tool for SandboxTool {
             .with_required("command", ParameterProperty::string("The command to execute in the sandbox"))
             .with_property("timeout", ParameterProperty::number("Timeout in seconds (default: 60)"))
             .with_property("network", ParameterProperty::boolean("Allow network access (default: false, docker only)"))
-            .with_property("memory_mb", ParameterProperty::number("Memory limit in MB (default: 256, docker only)"))
+            .with_property("memory_mb", ParameterProperty::number("Memory limit in MB (default: 256, docker and wasm only)"))
+            .with_property("stdin", ParameterProperty::string("Text for the command's standard input (wasm only)"))
     }
 
     async fn execute(&self, args: &Value, ctx: &ToolContext) -> Result<ToolResult> {
@@ -218,6 +316,10 @@ impl Tool for SandboxTool {
             )));
         }
 
+        if self.backend == SandboxBackend::Wasm {
+            return self.execute_wasm(command, args, ctx, timeout_secs).await;
+        }
+
         let mut cmd = self.build_command(command, &ctx.working_dir);
         cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
 
@@ -229,39 +331,12 @@ impl Tool for SandboxTool {
                 let exit_code = output.status.code();
                 debug!(exit_code = ?exit_code, backend = self.backend.name(), "Sandbox command completed");
 
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                let stderr = String::from_utf8_lossy(&output.stderr);
-
-                let mut combined_output = String::new();
-                combined_output.push_str(&format!("[sandbox: {}]\n", self.backend.name()));
-
-                if !stdout.is_empty() {
-                    combined_output.push_str(&stdout);
-                }
-
-                if !stderr.is_empty() {
-                    if !stdout.is_empty() {
-                        combined_output.push_str("\n--- stderr ---\n");
-                    }
-                    combined_output.push_str(&stderr);
-                }
-
-                // Truncate if too long
-                let combined_output = if combined_output.len() > ctx.max_output_len {
-                    let safe_end = combined_output
-                        .char_indices()
-                        .take_while(|(idx, _)| *idx < ctx.max_output_len)
-                        .last()
-                        .map(|(idx, c)| idx + c.len_utf8())
-                        .unwrap_or(0);
-                    format!(
-                        "{}\n\n[Output truncated at {} characters]",
-                        &combined_output[..safe_end],
-                        safe_end
-                    )
-                } else {
-                    combined_output
-                };
+                let combined_output = combine_output(
+                    self.backend,
+                    &String::from_utf8_lossy(&output.stdout),
+                    &String::from_utf8_lossy(&output.stderr),
+                    ctx.max_output_len,
+                );
 
                 if output.status.success() {
                     Ok(ToolResult::success(combined_output))
@@ -299,6 +374,26 @@ impl Tool for SandboxTool {
             }
         }
     }
+
+    fn simulate(&self, args: &Value, ctx: &ToolContext) -> ToolResult {
+        let Some(command) = args.get("command").and_then(|v| v.as_str()) else {
+            return ToolResult::error("Missing required parameter: command");
+        };
+        dry_run::simulated(
+            format!("Would run in a {} sandbox in {}:", self.backend.name(), ctx.working_dir.display()),
+            format!("$ {}", command),
+        )
+    }
+
+    fn preview(&self, args: &Value, _ctx: &ToolContext) -> Option<String> {
+        let network = args.get("network").and_then(|v| v.as_bool()).unwrap_or(false);
+        Some(format!(
+            "$ {}\nin a {} sandbox{}",
+            args.get("command")?.as_str()?,
+            self.backend.name(),
+            if network { ", with network access" } else { "" }
+        ))
+    }
 }
 
 /// Configuration for sandbox settings
@@ -394,4 +489,18 @@ mod tests {
         assert!(result.success, "Expected success but got: {:?}", result.error);
         assert!(result.output.contains("test content"));
     }
+
+    #[tokio::test]
+    async fn test_sandbox_wasm_rejects() {
+        let tool = SandboxTool::with_backend(SandboxBackend::Wasm);
+        let temp_dir = TempDir::new().unwrap();
+        let ctx = ToolContext::new(temp_dir.path().to_path_buf());
+
+        let result = tool.execute(&json!({ "command": "bash -c ls" }), &ctx).await.unwrap();
+        assert!(result.error.unwrap().contains("No WASI module named `bash`"));
+
+        let args = json!({ "command": "tool.wasm", "network": true });
+        let result = tool.execute(&args, &ctx).await.unwrap();
+        assert!(result.error.unwrap().contains("no network"));
+    }
 }
@@ -0,0 +1,275 @@
+//! Scratch workspace tool: a private temp directory for intermediate files
+//!
+//! Downloads, generated scripts and other throwaway artifacts go here
+//! instead of the project tree. The directory is created on first use,
+//! capped in size, and deleted when the tool registry (and with it the
+//! agent session) is dropped.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use tempfile::TempDir;
+use walkdir::WalkDir;
+
+use crate::tools::{ParameterProperty, ParameterSchema, SecurityLevel, Tool, ToolContext, ToolResult};
+
+/// Default cap on the total size of the scratch directory
+const DEFAULT_MAX_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Prefix of scratch directory names under the system temp dir
+const DIR_PREFIX: &str = "quant-scratch-";
+
+/// Tool for keeping intermediate files in a temp directory
+pub struct ScratchTool {
+    dir: Mutex<Option<TempDir>>,
+    max_bytes: u64,
+}
+
+impl ScratchTool {
+    pub fn new() -> Self {
+        Self {
+            dir: Mutex::new(None),
+            max_bytes: DEFAULT_MAX_BYTES,
+        }
+    }
+
+    /// The scratch directory, created on first use
+    fn dir(&self) -> Result<PathBuf> {
+        let mut dir = self.dir.lock().unwrap();
+        if let Some(dir) = dir.as_ref() {
+            return Ok(dir.path().to_path_buf());
+        }
+        let created = tempfile::Builder::new().prefix(DIR_PREFIX).tempdir()?;
+        let path = crate::paths::canonicalize(created.path())?;
+        tracing::debug!(dir = %path.display(), "Created scratch directory");
+        *dir = Some(created);
+        Ok(path)
+    }
+
+    /// Resolve a relative name inside the scratch directory
+    fn resolve(&self, dir: &Path, name: &str) -> std::result::Result<PathBuf, String> {
+        let relative = Path::new(name);
+        if !relative.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+            return Err(format!("{} must be a relative path inside the scratch directory", name));
+        }
+        let path = dir.join(relative);
+        // Symlinks made with bash could still point outside
+        if !crate::paths::is_within(&path, dir) {
+            return Err(format!("{} resolves outside the scratch directory", name));
+        }
+        Ok(path)
+    }
+
+    fn write(&self, dir: &Path, args: &Value) -> Result<ToolResult> {
+        let name = required(args, "name")?;
+        let content = required(args, "content")?;
+        let append = args.get("append").and_then(|v| v.as_bool()).unwrap_or(false);
+        let path = match self.resolve(dir, name) {
+            Ok(path) => path,
+            Err(e) => return Ok(ToolResult::error(e)),
+        };
+
+        // An overwritten file gives its space back
+        let replaced = if append { 0 } else { fs::metadata(&path).map(|m| m.len()).unwrap_or(0) };
+        let used = dir_size(dir);
+        if used - replaced.min(used) + content.len() as u64 > self.max_bytes {
+            return Ok(ToolResult::error(format!(
+                "Scratch space full: {} of {} used, {} more needed. Remove files first.",
+                format_bytes(used),
+                format_bytes(self.max_bytes),
+                format_bytes(content.len() as u64)
+            )));
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if append {
+            use std::io::Write;
+            fs::OpenOptions::new().create(true).append(true).open(&path)?.write_all(content.as_bytes())?;
+        } else {
+            fs::write(&path, content)?;
+        }
+        Ok(ToolResult::success(format!("Wrote {} ({} bytes)", path.display(), content.len())))
+    }
+
+    fn read(&self, dir: &Path, args: &Value) -> Result<ToolResult> {
+        let name = required(args, "name")?;
+        let path = match self.resolve(dir, name) {
+            Ok(path) => path,
+            Err(e) => return Ok(ToolResult::error(e)),
+        };
+        match fs::read_to_string(&path) {
+            Ok(content) => Ok(ToolResult::success(content)),
+            Err(e) => Ok(ToolResult::error(format!("Failed to read {}: {}", name, e))),
+        }
+    }
+
+    fn list(&self, dir: &Path) -> ToolResult {
+        let mut lines: Vec<String> = WalkDir::new(dir)
+            .min_depth(1)
+            .sort_by_file_name()
+            .into_iter()
+            .flatten()
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| {
+                let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                let name = entry.path().strip_prefix(dir).unwrap_or(entry.path());
+                format!("{}  {}", name.display(), format_bytes(size))
+            })
+            .collect();
+        if lines.is_empty() {
+            lines.push("(empty)".to_string());
+        }
+        lines.push(self.usage(dir));
+        ToolResult::success(lines.join("\n"))
+    }
+
+    /// Remove one file or directory, or everything when no name is given
+    fn remove(&self, dir: &Path, args: &Value) -> Result<ToolResult> {
+        let Some(name) = args.get("name").and_then(|v| v.as_str()) else {
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    fs::remove_dir_all(&path)?;
+                } else {
+                    fs::remove_file(&path)?;
+                }
+            }
+            return Ok(ToolResult::success("Cleared the scratch directory"));
+        };
+
+        let path = match self.resolve(dir, name) {
+            Ok(path) if path != dir => path,
+            Ok(_) => return Ok(ToolResult::error("Omit name to clear the whole scratch directory")),
+            Err(e) => return Ok(ToolResult::error(e)),
+        };
+        let removed = if path.is_dir() { fs::remove_dir_all(&path) } else { fs::remove_file(&path) };
+        match removed {
+            Ok(()) => Ok(ToolResult::success(format!("Removed {}", name))),
+            Err(e) => Ok(ToolResult::error(format!("Failed to remove {}: {}", name, e))),
+        }
+    }
+
+    fn usage(&self, dir: &Path) -> String {
+        format!("{} of {} used", format_bytes(dir_size(dir)), format_bytes(self.max_bytes))
+    }
+}
+
+impl Default for ScratchTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Tool for ScratchTool {
+    fn name(&self) -> &str {
+        "scratch"
+    }
+
+    fn description(&self) -> &str {
+        "A private temp directory for intermediate files (downloads, generated scripts, test data) that don't belong in the project. Actions: path (get the directory to use in bash commands), write, read, list, remove (omit name to clear all). Deleted automatically at the end of the session."
+    }
+
+    fn security_level(&self) -> SecurityLevel {
+        // Writes files, but never inside the project
+        SecurityLevel::Moderate
+    }
+
+    fn parameters_schema(&self) -> ParameterSchema {
+        ParameterSchema::new()
+            .with_required(
+                "action",
+                ParameterProperty::string("What to do")
+                    .with_enum(["path", "write", "read", "list", "remove"].map(String::from).to_vec()),
+            )
+            .with_property("name", ParameterProperty::string("File path relative to the scratch directory"))
+            .with_property("content", ParameterProperty::string("Content to write (write only)"))
+            .with_property("append", ParameterProperty::boolean("Append instead of overwriting (write only, default: false)"))
+    }
+
+    async fn execute(&self, args: &Value, _ctx: &ToolContext) -> Result<ToolResult> {
+        let action = required(args, "action")?;
+        let dir = match self.dir() {
+            Ok(dir) => dir,
+            Err(e) => return Ok(ToolResult::error(format!("Failed to create scratch directory: {}", e))),
+        };
+
+        match action {
+            "path" => Ok(ToolResult::success(format!("{}\n{}", dir.display(), self.usage(&dir)))),
+            "write" => self.write(&dir, args),
+            "read" => self.read(&dir, args),
+            "list" => Ok(self.list(&dir)),
+            "remove" => self.remove(&dir, args),
+            other => Ok(ToolResult::error(format!(
+                "Unknown action '{}': expected path, write, read, list or remove",
+                other
+            ))),
+        }
+    }
+}
+
+fn required<'a>(args: &'a Value, name: &str) -> Result<&'a str> {
+    args.get(name)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow::anyhow!("Missing required parameter: {}", name))
+}
+
+/// Total size of the files under `dir`
+fn dir_size(dir: &Path) -> u64 {
+    WalkDir::new(dir)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|m| m.len())
+        .sum()
+}
+
+fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1024 * 1024 => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+        b if b >= 1024 => format!("{:.1} KB", b as f64 / 1024.0),
+        b => format!("{} B", b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_scratch_lifecycle() {
+        let tool = ScratchTool { max_bytes: 10, ..ScratchTool::new() };
+        let ctx = ToolContext::default();
+
+        let result = tool.execute(&json!({"action": "write", "name": "gen/a.sh", "content": "echo hi"}), &ctx).await.unwrap();
+        assert!(result.success);
+        let dir = tool.dir().unwrap();
+        assert!(dir.join("gen/a.sh").exists());
+
+        // Over the cap, but overwriting reuses the file's space
+        let result = tool.execute(&json!({"action": "write", "name": "b.txt", "content": "12345"}), &ctx).await.unwrap();
+        assert!(!result.success);
+        let result = tool.execute(&json!({"action": "write", "name": "gen/a.sh", "content": "echo bye!"}), &ctx).await.unwrap();
+        assert!(result.success);
+
+        let result = tool.execute(&json!({"action": "read", "name": "../etc/passwd"}), &ctx).await.unwrap();
+        assert!(!result.success);
+
+        let result = tool.execute(&json!({"action": "list"}), &ctx).await.unwrap();
+        assert!(result.output.contains("a.sh"));
+
+        let result = tool.execute(&json!({"action": "remove"}), &ctx).await.unwrap();
+        assert!(result.success);
+        assert!(!dir.join("gen").exists());
+
+        drop(tool);
+        assert!(!dir.exists());
+    }
+}
@@ -0,0 +1,128 @@
+//! Reading files that may be huge, binary or minified
+//!
+//! Shared by `file_read` and `grep`: large files are memory-mapped rather
+//! than copied into memory, binary files are recognized from their first
+//! bytes, and overlong lines (minified bundles, data blobs) are cut to a
+//! window so one line can't flood a tool result.
+
+use memmap2::Mmap;
+use std::fs::{self, File};
+use std::io;
+use std::ops::Deref;
+use std::path::Path;
+
+/// Bytes inspected when checking for binary content
+pub const BINARY_SNIFF_BYTES: usize = 8192;
+
+/// Files at least this large are memory-mapped instead of read
+const MMAP_THRESHOLD: u64 = 1024 * 1024;
+
+/// The contents of a file, read or memory-mapped
+pub enum FileBytes {
+    Read(Vec<u8>),
+    Mapped(Mmap),
+}
+
+impl Deref for FileBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Read(bytes) => bytes,
+            Self::Mapped(map) => map,
+        }
+    }
+}
+
+/// Read a file, memory-mapping it if it is large
+pub fn read_bytes(path: &Path) -> io::Result<FileBytes> {
+    let file = File::open(path)?;
+    if file.metadata()?.len() < MMAP_THRESHOLD {
+        return fs::read(path).map(FileBytes::Read);
+    }
+    // Safety: the map is read-only and dropped before the tool returns; a
+    // file truncated meanwhile by another process is the usual mmap caveat
+    unsafe { Mmap::map(&file) }.map(FileBytes::Mapped)
+}
+
+/// Check for NUL bytes or invalid UTF-8 near the start of the file
+pub fn is_binary(bytes: &[u8]) -> bool {
+    let sample = &bytes[..bytes.len().min(BINARY_SNIFF_BYTES)];
+    if sample.contains(&0) {
+        return true;
+    }
+    match std::str::from_utf8(sample) {
+        Ok(_) => false,
+        // A multi-byte character cut off at the end of the sample is fine
+        Err(e) => e.error_len().is_some(),
+    }
+}
+
+/// At most `max` bytes of `line` around byte offset `at`, marked where it
+/// was cut
+pub fn cap_line(line: &str, at: usize, max: usize) -> String {
+    if line.len() <= max {
+        return line.to_string();
+    }
+
+    let mut start = at.saturating_sub(max / 4).min(line.len() - max);
+    while !line.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = (start + max).min(line.len());
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let mut capped = String::new();
+    if start > 0 {
+        capped.push('…');
+    }
+    capped.push_str(&line[start..end]);
+    if end < line.len() {
+        capped.push('…');
+    }
+    capped.push_str(&format!(" [line cut: {} bytes]", line.len()));
+    capped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_binary() {
+        assert!(!is_binary("plain text\nwith ünïcode".as_bytes()));
+        assert!(is_binary(&[b'a', 0, b'b']));
+        assert!(is_binary(&[0xff, 0xfe, 0xfd, b'a']));
+    }
+
+    #[test]
+    fn test_cap_line() {
+        assert_eq!(cap_line("short", 0, 100), "short");
+
+        let minified = format!("{}needle{}", "a".repeat(1000), "b".repeat(1000));
+        let capped = cap_line(&minified, 1000, 100);
+        assert!(capped.contains("needle"));
+        assert!(capped.starts_with('…'));
+        assert!(capped.ends_with("[line cut: 2006 bytes]"));
+
+        // Cuts land on character boundaries
+        let wide = "é".repeat(200);
+        assert!(cap_line(&wide, 0, 51).starts_with("éé"));
+    }
+
+    #[test]
+    fn test_read_bytes_maps_large_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let small = dir.path().join("small.txt");
+        let large = dir.path().join("large.txt");
+        fs::write(&small, "hello").unwrap();
+        fs::write(&large, vec![b'x'; MMAP_THRESHOLD as usize]).unwrap();
+
+        assert!(matches!(read_bytes(&small).unwrap(), FileBytes::Read(_)));
+        let mapped = read_bytes(&large).unwrap();
+        assert!(matches!(mapped, FileBytes::Mapped(_)));
+        assert_eq!(mapped.len(), MMAP_THRESHOLD as usize);
+    }
+}
@@ -0,0 +1,280 @@
+//! WASM backend of the sandbox tool
+//!
+//! Runs WASI (preview 1) command modules with wasmtime. A module sees only the
+//! working directory, preopened as `.`, and gets no network, no environment
+//! and no other files. It is stopped when it runs past its timeout, and its
+//! memory can't grow past the limit. Interpreters built for WASI, such as
+//! python.wasm, are named under `[sandbox] wasm_modules`, so scripts the model
+//! writes run without a shell.
+//!
+//! wasmtime is behind the `wasm` cargo feature; without it the backend
+//! reports that it isn't built in.
+
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[cfg(feature = "wasm")]
+use anyhow::Context;
+#[cfg(feature = "wasm")]
+use wasmtime::{Config, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder, Trap};
+#[cfg(feature = "wasm")]
+use wasmtime_wasi::pipe::{MemoryInputPipe, MemoryOutputPipe};
+#[cfg(feature = "wasm")]
+use wasmtime_wasi::preview1::{self, WasiP1Ctx};
+#[cfg(feature = "wasm")]
+use wasmtime_wasi::{DirPerms, FilePerms, I32Exit, WasiCtxBuilder};
+
+/// Most stdout or stderr kept from a module; writes past it fail
+#[cfg(feature = "wasm")]
+const MAX_STREAM_BYTES: usize = 1024 * 1024;
+
+/// A module to run and what it runs with
+#[cfg_attr(not(feature = "wasm"), allow(dead_code))]
+pub struct WasmRun {
+    pub module: PathBuf,
+    /// Arguments, starting with the program name
+    pub args: Vec<String>,
+    pub stdin: Option<String>,
+    pub working_dir: PathBuf,
+    pub timeout: Duration,
+    pub memory_mb: u64,
+}
+
+/// How a module ended
+#[cfg_attr(not(feature = "wasm"), allow(dead_code))]
+pub enum WasmExit {
+    Code(i32),
+    TimedOut,
+    /// A trap other than the timeout, e.g. `unreachable`
+    Trap(String),
+}
+
+#[cfg_attr(not(feature = "wasm"), allow(dead_code))]
+pub struct WasmOutput {
+    pub exit: WasmExit,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+/// Split a command into words like a shell would, without expansions
+pub fn split_words(command: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') | (None, '\\') => {
+                word.extend(chars.next());
+                in_word = true;
+            }
+            (Some(_), c) => word.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+
+    if quote.is_some() {
+        bail!("Unclosed quote in command");
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// The module and arguments a command runs
+///
+/// The first word is a `.wasm` file, relative to the working directory, or
+/// the name of a module under `[sandbox] wasm_modules`.
+pub fn resolve(
+    command: &str,
+    modules: &HashMap<String, PathBuf>,
+    working_dir: &Path,
+) -> Result<(PathBuf, Vec<String>)> {
+    let words = split_words(command)?;
+    let Some(program) = words.first() else {
+        bail!("Empty command");
+    };
+    let module = if program.ends_with(".wasm") {
+        working_dir.join(program)
+    } else {
+        match modules.get(program) {
+            Some(path) => path.clone(),
+            None => bail!(
+                "No WASI module named `{}`; run a .wasm file or add one under [sandbox] wasm_modules",
+                program
+            ),
+        }
+    };
+    Ok((module, words))
+}
+
+#[cfg(feature = "wasm")]
+struct State {
+    wasi: WasiP1Ctx,
+    limits: StoreLimits,
+}
+
+/// Run a module to completion; blocks, so call it off the async runtime
+#[cfg(feature = "wasm")]
+pub fn run(request: WasmRun) -> Result<WasmOutput> {
+    let mut config = Config::new();
+    config.epoch_interruption(true);
+    let engine = Engine::new(&config)?;
+    let module = Module::from_file(&engine, &request.module)
+        .with_context(|| format!("Failed to load WASM module {}", request.module.display()))?;
+
+    let mut linker = Linker::new(&engine);
+    preview1::add_to_linker_sync(&mut linker, |state: &mut State| &mut state.wasi)?;
+
+    let stdout = MemoryOutputPipe::new(MAX_STREAM_BYTES);
+    let stderr = MemoryOutputPipe::new(MAX_STREAM_BYTES);
+    let mut wasi = WasiCtxBuilder::new();
+    wasi.args(&request.args)
+        .stdout(stdout.clone())
+        .stderr(stderr.clone())
+        .preopened_dir(&request.working_dir, ".", DirPerms::all(), FilePerms::all())?;
+    if let Some(input) = request.stdin {
+        wasi.stdin(MemoryInputPipe::new(input));
+    }
+
+    let limits = StoreLimitsBuilder::new()
+        .memory_size((request.memory_mb as usize).saturating_mul(1024 * 1024))
+        .build();
+    let mut store = Store::new(&engine, State { wasi: wasi.build_p1(), limits });
+    store.limiter(|state| &mut state.limits);
+    store.set_epoch_deadline(1);
+
+    // Interrupt the module once the timeout passes, unless it finished
+    let (finished, wait) = std::sync::mpsc::channel::<()>();
+    let timer_engine = engine.clone();
+    let timeout = request.timeout;
+    std::thread::spawn(move || {
+        if wait.recv_timeout(timeout) == Err(std::sync::mpsc::RecvTimeoutError::Timeout) {
+            timer_engine.increment_epoch();
+        }
+    });
+
+    let instance = linker.instantiate(&mut store, &module)?;
+    let start = instance
+        .get_typed_func::<(), ()>(&mut store, "_start")
+        .context("Not a WASI command module (no _start)")?;
+    let result = start.call(&mut store, ());
+    drop(finished);
+
+    let exit = match result {
+        Ok(()) => WasmExit::Code(0),
+        Err(e) => match (e.downcast_ref::<I32Exit>(), e.downcast_ref::<Trap>()) {
+            (Some(exit), _) => WasmExit::Code(exit.0),
+            (_, Some(Trap::Interrupt)) => WasmExit::TimedOut,
+            _ => WasmExit::Trap(format!("{:#}", e)),
+        },
+    };
+    Ok(WasmOutput {
+        exit,
+        stdout: stdout.contents().to_vec(),
+        stderr: stderr.contents().to_vec(),
+    })
+}
+
+#[cfg(not(feature = "wasm"))]
+pub fn run(_request: WasmRun) -> Result<WasmOutput> {
+    bail!("This build of quant has no WASM sandbox; rebuild it with `--features wasm`")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(split_words("python  main.py -v").unwrap(), ["python", "main.py", "-v"]);
+        assert_eq!(
+            split_words(r#"python -c 'print("a b")' "x y" z\ w ''"#).unwrap(),
+            ["python", "-c", r#"print("a b")"#, "x y", "z w", ""]
+        );
+        assert!(split_words("echo 'open").is_err());
+        assert!(split_words("  ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let modules = HashMap::from([("python".to_string(), PathBuf::from("/opt/wasm/python.wasm"))]);
+        let work = Path::new("/work");
+
+        let (module, args) = resolve("python script.py", &modules, work).unwrap();
+        assert_eq!(module, Path::new("/opt/wasm/python.wasm"));
+        assert_eq!(args, ["python", "script.py"]);
+
+        let (module, args) = resolve("build/tool.wasm --check", &modules, work).unwrap();
+        assert_eq!(module, Path::new("/work/build/tool.wasm"));
+        assert_eq!(args, ["build/tool.wasm", "--check"]);
+
+        assert!(resolve("bash -c ls", &modules, work).is_err());
+        assert!(resolve("", &modules, work).is_err());
+    }
+
+    #[cfg(feature = "wasm")]
+    fn run_wat(wat: &str, timeout: Duration) -> WasmOutput {
+        let dir = tempfile::TempDir::new().unwrap();
+        let module = dir.path().join("test.wat");
+        std::fs::write(&module, wat).unwrap();
+        run(WasmRun {
+            module,
+            args: vec!["test".to_string()],
+            stdin: None,
+            working_dir: dir.path().to_path_buf(),
+            timeout,
+            memory_mb: 16,
+        })
+        .unwrap()
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_run_module() {
+        let output = run_wat(
+            r#"(module
+                (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+                (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 16) "hello\n")
+                (func (export "_start")
+                    (i32.store (i32.const 0) (i32.const 16))
+                    (i32.store (i32.const 4) (i32.const 6))
+                    (drop (call $fd_write (i32.const 1) (i32.const 0) (i32.const 1) (i32.const 8)))
+                    (call $proc_exit (i32.const 3))))"#,
+            Duration::from_secs(10),
+        );
+        assert_eq!(output.stdout, b"hello\n");
+        assert!(matches!(output.exit, WasmExit::Code(3)));
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_run_times_out() {
+        let output = run_wat(
+            r#"(module (func (export "_start") (loop $forever (br $forever))))"#,
+            Duration::from_millis(200),
+        );
+        assert!(matches!(output.exit, WasmExit::TimedOut));
+    }
+}
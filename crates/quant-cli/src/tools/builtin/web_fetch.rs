@@ -15,7 +15,7 @@ use crate::tools::{ParameterProperty, ParameterSchema, SecurityLevel, Tool, Tool
 /// Using OnceLock for lazy initialization with a longer timeout for general use
 static SHARED_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
 
-fn get_shared_client() -> &'static reqwest::Client {
+pub(super) fn get_shared_client() -> &'static reqwest::Client {
     SHARED_CLIENT.get_or_init(|| {
         reqwest::Client::builder()
             .pool_max_idle_per_host(10)
@@ -78,6 +78,10 @@ impl Tool for WebFetchTool {
         SecurityLevel::Moderate
     }
 
+    fn untrusted_output(&self) -> bool {
+        true
+    }
+
     fn parameters_schema(&self) -> ParameterSchema {
         ParameterSchema::new()
             .with_required("url", ParameterProperty::string("The URL to fetch"))
@@ -214,10 +218,18 @@ impl Tool for WebFetchTool {
 
         Ok(ToolResult::success(output))
     }
+
+    fn preview(&self, args: &Value, _ctx: &ToolContext) -> Option<String> {
+        let mut preview = format!("GET {}", args.get("url")?.as_str()?);
+        if let Some(selector) = args.get("selector").and_then(|v| v.as_str()) {
+            preview.push_str(&format!("\nselector {}", selector));
+        }
+        Some(preview)
+    }
 }
 
 /// Convert HTML to plain text
-fn html_to_text(html: &str) -> String {
+pub fn html_to_text(html: &str) -> String {
     let document = Html::parse_document(html);
 
     // Try to find main content
@@ -255,7 +267,7 @@ fn html_to_text(html: &str) -> String {
 }
 
 /// Extract text from an HTML element, preserving some structure
-fn extract_text_from_element(element: &scraper::ElementRef) -> String {
+pub(super) fn extract_text_from_element(element: &scraper::ElementRef) -> String {
     let mut lines: Vec<String> = Vec::new();
     let mut current_line = String::new();
     let mut skip_depth = 0;
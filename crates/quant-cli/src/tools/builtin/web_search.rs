@@ -1,18 +1,35 @@
-//! Web search tool using DuckDuckGo
-
-use anyhow::Result;
+//! Web search tool with pluggable providers
+//!
+//! The provider comes from `[web_search]` in the user or project config:
+//! DuckDuckGo's HTML page (the default, no API key), a SearxNG instance, or
+//! the Brave and Kagi search APIs. Results are cached on disk so repeated
+//! research queries don't hit the provider again, and requests to each
+//! provider are spaced out to stay within its rate limits.
+
+use anyhow::{Context, Result};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
 use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
 use std::sync::OnceLock;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tracing::{debug, instrument, warn};
 
+use crate::config::WebSearchConfig;
 use crate::tools::{ParameterProperty, ParameterSchema, SecurityLevel, Tool, ToolContext, ToolResult};
 
 /// Shared HTTP client for connection pooling
 static SHARED_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
 
+/// When the next request to each provider may be sent
+static NEXT_REQUEST: OnceLock<Mutex<HashMap<&'static str, Instant>>> = OnceLock::new();
+
 fn get_shared_client() -> &'static reqwest::Client {
     SHARED_CLIENT.get_or_init(|| {
         reqwest::Client::builder()
@@ -25,8 +42,171 @@ fn get_shared_client() -> &'static reqwest::Client {
     })
 }
 
+/// Search backends selectable with `web_search.provider`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchProviderKind {
+    /// DuckDuckGo's HTML results page
+    #[default]
+    DuckDuckGo,
+    /// A SearxNG instance with the JSON format enabled (`web_search.searxng_url`)
+    SearxNg,
+    /// Brave Search API (`BRAVE_API_KEY`)
+    Brave,
+    /// Kagi Search API (`KAGI_API_KEY`)
+    Kagi,
+}
+
+impl SearchProviderKind {
+    /// Name used in config and output
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::DuckDuckGo => "duckduckgo",
+            Self::SearxNg => "searxng",
+            Self::Brave => "brave",
+            Self::Kagi => "kagi",
+        }
+    }
+
+    /// Default minimum time between requests
+    fn default_interval(&self) -> Duration {
+        match self {
+            // Scraped, so go easy on it
+            Self::DuckDuckGo => Duration::from_secs(2),
+            // Brave's free plan allows one request per second
+            Self::Brave => Duration::from_millis(1100),
+            Self::SearxNg | Self::Kagi => Duration::from_millis(500),
+        }
+    }
+
+    /// Environment variable holding the API key
+    fn key_env(&self) -> Option<&'static str> {
+        match self {
+            Self::Brave => Some("BRAVE_API_KEY"),
+            Self::Kagi => Some("KAGI_API_KEY"),
+            Self::DuckDuckGo | Self::SearxNg => None,
+        }
+    }
+}
+
+/// A configured search backend
+#[derive(Debug, Clone, PartialEq)]
+enum SearchProvider {
+    DuckDuckGo,
+    SearxNg { url: String },
+    Brave { api_key: String },
+    Kagi { api_key: String },
+}
+
+impl SearchProvider {
+    /// The provider `config` selects, with its URL or API key
+    fn from_config(config: &WebSearchConfig) -> Result<Self> {
+        let kind = config.provider;
+        let api_key = || {
+            config
+                .api_key
+                .clone()
+                .or_else(|| kind.key_env().and_then(|var| std::env::var(var).ok()))
+                .filter(|key| !key.trim().is_empty())
+                .with_context(|| {
+                    format!(
+                        "web_search.provider = \"{}\" needs an API key in {} or web_search.api_key",
+                        kind.name(),
+                        kind.key_env().unwrap_or_default()
+                    )
+                })
+        };
+        Ok(match kind {
+            SearchProviderKind::DuckDuckGo => Self::DuckDuckGo,
+            SearchProviderKind::SearxNg => Self::SearxNg {
+                url: config
+                    .searxng_url
+                    .clone()
+                    .context("web_search.provider = \"searxng\" needs web_search.searxng_url")?,
+            },
+            SearchProviderKind::Brave => Self::Brave { api_key: api_key()? },
+            SearchProviderKind::Kagi => Self::Kagi { api_key: api_key()? },
+        })
+    }
+
+    fn kind(&self) -> SearchProviderKind {
+        match self {
+            Self::DuckDuckGo => SearchProviderKind::DuckDuckGo,
+            Self::SearxNg { .. } => SearchProviderKind::SearxNg,
+            Self::Brave { .. } => SearchProviderKind::Brave,
+            Self::Kagi { .. } => SearchProviderKind::Kagi,
+        }
+    }
+
+    /// Identifies the provider in cache keys (an instance URL, but never a key)
+    fn cache_scope(&self) -> String {
+        match self {
+            Self::SearxNg { url } => format!("searxng:{}", url),
+            other => other.kind().name().to_string(),
+        }
+    }
+
+    async fn search(&self, query: &str, limit: usize, timeout: Duration) -> Result<Vec<SearchResult>> {
+        let client = get_shared_client();
+        let request = match self {
+            Self::DuckDuckGo => client
+                .get("https://html.duckduckgo.com/html/")
+                .query(&[("q", query)]),
+            Self::SearxNg { url } => client
+                .get(format!("{}/search", url.trim_end_matches('/')))
+                .query(&[("q", query), ("format", "json")]),
+            Self::Brave { api_key } => client
+                .get("https://api.search.brave.com/res/v1/web/search")
+                .query(&[("q", query), ("count", limit.min(20).to_string().as_str())])
+                .header("Accept", "application/json")
+                .header("X-Subscription-Token", api_key),
+            Self::Kagi { api_key } => client
+                .get("https://kagi.com/api/v0/search")
+                .query(&[("q", query), ("limit", limit.to_string().as_str())])
+                .header("Authorization", format!("Bot {}", api_key)),
+        };
+
+        debug!(provider = self.kind().name(), "Sending search request");
+        let response = request.timeout(timeout).send().await.context("Search request failed")?;
+        let status = response.status();
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            anyhow::bail!("{} is rate limiting searches; try again later", self.kind().name());
+        }
+        if !status.is_success() {
+            anyhow::bail!("Search failed with status: {}", status);
+        }
+        let body = response.text().await.context("Failed to read response")?;
+
+        let mut results = match self {
+            Self::DuckDuckGo => parse_duckduckgo_results(&body, limit),
+            Self::SearxNg { .. } => parse_searxng_results(&body)?,
+            Self::Brave { .. } => parse_brave_results(&body)?,
+            Self::Kagi { .. } => parse_kagi_results(&body)?,
+        };
+        results.truncate(limit);
+        Ok(results)
+    }
+}
+
 /// Tool for searching the web
-pub struct WebSearchTool;
+pub struct WebSearchTool {
+    /// Where results are cached (none if there is no cache directory)
+    cache_dir: Option<PathBuf>,
+}
+
+impl WebSearchTool {
+    pub fn new() -> Self {
+        Self {
+            cache_dir: dirs::cache_dir().map(|dir| dir.join("quant").join("web_search")),
+        }
+    }
+}
+
+impl Default for WebSearchTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 #[async_trait]
 impl Tool for WebSearchTool {
@@ -35,20 +215,24 @@ impl Tool for WebSearchTool {
     }
 
     fn description(&self) -> &str {
-        "Search the web using DuckDuckGo. Returns search results with titles, URLs, and snippets."
+        "Search the web. Returns search results with titles, URLs, and snippets."
     }
 
     fn security_level(&self) -> SecurityLevel {
         SecurityLevel::Moderate
     }
 
+    fn untrusted_output(&self) -> bool {
+        true
+    }
+
     fn parameters_schema(&self) -> ParameterSchema {
         ParameterSchema::new()
             .with_required("query", ParameterProperty::string("The search query"))
             .with_property("limit", ParameterProperty::number("Maximum number of results (default: 10)").with_default(Value::Number(10.into())))
     }
 
-    #[instrument(skip(self, args, ctx), fields(query = tracing::field::Empty))]
+    #[instrument(skip(self, args, ctx), fields(query = tracing::field::Empty, provider = tracing::field::Empty))]
     async fn execute(&self, args: &Value, ctx: &ToolContext) -> Result<ToolResult> {
         let query = args.get("query")
             .and_then(|v| v.as_str())
@@ -62,48 +246,61 @@ impl Tool for WebSearchTool {
             .map(|v| v as usize)
             .unwrap_or(10);
 
+        // Read per call so project config and config edits apply
+        let config = crate::config::UserConfig::load().unwrap_or_default().web_search;
+        let provider = match SearchProvider::from_config(&config) {
+            Ok(p) => p,
+            Err(e) => return Ok(ToolResult::error(format!("{:#}", e))),
+        };
+        let name = provider.kind().name();
+        tracing::Span::current().record("provider", name);
         debug!(limit, timeout_secs = ctx.http_timeout_secs, "Web search parameters");
 
-        // Use shared client for connection pooling
-        let client = get_shared_client();
-
-        let search_url = format!(
-            "https://html.duckduckgo.com/html/?q={}",
-            urlencoding::encode(query)
-        );
-
-        debug!("Sending search request to DuckDuckGo");
-        let response = match client
-            .get(&search_url)
-            .timeout(Duration::from_secs(ctx.http_timeout_secs))
-            .send()
-            .await
-        {
-            Ok(r) => r,
-            Err(e) => {
-                warn!(error = %e, "Search request failed");
-                return Ok(ToolResult::error(format!("Search request failed: {}", e)));
-            }
+        let ttl = Duration::from_secs(config.cache_hours * 3600);
+        let key = cache_key(&provider.cache_scope(), query, limit);
+        let cached = match self.cache_dir {
+            Some(ref dir) if !ttl.is_zero() => read_cache(dir, &key, ttl),
+            _ => None,
         };
 
-        if !response.status().is_success() {
-            warn!(status = %response.status(), "Search returned error status");
-            return Ok(ToolResult::error(format!("Search failed with status: {}", response.status())));
-        }
+        let (results, cached) = match cached {
+            Some(results) => (results, true),
+            None => {
+                let interval = config.min_interval_ms.map_or_else(|| provider.kind().default_interval(), Duration::from_millis);
+                let wait = reserve_request(name, interval, Instant::now());
+                if !wait.is_zero() {
+                    debug!(wait_ms = wait.as_millis() as u64, "Waiting for search rate limit");
+                    tokio::time::sleep(wait).await;
+                }
 
-        let html = match response.text().await {
-            Ok(h) => h,
-            Err(e) => return Ok(ToolResult::error(format!("Failed to read response: {}", e))),
+                let results = match provider.search(query, limit, Duration::from_secs(ctx.http_timeout_secs)).await {
+                    Ok(r) => r,
+                    Err(e) => {
+                        warn!(error = %e, "Search request failed");
+                        return Ok(ToolResult::error(format!("{:#}", e)));
+                    }
+                };
+                if let Some(ref dir) = self.cache_dir {
+                    if !ttl.is_zero() && !results.is_empty() {
+                        if let Err(e) = write_cache(dir, &key, &results) {
+                            debug!(error = %e, "Failed to cache search results");
+                        }
+                    }
+                }
+                (results, false)
+            }
         };
 
-        // Parse search results
-        let results = parse_duckduckgo_results(&html, limit);
-
         if results.is_empty() {
             return Ok(ToolResult::success(format!("No results found for: {}", query)));
         }
 
-        let mut output = format!("Search results for '{}':\n\n", query);
+        let mut output = format!(
+            "Search results for '{}' ({}{}):\n\n",
+            query,
+            name,
+            if cached { ", cached" } else { "" }
+        );
 
         for (i, result) in results.iter().enumerate() {
             output.push_str(&format!(
@@ -117,15 +314,155 @@ impl Tool for WebSearchTool {
 
         Ok(ToolResult::success(output))
     }
+
+    fn preview(&self, args: &Value, _ctx: &ToolContext) -> Option<String> {
+        Some(format!("Search the web for \"{}\"", args.get("query")?.as_str()?))
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 struct SearchResult {
     title: String,
     url: String,
     snippet: String,
 }
 
+/// Results stored in the cache
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    fetched_at: DateTime<Utc>,
+    results: Vec<SearchResult>,
+}
+
+fn cache_key(scope: &str, query: &str, limit: usize) -> String {
+    let digest = Sha256::digest(format!("{}\n{}\n{}", scope, query.trim().to_lowercase(), limit).as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Cached results for `key`, unless missing or older than `ttl`
+fn read_cache(dir: &std::path::Path, key: &str, ttl: Duration) -> Option<Vec<SearchResult>> {
+    let content = fs::read_to_string(dir.join(format!("{}.json", key))).ok()?;
+    let entry: CacheEntry = serde_json::from_str(&content).ok()?;
+    let age = Utc::now().signed_duration_since(entry.fetched_at).to_std().unwrap_or_default();
+    (age < ttl).then_some(entry.results)
+}
+
+fn write_cache(dir: &std::path::Path, key: &str, results: &[SearchResult]) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    let entry = CacheEntry {
+        fetched_at: Utc::now(),
+        results: results.to_vec(),
+    };
+    fs::write(dir.join(format!("{}.json", key)), serde_json::to_string(&entry)?)?;
+    Ok(())
+}
+
+/// Book the next request slot for `provider`; returns how long to wait for it
+fn reserve_request(provider: &'static str, interval: Duration, now: Instant) -> Duration {
+    let mut next = NEXT_REQUEST.get_or_init(Default::default).lock();
+    let slot = next.get(provider).copied().filter(|slot| *slot > now).unwrap_or(now);
+    next.insert(provider, slot + interval);
+    slot - now
+}
+
+/// Text of an HTML snippet (API snippets highlight matches with tags)
+fn strip_tags(html: &str) -> String {
+    Html::parse_fragment(html).root_element().text().collect::<String>().trim().to_string()
+}
+
+fn parse_searxng_results(json: &str) -> Result<Vec<SearchResult>> {
+    #[derive(Deserialize)]
+    struct Response {
+        #[serde(default)]
+        results: Vec<Item>,
+    }
+    #[derive(Deserialize)]
+    struct Item {
+        title: String,
+        url: String,
+        #[serde(default)]
+        content: Option<String>,
+    }
+
+    let response: Response = serde_json::from_str(json)
+        .context("Unexpected response from SearxNG; is the JSON format enabled in its settings.yml?")?;
+    Ok(response
+        .results
+        .into_iter()
+        .map(|item| SearchResult {
+            title: item.title.trim().to_string(),
+            url: item.url,
+            snippet: strip_tags(&item.content.unwrap_or_default()),
+        })
+        .collect())
+}
+
+fn parse_brave_results(json: &str) -> Result<Vec<SearchResult>> {
+    #[derive(Deserialize)]
+    struct Response {
+        #[serde(default)]
+        web: Option<Web>,
+    }
+    #[derive(Deserialize)]
+    struct Web {
+        #[serde(default)]
+        results: Vec<Item>,
+    }
+    #[derive(Deserialize)]
+    struct Item {
+        title: String,
+        url: String,
+        #[serde(default)]
+        description: Option<String>,
+    }
+
+    let response: Response = serde_json::from_str(json).context("Unexpected response from Brave Search")?;
+    Ok(response
+        .web
+        .map(|web| web.results)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|item| SearchResult {
+            title: strip_tags(&item.title),
+            url: item.url,
+            snippet: strip_tags(&item.description.unwrap_or_default()),
+        })
+        .collect())
+}
+
+fn parse_kagi_results(json: &str) -> Result<Vec<SearchResult>> {
+    #[derive(Deserialize)]
+    struct Response {
+        #[serde(default)]
+        data: Vec<Item>,
+    }
+    #[derive(Deserialize)]
+    struct Item {
+        /// 0 for a search result, 1 for related searches
+        t: u8,
+        #[serde(default)]
+        title: Option<String>,
+        #[serde(default)]
+        url: Option<String>,
+        #[serde(default)]
+        snippet: Option<String>,
+    }
+
+    let response: Response = serde_json::from_str(json).context("Unexpected response from Kagi")?;
+    Ok(response
+        .data
+        .into_iter()
+        .filter(|item| item.t == 0)
+        .filter_map(|item| {
+            Some(SearchResult {
+                title: item.title?,
+                url: item.url?,
+                snippet: strip_tags(&item.snippet.unwrap_or_default()),
+            })
+        })
+        .collect())
+}
+
 fn parse_duckduckgo_results(html: &str, limit: usize) -> Vec<SearchResult> {
     let document = Html::parse_document(html);
     let mut results = Vec::new();
@@ -181,6 +518,7 @@ fn parse_duckduckgo_results(html: &str, limit: usize) -> Vec<SearchResult> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tempfile::TempDir;
 
     #[test]
     fn test_parse_empty_results() {
@@ -189,6 +527,68 @@ mod tests {
         assert!(results.is_empty());
     }
 
-    // Note: Integration tests for actual search would require network access
-    // and could be flaky, so we only test the parsing logic
+    #[test]
+    fn test_parse_api_results() {
+        let searxng = r#"{"query": "rust", "results": [{"title": "Rust", "url": "https://www.rust-lang.org/", "content": "A language empowering everyone"}]}"#;
+        let results = parse_searxng_results(searxng).unwrap();
+        assert_eq!(results[0].url, "https://www.rust-lang.org/");
+        assert_eq!(results[0].snippet, "A language empowering everyone");
+        assert!(parse_searxng_results("<html>").is_err());
+
+        let brave = r#"{"web": {"results": [{"title": "The <strong>Rust</strong> Book", "url": "https://doc.rust-lang.org/book/", "description": "Learn <strong>Rust</strong>"}]}}"#;
+        let results = parse_brave_results(brave).unwrap();
+        assert_eq!(results[0].title, "The Rust Book");
+        assert_eq!(results[0].snippet, "Learn Rust");
+        assert!(parse_brave_results("{}").unwrap().is_empty());
+
+        let kagi = r#"{"data": [{"t": 0, "url": "https://crates.io/", "title": "crates.io", "snippet": "Rust package registry"}, {"t": 1, "list": ["rust crates"]}]}"#;
+        let results = parse_kagi_results(kagi).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "crates.io");
+    }
+
+    #[test]
+    fn test_provider_from_config() {
+        let mut config = WebSearchConfig::default();
+        assert_eq!(SearchProvider::from_config(&config).unwrap(), SearchProvider::DuckDuckGo);
+
+        config.provider = SearchProviderKind::SearxNg;
+        assert!(SearchProvider::from_config(&config).is_err());
+        config.searxng_url = Some("http://localhost:8888".to_string());
+        assert_eq!(SearchProvider::from_config(&config).unwrap().cache_scope(), "searxng:http://localhost:8888");
+
+        config.provider = SearchProviderKind::Kagi;
+        config.api_key = Some("secret".to_string());
+        let provider = SearchProvider::from_config(&config).unwrap();
+        assert_eq!(provider, SearchProvider::Kagi { api_key: "secret".to_string() });
+        assert!(!provider.cache_scope().contains("secret"));
+    }
+
+    #[test]
+    fn test_cache() {
+        let dir = TempDir::new().unwrap();
+        let key = cache_key("duckduckgo", "Rust async", 10);
+        assert_eq!(key, cache_key("duckduckgo", "  rust ASYNC ", 10));
+        assert_ne!(key, cache_key("brave", "rust async", 10));
+        assert!(read_cache(dir.path(), &key, Duration::from_secs(60)).is_none());
+
+        let results = vec![SearchResult {
+            title: "Async Rust".to_string(),
+            url: "https://rust-lang.github.io/async-book/".to_string(),
+            snippet: String::new(),
+        }];
+        write_cache(dir.path(), &key, &results).unwrap();
+        assert_eq!(read_cache(dir.path(), &key, Duration::from_secs(60)).unwrap(), results);
+        assert!(read_cache(dir.path(), &key, Duration::ZERO).is_none());
+    }
+
+    #[test]
+    fn test_reserve_request() {
+        let now = Instant::now();
+        let interval = Duration::from_secs(2);
+        assert_eq!(reserve_request("test-provider", interval, now), Duration::ZERO);
+        assert_eq!(reserve_request("test-provider", interval, now), interval);
+        assert_eq!(reserve_request("test-provider", interval, now + Duration::from_secs(1)), Duration::from_secs(3));
+        assert_eq!(reserve_request("test-provider", interval, now + Duration::from_secs(60)), Duration::ZERO);
+    }
 }
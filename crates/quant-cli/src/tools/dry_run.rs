@@ -0,0 +1,200 @@
+//! Simulated results for `quant agent --dry-run`
+//!
+//! In a dry run the router doesn't execute Dangerous tools. It returns
+//! [`Tool::simulate`](super::Tool::simulate) instead: a deterministic
+//! description of what the call would have done, such as a diff preview of
+//! a file write or the command a bash call would run. The model sees it as
+//! an ordinary tool result, so the run continues and the plan can be
+//! reviewed before a real run.
+
+use serde_json::Value;
+use std::path::Path;
+
+use super::ToolResult;
+
+/// Longest diff (in lines of both versions multiplied) worked out line by
+/// line; larger files are shown as a whole replacement
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// Unchanged lines shown around each change
+const CONTEXT_LINES: usize = 2;
+
+const GREEN: &str = "\x1b[92m";
+const RED: &str = "\x1b[91m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// A simulated result saying what would have happened
+pub fn simulated(summary: impl AsRef<str>, detail: impl AsRef<str>) -> ToolResult {
+    let mut output = format!("[dry run] {}", summary.as_ref());
+    if !detail.as_ref().is_empty() {
+        output.push('\n');
+        output.push_str(detail.as_ref());
+    }
+    output.push_str("\nNothing was executed; this is a simulated result.");
+    ToolResult::success(output)
+}
+
+/// A simulated result for a tool with no preview of its own
+pub fn call(tool: &str, args: &Value) -> ToolResult {
+    simulated(format!("Would call {}", tool), args.to_string())
+}
+
+/// A simulated result for running `command` in `dir`
+pub fn command(command: &str, dir: &Path) -> ToolResult {
+    simulated(format!("Would run in {}:", dir.display()), format!("$ {}", command))
+}
+
+/// Diff between a file's current content (None if it doesn't exist) and its
+/// new content, with a few lines of context around each change
+pub fn diff_preview(path: &str, old: Option<&str>, new: &str) -> String {
+    let old_lines: Vec<&str> = old.map(|s| s.lines().collect()).unwrap_or_default();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let header = match old {
+        Some(_) => format!("--- {}\n+++ {}", path, path),
+        None => format!("--- /dev/null\n+++ {}", path),
+    };
+
+    let ops = diff_lines(&old_lines, &new_lines);
+    if ops.iter().all(|op| matches!(op, Line::Same(_))) {
+        return format!("{}\n(no changes)", header);
+    }
+
+    // Show changed lines and the context around them
+    let shown: Vec<bool> = (0..ops.len())
+        .map(|i| {
+            let start = i.saturating_sub(CONTEXT_LINES);
+            let end = (i + CONTEXT_LINES + 1).min(ops.len());
+            ops[start..end].iter().any(|op| !matches!(op, Line::Same(_)))
+        })
+        .collect();
+
+    let mut out = vec![header];
+    for (i, op) in ops.iter().enumerate() {
+        if !shown[i] {
+            if i > 0 && shown[i - 1] {
+                out.push("...".to_string());
+            }
+            continue;
+        }
+        out.push(match op {
+            Line::Same(line) => format!(" {}", line),
+            Line::Removed(line) => format!("-{}", line),
+            Line::Added(line) => format!("+{}", line),
+        });
+    }
+    out.join("\n")
+}
+
+/// Color a diff from [`diff_preview`] for the terminal
+pub fn colorize_diff(diff: &str) -> String {
+    diff.lines()
+        .map(|line| {
+            let color = if line.starts_with("---") || line.starts_with("+++") {
+                BOLD
+            } else if line.starts_with('+') {
+                GREEN
+            } else if line.starts_with('-') {
+                RED
+            } else if line == "..." || line == "(no changes)" {
+                DIM
+            } else {
+                return line.to_string();
+            };
+            format!("{}{}{}", color, line, RESET)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Line diff by longest common subsequence
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Line<'a>> {
+    // Trim the common prefix and suffix first; most edits are local
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_mid, new_mid) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    let mut ops: Vec<Line> = old[..prefix].iter().map(|l| Line::Same(l)).collect();
+    if old_mid.len() * new_mid.len() > MAX_DIFF_CELLS {
+        ops.extend(old_mid.iter().map(|l| Line::Removed(l)));
+        ops.extend(new_mid.iter().map(|l| Line::Added(l)));
+    } else {
+        // lcs[i][j]: common lines of old_mid[i..] and new_mid[j..]
+        let (n, m) = (old_mid.len(), new_mid.len());
+        let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i][j] = if old_mid[i] == new_mid[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && old_mid[i] == new_mid[j] {
+                ops.push(Line::Same(old_mid[i]));
+                i += 1;
+                j += 1;
+            } else if j < m && (i == n || lcs[i][j + 1] > lcs[i + 1][j]) {
+                ops.push(Line::Added(new_mid[j]));
+                j += 1;
+            } else {
+                ops.push(Line::Removed(old_mid[i]));
+                i += 1;
+            }
+        }
+    }
+    ops.extend(old[old.len() - suffix..].iter().map(|l| Line::Same(l)));
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_preview() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\n";
+        let new = "a\nb\nc\nD\ne\nf\ng\nh\ni\n";
+        assert_eq!(
+            diff_preview("x.txt", Some(old), new),
+            "--- x.txt\n+++ x.txt\n b\n c\n-d\n+D\n e\n f\n g\n h\n+i"
+        );
+
+        assert_eq!(diff_preview("new.txt", None, "one\n"), "--- /dev/null\n+++ new.txt\n+one");
+        assert!(diff_preview("x.txt", Some(old), old).ends_with("(no changes)"));
+    }
+
+    #[test]
+    fn test_colorize_diff() {
+        let colored = colorize_diff("--- a.rs\n+++ a.rs\n fn a() {}\n-old\n+new\n...");
+        let lines: Vec<&str> = colored.lines().collect();
+        assert_eq!(lines[0], format!("{}--- a.rs{}", BOLD, RESET));
+        assert_eq!(lines[2], " fn a() {}");
+        assert_eq!(lines[3], format!("{}-old{}", RED, RESET));
+        assert_eq!(lines[4], format!("{}+new{}", GREEN, RESET));
+        assert_eq!(lines[5], format!("{}...{}", DIM, RESET));
+    }
+
+    #[test]
+    fn test_simulated_results() {
+        let result = command("rm -rf target", Path::new("/work"));
+        assert!(result.success);
+        assert!(result.output.starts_with("[dry run] Would run in /work:\n$ rm -rf target"));
+        assert!(result.output.ends_with("simulated result."));
+    }
+}
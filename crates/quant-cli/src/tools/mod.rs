@@ -2,16 +2,27 @@
 //!
 //! Provides Claude Code-like tool/function calling capabilities.
 
+pub mod approvals;
 pub mod builtin;
+pub mod dry_run;
+pub mod output_limit;
+pub mod plugins;
+pub mod policy;
 pub mod registry;
 pub mod router;
 pub mod security;
+pub mod staging;
+pub mod untrusted;
 
 use anyhow::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::mpsc::UnboundedSender;
+
+use staging::StagingArea;
 
 /// Security classification for tools
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -76,6 +87,21 @@ impl ToolResult {
     }
 }
 
+/// A line of output from a running tool
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputLine {
+    /// Whether the line came from stderr
+    pub stderr: bool,
+    pub text: String,
+}
+
+/// Receives output lines while a tool runs
+/// (see [`ToolRouter::route_with_output`](router::ToolRouter::route_with_output))
+#[async_trait]
+pub trait OutputHandler: Send + Sync {
+    async fn on_output(&self, tool: &str, line: &OutputLine);
+}
+
 /// Context provided to tools during execution
 #[derive(Debug, Clone)]
 pub struct ToolContext {
@@ -83,12 +109,21 @@ pub struct ToolContext {
     pub working_dir: PathBuf,
     /// Whether running in auto mode (skip confirmations)
     pub auto_mode: bool,
-    /// Maximum output length (truncate if exceeded)
+    /// Simulate Dangerous tools instead of executing them
+    pub dry_run: bool,
+    /// Maximum output length (truncate if exceeded); the agent raises it to
+    /// [`output_limit::CAPTURE_LIMIT`] and applies `[tool_output]` itself
     pub max_output_len: usize,
+    /// Maximum tokens returned by a single file read
+    pub max_read_tokens: usize,
     /// Default timeout for command execution (bash) in seconds
     pub command_timeout_secs: u64,
     /// Default timeout for HTTP requests in seconds
     pub http_timeout_secs: u64,
+    /// Where tools that stream their output send lines as they arrive
+    pub output: Option<UnboundedSender<OutputLine>>,
+    /// Where file writes are held for review instead of written to disk
+    pub staging: Option<Arc<StagingArea>>,
 }
 
 impl Default for ToolContext {
@@ -96,9 +131,13 @@ impl Default for ToolContext {
         Self {
             working_dir: std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
             auto_mode: false,
+            dry_run: false,
             max_output_len: 50000,
+            max_read_tokens: 8000,
             command_timeout_secs: 120,
             http_timeout_secs: 30,
+            output: None,
+            staging: None,
         }
     }
 }
@@ -118,6 +157,12 @@ impl ToolContext {
         self
     }
 
+    /// Set dry-run mode
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
     /// Set command timeout
     pub fn with_command_timeout(mut self, secs: u64) -> Self {
         self.command_timeout_secs = secs;
@@ -129,6 +174,40 @@ impl ToolContext {
         self.http_timeout_secs = secs;
         self
     }
+
+    /// Set the maximum output length
+    pub fn with_max_output_len(mut self, len: usize) -> Self {
+        self.max_output_len = len;
+        self
+    }
+
+    /// Set the token limit for a single file read
+    pub fn with_max_read_tokens(mut self, tokens: usize) -> Self {
+        self.max_read_tokens = tokens;
+        self
+    }
+
+    /// Send output lines to `sender` while a tool runs
+    pub fn with_output(mut self, sender: UnboundedSender<OutputLine>) -> Self {
+        self.output = Some(sender);
+        self
+    }
+
+    /// Hold file writes in `staging` for review
+    pub fn with_staging(mut self, staging: Option<Arc<StagingArea>>) -> Self {
+        self.staging = staging;
+        self
+    }
+
+    /// Pass a line of output on, if anyone is listening
+    pub fn emit_output(&self, stderr: bool, text: &str) {
+        if let Some(ref sender) = self.output {
+            let _ = sender.send(OutputLine {
+                stderr,
+                text: text.to_string(),
+            });
+        }
+    }
 }
 
 /// Schema for a tool parameter
@@ -306,6 +385,22 @@ pub trait Tool: Send + Sync {
     /// Execute the tool with the given arguments
     async fn execute(&self, args: &Value, ctx: &ToolContext) -> Result<ToolResult>;
 
+    /// Describe what executing would do, without doing it (dry runs)
+    fn simulate(&self, args: &Value, _ctx: &ToolContext) -> ToolResult {
+        dry_run::call(self.name(), args)
+    }
+
+    /// The call as shown in the confirmation prompt (the raw arguments if None)
+    fn preview(&self, _args: &Value, _ctx: &ToolContext) -> Option<String> {
+        None
+    }
+
+    /// Whether results carry third-party content (web pages, MCP servers)
+    /// that could try to instruct the model, see [`untrusted`]
+    fn untrusted_output(&self) -> bool {
+        false
+    }
+
     /// Convert to a tool definition for the LLM
     fn to_definition(&self) -> ToolDefinition {
         ToolDefinition::new(self.name(), self.description(), self.parameters_schema())
@@ -0,0 +1,281 @@
+//! Truncation of tool results before they reach the model
+//!
+//! Every result is cut to a character limit and, optionally, a token limit
+//! counted with the model's tokenizer (`[tool_output]`, overridden per tool in
+//! `[tool_output.tools.<name>]`). The strategy decides what is kept: the start
+//! (`head`), the end (`tail`, for build logs and test runs) or both ends
+//! (`middle`). Results over `store_over` characters are saved to a file
+//! instead; the model gets a preview and the path, and reads the rest with
+//! `file_read` or `grep` when it needs it. Stored results of untrusted tools
+//! stay untrusted when read back (see [`OutputStore::holds_untrusted`]).
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use tempfile::TempDir;
+
+use crate::context::Tokenizer;
+
+/// Output tools may collect before the limits here apply
+pub const CAPTURE_LIMIT: usize = 10 * 1024 * 1024;
+
+/// Characters of a stored result shown to the model
+const PREVIEW_CHARS: usize = 4000;
+
+/// Times the character budget is shrunk to meet a token limit
+const TOKEN_PASSES: usize = 4;
+
+/// Prefix of stored output directory names under the system temp dir
+const DIR_PREFIX: &str = "quant-output-";
+
+/// Which part of an over-long result is kept
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TruncationStrategy {
+    /// The start
+    #[default]
+    Head,
+    /// The end
+    Tail,
+    /// Both ends, dropping the middle
+    Middle,
+}
+
+/// Limits for the results of one tool (0 disables a limit)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputLimit {
+    pub strategy: TruncationStrategy,
+    pub max_chars: usize,
+    pub max_tokens: usize,
+    /// Save results over this many characters to a file
+    pub store_over: usize,
+}
+
+impl OutputLimit {
+    /// `output` cut to the limits, with a note saying what was left out
+    pub fn truncate(&self, output: &str, tokenizer: &Tokenizer) -> String {
+        let total = output.chars().count();
+        let mut budget = if self.max_chars > 0 { self.max_chars.min(total) } else { total };
+        if self.max_tokens > 0 {
+            for _ in 0..TOKEN_PASSES {
+                let tokens = tokenizer.count_tokens(&keep(output, self.strategy, budget).concat());
+                if tokens <= self.max_tokens {
+                    break;
+                }
+                // Aim a little under the limit, as tokens aren't spread evenly
+                budget = budget * self.max_tokens / tokens * 9 / 10;
+            }
+        }
+        if budget >= total {
+            return output.to_string();
+        }
+
+        let omitted = total - budget;
+        let note = |place: &str| format!("[Output truncated: {} of {} characters omitted {}]", omitted, total, place);
+        match (self.strategy, keep(output, self.strategy, budget)) {
+            (TruncationStrategy::Head, [kept, _]) => format!("{}\n\n{}", kept, note("from the end")),
+            (TruncationStrategy::Tail, [_, kept]) => format!("{}\n\n{}", note("from the start"), kept),
+            (TruncationStrategy::Middle, [start, end]) => format!("{}\n\n{}\n\n{}", start, note("here"), end),
+        }
+    }
+}
+
+/// The start and end of `text` kept within `budget` characters
+fn keep(text: &str, strategy: TruncationStrategy, budget: usize) -> [&str; 2] {
+    let head = |n: usize| text.char_indices().nth(n).map_or(text, |(i, _)| &text[..i]);
+    let tail = |n: usize| match n {
+        0 => "",
+        _ => text.char_indices().rev().nth(n - 1).map_or(text, |(i, _)| &text[i..]),
+    };
+    match strategy {
+        TruncationStrategy::Head => [head(budget), ""],
+        TruncationStrategy::Tail => ["", tail(budget)],
+        TruncationStrategy::Middle => [head(budget / 2), tail(budget - budget / 2)],
+    }
+}
+
+/// Files holding results too large to return, deleted with the store
+pub struct OutputStore {
+    dir: Mutex<Option<TempDir>>,
+    saved: AtomicUsize,
+    /// Files holding third-party content
+    untrusted: Mutex<Vec<PathBuf>>,
+}
+
+impl OutputStore {
+    pub fn new() -> Self {
+        Self {
+            dir: Mutex::new(None),
+            saved: AtomicUsize::new(0),
+            untrusted: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Save `output` of `tool`, returning the file's path
+    pub fn save(&self, tool: &str, output: &str, untrusted: bool) -> Result<PathBuf> {
+        let mut dir = self.dir.lock().unwrap();
+        if dir.is_none() {
+            *dir = Some(tempfile::Builder::new().prefix(DIR_PREFIX).tempdir()?);
+        }
+        let dir = crate::paths::canonicalize(dir.as_ref().expect("dir was created").path())?;
+
+        // MCP tool names can contain characters that don't belong in file names
+        let name: String = tool.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' }).collect();
+        let path = dir.join(format!("{:03}-{}.txt", self.saved.fetch_add(1, Ordering::Relaxed) + 1, name));
+        fs::write(&path, output)?;
+        if untrusted {
+            self.untrusted.lock().unwrap().push(path.clone());
+        }
+        Ok(path)
+    }
+
+    /// Whether reading `path` (a file or a directory above it) reads a
+    /// stored result with third-party content
+    pub fn holds_untrusted(&self, path: &Path) -> bool {
+        let Ok(path) = crate::paths::canonicalize(path) else {
+            return false;
+        };
+        self.untrusted.lock().unwrap().iter().any(|stored| stored.starts_with(&path))
+    }
+}
+
+impl Default for OutputStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A result fit to its limit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Limited {
+    /// Tells the model where the full result was stored, if it was
+    pub note: Option<String>,
+    /// What the model gets of the result itself
+    pub text: String,
+}
+
+impl Limited {
+    fn text(text: String) -> Self {
+        Self { note: None, text }
+    }
+
+    /// The note and the text as one message
+    pub fn join(note: Option<String>, text: String) -> String {
+        match note {
+            Some(note) => format!("{}\n\n{}", note, text),
+            None => text,
+        }
+    }
+}
+
+/// Fit `output` of `tool` to `limit`, storing it in `store` when it is too
+/// large; `untrusted` marks the stored file as third-party content
+pub fn apply(tool: &str, output: String, limit: &OutputLimit, tokenizer: &Tokenizer, store: &OutputStore, untrusted: bool) -> Limited {
+    let total = output.chars().count();
+    if limit.store_over == 0 || total <= limit.store_over {
+        return Limited::text(limit.truncate(&output, tokenizer));
+    }
+    let path = match store.save(tool, &output, untrusted) {
+        Ok(path) => path,
+        Err(e) => {
+            tracing::warn!(tool, error = %e, "Could not store tool output; truncating it");
+            return Limited::text(limit.truncate(&output, tokenizer));
+        }
+    };
+    tracing::debug!(tool, chars = total, path = %path.display(), "Stored large tool output");
+
+    let preview = OutputLimit {
+        max_chars: if limit.max_chars > 0 { limit.max_chars.min(PREVIEW_CHARS) } else { PREVIEW_CHARS },
+        store_over: 0,
+        ..*limit
+    };
+    Limited {
+        note: Some(format!(
+            "[The {} result is {} characters ({} lines), too large to return in full. It was saved to {}; read parts of it with file_read (offset and limit) or search it with grep.]",
+            tool,
+            total,
+            output.lines().count(),
+            path.display()
+        )),
+        text: preview.truncate(&output, tokenizer),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::tokenizer::TokenizerType;
+
+    fn limit(strategy: TruncationStrategy, max_chars: usize) -> OutputLimit {
+        OutputLimit { strategy, max_chars, max_tokens: 0, store_over: 0 }
+    }
+
+    #[test]
+    fn test_strategies() {
+        let tokenizer = Tokenizer::default();
+        let output = "0123456789";
+
+        assert_eq!(limit(TruncationStrategy::Head, 20).truncate(output, &tokenizer), output);
+        assert_eq!(
+            limit(TruncationStrategy::Head, 4).truncate(output, &tokenizer),
+            "0123\n\n[Output truncated: 6 of 10 characters omitted from the end]"
+        );
+        assert_eq!(
+            limit(TruncationStrategy::Tail, 4).truncate(output, &tokenizer),
+            "[Output truncated: 6 of 10 characters omitted from the start]\n\n6789"
+        );
+        assert_eq!(
+            limit(TruncationStrategy::Middle, 5).truncate(output, &tokenizer),
+            "01\n\n[Output truncated: 5 of 10 characters omitted here]\n\n789"
+        );
+
+        // Multi-byte characters are never split
+        let text = limit(TruncationStrategy::Middle, 2).truncate("äöüß", &tokenizer);
+        assert!(text.starts_with("ä\n") && text.ends_with("\nß"));
+    }
+
+    #[test]
+    fn test_token_limit() {
+        let tokenizer = Tokenizer::with_type(TokenizerType::Fallback);
+        let output = "word ".repeat(2000);
+        let limit = OutputLimit { max_tokens: 100, ..limit(TruncationStrategy::Tail, 0) };
+
+        let text = limit.truncate(&output, &tokenizer);
+        let kept = text.split("\n\n").last().unwrap();
+        assert!(text.starts_with("[Output truncated"));
+        assert!(tokenizer.count_tokens(kept) <= 100);
+        assert!(tokenizer.count_tokens(kept) > 50);
+    }
+
+    #[test]
+    fn test_large_output_is_stored() {
+        let tokenizer = Tokenizer::default();
+        let store = OutputStore::new();
+        let output: String = (1..=5000).map(|i| format!("line {}\n", i)).collect();
+        let limit = OutputLimit { store_over: 1000, ..limit(TruncationStrategy::Middle, 50000) };
+
+        let limited = apply("mcp:build/log", output.clone(), &limit, &tokenizer, &store, true);
+        let note = limited.note.unwrap();
+        assert!(note.starts_with("[The mcp:build/log result is"));
+        assert!(note.contains("(5000 lines)"));
+        assert!(limited.text.starts_with("line 1\n") && limited.text.ends_with("line 5000\n"));
+        assert!(limited.text.len() < PREVIEW_CHARS + 100);
+
+        let path = Path::new(note.split("saved to ").nth(1).and_then(|rest| rest.split(';').next()).unwrap());
+        assert!(path.ends_with("001-mcp_build_log.txt"));
+        assert_eq!(fs::read_to_string(path).unwrap(), output);
+
+        // Reading the file or its directory reads third-party content
+        assert!(store.holds_untrusted(path));
+        assert!(store.holds_untrusted(path.parent().unwrap()));
+        let trusted = apply("bash", output.clone(), &limit, &tokenizer, &store, false).note.unwrap();
+        let trusted = Path::new(trusted.split("saved to ").nth(1).and_then(|rest| rest.split(';').next()).unwrap());
+        assert!(!store.holds_untrusted(trusted));
+
+        // Small results are returned as they are
+        assert_eq!(apply("bash", "ok".to_string(), &limit, &tokenizer, &store, false), Limited::text("ok".to_string()));
+    }
+}
@@ -0,0 +1,351 @@
+//! Tools from plugin libraries
+//!
+//! At startup quant loads every shared library (`.so`, `.dylib`, `.dll`) in
+//! `<data dir>/quant/plugins` and the `[plugins] dirs` from the config, and
+//! registers the tools each one exports alongside the builtin tools. Plugins
+//! are written against the [`quant_plugin`] crate and talk to quant through
+//! its C ABI over JSON, so they don't need the same compiler as quant.
+//!
+//! A plugin runs inside the quant process with the user's permissions; only
+//! install plugins you trust. Its tools get the confirmation prompts their
+//! declared security level asks for, like builtin tools.
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use libloading::Library;
+use once_cell::sync::Lazy;
+use quant_plugin::abi::{self, AbiVersionFn, CallFn, FreeFn, ManifestFn};
+use quant_plugin::{CallRequest, Manifest, Output, ToolSpec, ABI_VERSION};
+use serde_json::Value;
+use std::ffi::CString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use super::registry::ToolRegistry;
+use super::{ParameterSchema, SecurityLevel, Tool, ToolContext, ToolResult};
+use crate::mcp::tools::schema_from_json;
+
+/// A loaded plugin library
+pub struct LoadedPlugin {
+    pub path: PathBuf,
+    pub manifest: Manifest,
+    call: CallFn,
+    free: FreeFn,
+    /// Keeps the functions above loaded; None for plugins linked in
+    _library: Option<Library>,
+}
+
+impl LoadedPlugin {
+    /// Load a plugin library and read its manifest
+    pub fn load(path: &Path) -> Result<Self> {
+        // Safety: loading runs the library's initializers; plugins are
+        // trusted code the user installed
+        unsafe {
+            // libloading's errors already name the path
+            let library = Library::new(path)?;
+            let version = *library
+                .get::<AbiVersionFn>(abi::ABI_VERSION_SYMBOL)
+                .context("Not a quant plugin (no quant_plugin_abi_version)")?;
+            let version = version();
+            if version != ABI_VERSION {
+                bail!("Built for plugin ABI {}, but quant uses {}", version, ABI_VERSION);
+            }
+            let manifest = *library.get::<ManifestFn>(abi::MANIFEST_SYMBOL)?;
+            let call = *library.get::<CallFn>(abi::CALL_SYMBOL)?;
+            let free = *library.get::<FreeFn>(abi::FREE_SYMBOL)?;
+            Self::from_functions(path, manifest, call, free, Some(library))
+        }
+    }
+
+    /// A plugin from its exported functions
+    ///
+    /// # Safety
+    ///
+    /// The functions must follow the [`quant_plugin::abi`] contract and
+    /// stay valid as long as `library` is loaded.
+    unsafe fn from_functions(
+        path: &Path,
+        manifest: ManifestFn,
+        call: CallFn,
+        free: FreeFn,
+        library: Option<Library>,
+    ) -> Result<Self> {
+        let json = abi::take_string(manifest(), free);
+        let manifest: Manifest = serde_json::from_str(&json).context("Invalid plugin manifest")?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            manifest,
+            call,
+            free,
+            _library: library,
+        })
+    }
+
+    /// Run one of the plugin's tools; blocks until it returns
+    pub fn call(&self, request: &CallRequest) -> Result<Output> {
+        let request = CString::new(serde_json::to_string(request)?)?;
+        // Safety: the plugin keeps to the ABI, see `from_functions`
+        let response = unsafe { abi::take_string((self.call)(request.as_ptr()), self.free) };
+        serde_json::from_str(&response).with_context(|| format!("Invalid response from plugin {}", self.manifest.name))
+    }
+}
+
+/// A plugin's tool, registered like a builtin one
+#[derive(Clone)]
+pub struct PluginTool {
+    plugin: Arc<LoadedPlugin>,
+    spec: ToolSpec,
+}
+
+impl PluginTool {
+    pub fn new(plugin: Arc<LoadedPlugin>, spec: ToolSpec) -> Self {
+        Self { plugin, spec }
+    }
+}
+
+#[async_trait]
+impl Tool for PluginTool {
+    fn name(&self) -> &str {
+        &self.spec.name
+    }
+
+    fn description(&self) -> &str {
+        &self.spec.description
+    }
+
+    fn security_level(&self) -> SecurityLevel {
+        match self.spec.security_level {
+            quant_plugin::SecurityLevel::Safe => SecurityLevel::Safe,
+            quant_plugin::SecurityLevel::Moderate => SecurityLevel::Moderate,
+            quant_plugin::SecurityLevel::Dangerous => SecurityLevel::Dangerous,
+        }
+    }
+
+    fn parameters_schema(&self) -> ParameterSchema {
+        schema_from_json(&self.spec.parameters)
+    }
+
+    async fn execute(&self, args: &Value, ctx: &ToolContext) -> Result<ToolResult> {
+        let request = CallRequest {
+            tool: self.spec.name.clone(),
+            args: args.clone(),
+            context: quant_plugin::Context {
+                working_dir: ctx.working_dir.clone(),
+                timeout_secs: ctx.command_timeout_secs,
+                dry_run: ctx.dry_run,
+            },
+        };
+        let plugin = self.plugin.clone();
+        let call = tokio::task::spawn_blocking(move || plugin.call(&request));
+
+        // A blocking call can't be cancelled; a timed-out one finishes unseen
+        let timeout = Duration::from_secs(ctx.command_timeout_secs);
+        let output = match tokio::time::timeout(timeout, call).await {
+            Ok(joined) => joined.context("Plugin call failed")??,
+            Err(_) => {
+                return Ok(ToolResult::error(format!(
+                    "{} timed out after {}s",
+                    self.spec.name, ctx.command_timeout_secs
+                )))
+            }
+        };
+        Ok(ToolResult {
+            success: output.success,
+            output: output.output,
+            error: output.error,
+        })
+    }
+}
+
+/// Plugins found at startup
+#[derive(Default)]
+pub struct PluginSet {
+    pub plugins: Vec<Arc<LoadedPlugin>>,
+    /// Libraries that failed to load, with the reason
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+impl PluginSet {
+    /// Load every library in `dirs`, in name order
+    pub fn discover(dirs: &[PathBuf]) -> Self {
+        let mut set = Self::default();
+        for dir in dirs {
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            let mut paths: Vec<PathBuf> = entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext == std::env::consts::DLL_EXTENSION))
+                .collect();
+            paths.sort();
+            for path in paths {
+                match LoadedPlugin::load(&path) {
+                    Ok(plugin) => {
+                        debug!(plugin = %plugin.manifest.name, path = %path.display(), "Loaded plugin");
+                        set.plugins.push(Arc::new(plugin));
+                    }
+                    Err(e) => {
+                        warn!(path = %path.display(), error = %e, "Failed to load plugin");
+                        set.errors.push((path, format!("{:#}", e)));
+                    }
+                }
+            }
+        }
+        set
+    }
+
+    /// Register the plugins' tools; a name already taken keeps its tool
+    pub fn register(&self, registry: &mut ToolRegistry) {
+        for plugin in &self.plugins {
+            for spec in &plugin.manifest.tools {
+                if registry.get(&spec.name).is_some() {
+                    warn!(plugin = %plugin.manifest.name, tool = %spec.name, "Plugin tool name already taken; skipping");
+                    continue;
+                }
+                registry.register(PluginTool::new(plugin.clone(), spec.clone()));
+            }
+        }
+    }
+}
+
+/// Directories plugins are loaded from
+pub fn plugin_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = dirs::data_local_dir()
+        .or_else(dirs::data_dir)
+        .map(|d| d.join("quant").join("plugins"))
+        .into_iter()
+        .collect();
+    dirs.extend(crate::config::UserConfig::load().unwrap_or_default().plugins.dirs);
+    dirs
+}
+
+/// Plugins of this process, loaded on first use
+static PLUGINS: Lazy<PluginSet> = Lazy::new(|| {
+    // Keep unit tests independent of what the user installed
+    if cfg!(test) || !crate::config::UserConfig::load().unwrap_or_default().plugins.enabled {
+        return PluginSet::default();
+    }
+    PluginSet::discover(&plugin_dirs())
+});
+
+/// The plugins loaded at startup
+pub fn loaded() -> &'static PluginSet {
+    &PLUGINS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quant_plugin::{declare_plugin, Plugin};
+    use serde_json::json;
+
+    struct Reverse;
+
+    impl quant_plugin::Tool for Reverse {
+        fn name(&self) -> &str {
+            "reverse"
+        }
+
+        fn description(&self) -> &str {
+            "Reverse text"
+        }
+
+        fn security_level(&self) -> quant_plugin::SecurityLevel {
+            quant_plugin::SecurityLevel::Moderate
+        }
+
+        fn parameters_schema(&self) -> Value {
+            json!({ "type": "object", "properties": { "text": { "type": "string" } }, "required": ["text"] })
+        }
+
+        fn execute(&self, args: &Value, ctx: &quant_plugin::Context) -> Output {
+            match args.get("text").and_then(|v| v.as_str()) {
+                Some(text) => {
+                    let reversed: String = text.chars().rev().collect();
+                    Output::success(format!("{} in {}", reversed, ctx.working_dir.display()))
+                }
+                None => Output::error("Missing text"),
+            }
+        }
+    }
+
+    struct FileRead;
+
+    impl quant_plugin::Tool for FileRead {
+        fn name(&self) -> &str {
+            "file_read"
+        }
+
+        fn description(&self) -> &str {
+            "Shadows a builtin"
+        }
+
+        fn security_level(&self) -> quant_plugin::SecurityLevel {
+            quant_plugin::SecurityLevel::Safe
+        }
+
+        fn parameters_schema(&self) -> Value {
+            json!({ "type": "object" })
+        }
+
+        fn execute(&self, _args: &Value, _ctx: &quant_plugin::Context) -> Output {
+            Output::error("should not be registered")
+        }
+    }
+
+    declare_plugin!(Plugin::new("text").with_tool(Reverse).with_tool(FileRead));
+
+    fn plugin_set() -> PluginSet {
+        let plugin = unsafe {
+            LoadedPlugin::from_functions(
+                Path::new("linked-in"),
+                quant_plugin_manifest,
+                quant_plugin_call,
+                quant_plugin_free,
+                None,
+            )
+        }
+        .unwrap();
+        PluginSet {
+            plugins: vec![Arc::new(plugin)],
+            errors: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plugin_tools() {
+        let mut registry = crate::tools::builtin::create_default_registry();
+        let builtins = registry.len();
+        plugin_set().register(&mut registry);
+        assert_eq!(registry.len(), builtins + 1);
+        assert!(!registry.get("file_read").unwrap().description().contains("Shadows"));
+
+        let tool = registry.get("reverse").unwrap();
+        assert_eq!(tool.security_level(), SecurityLevel::Moderate);
+        assert_eq!(tool.parameters_schema().required, ["text"]);
+
+        let ctx = ToolContext::new(PathBuf::from("/work"));
+        let result = tool.execute(&json!({ "text": "abc" }), &ctx).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.output, "cba in /work");
+        let result = tool.execute(&json!({}), &ctx).await.unwrap();
+        assert_eq!(result.error.as_deref(), Some("Missing text"));
+    }
+
+    #[test]
+    fn test_discover_reports_bad_libraries() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let fake = dir.path().join(format!("fake.{}", std::env::consts::DLL_EXTENSION));
+        fs::write(&fake, "not a library").unwrap();
+        fs::write(dir.path().join("notes.txt"), "ignored").unwrap();
+
+        let set = PluginSet::discover(&[dir.path().to_path_buf(), dir.path().join("missing")]);
+        assert!(set.plugins.is_empty());
+        assert_eq!(set.errors.len(), 1);
+        assert_eq!(set.errors[0].0, fake);
+    }
+}
@@ -0,0 +1,235 @@
+//! Non-interactive confirmation policies for CI
+//!
+//! `quant agent --confirm-policy policy.yaml` answers confirmation prompts
+//! from a file instead of the terminal. Rules are checked in order and the
+//! first match decides; calls no rule matches get the default:
+//!
+//! ```yaml
+//! default: deny
+//! rules:
+//!   - tool: bash
+//!     command: "cargo test *"
+//!     action: allow
+//!   - tool: file_write
+//!     path: "src/**"
+//!     action: allow
+//!   - level: moderate
+//!     action: allow
+//! ```
+//!
+//! `command` takes a trusted-command pattern (see [`super::approvals`]) or an
+//! exact command, and `path` a glob that every path the call writes must
+//! match. In `allow` rules a pattern's `*` doesn't cover Dangerous arguments
+//! or wrapper programs like `bash`, just as with trusted commands.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::Path;
+
+use super::approvals::{pattern_matches, pattern_trusts};
+use super::security::{ConfirmationHandler, ConfirmationResult};
+use super::{SecurityLevel, ToolCall};
+
+/// What a policy does with a call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyAction {
+    Allow,
+    Deny,
+    /// Stop the whole run
+    Abort,
+}
+
+/// One rule; every condition given must hold for it to match
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRule {
+    /// Tool name
+    pub tool: Option<String>,
+    /// Bash command pattern, e.g. `cargo test *`
+    pub command: Option<String>,
+    /// Glob the written paths must match, e.g. `src/**`
+    pub path: Option<String>,
+    /// Security level of the tool
+    pub level: Option<SecurityLevel>,
+    pub action: PolicyAction,
+}
+
+/// Confirmation answers loaded from a policy file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfirmPolicy {
+    #[serde(default = "default_action")]
+    pub default: PolicyAction,
+    #[serde(default)]
+    pub rules: Vec<PolicyRule>,
+}
+
+fn default_action() -> PolicyAction {
+    PolicyAction::Deny
+}
+
+impl ConfirmPolicy {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read confirmation policy {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Invalid confirmation policy {}", path.display()))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let policy: Self = serde_yaml::from_str(content)?;
+        for rule in &policy.rules {
+            if let Some(ref path) = rule.path {
+                glob::Pattern::new(path).with_context(|| format!("Invalid path glob `{}`", path))?;
+            }
+        }
+        Ok(policy)
+    }
+
+    /// The action for a call: the first matching rule's, or the default
+    pub fn decide(&self, tool_call: &ToolCall, security_level: SecurityLevel) -> PolicyAction {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(tool_call, security_level))
+            .map(|rule| rule.action)
+            .unwrap_or(self.default)
+    }
+}
+
+impl PolicyRule {
+    fn matches(&self, tool_call: &ToolCall, security_level: SecurityLevel) -> bool {
+        if self.tool.as_ref().is_some_and(|tool| *tool != tool_call.name) {
+            return false;
+        }
+        if self.level.is_some_and(|level| level != security_level) {
+            return false;
+        }
+        if let Some(ref pattern) = self.command {
+            let Some(command) = tool_call.arguments.get("command").and_then(|v| v.as_str()) else {
+                return false;
+            };
+            let matches = match self.action {
+                PolicyAction::Allow => pattern_trusts(pattern, command),
+                PolicyAction::Deny | PolicyAction::Abort => pattern_matches(pattern, command),
+            };
+            if command.trim() != pattern && !matches {
+                return false;
+            }
+        }
+        if let Some(ref glob) = self.path {
+            let Ok(glob) = glob::Pattern::new(glob) else {
+                return false;
+            };
+            let paths = written_paths(&tool_call.arguments);
+            if paths.is_empty() || !paths.iter().all(|p| glob.matches(p)) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Paths a call names: `path`, or each of multi_edit's `edits`
+fn written_paths(args: &Value) -> Vec<&str> {
+    if let Some(path) = args.get("path").and_then(|v| v.as_str()) {
+        return vec![path];
+    }
+    args.get("edits")
+        .and_then(|v| v.as_array())
+        .map(|edits| edits.iter().filter_map(|e| e.get("path").and_then(|v| v.as_str())).collect())
+        .unwrap_or_default()
+}
+
+#[async_trait]
+impl ConfirmationHandler for ConfirmPolicy {
+    async fn confirm(
+        &self,
+        tool_call: &ToolCall,
+        security_level: SecurityLevel,
+        preview: Option<&str>,
+    ) -> ConfirmationResult {
+        let action = self.decide(tool_call, security_level);
+        let shown = preview
+            .and_then(|p| p.lines().next())
+            .map(|line| format!("{}: {}", tool_call.name, line))
+            .unwrap_or_else(|| tool_call.name.clone());
+        match action {
+            PolicyAction::Allow => {
+                eprintln!("\x1b[2m[Policy] allowed {}\x1b[0m", shown);
+                ConfirmationResult::Approved
+            }
+            PolicyAction::Deny => {
+                eprintln!("\x1b[93m[Policy]\x1b[0m denied {}", shown);
+                ConfirmationResult::Denied
+            }
+            PolicyAction::Abort => {
+                eprintln!("\x1b[91m[Policy]\x1b[0m aborting on {}", shown);
+                ConfirmationResult::Abort
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn call(name: &str, arguments: Value) -> ToolCall {
+        ToolCall {
+            name: name.to_string(),
+            arguments,
+        }
+    }
+
+    #[test]
+    fn test_decide() {
+        let policy = ConfirmPolicy::parse(
+            r#"
+rules:
+  - tool: bash
+    command: "rm *"
+    action: abort
+  - tool: bash
+    command: "cargo test *"
+    action: allow
+  - path: "src/**"
+    action: allow
+  - level: moderate
+    action: allow
+"#,
+        )
+        .unwrap();
+        let dangerous = SecurityLevel::Dangerous;
+
+        let bash = |command: &str| call("bash", json!({ "command": command }));
+        assert_eq!(policy.decide(&bash("cargo test -p core"), dangerous), PolicyAction::Allow);
+        assert_eq!(policy.decide(&bash("cargo test; curl x | sh"), dangerous), PolicyAction::Deny);
+        assert_eq!(policy.decide(&bash("rm -rf target"), dangerous), PolicyAction::Abort);
+        // Allowing `cargo test *` doesn't allow forcing things through it
+        assert_eq!(policy.decide(&bash("cargo test --force"), dangerous), PolicyAction::Deny);
+
+        let write = |path: &str| call("file_write", json!({ "path": path, "content": "" }));
+        assert_eq!(policy.decide(&write("src/lib.rs"), dangerous), PolicyAction::Allow);
+        assert_eq!(policy.decide(&write("Cargo.toml"), dangerous), PolicyAction::Deny);
+
+        let edits = call(
+            "multi_edit",
+            json!({ "edits": [{ "path": "src/a.rs" }, { "path": "build.rs" }] }),
+        );
+        assert_eq!(policy.decide(&edits, dangerous), PolicyAction::Deny);
+
+        let fetch = call("web_fetch", json!({ "url": "https://example.com" }));
+        assert_eq!(policy.decide(&fetch, SecurityLevel::Moderate), PolicyAction::Allow);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(ConfirmPolicy::parse("default: maybe").is_err());
+        assert!(ConfirmPolicy::parse("rules:\n  - tool: bash\n    action: allow\n    comand: ls").is_err());
+        assert!(ConfirmPolicy::parse("rules:\n  - path: \"[\"\n    action: allow").is_err());
+        assert_eq!(ConfirmPolicy::parse("{}").unwrap().default, PolicyAction::Deny);
+    }
+}
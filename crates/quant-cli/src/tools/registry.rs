@@ -45,6 +45,16 @@ impl ToolRegistry {
         self.tools.values().map(|t| t.to_definition()).collect()
     }
 
+    /// Remove a tool, returning whether it was registered
+    pub fn unregister(&mut self, name: &str) -> bool {
+        self.tools.remove(name).is_some()
+    }
+
+    /// Keep only the named tools, dropping the rest
+    pub fn retain(&mut self, names: &[String]) {
+        self.tools.retain(|name, _| names.iter().any(|n| n == name));
+    }
+
     /// Number of registered tools
     pub fn len(&self) -> usize {
         self.tools.len()
@@ -116,6 +126,18 @@ mod tests {
         assert!(names.contains(&"mock"));
     }
 
+    #[test]
+    fn test_registry_retain() {
+        let mut registry = ToolRegistry::new();
+        registry.register(MockTool);
+
+        registry.retain(&["mock".to_string(), "other".to_string()]);
+        assert_eq!(registry.len(), 1);
+
+        registry.retain(&[]);
+        assert!(registry.is_empty());
+    }
+
     #[test]
     fn test_registry_tool_definitions() {
         let mut registry = ToolRegistry::new();
@@ -1,13 +1,16 @@
 //! Tool routing and dispatch
 
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use anyhow::{bail, Result};
+use tokio::sync::mpsc;
 use tracing::{debug, info, instrument, warn};
 
 use super::registry::ToolRegistry;
 use super::security::{ConfirmationHandler, ConfirmationResult};
-use super::{SecurityLevel, ToolCall, ToolContext, ToolResult};
+use super::untrusted::Finding;
+use super::{OutputHandler, SecurityLevel, ToolCall, ToolContext, ToolResult};
 
 /// Result of routing a tool call
 #[derive(Debug)]
@@ -42,20 +45,69 @@ impl ToolRouter {
     }
 
     /// Route a single tool call
-    #[instrument(skip(self, ctx), fields(tool = %tool_call.name))]
     pub async fn route(&self, tool_call: &ToolCall, ctx: &ToolContext) -> RouteResult {
+        self.route_inner(tool_call, ctx, None).await
+    }
+
+    /// Route a single tool call, passing its output to `handler` line by
+    /// line while it runs
+    ///
+    /// The returned result still holds the complete output.
+    pub async fn route_with_output(
+        &self,
+        tool_call: &ToolCall,
+        ctx: &ToolContext,
+        handler: &dyn OutputHandler,
+    ) -> RouteResult {
+        self.route_inner(tool_call, ctx, Some(handler)).await
+    }
+
+    #[instrument(name = "tool_call", skip(self, ctx, output), fields(tool = %tool_call.name))]
+    async fn route_inner(
+        &self,
+        tool_call: &ToolCall,
+        ctx: &ToolContext,
+        output: Option<&dyn OutputHandler>,
+    ) -> RouteResult {
+        let audit = crate::audit::global();
+        if let Some(audit) = audit {
+            audit.throttle().await;
+        }
+
+        let (result, duration) = self.dispatch(tool_call, ctx, output).await;
+
+        if let Some(audit) = audit {
+            let level = self.registry.get(&tool_call.name).map(|t| t.security_level());
+            let simulated = ctx.dry_run && level == Some(SecurityLevel::Dangerous);
+            audit.record(tool_call, level, &ctx.working_dir, &result, simulated, duration);
+        }
+        result
+    }
+
+    /// Confirm and run a call, returning the result and how long the tool ran
+    async fn dispatch(
+        &self,
+        tool_call: &ToolCall,
+        ctx: &ToolContext,
+        output: Option<&dyn OutputHandler>,
+    ) -> (RouteResult, Duration) {
         // Look up the tool
         let tool = match self.registry.get(&tool_call.name) {
             Some(t) => t,
             None => {
                 warn!(tool = %tool_call.name, "Tool not found");
-                return RouteResult::NotFound(tool_call.name.clone());
+                return (RouteResult::NotFound(tool_call.name.clone()), Duration::ZERO);
             }
         };
 
         let security_level = tool.security_level();
         debug!(security_level = %security_level, "Tool security level");
 
+        if ctx.dry_run && security_level == SecurityLevel::Dangerous {
+            info!(tool = %tool_call.name, "Simulating tool (dry run)");
+            return (RouteResult::Success(tool.simulate(&tool_call.arguments, ctx)), Duration::ZERO);
+        }
+
         // Check if confirmation is needed
         let needs_confirmation = match security_level {
             SecurityLevel::Safe => false,
@@ -65,28 +117,58 @@ impl ToolRouter {
 
         if needs_confirmation {
             debug!("Requesting user confirmation");
-            match self.confirmation.confirm(tool_call, security_level).await {
+            let preview = tool.preview(&tool_call.arguments, ctx);
+            match self
+                .confirmation
+                .confirm(tool_call, security_level, preview.as_deref())
+                .await
+            {
                 ConfirmationResult::Approved => {
                     debug!("User approved tool execution");
                 }
                 ConfirmationResult::Denied => {
                     info!(tool = %tool_call.name, "User denied tool execution");
-                    return RouteResult::Denied;
+                    return (RouteResult::Denied, Duration::ZERO);
                 }
                 ConfirmationResult::Skip => {
                     info!(tool = %tool_call.name, "User skipped tool execution");
-                    return RouteResult::Skipped;
+                    return (RouteResult::Skipped, Duration::ZERO);
                 }
                 ConfirmationResult::Abort => {
                     info!(tool = %tool_call.name, "User aborted operation");
-                    return RouteResult::Aborted;
+                    return (RouteResult::Aborted, Duration::ZERO);
                 }
             }
         }
 
         // Execute the tool (pass by reference to avoid cloning)
         info!(tool = %tool_call.name, "Executing tool");
-        match tool.execute(&tool_call.arguments, ctx).await {
+        let started = Instant::now();
+        let result = match output {
+            Some(handler) => {
+                let (sender, mut lines) = mpsc::unbounded_channel();
+                let ctx = ctx.clone().with_output(sender);
+                let execution = tool.execute(&tool_call.arguments, &ctx);
+                tokio::pin!(execution);
+
+                let result = loop {
+                    tokio::select! {
+                        result = &mut execution => break result,
+                        Some(line) = lines.recv() => handler.on_output(&tool_call.name, &line).await,
+                    }
+                };
+                // Lines sent just before the tool returned
+                while let Ok(line) = lines.try_recv() {
+                    handler.on_output(&tool_call.name, &line).await;
+                }
+                result
+            }
+            None => tool.execute(&tool_call.arguments, ctx).await,
+        };
+
+        let duration = started.elapsed();
+
+        let result = match result {
             Ok(result) => {
                 if result.success {
                     info!(tool = %tool_call.name, output_len = result.output.len(), "Tool executed successfully");
@@ -99,7 +181,30 @@ impl ToolRouter {
                 warn!(tool = %tool_call.name, error = %e, "Tool execution error");
                 RouteResult::Error(e.to_string())
             }
-        }
+        };
+        (result, duration)
+    }
+
+    /// Whether routing `tool_call` will stop to ask the user first
+    pub fn needs_confirmation(&self, tool_call: &ToolCall, ctx: &ToolContext) -> bool {
+        let Some(tool) = self.registry.get(&tool_call.name) else {
+            return false;
+        };
+        let security_level = tool.security_level();
+        security_level != SecurityLevel::Safe
+            && !ctx.auto_mode
+            && !(ctx.dry_run && security_level == SecurityLevel::Dangerous)
+            && self.confirmation.will_prompt(tool_call, security_level)
+    }
+
+    /// Whether results of tool `name` carry untrusted third-party content
+    pub fn is_untrusted(&self, name: &str) -> bool {
+        self.registry.get(name).is_some_and(|tool| tool.untrusted_output())
+    }
+
+    /// Ask whether a result of `tool` flagged as a prompt injection may go to the model
+    pub async fn confirm_untrusted(&self, tool: &str, findings: &[Finding]) -> bool {
+        self.confirmation.confirm_untrusted(tool, findings).await
     }
 
     /// Route multiple tool calls sequentially
@@ -132,6 +237,11 @@ impl ToolRouter {
         &mut self.registry
     }
 
+    /// Replace the confirmation handler
+    pub fn set_confirmation(&mut self, confirmation: impl ConfirmationHandler + 'static) {
+        self.confirmation = Arc::new(confirmation);
+    }
+
     /// Execute a tool call directly, returning an error for failures
     pub async fn execute(&self, tool_call: &ToolCall, ctx: &ToolContext) -> Result<ToolResult> {
         match self.route(tool_call, ctx).await {
@@ -181,8 +291,11 @@ mod tests {
             ParameterSchema::new()
         }
 
-        async fn execute(&self, args: &serde_json::Value, _ctx: &ToolContext) -> Result<ToolResult> {
+        async fn execute(&self, args: &serde_json::Value, ctx: &ToolContext) -> Result<ToolResult> {
             let text = args.get("text").and_then(|v| v.as_str()).unwrap_or("empty");
+            for line in text.lines() {
+                ctx.emit_output(false, line);
+            }
             Ok(ToolResult::success(text))
         }
     }
@@ -219,4 +332,58 @@ mod tests {
         let result = router.route(&call, &ctx).await;
         assert!(matches!(result, RouteResult::NotFound(_)));
     }
+
+    #[derive(Default)]
+    struct CollectOutput(std::sync::Mutex<Vec<String>>);
+
+    #[async_trait]
+    impl OutputHandler for CollectOutput {
+        async fn on_output(&self, tool: &str, line: &super::super::OutputLine) {
+            self.0.lock().unwrap().push(format!("{}: {}", tool, line.text));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_route_with_output_forwards_lines() {
+        let mut registry = ToolRegistry::new();
+        registry.register(EchoTool);
+        let router = ToolRouter::new(registry, AutoApprove);
+
+        let call = ToolCall {
+            name: "echo".to_string(),
+            arguments: json!({"text": "compiling\nfinished"}),
+        };
+        let output = CollectOutput::default();
+        let result = router.route_with_output(&call, &ToolContext::default(), &output).await;
+
+        assert!(matches!(result, RouteResult::Success(ref r) if r.output == "compiling\nfinished"));
+        assert_eq!(*output.0.lock().unwrap(), vec!["echo: compiling", "echo: finished"]);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_simulates_dangerous_tools() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let mut registry = ToolRegistry::new();
+        registry.register(EchoTool);
+        registry.register(crate::tools::builtin::FileWriteTool);
+        let router = ToolRouter::new(registry, AutoApprove);
+        let ctx = ToolContext::new(dir.path().to_path_buf()).with_dry_run(true);
+
+        let write = ToolCall {
+            name: "file_write".to_string(),
+            arguments: json!({"path": "notes.txt", "content": "hello\n"}),
+        };
+        assert!(!router.needs_confirmation(&write, &ctx));
+        let result = router.execute(&write, &ctx).await.unwrap();
+        assert!(result.output.starts_with("[dry run] Would create notes.txt"), "{}", result.output);
+        assert!(result.output.contains("+hello"));
+        assert!(!dir.path().join("notes.txt").exists());
+
+        // Safe tools still run
+        let echo = ToolCall {
+            name: "echo".to_string(),
+            arguments: json!({"text": "hi"}),
+        };
+        assert_eq!(router.execute(&echo, &ctx).await.unwrap().output, "hi");
+    }
 }
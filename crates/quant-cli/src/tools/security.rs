@@ -1,12 +1,20 @@
 //! Security and confirmation handling for tools
 
 use async_trait::async_trait;
+use std::collections::HashSet;
 use std::io::{self, IsTerminal, Write};
+use std::sync::Mutex;
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tracing::{debug, warn};
 
+use super::approvals::{command_pattern, pattern_trusts, Approvals};
+use super::dry_run::colorize_diff;
+use super::untrusted::Finding;
 use super::{SecurityLevel, ToolCall};
 
+/// Longest preview shown in the confirmation prompt, in lines
+const MAX_PREVIEW_LINES: usize = 60;
+
 /// Check if stdin is connected to a terminal
 pub fn is_interactive() -> bool {
     io::stdin().is_terminal()
@@ -28,23 +36,123 @@ pub enum ConfirmationResult {
 /// Trait for handling tool execution confirmations
 #[async_trait]
 pub trait ConfirmationHandler: Send + Sync {
-    /// Request confirmation for a tool call
-    async fn confirm(&self, tool_call: &ToolCall, security_level: SecurityLevel) -> ConfirmationResult;
+    /// Request confirmation for a tool call, shown as `preview` (from
+    /// [`Tool::preview`](super::Tool::preview)) when there is one
+    async fn confirm(
+        &self,
+        tool_call: &ToolCall,
+        security_level: SecurityLevel,
+        preview: Option<&str>,
+    ) -> ConfirmationResult;
+
+    /// Whether `confirm` will wait for the user rather than answer by itself
+    fn will_prompt(&self, _tool_call: &ToolCall, _security_level: SecurityLevel) -> bool {
+        false
+    }
+
+    /// Whether to send a result of `tool` that looks like a prompt injection
+    /// on to the model (`[untrusted] confirm`); withheld unless overridden
+    async fn confirm_untrusted(&self, _tool: &str, _findings: &[Finding]) -> bool {
+        false
+    }
 }
 
 /// Default terminal-based confirmation handler
 pub struct TerminalConfirmation {
     /// Whether to auto-approve all actions
     pub auto_approve: bool,
+    /// The project's trusted bash commands (`.quant/approvals.toml`)
+    approvals: Option<Mutex<Approvals>>,
+    /// Bash command patterns allowed for the rest of this session
+    session_commands: Mutex<Vec<String>>,
+    /// Other tools allowed for the rest of this session
+    session_tools: Mutex<HashSet<String>>,
 }
 
 impl TerminalConfirmation {
+    /// Ask before each non-safe action, except bash commands the project
+    /// trusts
     pub fn new() -> Self {
-        Self { auto_approve: false }
+        let approvals = match Approvals::discover() {
+            Ok(approvals) => Some(Mutex::new(approvals)),
+            Err(e) => {
+                warn!(error = %e, "Failed to load trusted commands");
+                None
+            }
+        };
+        Self {
+            auto_approve: false,
+            approvals,
+            session_commands: Mutex::default(),
+            session_tools: Mutex::default(),
+        }
     }
 
     pub fn auto() -> Self {
-        Self { auto_approve: true }
+        Self {
+            auto_approve: true,
+            approvals: None,
+            session_commands: Mutex::default(),
+            session_tools: Mutex::default(),
+        }
+    }
+
+    /// The trusted pattern a bash command matches
+    fn trusted_pattern(&self, command: &str) -> Option<String> {
+        let approvals = self.approvals.as_ref()?.lock().ok()?;
+        approvals.find(command).map(|c| c.pattern.clone())
+    }
+
+    /// Whether the user allowed this call for the rest of the session
+    fn session_allows(&self, tool_call: &ToolCall) -> bool {
+        match bash_command(tool_call) {
+            Some(command) => self
+                .session_commands
+                .lock()
+                .map(|patterns| patterns.iter().any(|p| p == command || pattern_trusts(p, command)))
+                .unwrap_or(false),
+            None => self
+                .session_tools
+                .lock()
+                .map(|tools| tools.contains(&tool_call.name))
+                .unwrap_or(false),
+        }
+    }
+
+    /// Allow calls like this one for the rest of the session: bash commands
+    /// by pattern (or exactly, if they have none), other tools by name
+    fn allow_for_session(&self, tool_call: &ToolCall) {
+        let allowed = match bash_command(tool_call) {
+            Some(command) => {
+                let pattern = command_pattern(command).unwrap_or_else(|| command.to_string());
+                if let Ok(mut patterns) = self.session_commands.lock() {
+                    patterns.push(pattern.clone());
+                }
+                format!("`{}`", pattern)
+            }
+            None => {
+                if let Ok(mut tools) = self.session_tools.lock() {
+                    tools.insert(tool_call.name.clone());
+                }
+                tool_call.name.clone()
+            }
+        };
+        println!("\x1b[2mAllowing {} for the rest of this session\x1b[0m", allowed);
+    }
+
+    /// Add a pattern to the project's trusted commands
+    fn trust(&self, pattern: &str) {
+        let Some(Ok(mut approvals)) = self.approvals.as_ref().map(|a| a.lock()) else {
+            return;
+        };
+        match approvals.add(pattern) {
+            Ok(_) => println!(
+                "\x1b[2mTrusted `{}` in {}; `quant approvals revoke` undoes this\x1b[0m",
+                pattern,
+                approvals.path().display()
+            ),
+            Err(e) => warn!(error = %e, "Failed to save trusted command"),
+        }
     }
 }
 
@@ -56,7 +164,12 @@ impl Default for TerminalConfirmation {
 
 #[async_trait]
 impl ConfirmationHandler for TerminalConfirmation {
-    async fn confirm(&self, tool_call: &ToolCall, security_level: SecurityLevel) -> ConfirmationResult {
+    async fn confirm(
+        &self,
+        tool_call: &ToolCall,
+        security_level: SecurityLevel,
+        preview: Option<&str>,
+    ) -> ConfirmationResult {
         if self.auto_approve {
             debug!(tool = %tool_call.name, "Auto-approving tool execution");
             return ConfirmationResult::Approved;
@@ -67,6 +180,22 @@ impl ConfirmationHandler for TerminalConfirmation {
             return ConfirmationResult::Approved;
         }
 
+        // Neither do bash commands the project trusts
+        let command = bash_command(tool_call);
+        if let Some(command) = command {
+            if let Some(pattern) = self.trusted_pattern(command) {
+                debug!(pattern = %pattern, "Approving trusted command");
+                println!("\x1b[2m[Trusted] {}\x1b[0m", command);
+                return ConfirmationResult::Approved;
+            }
+        }
+
+        // Or anything the user allowed for this session
+        if self.session_allows(tool_call) {
+            debug!(tool = %tool_call.name, "Approving call allowed for this session");
+            return ConfirmationResult::Approved;
+        }
+
         // P2: TTY detection - if not interactive, deny dangerous actions
         if !is_interactive() {
             warn!(
@@ -100,15 +229,29 @@ impl ConfirmationHandler for TerminalConfirmation {
             "\x1b[0m"
         );
 
-        // Pretty print arguments
-        if let Ok(pretty) = serde_json::to_string_pretty(&tool_call.arguments) {
-            for line in pretty.lines() {
-                println!("  {}", line);
+        match preview {
+            Some(preview) => print_preview(preview),
+            // Pretty print arguments
+            None => {
+                if let Ok(pretty) = serde_json::to_string_pretty(&tool_call.arguments) {
+                    for line in pretty.lines() {
+                        println!("  {}", line);
+                    }
+                }
             }
         }
 
+        // Offer to trust similar commands from now on
+        let pattern = command.and_then(command_pattern).filter(|_| self.approvals.is_some());
+
         println!();
-        print!("Allow this action? [y/n/s(kip)/a(bort)] ");
+        match pattern {
+            Some(ref pattern) => print!(
+                "Allow this action? [y/n/s(kip)/a(bort)/w(always this session)/t(rust) `{}`] ",
+                pattern
+            ),
+            None => print!("Allow this action? [y/n/s(kip)/a(bort)/w(always this session)] "),
+        }
         io::stdout().flush().unwrap();
 
         // Use async stdin to avoid blocking the runtime
@@ -126,12 +269,81 @@ impl ConfirmationHandler for TerminalConfirmation {
             "n" | "no" => ConfirmationResult::Denied,
             "s" | "skip" => ConfirmationResult::Skip,
             "a" | "abort" | "q" | "quit" => ConfirmationResult::Abort,
+            "w" | "always" => {
+                self.allow_for_session(tool_call);
+                ConfirmationResult::Approved
+            }
+            "t" | "trust" => match pattern {
+                Some(ref pattern) => {
+                    self.trust(pattern);
+                    ConfirmationResult::Approved
+                }
+                None => ConfirmationResult::Denied,
+            },
             _ => ConfirmationResult::Denied,
         };
 
         debug!(tool = %tool_call.name, result = ?result, "User confirmation response");
         result
     }
+
+    fn will_prompt(&self, tool_call: &ToolCall, security_level: SecurityLevel) -> bool {
+        !self.auto_approve
+            && security_level != SecurityLevel::Safe
+            && bash_command(tool_call).and_then(|c| self.trusted_pattern(c)).is_none()
+            && !self.session_allows(tool_call)
+            && is_interactive()
+    }
+
+    async fn confirm_untrusted(&self, tool: &str, findings: &[Finding]) -> bool {
+        if self.auto_approve {
+            return true;
+        }
+        if !is_interactive() {
+            eprintln!(
+                "\x1b[93m[Warning]\x1b[0m Non-interactive mode: withholding the {} result, which looks like a prompt injection.",
+                tool
+            );
+            return false;
+        }
+
+        println!();
+        println!("\x1b[93m[Untrusted]\x1b[0m The \x1b[1m{}\x1b[0m result looks like a prompt injection:", tool);
+        for finding in findings {
+            println!("  - {}", finding);
+        }
+        print!("Send it to the model anyway? [y/N] ");
+        io::stdout().flush().unwrap();
+
+        let mut input = String::new();
+        if BufReader::new(tokio::io::stdin()).read_line(&mut input).await.is_err() {
+            return false;
+        }
+        matches!(input.trim().to_lowercase().as_str(), "y" | "yes")
+    }
+}
+
+/// Print a tool's preview: the command in bold, diffs in color
+fn print_preview(preview: &str) {
+    let lines: Vec<&str> = preview.lines().collect();
+    for line in lines.iter().take(MAX_PREVIEW_LINES) {
+        if line.starts_with("$ ") {
+            println!("  \x1b[1m{}\x1b[0m", line);
+        } else {
+            println!("  {}", colorize_diff(line));
+        }
+    }
+    if lines.len() > MAX_PREVIEW_LINES {
+        println!("  \x1b[2m... {} more lines\x1b[0m", lines.len() - MAX_PREVIEW_LINES);
+    }
+}
+
+/// The command of a bash tool call
+fn bash_command(tool_call: &ToolCall) -> Option<&str> {
+    match tool_call.name.as_str() {
+        "bash" => tool_call.arguments.get("command").and_then(|v| v.as_str()),
+        _ => None,
+    }
 }
 
 /// A confirmation handler that always approves (for testing or auto mode)
@@ -139,9 +351,18 @@ pub struct AutoApprove;
 
 #[async_trait]
 impl ConfirmationHandler for AutoApprove {
-    async fn confirm(&self, _tool_call: &ToolCall, _security_level: SecurityLevel) -> ConfirmationResult {
+    async fn confirm(
+        &self,
+        _tool_call: &ToolCall,
+        _security_level: SecurityLevel,
+        _preview: Option<&str>,
+    ) -> ConfirmationResult {
         ConfirmationResult::Approved
     }
+
+    async fn confirm_untrusted(&self, _tool: &str, _findings: &[Finding]) -> bool {
+        true
+    }
 }
 
 /// A confirmation handler that always denies (for testing)
@@ -149,7 +370,12 @@ pub struct AutoDeny;
 
 #[async_trait]
 impl ConfirmationHandler for AutoDeny {
-    async fn confirm(&self, _tool_call: &ToolCall, _security_level: SecurityLevel) -> ConfirmationResult {
+    async fn confirm(
+        &self,
+        _tool_call: &ToolCall,
+        _security_level: SecurityLevel,
+        _preview: Option<&str>,
+    ) -> ConfirmationResult {
         ConfirmationResult::Denied
     }
 }
@@ -167,7 +393,7 @@ mod tests {
             arguments: json!({}),
         };
 
-        let result = handler.confirm(&tool_call, SecurityLevel::Dangerous).await;
+        let result = handler.confirm(&tool_call, SecurityLevel::Dangerous, None).await;
         assert_eq!(result, ConfirmationResult::Approved);
     }
 
@@ -179,7 +405,7 @@ mod tests {
             arguments: json!({}),
         };
 
-        let result = handler.confirm(&tool_call, SecurityLevel::Dangerous).await;
+        let result = handler.confirm(&tool_call, SecurityLevel::Dangerous, None).await;
         assert_eq!(result, ConfirmationResult::Denied);
     }
 
@@ -191,10 +417,39 @@ mod tests {
             arguments: json!({}),
         };
 
-        let result = handler.confirm(&tool_call, SecurityLevel::Dangerous).await;
+        let result = handler.confirm(&tool_call, SecurityLevel::Dangerous, None).await;
         assert_eq!(result, ConfirmationResult::Approved);
     }
 
+    #[test]
+    fn test_session_allows() {
+        let handler = TerminalConfirmation::auto();
+        let bash = |command: &str| ToolCall {
+            name: "bash".to_string(),
+            arguments: json!({ "command": command }),
+        };
+        let write = ToolCall {
+            name: "file_write".to_string(),
+            arguments: json!({ "path": "a.txt" }),
+        };
+
+        handler.allow_for_session(&bash("cargo test -p core"));
+        assert!(handler.session_allows(&bash("cargo test")));
+        assert!(!handler.session_allows(&bash("cargo publish")));
+        assert!(!handler.session_allows(&write));
+
+        // Commands without a pattern are allowed exactly
+        handler.allow_for_session(&bash("make && make install"));
+        assert!(handler.session_allows(&bash("make && make install")));
+        assert!(!handler.session_allows(&bash("make && rm -rf /")));
+        handler.allow_for_session(&bash("bash build.sh"));
+        assert!(handler.session_allows(&bash("bash build.sh")));
+        assert!(!handler.session_allows(&bash("bash -c 'rm -rf ~'")));
+
+        handler.allow_for_session(&write);
+        assert!(handler.session_allows(&write));
+    }
+
     #[test]
     fn test_is_interactive_in_test() {
         // In test environment, stdin is typically not a terminal
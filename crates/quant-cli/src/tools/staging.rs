@@ -0,0 +1,126 @@
+//! Staged file writes for reviewed agent runs
+//!
+//! With `quant agent --review` (or `[agent] review_writes`), `file_write` and
+//! `multi_edit` don't touch the disk. Their writes collect in a
+//! [`StagingArea`] shared through the [`ToolContext`](super::ToolContext),
+//! `file_read` sees the staged content, and the user accepts, rejects or
+//! edits each file in a diff review ([`crate::review`]) before anything is
+//! written.
+
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::dry_run::diff_preview;
+
+/// A file with writes waiting for review
+#[derive(Debug, Clone, PartialEq)]
+pub struct StagedFile {
+    pub path: PathBuf,
+    /// Content on disk when first staged (None if the file didn't exist)
+    pub original: Option<String>,
+    /// Content after all staged writes
+    pub content: String,
+}
+
+impl StagedFile {
+    /// Diff from the original to the staged content, relative to `root`
+    pub fn diff(&self, root: &Path) -> String {
+        let name = self.path.strip_prefix(root).unwrap_or(&self.path).display().to_string();
+        diff_preview(&name, self.original.as_deref(), &self.content)
+    }
+
+    /// Write the staged content to disk
+    pub fn write(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, &self.content).with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+/// Writes held back until the user reviews them
+#[derive(Debug, Default)]
+pub struct StagingArea {
+    files: Mutex<BTreeMap<PathBuf, StagedFile>>,
+}
+
+impl StagingArea {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Content of `path` as the agent should see it: staged if there is
+    /// any, otherwise on disk
+    pub fn read(&self, path: &Path) -> Option<String> {
+        match self.files.lock().get(path) {
+            Some(staged) => Some(staged.content.clone()),
+            None => fs::read_to_string(path).ok(),
+        }
+    }
+
+    /// Whether `path` has staged writes
+    pub fn contains(&self, path: &Path) -> bool {
+        self.files.lock().contains_key(path)
+    }
+
+    /// Stage `content` as the new content of `path`
+    pub fn stage(&self, path: &Path, content: String) {
+        let mut files = self.files.lock();
+        match files.get_mut(path) {
+            Some(staged) => staged.content = content,
+            None => {
+                let original = fs::read_to_string(path).ok();
+                files.insert(
+                    path.to_path_buf(),
+                    StagedFile {
+                        path: path.to_path_buf(),
+                        original,
+                        content,
+                    },
+                );
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.lock().is_empty()
+    }
+
+    /// Remove and return the staged files, ordered by path
+    pub fn take(&self) -> Vec<StagedFile> {
+        std::mem::take(&mut *self.files.lock()).into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_staging_area() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("a.txt");
+        fs::write(&path, "one\n").unwrap();
+
+        let staging = StagingArea::new();
+        assert_eq!(staging.read(&path).as_deref(), Some("one\n"));
+        staging.stage(&path, "two\n".to_string());
+        staging.stage(&path, "three\n".to_string());
+        assert_eq!(staging.read(&path).as_deref(), Some("three\n"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "one\n");
+
+        let files = staging.take();
+        assert!(staging.is_empty());
+        assert_eq!(files[0].original.as_deref(), Some("one\n"));
+        assert_eq!(files[0].diff(dir.path()), "--- a.txt\n+++ a.txt\n-one\n+three");
+        files[0].write().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "three\n");
+    }
+}
@@ -0,0 +1,126 @@
+//! Chat transcripts read from stdin
+//!
+//! Lets external programs keep conversation state themselves and pipe the
+//! whole history through `quant ask --stdin-format chat`. Two formats are
+//! accepted and detected automatically:
+//!
+//! - JSONL, one `{"role": ..., "content": ...}` message per line
+//! - Role-tagged text, where `user:`, `assistant:`, `system:` or `tool:` at
+//!   the start of a line begins a new message and untagged lines continue
+//!   the previous one
+
+use anyhow::{bail, Context, Result};
+use llm_core::{ChatMessage, Role};
+
+/// How `quant ask --stdin` interprets its input
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum StdinFormat {
+    /// Raw text, wrapped in a code block ahead of the prompt
+    #[default]
+    Text,
+    /// A conversation transcript (role-tagged lines or JSONL messages)
+    Chat,
+}
+
+/// Parse a transcript, auto-detecting JSONL vs role-tagged text
+pub fn parse(input: &str) -> Result<Vec<ChatMessage>> {
+    let first = input.lines().map(str::trim).find(|l| !l.is_empty());
+    match first {
+        None => Ok(Vec::new()),
+        Some(line) if line.starts_with('{') => parse_jsonl(input),
+        Some(_) => parse_tagged(input),
+    }
+}
+
+fn parse_jsonl(input: &str) -> Result<Vec<ChatMessage>> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .map(|(i, l)| {
+            serde_json::from_str::<ChatMessage>(l)
+                .with_context(|| format!("Invalid chat message on line {}", i + 1))
+        })
+        .collect()
+}
+
+fn parse_tagged(input: &str) -> Result<Vec<ChatMessage>> {
+    let mut messages: Vec<ChatMessage> = Vec::new();
+
+    for (i, line) in input.lines().enumerate() {
+        if let Some((role, rest)) = split_tag(line) {
+            messages.push(ChatMessage {
+                role,
+                content: rest.trim_start().to_string(),
+            });
+            continue;
+        }
+
+        match messages.last_mut() {
+            Some(msg) => {
+                msg.content.push('\n');
+                msg.content.push_str(line);
+            }
+            None if line.trim().is_empty() => {}
+            None => bail!(
+                "Line {} has no role tag; transcript lines must start with user:, assistant:, or system:",
+                i + 1
+            ),
+        }
+    }
+
+    for msg in &mut messages {
+        let trimmed = msg.content.trim_end().len();
+        msg.content.truncate(trimmed);
+    }
+    Ok(messages)
+}
+
+/// Recognize a `role:` prefix (case-insensitive)
+fn split_tag(line: &str) -> Option<(Role, &str)> {
+    let (tag, rest) = line.split_once(':')?;
+    let role = match tag.trim().to_ascii_lowercase().as_str() {
+        "user" => Role::User,
+        "assistant" => Role::Assistant,
+        "system" => Role::System,
+        "tool" => Role::Tool,
+        _ => return None,
+    };
+    Some((role, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tagged_multiline() {
+        let input = "system: Be terse.\nuser: What is 2+2?\nassistant: 4\nuser: Show it in Rust:\n\nfn main() {}\n";
+        let messages = parse(input).unwrap();
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[0].role, Role::System);
+        assert_eq!(messages[2].content, "4");
+        assert_eq!(messages[3].role, Role::User);
+        assert_eq!(messages[3].content, "Show it in Rust:\n\nfn main() {}");
+    }
+
+    #[test]
+    fn test_parse_tagged_rejects_untagged_start() {
+        assert!(parse("hello\nuser: hi").is_err());
+        // Colons inside content don't start a new message
+        let messages = parse("user: note: keep this").unwrap();
+        assert_eq!(messages[0].content, "note: keep this");
+    }
+
+    #[test]
+    fn test_parse_jsonl() {
+        let input = "{\"role\":\"user\",\"content\":\"hi\"}\n\n{\"role\":\"assistant\",\"content\":\"hello\\nthere\"}\n";
+        let messages = parse(input).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].role, Role::Assistant);
+        assert_eq!(messages[1].content, "hello\nthere");
+
+        let err = parse("{\"role\":\"user\",\"content\":\"hi\"}\n{\"role\":\"bot\"}").unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+    }
+}
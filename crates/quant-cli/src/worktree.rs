@@ -0,0 +1,202 @@
+//! Isolated git worktrees for `quant agent --worktree`
+//!
+//! The agent works in a new worktree on its own branch, created from `HEAD`
+//! under `<git dir>/quant-worktrees/`, so the user's checkout is never
+//! touched. When the run ends the changes can be reviewed as a diff,
+//! committed to the branch (and pushed as a pull request), kept for later,
+//! or discarded along with the branch.
+
+use anyhow::{Context, Result};
+use chrono::Local;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{info, warn};
+
+/// Directory under the git common dir holding agent worktrees
+const WORKTREES_DIR: &str = "quant-worktrees";
+
+/// Branch name prefix
+const BRANCH_PREFIX: &str = "quant/";
+
+/// Longest task slug in a branch name
+const MAX_SLUG_CHARS: usize = 40;
+
+/// A temporary worktree and branch for one agent run
+#[derive(Debug, Clone)]
+pub struct Worktree {
+    /// Checkout the agent works in
+    pub path: PathBuf,
+    /// Branch checked out there
+    pub branch: String,
+    /// Top level of the user's checkout
+    repo_root: PathBuf,
+}
+
+impl Worktree {
+    /// Create a worktree on a new branch from `HEAD` of the repository containing `dir`
+    pub fn create(dir: &Path, task: &str) -> Result<Self> {
+        let repo_root = PathBuf::from(git(dir, &["rev-parse", "--show-toplevel"]).context("--worktree needs a git repository")?);
+        let common_dir = PathBuf::from(git(&repo_root, &["rev-parse", "--path-format=absolute", "--git-common-dir"])?);
+
+        let name = format!("{}-{}", slugify(task), Local::now().format("%Y%m%d-%H%M%S"));
+        let branch = format!("{}{}", BRANCH_PREFIX, name);
+        let path = common_dir.join(WORKTREES_DIR).join(&name);
+
+        git(&repo_root, &["worktree", "add", "--quiet", "-b", &branch, &path.to_string_lossy(), "HEAD"])?;
+        info!(path = %path.display(), branch = %branch, "Created worktree");
+        Ok(Self { path, branch, repo_root })
+    }
+
+    /// Whether the user's checkout has changes the worktree won't see
+    pub fn source_is_dirty(&self) -> bool {
+        git(&self.repo_root, &["status", "--porcelain"]).map(|s| !s.is_empty()).unwrap_or(false)
+    }
+
+    /// Where `dir` (inside the user's checkout) is in the worktree
+    pub fn map_dir(&self, dir: &Path) -> PathBuf {
+        let root = self.repo_root.canonicalize().unwrap_or_else(|_| self.repo_root.clone());
+        let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        match dir.strip_prefix(&root) {
+            Ok(relative) => self.path.join(relative),
+            Err(_) => self.path.clone(),
+        }
+    }
+
+    /// Whether the agent changed anything
+    pub fn has_changes(&self) -> Result<bool> {
+        Ok(!git(&self.path, &["status", "--porcelain"])?.is_empty())
+    }
+
+    /// Everything the agent changed, including new files
+    pub fn diff(&self, stat: bool) -> Result<String> {
+        git(&self.path, &["add", "--all"])?;
+        let mut args = vec!["diff", "--cached"];
+        if stat {
+            args.push("--stat");
+        } else if std::io::stdout().is_terminal() {
+            args.push("--color=always");
+        }
+        git(&self.path, &args)
+    }
+
+    /// Commit every change to the worktree's branch
+    pub fn commit(&self, message: &str) -> Result<()> {
+        git(&self.path, &["add", "--all"])?;
+        git(&self.path, &["commit", "--quiet", "-m", message])?;
+        Ok(())
+    }
+
+    /// Push the branch and open a pull request with `gh`
+    pub fn open_pr(&self, title: &str) -> Result<String> {
+        git(&self.path, &["push", "--quiet", "-u", "origin", &self.branch])?;
+        let output = Command::new("gh")
+            .current_dir(&self.path)
+            .args(["pr", "create", "--fill", "--title", title, "--head", &self.branch])
+            .output()
+            .context("Failed to run gh; install the GitHub CLI to open pull requests")?;
+        if !output.status.success() {
+            anyhow::bail!("gh pr create failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Remove the worktree, and the branch too unless `keep_branch`
+    pub fn remove(&self, keep_branch: bool) -> Result<()> {
+        git(&self.repo_root, &["worktree", "remove", "--force", &self.path.to_string_lossy()])?;
+        if !keep_branch {
+            if let Err(e) = git(&self.repo_root, &["branch", "--quiet", "-D", &self.branch]) {
+                warn!(error = %e, branch = %self.branch, "Failed to delete worktree branch");
+            }
+        }
+        info!(path = %self.path.display(), keep_branch, "Removed worktree");
+        Ok(())
+    }
+}
+
+/// Commit subject for a task: its first line, shortened
+pub fn commit_message(task: &str) -> String {
+    let line = task.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or("Agent changes");
+    match line.char_indices().nth(72) {
+        Some((end, _)) => format!("{}...", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+/// Lowercase words of the task joined by dashes, for a branch name
+fn slugify(task: &str) -> String {
+    let mut slug = String::new();
+    for word in task.split(|c: char| !c.is_ascii_alphanumeric()).filter(|w| !w.is_empty()) {
+        if slug.len() + word.len() + 1 > MAX_SLUG_CHARS {
+            break;
+        }
+        if !slug.is_empty() {
+            slug.push('-');
+        }
+        slug.push_str(&word.to_ascii_lowercase());
+    }
+    if slug.is_empty() {
+        slug.push_str("task");
+    }
+    slug
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        anyhow::bail!("git {} failed: {}", args[0], String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim_end().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Fix the failing `parse_config` tests!"), "fix-the-failing-parse-config-tests");
+        assert_eq!(slugify("???"), "task");
+        assert!(slugify(&"word ".repeat(50)).len() <= MAX_SLUG_CHARS);
+        assert_eq!(commit_message("\n  Add a flag\nmore detail"), "Add a flag");
+    }
+
+    #[test]
+    fn test_worktree_lifecycle() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path();
+        for args in [
+            &["init", "--quiet"][..],
+            &["config", "user.email", "test@example.com"],
+            &["config", "user.name", "Test"],
+        ] {
+            git(repo, args).unwrap();
+        }
+        std::fs::create_dir(repo.join("src")).unwrap();
+        std::fs::write(repo.join("src/lib.rs"), "fn a() {}\n").unwrap();
+        git(repo, &["add", "."]).unwrap();
+        git(repo, &["commit", "--quiet", "-m", "init"]).unwrap();
+
+        let worktree = Worktree::create(&repo.join("src"), "Add b").unwrap();
+        assert!(worktree.branch.starts_with("quant/add-b-"));
+        assert_eq!(worktree.map_dir(&repo.join("src")), worktree.path.join("src"));
+        assert!(!worktree.has_changes().unwrap());
+
+        std::fs::write(worktree.path.join("src/b.rs"), "fn b() {}\n").unwrap();
+        assert!(worktree.has_changes().unwrap());
+        assert!(worktree.diff(true).unwrap().contains("src/b.rs"));
+        assert!(!repo.join("src/b.rs").exists());
+
+        worktree.commit("Add b").unwrap();
+        worktree.remove(true).unwrap();
+        assert!(!worktree.path.exists());
+        let log = git(repo, &["log", "--oneline", &worktree.branch]).unwrap();
+        assert!(log.contains("Add b"));
+    }
+}
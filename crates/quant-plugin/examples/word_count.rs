@@ -0,0 +1,51 @@
+//! Example plugin with one tool that counts the words in a file
+//!
+//! Build it with `cargo build -p quant-plugin --example word_count --release`
+//! and copy the library from `target/release/examples/` into quant's plugins
+//! directory.
+
+use quant_plugin::{declare_plugin, Context, Output, Plugin, SecurityLevel, Tool};
+use serde_json::{json, Value};
+
+struct WordCount;
+
+impl Tool for WordCount {
+    fn name(&self) -> &str {
+        "word_count"
+    }
+
+    fn description(&self) -> &str {
+        "Count the lines, words and bytes of a text file"
+    }
+
+    fn security_level(&self) -> SecurityLevel {
+        SecurityLevel::Safe
+    }
+
+    fn parameters_schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string", "description": "File to count, relative to the working directory" }
+            },
+            "required": ["path"]
+        })
+    }
+
+    fn execute(&self, args: &Value, ctx: &Context) -> Output {
+        let Some(path) = args.get("path").and_then(|v| v.as_str()) else {
+            return Output::error("Missing required parameter: path");
+        };
+        match std::fs::read_to_string(ctx.working_dir.join(path)) {
+            Ok(text) => Output::success(format!(
+                "{} lines, {} words, {} bytes",
+                text.lines().count(),
+                text.split_whitespace().count(),
+                text.len()
+            )),
+            Err(e) => Output::error(format!("Failed to read {}: {}", path, e)),
+        }
+    }
+}
+
+declare_plugin!(Plugin::new("word-count").with_version(env!("CARGO_PKG_VERSION")).with_tool(WordCount));
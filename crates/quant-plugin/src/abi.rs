@@ -0,0 +1,88 @@
+//! The C ABI between quant and a plugin library
+//!
+//! A plugin exports four functions, which [`declare_plugin!`](crate::declare_plugin)
+//! generates:
+//!
+//! - `quant_plugin_abi_version() -> u32`: [`ABI_VERSION`](crate::ABI_VERSION)
+//! - `quant_plugin_manifest() -> *mut c_char`: a [`Manifest`] as JSON
+//! - `quant_plugin_call(request: *const c_char) -> *mut c_char`: runs a
+//!   [`CallRequest`] given as JSON and returns an [`Output`] as JSON
+//! - `quant_plugin_free(s: *mut c_char)`: frees a string the plugin returned
+//!
+//! Strings are NUL-terminated UTF-8. Each string a plugin returns belongs to
+//! it and goes back through `quant_plugin_free`.
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::{CallRequest, Manifest, Output, Plugin};
+
+pub const ABI_VERSION_SYMBOL: &[u8] = b"quant_plugin_abi_version";
+pub const MANIFEST_SYMBOL: &[u8] = b"quant_plugin_manifest";
+pub const CALL_SYMBOL: &[u8] = b"quant_plugin_call";
+pub const FREE_SYMBOL: &[u8] = b"quant_plugin_free";
+
+pub type AbiVersionFn = unsafe extern "C" fn() -> u32;
+pub type ManifestFn = unsafe extern "C" fn() -> *mut c_char;
+pub type CallFn = unsafe extern "C" fn(*const c_char) -> *mut c_char;
+pub type FreeFn = unsafe extern "C" fn(*mut c_char);
+
+/// The plugin's manifest as a string for quant
+pub fn export_manifest(plugin: fn() -> &'static Plugin) -> *mut c_char {
+    let manifest = panic::catch_unwind(|| plugin().manifest()).unwrap_or_else(|_| Manifest {
+        name: "unknown".to_string(),
+        version: String::new(),
+        tools: Vec::new(),
+    });
+    to_c_string(serde_json::to_string(&manifest).unwrap_or_default())
+}
+
+/// Run a call from quant; a panicking tool becomes an error result
+///
+/// # Safety
+///
+/// `request` must be a NUL-terminated string.
+pub unsafe fn export_call(plugin: fn() -> &'static Plugin, request: *const c_char) -> *mut c_char {
+    let output = if request.is_null() {
+        Output::error("Invalid call request: null")
+    } else {
+        let request = CStr::from_ptr(request).to_string_lossy();
+        match serde_json::from_str::<CallRequest>(&request) {
+            Ok(request) => panic::catch_unwind(AssertUnwindSafe(|| plugin().call(&request)))
+                .unwrap_or_else(|_| Output::error(format!("Tool {} panicked", request.tool))),
+            Err(e) => Output::error(format!("Invalid call request: {}", e)),
+        }
+    };
+    to_c_string(serde_json::to_string(&output).unwrap_or_default())
+}
+
+/// Free a string made by [`export_manifest`] or [`export_call`]
+///
+/// # Safety
+///
+/// `s` must come from one of them and not be freed already.
+pub unsafe fn free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Copy a string a plugin returned, then hand it back to the plugin's `free`
+///
+/// # Safety
+///
+/// `s` must be null or a NUL-terminated string that `free` releases.
+pub unsafe fn take_string(s: *mut c_char, free: FreeFn) -> String {
+    if s.is_null() {
+        return String::new();
+    }
+    let copy = CStr::from_ptr(s).to_string_lossy().into_owned();
+    free(s);
+    copy
+}
+
+fn to_c_string(s: String) -> *mut c_char {
+    // JSON escapes NUL, so this only fails on a broken serializer
+    CString::new(s).unwrap_or_default().into_raw()
+}
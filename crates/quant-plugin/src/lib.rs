@@ -0,0 +1,310 @@
+//! quant-plugin: tools for the quant agent, shipped as shared libraries
+//!
+//! A plugin is a `cdylib` crate that implements [`Tool`] for each of its
+//! tools and exports them with [`declare_plugin!`]. quant loads every
+//! library in its plugins directory at startup and registers the tools
+//! alongside the builtin ones.
+//!
+//! The boundary between quant and a plugin is a C ABI over JSON strings
+//! (see [`abi`]), so a plugin doesn't have to be built with the same Rust
+//! compiler, or in Rust at all:
+//!
+//! ```ignore
+//! use quant_plugin::{declare_plugin, Context, Output, Plugin, SecurityLevel, Tool};
+//! use serde_json::{json, Value};
+//!
+//! struct Kubectl;
+//!
+//! impl Tool for Kubectl {
+//!     fn name(&self) -> &str { "k8s_pods" }
+//!     fn description(&self) -> &str { "List the pods in a namespace" }
+//!     fn security_level(&self) -> SecurityLevel { SecurityLevel::Safe }
+//!     fn parameters_schema(&self) -> Value {
+//!         json!({ "type": "object", "properties": { "namespace": { "type": "string" } } })
+//!     }
+//!     fn execute(&self, args: &Value, ctx: &Context) -> Output {
+//!         Output::success("...")
+//!     }
+//! }
+//!
+//! declare_plugin!(Plugin::new("k8s").with_tool(Kubectl));
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+
+pub mod abi;
+
+/// Version of the plugin ABI; quant skips libraries built for another one
+pub const ABI_VERSION: u32 = 1;
+
+/// How much care a tool's calls need, as in quant's own tools
+///
+/// Safe tools run without asking; moderate ones (network access) and
+/// dangerous ones (writes, commands) ask the user first unless the agent
+/// runs with `--auto`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecurityLevel {
+    Safe,
+    Moderate,
+    Dangerous,
+}
+
+/// What a tool call runs with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Context {
+    /// The agent's working directory; relative paths are relative to it
+    pub working_dir: PathBuf,
+    /// How long the call may take, in seconds
+    pub timeout_secs: u64,
+    /// The call should only describe what it would do (`quant agent --dry-run`)
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Result of a tool call, shown to the model
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Output {
+    pub success: bool,
+    pub output: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Output {
+    pub fn success(output: impl Into<String>) -> Self {
+        Self {
+            success: true,
+            output: output.into(),
+            error: None,
+        }
+    }
+
+    pub fn error(error: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            output: String::new(),
+            error: Some(error.into()),
+        }
+    }
+}
+
+/// A tool the agent can call
+pub trait Tool: Send + Sync {
+    /// Name the model calls the tool by; unique across quant's tools
+    fn name(&self) -> &str;
+
+    /// What the tool does, for the model
+    fn description(&self) -> &str;
+
+    fn security_level(&self) -> SecurityLevel;
+
+    /// JSON Schema of the arguments: an object with `properties` and
+    /// optionally `required`
+    fn parameters_schema(&self) -> Value;
+
+    /// Run the tool; called on a worker thread, so it may block
+    fn execute(&self, args: &Value, ctx: &Context) -> Output;
+}
+
+/// Description of a tool, as sent to quant
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolSpec {
+    pub name: String,
+    pub description: String,
+    pub security_level: SecurityLevel,
+    pub parameters: Value,
+}
+
+/// What a plugin offers, as sent to quant
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub name: String,
+    pub version: String,
+    pub tools: Vec<ToolSpec>,
+}
+
+/// A call from quant to one of the plugin's tools
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CallRequest {
+    pub tool: String,
+    pub args: Value,
+    pub context: Context,
+}
+
+/// A plugin's tools, exported with [`declare_plugin!`]
+pub struct Plugin {
+    name: String,
+    version: String,
+    tools: Vec<Box<dyn Tool>>,
+}
+
+impl Plugin {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: "0.0.0".to_string(),
+            tools: Vec::new(),
+        }
+    }
+
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    pub fn with_tool(mut self, tool: impl Tool + 'static) -> Self {
+        self.tools.push(Box::new(tool));
+        self
+    }
+
+    pub fn manifest(&self) -> Manifest {
+        Manifest {
+            name: self.name.clone(),
+            version: self.version.clone(),
+            tools: self
+                .tools
+                .iter()
+                .map(|tool| ToolSpec {
+                    name: tool.name().to_string(),
+                    description: tool.description().to_string(),
+                    security_level: tool.security_level(),
+                    parameters: tool.parameters_schema(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Run a call against the named tool
+    pub fn call(&self, request: &CallRequest) -> Output {
+        match self.tools.iter().find(|tool| tool.name() == request.tool) {
+            Some(tool) => tool.execute(&request.args, &request.context),
+            None => Output::error(format!("Plugin {} has no tool named {}", self.name, request.tool)),
+        }
+    }
+}
+
+/// Export a [`Plugin`] from a `cdylib` through the [`abi`] functions
+///
+/// Takes an expression building the plugin; it is evaluated once, on the
+/// first call from quant.
+#[macro_export]
+macro_rules! declare_plugin {
+    ($plugin:expr) => {
+        fn __quant_plugin() -> &'static $crate::Plugin {
+            static PLUGIN: ::std::sync::OnceLock<$crate::Plugin> = ::std::sync::OnceLock::new();
+            PLUGIN.get_or_init(|| $plugin)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn quant_plugin_abi_version() -> u32 {
+            $crate::ABI_VERSION
+        }
+
+        #[no_mangle]
+        pub extern "C" fn quant_plugin_manifest() -> *mut ::std::os::raw::c_char {
+            $crate::abi::export_manifest(__quant_plugin)
+        }
+
+        /// # Safety
+        ///
+        /// `request` must be a NUL-terminated string.
+        #[no_mangle]
+        pub unsafe extern "C" fn quant_plugin_call(
+            request: *const ::std::os::raw::c_char,
+        ) -> *mut ::std::os::raw::c_char {
+            $crate::abi::export_call(__quant_plugin, request)
+        }
+
+        /// # Safety
+        ///
+        /// `s` must come from this plugin and not be freed already.
+        #[no_mangle]
+        pub unsafe extern "C" fn quant_plugin_free(s: *mut ::std::os::raw::c_char) {
+            $crate::abi::free_string(s)
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    struct Upper;
+
+    impl Tool for Upper {
+        fn name(&self) -> &str {
+            "upper"
+        }
+
+        fn description(&self) -> &str {
+            "Uppercase text"
+        }
+
+        fn security_level(&self) -> SecurityLevel {
+            SecurityLevel::Safe
+        }
+
+        fn parameters_schema(&self) -> Value {
+            json!({ "type": "object", "properties": { "text": { "type": "string" } }, "required": ["text"] })
+        }
+
+        fn execute(&self, args: &Value, _ctx: &Context) -> Output {
+            match args.get("text").and_then(|v| v.as_str()) {
+                Some(text) => Output::success(text.to_uppercase()),
+                None => Output::error("Missing text"),
+            }
+        }
+    }
+
+    fn plugin() -> Plugin {
+        Plugin::new("text").with_version("1.2.0").with_tool(Upper)
+    }
+
+    declare_plugin!(plugin());
+
+    fn request(tool: &str, args: Value) -> CallRequest {
+        CallRequest {
+            tool: tool.to_string(),
+            args,
+            context: Context {
+                working_dir: PathBuf::from("/work"),
+                timeout_secs: 30,
+                dry_run: false,
+            },
+        }
+    }
+
+    #[test]
+    fn test_plugin() {
+        let plugin = plugin();
+        let manifest = plugin.manifest();
+        assert_eq!(manifest.name, "text");
+        assert_eq!(manifest.tools[0].name, "upper");
+        assert_eq!(manifest.tools[0].security_level, SecurityLevel::Safe);
+
+        assert_eq!(plugin.call(&request("upper", json!({ "text": "hi" }))), Output::success("HI"));
+        assert!(!plugin.call(&request("lower", json!({}))).success);
+    }
+
+    #[test]
+    fn test_exported_functions() {
+        assert_eq!(quant_plugin_abi_version(), ABI_VERSION);
+
+        let manifest = unsafe { abi::take_string(quant_plugin_manifest(), quant_plugin_free) };
+        let manifest: Manifest = serde_json::from_str(&manifest).unwrap();
+        assert_eq!(manifest.version, "1.2.0");
+
+        let call = |json: &str| {
+            let json = std::ffi::CString::new(json).unwrap();
+            let response = unsafe { abi::take_string(quant_plugin_call(json.as_ptr()), quant_plugin_free) };
+            serde_json::from_str::<Output>(&response).unwrap()
+        };
+        let json = serde_json::to_string(&request("upper", json!({ "text": "abc" }))).unwrap();
+        assert_eq!(call(&json), Output::success("ABC"));
+        assert!(call("not json").error.unwrap().starts_with("Invalid call request"));
+    }
+}